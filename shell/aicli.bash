@@ -0,0 +1,30 @@
+# aicli shell integration for bash. Add to ~/.bashrc:
+#   eval "$(aicli shell-hook bash)"
+# Ctrl+G explains the last command (or the one being typed) and puts a
+# corrected one on the command line, to edit or run with Enter.
+
+__aicli_last_status=0
+__aicli_last_command=
+
+__aicli_record() {
+    __aicli_last_status=$?
+    __aicli_last_command=$(HISTTIMEFORMAT= builtin history 1 | sed 's/^ *[0-9]* *//')
+}
+# First, so it sees the command's exit status before anything else runs
+PROMPT_COMMAND="__aicli_record${PROMPT_COMMAND:+; $PROMPT_COMMAND}"
+
+__aicli_fix() {
+    local fix
+    if [ -n "$READLINE_LINE" ]; then
+        fix=$(aicli fix --shell bash -- "$READLINE_LINE")
+    elif [ -n "$__aicli_last_command" ]; then
+        fix=$(aicli fix --shell bash --status "$__aicli_last_status" -- "$__aicli_last_command")
+    fi
+    if [ -n "$fix" ]; then
+        READLINE_LINE=$fix
+        READLINE_POINT=${#fix}
+    fi
+}
+if [[ $- == *i* ]]; then
+    bind -x '"\C-g": __aicli_fix'
+fi
@@ -0,0 +1,25 @@
+# aicli shell integration for fish. Add to ~/.config/fish/config.fish:
+#   aicli shell-hook fish | source
+# Ctrl+G explains the last command (or the one being typed) and puts a
+# corrected one on the command line, to edit or run with Enter.
+
+function __aicli_postexec --on-event fish_postexec
+    set -g __aicli_last_status $status
+    set -g __aicli_last_command $argv[1]
+end
+
+function __aicli_fix
+    set -l line (commandline)
+    set -l fix
+    if test -n "$line"
+        set fix (aicli fix --shell fish -- "$line")
+    else if set -q __aicli_last_command
+        set fix (aicli fix --shell fish --status $__aicli_last_status -- "$__aicli_last_command")
+    end
+    if test -n "$fix"
+        commandline -r -- "$fix"
+        commandline -C (string length -- "$fix")
+    end
+    commandline -f repaint
+end
+bind \cg __aicli_fix
@@ -0,0 +1,30 @@
+# aicli shell integration for PowerShell (PSReadLine). Add to $PROFILE:
+#   aicli shell-hook powershell | Out-String | Invoke-Expression
+# Ctrl+G explains the last command (or the one being typed) and puts a
+# corrected one on the command line, to edit or run with Enter.
+
+$global:__aicliLastStatus = 0
+$global:__aicliPrompt = $function:prompt
+function global:prompt {
+    # First, before anything in the prompt changes $? or $LASTEXITCODE
+    $global:__aicliLastStatus = if ($?) { 0 } elseif ($LASTEXITCODE) { $LASTEXITCODE } else { 1 }
+    & $global:__aicliPrompt
+}
+
+Set-PSReadLineKeyHandler -Chord 'Ctrl+g' -BriefDescription 'aicli fix' -ScriptBlock {
+    $line = $null
+    $cursor = $null
+    [Microsoft.PowerShell.PSConsoleReadLine]::GetBufferState([ref]$line, [ref]$cursor)
+    $typed = $line
+    if ($line) {
+        $fix = aicli fix --shell powershell -- $line
+    } else {
+        $last = Get-History -Count 1
+        if (-not $last) { return }
+        $fix = aicli fix --shell powershell --status $global:__aicliLastStatus -- $last.CommandLine
+    }
+    if ($fix) {
+        [Microsoft.PowerShell.PSConsoleReadLine]::Replace(0, $typed.Length, ($fix -join "`n"))
+    }
+    [Microsoft.PowerShell.PSConsoleReadLine]::InvokePrompt()
+}
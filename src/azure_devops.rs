@@ -0,0 +1,248 @@
+use crate::config::AzureDevOpsConfig;
+use crate::tools::{block_on, truncate_output};
+use anyhow::{Context, Result};
+use serde_json::{json, Value};
+use std::process::Command;
+
+const API_VERSION: &str = "7.0";
+/// Entra ID resource id of Azure DevOps, for `az account get-access-token`
+const AZURE_DEVOPS_RESOURCE: &str = "499b84ac-1321-427f-aa17-267ca6975798";
+const DEFAULT_RUNS: u64 = 10;
+const MAX_LOG_CHARS: usize = 15000;
+
+enum Auth {
+    Pat(String),
+    Bearer(String),
+}
+
+/// PAT from config or AZURE_DEVOPS_EXT_PAT, otherwise an Entra ID token from the Azure CLI
+fn auth(config: &AzureDevOpsConfig) -> Result<Auth> {
+    if let Some(pat) = config.pat.as_ref().filter(|p| !p.is_empty()) {
+        return Ok(Auth::Pat(pat.clone()));
+    }
+    if let Ok(pat) = std::env::var("AZURE_DEVOPS_EXT_PAT") {
+        if !pat.is_empty() {
+            return Ok(Auth::Pat(pat));
+        }
+    }
+
+    let output = Command::new(if cfg!(windows) { "az.cmd" } else { "az" })
+        .args(["account", "get-access-token", "--resource", AZURE_DEVOPS_RESOURCE, "--query", "accessToken", "-o", "tsv"])
+        .output()
+        .context("No Azure DevOps PAT configured and the Azure CLI (az) is not available")?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "No PAT configured and `az account get-access-token` failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(Auth::Bearer(String::from_utf8_lossy(&output.stdout).trim().to_string()))
+}
+
+fn project_url(config: &AzureDevOpsConfig, input: &Value) -> Result<String> {
+    if config.organization.is_empty() {
+        anyhow::bail!("Set [tools.azure_devops] organization in ~/.aicli/config.toml");
+    }
+    let project = input
+        .get("project")
+        .and_then(|p| p.as_str())
+        .unwrap_or(&config.project);
+    if project.is_empty() {
+        anyhow::bail!("No project given and [tools.azure_devops] project is not set");
+    }
+    Ok(format!("https://dev.azure.com/{}/{}/_apis", config.organization, project))
+}
+
+async fn api(config: &AzureDevOpsConfig, method: reqwest::Method, url: &str, body: Option<Value>) -> Result<reqwest::Response> {
+    let separator = if url.contains('?') { '&' } else { '?' };
+    let mut request = reqwest::Client::new()
+        .request(method, format!("{}{}api-version={}", url, separator, API_VERSION));
+    request = match auth(config)? {
+        Auth::Pat(pat) => request.basic_auth("", Some(pat)),
+        Auth::Bearer(token) => request.bearer_auth(token),
+    };
+    if let Some(body) = body {
+        request = request.json(&body);
+    }
+
+    let response = request.send().await?;
+    let status = response.status();
+    if !status.is_success() {
+        let body: Value = response.json().await.unwrap_or(Value::Null);
+        let message = body.get("message").and_then(|m| m.as_str()).unwrap_or("request failed");
+        anyhow::bail!("Azure DevOps API error ({}): {}", status, message);
+    }
+    Ok(response)
+}
+
+async fn api_json(config: &AzureDevOpsConfig, method: reqwest::Method, url: &str, body: Option<Value>) -> Result<Value> {
+    Ok(api(config, method, url, body).await?.json().await?)
+}
+
+/// Work item descriptions are HTML
+fn html_to_text(html: &str) -> String {
+    let breaks = regex::Regex::new(r"(?i)<br\s*/?>|</(p|div|li|h\d)>").unwrap();
+    let tags = regex::Regex::new(r"<[^>]+>").unwrap();
+    let text = breaks.replace_all(html, "\n");
+    let text = tags.replace_all(&text, "");
+    text.replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+        .trim()
+        .to_string()
+}
+
+fn field<'a>(fields: &'a Value, name: &str) -> &'a str {
+    fields.get(name).and_then(|v| v.as_str()).unwrap_or("")
+}
+
+fn id_param(input: &Value, key: &str) -> Result<u64> {
+    input
+        .get(key)
+        .and_then(|n| n.as_u64().or_else(|| n.as_str().and_then(|s| s.trim_start_matches('#').parse().ok())))
+        .ok_or_else(|| anyhow::anyhow!("Missing '{}' parameter", key))
+}
+
+pub fn read_work_item(input: &Value, config: &AzureDevOpsConfig) -> Result<String> {
+    let base = project_url(config, input)?;
+    let id = id_param(input, "id")?;
+
+    let item = block_on(api_json(config, reqwest::Method::GET, &format!("{}/wit/workitems/{}", base, id), None))?;
+    let fields = &item["fields"];
+
+    let mut result = format!(
+        "{} {}: {}\nState: {} · Assigned to: {}\n\n{}",
+        field(fields, "System.WorkItemType"),
+        id,
+        field(fields, "System.Title"),
+        field(fields, "System.State"),
+        fields.pointer("/System.AssignedTo/displayName").and_then(|a| a.as_str()).unwrap_or("unassigned"),
+        html_to_text(field(fields, "System.Description")),
+    );
+    let criteria = html_to_text(field(fields, "Microsoft.VSTS.Common.AcceptanceCriteria"));
+    if !criteria.is_empty() {
+        result.push_str(&format!("\n\n## Acceptance criteria\n{}", criteria));
+    }
+    let repro = html_to_text(field(fields, "Microsoft.VSTS.TCM.ReproSteps"));
+    if !repro.is_empty() {
+        result.push_str(&format!("\n\n## Repro steps\n{}", repro));
+    }
+    Ok(result)
+}
+
+pub fn pipeline_runs(input: &Value, config: &AzureDevOpsConfig) -> Result<String> {
+    let base = project_url(config, input)?;
+    let top = input.get("limit").and_then(|l| l.as_u64()).unwrap_or(DEFAULT_RUNS).min(50);
+    let mut url = format!("{}/build/builds?$top={}", base, top);
+    if let Some(pipeline) = input.get("pipeline_id").and_then(|p| p.as_u64()) {
+        url.push_str(&format!("&definitions={}", pipeline));
+    }
+    if let Some(branch) = input.get("branch").and_then(|b| b.as_str()) {
+        let branch = if branch.starts_with("refs/") { branch.to_string() } else { format!("refs/heads/{}", branch) };
+        url.push_str(&format!("&branchName={}", branch));
+    }
+
+    let builds = block_on(api_json(config, reqwest::Method::GET, &url, None))?;
+    let builds = builds["value"].as_array().cloned().unwrap_or_default();
+    if builds.is_empty() {
+        return Ok("No pipeline runs found".to_string());
+    }
+
+    let lines: Vec<String> = builds
+        .iter()
+        .map(|b| {
+            format!(
+                "{} {} #{} [{}{}] {} ({})",
+                b["id"],
+                field(&b["definition"], "name"),
+                field(b, "buildNumber"),
+                field(b, "status"),
+                b.get("result").and_then(|r| r.as_str()).map(|r| format!(": {}", r)).unwrap_or_default(),
+                field(b, "sourceBranch").trim_start_matches("refs/heads/"),
+                field(b, "queueTime"),
+            )
+        })
+        .collect();
+    Ok(lines.join("\n"))
+}
+
+/// Logs of the failed steps of a run, or of every step when nothing failed
+pub fn pipeline_logs(input: &Value, config: &AzureDevOpsConfig) -> Result<String> {
+    let base = project_url(config, input)?;
+    let build_id = id_param(input, "run_id")?;
+
+    block_on(async {
+        let timeline = api_json(config, reqwest::Method::GET, &format!("{}/build/builds/{}/timeline", base, build_id), None).await?;
+        let records = timeline["records"].as_array().cloned().unwrap_or_default();
+        let with_logs: Vec<&Value> = records.iter().filter(|r| r["log"]["id"].is_u64()).collect();
+        let failed: Vec<&Value> = with_logs
+            .iter()
+            .copied()
+            .filter(|r| matches!(r["result"].as_str(), Some("failed") | Some("canceled")))
+            .collect();
+        let selected = if failed.is_empty() { with_logs } else { failed };
+
+        let mut result = String::new();
+        for record in selected {
+            let log_id = record["log"]["id"].as_u64().unwrap_or_default();
+            let log = api(config, reqwest::Method::GET, &format!("{}/build/builds/{}/logs/{}", base, build_id, log_id), None)
+                .await?
+                .text()
+                .await?;
+            result.push_str(&format!(
+                "=== {} [{}] ===\n{}\n\n",
+                field(record, "name"),
+                record["result"].as_str().unwrap_or("pending"),
+                truncate_output(&log, MAX_LOG_CHARS)
+            ));
+        }
+        if result.is_empty() {
+            result = format!("Run {} has no logs yet", build_id);
+        }
+        Ok(result)
+    })
+}
+
+pub fn create_pr(input: &Value, config: &AzureDevOpsConfig) -> Result<String> {
+    let base = project_url(config, input)?;
+    let repository = input
+        .get("repository")
+        .and_then(|r| r.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing 'repository' parameter"))?;
+    let title = input
+        .get("title")
+        .and_then(|t| t.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing 'title' parameter"))?;
+    let source = match input.get("source_branch").and_then(|s| s.as_str()) {
+        Some(branch) => branch.to_string(),
+        None => crate::github::current_branch()?,
+    };
+    let target = input.get("target_branch").and_then(|t| t.as_str()).unwrap_or("main");
+    let as_ref = |b: &str| if b.starts_with("refs/") { b.to_string() } else { format!("refs/heads/{}", b) };
+
+    let body = json!({
+        "sourceRefName": as_ref(&source),
+        "targetRefName": as_ref(target),
+        "title": title,
+        "description": input.get("description").and_then(|d| d.as_str()).unwrap_or(""),
+        "isDraft": input.get("draft").and_then(|d| d.as_bool()).unwrap_or(false),
+    });
+    let pr = block_on(api_json(
+        config,
+        reqwest::Method::POST,
+        &format!("{}/git/repositories/{}/pullrequests", base, repository),
+        Some(body),
+    ))?;
+
+    Ok(format!(
+        "Created pull request {}: https://dev.azure.com/{}/{}/_git/{}/pullrequest/{}",
+        pr["pullRequestId"],
+        config.organization,
+        field(&pr["repository"]["project"], "name"),
+        repository,
+        pr["pullRequestId"]
+    ))
+}
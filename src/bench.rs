@@ -0,0 +1,266 @@
+use anyhow::{Context, Result};
+use regex::Regex;
+use std::fs;
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+use crate::client::{AzureClient, TokenUsage};
+use crate::config::{AppConfig, ModelConfig};
+use crate::console::println;
+use crate::text;
+
+const BENCH_PROMPT: &str = "You are a helpful assistant. Answer the question directly.";
+const JUDGE_PROMPT: &str = "You judge candidate answers to the same request. Pick the one that is most correct and complete; for code, prefer the candidate that would compile, handles edge cases and changes the least. Reply with \"Best: <number>\" on the first line, then one or two sentences on why it beats the others.";
+const MERGE_PROMPT: &str = "You are given candidate answers to the same request. Write the best possible answer from them: keep what each gets right, fix their mistakes and drop what is wrong. Reply with that answer only, as if you were answering the request yourself.";
+/// `/best` candidates get this much hotter each
+const BEST_TEMPERATURE_STEP: f32 = 0.3;
+const BEST_MAX_TEMPERATURE: f32 = 1.5;
+
+/// One model's results over every prompt
+#[derive(Default)]
+struct Totals {
+    runs: usize,
+    errors: usize,
+    latency: Duration,
+    first_token: Duration,
+    /// Runs that streamed at least one token
+    streamed: usize,
+    /// Output tokens and generation time, for tokens/sec
+    output_tokens: usize,
+    generating: Duration,
+    usage: TokenUsage,
+    /// None when the model has no prices configured
+    cost: Option<f64>,
+}
+
+/// Prompts from the file: one per line; blank lines and `#` comments skipped
+fn read_prompts(path: &str) -> Result<Vec<String>> {
+    let path = crate::paths::resolve(path);
+    let content = fs::read_to_string(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let prompts: Vec<String> = content
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .map(String::from)
+        .collect();
+    if prompts.is_empty() {
+        anyhow::bail!("{} has no prompts (one per line)", path.display());
+    }
+    Ok(prompts)
+}
+
+/// `aicli bench [--models A,B] --prompt-file FILE`: send every prompt to each
+/// model in turn and compare latency, throughput and cost. Without
+/// --models, every configured model runs.
+pub async fn run(config: &AppConfig, models: &[String], prompt_file: &str) -> Result<()> {
+    let prompts = read_prompts(prompt_file)?;
+    let names: Vec<String> = if models.is_empty() {
+        let mut names: Vec<String> = config.models.keys().cloned().collect();
+        names.sort();
+        names
+    } else {
+        models.to_vec()
+    };
+    if let Some(missing) = names.iter().find(|n| !config.models.contains_key(*n)) {
+        let mut known: Vec<&str> = config.models.keys().map(|k| k.as_str()).collect();
+        known.sort();
+        anyhow::bail!("No model '{}'. Configured: {}", missing, known.join(", "));
+    }
+    if names.is_empty() {
+        anyhow::bail!("No models configured. Run 'aicli models add' first");
+    }
+
+    println!(
+        "\n\x1b[1mBenchmarking {} model(s) on {} prompt(s)\x1b[0m\n",
+        names.len(),
+        prompts.len()
+    );
+    let mut results = Vec::new();
+    for name in &names {
+        let model = config.models[name].clone();
+        let client = AzureClient::new(model.clone());
+        let mut totals = Totals::default();
+        for (i, prompt) in prompts.iter().enumerate() {
+            let started = Instant::now();
+            let mut first_token = None;
+            let result = client
+                .complete_streaming(BENCH_PROMPT, prompt, |_| {
+                    first_token.get_or_insert_with(|| started.elapsed());
+                })
+                .await;
+            let elapsed = started.elapsed();
+            totals.runs += 1;
+
+            match result {
+                Ok((_, usage)) => {
+                    totals.latency += elapsed;
+                    if let Some(first) = first_token {
+                        totals.streamed += 1;
+                        totals.first_token += first;
+                        totals.output_tokens += usage.completion_tokens;
+                        totals.generating += elapsed.saturating_sub(first);
+                    }
+                    if let Some(cost) = usage.estimate_cost(&model) {
+                        *totals.cost.get_or_insert(0.0) += cost;
+                    }
+                    totals.usage.add(&usage);
+                    println!(
+                        "  \x1b[38;5;141m{}\x1b[0m  {}/{}  {:.1}s  \x1b[90m{}\x1b[0m",
+                        name,
+                        i + 1,
+                        prompts.len(),
+                        elapsed.as_secs_f64(),
+                        text::truncate(prompt, 50)
+                    );
+                }
+                Err(e) => {
+                    totals.errors += 1;
+                    println!(
+                        "  \x1b[38;5;141m{}\x1b[0m  {}/{}  \x1b[31m✗ {}\x1b[0m",
+                        name,
+                        i + 1,
+                        prompts.len(),
+                        text::truncate(&e.to_string(), 80)
+                    );
+                }
+            }
+        }
+        results.push((name, totals));
+    }
+
+    print_table(&results);
+    Ok(())
+}
+
+fn print_table(results: &[(&String, Totals)]) {
+    println!(
+        "\n  \x1b[90m{:<24} {:>6} {:>9} {:>8} {:>8} {:>10} {:>10}\x1b[0m",
+        "model", "ok", "latency", "ttft", "tok/s", "tokens", "cost"
+    );
+    for (name, totals) in results {
+        let ok = totals.runs - totals.errors;
+        let average = |total: Duration, count: usize| {
+            if count == 0 {
+                "-".to_string()
+            } else {
+                format!("{:.2}s", total.as_secs_f64() / count as f64)
+            }
+        };
+        let rate = if totals.generating.is_zero() {
+            "-".to_string()
+        } else {
+            format!("{:.1}", totals.output_tokens as f64 / totals.generating.as_secs_f64())
+        };
+        let cost = match totals.cost {
+            Some(cost) => format!("${:.4}", cost),
+            None => "-".to_string(),
+        };
+        println!(
+            "  {:<24} {:>6} {:>9} {:>8} {:>8} {:>10} {:>10}",
+            text::truncate(name, 24),
+            format!("{}/{}", ok, totals.runs),
+            average(totals.latency, ok),
+            average(totals.first_token, totals.streamed),
+            rate,
+            totals.usage.total_tokens,
+            cost
+        );
+    }
+    println!("\n  \x1b[90mlatency and ttft are averages per prompt · cost needs input_price/output_price\x1b[0m\n");
+}
+
+/// One answer to a `/compare` or `/best` prompt
+pub struct Comparison {
+    /// The model, or which `/best` candidate
+    pub model: String,
+    pub answer: Result<String>,
+    pub elapsed: Duration,
+    pub first_token: Option<Duration>,
+    pub usage: TokenUsage,
+    pub cost: Option<f64>,
+}
+
+impl Comparison {
+    /// "2.1s · first token 0.4s · 512 tokens · ~$0.0031"
+    pub fn summary(&self) -> String {
+        let mut parts = vec![format!("{:.1}s", self.elapsed.as_secs_f64())];
+        if let Some(first) = self.first_token {
+            parts.push(format!("first token {:.1}s", first.as_secs_f64()));
+        }
+        if self.usage.total_tokens > 0 {
+            parts.push(format!("{} tokens", self.usage.total_tokens));
+        }
+        if let Some(cost) = self.cost {
+            parts.push(format!("~${:.4}", cost));
+        }
+        parts.join(" · ")
+    }
+}
+
+/// `/compare A B <prompt>`: send `prompt` to every model in `models` at once
+pub async fn compare(config: &AppConfig, models: &[&str], prompt: &str) -> Result<Vec<Comparison>> {
+    if let Some(missing) = models.iter().find(|n| !config.models.contains_key(**n)) {
+        let mut known: Vec<&str> = config.models.keys().map(|k| k.as_str()).collect();
+        known.sort();
+        anyhow::bail!("No model '{}'. Configured: {}", missing, known.join(", "));
+    }
+    let runs = models.iter().map(|name| ask(name.to_string(), config.models[*name].clone(), prompt));
+    Ok(futures::future::join_all(runs).await)
+}
+
+/// Sampling settings of the `index`th `/best` candidate: the model's own
+/// temperature first, then hotter ones, each with its own seed
+fn candidate(model: &ModelConfig, index: usize) -> ModelConfig {
+    let mut model = model.clone();
+    model.temperature = (model.temperature + BEST_TEMPERATURE_STEP * index as f32).min(BEST_MAX_TEMPERATURE);
+    model.seed = Some(index as u64 + 1);
+    model
+}
+
+/// `/best N <prompt>`: N samples of `model`'s answer to `prompt`, at once
+pub async fn sample(model: &ModelConfig, count: usize, prompt: &str) -> Vec<Comparison> {
+    let runs = (0..count).map(|index| {
+        let model = candidate(model, index);
+        let label = format!("#{} · temperature {:.1}", index + 1, model.temperature);
+        ask(label, model, prompt)
+    });
+    futures::future::join_all(runs).await
+}
+
+/// Ask `judge` which of the `candidates` answers `prompt` best, or with
+/// `merge` to write one answer from all of them
+pub async fn judge(judge: &ModelConfig, prompt: &str, candidates: &[&str], merge: bool) -> Result<(String, TokenUsage)> {
+    let mut request = format!("# Request\n\n{}\n", prompt);
+    for (index, candidate) in candidates.iter().enumerate() {
+        request.push_str(&format!("\n# Candidate {}\n\n{}\n", index + 1, candidate));
+    }
+    let system = if merge { MERGE_PROMPT } else { JUDGE_PROMPT };
+    let client = AzureClient::new(judge.clone());
+    client.complete_streaming(system, &request, |_| {}).await.map_err(crate::safety::explain)
+}
+
+/// The candidate a `JUDGE_PROMPT` verdict picked, from its "Best: N" line
+pub fn verdict_pick(verdict: &str, candidates: usize) -> Option<usize> {
+    static BEST: OnceLock<Regex> = OnceLock::new();
+    let best = BEST.get_or_init(|| Regex::new(r"(?i)best\W*(?:candidate\W*)?#?(\d+)").unwrap());
+    let number: usize = best.captures(verdict)?[1].parse().ok()?;
+    (1..=candidates).contains(&number).then(|| number - 1)
+}
+
+async fn ask(label: String, model: ModelConfig, prompt: &str) -> Comparison {
+    let client = AzureClient::new(model.clone());
+    let started = Instant::now();
+    let mut first_token = None;
+    let result = client
+        .complete_streaming(BENCH_PROMPT, prompt, |_| {
+            first_token.get_or_insert_with(|| started.elapsed());
+        })
+        .await;
+    let elapsed = started.elapsed();
+    let (answer, usage) = match result {
+        Ok((answer, usage)) => (Ok(answer), usage),
+        Err(e) => (Err(crate::safety::explain(e)), TokenUsage::default()),
+    };
+    let cost = usage.estimate_cost(&model).filter(|_| answer.is_ok());
+    Comparison { model: label, answer, elapsed, first_token, usage, cost }
+}
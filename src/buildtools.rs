@@ -0,0 +1,495 @@
+//! run_build, run_tests and run_lint: detect the project (Cargo, npm/yarn/
+//! pnpm, Python), run its usual command and pick the failures out of the
+//! output as `file:line:column: severity: message`, so the model gets the
+//! locations without reading a whole build log.
+
+use anyhow::Result;
+use regex::Regex;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::Instant;
+
+use crate::config::ToolsConfig;
+use crate::doctor::find_in_path;
+
+const MAX_PROBLEMS: usize = 50;
+/// Output shown with the problems, from the end
+const TAIL_LINES_FAILED: usize = 30;
+const TAIL_LINES_PASSED: usize = 8;
+/// `/fix` attaches this many lines around each problem, for the first few files
+const SNIPPET_CONTEXT: usize = 6;
+const SNIPPET_FILES: usize = 5;
+const SNIPPET_CHARS: usize = 16_000;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Task {
+    Build,
+    Test,
+    Lint,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Kind {
+    Cargo,
+    Node,
+    Python,
+}
+
+impl Kind {
+    fn name(self) -> &'static str {
+        match self {
+            Kind::Cargo => "cargo",
+            Kind::Node => "npm",
+            Kind::Python => "python",
+        }
+    }
+}
+
+struct Problem {
+    file: String,
+    line: usize,
+    column: Option<usize>,
+    severity: String,
+    message: String,
+}
+
+/// The project directory and its kind: `path` or the nearest parent with a
+/// Cargo.toml, package.json or Python project file
+fn detect(start: &Path, wanted: Option<&str>) -> Result<(PathBuf, Kind)> {
+    let markers: &[(Kind, &[&str])] = &[
+        (Kind::Cargo, &["Cargo.toml"]),
+        (Kind::Node, &["package.json"]),
+        (Kind::Python, &["pyproject.toml", "setup.py", "setup.cfg", "requirements.txt", "pytest.ini"]),
+    ];
+    for dir in start.ancestors() {
+        for (kind, files) in markers {
+            if wanted.is_some_and(|w| w != kind.name()) {
+                continue;
+            }
+            if files.iter().any(|f| dir.join(f).is_file()) {
+                return Ok((dir.to_path_buf(), *kind));
+            }
+        }
+    }
+    anyhow::bail!(
+        "No Cargo.toml, package.json or Python project file in {} or its parents; use execute_command",
+        crate::paths::display(start)
+    )
+}
+
+fn node_runner(dir: &Path) -> &'static str {
+    if dir.join("pnpm-lock.yaml").exists() {
+        "pnpm"
+    } else if dir.join("yarn.lock").exists() {
+        "yarn"
+    } else {
+        "npm"
+    }
+}
+
+fn node_script(dir: &Path, runner: &str, script: &str) -> Result<String> {
+    let manifest: Value = serde_json::from_str(&std::fs::read_to_string(dir.join("package.json"))?)?;
+    let scripts = manifest.get("scripts").and_then(|s| s.as_object());
+    if scripts.is_some_and(|s| s.contains_key(script)) {
+        return Ok(format!("{} run {}", runner, script));
+    }
+    let known: Vec<&str> = scripts.map(|s| s.keys().map(|k| k.as_str()).collect()).unwrap_or_default();
+    anyhow::bail!(
+        "package.json has no \"{}\" script (scripts: {}); use execute_command",
+        script,
+        if known.is_empty() { "none".to_string() } else { known.join(", ") }
+    )
+}
+
+fn python() -> &'static str {
+    if cfg!(windows) || find_in_path("python3").is_none() {
+        "python"
+    } else {
+        "python3"
+    }
+}
+
+fn command_for(task: Task, kind: Kind, dir: &Path, filter: Option<&str>) -> Result<String> {
+    let filter = filter.map(|f| format!(" {}", shell_quote(f))).unwrap_or_default();
+    Ok(match (kind, task) {
+        (Kind::Cargo, Task::Build) => "cargo build --message-format=short".to_string(),
+        (Kind::Cargo, Task::Test) => format!("cargo test --message-format=short{}", filter),
+        (Kind::Cargo, Task::Lint) => "cargo clippy --all-targets --message-format=short".to_string(),
+        (Kind::Node, Task::Build) => node_script(dir, node_runner(dir), "build")?,
+        (Kind::Node, Task::Test) => match node_runner(dir) {
+            "npm" if !filter.is_empty() => format!("npm test --{}", filter),
+            runner => format!("{} test{}", runner, filter),
+        },
+        (Kind::Node, Task::Lint) => node_script(dir, node_runner(dir), "lint")?,
+        // Byte-compiling finds syntax errors without running anything
+        (Kind::Python, Task::Build) => format!("{} -m compileall -q .", python()),
+        (Kind::Python, Task::Test) => {
+            let filter = if filter.is_empty() { String::new() } else { format!(" -k{}", filter) };
+            format!("{} -m pytest -q --tb=short{}", python(), filter)
+        }
+        (Kind::Python, Task::Lint) if find_in_path("ruff").is_some() => "ruff check --output-format=concise .".to_string(),
+        (Kind::Python, Task::Lint) => format!("{} -m flake8", python()),
+    })
+}
+
+fn shell_quote(text: &str) -> String {
+    if text.chars().all(|c| c.is_alphanumeric() || "_-:./".contains(c)) {
+        text.to_string()
+    } else if cfg!(windows) {
+        format!("\"{}\"", text.replace('"', ""))
+    } else {
+        format!("'{}'", text.replace('\'', r"'\''"))
+    }
+}
+
+/// What a build, test or lint run found
+pub struct Report {
+    command: String,
+    dir: PathBuf,
+    elapsed: f64,
+    outcome: String,
+    pub passed: bool,
+    problems: Vec<Problem>,
+    output: String,
+}
+
+impl Task {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "build" => Some(Task::Build),
+            "test" | "tests" => Some(Task::Test),
+            "lint" => Some(Task::Lint),
+            _ => None,
+        }
+    }
+}
+
+/// Detect the project from `input` (`path`, `project`, `filter`) and run the task
+pub fn check(task: Task, input: &Value, config: &ToolsConfig) -> Result<Report> {
+    let start = match input.get("path").and_then(|p| p.as_str()) {
+        Some(path) => std::path::absolute(crate::paths::resolve(path))?,
+        None => std::env::current_dir()?,
+    };
+    let start = if start.is_file() { start.parent().map(Path::to_path_buf).unwrap_or(start) } else { start };
+    let (dir, kind) = detect(&start, input.get("project").and_then(|p| p.as_str()))?;
+    let filter = input.get("filter").and_then(|f| f.as_str()).filter(|f| !f.trim().is_empty());
+    let command = command_for(task, kind, &dir, filter.filter(|_| task == Task::Test))?;
+
+    let mut cmd = crate::tools::shell_command(&command, config);
+    cmd.current_dir(&dir);
+    let started = Instant::now();
+    let (status, stdout, stderr) = crate::tools::run_with_timeout(cmd, config.command_timeout_secs)?;
+    let elapsed = started.elapsed().as_secs_f64();
+    // Progress and compiler messages go to stderr, results to stdout
+    let output = format!("{}{}", stderr, stdout);
+
+    let default_severity = if task == Task::Lint { "warning" } else { "error" };
+    let problems = parse_problems(&output, &dir, default_severity);
+    let outcome = match status {
+        None => format!("timed out after {}s", config.command_timeout_secs),
+        Some(status) if status.success() => "passed".to_string(),
+        Some(status) => format!("failed (exit code {})", status.code().unwrap_or(-1)),
+    };
+    let passed = status.is_some_and(|s| s.success());
+    Ok(Report { command, dir, elapsed, outcome, passed, problems, output })
+}
+
+/// `run_build`, `run_tests` or `run_lint`
+pub fn run(task: Task, input: &Value, config: &ToolsConfig) -> Result<String> {
+    Ok(check(task, input, config)?.render())
+}
+
+impl Report {
+    /// One `file:line:column: severity: message` per problem
+    pub fn problem_lines(&self) -> Vec<String> {
+        self.problems
+            .iter()
+            .map(|problem| {
+                let column = problem.column.map(|c| format!(":{}", c)).unwrap_or_default();
+                format!("{}:{}{}: {}: {}", problem.file, problem.line, column, problem.severity, problem.message)
+            })
+            .collect()
+    }
+
+    pub fn summary(&self) -> String {
+        format!("$ {}  (in {}, {:.1}s)\n{}", self.command, crate::paths::display(&self.dir), self.elapsed, self.outcome)
+    }
+
+    fn tail(&self, count: usize) -> String {
+        let lines: Vec<&str> = self.output.lines().filter(|l| !l.trim().is_empty()).collect();
+        lines[lines.len().saturating_sub(count)..].join("\n")
+    }
+
+    fn render(&self) -> String {
+        let mut result = format!("{}\n", self.summary());
+        let lines = self.problem_lines();
+        if !lines.is_empty() {
+            result.push_str(&format!("\n{} problem(s):\n", lines.len()));
+            for line in lines.iter().take(MAX_PROBLEMS) {
+                result.push_str(line);
+                result.push('\n');
+            }
+            if lines.len() > MAX_PROBLEMS {
+                result.push_str(&format!("... and {} more\n", lines.len() - MAX_PROBLEMS));
+            }
+        }
+        let tail = self.tail(if self.passed { TAIL_LINES_PASSED } else { TAIL_LINES_FAILED });
+        if !tail.is_empty() {
+            result.push_str(&format!("\nLast lines of output:\n{}\n", tail));
+        }
+        result
+    }
+
+    /// `/fix`: the problems and the code around them, asking the agent to fix them
+    pub fn fix_prompt(&self, tool: &str) -> String {
+        let mut prompt = format!(
+            "`{}` {} in {}. Fix the cause of these problems, then call {} again to check.\n",
+            self.command,
+            self.outcome,
+            crate::paths::display(&self.dir),
+            tool
+        );
+        let lines = self.problem_lines();
+        if lines.is_empty() {
+            prompt.push_str(&format!("\nNo error locations were recognized. The end of the output:\n```\n{}\n```\n", self.tail(TAIL_LINES_FAILED)));
+            return prompt;
+        }
+        prompt.push_str("\nProblems:\n");
+        for line in lines.iter().take(MAX_PROBLEMS) {
+            prompt.push_str(&format!("- {}\n", line));
+        }
+
+        // The lines around each problem, per file, for the first few files
+        let mut files: Vec<(&str, Vec<usize>)> = Vec::new();
+        for problem in self.problems.iter().filter(|p| p.line > 0) {
+            match files.iter_mut().find(|(f, _)| *f == problem.file) {
+                Some((_, lines)) => lines.push(problem.line),
+                None => files.push((&problem.file, vec![problem.line])),
+            }
+        }
+        let mut budget = SNIPPET_CHARS;
+        for (file, problem_lines) in files.iter().take(SNIPPET_FILES) {
+            let path = if Path::new(file).is_absolute() { PathBuf::from(file) } else { self.dir.join(file) };
+            let Ok(content) = std::fs::read_to_string(&path) else {
+                continue;
+            };
+            let source: Vec<&str> = content.lines().collect();
+            let mut shown: Vec<(usize, usize)> = Vec::new();
+            for &line in problem_lines {
+                let from = line.saturating_sub(SNIPPET_CONTEXT + 1);
+                let to = (line + SNIPPET_CONTEXT).min(source.len());
+                match shown.last_mut() {
+                    Some(last) if from <= last.1 => last.1 = last.1.max(to),
+                    _ => shown.push((from, to)),
+                }
+            }
+            let mut snippet = String::new();
+            for (i, (from, to)) in shown.iter().enumerate() {
+                if i > 0 {
+                    snippet.push_str("     ┆\n");
+                }
+                for (n, text) in source.iter().enumerate().take(*to).skip(*from) {
+                    snippet.push_str(&format!("{:4} │ {}\n", n + 1, text));
+                }
+            }
+            if snippet.len() > budget {
+                break;
+            }
+            budget -= snippet.len();
+            let language = crate::tools::detect_language(&path).unwrap_or_default();
+            prompt.push_str(&format!("\n{}:\n```{}\n{}```\n", file, language, snippet));
+        }
+        prompt
+    }
+}
+
+struct Patterns {
+    /// rustc --message-format=short, gcc, go, ruff, flake8, tsc --pretty
+    location: Regex,
+    /// tsc: file(line,col): error TS1234: message
+    paren: Regex,
+    /// eslint's default output: the file on its own line, then "  line:col  error  message  rule"
+    eslint_entry: Regex,
+    python_frame: Regex,
+    python_error: Regex,
+    pytest_failed: Regex,
+    cargo_panic: Regex,
+    cargo_panic_old: Regex,
+    jest_title: Regex,
+    js_frame: Regex,
+}
+
+fn patterns() -> &'static Patterns {
+    static PATTERNS: OnceLock<Patterns> = OnceLock::new();
+    PATTERNS.get_or_init(|| Patterns {
+        location: Regex::new(r"^(?P<file>[^\s:][^:\n]*?):(?P<line>\d+):(?:(?P<col>\d+):?)?\s*(?P<rest>.+)$").unwrap(),
+        paren: Regex::new(r"^(?P<file>[^\s(]+)\((?P<line>\d+),(?P<col>\d+)\): (?P<sev>error|warning) (?P<msg>.+)$").unwrap(),
+        eslint_entry: Regex::new(r"^\s+(?P<line>\d+):(?P<col>\d+)\s+(?P<sev>error|warning)\s+(?P<msg>.+?)$").unwrap(),
+        python_frame: Regex::new(r#"^\s*File "(?P<file>[^"]+)", line (?P<line>\d+)"#).unwrap(),
+        python_error: Regex::new(r"^(?P<kind>[A-Z]\w*(Error|Exception)): (?P<msg>.+)$").unwrap(),
+        pytest_failed: Regex::new(r"^FAILED (?P<file>[^:\s]+)::(?P<test>\S+)(?: - (?P<msg>.*))?$").unwrap(),
+        cargo_panic: Regex::new(r"^thread '(?P<test>[^']+)'(?: \(\d+\))? panicked at (?P<file>[^:\s]+):(?P<line>\d+):(?P<col>\d+):$").unwrap(),
+        cargo_panic_old: Regex::new(r"^thread '(?P<test>[^']+)' panicked at '(?P<msg>.*)', (?P<file>[^:\s]+):(?P<line>\d+):(?P<col>\d+)$").unwrap(),
+        jest_title: Regex::new(r"^\s*● (?P<name>.+)$").unwrap(),
+        js_frame: Regex::new(r"\((?P<file>[^()\s]+):(?P<line>\d+):(?P<col>\d+)\)|at (?P<bare>[^()\s]+):(?P<bline>\d+):(?P<bcol>\d+)$").unwrap(),
+    })
+}
+
+/// Failures in a build, test or lint log. Only locations of files that exist
+/// count, which keeps URLs and timestamps out.
+fn parse_problems(output: &str, dir: &Path, default_severity: &str) -> Vec<Problem> {
+    let p = patterns();
+    let exists = |file: &str| !file.contains("node_modules") && (dir.join(file).is_file() || Path::new(file).is_file());
+    let number = |m: Option<regex::Match>| m.and_then(|m| m.as_str().parse::<usize>().ok());
+    let lines: Vec<&str> = output.lines().collect();
+
+    let mut problems: Vec<Problem> = Vec::new();
+    let mut push = |mut problem: Problem| {
+        problem.file = problem.file.trim_start_matches("./").to_string();
+        let duplicate = problems
+            .iter()
+            .any(|p| p.file == problem.file && p.line == problem.line && p.message == problem.message);
+        if !duplicate {
+            problems.push(problem);
+        }
+    };
+
+    let mut eslint_file: Option<String> = None;
+    let mut python_frame: Option<(String, usize)> = None;
+    // Last traceback line per file, for pytest's FAILED summary
+    let mut last_line: HashMap<String, usize> = HashMap::new();
+    let mut jest_test: Option<String> = None;
+
+    for (index, line) in lines.iter().enumerate() {
+        let line = line.trim_end();
+        let next = lines.get(index + 1).map(|l| l.trim()).unwrap_or_default();
+
+        if let Some(c) = p.cargo_panic.captures(line) {
+            push(Problem {
+                file: c["file"].to_string(),
+                line: number(c.name("line")).unwrap_or(0),
+                column: number(c.name("col")),
+                severity: "test failed".to_string(),
+                message: format!("{}: {}", &c["test"], next),
+            });
+            continue;
+        }
+        if let Some(c) = p.cargo_panic_old.captures(line) {
+            push(Problem {
+                file: c["file"].to_string(),
+                line: number(c.name("line")).unwrap_or(0),
+                column: number(c.name("col")),
+                severity: "test failed".to_string(),
+                message: format!("{}: {}", &c["test"], &c["msg"]),
+            });
+            continue;
+        }
+        if let Some(c) = p.pytest_failed.captures(line) {
+            let file = c["file"].to_string();
+            let message = match c.name("msg") {
+                Some(msg) => format!("{}: {}", &c["test"], msg.as_str()),
+                None => c["test"].to_string(),
+            };
+            push(Problem {
+                line: last_line.get(&file).copied().unwrap_or(0),
+                file,
+                column: None,
+                severity: "test failed".to_string(),
+                message,
+            });
+            continue;
+        }
+        if let Some(c) = p.python_frame.captures(line) {
+            python_frame = Some((c["file"].to_string(), number(c.name("line")).unwrap_or(0)));
+            continue;
+        }
+        if let Some(c) = p.python_error.captures(line.trim_start()) {
+            if let Some((file, line)) = python_frame.take().filter(|(f, _)| exists(f)) {
+                push(Problem {
+                    file,
+                    line,
+                    column: None,
+                    severity: default_severity.to_string(),
+                    message: format!("{}: {}", &c["kind"], &c["msg"]),
+                });
+            }
+            continue;
+        }
+        if let Some(c) = p.jest_title.captures(line) {
+            jest_test = Some(c["name"].to_string());
+            continue;
+        }
+        if let Some(test) = jest_test.as_ref() {
+            if let Some(c) = p.js_frame.captures(line) {
+                let file = c.name("file").or(c.name("bare")).map(|m| m.as_str()).unwrap_or_default();
+                if exists(file) {
+                    push(Problem {
+                        file: file.to_string(),
+                        line: number(c.name("line").or(c.name("bline"))).unwrap_or(0),
+                        column: number(c.name("col").or(c.name("bcol"))),
+                        severity: "test failed".to_string(),
+                        message: test.clone(),
+                    });
+                    jest_test = None;
+                    continue;
+                }
+            }
+        }
+        if let Some(c) = p.paren.captures(line) {
+            if exists(&c["file"]) {
+                push(Problem {
+                    file: c["file"].to_string(),
+                    line: number(c.name("line")).unwrap_or(0),
+                    column: number(c.name("col")),
+                    severity: c["sev"].to_string(),
+                    message: c["msg"].to_string(),
+                });
+                continue;
+            }
+        }
+        if let Some(c) = p.location.captures(line) {
+            let file = c["file"].to_string();
+            if exists(&file) {
+                let line_number = number(c.name("line")).unwrap_or(0);
+                last_line.insert(file.clone(), line_number);
+                let rest = c["rest"].trim_start_matches("- ").trim();
+                let (severity, message) = split_severity(rest, default_severity);
+                if !matches!(severity.as_str(), "note" | "help") && !rest.starts_with("in ") {
+                    push(Problem { file, line: line_number, column: number(c.name("col")), severity, message });
+                }
+                continue;
+            }
+        }
+        if !line.starts_with(char::is_whitespace) && exists(line.trim()) {
+            eslint_file = Some(line.trim().to_string());
+            continue;
+        }
+        if let (Some(file), Some(c)) = (eslint_file.as_ref(), p.eslint_entry.captures(line)) {
+            push(Problem {
+                file: file.clone(),
+                line: number(c.name("line")).unwrap_or(0),
+                column: number(c.name("col")),
+                severity: c["sev"].to_string(),
+                message: c["msg"].split("  ").filter(|s| !s.is_empty()).collect::<Vec<_>>().join(" · "),
+            });
+        }
+    }
+    problems
+}
+
+/// "error[E0308]: mismatched types" -> ("error[E0308]", "mismatched types");
+/// lint codes like "F401 unused import" keep the default severity
+fn split_severity(rest: &str, default: &str) -> (String, String) {
+    for severity in ["error", "warning", "note", "help", "info"] {
+        if let Some(after) = rest.strip_prefix(severity) {
+            let (code, message) = match after.split_once(':') {
+                Some((code, message)) if code.is_empty() || code.starts_with('[') || code.starts_with(" TS") => (code, message),
+                _ => continue,
+            };
+            return (format!("{}{}", severity, code), message.trim().to_string());
+        }
+    }
+    (default.to_string(), rest.to_string())
+}
@@ -0,0 +1,71 @@
+use crate::client::Message;
+use crate::config::ModelConfig;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+/// A cached one-shot response
+#[derive(Debug, Serialize, Deserialize)]
+struct CacheEntry {
+    created_at: i64,
+    model: String,
+    response: String,
+}
+
+pub fn get_cache_dir() -> PathBuf {
+    let home = dirs::home_dir().unwrap_or_else(|| PathBuf::from("."));
+    home.join(".aicli").join("cache")
+}
+
+/// FNV-1a, stable across builds unlike std's DefaultHasher
+fn fnv1a(data: &[u8], mut hash: u64) -> u64 {
+    for byte in data {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+/// Key on everything that changes the answer: model, sampling params, the
+/// full system prompt (working directory, date, memory and AICLI.md), the
+/// tools the model may call and the messages.
+pub fn cache_key(model: &ModelConfig, system_prompt: &str, tools: &[String], messages: &[Message]) -> String {
+    let params = format!(
+        "{}|{}|{}|{}|{}|{}",
+        model.endpoint, model.deployment, model.max_tokens, model.temperature, system_prompt, tools.join(",")
+    );
+    let body = serde_json::to_string(messages).unwrap_or_default();
+
+    // Two seeds give a 128-bit key, plenty for a local cache
+    let a = fnv1a(body.as_bytes(), fnv1a(params.as_bytes(), 0xcbf29ce484222325));
+    let b = fnv1a(body.as_bytes(), fnv1a(params.as_bytes(), 0x84222325cbf29ce4));
+    format!("{:016x}{:016x}", a, b)
+}
+
+/// Look up a response no older than `ttl_hours` (0 = never expires)
+pub fn get(key: &str, ttl_hours: u64) -> Option<String> {
+    let content = fs::read_to_string(get_cache_dir().join(format!("{}.json", key))).ok()?;
+    let entry: CacheEntry = serde_json::from_str(&content).ok()?;
+
+    let age_secs = chrono::Utc::now().timestamp() - entry.created_at;
+    if ttl_hours > 0 && age_secs > (ttl_hours * 3600) as i64 {
+        return None;
+    }
+
+    Some(entry.response)
+}
+
+pub fn put(key: &str, model: &str, response: &str) -> Result<()> {
+    let dir = get_cache_dir();
+    fs::create_dir_all(&dir)?;
+
+    let entry = CacheEntry {
+        created_at: chrono::Utc::now().timestamp(),
+        model: model.to_string(),
+        response: response.to_string(),
+    };
+    fs::write(dir.join(format!("{}.json", key)), serde_json::to_string(&entry)?)?;
+
+    Ok(())
+}
@@ -0,0 +1,395 @@
+//! `aicli models catalog`: the deployments of an Azure AI Foundry (or Azure
+//! OpenAI) resource and the models it offers, with their capabilities and
+//! quotas. The key of a configured model reads the lists; the Azure CLI,
+//! when signed in, adds the quotas and the models of other providers.
+//! Picking a deployment adds it to config.toml.
+
+use anyhow::{Context, Result};
+use serde_json::Value;
+use std::process::Command;
+
+use crate::config::{self, AppConfig, ModelConfig, ModelType};
+use crate::console::println;
+use crate::input::format_tokens;
+use crate::ui::{MenuItem, UI};
+
+const DEPLOYMENTS_API_VERSION: &str = "2022-12-01";
+const MODELS_API_VERSION: &str = "2024-10-21";
+
+struct Deployment {
+    name: String,
+    model: String,
+    version: String,
+    state: String,
+    requests_per_minute: Option<u32>,
+    tokens_per_minute: Option<u32>,
+}
+
+struct Offer {
+    name: String,
+    version: String,
+    format: String,
+    capabilities: Vec<String>,
+    lifecycle: String,
+    retires: Option<String>,
+    /// Deployment types with the most TPM each allows, e.g. "GlobalStandard 450K"
+    skus: Vec<String>,
+}
+
+/// Where the lists came from; the Azure CLI knows more than the key
+struct Catalog {
+    deployments: Vec<Deployment>,
+    offers: Vec<Offer>,
+    /// Resource group and account, when the Azure CLI found the resource
+    resource: Option<(String, String)>,
+}
+
+/// `scheme://host[:port]` of an endpoint, without the API path
+fn resource_base(endpoint: &str) -> Result<String> {
+    let url = reqwest::Url::parse(endpoint).with_context(|| format!("Invalid endpoint '{}'", endpoint))?;
+    let host = url.host_str().unwrap_or_default();
+    Ok(match url.port() {
+        Some(port) => format!("{}://{}:{}", url.scheme(), host, port),
+        None => format!("{}://{}", url.scheme(), host),
+    })
+}
+
+fn text(value: &Value, key: &str) -> String {
+    value.get(key).and_then(|v| v.as_str()).unwrap_or_default().to_string()
+}
+
+/// `"true"` in ARM, `true` in the data plane
+fn is_true(value: &Value) -> bool {
+    value.as_bool().unwrap_or(false) || value.as_str() == Some("true")
+}
+
+/// The capabilities worth showing, in the data plane's or ARM's spelling
+fn capabilities(value: &Value) -> Vec<String> {
+    const NAMES: &[(&str, &str, &str)] = &[
+        ("chat_completion", "chatCompletion", "chat"),
+        ("completion", "completion", "completion"),
+        ("embeddings", "embeddings", "embeddings"),
+        ("fine_tune", "fineTune", "fine-tuning"),
+        ("imageGenerations", "imageGenerations", "images"),
+        ("audio", "audio", "audio"),
+        ("responses", "responses", "responses"),
+        ("assistants", "assistants", "assistants"),
+    ];
+    let mut found: Vec<String> = NAMES
+        .iter()
+        .filter(|(plane, arm, _)| value.get(*plane).or(value.get(*arm)).is_some_and(is_true))
+        .map(|(_, _, label)| label.to_string())
+        .collect();
+    if let Some(context) = value.get("maxContextToken").and_then(|v| v.as_str()).and_then(|v| v.parse::<usize>().ok()) {
+        found.push(format!("{} context", format_tokens(context)));
+    }
+    found
+}
+
+/// A rate limit as per minute, whatever period Azure counts it over
+fn per_minute(limits: &[Value], key: &str) -> Option<u32> {
+    let limit = limits.iter().find(|l| l.get("key").and_then(|k| k.as_str()) == Some(key))?;
+    let count = limit.get("count")?.as_f64()?;
+    let period = limit.get("renewalPeriod").and_then(|p| p.as_f64()).filter(|p| *p > 0.0).unwrap_or(60.0);
+    Some((count * 60.0 / period) as u32)
+}
+
+/// GET a data-plane list with the model's key
+async fn data_plane(base: &str, path: &str, api_version: &str, api_key: &str) -> Result<Vec<Value>> {
+    let url = format!("{}/openai/{}?api-version={}", base, path, api_version);
+    crate::policy::check_endpoint(&url)?;
+    let response = reqwest::Client::new().get(&url).header("api-key", api_key).send().await?;
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        anyhow::bail!("{} returned HTTP {}: {}", url, status.as_u16(), crate::text::truncate(body.trim(), 200));
+    }
+    let json: Value = response.json().await?;
+    Ok(json.get("data").and_then(|d| d.as_array()).cloned().unwrap_or_default())
+}
+
+fn from_data_plane(deployments: Vec<Value>, models: Vec<Value>) -> Catalog {
+    let deployments = deployments
+        .iter()
+        .map(|d| Deployment {
+            name: text(d, "id"),
+            model: text(d, "model"),
+            version: String::new(),
+            state: text(d, "status"),
+            requests_per_minute: None,
+            tokens_per_minute: None,
+        })
+        .collect();
+    let offers = models
+        .iter()
+        // Fine-tuned models list their base model; the catalog is the base ones
+        .filter(|m| m.get("model").is_none())
+        .map(|m| Offer {
+            name: text(m, "id"),
+            version: String::new(),
+            format: "OpenAI".to_string(),
+            capabilities: m.get("capabilities").map(capabilities).unwrap_or_default(),
+            lifecycle: text(m, "lifecycle_status"),
+            retires: m
+                .pointer("/deprecation/inference")
+                .and_then(|t| t.as_i64())
+                .and_then(|t| chrono::DateTime::from_timestamp(t, 0))
+                .map(|t| t.format("%Y-%m-%d").to_string()),
+            skus: Vec::new(),
+        })
+        .collect();
+    Catalog { deployments, offers, resource: None }
+}
+
+/// Run `az` and parse its JSON; None when it is missing, signed out or refuses
+fn az(args: &[&str]) -> Option<Value> {
+    let output = Command::new(if cfg!(windows) { "az.cmd" } else { "az" }).args(args).args(["-o", "json"]).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    serde_json::from_slice(&output.stdout).ok()
+}
+
+/// The deployments and models of the account through ARM, which has the
+/// quotas and lists every provider's models
+fn from_azure_cli(account: &str) -> Option<Catalog> {
+    let query = format!("[?name=='{}'] | [0].resourceGroup", account);
+    let group = az(&["cognitiveservices", "account", "list", "--query", &query])?.as_str()?.to_string();
+    let deployments = az(&["cognitiveservices", "account", "deployment", "list", "-g", &group, "-n", account])?;
+    let models = az(&["cognitiveservices", "account", "list-models", "-g", &group, "-n", account]).unwrap_or_default();
+
+    let deployments = deployments
+        .as_array()?
+        .iter()
+        .map(|d| {
+            let properties = d.get("properties").cloned().unwrap_or_default();
+            let limits = properties.get("rateLimits").and_then(|l| l.as_array()).cloned().unwrap_or_default();
+            // Standard capacity is in units of 1,000 TPM
+            let capacity = d.pointer("/sku/capacity").and_then(|c| c.as_u64()).map(|c| (c * 1000) as u32);
+            Deployment {
+                name: text(d, "name"),
+                model: properties.pointer("/model/name").and_then(|n| n.as_str()).unwrap_or_default().to_string(),
+                version: properties.pointer("/model/version").and_then(|v| v.as_str()).unwrap_or_default().to_string(),
+                state: text(&properties, "provisioningState"),
+                requests_per_minute: per_minute(&limits, "request"),
+                tokens_per_minute: per_minute(&limits, "token").or(capacity),
+            }
+        })
+        .collect();
+    let offers = models
+        .as_array()
+        .map(|models| {
+            models
+                .iter()
+                .map(|m| {
+                    let skus = m
+                        .get("skus")
+                        .and_then(|s| s.as_array())
+                        .map(|skus| {
+                            skus.iter()
+                                .map(|sku| match sku.pointer("/capacity/maximum").and_then(|c| c.as_u64()) {
+                                    Some(max) => format!("{} {}", text(sku, "name"), format_tokens(max as usize * 1000)),
+                                    None => text(sku, "name"),
+                                })
+                                .collect()
+                        })
+                        .unwrap_or_default();
+                    Offer {
+                        name: text(m, "name"),
+                        version: text(m, "version"),
+                        format: text(m, "format"),
+                        capabilities: m.get("capabilities").map(capabilities).unwrap_or_default(),
+                        lifecycle: text(m, "lifecycleStatus"),
+                        retires: m.pointer("/deprecation/inference").and_then(|d| d.as_str()).map(|d| d.chars().take(10).collect()),
+                        skus,
+                    }
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    Some(Catalog { deployments, offers, resource: Some((group, account.to_string())) })
+}
+
+async fn load(base: &str, api_key: &str, ui: &UI) -> Result<Catalog> {
+    let host = base.split("://").nth(1).unwrap_or(base);
+    if host.ends_with(".azure.com") {
+        let account = host.split('.').next().unwrap_or_default().to_string();
+        if let Some(catalog) = tokio::task::spawn_blocking(move || from_azure_cli(&account)).await? {
+            return Ok(catalog);
+        }
+        ui.print_info("Quotas and non-OpenAI models need the Azure CLI signed in (az login); listing with the key");
+    }
+    let (deployments, models) = tokio::join!(
+        data_plane(base, "deployments", DEPLOYMENTS_API_VERSION, api_key),
+        data_plane(base, "models", MODELS_API_VERSION, api_key)
+    );
+    Ok(from_data_plane(deployments?, models.unwrap_or_default()))
+}
+
+fn deployment_detail(deployment: &Deployment, offers: &[Offer]) -> String {
+    let mut detail = vec![match deployment.version.is_empty() {
+        true => deployment.model.clone(),
+        false => format!("{} {}", deployment.model, deployment.version),
+    }];
+    let offer = offers
+        .iter()
+        .find(|o| o.name == deployment.model && o.version == deployment.version)
+        .or_else(|| offers.iter().find(|o| o.name == deployment.model));
+    if let Some(offer) = offer.filter(|o| !o.capabilities.is_empty()) {
+        detail.push(offer.capabilities.join(", "));
+    }
+    if let Some(tpm) = deployment.tokens_per_minute {
+        detail.push(format!("{} TPM", format_tokens(tpm as usize)));
+    }
+    if let Some(rpm) = deployment.requests_per_minute {
+        detail.push(format!("{} RPM", rpm));
+    }
+    if !deployment.state.is_empty() && !deployment.state.eq_ignore_ascii_case("succeeded") {
+        detail.push(deployment.state.to_lowercase());
+    }
+    detail.join(" · ")
+}
+
+fn offer_detail(offer: &Offer) -> String {
+    let mut detail = Vec::new();
+    if !offer.format.is_empty() {
+        detail.push(offer.format.clone());
+    }
+    if !offer.capabilities.is_empty() {
+        detail.push(offer.capabilities.join(", "));
+    }
+    if !offer.skus.is_empty() {
+        detail.push(offer.skus.join(", "));
+    }
+    if !offer.lifecycle.is_empty() {
+        detail.push(offer.lifecycle.to_lowercase());
+    }
+    if let Some(retires) = &offer.retires {
+        detail.push(format!("retires {}", retires));
+    }
+    detail.join(" · ")
+}
+
+fn offer_label(offer: &Offer) -> String {
+    match offer.version.is_empty() {
+        true => offer.name.clone(),
+        false => format!("{} {}", offer.name, offer.version),
+    }
+}
+
+/// Add `deployment` to the config under a name the user picks
+fn add(config: &mut AppConfig, source: &ModelConfig, base: &str, deployment: &Deployment, ui: &UI) -> Result<()> {
+    let name = loop {
+        let answer = ui.read_line(&format!("Name for {} in aicli [{}]: ", deployment.name, deployment.name)).unwrap_or_default();
+        let name = if answer.is_empty() { deployment.name.clone() } else { answer };
+        if !config.models.contains_key(&name) {
+            break name;
+        }
+        ui.print_error(&format!("A model named '{}' is already configured", name));
+    };
+    let model_type = config::detect_model_type(&format!("{} {}", deployment.name, deployment.model));
+    // Claude and OpenAI deployments of one resource sit under different paths
+    let claude = |t: &ModelType| *t == ModelType::Claude;
+    let endpoint = if claude(&model_type) == claude(&source.model_type) { source.endpoint.clone() } else { base.to_string() };
+    config.add_model(ModelConfig {
+        name: name.clone(),
+        api_key: source.api_key.clone(),
+        endpoint,
+        deployment: deployment.name.clone(),
+        model_type,
+        requests_per_minute: deployment.requests_per_minute,
+        tokens_per_minute: deployment.tokens_per_minute,
+        ..Default::default()
+    });
+    config::save_config(config)?;
+    ui.print_success(&format!("Added model '{}'; /model {} switches to it", name, name));
+    Ok(())
+}
+
+/// How to deploy a model the resource offers but hasn't deployed
+fn deploy_hint(offer: &Offer, resource: Option<&(String, String)>, ui: &UI) {
+    ui.print_info(&format!("{} isn't deployed yet. Deploy it in Azure AI Foundry (Deployments), or:", offer_label(offer)));
+    let (group, account) = match resource {
+        Some((group, account)) => (group.as_str(), account.as_str()),
+        None => ("<resource-group>", "<resource>"),
+    };
+    let sku = offer.skus.first().and_then(|s| s.split_whitespace().next()).unwrap_or("GlobalStandard");
+    println!(
+        "  az cognitiveservices account deployment create -g {} -n {} --deployment-name {} --model-name {} --model-version {} --model-format {} --sku-name {} --sku-capacity 10",
+        group,
+        account,
+        offer.name,
+        offer.name,
+        if offer.version.is_empty() { "<version>" } else { &offer.version },
+        if offer.format.is_empty() { "OpenAI" } else { &offer.format },
+        sku
+    );
+    println!("  Then run aicli models catalog again to add it.");
+}
+
+/// `aicli models catalog [--model NAME]`: the resource of NAME, or of the
+/// active model
+pub async fn run(mut config: AppConfig, model: Option<&str>) -> Result<()> {
+    let source = match model {
+        Some(name) => config.models.get(name).ok_or_else(|| anyhow::anyhow!("Model '{}' not found", name))?,
+        None => config.get_active_model().ok_or_else(|| anyhow::anyhow!("No model configured; add one with 'aicli models add'"))?,
+    }
+    .clone();
+    let base = resource_base(&source.endpoint)?;
+    let mut ui = UI::new(config.language);
+    ui.set_code_style(&config.ui);
+
+    eprintln!("\x1b[38;5;245mReading the catalog of {}...\x1b[0m", base);
+    let catalog = load(&base, &source.api_key, &ui).await?;
+    let configured = |deployment: &str| config.models.values().any(|m| m.deployment == deployment && resource_base(&m.endpoint).is_ok_and(|b| b == base));
+
+    let detail = |deployment: &Deployment| match configured(&deployment.name) {
+        true => format!("{} · configured", deployment_detail(deployment, &catalog.offers)),
+        false => deployment_detail(deployment, &catalog.offers),
+    };
+
+    let mut items = Vec::new();
+    for deployment in &catalog.deployments {
+        items.push(MenuItem::new(&deployment.name, detail(deployment), false).in_group("Deployments"));
+    }
+    let deployed = |offer: &Offer| catalog.deployments.iter().any(|d| d.model == offer.name && (d.version.is_empty() || d.version == offer.version));
+    let offers: Vec<&Offer> = catalog.offers.iter().filter(|o| !deployed(o)).collect();
+    for offer in &offers {
+        items.push(MenuItem::new(offer_label(offer), offer_detail(offer), false).in_group("Models you can deploy"));
+    }
+    if items.is_empty() {
+        println!("{} has no deployments or models to list.", base);
+        return Ok(());
+    }
+
+    if !ui.menus_interactive() {
+        println!("Deployments:");
+        for deployment in &catalog.deployments {
+            println!("  {:<28} {}", deployment.name, detail(deployment));
+        }
+        if !offers.is_empty() {
+            println!("\nModels you can deploy:");
+            for offer in &offers {
+                println!("  {:<28} {}", offer_label(offer), offer_detail(offer));
+            }
+        }
+        return Ok(());
+    }
+
+    let title = format!("{} · {} deployment(s)", base, catalog.deployments.len());
+    let mut selected = 0;
+    while let Some(choice) = ui.pick(&title, &items, selected) {
+        selected = choice;
+        match catalog.deployments.get(choice) {
+            Some(deployment) if configured(&deployment.name) => {
+                ui.print_info(&format!("{} is already configured; aicli models lists it", deployment.name));
+            }
+            Some(deployment) => return add(&mut config, &source, &base, deployment, &ui),
+            None => {
+                deploy_hint(offers[choice - catalog.deployments.len()], catalog.resource.as_ref(), &ui);
+                return Ok(());
+            }
+        }
+    }
+    Ok(())
+}
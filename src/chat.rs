@@ -1,33 +1,334 @@
-use crate::client::{AzureClient, Message, MessageContent};
-use crate::config::{AppConfig, add_model_interactive, save_config};
-use crate::i18n::Language;
-use crate::input::{InputReader, parse_file_references, strip_file_references, read_file_context};
-use crate::tools::{ToolCall, ToolExecutor, ToolResult};
-use crate::ui::UI;
+use crate::bench;
+use crate::buildtools;
+use crate::cache;
+use crate::codeblocks;
+use crate::context;
+use crate::client::{AzureClient, ContentPart, Message, MessageContent, StopReason, TokenUsage};
+use crate::config::{AgentConfig, AppConfig, ContextStrategy, ModelConfig, add_model_interactive, mask_secret, save_config};
+use crate::i18n::{Language, Strings};
+use crate::workspace::{self, WorkspaceState};
+use crate::logtail::{self, LogTail};
+use crate::lsp;
+use crate::memory;
+use crate::output::OutputFile;
+use crate::palette;
+use crate::input::{InputReader, format_tokens, parse_file_references, strip_file_references, read_file_context};
+use crate::patch;
+use crate::paths;
+use crate::process;
+use crate::project;
+use crate::redact;
+use crate::reload::{self, ConfigWatcher};
+use crate::router;
+use crate::safety;
+use crate::speech::{self, Speaker};
+use crate::suggest;
+use crate::session::{self, Session, SessionInfo};
+use crate::checkpoint;
+use crate::snapshot;
+use crate::text;
+use crate::github;
+use crate::history;
+use crate::graphics;
+use crate::images;
+use crate::tools::{block_on, ToolCall, ToolExecutor, ToolResult};
+use crate::ui::{MenuItem, ThinkingBlock, UI};
 use anyhow::Result;
 use rustyline::error::ReadlineError;
-use std::time::Duration;
+use std::collections::HashMap;
+use std::io::IsTerminal;
+use std::time::{Duration, Instant};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
+use std::cell::RefCell;
 
 const COMPACT_THRESHOLD: f32 = 0.85; // Compact when context reaches 85%
+/// Latest messages compacting keeps, unless the model sets keep_messages
+const KEEP_MESSAGES: usize = 4;
+/// Compacting aims below this share of the threshold, so the next turn
+/// doesn't set it off again
+const COMPACT_TARGET: f32 = 0.75;
+const PR_DIFF_CHARS: usize = 20000;
+
+/// Piped input bigger than a quarter of the context is summarized first
+const PIPED_CONTEXT_SHARE: usize = 4;
+/// The summary is made from this much of the start and end of the input
+const PIPED_SUMMARY_CHARS: usize = 200_000;
+
+const PIPED_SUMMARY_PROMPT: &str = "Summarize the text below, which the user piped into a chat to ask about it. \
+Keep errors, warnings, stack traces, file paths, line numbers, identifiers and timestamps verbatim, \
+and say what repeats instead of repeating it. Reply with the summary only.";
+
+const FILES_SUMMARY_PROMPT: &str = "Summarize the files below, which the user attached to a question but are \
+too large to send whole. Keep each file's name, and its structure: types, functions and their signatures, \
+configuration keys, and the errors, paths, identifiers and numbers it contains, verbatim. Reply with the summary only.";
+
+const FOLLOW_UP_PROMPT: &str = "Suggest three short follow-up questions or requests the user might send next, \
+given their message and the answer below. Make them specific to the answer, under 80 characters each, \
+written as the user would type them. Reply with one per line and nothing else.";
+const FOLLOW_UPS: usize = 3;
+const FOLLOW_UP_CHARS: usize = 100;
+/// Candidates `/best` asks for without a number, and at most
+const BEST_OF_DEFAULT: usize = 3;
+const BEST_OF_MAX: usize = 8;
+
+const PR_PROMPT: &str = "Write a GitHub pull request for the commits and diff below. \
+The first line is the title (imperative, under 72 characters, no prefix). \
+Then a blank line and a Markdown description: what changed and why, and how to test it. \
+Reply with the title and description only.";
+
+/// Token and cost totals for the whole session, shown by /cost
+#[derive(Default)]
+struct SessionCost {
+    usage: TokenUsage,
+    cost: f64,
+    priced_calls: usize,
+    unpriced_calls: usize,
+}
 
-/// Animated spinner that runs until stopped
-fn start_thinking_animation(ui: &UI) -> Arc<AtomicBool> {
-    let stop_flag = Arc::new(AtomicBool::new(false));
-    let stop_clone = stop_flag.clone();
+/// Mutable per-conversation state shared between the chat loop and commands
+#[derive(Default)]
+struct ChatState {
+    messages: Vec<Message>,
+    total_tokens: usize,
+    session_cost: SessionCost,
+    /// Where the conversation is saved after every turn
+    session: SessionInfo,
+    /// Root of the project aicli was started in, and what it remembers about it
+    workspace_root: String,
+    workspace: WorkspaceState,
+    /// Reads answers aloud while /speak is on
+    speaker: Option<Speaker>,
+    /// Raw Markdown copy of the answers while /output is on
+    output: Option<OutputFile>,
+    /// The log file /tail follows
+    tail: Option<LogTail>,
+    /// `cat file | aicli`: context for the first message
+    piped: Option<String>,
+    /// Messages and code blocks /quote adds to the next prompt
+    quotes: Vec<String>,
+    /// The command final answers are piped through (/pipe, [ui] pipe)
+    pipe: Option<String>,
+    /// Claude's extended thinking in the last turn, for /thinking
+    thinking: Vec<String>,
+}
+
+/// How the interactive chat starts
+#[derive(Default)]
+pub struct Start {
+    pub resume: Option<Session>,
+    /// `aicli tail`: the log to follow and how many of its last lines to include
+    pub tail: Option<(String, usize)>,
+    /// `cat file | aicli`: the piped text
+    pub piped: Option<String>,
+}
+
+impl ChatState {
+    /// Memory, AICLI.md and pinned files for the system prompt, re-read
+    /// every turn so edits to them are seen
+    fn prompt_context(&self) -> String {
+        memory::prompt_section()
+            + &project::instructions_section(&self.workspace_root)
+            + &self.workspace.pinned_section()
+            + &self.tail.as_ref().map(LogTail::prompt_section).unwrap_or_default()
+    }
+
+    /// Follow `file` (/tail, `aicli tail`)
+    fn start_tail(&mut self, file: &str, lines: usize, ui: &UI, input_reader: &mut InputReader) {
+        match LogTail::start(&paths::resolve(file), lines, input_reader.printer()) {
+            Ok(tail) => {
+                ui.print_success(&format!(
+                    "Following {}; new lines go with your next message (/tail off to stop)",
+                    paths::display(tail.path())
+                ));
+                self.tail = Some(tail);
+            }
+            Err(e) => ui.print_error(&format!("/tail failed: {}", e)),
+        }
+    }
+
+    /// Remove `range` from the conversation (/context), keeping the file
+    /// changes recorded after it in step with the messages that remain
+    fn drop_messages(&mut self, range: std::ops::Range<usize>, ui: &mut UI) {
+        let removed = range.len();
+        self.messages.drain(range.clone());
+        self.session.changes.retain(|c| !(c.after > range.start && c.after <= range.end));
+        for change in self.session.changes.iter_mut().filter(|c| c.after > range.end) {
+            change.after -= removed;
+        }
+        self.total_tokens = estimate_tokens(&self.messages);
+        ui.update_context(self.total_tokens);
+        if let Err(e) = session::save(&mut self.session, &self.messages) {
+            ui.print_error(&format!("Failed to save session: {}", e));
+        }
+    }
+
+    fn save_workspace(&self, ui: &UI) {
+        if let Err(e) = workspace::save(&self.workspace_root, &self.workspace) {
+            ui.print_error(&format!("Failed to save workspace state: {}", e));
+        }
+    }
+
+    /// Replace the conversation with a saved session
+    fn resume(&mut self, session: Session, ui: &mut UI) {
+        ui.print_info(&format!(
+            "Resumed session {} · {} messages · {}",
+            session.info.id,
+            session.messages.len(),
+            text::truncate(&session.display_title(), 60)
+        ));
+        self.messages = session.messages;
+        self.session = session.info;
+        self.total_tokens = estimate_tokens(&self.messages);
+        ui.update_context(self.total_tokens);
+    }
+
+    /// Continue in a new session holding the first `keep` messages, titled
+    /// `title` or after the current one, which is saved and left as it is
+    fn fork(&mut self, keep: usize, title: Option<String>, ui: &mut UI) {
+        if let Err(e) = session::save(&mut self.session, &self.messages) {
+            ui.print_error(&format!("Failed to save session: {}", e));
+            return;
+        }
+        let parent = Session { info: self.session.clone(), messages: self.messages.clone() };
+        let mut info = SessionInfo::new(&parent.info.model);
+        let base = info.id.clone();
+        let mut n = 1;
+        while info.id == parent.info.id || session::exists(&info.id) {
+            n += 1;
+            info.id = format!("{}-{}", base, n);
+        }
+        info.title = Some(title.unwrap_or_else(|| format!("Fork of {}", text::truncate(&parent.display_title(), 60))));
+        info.changes = parent.info.changes.iter().filter(|c| c.after < keep).cloned().collect();
+
+        self.messages.truncate(keep);
+        self.session = info;
+        self.total_tokens = estimate_tokens(&self.messages);
+        ui.update_context(self.total_tokens);
+        if let Err(e) = session::save(&mut self.session, &self.messages) {
+            ui.print_error(&format!("Failed to save session: {}", e));
+        }
+        self.workspace.last_session = Some(self.session.id.clone());
+        self.save_workspace(ui);
+        ui.print_success(&format!(
+            "Forked into session {} with {} message(s) · {} is kept as it was (/resume {})",
+            self.session.id,
+            self.messages.len(),
+            parent.info.id,
+            parent.info.id
+        ));
+    }
+}
+
+impl SessionCost {
+    fn record(&mut self, usage: &TokenUsage, model: &ModelConfig) {
+        self.usage.add(usage);
+        match usage.estimate_cost(model) {
+            Some(cost) => {
+                self.cost += cost;
+                self.priced_calls += 1;
+            }
+            None => self.unpriced_calls += 1,
+        }
+    }
+}
 
+/// What the current turn has spent so far, checked against [agent] limits
+#[derive(Default)]
+struct TurnBudget {
+    iterations: usize,
+    tool_calls: usize,
+    tokens: usize,
+    cost: f64,
+}
+
+impl TurnBudget {
+    fn record(&mut self, usage: &TokenUsage, model: &ModelConfig) {
+        self.tokens += usage.prompt_tokens + usage.completion_tokens;
+        self.cost += usage.estimate_cost(model).unwrap_or(0.0);
+    }
+
+    /// The first limit that has been reached, if any
+    fn exceeded(&self, limits: &AgentConfig) -> Option<String> {
+        if self.iterations >= limits.max_iterations {
+            return Some(format!("{} iterations", limits.max_iterations));
+        }
+        if let Some(max) = limits.max_tool_calls.filter(|max| self.tool_calls >= *max) {
+            return Some(format!("{} tool calls", max));
+        }
+        if let Some(max) = limits.max_tokens.filter(|max| self.tokens >= *max) {
+            return Some(format!("{} tokens", max));
+        }
+        if let Some(max) = limits.max_cost.filter(|max| self.cost >= *max) {
+            return Some(format!("${:.2}", max));
+        }
+        None
+    }
+
+    fn summary(&self) -> String {
+        let mut summary = format!(
+            "{} iterations, {} tool calls, {} tokens",
+            self.iterations, self.tool_calls, self.tokens
+        );
+        if self.cost > 0.0 {
+            summary.push_str(&format!(", ~${:.4}", self.cost));
+        }
+        summary
+    }
+}
+
+/// Animated spinner that runs until stopped. Shows the time since `started`
+/// and, for follow-up requests, which agent step is running.
+fn start_thinking_animation(ui: &UI, started: Instant, step: Option<(usize, usize)>) -> Arc<AtomicBool> {
     let thinking_text = ui.strings.thinking().to_string();
+    let step = step.map(|(n, max)| format!(" · step {}/{}", n, max)).unwrap_or_default();
 
-    std::thread::spawn(move || {
-        let mut frame = 0;
+    spawn_spinner(started, move |frame, elapsed| {
         let spinners = ["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
         let dots = ["", ".", "..", "..."];
+        let s = spinners[frame % spinners.len()];
+        let d = dots[(frame / 3) % dots.len()];
+        format!(
+            "\x1b[38;5;141m{}\x1b[0m \x1b[38;5;103m{}{:<3} {}{}\x1b[0m",
+            s, thinking_text, d, format_elapsed(elapsed), step
+        )
+    })
+}
+
+/// Spinner shown while a tool runs, with its name and elapsed time
+fn start_tool_animation(tool_name: &str, position: (usize, usize)) -> Arc<AtomicBool> {
+    let label = if position.1 > 1 {
+        format!("Running {} ({}/{})", tool_name, position.0, position.1)
+    } else {
+        format!("Running {}", tool_name)
+    };
+
+    spawn_spinner(Instant::now(), move |frame, elapsed| {
+        let spinners = ["⣾", "⣽", "⣻", "⢿", "⡿", "⣟", "⣯", "⣷"];
+        let s = spinners[frame % spinners.len()];
+        format!(
+            "\x1b[38;5;215m{}\x1b[0m \x1b[38;5;103m{} · {}\x1b[0m",
+            s, label, format_elapsed(elapsed)
+        )
+    })
+}
+
+/// Redraw `render(frame, elapsed)` on the current line every 80ms until the
+/// returned flag is set
+fn spawn_spinner<F>(started: Instant, render: F) -> Arc<AtomicBool>
+where
+    F: Fn(usize, Duration) -> String + Send + 'static,
+{
+    let stop_flag = Arc::new(AtomicBool::new(false));
+    let stop_clone = stop_flag.clone();
 
+    if crate::ui::is_plain() {
+        return stop_flag;
+    }
+    std::thread::spawn(move || {
+        let mut frame = 0;
         while !stop_clone.load(Ordering::Relaxed) {
-            let s = spinners[frame % spinners.len()];
-            let d = dots[(frame / 3) % dots.len()];
-            print!("\r\x1b[K\x1b[38;5;141m{}\x1b[0m \x1b[38;5;103m{}{}\x1b[0m", s, thinking_text, d);
+            print!("\r\x1b[K{}", crate::ui::for_console(&render(frame, started.elapsed())));
             std::io::Write::flush(&mut std::io::stdout()).unwrap();
             std::thread::sleep(Duration::from_millis(80));
             frame += 1;
@@ -37,20 +338,49 @@ fn start_thinking_animation(ui: &UI) -> Arc<AtomicBool> {
     stop_flag
 }
 
-pub async fn run(mut config: AppConfig) -> Result<()> {
+/// Stop a spinner and wait for its last frame to be drawn
+fn stop_spinner(stop_flag: &AtomicBool) {
+    if !stop_flag.swap(true, Ordering::Relaxed) {
+        std::thread::sleep(Duration::from_millis(100));
+    }
+}
+
+fn format_elapsed(elapsed: Duration) -> String {
+    let secs = elapsed.as_secs();
+    if secs < 60 {
+        format!("{}s", secs)
+    } else {
+        format!("{}m{:02}s", secs / 60, secs % 60)
+    }
+}
+
+pub async fn run(mut config: AppConfig, start: Start) -> Result<()> {
+    if config.ui.ascii_only {
+        crate::ui::set_ascii_only(true);
+    }
+    // `aicli < questions.txt > answers.md`
+    if !std::io::stdin().is_terminal() || !std::io::stdout().is_terminal() {
+        crate::ui::set_plain(true);
+    }
     let mut ui = UI::new(config.language);
+    ui.set_code_style(&config.ui);
 
     let active_model = config.get_active_model()
         .ok_or_else(|| anyhow::anyhow!("No active model configured"))?
         .clone();
 
     let mut client = AzureClient::new(active_model.clone());
+    client.set_language(config.language);
+    client.set_prompt_context(memory::prompt_section());
+    let executor = ToolExecutor::new(config.tools.clone());
+    redact::configure(&config.redaction);
+    crate::metrics::configure(&config.metrics);
 
     // Set context max from client
     ui.set_context_max(client.get_max_context());
 
     let model_names: Vec<String> = config.models.keys().cloned().collect();
-    let mut input_reader = InputReader::new(model_names);
+    let mut input_reader = InputReader::new(model_names, config.snippets.clone());
 
     let current_dir = std::env::current_dir()
         .map(|p| p.to_string_lossy().to_string())
@@ -63,20 +393,85 @@ pub async fn run(mut config: AppConfig) -> Result<()> {
 
     ui.print_banner(&active_model.name, &active_model.model_type.to_string(), &current_dir);
     ui.print_welcome_line();
+    ui.set_sticky_status(config.ui.sticky_status);
+    if let Some(loaded) = crate::dotenv::loaded() {
+        if !loaded.variables.is_empty() {
+            ui.print_info(&format!(
+                ".env: {} (--no-dotenv to skip)",
+                loaded.variables.join(", ")
+            ));
+        }
+        if !loaded.skipped.is_empty() {
+            ui.print_error(&format!(".env may not set {}; ignored", loaded.skipped.join(", ")));
+        }
+    }
+    if crate::tools::is_dry_run() {
+        ui.print_info("Dry run: file edits, commands and other changes are only described (/dryrun off to stop)");
+    }
 
-    let mut messages: Vec<Message> = Vec::new();
-    let mut total_tokens: usize = 0;
+    let workspace_root = memory::project_root();
+    let mut state = ChatState {
+        session: SessionInfo::new(&active_model.name),
+        workspace: workspace::load(&workspace_root),
+        workspace_root,
+        pipe: config.ui.pipe.clone(),
+        ..Default::default()
+    };
+    if let Some(session) = start.resume {
+        state.resume(session, &mut ui);
+    } else if let Some(last) = state.workspace.last_session.as_deref().and_then(|id| session::load(id).ok()) {
+        ui.print_info(&format!(
+            "Last session here: {} ({}) · /resume to continue it",
+            text::truncate(&last.display_title(), 50),
+            last.info.id
+        ));
+    }
+    if config.speech.enabled {
+        match Speaker::start(&config.speech, config.language) {
+            Ok(speaker) => state.speaker = Some(speaker),
+            Err(e) => ui.print_error(&format!("Speech disabled: {}", e)),
+        }
+    }
+    if !state.workspace.pinned.is_empty() {
+        let pinned: Vec<String> = state.workspace.pinned.iter().map(|p| paths::display(std::path::Path::new(p))).collect();
+        ui.print_info(&format!("Pinned: {}", pinned.join(", ")));
+    }
+    if let Some((file, lines)) = start.tail {
+        state.start_tail(&file, lines, &ui, &mut input_reader);
+    }
+    if let Some(text) = start.piped.filter(|t| !t.trim().is_empty()) {
+        state.piped = Some(piped_context(&text, &client, &ui).await);
+    }
 
+    let mut watcher = ConfigWatcher::new();
+    // The input line the command palette left to edit
+    let mut initial: Option<String> = None;
     loop {
         // Draw input prompt
         ui.draw_input_box();
         let prompt = ui.get_prompt();
 
-        let input = match input_reader.readline(&prompt) {
+        if let Some(tail) = &state.tail {
+            tail.set_idle(true);
+        }
+        input_reader.set_context(ui.context_used, ui.context_max);
+        let line = match initial.take() {
+            Some(initial) => input_reader.readline_with_initial(&prompt, &initial),
+            None => input_reader.readline(&prompt),
+        };
+        if let Some(tail) = &state.tail {
+            tail.set_idle(false);
+        }
+        let input = match line {
             Ok(line) => line,
             Err(ReadlineError::Interrupted) => {
                 println!();
-                ui.print_info(&ui.strings.ctrl_c_hint().to_string());
+                if let Some(typed) = input_reader.take_palette() {
+                    let models: Vec<String> = config.models.keys().cloned().collect();
+                    initial = Some(palette::open(&typed, &state.messages, &models, &config.active_model, &ui));
+                    continue;
+                }
+                ui.print_info(ui.strings.ctrl_c_hint());
                 continue;
             }
             Err(ReadlineError::Eof) => {
@@ -89,89 +484,233 @@ pub async fn run(mut config: AppConfig) -> Result<()> {
             }
         };
 
-        let input = input.trim();
+        let mut input = input.trim().to_string();
+        let mut retry: Option<Box<Retry>> = None;
+        if let Some(reply) = input_reader.take_quick_reply() {
+            ui.print_quick_reply(&reply);
+            input_reader.add_history_entry(&reply);
+            input = reply;
+        }
         if input.is_empty() {
             continue;
         }
 
-        input_reader.add_history_entry(input);
+        // Edits to config.toml apply from the prompt being entered
+        match watcher.poll() {
+            Some(Ok(reloaded)) => apply_reload(&mut ui, &mut config, &mut client, &mut input_reader, reloaded),
+            Some(Err(e)) => ui.print_error(&format!("config.toml not reloaded: {}", e)),
+            None => {}
+        }
+
+        input_reader.add_history_entry(&input);
 
         // Handle commands
         if input.starts_with('/') {
-            match handle_command(input, &mut ui, &mut config, &mut client, &mut messages, &mut input_reader, &mut total_tokens) {
+            match handle_command(&input, &mut ui, &mut config, &mut client, &mut state, &mut input_reader) {
                 CommandResult::Continue => continue,
                 CommandResult::Exit => break,
                 CommandResult::Processed => continue,
+                CommandResult::Prompt(prompt) => {
+                    input_reader.add_history_entry(&prompt);
+                    input = prompt;
+                }
+                CommandResult::Retry(replay) => {
+                    input = history::prompt_text(&replay.prompt);
+                    retry = Some(replay);
+                }
             }
         }
+        let input = input.as_str();
+        ui.print_question(input);
 
         // Parse file references
-        let file_refs = parse_file_references(input);
+        let mut file_refs = parse_file_references(input);
         let clean_input = strip_file_references(input);
 
+        if config.content_safety.enabled && retry.is_none() {
+            match safety::analyze(&config.content_safety, &clean_input).await {
+                Ok(flags) if flags.is_empty() => {}
+                Ok(flags) => {
+                    ui.print_error(&format!("Prompt not sent: Content Safety flagged {}", safety::describe(&flags)));
+                    continue;
+                }
+                Err(e) => {
+                    ui.print_error(&format!("Prompt not sent: {} (or set [content_safety] enabled = false)", e));
+                    continue;
+                }
+            }
+        }
+
+        if config.ui.suggest_files && retry.is_none() && std::io::stdin().is_terminal() {
+            let attached: Vec<String> = file_refs.iter().chain(&state.workspace.pinned).cloned().collect();
+            for suggestion in suggest::files(&clean_input, &attached) {
+                if ui.confirm(&format!("Mentioning `{}`: attach {}?", suggestion.mention, suggestion.path)) {
+                    file_refs.push(suggestion.path);
+                }
+            }
+        }
+
         let mut full_message = clean_input.clone();
 
-        if !file_refs.is_empty() {
+        if let Some(retry) = &retry {
+            // Sent as it was the first time; quotes and new log lines wait for the next prompt
+            full_message = retry.prompt.content.as_text();
+        } else if !file_refs.is_empty() {
             ui.print_file_context(&file_refs);
-            let context = read_file_context(&file_refs);
+            for image in file_refs.iter().map(|f| paths::resolve(f)).filter(|p| graphics::is_image(p)) {
+                graphics::show(&image, config.ui.images);
+            }
+            let mut context = read_file_context(&file_refs);
+            let tokens = context.len() / 4;
+            if config.ui.file_warning_tokens > 0 && tokens > config.ui.file_warning_tokens {
+                match ask_large_files(file_refs.len(), tokens, &ui) {
+                    LargeFiles::Attach => {}
+                    LargeFiles::Summarize => match summarize_files(&file_refs, &context, &client, &ui).await {
+                        Some(summary) => context = summary,
+                        None => {
+                            initial = Some(input.to_string());
+                            continue;
+                        }
+                    },
+                    LargeFiles::Cancel => {
+                        ui.print_info("Not sent; the prompt is back on the input line");
+                        initial = Some(input.to_string());
+                        continue;
+                    }
+                }
+            }
             full_message = format!("{}\n\nFile context:{}", clean_input, context);
         }
+        if !state.quotes.is_empty() && retry.is_none() {
+            ui.print_info(&format!("Attached {} quote(s)", state.quotes.len()));
+            full_message = format!("{}\n\n{}", full_message, std::mem::take(&mut state.quotes).join("\n\n"));
+        }
+        if let Some(piped) = state.piped.take() {
+            full_message = format!("{}\n\n{}", full_message, piped);
+        }
+        if let Some(tail) = state.tail.as_mut().filter(|_| retry.is_none()) {
+            if let Some(lines) = tail.take() {
+                ui.print_info(&format!("Attached the new lines of {}", paths::display(tail.path())));
+                full_message = format!("{}\n\n{}", full_message, lines);
+            }
+        }
 
-        messages.push(Message {
+        state.messages.push(Message {
             role: "user".to_string(),
             content: MessageContent::Text(full_message),
         });
+        snapshot::begin_turn(&clean_input);
+
+        // Route this turn to the fast or smart model, unless /retry picked one
+        let routed = if retry.is_some() { None } else { router::pick_model(&config, &clean_input, !file_refs.is_empty()).await };
+        if let Some((route, model)) = routed {
+            client.update_config(model.clone());
+            ui.set_context_max(client.get_max_context());
+            ui.set_model_info(&model.name, &model.model_type.to_string(), &ui.current_path.clone());
+            ui.route = Some(route.to_string());
+        }
 
-        // Check if we need to auto-compact before the API call
-        let context_percent = (total_tokens as f32) / (ui.context_max as f32);
-        if context_percent > COMPACT_THRESHOLD && messages.len() > 4 {
+        // Check if we need to auto-compact before the API call, the way
+        // the model's context_strategy says
+        let model = client.model_config();
+        let threshold = model.compact_threshold.unwrap_or(COMPACT_THRESHOLD);
+        let keep = model.keep_messages.unwrap_or(KEEP_MESSAGES).max(1);
+        let context_percent = (state.total_tokens as f32) / (ui.context_max as f32);
+        if context_percent > threshold && state.messages.len() > keep {
             ui.print_info(&format!("Context {}% full. Auto-compacting...", (context_percent * 100.0) as usize));
-            messages = compact_messages(&messages, &client, &ui).await;
-            total_tokens = estimate_tokens(&messages);
-            ui.update_context(total_tokens);
-            ui.print_success("Conversation compacted. Continuing...");
+            // What the system prompt and tools took in the last request
+            let overhead = state.total_tokens.saturating_sub(estimate_tokens(&state.messages[..state.messages.len() - 1]));
+            let budget = ((ui.context_max as f32 * threshold * COMPACT_TARGET) as usize).saturating_sub(overhead);
+            let before = state.messages.len();
+            state.messages = compact_messages(&state.messages, model.context_strategy, keep, budget);
+            state.total_tokens = estimate_tokens(&state.messages);
+            ui.update_context(state.total_tokens);
+            ui.print_success(&format!(
+                "Conversation compacted ({} of {} messages kept). Continuing...",
+                state.messages.len(),
+                before
+            ));
         }
 
+        // This turn's prompt; what follows it is the answer
+        let turn_start = state.messages.len() - 1;
+        client.set_prompt_context(state.prompt_context());
+
         let mut response_started = false;
         ui.reset_code_state();
 
         // Start animated thinking spinner
-        let stop_animation = start_thinking_animation(&ui);
-
+        client.take_timings();
+        let turn_started = Instant::now();
+        let stop_animation = start_thinking_animation(&ui, turn_started, None);
+
+        // A piped answer is shown once it is complete, as the command prints it
+        let piping = state.pipe.is_some();
+        state.thinking.clear();
+        let thinking = RefCell::new(ThinkingBlock::default());
         let result = client
-            .chat(&messages, |token| {
-                if !response_started {
-                    // Stop animation and clear line
-                    stop_animation.store(true, Ordering::Relaxed);
-                    std::thread::sleep(Duration::from_millis(100)); // Wait for animation to stop
-                    ui.clear_line();
-                    ui.print_assistant_prefix();
-                    response_started = true;
-                }
-                ui.print_token(token);
-            })
+            .chat_with_thinking(
+                &state.messages,
+                |token| {
+                    if let Some(speaker) = state.speaker.as_mut() {
+                        speaker.push(token);
+                    }
+                    if piping {
+                        return;
+                    }
+                    if !response_started {
+                        // Stop animation and clear line
+                        stop_spinner(&stop_animation);
+                        ui.clear_line();
+                        thinking.borrow_mut().collapse();
+                        ui.print_assistant_prefix();
+                        response_started = true;
+                    }
+                    ui.print_token(token);
+                    if let Some(output) = state.output.as_mut() {
+                        output.write(token);
+                    }
+                },
+                |delta| {
+                    stop_spinner(&stop_animation);
+                    thinking.borrow_mut().push(delta);
+                },
+            )
             .await;
 
         // Make sure animation is stopped
-        stop_animation.store(true, Ordering::Relaxed);
+        stop_spinner(&stop_animation);
+        state.thinking.extend(thinking.into_inner().into_text());
+        if let Some(speaker) = state.speaker.as_mut() {
+            speaker.finish();
+        }
+        if let Some(output) = state.output.as_mut() {
+            output.finish();
+        }
 
+        let mut stop = StopReason::Complete;
         match result {
-            Ok((content, tool_calls, usage)) => {
+            Ok((content, tool_calls, usage, stop_reason)) => {
+                stop = stop_reason;
                 // Update token usage
-                total_tokens = usage.total_tokens;
-                ui.update_context(total_tokens);
-                if !response_started && !content.is_empty() {
+                state.total_tokens = usage.total_tokens;
+                state.session_cost.record(&usage, client.model_config());
+                ui.update_context(state.total_tokens);
+                let mut budget = TurnBudget::default();
+                budget.record(&usage, client.model_config());
+                // Text before tool calls isn't the final answer, so it isn't piped
+                let shown = !piping || !tool_calls.is_empty();
+                if !response_started && !content.is_empty() && shown {
                     ui.clear_line();
                     ui.print_assistant_prefix();
                     ui.print_token(&content);
                 }
 
-                if !content.is_empty() {
+                if !content.is_empty() && shown {
                     ui.print_newline();
-                    messages.push(Message {
-                        role: "assistant".to_string(),
-                        content: MessageContent::Text(content.clone()),
-                    });
+                }
+                if let Some(answer) = assistant_message(&content, &tool_calls, client.take_thinking()) {
+                    state.messages.push(answer);
                 }
 
                 // Execute tools with animation
@@ -180,28 +719,24 @@ pub async fn run(mut config: AppConfig) -> Result<()> {
                         ui.clear_line();
                     }
 
-                    let tool_results = execute_tools_animated(&ui, &tool_calls);
+                    let tool_results = execute_tools_animated(&ui, &executor, &tool_calls);
+                    budget.tool_calls += tool_calls.len();
 
-                    let mut iterations = 0;
-                    let max_iterations = 10;
                     let mut pending_results = tool_results;
 
-                    while !pending_results.is_empty() && iterations < max_iterations {
-                        iterations += 1;
-
-                        let results_text = pending_results
-                            .iter()
-                            .map(|r| format!("[Tool: {} | Success: {}]\n{}", r.tool_name, r.success, r.output))
-                            .collect::<Vec<_>>()
-                            .join("\n\n---\n\n");
+                    while !pending_results.is_empty() {
+                        if let Some(limit) = budget.exceeded(&config.agent) {
+                            ui.print_info(&format!("Turn budget reached ({}): {}", limit, budget.summary()));
+                            if !ui.confirm("Continue this turn?") {
+                                break;
+                            }
+                            // Another full budget before asking again
+                            budget = TurnBudget::default();
+                        }
+                        budget.iterations += 1;
+                        let iterations = budget.iterations;
 
-                        messages.push(Message {
-                            role: "user".to_string(),
-                            content: MessageContent::Text(format!(
-                                "Tool execution results:\n\n{}\n\nContinue with the task.",
-                                results_text
-                            )),
-                        });
+                        state.messages.push(tool_results_message(&pending_results));
 
                         // Show thinking for follow-up
                         ui.print_thinking(iterations);
@@ -210,40 +745,71 @@ pub async fn run(mut config: AppConfig) -> Result<()> {
                         ui.reset_code_state();
 
                         // Start animated thinking spinner for follow-up
-                        let stop_animation = start_thinking_animation(&ui);
+                        let stop_animation = start_thinking_animation(
+                            &ui,
+                            turn_started,
+                            Some((iterations, config.agent.max_iterations)),
+                        );
 
+                        let thinking = RefCell::new(ThinkingBlock::default());
                         let follow_up = client
-                            .chat(&messages, |token| {
-                                if !response_started {
-                                    stop_animation.store(true, Ordering::Relaxed);
-                                    std::thread::sleep(Duration::from_millis(100));
-                                    ui.clear_line();
-                                    ui.print_assistant_prefix();
-                                    response_started = true;
-                                }
-                                ui.print_token(token);
-                            })
+                            .chat_with_thinking(
+                                &state.messages,
+                                |token| {
+                                    if let Some(speaker) = state.speaker.as_mut() {
+                                        speaker.push(token);
+                                    }
+                                    if piping {
+                                        return;
+                                    }
+                                    if !response_started {
+                                        stop_spinner(&stop_animation);
+                                        ui.clear_line();
+                                        thinking.borrow_mut().collapse();
+                                        ui.print_assistant_prefix();
+                                        response_started = true;
+                                    }
+                                    ui.print_token(token);
+                                    if let Some(output) = state.output.as_mut() {
+                                        output.write(token);
+                                    }
+                                },
+                                |delta| {
+                                    stop_spinner(&stop_animation);
+                                    thinking.borrow_mut().push(delta);
+                                },
+                            )
                             .await;
 
-                        stop_animation.store(true, Ordering::Relaxed);
+                        stop_spinner(&stop_animation);
+                        state.thinking.extend(thinking.into_inner().into_text());
+                        if let Some(speaker) = state.speaker.as_mut() {
+                            speaker.finish();
+                        }
+                        if let Some(output) = state.output.as_mut() {
+                            output.finish();
+                        }
 
                         match follow_up {
-                            Ok((follow_content, follow_tools, follow_usage)) => {
+                            Ok((follow_content, follow_tools, follow_usage, follow_stop)) => {
+                                stop = follow_stop;
                                 // Update token usage
-                                total_tokens = follow_usage.total_tokens;
-                                ui.update_context(total_tokens);
-                                if !response_started && !follow_content.is_empty() {
+                                state.total_tokens = follow_usage.total_tokens;
+                                state.session_cost.record(&follow_usage, client.model_config());
+                                budget.record(&follow_usage, client.model_config());
+                                ui.update_context(state.total_tokens);
+                                let shown = !piping || !follow_tools.is_empty();
+                                if !response_started && !follow_content.is_empty() && shown {
                                     ui.clear_line();
                                     ui.print_assistant_prefix();
                                     ui.print_token(&follow_content);
                                 }
 
-                                if !follow_content.is_empty() {
+                                if !follow_content.is_empty() && shown {
                                     ui.print_newline();
-                                    messages.push(Message {
-                                        role: "assistant".to_string(),
-                                        content: MessageContent::Text(follow_content),
-                                    });
+                                }
+                                if let Some(answer) = assistant_message(&follow_content, &follow_tools, client.take_thinking()) {
+                                    state.messages.push(answer);
                                 }
 
                                 if follow_tools.is_empty() {
@@ -252,233 +818,1663 @@ pub async fn run(mut config: AppConfig) -> Result<()> {
                                     if !response_started {
                                         ui.clear_line();
                                     }
-                                    pending_results = execute_tools_animated(&ui, &follow_tools);
+                                    pending_results = execute_tools_animated(&ui, &executor, &follow_tools);
+                                    budget.tool_calls += follow_tools.len();
                                 }
                             }
                             Err(e) => {
                                 ui.clear_line();
-                                ui.print_error(&format!("API error: {}", e));
+                                ui.print_error(&format!("API error: {}", safety::explain(e)));
                                 break;
                             }
                         }
                     }
-
-                    if iterations >= max_iterations {
-                        ui.print_info("Max iterations reached.");
-                    }
                 }
             }
             Err(e) => {
                 ui.clear_line();
-                ui.print_error(&format!("API error: {}", e));
-                messages.pop();
+                ui.print_error(&format!("API error: {}", safety::explain(e)));
+                state.messages.pop();
+            }
+        }
+
+        if let Some(command) = state.pipe.clone() {
+            let last = state.messages.get(turn_start + 1..).and_then(|turn| turn.last());
+            let answer = last.filter(|m| m.role == "assistant").map(|m| m.content.as_text()).filter(|a| !a.is_empty());
+            if let Some(answer) = answer {
+                pipe_answer(&command, &answer, &config, &mut ui, state.output.as_mut());
+            }
+        }
+
+        match stop {
+            StopReason::Length => offer_continue(&mut ui, &client, &mut state).await,
+            StopReason::ContentFilter => ui.print_error(ui.strings.get("stopped_filter")),
+            StopReason::Complete => {}
+        }
+
+        if let Some(error) = state.speaker.as_ref().and_then(|s| s.take_error()) {
+            ui.print_error(&format!("Speech: {}", error));
+        }
+        if let Some(error) = state.output.as_mut().and_then(|o| o.take_error()) {
+            ui.print_error(&error);
+        }
+        if config.content_safety.enabled && config.content_safety.check_responses {
+            let answer = answer_text(state.messages.get(turn_start..).unwrap_or_default());
+            if !answer.is_empty() {
+                match safety::analyze(&config.content_safety, &answer).await {
+                    Ok(flags) if flags.is_empty() => {}
+                    Ok(flags) => ui.print_error(&format!("Content Safety flagged this answer: {}", safety::describe(&flags))),
+                    Err(e) => ui.print_error(&format!("Content Safety: {}", e)),
+                }
+            }
+        }
+
+        if config.ui.verbose_stats {
+            ui.print_turn_stats(&client.take_timings());
+        }
+        if let Some(turn) = snapshot::current_turn() {
+            let changes = snapshot::changes(&turn);
+            if !changes.is_empty() {
+                ui.print_file_changes(&changes);
+                state.session.changes.push(session::TurnChanges { after: state.messages.len(), files: changes });
+            }
+        }
+        match checkpoint::after_turn(config.agent.checkpoints) {
+            Ok(Some(done)) => ui.print_success(&format!("Checkpoint: {}", done)),
+            Ok(None) => {}
+            Err(e) => ui.print_error(&format!("Checkpoint failed: {}", e)),
+        }
+
+        if let Some(retry) = retry {
+            finish_retry(retry, turn_start, &mut ui, &mut client, &mut state);
+        }
+        if config.ui.follow_ups && !crate::ui::is_plain() && stop == StopReason::Complete {
+            let answer = answer_text(state.messages.get(turn_start..).unwrap_or_default());
+            if !answer.is_empty() {
+                let model = config.models.get(&config.router.fast_model).unwrap_or(client.model_config());
+                let suggestions = follow_ups(model, &clean_input, &answer).await;
+                ui.print_follow_ups(&suggestions);
+                input_reader.set_quick_replies(suggestions);
             }
         }
 
+        let answered = state.messages.iter().any(|m| m.role == "assistant");
+        if config.sessions.auto_title && state.session.title.is_none() && answered {
+            let model = config.models.get(&config.router.fast_model).unwrap_or(client.model_config());
+            state.session.title = Some(session::generate_title(model, &state.messages).await);
+        }
+        if let Err(e) = session::save(&mut state.session, &state.messages) {
+            ui.print_error(&format!("Failed to save session: {}", e));
+        }
+        if !state.messages.is_empty() && state.workspace.last_session.as_ref() != Some(&state.session.id) {
+            state.workspace.last_session = Some(state.session.id.clone());
+            state.save_workspace(&ui);
+        }
+
         ui.print_newline();
         ui.print_context_status();
     }
 
-    println!("\n\x1b[36m    {} 🐱\x1b[0m\n", ui.strings.goodbye());
+    process::kill_all();
+    lsp::shutdown();
+
+    if !crate::ui::is_plain() {
+        println!("\n\x1b[36m    {}\x1b[0m\n", crate::ui::for_console(&format!("{} 🐱", ui.strings.goodbye())));
+    }
     Ok(())
 }
 
-fn execute_tools_animated(ui: &UI, tool_calls: &[ToolCall]) -> Vec<ToolResult> {
-    let mut results = Vec::new();
+/// After an answer stopped at max_tokens: say so, and keep continuing it
+/// for as long as the user agrees
+async fn offer_continue(ui: &mut UI, client: &AzureClient, state: &mut ChatState) {
+    let limit = client.model_config().max_tokens.to_string();
+    loop {
+        ui.print_info(&ui.strings.format("stopped_length", &[("tokens", &limit)]));
+        if !ui.confirm(ui.strings.get("continue_answer")) {
+            return;
+        }
+        match continue_last_answer(ui, client, state).await {
+            Ok(StopReason::Length) => continue,
+            Ok(StopReason::ContentFilter) => ui.print_error(ui.strings.get("stopped_filter")),
+            Ok(StopReason::Complete) => {}
+            Err(e) => ui.print_error(&format!("API error: {}", safety::explain(e))),
+        }
+        return;
+    }
+}
 
-    for tool_call in tool_calls.iter() {
-        let input_str = serde_json::to_string_pretty(&tool_call.input).unwrap_or_default();
-        ui.print_tool_call(&tool_call.name, &input_str);
+/// Stream the rest of the last answer and append it to that same message.
+/// A code block that was cut off is drawn whole once the continuation
+/// closes it. Tools the continuation asks for are not run.
+async fn continue_last_answer(ui: &mut UI, client: &AzureClient, state: &mut ChatState) -> Result<StopReason> {
+    let answer = match state.messages.last() {
+        Some(last) if last.role == "assistant" => last.content.as_text(),
+        _ => anyhow::bail!("There is no answer to continue"),
+    };
+    ui.resume_code_state(&answer);
+    if let Some(output) = state.output.as_mut() {
+        output.resume();
+    }
 
-        // Brief animation while executing
-        for frame in 0..3 {
-            ui.print_working(frame, &format!("Executing {}", tool_call.name));
-            std::thread::sleep(Duration::from_millis(100));
-        }
+    let stop_animation = start_thinking_animation(ui, Instant::now(), None);
+    let mut started = false;
+    let result = client
+        .continue_answer(&state.messages, |token| {
+            if !started {
+                stop_spinner(&stop_animation);
+                ui.clear_line();
+                ui.print_assistant_prefix();
+                print!("… ");
+                started = true;
+            }
+            ui.print_token(token);
+            if let Some(speaker) = state.speaker.as_mut() {
+                speaker.push(token);
+            }
+            if let Some(output) = state.output.as_mut() {
+                output.write(token);
+            }
+        })
+        .await;
+    stop_spinner(&stop_animation);
+    if let Some(speaker) = state.speaker.as_mut() {
+        speaker.finish();
+    }
+    if let Some(output) = state.output.as_mut() {
+        output.finish();
+    }
+    if !started {
         ui.clear_line();
+    }
 
-        let result = ToolExecutor::execute(tool_call);
-        ui.print_tool_result(&result.tool_name, &result.output, result.success);
+    let (continuation, _, usage, stop) = result?;
+    ui.print_newline();
+    if let Some(last) = state.messages.last_mut() {
+        last.content = MessageContent::Text(format!("{}{}", answer.trim_end(), continuation));
+    }
+    state.total_tokens = usage.total_tokens;
+    state.session_cost.record(&usage, client.model_config());
+    ui.update_context(state.total_tokens);
+    Ok(stop)
+}
 
-        results.push(result);
+/// Apply an edited config.toml to the running chat and say what changed
+fn apply_reload(ui: &mut UI, config: &mut AppConfig, client: &mut AzureClient, input_reader: &mut InputReader, reloaded: AppConfig) {
+    let changes = reload::apply(config, reloaded);
+    if changes.is_empty() {
+        return;
+    }
+    if changes.ui {
+        if config.ui.ascii_only {
+            crate::ui::set_ascii_only(true);
+        }
+        ui.set_code_style(&config.ui);
+        ui.set_sticky_status(config.ui.sticky_status);
+    }
+    if changes.language {
+        ui.set_language(config.language);
+        client.set_language(config.language);
     }
+    if changes.active_model {
+        if let Some(model) = config.get_active_model() {
+            client.update_config(model.clone());
+            ui.set_context_max(client.get_max_context());
+        }
+    }
+    input_reader.update_models(config.models.keys().cloned().collect());
+    input_reader.set_snippets(config.snippets.clone());
 
-    results
+    let mut note = String::new();
+    if !changes.applied.is_empty() {
+        note = format!("config.toml reloaded: {}", changes.applied.join(", "));
+    }
+    if !changes.restart.is_empty() {
+        let restart = format!("restart to apply {}", changes.restart.join(", "));
+        note = if note.is_empty() { format!("config.toml changed: {}", restart) } else { format!("{} · {}", note, restart) };
+    }
+    ui.print_info(&format!("⟳ {}", note));
 }
 
-enum CommandResult {
-    Continue,
-    Exit,
-    Processed,
+/// Show `answer` as `command` prints it, and write that to /output; the
+/// answer itself is shown when the command can't run or prints nothing
+fn pipe_answer(command: &str, answer: &str, config: &AppConfig, ui: &mut UI, output: Option<&mut OutputFile>) {
+    ui.clear_line();
+    let saved = match crate::pipe::run(command, answer, &config.tools) {
+        Ok(piped) => {
+            ui.print_piped(&piped.stdout);
+            if !piped.success() {
+                ui.print_error(&piped.failure(command));
+            }
+            if piped.stdout.trim().is_empty() {
+                ui.print_answer_section("Answer", "as the model wrote it", answer);
+                answer.to_string()
+            } else {
+                piped.stdout
+            }
+        }
+        Err(e) => {
+            ui.print_error(&format!("/pipe: {:#}", e));
+            ui.print_answer_section("Answer", "as the model wrote it", answer);
+            answer.to_string()
+        }
+    };
+    if let Some(output) = output {
+        output.write(&saved);
+        output.finish();
+    }
 }
 
-fn handle_command(
-    input: &str,
-    ui: &mut UI,
-    config: &mut AppConfig,
-    client: &mut AzureClient,
-    messages: &mut Vec<Message>,
-    input_reader: &mut InputReader,
-    total_tokens: &mut usize,
-) -> CommandResult {
-    let parts: Vec<&str> = input.split_whitespace().collect();
-    let command = parts.first().map(|s| s.to_lowercase()).unwrap_or_default();
-    let args: Vec<&str> = parts.iter().skip(1).cloned().collect();
+/// The assistant's text in `messages`, joined
+fn answer_text(messages: &[Message]) -> String {
+    messages
+        .iter()
+        .filter(|m| m.role == "assistant")
+        .map(|m| m.content.as_text())
+        .filter(|answer| !answer.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
 
-    match command.as_str() {
-        "/exit" | "/quit" | "/q" => CommandResult::Exit,
+/// Up to FOLLOW_UPS questions the user might ask after `answer`; none when
+/// the model call fails
+async fn follow_ups(model: &ModelConfig, prompt: &str, answer: &str) -> Vec<String> {
+    let exchange = format!("User: {}\n\nAssistant: {}", text::truncate(prompt, 2000), text::truncate_start(answer, 6000));
+    let Ok(reply) = AzureClient::new(model.clone()).complete(FOLLOW_UP_PROMPT, &exchange).await else {
+        return Vec::new();
+    };
+    reply
+        .lines()
+        .map(|line| line.trim().trim_start_matches(|c: char| c.is_ascii_digit() || matches!(c, '.' | ')' | '-' | '*' | ' ')).trim())
+        .filter(|line| !line.is_empty())
+        .take(FOLLOW_UPS)
+        .map(|line| text::truncate(line, FOLLOW_UP_CHARS))
+        .collect()
+}
 
-        "/clear" | "/c" => {
-            messages.clear();
-            *total_tokens = 0;
-            ui.update_context(0);
-            if let Some(model) = config.get_active_model() {
-                let current_dir = std::env::current_dir()
-                    .map(|p| p.to_string_lossy().to_string())
-                    .unwrap_or_else(|_| ".".to_string());
-                ui.clear_screen();
-                ui.set_model_info(&model.name, &model.model_type.to_string(), &current_dir);
-                ui.print_banner(&model.name, &model.model_type.to_string(), &current_dir);
-                ui.print_welcome_line();
+/// An answer for the conversation, with the tools it called as tool_use
+/// parts for the results to answer, after the thinking that led to them
+/// (Anthropic wants it back); None when it has neither
+fn assistant_message(content: &str, tool_calls: &[ToolCall], thinking: Vec<ContentPart>) -> Option<Message> {
+    if tool_calls.is_empty() {
+        return (!content.is_empty()).then(|| Message {
+            role: "assistant".to_string(),
+            content: MessageContent::Text(content.to_string()),
+        });
+    }
+    let mut parts = thinking;
+    if !content.is_empty() {
+        parts.push(ContentPart::Text { text: content.to_string() });
+    }
+    parts.extend(tool_calls.iter().map(|call| ContentPart::ToolUse {
+        id: call.id.clone(),
+        name: call.name.clone(),
+        input: call.input.clone(),
+    }));
+    Some(Message { role: "assistant".to_string(), content: MessageContent::Parts(parts) })
+}
+
+/// Feed tool results back to the model as a user turn, as tool_result
+/// parts (models without them are sent the text `as_text` makes)
+fn tool_results_message(results: &[ToolResult]) -> Message {
+    let parts = results
+        .iter()
+        .map(|r| ContentPart::ToolResult {
+            tool_use_id: r.tool_call_id.clone(),
+            name: r.tool_name.clone(),
+            content: r.output.clone(),
+            is_error: !r.success,
+        })
+        .collect();
+
+    Message { role: "user".to_string(), content: MessageContent::Parts(parts) }
+}
+
+/// Build the user message for a prompt, inlining any @file references
+fn build_user_message(input: &str) -> Message {
+    let file_refs = parse_file_references(input);
+    let clean_input = strip_file_references(input);
+
+    let content = if file_refs.is_empty() {
+        clean_input
+    } else {
+        format!("{}\n\nFile context:{}", clean_input, read_file_context(&file_refs))
+    };
+
+    Message {
+        role: "user".to_string(),
+        content: MessageContent::Text(content),
+    }
+}
+
+/// Non-interactive single prompt (`aicli -p`): plain text to stdout, tool
+/// activity to stderr. Tool-free answers are served from / stored in the
+/// response cache when it is enabled. `tools`, from a recipe, limits the
+/// tools the model may call.
+pub async fn run_prompt(
+    config: AppConfig,
+    prompt: &str,
+    use_cache: bool,
+    output: Option<&str>,
+    tools: Option<Vec<String>>,
+) -> Result<()> {
+    let mut run = Headless::new(config, output, tools)?;
+    let cache_key = (use_cache && run.config.cache.enabled).then(|| {
+        let tools: Vec<String> = run
+            .client
+            .offered_tools()
+            .iter()
+            .filter_map(|tool| tool["function"]["name"].as_str().map(String::from))
+            .collect();
+        cache::cache_key(&run.model, &run.client.system_prompt(), &tools, &[build_user_message(prompt)])
+    });
+    if let Some(key) = &cache_key {
+        if let Some(cached) = cache::get(key, run.config.cache.ttl_hours) {
+            match run.config.ui.pipe.clone() {
+                Some(command) => run.pipe(&command, &cached)?,
+                None => {
+                    println!("{}", cached);
+                    if let Some(output) = run.output.as_mut() {
+                        output.write(&cached);
+                    }
+                }
+            }
+            if let Some(error) = run.output.as_mut().and_then(|o| o.take_error()) {
+                anyhow::bail!(error);
+            }
+            return Ok(());
+        }
+    }
+
+    let answer = run.ask(prompt).await?;
+    // Tool runs have side effects, so only pure, complete answers are replayable
+    if let Some(key) = cache_key {
+        if !answer.used_tools && answer.stop == StopReason::Complete && !answer.text.is_empty() {
+            let _ = cache::put(&key, &run.model.name, &answer.text);
+        }
+    }
+    run.finish()
+}
+
+/// The answer to one prompt of a headless run
+pub struct HeadlessAnswer {
+    /// The last message of the answer, after any tool calls
+    pub text: String,
+    pub used_tools: bool,
+    pub stop: StopReason,
+}
+
+/// A conversation without a terminal, for `aicli -p` and recipes: answers
+/// stream to stdout (and the output file), tool activity goes to stderr, and
+/// tools run without confirmation
+pub struct Headless {
+    config: AppConfig,
+    model: ModelConfig,
+    client: AzureClient,
+    executor: ToolExecutor,
+    messages: Vec<Message>,
+    output: Option<OutputFile>,
+}
+
+impl Headless {
+    pub fn new(config: AppConfig, output: Option<&str>, tools: Option<Vec<String>>) -> Result<Self> {
+        if config.ui.ascii_only {
+            crate::ui::set_ascii_only(true);
+        }
+        let model = config.get_active_model()
+            .ok_or_else(|| anyhow::anyhow!("No active model configured"))?
+            .clone();
+        let mut client = AzureClient::new(model.clone());
+        client.set_language(config.language);
+        client.set_prompt_context(memory::prompt_section() + &project::instructions_section(&memory::project_root()));
+        let mut executor = ToolExecutor::new(config.tools.clone());
+        if let Some(tools) = tools {
+            executor.restrict(tools.clone());
+            client.set_allowed_tools(Some(tools));
+        }
+        redact::configure(&config.redaction);
+        crate::metrics::configure(&config.metrics);
+        let output = output.map(OutputFile::create).transpose()?;
+        Ok(Self { config, model, client, executor, messages: Vec::new(), output })
+    }
+
+    /// Send `prompt` after the earlier ones and answer it, running the tools
+    /// the model calls until it is done
+    pub async fn ask(&mut self, prompt: &str) -> Result<HeadlessAnswer> {
+        let config = &self.config;
+        if config.content_safety.enabled {
+            let flags = safety::analyze(&config.content_safety, prompt).await?;
+            if !flags.is_empty() {
+                anyhow::bail!("Prompt not sent: Content Safety flagged {}", safety::describe(&flags));
+            }
+        }
+
+        let turn_start = self.messages.len();
+        self.messages.push(build_user_message(prompt));
+        snapshot::begin_turn(prompt);
+
+        let mut used_tools = false;
+        let mut final_text = String::new();
+        let mut budget = TurnBudget::default();
+        let mut stop;
+        // A piped answer is printed once it is complete, as the command prints it
+        let piping = config.ui.pipe.is_some();
+
+        loop {
+            let output = &mut self.output;
+            let (content, tool_calls, usage, stop_reason) = self
+                .client
+                .chat(&self.messages, |token| {
+                    if piping {
+                        return;
+                    }
+                    print!("{}", token);
+                    let _ = std::io::Write::flush(&mut std::io::stdout());
+                    if let Some(output) = output.as_mut() {
+                        output.write(token);
+                    }
+                })
+                .await
+                .map_err(safety::explain)?;
+            if let Some(output) = self.output.as_mut() {
+                output.finish();
+            }
+
+            if let Some(answer) = assistant_message(&content, &tool_calls, self.client.take_thinking()) {
+                self.messages.push(answer);
+            }
+            if !content.is_empty() {
+                final_text = content;
+            }
+            stop = stop_reason;
+
+            budget.record(&usage, &self.model);
+            if tool_calls.is_empty() {
+                break;
+            }
+            if let Some(limit) = budget.exceeded(&config.agent) {
+                eprintln!("[budget] stopped at {}: {}", limit, budget.summary());
+                break;
+            }
+            budget.iterations += 1;
+            budget.tool_calls += tool_calls.len();
+
+            used_tools = true;
+            let results: Vec<ToolResult> = tool_calls
+                .iter()
+                .map(|call| {
+                    eprintln!("[tool] {}", call.name);
+                    // Nobody is around to confirm in one-shot mode
+                    match self.executor.taint_source(call) {
+                        Some(source) => ToolResult {
+                            tool_call_id: call.id.clone(),
+                            tool_name: call.name.clone(),
+                            output: format!("Blocked: arguments came from untrusted {} output and -p mode cannot ask for confirmation", source),
+                            success: false,
+                        },
+                        None => self.executor.execute(call),
+                    }
+                })
+                .collect();
+            self.messages.push(tool_results_message(&results));
+        }
+        match config.ui.pipe.clone() {
+            Some(command) if !final_text.is_empty() => self.pipe(&command, &final_text)?,
+            Some(_) => {}
+            None => println!(),
+        }
+        let config = &self.config;
+
+        let strings = Strings::new(config.language);
+        match stop {
+            StopReason::Length => {
+                let limit = self.model.max_tokens.to_string();
+                eprintln!("[stopped] {}", strings.format("stopped_length", &[("tokens", &limit)]));
+            }
+            StopReason::ContentFilter => eprintln!("[stopped] {}", strings.get("stopped_filter")),
+            StopReason::Complete => {}
+        }
+
+        let answer = answer_text(&self.messages[turn_start..]);
+        if config.content_safety.enabled && config.content_safety.check_responses && !answer.is_empty() {
+            match safety::analyze(&config.content_safety, &answer).await {
+                Ok(flags) if flags.is_empty() => {}
+                Ok(flags) => eprintln!("[content safety] answer flagged: {}", safety::describe(&flags)),
+                Err(e) => eprintln!("[content safety] {}", e),
+            }
+        }
+
+        if config.ui.verbose_stats {
+            if let Some(stats) = crate::ui::turn_stats(&self.client.take_timings()) {
+                eprintln!("[stats] {}", stats);
+            }
+        }
+        for change in snapshot::current_turn().map(|t| snapshot::changes(&t)).unwrap_or_default() {
+            eprintln!("[changed] {}", change.describe());
+        }
+        match checkpoint::after_turn(config.agent.checkpoints) {
+            Ok(Some(done)) => eprintln!("[checkpoint] {}", done),
+            Ok(None) => {}
+            Err(e) => eprintln!("[checkpoint] failed: {}", e),
+        }
+        Ok(HeadlessAnswer { text: final_text, used_tools, stop })
+    }
+
+    /// Print `answer` as `command` prints it, and write that to the output
+    /// file; fails with the command's exit status
+    fn pipe(&mut self, command: &str, answer: &str) -> Result<()> {
+        let piped = crate::pipe::run(command, answer, &self.config.tools)?;
+        // The answer itself when the command printed nothing, so it isn't lost
+        let mut shown = if piped.stdout.trim().is_empty() { answer.to_string() } else { piped.stdout.clone() };
+        if !shown.ends_with('\n') {
+            shown.push('\n');
+        }
+        print!("{}", shown);
+        let _ = std::io::Write::flush(&mut std::io::stdout());
+        if let Some(output) = self.output.as_mut() {
+            output.write(&shown);
+            output.finish();
+        }
+        if !piped.success() {
+            anyhow::bail!(piped.failure(command));
+        }
+        Ok(())
+    }
+
+    /// Stop background processes and report a failed output file
+    pub fn finish(mut self) -> Result<()> {
+        process::kill_all();
+        lsp::shutdown();
+        if let Some(error) = self.output.as_mut().and_then(|o| o.take_error()) {
+            anyhow::bail!(error);
+        }
+        Ok(())
+    }
+}
+
+/// A tool call's arguments on one line, for the pause menu
+fn tool_call_preview(tool_call: &ToolCall) -> String {
+    text::truncate(&tool_call.input.to_string(), 70)
+}
+
+/// Let the user skip or deny calls that haven't run yet. Decisions are
+/// keyed by index into `tool_calls`.
+fn pause_tool_queue(ui: &UI, tool_calls: &[ToolCall], next: usize, decisions: &mut HashMap<usize, String>) {
+    loop {
+        let queue: Vec<(usize, &str, String, Option<&str>)> = tool_calls
+            .iter()
+            .enumerate()
+            .skip(next)
+            .map(|(i, call)| (i + 1, call.name.as_str(), tool_call_preview(call), decisions.get(&i).map(|d| d.as_str())))
+            .collect();
+        ui.print_tool_queue(&queue);
+
+        let Some(line) = ui.read_line("  \x1b[38;5;220m›\x1b[0m ") else {
+            return;
+        };
+        let mut parts = line.splitn(3, ' ');
+        let action = parts.next().unwrap_or_default();
+        let target = parts
+            .next()
+            .and_then(|n| n.parse::<usize>().ok())
+            .filter(|n| *n > next && *n <= tool_calls.len())
+            .map(|n| n - 1);
+        let reason = parts.next().map(|r| r.trim().to_string()).filter(|r| !r.is_empty());
+
+        match (action, target) {
+            ("", _) | ("c", _) | ("r", _) => return,
+            ("s", Some(i)) => {
+                decisions.insert(i, "skipped by the user".to_string());
+            }
+            ("d", Some(i)) => {
+                let decision = match reason {
+                    Some(reason) => format!("denied by the user: {}", reason),
+                    None => "denied by the user".to_string(),
+                };
+                decisions.insert(i, decision);
+            }
+            ("i", Some(i)) => {
+                let call = &tool_calls[i];
+                println!("\n  \x1b[38;5;75m{}\x1b[0m", call.name);
+                println!("{}", serde_json::to_string_pretty(&call.input).unwrap_or_default());
+            }
+            ("x", _) => {
+                for i in next..tool_calls.len() {
+                    decisions.entry(i).or_insert_with(|| "denied by the user".to_string());
+                }
+                return;
+            }
+            _ => ui.print_error("Unknown choice or call number"),
+        }
+    }
+}
+
+fn execute_tools_animated(ui: &UI, executor: &ToolExecutor, tool_calls: &[ToolCall]) -> Vec<ToolResult> {
+    let mut results = Vec::new();
+    let mut decisions: HashMap<usize, String> = HashMap::new();
+
+    for (index, tool_call) in tool_calls.iter().enumerate() {
+        if ui.pause_requested() {
+            pause_tool_queue(ui, tool_calls, index, &mut decisions);
+        }
+        if let Some(decision) = decisions.remove(&index) {
+            let result = ToolResult {
+                tool_call_id: tool_call.id.clone(),
+                tool_name: tool_call.name.clone(),
+                output: format!("Not executed: {}", decision),
+                success: false,
+            };
+            ui.print_tool_result(&result.tool_name, &result.output, result.success);
+            results.push(result);
+            continue;
+        }
+
+        let input_str = serde_json::to_string_pretty(&tool_call.input).unwrap_or_default();
+        ui.print_tool_call(&tool_call.name, &input_str);
+
+        if let Some(source) = executor.taint_source(tool_call) {
+            ui.print_info(&format!(
+                "The arguments of {} were copied from untrusted {} output",
+                tool_call.name, source
+            ));
+            if !ui.confirm("Run it anyway?") {
+                let result = ToolResult {
+                    tool_call_id: tool_call.id.clone(),
+                    tool_name: tool_call.name.clone(),
+                    output: format!("The user declined this call: its arguments came from untrusted {} output", source),
+                    success: false,
+                };
+                ui.print_tool_result(&result.tool_name, &result.output, result.success);
+                results.push(result);
+                continue;
+            }
+        }
+
+        let stop_animation = start_tool_animation(&tool_call.name, (index + 1, tool_calls.len()));
+        let result = executor.execute(tool_call);
+        stop_spinner(&stop_animation);
+        ui.clear_line();
+        ui.print_tool_result(&result.tool_name, &result.output, result.success);
+
+        results.push(result);
+    }
+
+    results
+}
+
+/// `/copy-code [n]`, `/save-code [n] <path>` and `/apply-code [n]` on the
+/// code blocks of the last response that has any. Blocks are numbered from 1;
+/// without `n` copy/save use the last block and apply writes every block whose
+/// header names a file.
+fn code_block_command(ui: &UI, messages: &[Message], command: &str, args: &[&str]) -> Result<()> {
+    let blocks = messages
+        .iter()
+        .rev()
+        .filter(|m| m.role == "assistant")
+        .map(|m| codeblocks::extract(&m.content.as_text()))
+        .find(|blocks| !blocks.is_empty())
+        .ok_or_else(|| anyhow::anyhow!("No code blocks in the conversation yet"))?;
+
+    let (index, rest) = match args.first().and_then(|a| a.parse::<usize>().ok()) {
+        Some(n) if n >= 1 && n <= blocks.len() => (Some(n - 1), &args[1..]),
+        Some(n) => anyhow::bail!("No code block {} (the last response has {})", n, blocks.len()),
+        None => (None, args),
+    };
+    let pick = index.unwrap_or(blocks.len() - 1);
+
+    match command {
+        "/copy-code" => {
+            let program = codeblocks::copy_to_clipboard(&blocks[pick].code)?;
+            let block = &blocks[pick];
+            let lang = if block.lang.is_empty() { "code" } else { block.lang.as_str() };
+            ui.print_success(&format!("Copied {} block {} to the clipboard ({})", lang, pick + 1, program));
+        }
+        "/save-code" => {
+            if rest.is_empty() {
+                anyhow::bail!("Usage: /save-code [n] <path>");
+            }
+            let path = paths::resolve(&rest.join(" "));
+            write_code_block(ui, &path, &blocks[pick].code)?;
+        }
+        _ => {
+            let targets: Vec<(usize, &codeblocks::CodeBlock)> = match index {
+                Some(i) => vec![(i, &blocks[i])],
+                None => blocks.iter().enumerate().filter(|(_, b)| b.path.is_some()).collect(),
+            };
+            if targets.is_empty() {
+                anyhow::bail!("No code block names a file; use /save-code [n] <path>");
+            }
+            for (i, block) in &targets {
+                match &block.path {
+                    Some(path) => ui.print_info(&format!("[{}] {} ({} lines)", i + 1, path, block.code.lines().count())),
+                    None => anyhow::bail!("Code block {} does not name a file; use /save-code {} <path>", i + 1, i + 1),
+                }
+            }
+            if !ui.confirm("Write these files?") {
+                return Ok(());
+            }
+            for (_, block) in targets {
+                if let Some(path) = &block.path {
+                    write_code_block(ui, &paths::resolve(path), &block.code)?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// `/revert list`, or undo the last turn's (or a given turn's and every later
+/// turn's) file writes after a confirmation
+fn revert_command(ui: &UI, arg: Option<&str>) -> Result<()> {
+    if arg == Some("list") {
+        let turns = snapshot::list();
+        if turns.is_empty() {
+            ui.print_info("No agent changes to revert in this workspace");
+        }
+        for turn in turns.iter().rev() {
+            println!(
+                "  \x1b[38;5;141m{}\x1b[0m  {} file(s)  \x1b[90m{}\x1b[0m",
+                turn.id,
+                turn.files.len(),
+                turn.prompt
+            );
+        }
+        return Ok(());
+    }
+
+    let turns = snapshot::to_revert(arg)?;
+    let mut files: Vec<&snapshot::BackedUpFile> = Vec::new();
+    for file in turns.iter().flat_map(|t| &t.files) {
+        if !files.iter().any(|f| f.path == file.path) {
+            files.push(file);
+        }
+    }
+    println!();
+    for file in &files {
+        let action = if file.existed { "restore" } else { "delete " };
+        println!("  {}  {}", action, paths::display(&file.path));
+    }
+    println!();
+    let question = match turns.as_slice() {
+        [turn] => format!("Revert turn {} ({} file(s))?", turn.id, files.len()),
+        _ => format!("Revert the last {} turns ({} file(s))?", turns.len(), files.len()),
+    };
+    if !ui.confirm(&question) {
+        return Ok(());
+    }
+    let touched = snapshot::revert(&turns)?;
+    ui.print_success(&format!("Reverted {} file(s)", touched.len()));
+    Ok(())
+}
+
+/// Apply the last diff block of the conversation after showing what it touches
+fn apply_last_diff(ui: &UI, messages: &[Message]) -> Result<()> {
+    let diff = messages
+        .iter()
+        .rev()
+        .filter(|m| m.role == "assistant")
+        .flat_map(|m| codeblocks::extract(&m.content.as_text()).into_iter().rev())
+        .find(|b| matches!(b.lang.as_str(), "diff" | "patch") || patch::is_diff(&b.code))
+        .ok_or_else(|| anyhow::anyhow!("No diff block in the conversation yet"))?;
+
+    let patches = patch::parse(&diff.code)?;
+    for file in &patches {
+        let (added, removed) = file.stats();
+        let action = match (&file.old_path, &file.new_path) {
+            (None, _) => "create",
+            (_, None) => "delete",
+            _ => "modify",
+        };
+        ui.print_info(&format!("{} {} (+{} -{})", action, file.target(), added, removed));
+    }
+    if !ui.confirm("Apply this diff?") {
+        return Ok(());
+    }
+
+    let written = patch::apply_all(&patches)?;
+    ui.print_success(&format!("Applied diff to {} file(s)", written.len()));
+    Ok(())
+}
+
+fn write_code_block(ui: &UI, path: &std::path::Path, code: &str) -> Result<()> {
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, code)
+        .map_err(|e| anyhow::anyhow!("Failed to write {}: {}", paths::display(path), e))?;
+    ui.print_success(&format!("Wrote {} lines to {}", code.lines().count(), paths::display(path)));
+    Ok(())
+}
+
+/// Push the current branch and open a PR whose description the model writes
+/// `/init`: scan the project and have the model write a starter AICLI.md
+/// at the workspace root
+fn init_project(ui: &UI, client: &AzureClient, root: &str) -> Result<std::path::PathBuf> {
+    let path = std::path::Path::new(root).join(project::INSTRUCTIONS_FILE);
+    if path.exists() && !ui.confirm(&format!("{} already exists. Overwrite it?", paths::display(&path))) {
+        anyhow::bail!("Kept the existing {}", project::INSTRUCTIONS_FILE);
+    }
+
+    ui.print_info(&format!("Scanning {}...", root));
+    let summary = project::scan(std::path::Path::new(root));
+
+    let spinner = start_thinking_animation(ui, Instant::now(), None);
+    let generated = block_on(client.complete(project::INIT_PROMPT, &summary));
+    stop_spinner(&spinner);
+    ui.clear_line();
+
+    std::fs::write(&path, project::clean_generated(&generated?))?;
+    Ok(path)
+}
+
+fn open_pull_request(
+    ui: &UI,
+    config: &AppConfig,
+    client: &AzureClient,
+    base: Option<String>,
+    draft: bool,
+) -> Result<String> {
+    let github = &config.tools.github;
+    let repo = github::current_repo()?;
+    let head = github::current_branch()?;
+    let base = match base {
+        Some(base) => base,
+        None => block_on(github::default_branch(github, &repo))?,
+    };
+    if head == base || head == "HEAD" {
+        anyhow::bail!("Check out a feature branch first (currently on {})", head);
+    }
+
+    ui.print_info(&format!("Pushing {} to origin...", head));
+    github::push_current_branch()?;
+
+    ui.print_info("Writing pull request description...");
+    let summary = github::branch_summary(&base, PR_DIFF_CHARS)?;
+    let generated = block_on(client.complete(PR_PROMPT, &summary))?;
+    let generated = generated.trim();
+    let (title, body) = generated.split_once('\n').unwrap_or((generated, ""));
+    let title = title.trim().trim_start_matches('#').trim();
+
+    block_on(github::create_pr(github, &repo, title, body.trim(), &head, Some(&base), draft))
+}
+
+enum CommandResult {
+    Continue,
+    Exit,
+    Processed,
+    /// Send this as the user's prompt (/mic)
+    Prompt(String),
+    /// Send the last prompt again (/retry)
+    Retry(Box<Retry>),
+}
+
+/// `/retry`: the last turn, taken back out of the conversation to be sent again
+struct Retry {
+    /// The prompt as it was sent, @file context and attachments included
+    prompt: Message,
+    /// What came after it the first time
+    previous: Vec<Message>,
+    /// The model settings to go back to after the turn, when they were changed
+    restore: Option<ModelConfig>,
+    /// Keep the new answer without comparing
+    replace: bool,
+}
+
+fn handle_command(
+    input: &str,
+    ui: &mut UI,
+    config: &mut AppConfig,
+    client: &mut AzureClient,
+    state: &mut ChatState,
+    input_reader: &mut InputReader,
+) -> CommandResult {
+    let parts: Vec<&str> = input.split_whitespace().collect();
+    let command = parts.first().map(|s| s.to_lowercase()).unwrap_or_default();
+    let args: Vec<&str> = parts.iter().skip(1).cloned().collect();
+
+    match command.as_str() {
+        "/exit" | "/quit" | "/q" => CommandResult::Exit,
+
+        "/clear" | "/c" => {
+            state.messages.clear();
+            state.total_tokens = 0;
+            // The cleared conversation stays saved; new messages start a new session
+            state.session = SessionInfo::new(&client.model_config().name);
+            ui.update_context(0);
+            if let Some(model) = config.get_active_model() {
+                let current_dir = std::env::current_dir()
+                    .map(|p| p.to_string_lossy().to_string())
+                    .unwrap_or_else(|_| ".".to_string());
+                ui.clear_screen();
+                ui.set_model_info(&model.name, &model.model_type.to_string(), &current_dir);
+                ui.print_banner(&model.name, &model.model_type.to_string(), &current_dir);
+                ui.print_welcome_line();
+            }
+            ui.print_success(ui.strings.cleared());
+            CommandResult::Processed
+        }
+
+        "/help" | "/h" | "/?" => {
+            ui.print_help();
+            CommandResult::Processed
+        }
+
+        "/config" => {
+            if let Some(model) = config.get_active_model() {
+                let api_key_masked = mask_secret(&model.api_key);
+                ui.print_config(
+                    &model.endpoint,
+                    &model.deployment,
+                    &model.model_type.to_string(),
+                    model.max_tokens,
+                    model.temperature,
+                    &api_key_masked,
+                );
+            }
+            CommandResult::Processed
+        }
+
+        "/model" => {
+            if args.is_empty() {
+                // Show model list, grouped by provider
+                let mut configured: Vec<(&String, &ModelConfig)> = config.models.iter().collect();
+                configured.sort_by_key(|(name, model)| (model.model_type.to_string(), name.to_lowercase()));
+                let models: Vec<(String, String, bool)> = configured
+                    .iter()
+                    .map(|(name, model)| {
+                        (name.to_string(), model.model_type.to_string(), *name == &config.active_model)
+                    })
+                    .collect();
+
+                let mut items: Vec<MenuItem> = configured
+                    .iter()
+                    .map(|(name, model)| {
+                        MenuItem::new(*name, model_summary(model), *name == &config.active_model).in_group(model.model_type.to_string())
+                    })
+                    .collect();
+                items.push(MenuItem::new("+ Add model", "", false));
+                let current = models.iter().position(|(_, _, is_active)| *is_active).unwrap_or(0);
+
+                match ui.pick(ui.strings.title_models(), &items, current) {
+                    Some(selected_idx) => {
+                        if selected_idx == models.len() {
+                            // "Add model" option selected
+                            if let Err(e) = add_model_interactive(config) {
+                                ui.print_error(&format!("Failed: {}", e));
+                            } else {
+                                let model_names: Vec<String> = config.models.keys().cloned().collect();
+                                input_reader.update_models(model_names);
+                            }
+                        } else if selected_idx < models.len() {
+                            let (selected_name, _, is_active) = &models[selected_idx];
+                            if !is_active {
+                                // Switch to selected model
+                                config.set_active_model(selected_name);
+                                if let Some(model) = config.get_active_model() {
+                                    client.update_config(model.clone());
+                                    ui.set_context_max(client.get_max_context());
+                                    ui.set_model_info(&model.name, &model.model_type.to_string(), &ui.current_path.clone());
+                                    let _ = save_config(config);
+                                    ui.print_model_switch(&model.name, &model.model_type.to_string());
+                                    let model_names: Vec<String> = config.models.keys().cloned().collect();
+                                    input_reader.update_models(model_names);
+                                }
+                            } else {
+                                ui.print_info("Already using this model");
+                            }
+                        }
+                    }
+                    None => ui.print_info("Selection cancelled"),
+                }
+            } else {
+                let model_name = args.join(" ");
+
+                if config.set_active_model(&model_name) {
+                    if let Some(model) = config.get_active_model() {
+                        client.update_config(model.clone());
+                        ui.set_context_max(client.get_max_context());
+                        ui.set_model_info(&model.name, &model.model_type.to_string(), &ui.current_path.clone());
+                        let _ = save_config(config);
+                        ui.print_model_switch(&model.name, &model.model_type.to_string());
+
+                        let model_names: Vec<String> = config.models.keys().cloned().collect();
+                        input_reader.update_models(model_names);
+                    }
+                } else {
+                    let matches: Vec<&String> = config.models.keys()
+                        .filter(|k| k.to_lowercase().contains(&model_name.to_lowercase()))
+                        .collect();
+
+                    if matches.len() == 1 {
+                        let matched_name = matches[0].clone();
+                        config.set_active_model(&matched_name);
+                        if let Some(model) = config.get_active_model() {
+                            client.update_config(model.clone());
+                            ui.set_context_max(client.get_max_context());
+                            ui.set_model_info(&model.name, &model.model_type.to_string(), &ui.current_path.clone());
+                            let _ = save_config(config);
+                            ui.print_model_switch(&model.name, &model.model_type.to_string());
+                        }
+                    } else if matches.is_empty() {
+                        ui.print_error(&format!("Model '{}' {}", model_name, ui.strings.not_found()));
+                    } else {
+                        ui.print_info(&format!("Multiple matches: {}",
+                            matches.iter().map(|s| s.as_str()).collect::<Vec<_>>().join(", ")));
+                    }
+                }
+            }
+            CommandResult::Processed
+        }
+
+        "/add-model" => {
+            if let Err(e) = add_model_interactive(config) {
+                ui.print_error(&format!("Failed: {}", e));
+            } else {
+                let model_names: Vec<String> = config.models.keys().cloned().collect();
+                input_reader.update_models(model_names);
+            }
+            CommandResult::Processed
+        }
+
+        "/cost" => {
+            let totals = &state.session_cost;
+            let cost = (totals.priced_calls > 0).then_some(totals.cost);
+            ui.print_cost(&totals.usage, cost, totals.unpriced_calls);
+            CommandResult::Processed
+        }
+
+        "/pr" => {
+            let draft = args.contains(&"--draft");
+            let base = args.iter().find(|a| !a.starts_with("--")).map(|b| b.to_string());
+            match open_pull_request(ui, config, client, base, draft) {
+                Ok(url) => ui.print_success(&format!("Pull request opened: {}", url)),
+                Err(e) => ui.print_error(&format!("/pr failed: {}", e)),
+            }
+            CommandResult::Processed
+        }
+
+        "/apply" => {
+            if let Err(e) = apply_last_diff(ui, &state.messages) {
+                ui.print_error(&format!("/apply failed: {}", e));
+            }
+            CommandResult::Processed
+        }
+
+        "/copy-code" | "/save-code" | "/apply-code" => {
+            if let Err(e) = code_block_command(ui, &state.messages, &command, &args) {
+                ui.print_error(&e.to_string());
+            }
+            CommandResult::Processed
+        }
+
+        "/compare" => {
+            if args.len() < 3 {
+                ui.print_error("Usage: /compare <model> <model> <prompt>");
+                return CommandResult::Processed;
+            }
+            let prompt = build_user_message(&args[2..].join(" ")).content.as_text();
+            let spinner = start_thinking_animation(ui, Instant::now(), None);
+            let results = block_on(bench::compare(config, &args[..2], &prompt));
+            stop_spinner(&spinner);
+            ui.clear_line();
+            match results {
+                Ok(results) => {
+                    for result in &results {
+                        if let Some(model) = config.models.get(&result.model) {
+                            state.session_cost.record(&result.usage, model);
+                        }
+                    }
+                    ui.print_comparison(&results);
+                }
+                Err(e) => ui.print_error(&e.to_string()),
+            }
+            CommandResult::Processed
+        }
+
+        "/best" => {
+            if let Err(e) = best_of(&args, config, client, ui, state) {
+                ui.print_error(&e.to_string());
+            }
+            CommandResult::Processed
+        }
+
+        "/retry" => {
+            match start_retry(&args, config, client, state) {
+                Ok(retry) => {
+                    let model = client.model_config();
+                    ui.set_context_max(client.get_max_context());
+                    ui.set_model_info(&model.name, &model.model_type.to_string(), &ui.current_path.clone());
+                    ui.print_info(&format!("Retrying with {} (temperature {})", model.name, model.temperature));
+                    return CommandResult::Retry(Box::new(retry));
+                }
+                Err(e) => ui.print_error(&e.to_string()),
+            }
+            CommandResult::Processed
+        }
+
+        "/pipe" => {
+            match args.first().copied() {
+                Some("off") => {
+                    state.pipe = None;
+                    ui.print_info("Answers are shown as they stream again");
+                }
+                Some(_) => {
+                    // As typed, so quoting and spacing in the command survive
+                    let command = input.trim().split_once(char::is_whitespace).map(|(_, rest)| rest.trim()).unwrap_or_default().to_string();
+                    ui.print_success(&format!("Piping answers through `{}` · /pipe off to stop", command));
+                    state.pipe = Some(command);
+                }
+                None => match &state.pipe {
+                    Some(command) => ui.print_info(&format!("Piping answers through `{}` · /pipe off to stop", command)),
+                    None => ui.print_info("Usage: /pipe <command> · /pipe off"),
+                },
+            }
+            CommandResult::Processed
+        }
+
+        "/quote" => {
+            if matches!(args.first().copied(), Some("clear" | "off")) {
+                let dropped = std::mem::take(&mut state.quotes).len();
+                ui.print_info(&format!("Dropped {} quote(s)", dropped));
+            } else {
+                match quote(&state.messages, &args) {
+                    Ok((quoted, what)) => {
+                        state.quotes.push(quoted);
+                        ui.print_success(&format!("Quoting {} in your next prompt · /quote clear to drop it", what));
+                    }
+                    Err(e) => ui.print_error(&e.to_string()),
+                }
+            }
+            CommandResult::Processed
+        }
+
+        "/revert" => {
+            if let Err(e) = revert_command(ui, args.first().copied()) {
+                ui.print_error(&format!("/revert failed: {}", e));
+            }
+            CommandResult::Processed
+        }
+
+        "/dryrun" => {
+            match args.first().map(|a| a.to_lowercase()).as_deref() {
+                Some("on") => {
+                    crate::tools::set_dry_run(true);
+                    ui.print_success("Dry run on - tools describe changes instead of making them");
+                }
+                Some("off") => {
+                    crate::tools::set_dry_run(false);
+                    ui.print_info("Dry run off - tools make changes again");
+                }
+                _ => {
+                    let state = if crate::tools::is_dry_run() { "on" } else { "off" };
+                    ui.print_info(&format!("Dry run is {} · /dryrun on · /dryrun off", state));
+                }
+            }
+            CommandResult::Processed
+        }
+
+        "/perf" => {
+            match args.first().copied() {
+                Some("clear") => match crate::metrics::clear() {
+                    Ok(()) => ui.print_success("Metrics cleared"),
+                    Err(e) => ui.print_error(&format!("/perf clear failed: {}", e)),
+                },
+                Some(days) => match days.parse::<u64>() {
+                    Ok(days) if days > 0 => crate::metrics::print_summary(days),
+                    _ => ui.print_error("Usage: /perf [days] · /perf clear"),
+                },
+                None => crate::metrics::print_summary(7),
+            }
+            CommandResult::Processed
+        }
+
+        "/redact" => {
+            match args.first().map(|a| a.to_lowercase()).as_deref() {
+                Some("on") => {
+                    redact::set_enabled(true);
+                    ui.print_success("Secret redaction enabled");
+                }
+                Some("off") if crate::policy::redaction_enforced() => {
+                    ui.print_error("Secret redaction is required by the organization policy");
+                }
+                Some("off") => {
+                    redact::set_enabled(false);
+                    ui.print_info("Secret redaction disabled for this session - tool output and @files are sent as-is");
+                }
+                _ => {
+                    let state = if redact::is_enabled() { "on" } else { "off" };
+                    ui.print_info(&format!("Secret redaction is {} · /redact on · /redact off", state));
+                }
+            }
+            CommandResult::Processed
+        }
+
+        "/title" => {
+            if args.is_empty() {
+                match &state.session.title {
+                    Some(title) => ui.print_info(&format!("Session {}: {}", state.session.id, title)),
+                    None => ui.print_info("This session has no title yet · /title <text>"),
+                }
+            } else {
+                let title = text::truncate(&args.join(" "), 80);
+                state.session.title = Some(title.clone());
+                match session::save(&mut state.session, &state.messages) {
+                    Ok(()) => ui.print_success(&format!("Session title set to \"{}\"", title)),
+                    Err(e) => ui.print_error(&format!("Failed to save session: {}", e)),
+                }
+            }
+            CommandResult::Processed
+        }
+
+        "/fork" => {
+            if state.messages.is_empty() {
+                ui.print_info("Nothing to fork yet");
+            } else {
+                let title = (!args.is_empty()).then(|| text::truncate(&args.join(" "), 80));
+                state.fork(state.messages.len(), title, ui);
+            }
+            CommandResult::Processed
+        }
+
+        "/export" => {
+            if state.messages.is_empty() {
+                ui.print_info("Nothing to export yet");
+            } else {
+                let path = match args.first() {
+                    Some(file) => paths::resolve(file),
+                    None => std::path::PathBuf::from(format!("aicli-{}.md", state.session.id)),
+                };
+                let session = Session { info: state.session.clone(), messages: state.messages.clone() };
+                match std::fs::write(&path, session::to_markdown(&session)) {
+                    Ok(()) => ui.print_success(&format!("Exported the conversation to {}", paths::display(&path))),
+                    Err(e) => ui.print_error(&format!("/export failed: {}", e)),
+                }
+            }
+            CommandResult::Processed
+        }
+
+        "/fix" => {
+            let task = match args.first() {
+                Some(name) => match buildtools::Task::parse(name) {
+                    Some(task) => task,
+                    None => {
+                        ui.print_info("Usage: /fix [build|test|lint]");
+                        return CommandResult::Processed;
+                    }
+                },
+                None => buildtools::Task::Build,
+            };
+            let tool = match task {
+                buildtools::Task::Build => "run_build",
+                buildtools::Task::Test => "run_tests",
+                buildtools::Task::Lint => "run_lint",
+            };
+            ui.print_info(&format!("Running {}...", tool));
+            match buildtools::check(task, &serde_json::json!({}), &config.tools) {
+                Ok(report) if report.passed && report.problem_lines().is_empty() => {
+                    ui.print_success(&format!("{}\nNothing to fix", report.summary()));
+                }
+                Ok(report) => {
+                    ui.print_error(&report.summary());
+                    for line in report.problem_lines().iter().take(10) {
+                        println!("  {}", line);
+                    }
+                    return CommandResult::Prompt(report.fix_prompt(tool));
+                }
+                Err(e) => ui.print_error(&format!("/fix failed: {}", e)),
+            }
+            CommandResult::Processed
+        }
+
+        "/tail" => {
+            match args.as_slice() {
+                [] => match &state.tail {
+                    Some(tail) => ui.print_info(&tail.status()),
+                    None => ui.print_info("Usage: /tail <file> [--lines N] · /tail off"),
+                },
+                ["off"] | ["stop"] => match state.tail.take() {
+                    Some(tail) => ui.print_success(&format!("Stopped following {}", paths::display(tail.path()))),
+                    None => ui.print_info("No log file is followed"),
+                },
+                [file] => state.start_tail(file, logtail::DEFAULT_LINES, ui, input_reader),
+                [file, "--lines" | "-n", count] => match count.parse() {
+                    Ok(lines) => state.start_tail(file, lines, ui, input_reader),
+                    Err(_) => ui.print_error(&format!("Not a number of lines: {}", count)),
+                },
+                _ => ui.print_info("Usage: /tail <file> [--lines N] · /tail off"),
+            }
+            CommandResult::Processed
+        }
+
+        "/search" => {
+            if args.is_empty() {
+                ui.print_info("Usage: /search <text>");
+            } else if let Err(e) = session::print_search(&args.join(" ")) {
+                ui.print_error(&format!("/search failed: {}", e));
+            }
+            CommandResult::Processed
+        }
+
+        "/remember" => {
+            let project = args.first() == Some(&"--project");
+            let fact = args.iter().skip(project as usize).cloned().collect::<Vec<_>>().join(" ");
+            if fact.is_empty() {
+                ui.print_info("Usage: /remember [--project] <fact>");
+            } else {
+                match memory::remember(&fact, project) {
+                    Ok(entry) => {
+                        client.set_prompt_context(memory::prompt_section());
+                        let scope = if project { "this project" } else { "every session" };
+                        ui.print_success(&format!("Remembered for {}: {}", scope, entry.text));
+                    }
+                    Err(e) => ui.print_error(&format!("/remember failed: {}", e)),
+                }
+            }
+            CommandResult::Processed
+        }
+
+        "/memory" => {
+            match args.as_slice() {
+                ["rm" | "forget" | "delete", number] => match number.parse::<usize>() {
+                    Ok(number) => match memory::forget(number) {
+                        Ok(entry) => {
+                            client.set_prompt_context(memory::prompt_section());
+                            ui.print_success(&format!("Forgot: {}", entry.text));
+                        }
+                        Err(e) => ui.print_error(&e.to_string()),
+                    },
+                    Err(_) => ui.print_error("Usage: /memory rm <number>"),
+                },
+                [] => {
+                    let entries = memory::visible();
+                    if entries.is_empty() {
+                        ui.print_info("Nothing remembered yet · /remember [--project] <fact>");
+                    } else {
+                        println!("\n\x1b[36m    Memory ({})\x1b[0m\n", paths::display(&memory::get_memory_path()));
+                        for (i, entry) in entries.iter().enumerate() {
+                            let scope = match entry.scope {
+                                memory::Scope::User => "\x1b[90muser\x1b[0m   ",
+                                memory::Scope::Project(_) => "\x1b[90mproject\x1b[0m",
+                            };
+                            println!("    {:>2}. {} {}", i + 1, scope, entry.text);
+                        }
+                        println!("\n    \x1b[90m/memory rm <number> to delete\x1b[0m\n");
+                    }
+                }
+                _ => ui.print_info("Usage: /memory · /memory rm <number>"),
+            }
+            CommandResult::Processed
+        }
+
+        "/resume" => {
+            let mut id = args.first().copied().or(state.workspace.last_session.as_deref()).map(str::to_string);
+            if args.is_empty() && ui.menus_interactive() {
+                let sessions: Vec<Session> = session::list().into_iter().filter(|s| s.info.id != state.session.id).collect();
+                if !sessions.is_empty() {
+                    let items: Vec<MenuItem> = sessions
+                        .iter()
+                        .map(|s| {
+                            let detail = format!("{} · {} msgs · {}", session::format_time(s.info.updated_at), s.messages.len(), paths::display(std::path::Path::new(&s.info.cwd)));
+                            MenuItem::new(s.display_title(), detail, false)
+                        })
+                        .collect();
+                    let last = sessions.iter().position(|s| Some(&s.info.id) == id.as_ref()).unwrap_or(0);
+                    match ui.pick("Resume a session", &items, last) {
+                        Some(i) => id = Some(sessions[i].info.id.clone()),
+                        None => return CommandResult::Processed,
+                    }
+                }
+            }
+            match id {
+                None => ui.print_info("No earlier session in this workspace · /resume <id> (see /search or aicli sessions list)"),
+                Some(id) => match session::load(&id) {
+                    Ok(session) if session.info.id == state.session.id => ui.print_info("Already in this session"),
+                    Ok(session) => state.resume(session, ui),
+                    Err(e) => ui.print_error(&e.to_string()),
+                },
+            }
+            CommandResult::Processed
+        }
+
+        "/pin" => {
+            if args.is_empty() {
+                if state.workspace.pinned.is_empty() {
+                    ui.print_info("No pinned files · /pin <path> sends a file with every request in this project");
+                } else {
+                    println!("\n\x1b[36m    Pinned files\x1b[0m\n");
+                    for (i, path) in state.workspace.pinned.iter().enumerate() {
+                        println!("    {:>2}. {}", i + 1, paths::display(std::path::Path::new(path)));
+                    }
+                    println!("\n    \x1b[90m/unpin <number|path> to remove\x1b[0m\n");
+                }
+            } else {
+                for raw in &args {
+                    match state.workspace.pin(raw.trim_start_matches('@')) {
+                        Ok(path) => ui.print_success(&format!("Pinned {}", paths::display(std::path::Path::new(&path)))),
+                        Err(e) => ui.print_error(&e.to_string()),
+                    }
+                }
+                state.save_workspace(ui);
             }
-            ui.print_success(ui.strings.cleared());
             CommandResult::Processed
         }
 
-        "/help" | "/h" | "/?" => {
-            ui.print_help();
+        "/unpin" => {
+            if args.first() == Some(&"all") {
+                state.workspace.pinned.clear();
+                ui.print_success("Unpinned all files");
+            } else if args.is_empty() {
+                ui.print_info("Usage: /unpin <number|path|all>");
+            } else {
+                for raw in &args {
+                    match state.workspace.unpin(raw.trim_start_matches('@')) {
+                        Some(path) => ui.print_success(&format!("Unpinned {}", paths::display(std::path::Path::new(&path)))),
+                        None => ui.print_error(&format!("{} is not pinned", raw)),
+                    }
+                }
+            }
+            state.save_workspace(ui);
             CommandResult::Processed
         }
 
-        "/config" => {
-            if let Some(model) = config.get_active_model() {
-                let api_key_preview = if model.api_key.len() > 8 {
-                    &model.api_key[..8]
-                } else {
-                    &model.api_key
-                };
-                ui.print_config(
-                    &model.endpoint,
-                    &model.deployment,
-                    &model.model_type.to_string(),
-                    model.max_tokens,
-                    model.temperature,
-                    api_key_preview,
-                );
+        "/thinking" => {
+            if state.thinking.is_empty() {
+                match client.model_config().thinking_budget {
+                    Some(_) => ui.print_info("The last answer came without thinking"),
+                    None => ui.print_info("Extended thinking is off · set thinking_budget for a Claude model in config.toml"),
+                }
+            } else {
+                println!();
+                for thought in &state.thinking {
+                    println!("  \x1b[38;5;103m✻ Thinking\x1b[0m");
+                    for line in thought.trim().lines() {
+                        println!("    \x1b[2;3m{}\x1b[0m", line);
+                    }
+                    println!();
+                }
             }
             CommandResult::Processed
         }
 
-        "/model" => {
-            if args.is_empty() {
-                // Show model list
-                let models: Vec<(String, String, bool)> = config.models
-                    .iter()
-                    .map(|(name, model)| {
-                        (name.clone(), model.model_type.to_string(), name == &config.active_model)
-                    })
-                    .collect();
-
-                // Show menu
-                ui.select_model_interactive(&models);
+        "/context" => {
+            let mut selected = 0;
+            loop {
+                let memory = memory::prompt_section();
+                let instructions = project::instructions_section(&state.workspace_root);
+                let tools = client.offered_tools();
+                let request = context::Request {
+                    system: client.base_system_prompt(),
+                    tools: tools.len(),
+                    tools_chars: serde_json::to_string(&tools).map(|t| t.len()).unwrap_or(0),
+                    memory,
+                    instructions,
+                    pinned: &state.workspace.pinned,
+                    tail: state.tail.as_ref().map(LogTail::prompt_section),
+                    messages: &state.messages,
+                    quotes: &state.quotes,
+                    piped: state.piped.as_deref(),
+                };
+                let Some((part, index)) = context::browse(&request, ui.context_max, selected, ui) else {
+                    break;
+                };
+                selected = index;
+                match part {
+                    context::Part::Pinned(path) => {
+                        state.workspace.pinned.retain(|p| *p != path);
+                        state.save_workspace(ui);
+                        ui.print_success(&format!("Unpinned {}", paths::display(std::path::Path::new(&path))));
+                    }
+                    context::Part::Tail => {
+                        if let Some(tail) = state.tail.take() {
+                            ui.print_success(&format!("Stopped following {}", paths::display(tail.path())));
+                        }
+                    }
+                    context::Part::Turn(range) => {
+                        let count = range.len();
+                        state.drop_messages(range, ui);
+                        ui.print_success(&format!("Dropped {} message(s)", count));
+                    }
+                    context::Part::Quote(i) => {
+                        state.quotes.remove(i);
+                        ui.print_success("Dropped the quote");
+                    }
+                    context::Part::Piped => {
+                        state.piped = None;
+                        ui.print_success("Dropped the piped input");
+                    }
+                }
+            }
+            CommandResult::Processed
+        }
 
-                // Read selection
-                print!("  \x1b[38;5;117m❯\x1b[0m ");
-                std::io::Write::flush(&mut std::io::stdout()).unwrap();
+        "/init" => {
+            match init_project(ui, client, &state.workspace_root) {
+                Ok(path) => ui.print_success(&format!(
+                    "Wrote {} · review it; it is added to the system prompt from now on",
+                    paths::display(&path)
+                )),
+                Err(e) => ui.print_error(&format!("/init: {}", e)),
+            }
+            CommandResult::Processed
+        }
 
-                let mut selection = String::new();
-                if std::io::stdin().read_line(&mut selection).is_ok() {
-                    if let Some(selected_idx) = ui.parse_model_selection(&selection, models.len()) {
-                        if selected_idx == models.len() {
-                            // "Add model" option selected
-                            if let Err(e) = add_model_interactive(config) {
-                                ui.print_error(&format!("Failed: {}", e));
-                            } else {
-                                let model_names: Vec<String> = config.models.keys().cloned().collect();
-                                input_reader.update_models(model_names);
-                            }
-                        } else if selected_idx < models.len() {
-                            let (selected_name, _, is_active) = &models[selected_idx];
-                            if !is_active {
-                                // Switch to selected model
-                                config.set_active_model(selected_name);
-                                if let Some(model) = config.get_active_model() {
-                                    client.update_config(model.clone());
-                                    ui.set_context_max(client.get_max_context());
-                                    ui.set_model_info(&model.name, &model.model_type.to_string(), &ui.current_path.clone());
-                                    let _ = save_config(config);
-                                    ui.print_model_switch(&model.name, &model.model_type.to_string());
-                                    let model_names: Vec<String> = config.models.keys().cloned().collect();
-                                    input_reader.update_models(model_names);
-                                }
-                            } else {
-                                ui.print_info("Already using this model");
-                            }
-                        }
-                    } else {
-                        ui.print_info("Selection cancelled");
+        "/speak" => {
+            match args.first().map(|a| a.to_lowercase()).as_deref() {
+                Some("on") => match Speaker::start(&config.speech, config.language) {
+                    Ok(speaker) => {
+                        state.speaker = Some(speaker);
+                        ui.print_success("Answers will be read aloud · /speak off to stop");
                     }
+                    Err(e) => ui.print_error(&format!("/speak: {}", e)),
+                },
+                Some("off") => {
+                    // Sentences already queued finish playing
+                    state.speaker = None;
+                    ui.print_info("Speech off");
                 }
-            } else {
-                let model_name = args.join(" ");
+                _ => {
+                    let status = if state.speaker.is_some() { "on" } else { "off" };
+                    ui.print_info(&format!("Speech is {} · /speak on · /speak off", status));
+                }
+            }
+            CommandResult::Processed
+        }
 
-                if config.set_active_model(&model_name) {
-                    if let Some(model) = config.get_active_model() {
-                        client.update_config(model.clone());
-                        ui.set_context_max(client.get_max_context());
-                        ui.set_model_info(&model.name, &model.model_type.to_string(), &ui.current_path.clone());
-                        let _ = save_config(config);
-                        ui.print_model_switch(&model.name, &model.model_type.to_string());
+        "/mic" => {
+            ui.print_info("🎙 Listening · press Enter to stop");
+            match block_on(speech::listen(&config.speech, config.language)) {
+                Ok(heard) => {
+                    ui.print_info("Enter sends what was heard · edit it first, or Ctrl+C to cancel");
+                    match input_reader.readline_with_initial(&ui.get_prompt(), &heard) {
+                        Ok(prompt) if !prompt.trim().is_empty() => return CommandResult::Prompt(prompt.trim().to_string()),
+                        _ => ui.print_info("Discarded"),
+                    }
+                }
+                Err(e) => ui.print_error(&format!("/mic: {}", e)),
+            }
+            CommandResult::Processed
+        }
 
-                        let model_names: Vec<String> = config.models.keys().cloned().collect();
-                        input_reader.update_models(model_names);
+        "/imagine" => {
+            let prompt = args.join(" ");
+            if prompt.is_empty() {
+                ui.print_info("Usage: /imagine <description>");
+                return CommandResult::Processed;
+            }
+            let spinner = start_thinking_animation(ui, Instant::now(), None);
+            let generated = block_on(images::generate(&config.images, client.model_config(), &prompt));
+            stop_spinner(&spinner);
+            ui.clear_line();
+            match generated {
+                Ok(image) => {
+                    ui.print_success("Image saved");
+                    graphics::show(&image.path, config.ui.images);
+                    if let Some(revised) = image.revised_prompt {
+                        ui.print_info(&format!("Drawn from: {}", revised));
                     }
-                } else {
-                    let matches: Vec<&String> = config.models.keys()
-                        .filter(|k| k.to_lowercase().contains(&model_name.to_lowercase()))
-                        .collect();
+                }
+                Err(e) => ui.print_error(&format!("/imagine: {}", e)),
+            }
+            CommandResult::Processed
+        }
 
-                    if matches.len() == 1 {
-                        let matched_name = matches[0].clone();
-                        config.set_active_model(&matched_name);
-                        if let Some(model) = config.get_active_model() {
-                            client.update_config(model.clone());
-                            ui.set_context_max(client.get_max_context());
-                            ui.set_model_info(&model.name, &model.model_type.to_string(), &ui.current_path.clone());
-                            let _ = save_config(config);
-                            ui.print_model_switch(&model.name, &model.model_type.to_string());
-                        }
-                    } else if matches.is_empty() {
-                        ui.print_error(&format!("Model '{}' {}", model_name, ui.strings.not_found()));
-                    } else {
-                        ui.print_info(&format!("Multiple matches: {}",
-                            matches.iter().map(|s| s.as_str()).collect::<Vec<_>>().join(", ")));
+        "/continue" => {
+            let continued = block_on(async {
+                let stop = continue_last_answer(ui, client, state).await?;
+                match stop {
+                    StopReason::Length => offer_continue(ui, client, state).await,
+                    StopReason::ContentFilter => ui.print_error(ui.strings.get("stopped_filter")),
+                    StopReason::Complete => {}
+                }
+                anyhow::Ok(())
+            });
+            match continued {
+                Ok(()) => {
+                    if let Err(e) = session::save(&mut state.session, &state.messages) {
+                        ui.print_error(&format!("Failed to save session: {}", e));
                     }
                 }
+                Err(e) => ui.print_error(&format!("/continue: {}", safety::explain(e))),
             }
             CommandResult::Processed
         }
 
-        "/add-model" => {
-            if let Err(e) = add_model_interactive(config) {
-                ui.print_error(&format!("Failed: {}", e));
-            } else {
-                let model_names: Vec<String> = config.models.keys().cloned().collect();
-                input_reader.update_models(model_names);
+        "/output" => {
+            match args.first().copied() {
+                Some("off") => match state.output.take() {
+                    Some(output) => ui.print_info(&format!("Stopped writing to {}", paths::display(output.path()))),
+                    None => ui.print_info("Answers aren't being written to a file"),
+                },
+                Some(_) => match OutputFile::create(&args.join(" ")) {
+                    Ok(output) => {
+                        ui.print_success(&format!(
+                            "Answers will also be written to {} · /output off to stop",
+                            paths::display(output.path())
+                        ));
+                        state.output = Some(output);
+                    }
+                    Err(e) => ui.print_error(&format!("/output: {}", e)),
+                },
+                None => match &state.output {
+                    Some(output) => ui.print_info(&format!("Writing answers to {} · /output off to stop", paths::display(output.path()))),
+                    None => ui.print_info("Usage: /output <file> · /output off"),
+                },
+            }
+            CommandResult::Processed
+        }
+
+        "/history" if ui.menus_interactive() => {
+            if let Some(keep) = history::browse(&state.messages, ui) {
+                state.fork(keep, None, ui);
             }
             CommandResult::Processed
         }
 
         "/history" => {
-            println!("\n\x1b[36m    Conversation ({} messages)\x1b[0m\n", messages.len());
-            for (i, msg) in messages.iter().enumerate() {
+            println!("\n\x1b[36m    Conversation ({} messages)\x1b[0m\n", state.messages.len());
+            for (i, msg) in state.messages.iter().enumerate() {
                 let role_color = if msg.role == "user" { "\x1b[32m" } else { "\x1b[36m" };
                 let content = msg.content.as_text();
-                let preview = if content.len() > 80 {
-                    format!("{}...", &content[..77])
-                } else {
-                    content
-                };
+                let preview = text::truncate(&content.replace('\n', " "), 80);
                 println!("    {}{:>2}. [{}]\x1b[0m {}", role_color, i + 1, msg.role, preview);
             }
             println!();
@@ -487,22 +2483,71 @@ fn handle_command(
 
         "/lang" => {
             if args.is_empty() {
-                ui.print_language_menu(config.language);
+                let items: Vec<MenuItem> = Language::ALL
+                    .iter()
+                    .map(|lang| MenuItem::new(lang.to_string(), lang.code(), *lang == config.language))
+                    .collect();
+                let current = Language::ALL.iter().position(|lang| *lang == config.language).unwrap_or(0);
+                if let Some(lang) = ui.pick(ui.strings.title_language(), &items, current).map(|i| Language::ALL[i]) {
+                    config.language = lang;
+                    ui.set_language(lang);
+                    let _ = save_config(config);
+                    client.set_language(lang);
+                    ui.print_lang_switch(&lang.to_string());
+                }
             } else {
-                let lang_str = args[0].to_lowercase();
-                let new_lang = match lang_str.as_str() {
-                    "en" | "english" | "ing" | "inglês" | "ingles" => Some(Language::En),
-                    "pt" | "portuguese" | "português" | "portugues" | "br" => Some(Language::Pt),
-                    _ => None,
-                };
+                let new_lang = Language::parse(args[0]);
 
                 if let Some(lang) = new_lang {
                     config.language = lang;
                     ui.set_language(lang);
                     let _ = save_config(config);
+                    client.set_language(lang);
                     ui.print_lang_switch(&lang.to_string());
                 } else {
-                    ui.print_error(&format!("Unknown language: {} (use 'en' or 'pt')", args[0]));
+                    let codes: Vec<&str> = Language::ALL.iter().map(|l| l.code()).collect();
+                    ui.print_error(&ui.strings.format("unknown_language", &[("lang", args[0]), ("codes", &codes.join(", "))]));
+                }
+            }
+            CommandResult::Processed
+        }
+
+        "/router" => {
+            match args.first().map(|a| a.to_lowercase()).as_deref() {
+                Some("on") => {
+                    if !config.models.contains_key(&config.router.fast_model)
+                        || !config.models.contains_key(&config.router.smart_model)
+                    {
+                        ui.print_error("Set router.fast_model and router.smart_model in config.toml first");
+                    } else {
+                        config.router.enabled = true;
+                        let _ = save_config(config);
+                        ui.print_success(&format!(
+                            "Routing enabled: fast = {}, smart = {}",
+                            config.router.fast_model, config.router.smart_model
+                        ));
+                    }
+                }
+                Some("off") => {
+                    config.router.enabled = false;
+                    let _ = save_config(config);
+                    ui.route = None;
+                    // Go back to the model the user picked
+                    if let Some(model) = config.get_active_model() {
+                        client.update_config(model.clone());
+                        ui.set_context_max(client.get_max_context());
+                        ui.set_model_info(&model.name, &model.model_type.to_string(), &ui.current_path.clone());
+                    }
+                    ui.print_success("Routing disabled");
+                }
+                _ => {
+                    let state = if config.router.enabled { "on" } else { "off" };
+                    ui.print_info(&format!(
+                        "Router is {} (fast: {}, smart: {}) · /router on · /router off",
+                        state,
+                        if config.router.fast_model.is_empty() { "-" } else { &config.router.fast_model },
+                        if config.router.smart_model.is_empty() { "-" } else { &config.router.smart_model },
+                    ));
                 }
             }
             CommandResult::Processed
@@ -531,30 +2576,32 @@ fn handle_command(
     }
 }
 
+/// Where `install` puts the binary: ~/.local/bin/aicli, or
+/// ~/.aicli/bin/aicli.exe on Windows
+pub fn install_path() -> Result<std::path::PathBuf> {
+    let home = dirs::home_dir().ok_or_else(|| anyhow::anyhow!("Cannot find home directory"))?;
+    Ok(if cfg!(windows) {
+        home.join(".aicli").join("bin").join("aicli.exe")
+    } else {
+        home.join(".local").join("bin").join("aicli")
+    })
+}
+
 /// Install AICLI to user's PATH
-fn install_aicli(ui: &UI) -> Result<()> {
+pub fn install_aicli(ui: &UI) -> Result<()> {
     use std::fs;
-    use std::path::PathBuf;
 
     // Get current executable path
     let current_exe = std::env::current_exe()
         .map_err(|e| anyhow::anyhow!("Failed to get current executable: {}", e))?;
 
-    // Determine install directory based on OS
-    let (install_dir, exe_name) = if cfg!(windows) {
-        let home = dirs::home_dir().ok_or_else(|| anyhow::anyhow!("Cannot find home directory"))?;
-        (home.join(".aicli").join("bin"), "aicli.exe")
-    } else {
-        let home = dirs::home_dir().ok_or_else(|| anyhow::anyhow!("Cannot find home directory"))?;
-        (home.join(".local").join("bin"), "aicli")
-    };
+    let install_path = install_path()?;
+    let install_dir = install_path.parent().unwrap_or(std::path::Path::new("."));
 
     // Create install directory
-    fs::create_dir_all(&install_dir)
+    fs::create_dir_all(install_dir)
         .map_err(|e| anyhow::anyhow!("Failed to create directory: {}", e))?;
 
-    let install_path = install_dir.join(exe_name);
-
     ui.print_info(&format!("Installing to: {}", install_path.display()));
 
     // Copy executable
@@ -565,9 +2612,9 @@ fn install_aicli(ui: &UI) -> Result<()> {
 
     // Add to PATH
     if cfg!(windows) {
-        add_to_path_windows(&install_dir, ui)?;
+        add_to_path_windows(install_dir, ui)?;
     } else {
-        add_to_path_unix(&install_dir, ui)?;
+        add_to_path_unix(install_dir, ui)?;
     }
 
     println!();
@@ -673,15 +2720,7 @@ fn add_to_path_unix(_install_dir: &std::path::Path, _ui: &UI) -> Result<()> {
 fn uninstall_aicli(ui: &UI) -> Result<()> {
     use std::fs;
 
-    let (install_dir, exe_name) = if cfg!(windows) {
-        let home = dirs::home_dir().ok_or_else(|| anyhow::anyhow!("Cannot find home directory"))?;
-        (home.join(".aicli").join("bin"), "aicli.exe")
-    } else {
-        let home = dirs::home_dir().ok_or_else(|| anyhow::anyhow!("Cannot find home directory"))?;
-        (home.join(".local").join("bin"), "aicli")
-    };
-
-    let install_path = install_dir.join(exe_name);
+    let install_path = install_path()?;
 
     if install_path.exists() {
         fs::remove_file(&install_path)
@@ -703,43 +2742,397 @@ fn estimate_tokens(messages: &[Message]) -> usize {
         .sum()
 }
 
-/// Compact messages by summarizing older conversation
-async fn compact_messages(messages: &[Message], _client: &AzureClient, _ui: &UI) -> Vec<Message> {
-    if messages.len() <= 4 {
-        return messages.to_vec();
+/// `/retry [--model <name>] [--temperature <t>] [--replace]`: take the last
+/// turn out of the conversation and switch the client to the settings for
+/// sending it again
+fn start_retry(args: &[&str], config: &AppConfig, client: &mut AzureClient, state: &mut ChatState) -> Result<Retry> {
+    let usage = "Usage: /retry [--model <name>] [--temperature <0-2>] [--replace]";
+    let mut model = client.model_config().clone();
+    let mut changed = false;
+    let mut replace = false;
+    let mut rest = args.iter();
+    while let Some(&arg) = rest.next() {
+        match arg {
+            "--model" | "-m" => {
+                let name = rest.next().ok_or_else(|| anyhow::anyhow!(usage))?;
+                let chosen = config.models.get(*name).ok_or_else(|| anyhow::anyhow!("No model named '{}' (see /model)", name))?;
+                let temperature = model.temperature;
+                model = chosen.clone();
+                model.temperature = temperature;
+                changed = true;
+            }
+            "--temperature" | "-t" => {
+                let value = rest.next().and_then(|v| v.parse::<f32>().ok()).filter(|t| (0.0..=2.0).contains(t));
+                model.temperature = value.ok_or_else(|| anyhow::anyhow!("--temperature takes a number from 0 to 2"))?;
+                changed = true;
+            }
+            "--replace" => replace = true,
+            _ => anyhow::bail!(usage),
+        }
+    }
+
+    let start = state
+        .messages
+        .iter()
+        .rposition(|m| m.role == "user" && !history::is_tool_results(m))
+        .ok_or_else(|| anyhow::anyhow!("Nothing to retry yet"))?;
+    let mut turn = state.messages.split_off(start);
+    let prompt = turn.remove(0);
+    state.total_tokens = estimate_tokens(&state.messages);
+
+    let restore = changed.then(|| client.model_config().clone());
+    if changed {
+        client.update_config(model);
+    }
+    Ok(Retry { prompt, previous: turn, restore, replace })
+}
+
+/// After a /retry turn: go back to the model settings from before, and unless
+/// --replace was given, show both answers and keep the one the user picks
+fn finish_retry(retry: Box<Retry>, turn_start: usize, ui: &mut UI, client: &mut AzureClient, state: &mut ChatState) {
+    let used = client.model_config().clone();
+    if let Some(model) = retry.restore {
+        client.update_config(model.clone());
+        ui.set_context_max(client.get_max_context());
+        ui.set_model_info(&model.name, &model.model_type.to_string(), &ui.current_path.clone());
+    }
+    if state.messages.len() <= turn_start {
+        // The request failed and took the prompt back out
+        state.messages.push(retry.prompt);
+        state.messages.extend(retry.previous);
+        state.total_tokens = estimate_tokens(&state.messages);
+        ui.update_context(state.total_tokens);
+        ui.print_info("Kept the previous answer");
+        return;
+    }
+    let new = answer_text(state.messages.get(turn_start + 1..).unwrap_or_default());
+    let previous = answer_text(&retry.previous);
+    if retry.replace || previous.is_empty() {
+        return;
+    }
+    let label = format!("New · {} · temperature {}", used.name, used.temperature);
+    ui.print_side_by_side("Previous", &previous, &label, &new);
+    let items = [MenuItem::new("Keep the new answer", "", false), MenuItem::new("Keep the previous answer", "", false)];
+    if ui.pick("Which answer stays in the conversation?", &items, 0) == Some(1) {
+        state.messages.truncate(turn_start + 1);
+        state.messages.extend(retry.previous);
+        state.total_tokens = estimate_tokens(&state.messages);
+        ui.update_context(state.total_tokens);
+        ui.print_info("Kept the previous answer");
+    }
+}
+
+/// `/best [n] [--judge [model]] [--merge] <prompt>`: ask the current model
+/// for n answers at different temperatures and seeds, then let the user, or
+/// a judge model, choose the one that goes into the conversation. With
+/// --merge the judge writes one answer from all of them instead.
+fn best_of(args: &[&str], config: &AppConfig, client: &AzureClient, ui: &mut UI, state: &mut ChatState) -> Result<()> {
+    const USAGE: &str = "Usage: /best [2-8] [--judge [model]] [--merge] <prompt>";
+    let default_judge = config.models.get(&config.router.smart_model).unwrap_or(client.model_config());
+    let mut count = BEST_OF_DEFAULT;
+    let mut judge: Option<&ModelConfig> = None;
+    let mut merge = false;
+    let mut i = 0;
+    while let Some(&arg) = args.get(i) {
+        match arg {
+            "--judge" | "-j" => match args.get(i + 1).and_then(|name| config.models.get(*name)) {
+                Some(named) => {
+                    judge = Some(named);
+                    i += 1;
+                }
+                None => judge = Some(default_judge),
+            },
+            "--merge" => merge = true,
+            _ => match arg.parse::<usize>() {
+                Ok(n) if (2..=BEST_OF_MAX).contains(&n) => count = n,
+                Ok(_) => anyhow::bail!("/best takes 2 to {} candidates", BEST_OF_MAX),
+                Err(_) => break,
+            },
+        }
+        i += 1;
+    }
+    if i == args.len() {
+        anyhow::bail!(USAGE);
+    }
+    if merge {
+        judge = judge.or(Some(default_judge));
+    }
+
+    let message = build_user_message(&args[i..].join(" "));
+    let prompt = message.content.as_text();
+    let model = client.model_config();
+    let spinner = start_thinking_animation(ui, Instant::now(), None);
+    let results = block_on(bench::sample(model, count, &prompt));
+    stop_spinner(&spinner);
+    ui.clear_line();
+    for result in &results {
+        state.session_cost.record(&result.usage, model);
+    }
+    ui.print_comparison(&results);
+
+    let candidates: Vec<(usize, &str)> =
+        results.iter().enumerate().filter_map(|(i, r)| r.answer.as_deref().ok().map(|a| (i, a))).collect();
+    if candidates.is_empty() {
+        anyhow::bail!("Every candidate failed");
     }
 
-    // Keep the last 4 messages, summarize the rest
-    let to_summarize = &messages[..messages.len() - 4];
-    let to_keep = &messages[messages.len() - 4..];
+    let mut chosen = None;
+    if let Some(judge) = judge.filter(|_| candidates.len() > 1) {
+        let answers: Vec<&str> = candidates.iter().map(|(_, a)| *a).collect();
+        let spinner = start_thinking_animation(ui, Instant::now(), None);
+        let verdict = block_on(bench::judge(judge, &prompt, &answers, merge));
+        stop_spinner(&spinner);
+        ui.clear_line();
+        match verdict {
+            Ok((verdict, usage)) => {
+                state.session_cost.record(&usage, judge);
+                if merge {
+                    ui.print_answer_section(&format!("Merged by {}", judge.name), "", &verdict);
+                    chosen = Some(verdict);
+                } else {
+                    ui.print_answer_section(&format!("Verdict of {}", judge.name), "", &verdict);
+                    match bench::verdict_pick(&verdict, candidates.len()) {
+                        Some(pick) => chosen = Some(candidates[pick].1.to_string()),
+                        None => ui.print_info("The judge didn't name a candidate; pick one yourself"),
+                    }
+                }
+            }
+            Err(e) => ui.print_error(&format!("The judge failed, pick one yourself: {}", e)),
+        }
+    }
+    let answer = match chosen {
+        Some(answer) => answer,
+        None => {
+            let items: Vec<MenuItem> = candidates
+                .iter()
+                .map(|(i, answer)| {
+                    let preview = answer.split_whitespace().collect::<Vec<_>>().join(" ");
+                    MenuItem::new(results[*i].model.clone(), text::truncate(&preview, 60), false)
+                })
+                .collect();
+            let Some(pick) = ui.pick("Which answer goes into the conversation?", &items, 0) else {
+                ui.print_info("Nothing added to the conversation");
+                return Ok(());
+            };
+            candidates[pick].1.to_string()
+        }
+    };
+
+    state.messages.push(message);
+    state.messages.push(Message { role: "assistant".to_string(), content: MessageContent::Text(answer) });
+    state.total_tokens = estimate_tokens(&state.messages);
+    ui.update_context(state.total_tokens);
+    if let Err(e) = session::save(&mut state.session, &state.messages) {
+        ui.print_error(&format!("Failed to save session: {}", e));
+    }
+    ui.print_success("Added to the conversation · /apply-code to use its code");
+    Ok(())
+}
+
+/// `/quote [n] [block]`: message n as numbered by /history (default: the
+/// last answer), or code block `block` of it, verbatim and attributed. Returns
+/// the text for the prompt and what was quoted.
+fn quote(messages: &[Message], args: &[&str]) -> Result<(String, String)> {
+    let number = match args.first() {
+        Some(arg) => arg.parse::<usize>().map_err(|_| anyhow::anyhow!("Usage: /quote [message number] [code block number] | clear"))?,
+        None => messages
+            .iter()
+            .rposition(|m| m.role == "assistant" && !m.content.as_text().is_empty())
+            .map(|i| i + 1)
+            .ok_or_else(|| anyhow::anyhow!("No answer to quote yet"))?,
+    };
+    let message = number
+        .checked_sub(1)
+        .and_then(|i| messages.get(i))
+        .ok_or_else(|| anyhow::anyhow!("No message {} (the conversation has {}, see /history)", number, messages.len()))?;
+    let source = if message.role == "assistant" {
+        format!("your answer (message {})", number)
+    } else {
+        format!("my earlier message (message {})", number)
+    };
+    let content = message.content.as_text();
+
+    if let Some(arg) = args.get(1) {
+        let blocks = codeblocks::extract(&content);
+        let block = arg
+            .parse::<usize>()
+            .ok()
+            .and_then(|n| n.checked_sub(1))
+            .and_then(|i| blocks.get(i))
+            .ok_or_else(|| anyhow::anyhow!("Message {} has {} code block(s)", number, blocks.len()))?;
+        let quoted = format!("[Quoted: code block {} of {}]\n```{}\n{}\n```\n[End of quote]", arg, source, block.lang, block.code.trim_end());
+        return Ok((quoted, format!("code block {} of message {}", arg, number)));
+    }
+
+    // @file context inlined into a prompt was sent along with it, not written
+    let content = content.split("\n\nFile context:").next().unwrap_or_default();
+    let quoted = content.lines().map(|line| format!("> {}", line).trim_end().to_string()).collect::<Vec<_>>().join("\n");
+    let lines = content.lines().count();
+    Ok((format!("[Quoted: {}]\n{}\n[End of quote]", source, quoted), format!("message {} ({} line(s))", number, lines)))
+}
+
+/// Deployment, context window and prices of a model, for the `/model` menu
+fn model_summary(model: &ModelConfig) -> String {
+    let mut parts = vec![model.deployment.clone(), format!("{}k context", model.model_type.context_window() / 1000)];
+    match (model.input_price, model.output_price) {
+        (Some(input), Some(output)) => parts.push(format!("${:.2}/${:.2} per 1M tokens", input, output)),
+        (Some(input), None) => parts.push(format!("${:.2} per 1M input tokens", input)),
+        _ => {}
+    }
+    parts.join(" · ")
+}
+
+/// The first message's context for `cat file | aicli`, summarized by the
+/// model when it would take too much of the context
+async fn piped_context(text: &str, client: &AzureClient, ui: &UI) -> String {
+    let lines = text.lines().count();
+    if text.len() / 4 <= ui.context_max / PIPED_CONTEXT_SHARE {
+        ui.print_info(&format!("Piped input: {} line(s), sent with your first message", lines));
+        return format!("Piped input ({} lines):\n```\n{}\n```", lines, text.trim_end());
+    }
+    ui.print_info(&format!("Piped input: {} lines, too long to send whole; summarizing...", lines));
+    match client.complete(PIPED_SUMMARY_PROMPT, &head_and_tail(text, PIPED_SUMMARY_CHARS)).await {
+        Ok(summary) => {
+            ui.print_success("Summarized; the summary is sent with your first message");
+            format!("Summary of the piped input ({} lines, too long to include):\n{}", lines, summary.trim())
+        }
+        Err(e) => {
+            ui.print_error(&format!("Summary failed ({}); sending the start and end of the input instead", e));
+            let excerpt = head_and_tail(text, ui.context_max / PIPED_CONTEXT_SHARE * 4);
+            format!("Start and end of the piped input ({} lines):\n```\n{}\n```", lines, excerpt)
+        }
+    }
+}
+
+/// What to do with @files bigger than `[ui] file_warning_tokens`
+enum LargeFiles {
+    Attach,
+    Summarize,
+    Cancel,
+}
 
-    // Create a summary of older messages
-    let summary_text: String = to_summarize.iter()
+fn ask_large_files(files: usize, tokens: usize, ui: &UI) -> LargeFiles {
+    let percent = (tokens * 100).checked_div(ui.context_max).unwrap_or(0);
+    // Prompts read from a pipe go out as before; there is nobody to ask
+    if !std::io::stdin().is_terminal() {
+        ui.print_info(&format!("Attaching {} file(s) totaling ~{} tokens ({}% of the context)", files, format_tokens(tokens), percent));
+        return LargeFiles::Attach;
+    }
+    let prompt = format!(
+        "\x1b[38;5;220m?\x1b[0m Attach {} file(s) totaling ~{} tokens ({}% of the context)? \x1b[38;5;245m[y/N/summarize]\x1b[0m ",
+        files,
+        format_tokens(tokens),
+        percent
+    );
+    match ui.read_line(&prompt) {
+        Some(answer) if ui.strings.is_yes(&answer) => LargeFiles::Attach,
+        Some(answer) if !answer.is_empty() && "summarize".starts_with(&answer.to_lowercase()) => LargeFiles::Summarize,
+        _ => LargeFiles::Cancel,
+    }
+}
+
+/// The file context replaced by the model's summary of it; None when the
+/// summary failed
+async fn summarize_files(files: &[String], context: &str, client: &AzureClient, ui: &UI) -> Option<String> {
+    ui.print_info(&format!("Summarizing {}...", files.join(", ")));
+    match client.complete(FILES_SUMMARY_PROMPT, &head_and_tail(context, PIPED_SUMMARY_CHARS)).await {
+        Ok(summary) => {
+            let tokens = summary.len() / 4;
+            ui.print_success(&format!("Summarized to ~{} tokens; the summary is sent instead of the files", tokens));
+            Some(format!("\nSummary of {} (too large to send whole):\n{}\n", files.join(", "), summary.trim()))
+        }
+        Err(e) => {
+            ui.print_error(&format!("Summary failed: {}; the prompt is back on the input line", e));
+            None
+        }
+    }
+}
+
+/// The first and last `max_chars / 2` bytes of `text`
+fn head_and_tail(text: &str, max_chars: usize) -> String {
+    if text.len() <= max_chars {
+        return text.to_string();
+    }
+    let mut head = max_chars / 2;
+    while !text.is_char_boundary(head) {
+        head -= 1;
+    }
+    let mut tail = text.len() - max_chars / 2;
+    while !text.is_char_boundary(tail) {
+        tail += 1;
+    }
+    format!("{}\n[... {} characters left out ...]\n{}", &text[..head], tail - head, &text[tail..])
+}
+
+/// Where to cut so the last `keep` messages stay: at the start of a turn (a
+/// prompt the user wrote), which keeps tool results with their call. When
+/// they all belong to the first turn, the cut is at the next one.
+fn keep_from(messages: &[Message], keep: usize) -> usize {
+    let start = messages.len().saturating_sub(keep);
+    let is_prompt = |i: &usize| messages[*i].role == "user" && !history::is_tool_results(&messages[*i]);
+    (1..=start).rev().find(is_prompt).or_else(|| (start..messages.len()).find(is_prompt)).unwrap_or(start)
+}
+
+/// One message standing for `messages`. An earlier summary among them is
+/// kept whole, the other messages are cut short.
+fn summarize_messages(messages: &[Message]) -> Message {
+    if let [only] = messages {
+        if only.content.as_text().starts_with("[Conversation Summary") {
+            return only.clone();
+        }
+    }
+    let summary_text: String = messages.iter()
         .map(|m| {
-            let role = if m.role == "user" { "User" } else { "Assistant" };
             let content = m.content.as_text();
-            let truncated = if content.len() > 200 {
-                format!("{}...", &content[..200])
-            } else {
-                content
-            };
+            if content.starts_with("[Conversation Summary") {
+                return content;
+            }
+            let role = if m.role == "user" { "User" } else { "Assistant" };
+            let truncated = text::truncate(&content, 203);
             format!("[{}]: {}", role, truncated)
         })
         .collect::<Vec<_>>()
         .join("\n");
 
-    // Create compacted message history
-    let mut compacted = vec![Message {
+    Message {
         role: "user".to_string(),
         content: MessageContent::Text(format!(
             "[Conversation Summary - {} earlier messages]\n{}\n[End of Summary]",
-            to_summarize.len(),
+            messages.len(),
             summary_text
         )),
-    }];
+    }
+}
 
-    // Add the recent messages
-    compacted.extend(to_keep.iter().cloned());
+/// Make the conversation fit with `strategy`, keeping at least the turn of
+/// the last `keep` messages. The strategies that leave out only what they
+/// must go by `budget`, the tokens the messages may take.
+fn compact_messages(messages: &[Message], strategy: ContextStrategy, keep: usize, budget: usize) -> Vec<Message> {
+    if messages.len() <= keep {
+        return messages.to_vec();
+    }
+    let cut = keep_from(messages, keep);
+    // The oldest turn start from which the rest fits the budget
+    let fits_from = || {
+        (1..cut)
+            .filter(|&i| messages[i].role == "user" && !history::is_tool_results(&messages[i]))
+            .find(|&i| estimate_tokens(&messages[i..]) <= budget)
+            .unwrap_or(cut)
+    };
 
-    compacted
+    match strategy {
+        ContextStrategy::SummarizeOldest => {
+            let mut compacted = vec![summarize_messages(&messages[..cut])];
+            compacted.extend(messages[cut..].iter().cloned());
+            compacted
+        }
+        ContextStrategy::DropOldest => messages[fits_from()..].to_vec(),
+        ContextStrategy::KeepLast => messages[cut..].to_vec(),
+        ContextStrategy::Hybrid => {
+            let from = fits_from();
+            let mut compacted = vec![summarize_messages(&messages[..from])];
+            compacted.extend(messages[from..].iter().cloned());
+            compacted
+        }
+    }
 }
@@ -0,0 +1,66 @@
+//! Git checkpoints after agent turns (`[agent] checkpoints`), so the
+//! changes can be reviewed and undone with plain git. A commit holds only
+//! the files the agent wrote with write_file/edit_file; a stash entry records
+//! the whole working tree without touching it.
+
+use anyhow::Result;
+use std::path::Path;
+
+use crate::config::CheckpointMode;
+use crate::github::git;
+use crate::snapshot;
+
+/// Trailer naming the turn, as in `.aicli/backups/<turn>`
+const TURN_TRAILER: &str = "Aicli-Turn";
+
+/// Checkpoint the turn that just ended. Returns what was recorded, or None
+/// when there is nothing to do (mode off, dry run, no files written, no git).
+pub fn after_turn(mode: CheckpointMode) -> Result<Option<String>> {
+    if mode.is_off() || crate::tools::is_dry_run() {
+        return Ok(None);
+    }
+    let Some(turn) = snapshot::current_turn().filter(|t| !t.files.is_empty()) else {
+        return Ok(None);
+    };
+    let Ok(root) = git(&["rev-parse", "--show-toplevel"]) else {
+        return Ok(None);
+    };
+    let root = Path::new(&root);
+    let files: Vec<String> = turn
+        .files
+        .iter()
+        .filter(|f| f.path.starts_with(root) && f.path.exists())
+        .map(|f| f.path.display().to_string())
+        .collect();
+    if files.is_empty() {
+        return Ok(None);
+    }
+
+    let summary = if turn.prompt.is_empty() { "agent changes".to_string() } else { turn.prompt.clone() };
+    let title = format!("aicli: {}", crate::text::truncate(&summary, 60));
+    let trailer = format!("{}: {}", TURN_TRAILER, turn.id);
+
+    match mode {
+        CheckpointMode::Off => Ok(None),
+        CheckpointMode::Commit => {
+            let mut add = vec!["add", "--"];
+            add.extend(files.iter().map(|f| f.as_str()));
+            git(&add)?;
+            // Only these paths: whatever else is staged stays staged
+            let mut commit = vec!["commit", "--quiet", "-m", &title, "-m", &trailer, "--"];
+            commit.extend(files.iter().map(|f| f.as_str()));
+            git(&commit)?;
+            let hash = git(&["rev-parse", "--short", "HEAD"])?;
+            Ok(Some(format!("Committed {} ({} file(s)): {}", hash, files.len(), title)))
+        }
+        CheckpointMode::Stash => {
+            let stash = git(&["stash", "create", &title])?;
+            if stash.is_empty() {
+                // Only new, untracked files: `stash create` doesn't see them
+                return Ok(None);
+            }
+            git(&["stash", "store", "-m", &format!("{} ({})", title, trailer), &stash])?;
+            Ok(Some(format!("Stashed stash@{{0}} ({} file(s)): {}", files.len(), title)))
+        }
+    }
+}
@@ -0,0 +1,471 @@
+//! Command-line parsing for `aicli`. The subcommand table drives the help
+//! text and the shell completion scripts, so adding a subcommand here keeps
+//! all three in sync.
+
+/// What `aicli` was asked to do
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    Chat,
+    Run { prompt: String, no_cache: bool, output: Option<String>, pipe: Option<String> },
+    RunRecipe { file: String, vars: Vec<(String, String)>, output: Option<String>, pipe: Option<String>, yes: bool },
+    Config,
+    ConfigEncrypt { key_file: Option<String> },
+    ConfigDecrypt,
+    ModelsList,
+    ModelsAdd,
+    ModelsCatalog { model: Option<String> },
+    ModelsRemove { name: String },
+    SessionsList,
+    SessionsShow { id: String },
+    SessionsRemove { ids: Vec<String> },
+    SessionsResume { id: String },
+    Search { query: String },
+    Import { format: crate::import::Format, file: String },
+    TemplatesList,
+    New { template: String, name: String },
+    Bench { models: Vec<String>, prompt_file: String },
+    Eval { file: String, model: Option<String> },
+    Tail { file: String, lines: usize },
+    Doctor { offline: bool },
+    Install,
+    Upgrade { check_only: bool },
+    Completions { shell: Shell },
+    ShellHook { shell: Shell },
+    Fix { command: String, status: Option<i32>, shell: Option<String> },
+    Help,
+    Version,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+    Powershell,
+}
+
+impl Shell {
+    const NAMES: [&'static str; 4] = ["bash", "zsh", "fish", "powershell"];
+
+    fn parse(name: &str) -> Option<Shell> {
+        match name.to_lowercase().as_str() {
+            "bash" => Some(Shell::Bash),
+            "zsh" => Some(Shell::Zsh),
+            "fish" => Some(Shell::Fish),
+            "powershell" | "pwsh" => Some(Shell::Powershell),
+            _ => None,
+        }
+    }
+}
+
+struct Subcommand {
+    name: &'static str,
+    /// Usage after the name, for the help text
+    usage: &'static str,
+    about: &'static str,
+    /// Flags after the subcommand, offered by completion
+    flags: &'static [&'static str],
+    /// Words after the subcommand, offered by completion
+    values: &'static [&'static str],
+}
+
+const SUBCOMMANDS: &[Subcommand] = &[
+    Subcommand { name: "chat", usage: "", about: "Start the interactive chat (default)", flags: &[], values: &[] },
+    Subcommand { name: "run", usage: "-p <TEXT> [-o FILE] | --recipe F", about: "Run a single prompt, or a recipe file, and print the answer", flags: &["-p", "--prompt", "--no-cache", "-o", "--output", "--recipe", "--var", "--yes", "--pipe"], values: &[] },
+    Subcommand { name: "config", usage: "[encrypt [--key-file F]|decrypt]", about: "Configure API settings, or encrypt the secrets in config.toml", flags: &["--key-file"], values: &["encrypt", "decrypt"] },
+    Subcommand { name: "models", usage: "<list|add|remove NAME|catalog>", about: "List, add or remove models; catalog picks from the resource", flags: &["--model"], values: &["list", "add", "remove", "catalog"] },
+    Subcommand { name: "sessions", usage: "<list|show|rm|resume> [ID]", about: "Manage saved conversations (ID: a prefix or 'last')", flags: &[], values: &["list", "show", "rm", "resume"] },
+    Subcommand { name: "search", usage: "<QUERY>", about: "Search saved conversations", flags: &[], values: &[] },
+    Subcommand { name: "import", usage: "--format <FORMAT> <FILE>", about: "Import conversations exported from ChatGPT or Claude Code", flags: &["--format"], values: &crate::import::Format::NAMES },
+    Subcommand { name: "new", usage: "[TEMPLATE NAME]", about: "Scaffold a project from a template (no args: list them)", flags: &[], values: &["rust-cli", "fastapi-service", "react-app"] },
+    Subcommand { name: "bench", usage: "[--models A,B] --prompt-file F", about: "Compare models' latency, tokens/sec and cost on the same prompts", flags: &["--models", "--prompt-file"], values: &[] },
+    Subcommand { name: "eval", usage: "<FILE> [--model NAME]", about: "Run prompt regression tests; exits 1 when a case fails", flags: &["--model"], values: &[] },
+    Subcommand { name: "tail", usage: "<FILE> [--lines N]", about: "Chat about a log file while following it", flags: &["--lines"], values: &[] },
+    Subcommand { name: "doctor", usage: "[--offline]", about: "Check config, endpoints, keys, PATH and terminal", flags: &["--offline"], values: &[] },
+    Subcommand { name: "install", usage: "", about: "Install aicli to the user's bin directory and PATH", flags: &[], values: &[] },
+    Subcommand { name: "upgrade", usage: "[--check]", about: "Update aicli to the latest GitHub release", flags: &["--check"], values: &[] },
+    Subcommand { name: "completions", usage: "<SHELL>", about: "Print a completion script for bash, zsh, fish or powershell", flags: &[], values: &Shell::NAMES },
+    Subcommand { name: "shell-hook", usage: "<SHELL>", about: "Print the shell integration: Ctrl+G explains or fixes the last command", flags: &[], values: &Shell::NAMES },
+    Subcommand { name: "fix", usage: "[--status N] -- <COMMAND>", about: "Explain a command and print a corrected one (used by shell-hook)", flags: &["--status", "--shell"], values: &[] },
+];
+
+const GLOBAL_FLAGS: &[&str] = &["-h", "--help", "-v", "--version", "-c", "--config", "-p", "--prompt", "--no-cache", "-o", "--output", "--no-dotenv", "--dry-run", "--ascii"];
+
+/// Parse the arguments after the program name. The pre-subcommand flags
+/// (`-c`, `-p "..."`) keep working.
+pub fn parse(args: &[String]) -> Result<Command, String> {
+    let Some(first) = args.first() else {
+        return Ok(Command::Chat);
+    };
+    let rest = &args[1..];
+
+    let command = match first.as_str() {
+        "-h" | "--help" | "help" => Command::Help,
+        "-v" | "--version" | "version" => Command::Version,
+        "-c" | "--config" => Command::Config,
+        "config" => return parse_config(rest),
+        "chat" => Command::Chat,
+        "-p" | "--prompt" => return parse_run(args),
+        "run" => return parse_run(rest),
+        "doctor" => {
+            let offline = take_flag(rest, "--offline")?;
+            return Ok(Command::Doctor { offline });
+        }
+        "install" => Command::Install,
+        "upgrade" => {
+            let check_only = take_flag(rest, "--check")?;
+            return Ok(Command::Upgrade { check_only });
+        }
+        "models" => return parse_models(rest),
+        "sessions" => return parse_sessions(rest),
+        "search" => {
+            if rest.is_empty() {
+                return Err("Usage: aicli search <QUERY>".to_string());
+            }
+            return Ok(Command::Search { query: rest.join(" ") });
+        }
+        "import" => return parse_import(rest),
+        "bench" => return parse_bench(rest),
+        "eval" => return parse_eval(rest),
+        "tail" => return parse_tail(rest),
+        "new" => {
+            return match rest {
+                [] => Ok(Command::TemplatesList),
+                [template, name] => Ok(Command::New { template: template.clone(), name: name.clone() }),
+                _ => Err("Usage: aicli new <TEMPLATE> <NAME>".to_string()),
+            };
+        }
+        "completions" => {
+            let name = rest.first().ok_or("Usage: aicli completions <bash|zsh|fish|powershell>")?;
+            let shell = Shell::parse(name).ok_or_else(|| format!("Unsupported shell '{}'", name))?;
+            return expect_end(&rest[1..], Command::Completions { shell });
+        }
+        "shell-hook" => {
+            let name = rest.first().ok_or("Usage: aicli shell-hook <bash|zsh|fish|powershell>")?;
+            let shell = Shell::parse(name).ok_or_else(|| format!("Unsupported shell '{}'", name))?;
+            return expect_end(&rest[1..], Command::ShellHook { shell });
+        }
+        "fix" => return parse_fix(rest),
+        other if other.starts_with('-') => return Err(format!("Unknown option '{}'", other)),
+        other => return Err(format!("Unknown command '{}'", other)),
+    };
+    expect_end(rest, command)
+}
+
+fn parse_config(args: &[String]) -> Result<Command, String> {
+    let Some(action) = args.first() else {
+        return Ok(Command::Config);
+    };
+    let rest = &args[1..];
+    match action.as_str() {
+        "encrypt" => match rest {
+            [] => Ok(Command::ConfigEncrypt { key_file: None }),
+            [flag, path] if flag == "--key-file" => Ok(Command::ConfigEncrypt { key_file: Some(path.clone()) }),
+            _ => Err("Usage: aicli config encrypt [--key-file PATH]".to_string()),
+        },
+        "decrypt" => expect_end(rest, Command::ConfigDecrypt),
+        other => Err(format!("Unknown config command '{}'", other)),
+    }
+}
+
+fn parse_models(args: &[String]) -> Result<Command, String> {
+    let Some(action) = args.first() else {
+        return Ok(Command::ModelsList);
+    };
+    let rest = &args[1..];
+    match action.as_str() {
+        "list" | "ls" => expect_end(rest, Command::ModelsList),
+        "add" => expect_end(rest, Command::ModelsAdd),
+        "catalog" => match rest {
+            [] => Ok(Command::ModelsCatalog { model: None }),
+            [flag, name] if flag == "--model" || flag == "-m" => Ok(Command::ModelsCatalog { model: Some(name.clone()) }),
+            _ => Err("Usage: aicli models catalog [--model NAME]".to_string()),
+        },
+        "remove" | "rm" => {
+            let name = rest.first().ok_or("Usage: aicli models remove <NAME>")?;
+            expect_end(&rest[1..], Command::ModelsRemove { name: name.clone() })
+        }
+        other => Err(format!("Unknown models command '{}'", other)),
+    }
+}
+
+fn parse_sessions(args: &[String]) -> Result<Command, String> {
+    let Some(action) = args.first() else {
+        return Ok(Command::SessionsList);
+    };
+    let rest = &args[1..];
+    let id = || rest.first().cloned().ok_or_else(|| format!("Usage: aicli sessions {} <ID>", action));
+    match action.as_str() {
+        "list" | "ls" => expect_end(rest, Command::SessionsList),
+        "show" => {
+            let id = id()?;
+            expect_end(&rest[1..], Command::SessionsShow { id })
+        }
+        "resume" => {
+            let id = id()?;
+            expect_end(&rest[1..], Command::SessionsResume { id })
+        }
+        "rm" | "remove" | "delete" => {
+            id()?;
+            Ok(Command::SessionsRemove { ids: rest.to_vec() })
+        }
+        other => Err(format!("Unknown sessions command '{}'", other)),
+    }
+}
+
+/// `--format <FORMAT> <FILE>` in any order
+fn parse_import(args: &[String]) -> Result<Command, String> {
+    const USAGE: &str = "Usage: aicli import --format <chatgpt|claude-code> <FILE>";
+    let mut format = None;
+    let mut file = None;
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "-f" | "--format" => {
+                let name = iter.next().ok_or(USAGE)?;
+                let parsed = crate::import::Format::parse(name)
+                    .ok_or_else(|| format!("Unknown format '{}' (chatgpt or claude-code)", name))?;
+                format = Some(parsed);
+            }
+            other if other.starts_with('-') => return Err(format!("Unknown option '{}'", other)),
+            other if file.is_none() => file = Some(other.to_string()),
+            other => return Err(format!("Unexpected argument '{}'", other)),
+        }
+    }
+    match (format, file) {
+        (Some(format), Some(file)) => Ok(Command::Import { format, file }),
+        _ => Err(USAGE.to_string()),
+    }
+}
+
+/// `[--models A,B] --prompt-file <FILE>` in any order
+fn parse_bench(args: &[String]) -> Result<Command, String> {
+    const USAGE: &str = "Usage: aicli bench [--models A,B] --prompt-file <FILE>";
+    let mut models = Vec::new();
+    let mut prompt_file = None;
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "-m" | "--models" => {
+                let list = iter.next().ok_or(USAGE)?;
+                models.extend(list.split(',').map(str::trim).filter(|m| !m.is_empty()).map(String::from));
+            }
+            "-f" | "--prompt-file" => prompt_file = Some(iter.next().ok_or(USAGE)?.clone()),
+            other if other.starts_with('-') => return Err(format!("Unknown option '{}'", other)),
+            other => return Err(format!("Unexpected argument '{}'", other)),
+        }
+    }
+    let prompt_file = prompt_file.ok_or(USAGE)?;
+    Ok(Command::Bench { models, prompt_file })
+}
+
+/// `<FILE> [--model NAME]` in any order
+fn parse_eval(args: &[String]) -> Result<Command, String> {
+    const USAGE: &str = "Usage: aicli eval <FILE> [--model NAME]";
+    let mut file = None;
+    let mut model = None;
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "-m" | "--model" => model = Some(iter.next().ok_or(USAGE)?.clone()),
+            other if other.starts_with('-') => return Err(format!("Unknown option '{}'", other)),
+            other if file.is_none() => file = Some(other.to_string()),
+            other => return Err(format!("Unexpected argument '{}'", other)),
+        }
+    }
+    let file = file.ok_or(USAGE)?;
+    Ok(Command::Eval { file, model })
+}
+
+/// `<FILE> [--lines N]` in any order
+fn parse_tail(args: &[String]) -> Result<Command, String> {
+    const USAGE: &str = "Usage: aicli tail <FILE> [--lines N]";
+    let mut file = None;
+    let mut lines = crate::logtail::DEFAULT_LINES;
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "-n" | "--lines" => {
+                let count = iter.next().ok_or(USAGE)?;
+                lines = count.parse().map_err(|_| format!("Not a number of lines: {}", count))?;
+            }
+            other if other.starts_with('-') => return Err(format!("Unknown option '{}'", other)),
+            other if file.is_none() => file = Some(other.to_string()),
+            other => return Err(format!("Unexpected argument '{}'", other)),
+        }
+    }
+    let file = file.ok_or(USAGE)?;
+    Ok(Command::Tail { file, lines })
+}
+
+/// `-p <TEXT> [--no-cache] [-o FILE]` in any order
+fn parse_run(args: &[String]) -> Result<Command, String> {
+    let mut prompt = None;
+    let mut no_cache = false;
+    let mut output = None;
+    let mut recipe = None;
+    let mut vars = Vec::new();
+    let mut yes = false;
+    let mut pipe = None;
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--recipe" => {
+                let path = iter.next().filter(|p| !p.starts_with('-'));
+                recipe = Some(path.ok_or("--recipe needs a file path")?.clone());
+            }
+            "--yes" | "-y" => yes = true,
+            "--pipe" => pipe = Some(iter.next().ok_or("--pipe needs a command")?.clone()),
+            "--var" => {
+                let var = iter.next().and_then(|v| v.split_once('=')).ok_or("--var needs KEY=VALUE")?;
+                vars.push((var.0.to_string(), var.1.to_string()));
+            }
+            "-p" | "--prompt" => {
+                let text = iter.next().filter(|p| !p.starts_with("--"));
+                prompt = Some(text.ok_or("-p needs a prompt")?.clone());
+            }
+            "--no-cache" => no_cache = true,
+            "-o" | "--output" => {
+                let path = iter.next().filter(|p| !p.starts_with('-'));
+                output = Some(path.ok_or("--output needs a file path")?.clone());
+            }
+            other if other.starts_with('-') => return Err(format!("Unknown option '{}'", other)),
+            // `aicli run "text"` without -p
+            other if prompt.is_none() => prompt = Some(other.to_string()),
+            other => return Err(format!("Unexpected argument '{}'", other)),
+        }
+    }
+    if let Some(file) = recipe {
+        if prompt.is_some() {
+            return Err("--recipe has its own prompt; leave out -p".to_string());
+        }
+        return Ok(Command::RunRecipe { file, vars, output, pipe, yes });
+    }
+    if !vars.is_empty() || yes {
+        return Err("--var and --yes only apply to --recipe".to_string());
+    }
+    let prompt = prompt.ok_or("Usage: aicli run -p \"<prompt>\" [--no-cache] [-o FILE] | --recipe FILE [--var KEY=VALUE]")?;
+    Ok(Command::Run { prompt, no_cache, output, pipe })
+}
+
+fn parse_fix(args: &[String]) -> Result<Command, String> {
+    let mut status = None;
+    let mut shell = None;
+    let mut iter = args.iter();
+    let mut words = Vec::new();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--status" => {
+                let value = iter.next().ok_or("--status needs a number")?;
+                status = Some(value.parse().map_err(|_| format!("Invalid --status '{}'", value))?);
+            }
+            "--shell" => shell = Some(iter.next().ok_or("--shell needs a name")?.clone()),
+            // Everything after -- is the command, dashes and all
+            "--" => {
+                words.extend(iter.by_ref().cloned());
+                break;
+            }
+            _ => words.push(arg.clone()),
+        }
+    }
+    if words.is_empty() {
+        return Err("Usage: aicli fix [--status N] [--shell NAME] -- <COMMAND>".to_string());
+    }
+    Ok(Command::Fix { command: words.join(" "), status, shell })
+}
+
+fn take_flag(args: &[String], flag: &str) -> Result<bool, String> {
+    match args.iter().find(|a| *a != flag) {
+        Some(other) => Err(format!("Unexpected argument '{}'", other)),
+        None => Ok(!args.is_empty()),
+    }
+}
+
+fn expect_end(rest: &[String], command: Command) -> Result<Command, String> {
+    match rest.first() {
+        Some(extra) => Err(format!("Unexpected argument '{}'", extra)),
+        None => Ok(command),
+    }
+}
+
+/// The "Usage" and "Subcommands" sections of `aicli --help`
+pub fn usage() -> String {
+    let mut text = String::from("Usage: aicli [OPTIONS]\n       aicli <COMMAND> [ARGS]\n\nSubcommands:\n");
+    let heads: Vec<String> = SUBCOMMANDS.iter().map(|sub| format!("{} {}", sub.name, sub.usage).trim_end().to_string()).collect();
+    let width = heads.iter().map(|head| head.len()).max().unwrap_or(0) + 2;
+    for (sub, head) in SUBCOMMANDS.iter().zip(&heads) {
+        text.push_str(&format!("  {:<width$}{}\n", head, sub.about, width = width));
+    }
+    text
+}
+
+/// Completion script for `shell`, completing subcommands, their arguments
+/// and flags
+pub fn completion_script(shell: Shell) -> String {
+    let names: Vec<&str> = SUBCOMMANDS.iter().map(|s| s.name).collect();
+    let words = |sub: &Subcommand| -> String {
+        sub.values.iter().chain(sub.flags).copied().collect::<Vec<_>>().join(" ")
+    };
+
+    match shell {
+        Shell::Bash => {
+            let mut cases = String::new();
+            for sub in SUBCOMMANDS.iter().filter(|s| !words(s).is_empty()) {
+                cases.push_str(&format!("        {}) COMPREPLY=($(compgen -W \"{}\" -- \"$cur\")) ;;\n", sub.name, words(sub)));
+            }
+            format!(
+                "_aicli() {{\n    local cur=\"${{COMP_WORDS[COMP_CWORD]}}\"\n    if [ \"$COMP_CWORD\" -eq 1 ]; then\n        COMPREPLY=($(compgen -W \"{} {}\" -- \"$cur\"))\n        return\n    fi\n    case \"${{COMP_WORDS[1]}}\" in\n{}    esac\n}}\ncomplete -F _aicli aicli\n",
+                names.join(" "),
+                GLOBAL_FLAGS.join(" "),
+                cases
+            )
+        }
+        Shell::Zsh => {
+            let mut cases = String::new();
+            for sub in SUBCOMMANDS.iter().filter(|s| !words(s).is_empty()) {
+                cases.push_str(&format!("        {}) compadd -- {} ;;\n", sub.name, words(sub)));
+            }
+            let described: Vec<String> = SUBCOMMANDS
+                .iter()
+                .map(|s| format!("'{}:{}'", s.name, s.about.replace('\'', "")))
+                .collect();
+            format!(
+                "#compdef aicli\n\n_aicli() {{\n    if (( CURRENT == 2 )); then\n        local -a commands\n        commands=({})\n        _describe 'command' commands\n        compadd -- {}\n        return\n    fi\n    case $words[2] in\n{}    esac\n}}\n\ncompdef _aicli aicli\n",
+                described.join(" "),
+                GLOBAL_FLAGS.join(" "),
+                cases
+            )
+        }
+        Shell::Fish => {
+            let mut script = String::from("complete -c aicli -f\n");
+            for sub in SUBCOMMANDS {
+                script.push_str(&format!(
+                    "complete -c aicli -n '__fish_use_subcommand' -a {} -d '{}'\n",
+                    sub.name,
+                    sub.about.replace('\'', "")
+                ));
+                if !words(sub).is_empty() {
+                    script.push_str(&format!(
+                        "complete -c aicli -n '__fish_seen_subcommand_from {}' -a '{}'\n",
+                        sub.name,
+                        words(sub)
+                    ));
+                }
+            }
+            script
+        }
+        Shell::Powershell => {
+            let mut cases = String::new();
+            for sub in SUBCOMMANDS.iter().filter(|s| !words(s).is_empty()) {
+                let quoted: Vec<String> = words(sub).split(' ').map(|w| format!("'{}'", w)).collect();
+                cases.push_str(&format!("        '{}' {{ @({}) }}\n", sub.name, quoted.join(", ")));
+            }
+            let top: Vec<String> = names.iter().chain(GLOBAL_FLAGS).map(|w| format!("'{}'", w)).collect();
+            format!(
+                "Register-ArgumentCompleter -Native -CommandName aicli -ScriptBlock {{\n    param($wordToComplete, $commandAst, $cursorPosition)\n    $words = $commandAst.CommandElements | ForEach-Object {{ $_.ToString() }}\n    $candidates = if ($words.Count -le 1 -or ($words.Count -eq 2 -and $wordToComplete)) {{\n        @({})\n    }} else {{\n        switch ($words[1]) {{\n{}        default {{ @() }}\n        }}\n    }}\n    $candidates | Where-Object {{ $_ -like \"$wordToComplete*\" }} | ForEach-Object {{\n        [System.Management.Automation.CompletionResult]::new($_, $_, 'ParameterValue', $_)\n    }}\n}}\n",
+                top.join(", "),
+                cases
+            )
+        }
+    }
+}
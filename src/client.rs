@@ -1,18 +1,66 @@
 use crate::config::{ModelConfig, ModelType};
-use crate::tools::{ToolCall, ToolResult};
+use crate::i18n::Language;
+use crate::ratelimit;
+use crate::tools::ToolCall;
 use anyhow::{anyhow, Result};
 use futures_util::StreamExt;
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 #[derive(Debug, Clone, Default)]
 pub struct TokenUsage {
     pub prompt_tokens: usize,
     pub completion_tokens: usize,
     pub total_tokens: usize,
+    /// Prompt tokens served from the Claude prompt cache (included in prompt_tokens)
+    pub cache_read_tokens: usize,
+    /// Prompt tokens written to the Claude prompt cache (included in prompt_tokens)
+    pub cache_write_tokens: usize,
+    /// Claude's extended thinking, estimated from its text (included in completion_tokens)
+    pub thinking_tokens: usize,
 }
 
+impl TokenUsage {
+    pub fn add(&mut self, other: &TokenUsage) {
+        self.prompt_tokens += other.prompt_tokens;
+        self.completion_tokens += other.completion_tokens;
+        self.total_tokens += other.total_tokens;
+        self.cache_read_tokens += other.cache_read_tokens;
+        self.cache_write_tokens += other.cache_write_tokens;
+        self.thinking_tokens += other.thinking_tokens;
+    }
+
+    /// Estimated cost in USD, if the model has prices configured.
+    /// Cache reads bill at 10% and cache writes at 125% of the input price.
+    pub fn estimate_cost(&self, model: &ModelConfig) -> Option<f64> {
+        let input_price = model.input_price?;
+        let output_price = model.output_price?;
+
+        let uncached = self.prompt_tokens
+            .saturating_sub(self.cache_read_tokens + self.cache_write_tokens) as f64;
+        let input = uncached * input_price
+            + self.cache_write_tokens as f64 * input_price * 1.25
+            + self.cache_read_tokens as f64 * input_price * 0.1;
+        let output = self.completion_tokens as f64 * output_price;
+
+        Some((input + output) / 1_000_000.0)
+    }
+}
+
+const MAX_RATE_LIMIT_RETRIES: usize = 3;
+
+/// Sent after an answer that hit max_tokens, for models without prefill
+const CONTINUE_PROMPT: &str = "Your previous answer was cut off. Continue it exactly where it stopped, \
+without repeating or summarizing anything, starting mid-sentence or mid-code-block if that is where it ended.";
+
+// Anthropic won't cache blocks under ~1024 tokens, so don't waste breakpoints on them
+const CACHE_MIN_CHARS: usize = 4096;
+// Anthropic allows at most 4 breakpoints; one goes to the system prompt
+const MAX_MESSAGE_BREAKPOINTS: usize = 3;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Message {
     pub role: String,
@@ -31,6 +79,20 @@ impl MessageContent {
         match self {
             MessageContent::Text(s) => s.clone(),
             MessageContent::Parts(parts) => {
+                // Tool results read as the text message models without
+                // tool_result blocks are sent
+                let results: Vec<String> = parts
+                    .iter()
+                    .filter_map(|p| match p {
+                        ContentPart::ToolResult { name, content, is_error, .. } => {
+                            Some(format!("[Tool: {} | Success: {}]\n{}", name, !is_error, content))
+                        }
+                        _ => None,
+                    })
+                    .collect();
+                if !results.is_empty() {
+                    return format!("Tool execution results:\n\n{}\n\nContinue with the task.", results.join("\n\n---\n\n"));
+                }
                 parts.iter()
                     .filter_map(|p| {
                         if let ContentPart::Text { text } = p {
@@ -44,6 +106,37 @@ impl MessageContent {
             }
         }
     }
+
+    /// Names of the tools an answer called
+    pub fn tool_names(&self) -> Vec<&str> {
+        match self {
+            MessageContent::Text(_) => Vec::new(),
+            MessageContent::Parts(parts) => parts
+                .iter()
+                .filter_map(|p| match p {
+                    ContentPart::ToolUse { name, .. } => Some(name.as_str()),
+                    _ => None,
+                })
+                .collect(),
+        }
+    }
+
+    /// Ids of the tool_use or tool_result blocks
+    fn tool_ids(&self, results: bool) -> Vec<&str> {
+        let MessageContent::Parts(parts) = self else {
+            return Vec::new();
+        };
+        let mut ids: Vec<&str> = parts
+            .iter()
+            .filter_map(|p| match p {
+                ContentPart::ToolUse { id, .. } if !results => Some(id.as_str()),
+                ContentPart::ToolResult { tool_use_id, .. } if results => Some(tool_use_id.as_str()),
+                _ => None,
+            })
+            .collect();
+        ids.sort();
+        ids
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -60,33 +153,161 @@ pub enum ContentPart {
     #[serde(rename = "tool_result")]
     ToolResult {
         tool_use_id: String,
+        /// The tool called, for showing the result; not sent
+        #[serde(default)]
+        name: String,
         content: String,
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        is_error: bool,
     },
+    /// Claude's extended thinking before a tool call, sent back with it
+    #[serde(rename = "thinking")]
+    Thinking { thinking: String, signature: String },
+    #[serde(rename = "redacted_thinking")]
+    RedactedThinking { data: String },
+}
+
+/// Why the model stopped writing
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum StopReason {
+    /// Finished the answer or handed over to tools
+    #[default]
+    Complete,
+    /// Ran into max_tokens mid-answer
+    Length,
+    /// Azure's content filter cut the answer off
+    ContentFilter,
+}
+
+impl StopReason {
+    /// From OpenAI's finish_reason or Anthropic's stop_reason
+    fn parse(reason: &str) -> Self {
+        match reason {
+            "length" | "max_tokens" => StopReason::Length,
+            "content_filter" | "refusal" => StopReason::ContentFilter,
+            _ => StopReason::Complete,
+        }
+    }
+}
+
+/// Non-success HTTP response from the model API
+#[derive(Debug, thiserror::Error)]
+#[error("HTTP {status}: {body}")]
+pub struct ApiError {
+    pub status: u16,
+    pub body: String,
+}
+
+/// How long one streamed request took (`[ui] verbose_stats`)
+#[derive(Debug, Clone, Copy)]
+pub struct RequestTiming {
+    /// Until the first streamed token, None when nothing streamed
+    pub first_token: Option<Duration>,
+    pub total: Duration,
+    pub output_tokens: usize,
+}
+
+/// A request to a model API as middlewares see it, before it is sent
+pub struct ApiRequest {
+    /// Name of the configured model
+    pub model: String,
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Value,
+}
+
+impl ApiRequest {
+    /// Set a header, replacing any of the same name
+    pub fn set_header(&mut self, name: &str, value: &str) {
+        self.headers.retain(|(n, _)| !n.eq_ignore_ascii_case(name));
+        self.headers.push((name.to_string(), value.to_string()));
+    }
+}
+
+/// The response to an `ApiRequest`, before its body streams to the chat.
+/// A retried request is seen once per try.
+pub struct ApiResponse<'a> {
+    pub model: &'a str,
+    pub url: &'a str,
+    pub status: u16,
+    pub headers: &'a reqwest::header::HeaderMap,
+    pub elapsed: Duration,
+}
+
+/// A step every model request passes through, in the order of
+/// `[[middlewares]]` in config.toml (built-ins in middleware.rs)
+pub trait Middleware: Send + Sync {
+    /// Change the request; an error stops it from being sent
+    fn on_request(&self, request: &mut ApiRequest) -> Result<()>;
+
+    fn on_response(&self, _response: &ApiResponse) {}
 }
 
 pub struct AzureClient {
     client: Client,
     config: ModelConfig,
+    /// Language the model is asked to answer in
+    language: Language,
+    /// Remembered facts and pinned files appended to the system prompt
+    prompt_context: String,
+    /// Requests since the last `take_timings`
+    timings: Mutex<Vec<RequestTiming>>,
+    /// Thinking blocks of the last answer, until `take_thinking`
+    thinking: Mutex<Vec<ContentPart>>,
+    /// Tools offered to the model; all of them when None (recipes narrow it)
+    allowed_tools: Option<Vec<String>>,
+    /// The configured middlewares that apply to this model
+    middlewares: Vec<Arc<dyn Middleware>>,
 }
 
 impl AzureClient {
     pub fn new(config: ModelConfig) -> Self {
         Self {
             client: Client::new(),
+            language: Language::default(),
+            prompt_context: String::new(),
+            timings: Mutex::new(Vec::new()),
+            thinking: Mutex::new(Vec::new()),
+            allowed_tools: None,
+            middlewares: crate::middleware::for_model(&config.name),
             config,
         }
     }
 
+    /// The timings of the requests made since the last call
+    pub fn take_timings(&self) -> Vec<RequestTiming> {
+        std::mem::take(&mut *self.timings.lock().unwrap())
+    }
+
+    /// The thinking blocks of the last answer (Claude with `thinking_budget`),
+    /// which go back with its tool calls
+    pub fn take_thinking(&self) -> Vec<ContentPart> {
+        std::mem::take(&mut *self.thinking.lock().unwrap())
+    }
+
+    pub fn set_language(&mut self, language: Language) {
+        self.language = language;
+    }
+
+    pub fn set_prompt_context(&mut self, context: String) {
+        self.prompt_context = context;
+    }
+
+    pub fn set_allowed_tools(&mut self, tools: Option<Vec<String>>) {
+        self.allowed_tools = tools;
+    }
+
     pub fn update_config(&mut self, config: ModelConfig) {
+        self.middlewares = crate::middleware::for_model(&config.name);
         self.config = config;
     }
 
-    pub fn get_model_name(&self) -> &str {
-        &self.config.name
+    pub fn model_config(&self) -> &ModelConfig {
+        &self.config
     }
 
-    pub fn get_model_type(&self) -> &ModelType {
-        &self.config.model_type
+    pub fn get_model_name(&self) -> &str {
+        &self.config.name
     }
 
     pub fn get_tools_schema() -> Vec<Value> {
@@ -94,35 +315,687 @@ impl AzureClient {
             json!({
                 "type": "function",
                 "function": {
-                    "name": "execute_command",
-                    "description": "Execute a shell command on the system. Use this to run any command-line operations.",
+                    "name": "execute_command",
+                    "description": "Execute a shell command on the system. Use this to run any command-line operations.",
+                    "parameters": {
+                        "type": "object",
+                        "properties": {
+                            "command": {
+                                "type": "string",
+                                "description": "The command to execute"
+                            },
+                            "working_dir": {
+                                "type": "string",
+                                "description": "Working directory for the command (optional)"
+                            },
+                            "timeout_secs": {
+                                "type": "integer",
+                                "description": "Kill the command after this many seconds (optional, at most and by default the configured limit)"
+                            },
+                            "env": {
+                                "type": "object",
+                                "description": "Extra environment variables for this command (optional)",
+                                "additionalProperties": { "type": "string" }
+                            }
+                        },
+                        "required": ["command"]
+                    }
+                }
+            }),
+            json!({
+                "type": "function",
+                "function": {
+                    "name": "read_file",
+                    "description": "Read the contents of a file",
+                    "parameters": {
+                        "type": "object",
+                        "properties": {
+                            "path": {
+                                "type": "string",
+                                "description": "Path to the file to read"
+                            }
+                        },
+                        "required": ["path"]
+                    }
+                }
+            }),
+            json!({
+                "type": "function",
+                "function": {
+                    "name": "write_file",
+                    "description": "Write content to a file, creating it if it doesn't exist",
+                    "parameters": {
+                        "type": "object",
+                        "properties": {
+                            "path": {
+                                "type": "string",
+                                "description": "Path to the file to write"
+                            },
+                            "content": {
+                                "type": "string",
+                                "description": "Content to write to the file"
+                            }
+                        },
+                        "required": ["path", "content"]
+                    }
+                }
+            }),
+            json!({
+                "type": "function",
+                "function": {
+                    "name": "edit_file",
+                    "description": "Edit a file by replacing specific text",
+                    "parameters": {
+                        "type": "object",
+                        "properties": {
+                            "path": {
+                                "type": "string",
+                                "description": "Path to the file to edit"
+                            },
+                            "old_text": {
+                                "type": "string",
+                                "description": "Text to find and replace"
+                            },
+                            "new_text": {
+                                "type": "string",
+                                "description": "Text to replace with"
+                            }
+                        },
+                        "required": ["path", "old_text", "new_text"]
+                    }
+                }
+            }),
+            json!({
+                "type": "function",
+                "function": {
+                    "name": "list_directory",
+                    "description": "List files and directories in a path",
+                    "parameters": {
+                        "type": "object",
+                        "properties": {
+                            "path": {
+                                "type": "string",
+                                "description": "Path to the directory to list"
+                            }
+                        },
+                        "required": ["path"]
+                    }
+                }
+            }),
+            json!({
+                "type": "function",
+                "function": {
+                    "name": "search_files",
+                    "description": "Search for files matching a pattern",
+                    "parameters": {
+                        "type": "object",
+                        "properties": {
+                            "pattern": {
+                                "type": "string",
+                                "description": "Glob pattern to match (e.g., '*.rs', '**/*.txt')"
+                            },
+                            "path": {
+                                "type": "string",
+                                "description": "Starting directory for search"
+                            }
+                        },
+                        "required": ["pattern"]
+                    }
+                }
+            }),
+            json!({
+                "type": "function",
+                "function": {
+                    "name": "search_content",
+                    "description": "Search for text content in files",
+                    "parameters": {
+                        "type": "object",
+                        "properties": {
+                            "query": {
+                                "type": "string",
+                                "description": "Text or regex pattern to search for"
+                            },
+                            "path": {
+                                "type": "string",
+                                "description": "Directory to search in"
+                            },
+                            "file_pattern": {
+                                "type": "string",
+                                "description": "File pattern to filter (e.g., '*.rs')"
+                            }
+                        },
+                        "required": ["query"]
+                    }
+                }
+            }),
+            json!({
+                "type": "function",
+                "function": {
+                    "name": "run_background",
+                    "description": "Start a long-running command (dev server, watcher) in the background without waiting for it. Returns a process id; use check_process to read its output.",
+                    "parameters": {
+                        "type": "object",
+                        "properties": {
+                            "command": {
+                                "type": "string",
+                                "description": "The command to start"
+                            },
+                            "working_dir": {
+                                "type": "string",
+                                "description": "Working directory for the command (optional)"
+                            },
+                            "env": {
+                                "type": "object",
+                                "description": "Extra environment variables for this command (optional)",
+                                "additionalProperties": { "type": "string" }
+                            }
+                        },
+                        "required": ["command"]
+                    }
+                }
+            }),
+            json!({
+                "type": "function",
+                "function": {
+                    "name": "run_build",
+                    "description": "Build the project with its usual command (cargo build, npm run build, Python byte-compile) and list the errors as file:line:column",
+                    "parameters": {
+                        "type": "object",
+                        "properties": {
+                            "path": {
+                                "type": "string",
+                                "description": "Project directory or a file in it (default: current directory)"
+                            },
+                            "project": {
+                                "type": "string",
+                                "enum": ["cargo", "npm", "python"],
+                                "description": "Project type, when the directory has several (default: detected)"
+                            }
+                        }
+                    }
+                }
+            }),
+            json!({
+                "type": "function",
+                "function": {
+                    "name": "run_tests",
+                    "description": "Run the project's tests (cargo test, npm test, pytest) and list the failing tests with their file and line",
+                    "parameters": {
+                        "type": "object",
+                        "properties": {
+                            "path": {
+                                "type": "string",
+                                "description": "Project directory or a file in it (default: current directory)"
+                            },
+                            "project": {
+                                "type": "string",
+                                "enum": ["cargo", "npm", "python"],
+                                "description": "Project type, when the directory has several (default: detected)"
+                            },
+                            "filter": {
+                                "type": "string",
+                                "description": "Only tests matching this name (optional)"
+                            }
+                        }
+                    }
+                }
+            }),
+            json!({
+                "type": "function",
+                "function": {
+                    "name": "run_lint",
+                    "description": "Run the project's linter (cargo clippy, npm run lint, ruff or flake8) and list the findings as file:line:column",
+                    "parameters": {
+                        "type": "object",
+                        "properties": {
+                            "path": {
+                                "type": "string",
+                                "description": "Project directory or a file in it (default: current directory)"
+                            },
+                            "project": {
+                                "type": "string",
+                                "enum": ["cargo", "npm", "python"],
+                                "description": "Project type, when the directory has several (default: detected)"
+                            }
+                        }
+                    }
+                }
+            }),
+            json!({
+                "type": "function",
+                "function": {
+                    "name": "check_process",
+                    "description": "Get status and recent output of a background process. Omit id to list all background processes.",
+                    "parameters": {
+                        "type": "object",
+                        "properties": {
+                            "id": {
+                                "type": "integer",
+                                "description": "Process id returned by run_background"
+                            },
+                            "lines": {
+                                "type": "integer",
+                                "description": "Number of recent output lines to return (default 50)"
+                            }
+                        }
+                    }
+                }
+            }),
+            json!({
+                "type": "function",
+                "function": {
+                    "name": "kill_process",
+                    "description": "Terminate a background process and its children",
+                    "parameters": {
+                        "type": "object",
+                        "properties": {
+                            "id": {
+                                "type": "integer",
+                                "description": "Process id returned by run_background"
+                            }
+                        },
+                        "required": ["id"]
+                    }
+                }
+            }),
+            json!({
+                "type": "function",
+                "function": {
+                    "name": "get_outline",
+                    "description": "List the functions, classes, types and other declarations of a source file with their line numbers and signatures. Use it on large files before reading them",
+                    "parameters": {
+                        "type": "object",
+                        "properties": {
+                            "path": {
+                                "type": "string",
+                                "description": "Path to the file"
+                            }
+                        },
+                        "required": ["path"]
+                    }
+                }
+            }),
+            json!({
+                "type": "function",
+                "function": {
+                    "name": "create_directory",
+                    "description": "Create a directory, including any missing parent directories",
+                    "parameters": {
+                        "type": "object",
+                        "properties": {
+                            "path": {
+                                "type": "string",
+                                "description": "Path of the directory to create"
+                            }
+                        },
+                        "required": ["path"]
+                    }
+                }
+            }),
+            json!({
+                "type": "function",
+                "function": {
+                    "name": "stat_file",
+                    "description": "Get file metadata: type, size, modification time, permissions, line count and detected language",
+                    "parameters": {
+                        "type": "object",
+                        "properties": {
+                            "path": {
+                                "type": "string",
+                                "description": "Path to the file or directory"
+                            }
+                        },
+                        "required": ["path"]
+                    }
+                }
+            }),
+            json!({
+                "type": "function",
+                "function": {
+                    "name": "http_request",
+                    "description": "Send an HTTP request (e.g. to test an API you just wrote) and return status, headers and body. JSON responses are pretty-printed. Only hosts in the configured allow-list can be called (localhost by default).",
+                    "parameters": {
+                        "type": "object",
+                        "properties": {
+                            "url": {
+                                "type": "string",
+                                "description": "Full URL including scheme, e.g. http://localhost:3000/api/users"
+                            },
+                            "method": {
+                                "type": "string",
+                                "description": "HTTP method (default GET)"
+                            },
+                            "headers": {
+                                "type": "object",
+                                "description": "Request headers (optional)",
+                                "additionalProperties": { "type": "string" }
+                            },
+                            "body": {
+                                "description": "Request body: objects/arrays are sent as JSON, strings as-is (optional)"
+                            },
+                            "timeout_secs": {
+                                "type": "integer",
+                                "description": "Request timeout in seconds (default 30)"
+                            }
+                        },
+                        "required": ["url"]
+                    }
+                }
+            }),
+            json!({
+                "type": "function",
+                "function": {
+                    "name": "database_query",
+                    "description": "Run a read-only SQL query (SELECT, WITH, EXPLAIN, SHOW, DESCRIBE, PRAGMA) against a database configured by the user and return the result as a table. Use it to inspect schemas and data.",
+                    "parameters": {
+                        "type": "object",
+                        "properties": {
+                            "database": {
+                                "type": "string",
+                                "description": "Name of the configured database"
+                            },
+                            "query": {
+                                "type": "string",
+                                "description": "A single read-only SQL statement"
+                            },
+                            "max_rows": {
+                                "type": "integer",
+                                "description": "Maximum rows to return (default 100)"
+                            }
+                        },
+                        "required": ["database", "query"]
+                    }
+                }
+            }),
+            json!({
+                "type": "function",
+                "function": {
+                    "name": "docker_ps",
+                    "description": "List Docker containers with image, status and ports",
+                    "parameters": {
+                        "type": "object",
+                        "properties": {
+                            "all": {
+                                "type": "boolean",
+                                "description": "Include stopped containers (default false)"
+                            }
+                        }
+                    }
+                }
+            }),
+            json!({
+                "type": "function",
+                "function": {
+                    "name": "docker_logs",
+                    "description": "Read recent logs of a Docker container",
+                    "parameters": {
+                        "type": "object",
+                        "properties": {
+                            "container": {
+                                "type": "string",
+                                "description": "Container name or id"
+                            },
+                            "lines": {
+                                "type": "integer",
+                                "description": "Number of lines from the end (default 200, max 2000)"
+                            },
+                            "since": {
+                                "type": "string",
+                                "description": "Only logs since this time, e.g. 10m or 2024-01-01T00:00:00 (optional)"
+                            }
+                        },
+                        "required": ["container"]
+                    }
+                }
+            }),
+            json!({
+                "type": "function",
+                "function": {
+                    "name": "docker_exec",
+                    "description": "Run a command inside a running Docker container (only if the user enabled it)",
+                    "parameters": {
+                        "type": "object",
+                        "properties": {
+                            "container": {
+                                "type": "string",
+                                "description": "Container name or id"
+                            },
+                            "command": {
+                                "type": "string",
+                                "description": "Command to run with sh -c inside the container"
+                            }
+                        },
+                        "required": ["container", "command"]
+                    }
+                }
+            }),
+            json!({
+                "type": "function",
+                "function": {
+                    "name": "kubectl_get",
+                    "description": "List Kubernetes resources (read-only), e.g. pods, deployments, events",
+                    "parameters": {
+                        "type": "object",
+                        "properties": {
+                            "resource": {
+                                "type": "string",
+                                "description": "Resource type, e.g. pods, deployments, svc, events"
+                            },
+                            "name": {
+                                "type": "string",
+                                "description": "Resource name (optional)"
+                            },
+                            "selector": {
+                                "type": "string",
+                                "description": "Label selector, e.g. app=web (optional)"
+                            },
+                            "output": {
+                                "type": "string",
+                                "enum": ["wide", "yaml", "json"],
+                                "description": "Output format (default wide)"
+                            },
+                            "namespace": {
+                                "type": "string",
+                                "description": "Namespace (optional, defaults to the first allowed one)"
+                            },
+                            "context": {
+                                "type": "string",
+                                "description": "kubeconfig context (optional, defaults to the current one)"
+                            }
+                        },
+                        "required": ["resource"]
+                    }
+                }
+            }),
+            json!({
+                "type": "function",
+                "function": {
+                    "name": "kubectl_describe",
+                    "description": "Describe a Kubernetes resource with its events (read-only)",
+                    "parameters": {
+                        "type": "object",
+                        "properties": {
+                            "resource": {
+                                "type": "string",
+                                "description": "Resource type, e.g. pod, deployment"
+                            },
+                            "name": {
+                                "type": "string",
+                                "description": "Resource name (optional)"
+                            },
+                            "namespace": {
+                                "type": "string",
+                                "description": "Namespace (optional)"
+                            },
+                            "context": {
+                                "type": "string",
+                                "description": "kubeconfig context (optional)"
+                            }
+                        },
+                        "required": ["resource"]
+                    }
+                }
+            }),
+            json!({
+                "type": "function",
+                "function": {
+                    "name": "kubectl_logs",
+                    "description": "Read recent logs of a pod; set previous=true for the last crashed container",
+                    "parameters": {
+                        "type": "object",
+                        "properties": {
+                            "pod": {
+                                "type": "string",
+                                "description": "Pod name"
+                            },
+                            "container": {
+                                "type": "string",
+                                "description": "Container name (optional)"
+                            },
+                            "lines": {
+                                "type": "integer",
+                                "description": "Number of lines from the end (default 200, max 2000)"
+                            },
+                            "previous": {
+                                "type": "boolean",
+                                "description": "Logs of the previous (crashed) instance"
+                            },
+                            "namespace": {
+                                "type": "string",
+                                "description": "Namespace (optional)"
+                            },
+                            "context": {
+                                "type": "string",
+                                "description": "kubeconfig context (optional)"
+                            }
+                        },
+                        "required": ["pod"]
+                    }
+                }
+            }),
+            json!({
+                "type": "function",
+                "function": {
+                    "name": "find_definition",
+                    "description": "Go to where a symbol is defined, using the language server for the file (needs [tools.lsp])",
+                    "parameters": {
+                        "type": "object",
+                        "properties": {
+                            "path": {
+                                "type": "string",
+                                "description": "File containing the symbol"
+                            },
+                            "line": {
+                                "type": "integer",
+                                "description": "1-based line the symbol is on"
+                            },
+                            "symbol": {
+                                "type": "string",
+                                "description": "The name as written on that line"
+                            },
+                            "column": {
+                                "type": "integer",
+                                "description": "1-based column, instead of symbol"
+                            }
+                        },
+                        "required": ["path", "line"]
+                    }
+                }
+            }),
+            json!({
+                "type": "function",
+                "function": {
+                    "name": "find_references",
+                    "description": "Find every use of a symbol across the project, using the language server (needs [tools.lsp])",
+                    "parameters": {
+                        "type": "object",
+                        "properties": {
+                            "path": {
+                                "type": "string",
+                                "description": "File containing the symbol"
+                            },
+                            "line": {
+                                "type": "integer",
+                                "description": "1-based line the symbol is on"
+                            },
+                            "symbol": {
+                                "type": "string",
+                                "description": "The name as written on that line"
+                            },
+                            "column": {
+                                "type": "integer",
+                                "description": "1-based column, instead of symbol"
+                            },
+                            "include_declaration": {
+                                "type": "boolean",
+                                "description": "Also list the definition (default false)"
+                            }
+                        },
+                        "required": ["path", "line"]
+                    }
+                }
+            }),
+            json!({
+                "type": "function",
+                "function": {
+                    "name": "rename_symbol",
+                    "description": "Rename a symbol everywhere it is used in the project, using the language server, and show the diff. Prefer it to search_content + edit_file for renames (needs [tools.lsp])",
+                    "parameters": {
+                        "type": "object",
+                        "properties": {
+                            "path": {
+                                "type": "string",
+                                "description": "File containing the symbol"
+                            },
+                            "line": {
+                                "type": "integer",
+                                "description": "1-based line the symbol is on"
+                            },
+                            "symbol": {
+                                "type": "string",
+                                "description": "The current name as written on that line"
+                            },
+                            "column": {
+                                "type": "integer",
+                                "description": "1-based column, instead of symbol"
+                            },
+                            "new_name": {
+                                "type": "string",
+                                "description": "The new name"
+                            }
+                        },
+                        "required": ["path", "line", "new_name"]
+                    }
+                }
+            }),
+            json!({
+                "type": "function",
+                "function": {
+                    "name": "document_symbols",
+                    "description": "Outline of a file: its classes, functions, fields and so on with line numbers (needs [tools.lsp])",
                     "parameters": {
                         "type": "object",
                         "properties": {
-                            "command": {
-                                "type": "string",
-                                "description": "The command to execute"
-                            },
-                            "working_dir": {
+                            "path": {
                                 "type": "string",
-                                "description": "Working directory for the command (optional)"
+                                "description": "Source file"
                             }
                         },
-                        "required": ["command"]
+                        "required": ["path"]
                     }
                 }
             }),
             json!({
                 "type": "function",
                 "function": {
-                    "name": "read_file",
-                    "description": "Read the contents of a file",
+                    "name": "diagnostics",
+                    "description": "Compiler errors and warnings the language server reports for a file (needs [tools.lsp])",
                     "parameters": {
                         "type": "object",
                         "properties": {
                             "path": {
                                 "type": "string",
-                                "description": "Path to the file to read"
+                                "description": "Source file"
                             }
                         },
                         "required": ["path"]
@@ -132,324 +1005,475 @@ impl AzureClient {
             json!({
                 "type": "function",
                 "function": {
-                    "name": "write_file",
-                    "description": "Write content to a file, creating it if it doesn't exist",
+                    "name": "github_list_issues",
+                    "description": "List GitHub issues and pull requests of a repository (defaults to the origin remote)",
                     "parameters": {
                         "type": "object",
                         "properties": {
-                            "path": {
+                            "repo": {
                                 "type": "string",
-                                "description": "Path to the file to write"
+                                "description": "owner/name (optional, defaults to the current repository)"
                             },
-                            "content": {
+                            "state": {
                                 "type": "string",
-                                "description": "Content to write to the file"
+                                "enum": ["open", "closed", "all"],
+                                "description": "Issue state (default open)"
+                            },
+                            "labels": {
+                                "type": "string",
+                                "description": "Comma-separated label names (optional)"
+                            },
+                            "limit": {
+                                "type": "integer",
+                                "description": "Maximum issues to return (default 20)"
+                            }
+                        }
+                    }
+                }
+            }),
+            json!({
+                "type": "function",
+                "function": {
+                    "name": "github_read_issue",
+                    "description": "Read a GitHub issue or pull request with its description and comments",
+                    "parameters": {
+                        "type": "object",
+                        "properties": {
+                            "repo": {
+                                "type": "string",
+                                "description": "owner/name (optional)"
+                            },
+                            "number": {
+                                "type": "integer",
+                                "description": "Issue or PR number"
                             }
                         },
-                        "required": ["path", "content"]
+                        "required": ["number"]
                     }
                 }
             }),
             json!({
                 "type": "function",
                 "function": {
-                    "name": "edit_file",
-                    "description": "Edit a file by replacing specific text",
+                    "name": "github_create_pr",
+                    "description": "Open a GitHub pull request from a pushed branch",
                     "parameters": {
                         "type": "object",
                         "properties": {
-                            "path": {
+                            "repo": {
                                 "type": "string",
-                                "description": "Path to the file to edit"
+                                "description": "owner/name (optional)"
                             },
-                            "old_text": {
+                            "title": {
                                 "type": "string",
-                                "description": "Text to find and replace"
+                                "description": "Pull request title"
                             },
-                            "new_text": {
+                            "body": {
                                 "type": "string",
-                                "description": "Text to replace with"
+                                "description": "Pull request description in Markdown"
+                            },
+                            "head": {
+                                "type": "string",
+                                "description": "Branch with the changes (default: current branch, must be pushed)"
+                            },
+                            "base": {
+                                "type": "string",
+                                "description": "Target branch (default: repository default branch)"
+                            },
+                            "draft": {
+                                "type": "boolean",
+                                "description": "Open as draft (default false)"
                             }
                         },
-                        "required": ["path", "old_text", "new_text"]
+                        "required": ["title"]
                     }
                 }
             }),
             json!({
                 "type": "function",
                 "function": {
-                    "name": "list_directory",
-                    "description": "List files and directories in a path",
+                    "name": "github_comment",
+                    "description": "Comment on a GitHub issue or pull request",
                     "parameters": {
                         "type": "object",
                         "properties": {
-                            "path": {
+                            "repo": {
                                 "type": "string",
-                                "description": "Path to the directory to list"
+                                "description": "owner/name (optional)"
+                            },
+                            "number": {
+                                "type": "integer",
+                                "description": "Issue or PR number"
+                            },
+                            "body": {
+                                "type": "string",
+                                "description": "Comment in Markdown"
                             }
                         },
-                        "required": ["path"]
+                        "required": ["number", "body"]
                     }
                 }
             }),
             json!({
                 "type": "function",
                 "function": {
-                    "name": "search_files",
-                    "description": "Search for files matching a pattern",
+                    "name": "read_issue",
+                    "description": "Read a Jira or Linear ticket (title, description, acceptance criteria, recent comments). Call this whenever the user mentions a ticket key like PROJ-123.",
                     "parameters": {
                         "type": "object",
                         "properties": {
-                            "pattern": {
+                            "key": {
                                 "type": "string",
-                                "description": "Glob pattern to match (e.g., '*.rs', '**/*.txt')"
+                                "description": "Ticket key, e.g. PROJ-123"
+                            }
+                        },
+                        "required": ["key"]
+                    }
+                }
+            }),
+            json!({
+                "type": "function",
+                "function": {
+                    "name": "azdo_read_work_item",
+                    "description": "Read an Azure Boards work item (title, state, description, acceptance criteria)",
+                    "parameters": {
+                        "type": "object",
+                        "properties": {
+                            "id": {
+                                "type": "integer",
+                                "description": "Work item id"
                             },
-                            "path": {
+                            "project": {
                                 "type": "string",
-                                "description": "Starting directory for search"
+                                "description": "Project (optional, defaults to the configured one)"
                             }
                         },
-                        "required": ["pattern"]
+                        "required": ["id"]
                     }
                 }
             }),
             json!({
                 "type": "function",
                 "function": {
-                    "name": "search_content",
-                    "description": "Search for text content in files",
+                    "name": "azdo_pipeline_runs",
+                    "description": "List recent Azure Pipelines runs with status and result",
                     "parameters": {
                         "type": "object",
                         "properties": {
-                            "query": {
+                            "pipeline_id": {
+                                "type": "integer",
+                                "description": "Pipeline (definition) id (optional)"
+                            },
+                            "branch": {
                                 "type": "string",
-                                "description": "Text or regex pattern to search for"
+                                "description": "Only runs for this branch (optional)"
                             },
-                            "path": {
+                            "limit": {
+                                "type": "integer",
+                                "description": "Maximum runs (default 10)"
+                            },
+                            "project": {
                                 "type": "string",
-                                "description": "Directory to search in"
+                                "description": "Project (optional)"
+                            }
+                        }
+                    }
+                }
+            }),
+            json!({
+                "type": "function",
+                "function": {
+                    "name": "azdo_pipeline_logs",
+                    "description": "Get the logs of an Azure Pipelines run; only failed steps when any failed",
+                    "parameters": {
+                        "type": "object",
+                        "properties": {
+                            "run_id": {
+                                "type": "integer",
+                                "description": "Run (build) id from azdo_pipeline_runs"
                             },
-                            "file_pattern": {
+                            "project": {
                                 "type": "string",
-                                "description": "File pattern to filter (e.g., '*.rs')"
+                                "description": "Project (optional)"
                             }
                         },
-                        "required": ["query"]
+                        "required": ["run_id"]
+                    }
+                }
+            }),
+            json!({
+                "type": "function",
+                "function": {
+                    "name": "azdo_create_pr",
+                    "description": "Open a pull request in Azure Repos from a pushed branch",
+                    "parameters": {
+                        "type": "object",
+                        "properties": {
+                            "repository": {
+                                "type": "string",
+                                "description": "Repository name"
+                            },
+                            "title": {
+                                "type": "string",
+                                "description": "Pull request title"
+                            },
+                            "description": {
+                                "type": "string",
+                                "description": "Pull request description in Markdown"
+                            },
+                            "source_branch": {
+                                "type": "string",
+                                "description": "Branch with the changes (default: current branch)"
+                            },
+                            "target_branch": {
+                                "type": "string",
+                                "description": "Target branch (default main)"
+                            },
+                            "draft": {
+                                "type": "boolean",
+                                "description": "Open as draft (default false)"
+                            },
+                            "project": {
+                                "type": "string",
+                                "description": "Project (optional)"
+                            }
+                        },
+                        "required": ["repository", "title"]
                     }
                 }
             }),
         ]
     }
 
-    fn get_system_prompt() -> String {
+    fn get_system_prompt(language: Language) -> String {
         let cwd = std::env::current_dir()
             .map(|p| p.display().to_string())
             .unwrap_or_else(|_| ".".to_string());
 
-        let today = chrono::Local::now().format("%Y-%m-%d").to_string();
-
-        format!(
-            r#"# Engenheiro de Software Especialista
-
-Você é um engenheiro de software sênior com acesso direto ao computador do usuário através de ferramentas especializadas.
-
-## Ambiente Atual
-- **Diretório de trabalho**: {}
-- **Sistema Operacional**: {}
-- **Data atual**: {}
-
-## Competências Técnicas
-
-### Linguagens & Frameworks
-- **JavaScript/TypeScript**: ES6+, Node.js, React, Vue, Angular, Express, NestJS, Bun, Deno
-- **Java**: Spring Boot, Spring Security, Maven/Gradle, JPA/Hibernate, Microsserviços, application.properties
-- **Rust**: Programação de sistemas, Cargo, async/await, Tokio, Actix, Axum
-- **Tauri**: Aplicações desktop híbridas, integração Rust + Web
-- **Python**: Pandas, NumPy, FastAPI, Django, SQLAlchemy, pipelines de dados
-- **PHP**: Laravel, Symfony, Composer, PSR standards, PHP 8+
-
-### Bancos de Dados & SQL
-- **MySQL/MariaDB**: InnoDB, replicação, particionamento, stored procedures
-- **PostgreSQL**: PL/pgSQL, extensões (PostGIS, pg_trgm), JSONB, CTEs recursivas
-- **Oracle/PL-SQL**: Packages, cursores, triggers, bulk operations, tuning
-- **Geral**: Modelagem relacional, normalização, índices, otimização de queries
-
-### Infraestrutura & DevOps
-- Docker, Kubernetes, CI/CD, Git, Linux, Nginx, Redis, RabbitMQ
-
-## Princípios Fundamentais
-
-### 1. Qualidade de Código
-- Código limpo, legível e de fácil manutenção
-- Princípios SOLID e padrões de projeto quando apropriado
-- Composição sobre herança
-- Funções pequenas e focadas (Responsabilidade Única)
-- Nomenclatura clara e significativa
-- DRY (Don't Repeat Yourself), mas evite abstrações prematuras
-- KISS (Keep It Simple, Stupid)
-- YAGNI (You Aren't Gonna Need It)
-
-### 2. Testes
-- Sempre inclua testes para código produzido
-- Pirâmide de testes: unitários > integração > e2e
-- Testes devem ser independentes, determinísticos e rápidos
-- **Frameworks por linguagem**:
-  - JS/TS: Jest, Vitest, Cypress, Playwright
-  - Java: JUnit 5, Mockito, AssertJ, TestContainers
-  - Rust: teste nativo, proptest
-  - Python: pytest, hypothesis
-  - PHP: PHPUnit, Pest, Mockery
-
-### 3. Segurança
-- Validação de todas as entradas do usuário
-- Sanitização de dados antes de queries (SQL injection)
-- Escape de output (XSS)
-- Uso de prepared statements/parametrized queries
-- Princípio do menor privilégio
-- Siga OWASP Top 10
-
-### 4. Configuração e Segredos (CRÍTICO)
-**NUNCA hardcode dados sensíveis ou configurações no código.** Sempre externalize:
-- Credenciais: Senhas, API keys, tokens, secrets
-- Conexões: URLs de banco, hosts, portas
-- Configurações: Feature flags, limites, timeouts
-
-**Arquivos de configuração por tecnologia:**
-- **Node.js/JS/TS**: `.env` + `dotenv` ou `@nestjs/config`
-- **Java/Spring**: `application.properties`, `application-{{profile}}.properties`
-- **Python**: `.env` + `python-dotenv`, `settings.py`
-- **PHP**: `.env` (Laravel/Symfony), `config/*.php`
-- **Rust**: `.env` + `dotenvy`, `config.toml`
-
-### 5. Performance
-- Análise de complexidade Big-O
-- Evite queries N+1
-- Use índices apropriados em bancos de dados
-- Cache quando benéfico (Redis, in-memory)
-- Lazy loading e paginação para grandes conjuntos de dados
-
-### 6. Tratamento de Erros
-- Nunca silencie erros
-- Use tipos de erro específicos (não genéricos)
-- Logging estruturado com níveis apropriados
-- Mensagens de erro úteis para debugging
-
-## Ferramentas Disponíveis
-
-| Ferramenta | Descrição |
-|------------|-----------|
-| `execute_command` | Executar comandos shell |
-| `read_file` | Ler conteúdo de arquivos |
-| `write_file` | Criar/sobrescrever arquivos |
-| `edit_file` | Modificar arquivos existentes |
-| `list_directory` | Listar conteúdo de diretórios |
-| `search_files` | Buscar arquivos por padrão (glob) |
-| `search_content` | Buscar texto dentro de arquivos |
-
-## Regras de Execução
-
-1. **Execute imediatamente** - Não peça confirmação para tarefas claras
-2. **Seja proativo** - Use ferramentas sem hesitação para completar tarefas
-3. **Soluções completas** - Entregue código funcional, não fragmentos
-4. **Multi-step** - Execute todos os passos necessários de uma tarefa
-5. **Auto-correção** - Se ocorrer erro, diagnostique e corrija automaticamente
-6. **Feedback claro** - Relate resultados de forma concisa e objetiva
-7. **Leia antes de editar** - Sempre leia um arquivo antes de modificá-lo
-8. **Preserve contexto** - Não altere código fora do escopo da tarefa
-9. **Externalize configs** - Ao criar projetos, sempre configure arquivos de ambiente
-
-## Formato de Resposta
-
-1. **Análise**: Entenda o problema; pergunte apenas se houver ambiguidade crítica
-2. **Abordagem**: Explique brevemente a estratégia (1-2 linhas)
-3. **Execução**: Use as ferramentas para implementar a solução
-4. **Código**: Limpo, tipado, com tratamento de erros
-5. **Testes**: Inclua casos de teste quando aplicável
-6. **Trade-offs**: Mencione alternativas relevantes se existirem
-
-## Diretrizes por Linguagem
-
-### TypeScript
-- `strict: true` sempre
-- Interfaces para shapes de objetos
-- Generics tipados, nunca `any`
-- Configs via `process.env` com validação
-
-### Java
-- Java 17+ features (records, sealed classes, pattern matching)
-- Optional ao invés de null
-- Imutabilidade preferida
-- Configs via `application.properties` + `@Value`
-
-### Rust
-- Ownership e borrowing idiomático
-- `Result<T, E>` para erros recuperáveis
-- `Option<T>` para valores opcionais
-- Clippy sem warnings
-
-### Python
-- Type hints obrigatórios (PEP 484)
-- PEP 8 para estilo
-- Dataclasses ou Pydantic para modelos
-- Pandas: operações vetorizadas
-
-### PHP
-- PHP 8+ features (named arguments, attributes, match, enums)
-- PSR-12 para estilo
-- Type declarations estritos
-
-### SQL (Geral)
-- Keywords em MAIÚSCULAS
-- Sempre use prepared statements
-- Especifique colunas explicitamente (nunca `SELECT *`)
-- Índices para colunas em WHERE, JOIN, ORDER BY
-- EXPLAIN para otimização
-
-## Restrições
-
-- ❌ APIs ou padrões depreciados
-- ❌ Dependências desnecessárias
-- ❌ Código duplicado
-- ❌ SELECT * em produção
-- ❌ Console.log/print em código de produção
-- ❌ **NUNCA: Senhas, tokens, API keys hardcoded**
-- ❌ **NUNCA: URLs de banco de dados no código**
-- ✅ Biblioteca padrão quando suficiente
-- ✅ Prepared statements sempre
-- ✅ **SEMPRE: Variáveis de ambiente para configurações sensíveis**
-- ✅ **SEMPRE: `.env.example` com template das variáveis**
-
-Seja eficiente, preciso e entregue soluções de qualidade profissional."#,
-            cwd,
-            std::env::consts::OS,
-            today
-        )
+        let today = chrono::Local::now().format(language.date_format()).to_string();
+
+        // Portuguese has its own prompt; other languages use the English one
+        // and are asked to answer in their language
+        let template = match language {
+            Language::Pt => include_str!("../prompts/system.pt.md"),
+            _ => include_str!("../prompts/system.en.md"),
+        };
+
+        template
+            .replace("{language}", language.english_name())
+            .replace("{os}", std::env::consts::OS)
+            .replace("{date}", &today)
+            .replace("{cwd}", &cwd)
+    }
+
+    /// The system prompt without the memory, AICLI.md and pinned files
+    /// appended to it (/context)
+    pub fn base_system_prompt(&self) -> String {
+        Self::get_system_prompt(self.language)
+    }
+
+    /// The system prompt a chat request is sent with
+    pub fn system_prompt(&self) -> String {
+        Self::get_system_prompt(self.language) + &self.prompt_context
+    }
+
+    /// The tools sent with a chat request: not disabled by policy, and
+    /// allowed for this run
+    pub fn offered_tools(&self) -> Vec<Value> {
+        Self::get_tools_schema()
+            .into_iter()
+            .filter(|tool| {
+                let name = tool["function"]["name"].as_str().unwrap_or_default();
+                !crate::policy::tool_disabled(name)
+                    && self.allowed_tools.as_ref().is_none_or(|allowed| allowed.iter().any(|t| t == name))
+            })
+            .collect()
     }
 
     pub async fn chat(
         &self,
         messages: &[Message],
-        mut on_token: impl FnMut(&str),
-    ) -> Result<(String, Vec<ToolCall>, TokenUsage)> {
-        let system_prompt = Self::get_system_prompt();
-        let tools = Self::get_tools_schema();
+        on_token: impl FnMut(&str),
+    ) -> Result<(String, Vec<ToolCall>, TokenUsage, StopReason)> {
+        self.chat_with_thinking(messages, on_token, |_| {}).await
+    }
 
-        match self.config.model_type {
-            ModelType::Claude => self.chat_claude(messages, &system_prompt, &tools, on_token).await,
+    /// `chat`, streaming Claude's extended thinking to `on_thinking`
+    pub async fn chat_with_thinking(
+        &self,
+        messages: &[Message],
+        on_token: impl FnMut(&str),
+        on_thinking: impl FnMut(&str),
+    ) -> Result<(String, Vec<ToolCall>, TokenUsage, StopReason)> {
+        let system_prompt = self.system_prompt();
+        let tools = self.offered_tools();
+
+        let started = std::time::Instant::now();
+        let mut first_token = None;
+        let mut on_token = on_token;
+        let timed = |token: &str| {
+            first_token.get_or_insert_with(|| started.elapsed());
+            on_token(token)
+        };
+        let result = match self.config.model_type {
+            ModelType::Claude => {
+                let thinking = self.config.thinking_budget.map(|budget| (budget, on_thinking));
+                self.chat_claude(messages, &system_prompt, &tools, thinking, timed).await
+            }
             ModelType::Gpt | ModelType::DeepSeek | ModelType::Other => {
-                self.chat_openai(messages, &system_prompt, &tools, on_token).await
+                self.chat_openai(messages, &system_prompt, &tools, timed).await
             }
+        };
+        let output_tokens = result.as_ref().map(|(_, _, usage, _)| usage.completion_tokens).unwrap_or(0);
+        crate::metrics::record_request(&self.config.name, first_token, started.elapsed(), output_tokens, result.is_err());
+        if result.is_ok() {
+            self.timings.lock().unwrap().push(RequestTiming { first_token, total: started.elapsed(), output_tokens });
         }
+        result
     }
 
-    pub fn get_max_context(&self) -> usize {
-        // Return context window size based on model type
-        match self.config.model_type {
-            ModelType::Claude => 200000,  // Claude 3 Opus: 200K
-            ModelType::Gpt => 128000,     // GPT-4 Turbo: 128K
-            ModelType::DeepSeek => 64000, // DeepSeek: 64K
-            ModelType::Other => 32000,    // Default: 32K
+    /// The request URL: the model's `url_template`, else `default`, with
+    /// {endpoint}, {deployment} and {api_version} filled in
+    fn request_url(&self, default: &str, api_version: &str) -> String {
+        let api_version = self.config.api_version.as_deref().filter(|v| !v.is_empty()).unwrap_or(api_version);
+        self.config
+            .url_template
+            .as_deref()
+            .filter(|t| !t.is_empty())
+            .unwrap_or(default)
+            .replace("{endpoint}", self.config.endpoint.trim_end_matches('/'))
+            .replace("{deployment}", &self.config.deployment)
+            .replace("{api_version}", api_version)
+    }
+
+    /// The headers carrying the key: `api_key_header` when set, else `defaults`
+    fn key_headers(&self, defaults: &[&str]) -> Vec<(String, String)> {
+        let names: Vec<&str> = match self.config.api_key_header.as_deref() {
+            Some("") => Vec::new(),
+            Some(name) => vec![name],
+            None => defaults.to_vec(),
+        };
+        names
+            .into_iter()
+            .map(|name| match name.eq_ignore_ascii_case("authorization") {
+                true => (name.to_string(), format!("Bearer {}", self.config.api_key)),
+                false => (name.to_string(), self.config.api_key.clone()),
+            })
+            .collect()
+    }
+
+    /// POST `body` with the model's extra headers, through the middlewares
+    /// and the model's rate limiter, backing off on 429s (honoring
+    /// `retry-after`) instead of failing the turn
+    async fn send(&self, url: String, headers: Vec<(String, String)>, body: Value, prompt_tokens: usize) -> Result<reqwest::Response> {
+        crate::policy::check_endpoint(&self.config.endpoint)?;
+        crate::policy::audit("model_request", json!({ "model": self.config.name, "endpoint": self.config.endpoint }));
+        let mut api_request = ApiRequest { model: self.config.name.clone(), url, headers, body };
+        for (name, value) in &self.config.headers {
+            api_request.set_header(name, value);
+        }
+        for middleware in &self.middlewares {
+            middleware.on_request(&mut api_request)?;
+        }
+        // A middleware may point the request elsewhere
+        crate::policy::check_endpoint(&api_request.url)?;
+        let mut request = self.client.post(&api_request.url);
+        for (name, value) in &api_request.headers {
+            request = request.header(name, value);
         }
+        let request = request.json(&api_request.body);
+
+        let mut attempt = 0;
+        loop {
+            ratelimit::acquire(&self.config, prompt_tokens + self.config.max_tokens as usize).await;
+
+            let this_try = request.try_clone()
+                .ok_or_else(|| anyhow!("Request body cannot be retried"))?;
+            let started = std::time::Instant::now();
+            let response = this_try.send().await?;
+            let seen = ApiResponse {
+                model: &self.config.name,
+                url: &api_request.url,
+                status: response.status().as_u16(),
+                headers: response.headers(),
+                elapsed: started.elapsed(),
+            };
+            for middleware in &self.middlewares {
+                middleware.on_response(&seen);
+            }
+
+            if response.status() != reqwest::StatusCode::TOO_MANY_REQUESTS || attempt >= MAX_RATE_LIMIT_RETRIES {
+                return Ok(response);
+            }
+
+            let wait = response.headers()
+                .get("retry-after")
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.parse::<u64>().ok())
+                .unwrap_or(2u64.pow(attempt as u32 + 1));
+            tokio::time::sleep(std::time::Duration::from_secs(wait.min(60))).await;
+            attempt += 1;
+        }
+    }
+
+    /// Continue the last message, an assistant answer that stopped at
+    /// max_tokens. Claude resumes it directly as a prefill; other models are
+    /// asked to pick up where it stopped. Either way the continuation follows
+    /// the answer with its trailing whitespace removed.
+    pub async fn continue_answer(
+        &self,
+        messages: &[Message],
+        on_token: impl FnMut(&str),
+    ) -> Result<(String, Vec<ToolCall>, TokenUsage, StopReason)> {
+        let mut request = messages.to_vec();
+        match request.last_mut() {
+            Some(last) if last.role == "assistant" => {
+                // Anthropic rejects a final assistant message ending in whitespace
+                last.content = MessageContent::Text(last.content.as_text().trim_end().to_string());
+            }
+            _ => return Err(anyhow!("No answer to continue")),
+        }
+        // Anthropic doesn't take a prefilled answer with extended thinking on
+        if self.config.model_type != ModelType::Claude || self.config.thinking_budget.is_some() {
+            request.push(Message {
+                role: "user".to_string(),
+                content: MessageContent::Text(CONTINUE_PROMPT.to_string()),
+            });
+        }
+        self.chat(&request, on_token).await
+    }
+
+    /// One-off completion with a custom system prompt and no tools.
+    /// Used for small helper calls (routing, summaries) outside the main chat.
+    pub async fn complete(&self, system_prompt: &str, prompt: &str) -> Result<String> {
+        let (content, _) = self.complete_streaming(system_prompt, prompt, |_| {}).await?;
+        Ok(content)
+    }
+
+    /// `complete`, streaming the answer to `on_token` and returning its usage
+    pub async fn complete_streaming(
+        &self,
+        system_prompt: &str,
+        prompt: &str,
+        on_token: impl FnMut(&str),
+    ) -> Result<(String, TokenUsage)> {
+        let messages = vec![Message {
+            role: "user".to_string(),
+            content: MessageContent::Text(prompt.to_string()),
+        }];
+
+        let (content, _, usage, _) = match self.config.model_type {
+            ModelType::Claude => self.chat_claude(&messages, system_prompt, &[], None::<(u32, fn(&str))>, on_token).await?,
+            ModelType::Gpt | ModelType::DeepSeek | ModelType::Other => {
+                self.chat_openai(&messages, system_prompt, &[], on_token).await?
+            }
+        };
+
+        Ok((content, usage))
+    }
+
+    pub fn get_max_context(&self) -> usize {
+        self.config.model_type.context_window()
     }
 
     async fn chat_openai(
@@ -458,7 +1482,7 @@ Seja eficiente, preciso e entregue soluções de qualidade profissional."#,
         system_prompt: &str,
         tools: &[Value],
         mut on_token: impl FnMut(&str),
-    ) -> Result<(String, Vec<ToolCall>, TokenUsage)> {
+    ) -> Result<(String, Vec<ToolCall>, TokenUsage, StopReason)> {
         let mut api_messages: Vec<Value> = vec![json!({
             "role": "system",
             "content": system_prompt
@@ -467,55 +1491,59 @@ Seja eficiente, preciso e entregue soluções de qualidade profissional."#,
         // Estimate prompt tokens (rough: 1 token ≈ 4 chars)
         let mut prompt_chars = system_prompt.len();
         for msg in messages {
-            prompt_chars += msg.content.as_text().len();
+            let content = msg.content.as_text();
+            // An answer that only called tools has no text to send
+            if content.is_empty() && msg.role == "assistant" {
+                continue;
+            }
+            prompt_chars += content.len();
             api_messages.push(json!({
                 "role": msg.role,
-                "content": msg.content.as_text()
+                "content": content
             }));
         }
 
         // Support both Azure OpenAI and Azure AI Foundry formats
         let endpoint = if self.config.endpoint.contains("/models") || self.config.endpoint.contains("services.ai.azure.com") {
             // Azure AI Foundry format
-            format!(
-                "{}/models/chat/completions?api-version=2024-05-01-preview",
-                self.config.endpoint.trim_end_matches('/')
-            )
+            self.request_url("{endpoint}/models/chat/completions?api-version={api_version}", "2024-05-01-preview")
         } else {
             // Classic Azure OpenAI format
-            format!(
-                "{}/openai/deployments/{}/chat/completions?api-version=2024-02-15-preview",
-                self.config.endpoint.trim_end_matches('/'),
-                self.config.deployment
+            self.request_url(
+                "{endpoint}/openai/deployments/{deployment}/chat/completions?api-version={api_version}",
+                "2024-02-15-preview",
             )
         };
 
-        let body = json!({
+        let mut body = json!({
             "model": self.config.deployment,
             "messages": api_messages,
             "max_tokens": self.config.max_tokens,
             "temperature": self.config.temperature,
-            "tools": tools,
             "stream": true
         });
+        if let Some(seed) = self.config.seed {
+            body["seed"] = json!(seed);
+        }
+        // OpenAI rejects an empty tools array
+        if !tools.is_empty() {
+            body["tools"] = json!(tools);
+        }
 
-        let response = self.client
-            .post(&endpoint)
-            .header("api-key", &self.config.api_key)
-            .header("Authorization", format!("Bearer {}", &self.config.api_key))
-            .header("Content-Type", "application/json")
-            .json(&body)
-            .send()
-            .await?;
+        let mut headers = self.key_headers(&["api-key", "Authorization"]);
+        headers.push(("Content-Type".to_string(), "application/json".to_string()));
+        let response = self.send(endpoint, headers, body, prompt_chars / 4).await?;
 
         if !response.status().is_success() {
-            let error_text = response.text().await?;
-            return Err(anyhow!("API error: {}", error_text));
+            let status = response.status().as_u16();
+            let body = response.text().await?;
+            return Err(ApiError { status, body }.into());
         }
 
         let mut full_content = String::new();
         let mut tool_calls: Vec<ToolCall> = Vec::new();
         let mut current_tool_call: Option<(String, String, String)> = None;
+        let mut stop_reason = StopReason::Complete;
 
         let mut stream = response.bytes_stream();
 
@@ -524,8 +1552,7 @@ Seja eficiente, preciso e entregue soluções de qualidade profissional."#,
             let text = String::from_utf8_lossy(&chunk);
 
             for line in text.lines() {
-                if line.starts_with("data: ") {
-                    let data = &line[6..];
+                if let Some(data) = line.strip_prefix("data: ") {
                     if data == "[DONE]" {
                         continue;
                     }
@@ -563,6 +1590,7 @@ Seja eficiente, preciso e entregue soluções de qualidade profissional."#,
 
                                 // Check if we should finalize tool call
                                 if let Some(finish_reason) = choice.get("finish_reason").and_then(|f| f.as_str()) {
+                                    stop_reason = StopReason::parse(finish_reason);
                                     if finish_reason == "tool_calls" || finish_reason == "stop" {
                                         if let Some((id, name, args)) = current_tool_call.take() {
                                             if !name.is_empty() {
@@ -594,29 +1622,56 @@ Seja eficiente, preciso e entregue soluções de qualidade profissional."#,
             prompt_tokens,
             completion_tokens,
             total_tokens: prompt_tokens + completion_tokens,
+            ..Default::default()
         };
 
-        Ok((full_content, tool_calls, usage))
+        Ok((full_content, tool_calls, usage, stop_reason))
     }
 
+    /// `thinking`: the budget for extended thinking and where its text streams
     async fn chat_claude(
         &self,
         messages: &[Message],
         system_prompt: &str,
         tools: &[Value],
+        thinking: Option<(u32, impl FnMut(&str))>,
         mut on_token: impl FnMut(&str),
-    ) -> Result<(String, Vec<ToolCall>, TokenUsage)> {
-        let mut api_messages: Vec<Value> = Vec::new();
-
+    ) -> Result<(String, Vec<ToolCall>, TokenUsage, StopReason)> {
+        let (budget, mut on_thinking) = thinking.unzip();
         // Estimate prompt tokens (rough: 1 token ≈ 4 chars)
-        let mut prompt_chars = system_prompt.len();
-        for msg in messages {
-            prompt_chars += msg.content.as_text().len();
-            api_messages.push(json!({
-                "role": msg.role,
-                "content": msg.content.as_text()
-            }));
+        let prompt_chars = system_prompt.len() + messages.iter().map(|m| m.content.as_text().len()).sum::<usize>();
+        let mut api_messages = Self::claude_messages(messages, budget.is_some());
+
+        let caching = self.config.prompt_caching;
+        if caching {
+            // An empty message has no block to mark, and thinking blocks can't be
+            let cacheable = |idx: usize| match api_messages[idx].as_ref().map(|m| &m["content"]) {
+                Some(Value::String(text)) => !text.is_empty(),
+                Some(Value::Array(blocks)) => blocks.last().is_some_and(|block| match block["type"].as_str() {
+                    Some("text") => block["text"].as_str().is_some_and(|t| !t.is_empty()),
+                    Some("thinking" | "redacted_thinking") => false,
+                    _ => true,
+                }),
+                _ => false,
+            };
+            for idx in Self::cache_breakpoints(messages, cacheable) {
+                let Some(message) = api_messages[idx].as_mut() else {
+                    continue;
+                };
+                match message["content"].as_array_mut().and_then(|blocks| blocks.last_mut()) {
+                    Some(block) => block["cache_control"] = json!({ "type": "ephemeral" }),
+                    None => {
+                        let text = message["content"].clone();
+                        message["content"] = json!([{
+                            "type": "text",
+                            "text": text,
+                            "cache_control": { "type": "ephemeral" }
+                        }]);
+                    }
+                }
+            }
         }
+        let api_messages: Vec<Value> = api_messages.into_iter().flatten().collect();
 
         // Convert tools to Claude format
         let claude_tools: Vec<Value> = tools.iter().map(|t| {
@@ -629,47 +1684,61 @@ Seja eficiente, preciso e entregue soluções de qualidade profissional."#,
         }).collect();
 
         // Support both direct Anthropic API and Azure AI Foundry
+        let query = if self.config.api_version.as_deref().is_some_and(|v| !v.is_empty()) { "?api-version={api_version}" } else { "" };
         let endpoint = if self.config.endpoint.contains("services.ai.azure.com") {
             // Azure AI Foundry format
-            format!(
-                "{}/anthropic/v1/messages",
-                self.config.endpoint.trim_end_matches('/')
-            )
+            self.request_url(&format!("{{endpoint}}/anthropic/v1/messages{}", query), "")
         } else {
             // Direct Anthropic API
-            format!(
-                "{}/v1/messages",
-                self.config.endpoint.trim_end_matches('/')
-            )
+            self.request_url(&format!("{{endpoint}}/v1/messages{}", query), "")
         };
 
-        let body = json!({
+        // A breakpoint on the system prompt caches the tool definitions too
+        let system = if caching {
+            json!([{
+                "type": "text",
+                "text": system_prompt,
+                "cache_control": { "type": "ephemeral" }
+            }])
+        } else {
+            json!(system_prompt)
+        };
+
+        let mut body = json!({
             "model": self.config.deployment,
             "max_tokens": self.config.max_tokens,
-            "system": system_prompt,
+            "system": system,
             "messages": api_messages,
-            "tools": claude_tools,
             "stream": true
         });
+        if !claude_tools.is_empty() {
+            body["tools"] = json!(claude_tools);
+        }
+        // Thinking counts against max_tokens, so the answer keeps all of it
+        if let Some(budget) = budget {
+            body["thinking"] = json!({ "type": "enabled", "budget_tokens": budget });
+            body["max_tokens"] = json!(self.config.max_tokens + budget);
+        }
 
-        let response = self.client
-            .post(&endpoint)
-            .header("api-key", &self.config.api_key)
-            .header("x-api-key", &self.config.api_key)
-            .header("anthropic-version", "2023-06-01")
-            .header("Content-Type", "application/json")
-            .json(&body)
-            .send()
-            .await?;
+        let mut headers = self.key_headers(&["api-key", "x-api-key"]);
+        headers.push(("anthropic-version".to_string(), "2023-06-01".to_string()));
+        headers.push(("Content-Type".to_string(), "application/json".to_string()));
+        let response = self.send(endpoint, headers, body, prompt_chars / 4).await?;
 
         if !response.status().is_success() {
-            let error_text = response.text().await?;
-            return Err(anyhow!("API error: {}", error_text));
+            let status = response.status().as_u16();
+            let body = response.text().await?;
+            return Err(ApiError { status, body }.into());
         }
 
         let mut full_content = String::new();
         let mut tool_calls: Vec<ToolCall> = Vec::new();
         let mut current_tool: Option<(String, String, String)> = None;
+        let mut current_thinking: Option<(String, String)> = None;
+        let mut thinking_blocks: Vec<ContentPart> = Vec::new();
+        let mut thinking_chars = 0;
+        let mut reported: Option<TokenUsage> = None;
+        let mut stop_reason = StopReason::Complete;
 
         let mut stream = response.bytes_stream();
 
@@ -678,19 +1747,50 @@ Seja eficiente, preciso e entregue soluções de qualidade profissional."#,
             let text = String::from_utf8_lossy(&chunk);
 
             for line in text.lines() {
-                if line.starts_with("data: ") {
-                    let data = &line[6..];
+                if let Some(data) = line.strip_prefix("data: ") {
 
                     if let Ok(json) = serde_json::from_str::<Value>(data) {
                         let event_type = json.get("type").and_then(|t| t.as_str()).unwrap_or("");
 
                         match event_type {
+                            "message_start" => {
+                                if let Some(u) = json.get("message").and_then(|m| m.get("usage")) {
+                                    let field = |k: &str| u.get(k).and_then(|v| v.as_u64()).unwrap_or(0) as usize;
+                                    let cache_read = field("cache_read_input_tokens");
+                                    let cache_write = field("cache_creation_input_tokens");
+                                    reported = Some(TokenUsage {
+                                        prompt_tokens: field("input_tokens") + cache_read + cache_write,
+                                        cache_read_tokens: cache_read,
+                                        cache_write_tokens: cache_write,
+                                        ..Default::default()
+                                    });
+                                }
+                            }
+                            "message_delta" => {
+                                if let Some(reason) = json.get("delta").and_then(|d| d.get("stop_reason")).and_then(|r| r.as_str()) {
+                                    stop_reason = StopReason::parse(reason);
+                                }
+                                if let (Some(usage), Some(out)) = (
+                                    reported.as_mut(),
+                                    json.get("usage").and_then(|u| u.get("output_tokens")).and_then(|v| v.as_u64()),
+                                ) {
+                                    usage.completion_tokens = out as usize;
+                                }
+                            }
                             "content_block_start" => {
                                 if let Some(content_block) = json.get("content_block") {
-                                    if content_block.get("type").and_then(|t| t.as_str()) == Some("tool_use") {
-                                        let id = content_block.get("id").and_then(|i| i.as_str()).unwrap_or("").to_string();
-                                        let name = content_block.get("name").and_then(|n| n.as_str()).unwrap_or("").to_string();
-                                        current_tool = Some((id, name, String::new()));
+                                    match content_block.get("type").and_then(|t| t.as_str()) {
+                                        Some("tool_use") => {
+                                            let id = content_block.get("id").and_then(|i| i.as_str()).unwrap_or("").to_string();
+                                            let name = content_block.get("name").and_then(|n| n.as_str()).unwrap_or("").to_string();
+                                            current_tool = Some((id, name, String::new()));
+                                        }
+                                        Some("thinking") => current_thinking = Some((String::new(), String::new())),
+                                        Some("redacted_thinking") => {
+                                            let data = content_block.get("data").and_then(|d| d.as_str()).unwrap_or("").to_string();
+                                            thinking_blocks.push(ContentPart::RedactedThinking { data });
+                                        }
+                                        _ => {}
                                     }
                                 }
                             }
@@ -705,9 +1805,24 @@ Seja eficiente, preciso e entregue soluções de qualidade profissional."#,
                                             args.push_str(partial_json);
                                         }
                                     }
+                                    if let Some((thinking, signature)) = current_thinking.as_mut() {
+                                        if let Some(text) = delta.get("thinking").and_then(|t| t.as_str()) {
+                                            thinking.push_str(text);
+                                            thinking_chars += text.len();
+                                            if let Some(on_thinking) = on_thinking.as_mut() {
+                                                on_thinking(text);
+                                            }
+                                        }
+                                        if let Some(part) = delta.get("signature").and_then(|s| s.as_str()) {
+                                            signature.push_str(part);
+                                        }
+                                    }
                                 }
                             }
                             "content_block_stop" => {
+                                if let Some((thinking, signature)) = current_thinking.take() {
+                                    thinking_blocks.push(ContentPart::Thinking { thinking, signature });
+                                }
                                 if let Some((id, name, args)) = current_tool.take() {
                                     if !name.is_empty() {
                                         let input: Value = serde_json::from_str(&args).unwrap_or(json!({}));
@@ -722,44 +1837,119 @@ Seja eficiente, preciso e entregue soluções de qualidade profissional."#,
             }
         }
 
-        // Estimate token usage (1 token ≈ 4 characters)
-        let prompt_tokens = prompt_chars / 4;
-        let completion_tokens = full_content.len() / 4;
-        let usage = TokenUsage {
-            prompt_tokens,
-            completion_tokens,
-            total_tokens: prompt_tokens + completion_tokens,
+        *self.thinking.lock().unwrap() = thinking_blocks;
+
+        // Prefer the usage Anthropic reports, whose output tokens include the
+        // thinking; fall back to estimating (1 token ≈ 4 characters)
+        let mut usage = match reported {
+            Some(mut usage) => {
+                if usage.completion_tokens == 0 {
+                    usage.completion_tokens = (full_content.len() + thinking_chars) / 4;
+                }
+                usage.total_tokens = usage.prompt_tokens + usage.completion_tokens;
+                usage
+            }
+            None => {
+                let prompt_tokens = prompt_chars / 4;
+                let completion_tokens = (full_content.len() + thinking_chars) / 4;
+                TokenUsage {
+                    prompt_tokens,
+                    completion_tokens,
+                    total_tokens: prompt_tokens + completion_tokens,
+                    ..Default::default()
+                }
+            }
         };
+        usage.thinking_tokens = (thinking_chars / 4).min(usage.completion_tokens);
 
-        Ok((full_content, tool_calls, usage))
+        Ok((full_content, tool_calls, usage, stop_reason))
     }
 
-    #[allow(dead_code)]
-    pub async fn chat_with_tool_results(
-        &self,
-        messages: &[Message],
-        tool_results: &[ToolResult],
-        on_token: impl FnMut(&str),
-    ) -> Result<(String, Vec<ToolCall>, TokenUsage)> {
-        let mut all_messages = messages.to_vec();
-
-        // Add tool results as assistant context
-        let results_text = tool_results
+    /// `messages` in Anthropic's schema, one for each (None for an answer
+    /// with nothing to send). An answer's tool calls and the results after it
+    /// go as tool_use and tool_result blocks when they pair up. A call left
+    /// without results (a turn stopped at its budget), or results whose call
+    /// was compacted away, go as text: the API rejects them unpaired. Thinking
+    /// blocks go along only while extended thinking is on.
+    fn claude_messages(messages: &[Message], thinking: bool) -> Vec<Option<Value>> {
+        let paired = |call: &Message, results: Option<&Message>| {
+            let calls = call.content.tool_ids(false);
+            !calls.is_empty()
+                && !calls.contains(&"")
+                && results.is_some_and(|r| r.role == "user" && r.content.tool_ids(true) == calls)
+        };
+        messages
             .iter()
-            .map(|r| format!("[Tool: {}]\n{}", r.tool_name, r.output))
-            .collect::<Vec<_>>()
-            .join("\n\n");
+            .enumerate()
+            .map(|(i, msg)| {
+                let native = match msg.role.as_str() {
+                    "assistant" => paired(msg, messages.get(i + 1)),
+                    _ => i > 0 && paired(&messages[i - 1], Some(msg)),
+                };
+                let content = match &msg.content {
+                    MessageContent::Parts(parts) if native => {
+                        let blocks: Vec<Value> = parts
+                            .iter()
+                            .filter_map(|part| match part {
+                                ContentPart::Text { text } if text.is_empty() => None,
+                                ContentPart::Text { text } => Some(json!({ "type": "text", "text": text })),
+                                ContentPart::ToolUse { id, name, input } => {
+                                    Some(json!({ "type": "tool_use", "id": id, "name": name, "input": input }))
+                                }
+                                ContentPart::Thinking { thinking: text, signature } if thinking => {
+                                    Some(json!({ "type": "thinking", "thinking": text, "signature": signature }))
+                                }
+                                ContentPart::RedactedThinking { data } if thinking => {
+                                    Some(json!({ "type": "redacted_thinking", "data": data }))
+                                }
+                                ContentPart::Thinking { .. } | ContentPart::RedactedThinking { .. } => None,
+                                ContentPart::ToolResult { tool_use_id, content, is_error, .. } => {
+                                    let mut block = json!({ "type": "tool_result", "tool_use_id": tool_use_id });
+                                    if !content.is_empty() {
+                                        block["content"] = json!(content);
+                                    }
+                                    if *is_error {
+                                        block["is_error"] = json!(true);
+                                    }
+                                    Some(block)
+                                }
+                            })
+                            .collect();
+                        json!(blocks)
+                    }
+                    content => {
+                        let text = content.as_text();
+                        if text.is_empty() && msg.role == "assistant" {
+                            return None;
+                        }
+                        json!(text)
+                    }
+                };
+                Some(json!({ "role": msg.role, "content": content }))
+            })
+            .collect()
+    }
 
-        all_messages.push(Message {
-            role: "assistant".to_string(),
-            content: MessageContent::Text(format!("Tool results:\n{}", results_text)),
-        });
+    /// Pick which messages get a `cache_control` breakpoint: the latest large
+    /// user messages (file context) plus the final message, so each agent-loop
+    /// follow-up re-reads the conversation prefix from cache. Only messages
+    /// that are `cacheable` qualify; the final one is then the last of those.
+    fn cache_breakpoints(messages: &[Message], cacheable: impl Fn(usize) -> bool) -> Vec<usize> {
+        let mut indices: Vec<usize> = Vec::new();
+        let Some(last) = (0..messages.len()).rev().find(|&idx| cacheable(idx)) else {
+            return indices;
+        };
+        indices.push(last);
 
-        all_messages.push(Message {
-            role: "user".to_string(),
-            content: MessageContent::Text("Continue based on the tool results above.".to_string()),
-        });
+        for (idx, msg) in messages.iter().enumerate().take(last).rev() {
+            if indices.len() >= MAX_MESSAGE_BREAKPOINTS {
+                break;
+            }
+            if msg.role == "user" && msg.content.as_text().len() >= CACHE_MIN_CHARS && cacheable(idx) {
+                indices.push(idx);
+            }
+        }
 
-        self.chat(&all_messages, on_token).await
+        indices
     }
 }
@@ -0,0 +1,140 @@
+use anyhow::{Context, Result};
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// A fenced code block from an assistant response
+#[derive(Debug, Clone)]
+pub struct CodeBlock {
+    pub lang: String,
+    /// File named in the fence header (```rust src/main.rs or ```rust:src/main.rs)
+    pub path: Option<String>,
+    pub code: String,
+}
+
+/// Fenced code blocks in `markdown`, in order. An unclosed last block is kept.
+pub fn extract(markdown: &str) -> Vec<CodeBlock> {
+    let mut blocks = Vec::new();
+    let mut current: Option<CodeBlock> = None;
+
+    for line in markdown.lines() {
+        let fence = line.trim_start();
+        if let Some(header) = fence.strip_prefix("```") {
+            match current.take() {
+                Some(block) => blocks.push(block),
+                None => {
+                    let (lang, path) = parse_header(header);
+                    current = Some(CodeBlock { lang, path, code: String::new() });
+                }
+            }
+        } else if let Some(block) = current.as_mut() {
+            block.code.push_str(line);
+            block.code.push('\n');
+        }
+    }
+
+    if let Some(block) = current.filter(|b| !b.code.is_empty()) {
+        blocks.push(block);
+    }
+    blocks
+}
+
+/// Split a fence header into language and file path. A lone token that looks
+/// like a path (```src/main.rs) is taken as the path, and the language is
+/// inferred from its extension.
+pub fn parse_header(header: &str) -> (String, Option<String>) {
+    let header = header.trim().trim_start_matches('`');
+    let (first, rest) = match header.split_once(|c: char| c == ':' || c.is_whitespace()) {
+        Some((first, rest)) => (first, rest.trim()),
+        None => (header, ""),
+    };
+    let rest = rest
+        .strip_prefix("file=")
+        .or_else(|| rest.strip_prefix("title="))
+        .unwrap_or(rest)
+        .trim_matches('"');
+
+    if !rest.is_empty() && looks_like_path(rest) {
+        return (first.to_string(), Some(rest.to_string()));
+    }
+    if rest.is_empty() && looks_like_path(first) {
+        return (language_for_path(first).unwrap_or_default().to_string(), Some(first.to_string()));
+    }
+    (first.to_string(), None)
+}
+
+/// Language name for a file's extension, as used in fence tags
+pub fn language_for_path(path: &str) -> Option<&'static str> {
+    let ext = path.rsplit_once('.')?.1.to_lowercase();
+    let lang = match ext.as_str() {
+        "rs" => "rust",
+        "py" | "pyi" => "python",
+        "js" | "mjs" | "cjs" => "javascript",
+        "jsx" => "jsx",
+        "ts" | "mts" | "cts" => "typescript",
+        "tsx" => "tsx",
+        "go" => "go",
+        "java" => "java",
+        "kt" | "kts" => "kotlin",
+        "cs" => "csharp",
+        "c" | "h" => "c",
+        "cpp" | "cc" | "cxx" | "hpp" | "hh" => "cpp",
+        "rb" => "ruby",
+        "php" => "php",
+        "swift" => "swift",
+        "sh" | "bash" | "zsh" => "bash",
+        "ps1" | "psm1" => "powershell",
+        "sql" => "sql",
+        "html" | "htm" => "html",
+        "css" => "css",
+        "scss" => "scss",
+        "json" => "json",
+        "yaml" | "yml" => "yaml",
+        "toml" => "toml",
+        "xml" => "xml",
+        "md" => "markdown",
+        "tf" => "hcl",
+        _ => return None,
+    };
+    Some(lang)
+}
+
+fn looks_like_path(token: &str) -> bool {
+    !token.contains(char::is_whitespace)
+        && (token.contains('/') || token.contains('\\') || token.contains('.'))
+}
+
+/// Copy text to the system clipboard using the platform's clipboard command.
+/// Returns the command that was used.
+pub fn copy_to_clipboard(text: &str) -> Result<&'static str> {
+    let candidates: &[(&'static str, &[&str])] = match std::env::consts::OS {
+        "macos" => &[("pbcopy", &[])],
+        "windows" => &[("clip", &[])],
+        _ => &[
+            ("wl-copy", &[]),
+            ("xclip", &["-selection", "clipboard"]),
+            ("xsel", &["--clipboard", "--input"]),
+        ],
+    };
+
+    for (program, args) in candidates {
+        let Ok(mut child) = Command::new(program)
+            .args(*args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+        else {
+            continue;
+        };
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(text.as_bytes())
+                .with_context(|| format!("Failed to write to {}", program))?;
+        }
+        if child.wait()?.success() {
+            return Ok(program);
+        }
+    }
+
+    let tried: Vec<&str> = candidates.iter().map(|(p, _)| *p).collect();
+    anyhow::bail!("No clipboard command available (tried {})", tried.join(", "))
+}
@@ -2,36 +2,673 @@ use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs;
-use std::io::{self, Write};
+use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use crossterm::terminal;
+use std::io::{self, IsTerminal, Write};
 use std::path::PathBuf;
-use crate::i18n::Language;
+use crate::console::{print, println};
+use crate::i18n::{Language, Strings};
+use crate::text;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct AppConfig {
+    /// Schema version; older files are migrated on load (see MIGRATIONS)
+    #[serde(default)]
+    pub version: u32,
     pub active_model: String,
     pub models: HashMap<String, ModelConfig>,
     #[serde(default)]
     pub github_username: String,
     #[serde(default)]
     pub language: Language,
+    #[serde(default)]
+    pub router: RouterConfig,
+    #[serde(default)]
+    pub cache: CacheConfig,
+    #[serde(default)]
+    pub tools: ToolsConfig,
+    #[serde(default)]
+    pub redaction: RedactionConfig,
+    #[serde(default)]
+    pub agent: AgentConfig,
+    #[serde(default)]
+    pub ui: UiConfig,
+    #[serde(default)]
+    pub sessions: SessionsConfig,
+    #[serde(default)]
+    pub speech: SpeechConfig,
+    #[serde(default)]
+    pub images: ImagesConfig,
+    #[serde(default)]
+    pub content_safety: ContentSafetyConfig,
+    #[serde(default)]
+    pub metrics: MetricsConfig,
+    /// Text a `;name` typed at the prompt expands to, keyed by name
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub snippets: HashMap<String, String>,
+    /// What every model request passes through, in order (`[[middlewares]]`)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub middlewares: Vec<MiddlewareConfig>,
+    /// Set by `aicli config encrypt`; the secrets are then stored encrypted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encryption: Option<crate::vault::EncryptionConfig>,
 }
 
+/// A `[[middlewares]]` entry; the fields besides `kind` and `models` are
+/// those of its kind
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ModelConfig {
-    pub name: String,
-    pub api_key: String,
+pub struct MiddlewareConfig {
+    pub kind: MiddlewareKind,
+    /// Models it applies to; all of them when empty
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub models: Vec<String>,
+    /// `headers`: set on each request, replacing aicli's of the same name
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub headers: HashMap<String, String>,
+    /// `log`: the file; requests.jsonl next to config.toml when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    /// `command`: the program, run through the shell
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub command: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MiddlewareKind {
+    Headers,
+    Log,
+    Redact,
+    Command,
+}
+
+/// Local latency, throughput and error metrics for `/perf`; opt-in and
+/// never uploaded
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MetricsConfig {
+    #[serde(default)]
+    pub enabled: bool,
+}
+
+/// Azure AI Content Safety screening of prompts and answers, on top of the
+/// content filter Azure OpenAI deployments already apply
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContentSafetyConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Content Safety resource endpoint; CONTENT_SAFETY_ENDPOINT when empty
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub endpoint: String,
+    /// Content Safety resource key; CONTENT_SAFETY_KEY when empty
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub key: String,
+    /// Lowest severity that counts as flagged: 2 (low), 4 (medium) or 6 (high)
+    #[serde(default = "default_safety_threshold")]
+    pub threshold: u8,
+    /// Also screen answers; they are already shown, so this only warns
+    #[serde(default = "default_true")]
+    pub check_responses: bool,
+}
+
+impl Default for ContentSafetyConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            endpoint: String::new(),
+            key: String::new(),
+            threshold: default_safety_threshold(),
+            check_responses: true,
+        }
+    }
+}
+
+impl ContentSafetyConfig {
+    pub fn endpoint(&self) -> Option<String> {
+        non_empty(&self.endpoint).or_else(|| std::env::var("CONTENT_SAFETY_ENDPOINT").ok().filter(|e| !e.is_empty()))
+    }
+
+    pub fn key(&self) -> Option<String> {
+        non_empty(&self.key).or_else(|| std::env::var("CONTENT_SAFETY_KEY").ok().filter(|k| !k.is_empty()))
+    }
+}
+
+/// Image generation with an Azure OpenAI DALL-E deployment (/imagine)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImagesConfig {
+    /// Image deployment name, e.g. "dall-e-3"
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub deployment: String,
-    pub model_type: ModelType,
-    #[serde(default = "default_max_tokens")]
-    pub max_tokens: u32,
-    #[serde(default = "default_temperature")]
-    pub temperature: f32,
+    /// Azure OpenAI endpoint; the active model's when empty
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub endpoint: String,
+    /// API key; the active model's when empty
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub api_key: String,
+    #[serde(default = "default_image_size")]
+    pub size: String,
+    /// Where images are saved; the working directory when empty
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub output_dir: String,
+}
+
+impl Default for ImagesConfig {
+    fn default() -> Self {
+        Self {
+            deployment: String::new(),
+            endpoint: String::new(),
+            api_key: String::new(),
+            size: default_image_size(),
+            output_dir: String::new(),
+        }
+    }
+}
+
+/// Azure Speech for reading answers aloud (/speak) and voice prompts (/mic)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SpeechConfig {
+    /// Speak answers from the start of every session
+    #[serde(default)]
+    pub enabled: bool,
+    /// Speech resource key; AZURE_SPEECH_KEY when empty
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub key: String,
+    /// Speech resource region, e.g. "eastus"; AZURE_SPEECH_REGION when empty
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub region: String,
+    /// Neural voice name; defaults to one matching the UI language. Its
+    /// locale is also the language /mic listens for
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub voice: Option<String>,
+}
+
+impl SpeechConfig {
+    pub fn key(&self) -> Option<String> {
+        non_empty(&self.key).or_else(|| std::env::var("AZURE_SPEECH_KEY").ok().filter(|k| !k.is_empty()))
+    }
+
+    pub fn region(&self) -> Option<String> {
+        non_empty(&self.region).or_else(|| std::env::var("AZURE_SPEECH_REGION").ok().filter(|r| !r.is_empty()))
+    }
+}
+
+fn non_empty(value: &str) -> Option<String> {
+    (!value.is_empty()).then(|| value.to_string())
+}
+
+/// Saved conversations (~/.aicli/sessions)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionsConfig {
+    /// Name untitled sessions from their first exchange with a small model
+    /// call (the router's fast model when one is configured)
+    #[serde(default = "default_true")]
+    pub auto_title: bool,
+}
+
+impl Default for SessionsConfig {
+    fn default() -> Self {
+        Self { auto_title: true }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UiConfig {
+    /// Colors used to highlight code blocks
+    #[serde(default)]
+    pub code_theme: CodeTheme,
+    /// Number the lines of code blocks
+    #[serde(default)]
+    pub line_numbers: bool,
+    /// How images are drawn in the terminal
+    #[serde(default)]
+    pub images: ImageProtocol,
+    /// A footer after each answer with time to first token, total time and tokens/sec
+    #[serde(default)]
+    pub verbose_stats: bool,
+    /// Keep the status line on the terminal's last row instead of printing
+    /// it after every turn
+    #[serde(default = "default_true")]
+    pub sticky_status: bool,
+    /// Plain ASCII instead of box drawing, spinners and emoji
+    #[serde(default)]
+    pub ascii_only: bool,
+    /// Ask for a few follow-up questions after each answer, sent by
+    /// pressing their number
+    #[serde(default)]
+    pub follow_ups: bool,
+    /// Command every final answer is piped through before it is shown or
+    /// written to /output, e.g. "glow -"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pipe: Option<String>,
+    /// Ask before sending @files estimated above this many tokens, with
+    /// the option to send a summary instead; 0 never asks
+    #[serde(default = "default_file_warning_tokens")]
+    pub file_warning_tokens: usize,
+    /// Offer to attach the files a prompt mentions without an @
+    #[serde(default = "default_true")]
+    pub suggest_files: bool,
+}
+
+impl Default for UiConfig {
+    fn default() -> Self {
+        Self {
+            code_theme: CodeTheme::default(),
+            line_numbers: false,
+            images: ImageProtocol::default(),
+            verbose_stats: false,
+            sticky_status: true,
+            ascii_only: false,
+            follow_ups: false,
+            pipe: None,
+            file_warning_tokens: default_file_warning_tokens(),
+            suggest_files: true,
+        }
+    }
+}
+
+/// Terminal graphics protocol for inline images
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageProtocol {
+    /// Detect from the terminal's environment variables
+    #[default]
+    Auto,
+    Kitty,
+    Iterm,
+    /// Converted with img2sixel or ImageMagick
+    Sixel,
+    /// Only print the path
+    Off,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CodeTheme {
+    #[default]
+    Dracula,
+    Monokai,
+    SolarizedDark,
+    /// For light terminal backgrounds
+    GithubLight,
+    /// No highlighting
+    Plain,
+}
+
+/// Limits for one turn of the tool loop. When one is hit the loop pauses and
+/// asks whether to keep going.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgentConfig {
+    /// Model round-trips after tool results
+    #[serde(default = "default_max_iterations")]
+    pub max_iterations: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tool_calls: Option<usize>,
+    /// Prompt + completion tokens summed over the turn's requests
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<usize>,
+    /// Estimated USD, needs input_price/output_price on the model
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_cost: Option<f64>,
+    /// Record each turn that wrote files in git
+    #[serde(default, skip_serializing_if = "CheckpointMode::is_off")]
+    pub checkpoints: CheckpointMode,
+}
+
+/// How `[agent] checkpoints` records a turn's file changes in git
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckpointMode {
+    #[default]
+    Off,
+    /// A commit of the files the agent wrote
+    Commit,
+    /// A stash entry of the working tree, which is left as it is
+    Stash,
+}
+
+impl CheckpointMode {
+    pub fn is_off(&self) -> bool {
+        *self == CheckpointMode::Off
+    }
+}
+
+impl Default for AgentConfig {
+    fn default() -> Self {
+        Self {
+            max_iterations: default_max_iterations(),
+            max_tool_calls: None,
+            max_tokens: None,
+            max_cost: None,
+            checkpoints: CheckpointMode::Off,
+        }
+    }
+}
+
+/// Masking of secrets in tool output and file context before they reach
+/// the model or the terminal
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RedactionConfig {
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// Extra regexes; a named group `value` masks only that part
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub patterns: Vec<String>,
+}
+
+impl Default for RedactionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            patterns: Vec::new(),
+        }
+    }
+}
+
+/// Limits applied to tool execution
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolsConfig {
+    /// Kill `execute_command` after this many seconds (0 = no limit)
+    #[serde(default = "default_command_timeout")]
+    pub command_timeout_secs: u64,
+    /// Tool output longer than this is cut to head + tail (0 = no limit)
+    #[serde(default = "default_max_output_chars")]
+    pub max_output_chars: usize,
+    /// Shell for execute_command/run_background ("sh", "bash", "zsh", "pwsh",
+    /// "powershell", "cmd", "nushell"); defaults to cmd on Windows, sh elsewhere
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shell: Option<String>,
+    /// Per-OS overrides keyed by `std::env::consts::OS` ("windows", "macos", "linux")
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub shells: HashMap<String, String>,
+    /// Extra environment variables set for every command
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub env: HashMap<String, String>,
+    /// Hosts the http_request tool may call; an entry also matches its
+    /// subdomains and "*" allows everything
+    #[serde(default = "default_http_allowed_hosts")]
+    pub http_allowed_hosts: Vec<String>,
+    /// Connections the database_query tool can use, by name
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub databases: HashMap<String, DatabaseConfig>,
+    #[serde(default)]
+    pub docker: DockerConfig,
+    #[serde(default)]
+    pub kubernetes: KubernetesConfig,
+    #[serde(default)]
+    pub lsp: LspConfig,
+    #[serde(default)]
+    pub github: GitHubConfig,
+    #[serde(default)]
+    pub issues: IssueTrackerConfig,
+    #[serde(default)]
+    pub azure_devops: AzureDevOpsConfig,
+    #[serde(default)]
+    pub guard: GuardConfig,
+}
+
+/// Prompt-injection guard for output of web/ticket/log tools
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GuardConfig {
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// Globs of files whose content is treated as untrusted when read (e.g. "vendor/**")
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub untrusted_paths: Vec<String>,
+}
+
+impl Default for GuardConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            untrusted_paths: Vec::new(),
+        }
+    }
+}
+
+/// Azure Repos/Boards/Pipelines access for the azdo_* tools
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AzureDevOpsConfig {
+    #[serde(default)]
+    pub organization: String,
+    /// Default project; tools can override it per call
+    #[serde(default)]
+    pub project: String,
+    /// Personal access token; falls back to AZURE_DEVOPS_EXT_PAT, then Entra ID via `az`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pat: Option<String>,
+}
+
+/// Jira or Linear connection for the read_issue tool
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct IssueTrackerConfig {
+    #[serde(default)]
+    pub provider: IssueProvider,
+    /// Jira site URL (unused for Linear)
+    #[serde(default)]
+    pub url: String,
+    /// Jira Cloud account email; without it the token is sent as a bearer PAT
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    /// Jira API token / PAT or Linear API key
+    #[serde(default)]
+    pub token: String,
+    /// Jira custom field holding acceptance criteria, e.g. "customfield_10035"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub acceptance_criteria_field: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IssueProvider {
+    #[default]
+    Jira,
+    Linear,
+}
+
+/// GitHub REST API access for the github_* tools and /pr
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GitHubConfig {
+    /// Personal access token; falls back to GITHUB_TOKEN, GH_TOKEN or `gh auth token`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+    /// Change for GitHub Enterprise, e.g. https://github.example.com/api/v3
+    #[serde(default = "default_github_api_url")]
+    pub api_url: String,
+}
+
+impl Default for GitHubConfig {
+    fn default() -> Self {
+        Self {
+            token: None,
+            api_url: default_github_api_url(),
+        }
+    }
+}
+
+/// Guardrails for the docker_* tools
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DockerConfig {
+    /// docker_ps and docker_logs are only offered once this is on
+    #[serde(default)]
+    pub enabled: bool,
+    /// docker_exec runs arbitrary commands in containers, so it needs its own switch
+    #[serde(default)]
+    pub allow_exec: bool,
+    /// Container name patterns (globs) the tools may touch; empty = all
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub containers: Vec<String>,
+}
+
+/// Guardrails for the read-only kubectl_* tools
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct KubernetesConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// kubeconfig contexts the tools may use; empty = any
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub contexts: Vec<String>,
+    /// Namespaces the tools may read; the first is the default. Empty = any
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub namespaces: Vec<String>,
+}
+
+/// Language servers behind find_definition, find_references,
+/// document_symbols and diagnostics, keyed by language name
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LspConfig {
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub servers: HashMap<String, LspServerConfig>,
+    /// Seconds to wait for a server to answer (indexing a large project is slow)
+    #[serde(default = "default_lsp_timeout")]
+    pub timeout_secs: u64,
+}
+
+/// One language server, started on first use and kept for the session
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LspServerConfig {
+    pub command: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub args: Vec<String>,
+    /// File extensions it handles, without the dot
+    pub extensions: Vec<String>,
+}
+
+fn default_lsp_timeout() -> u64 {
+    30
+}
+
+impl Default for LspConfig {
+    fn default() -> Self {
+        Self { servers: HashMap::new(), timeout_secs: default_lsp_timeout() }
+    }
+}
+
+/// A database the agent may query read-only. The URL never comes from the model.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DatabaseConfig {
+    pub kind: DatabaseKind,
+    /// File path for SQLite, connection URL for Postgres/MySQL
+    pub url: String,
 }
 
-// Legacy config for backwards compatibility
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DatabaseKind {
+    Sqlite,
+    #[serde(alias = "postgresql")]
+    Postgres,
+    #[serde(alias = "mariadb")]
+    Mysql,
+}
+
+impl std::fmt::Display for DatabaseKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DatabaseKind::Sqlite => write!(f, "sqlite3"),
+            DatabaseKind::Postgres => write!(f, "psql"),
+            DatabaseKind::Mysql => write!(f, "mysql"),
+        }
+    }
+}
+
+impl ToolsConfig {
+    /// The shell configured for the current OS, if any
+    pub fn shell_for_os(&self) -> Option<&str> {
+        self.shells
+            .get(std::env::consts::OS)
+            .or(self.shell.as_ref())
+            .map(|s| s.as_str())
+    }
+}
+
+impl Default for ToolsConfig {
+    fn default() -> Self {
+        Self {
+            command_timeout_secs: default_command_timeout(),
+            max_output_chars: default_max_output_chars(),
+            shell: None,
+            shells: HashMap::new(),
+            env: HashMap::new(),
+            http_allowed_hosts: default_http_allowed_hosts(),
+            databases: HashMap::new(),
+            docker: DockerConfig::default(),
+            kubernetes: KubernetesConfig::default(),
+            lsp: LspConfig::default(),
+            github: GitHubConfig::default(),
+            issues: IssueTrackerConfig::default(),
+            azure_devops: AzureDevOpsConfig::default(),
+            guard: GuardConfig::default(),
+        }
+    }
+}
+
+/// Opt-in cache for one-shot (`aicli -p`) responses
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct LegacyConfig {
+pub struct CacheConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Hours before an entry expires (0 = never)
+    #[serde(default = "default_cache_ttl")]
+    pub ttl_hours: u64,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            ttl_hours: default_cache_ttl(),
+        }
+    }
+}
+
+/// Per-turn routing between a cheap "fast" model and a stronger "smart" one
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RouterConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default)]
+    pub fast_model: String,
+    #[serde(default)]
+    pub smart_model: String,
+    #[serde(default)]
+    pub strategy: RouterStrategy,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RouterStrategy {
+    /// Keyword and size heuristics, no extra API call
+    #[default]
+    Heuristic,
+    /// Ask the fast model to classify the prompt first
+    Model,
+}
+
+/// How a model's conversation is made to fit once it passes
+/// `compact_threshold` of the context window
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ContextStrategy {
+    /// The older messages become one summary message
+    #[default]
+    SummarizeOldest,
+    /// The oldest turns are left out until it fits again: a sliding window
+    DropOldest,
+    /// Only the system prompt and the last `keep_messages` messages are kept
+    KeepLast,
+    /// Summarize the older messages, then drop the oldest turns still kept
+    /// if that isn't enough
+    Hybrid,
+}
+
+impl ContextStrategy {
+    pub fn is_default(&self) -> bool {
+        *self == ContextStrategy::default()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelConfig {
+    pub name: String,
     pub api_key: String,
     pub endpoint: String,
     pub deployment: String,
@@ -40,10 +677,99 @@ pub struct LegacyConfig {
     pub max_tokens: u32,
     #[serde(default = "default_temperature")]
     pub temperature: f32,
+    /// Add Anthropic `cache_control` breakpoints (Claude only)
+    #[serde(default = "default_true")]
+    pub prompt_caching: bool,
+    /// USD per million input tokens, used for `/cost` estimates
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub input_price: Option<f64>,
+    /// USD per million output tokens, used for `/cost` estimates
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_price: Option<f64>,
+    /// Client-side request budget (RPM), matching the deployment's quota
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub requests_per_minute: Option<u32>,
+    /// Client-side token budget (TPM), matching the deployment's quota
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tokens_per_minute: Option<u32>,
+    /// What the chat does when the conversation nears the context window
+    #[serde(default, skip_serializing_if = "ContextStrategy::is_default")]
+    pub context_strategy: ContextStrategy,
+    /// Share of the context window that sets off `context_strategy` (0.85)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compact_threshold: Option<f32>,
+    /// Latest messages `context_strategy` always keeps (4)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keep_messages: Option<usize>,
+    /// Tokens Claude may spend on extended thinking before it answers (at
+    /// least 1024); off when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thinking_budget: Option<u32>,
+    /// Header the key goes in, for gateways with their own ("Authorization"
+    /// sends "Bearer KEY", "" none); api-key and its usual companion when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_key_header: Option<String>,
+    /// Sent with every request, replacing aicli's of the same name
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub headers: HashMap<String, String>,
+    /// Replaces the api-version query parameter; Claude's URL has one only when set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_version: Option<String>,
+    /// The request URL, with {endpoint}, {deployment} and {api_version}
+    /// filled in; replaces the Azure and Foundry paths
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url_template: Option<String>,
+    /// Defined by AICLI_MODELS or AICLI_MODEL_* variables; never saved
+    #[serde(skip)]
+    pub from_env: bool,
+    /// Sampling seed for one request (OpenAI format only); never saved
+    #[serde(skip)]
+    pub seed: Option<u64>,
+}
+
+impl Default for ModelConfig {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            api_key: String::new(),
+            endpoint: String::new(),
+            deployment: String::new(),
+            model_type: ModelType::Other,
+            max_tokens: default_max_tokens(),
+            temperature: default_temperature(),
+            prompt_caching: true,
+            input_price: None,
+            output_price: None,
+            requests_per_minute: None,
+            tokens_per_minute: None,
+            context_strategy: ContextStrategy::default(),
+            compact_threshold: None,
+            keep_messages: None,
+            thinking_budget: None,
+            api_key_header: None,
+            headers: HashMap::new(),
+            api_version: None,
+            url_template: None,
+            from_env: false,
+            seed: None,
+        }
+    }
 }
 
 fn default_max_tokens() -> u32 { 4096 }
+fn default_image_size() -> String { "1024x1024".to_string() }
+fn default_safety_threshold() -> u8 { 4 }
 fn default_temperature() -> f32 { 0.7 }
+fn default_true() -> bool { true }
+fn default_cache_ttl() -> u64 { 24 }
+fn default_file_warning_tokens() -> usize { 20_000 }
+fn default_command_timeout() -> u64 { 120 }
+fn default_max_iterations() -> usize { 10 }
+fn default_max_output_chars() -> usize { 30000 }
+fn default_github_api_url() -> String { "https://api.github.com".to_string() }
+fn default_http_allowed_hosts() -> Vec<String> {
+    vec!["localhost".to_string(), "127.0.0.1".to_string(), "::1".to_string()]
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "lowercase")]
@@ -65,6 +791,18 @@ impl std::fmt::Display for ModelType {
     }
 }
 
+impl ModelType {
+    /// Context window size in tokens
+    pub fn context_window(&self) -> usize {
+        match self {
+            ModelType::Claude => 200000,  // Claude 3 Opus: 200K
+            ModelType::Gpt => 128000,     // GPT-4 Turbo: 128K
+            ModelType::DeepSeek => 64000, // DeepSeek: 64K
+            ModelType::Other => 32000,    // Default: 32K
+        }
+    }
+}
+
 pub fn get_config_path() -> PathBuf {
     let home = dirs::home_dir().unwrap_or_else(|| PathBuf::from("."));
     home.join(".aicli").join("config.toml")
@@ -92,12 +830,167 @@ impl AppConfig {
         }
     }
 
-    pub fn list_models(&self) -> Vec<(&String, &ModelConfig)> {
-        self.models.iter().collect()
+    /// Remove a model; the active model moves to another one when it is removed
+    pub fn remove_model(&mut self, name: &str) -> bool {
+        if self.models.remove(name).is_none() {
+            return false;
+        }
+        if self.active_model == name {
+            let mut names: Vec<&String> = self.models.keys().collect();
+            names.sort();
+            self.active_model = names.first().map(|n| n.to_string()).unwrap_or_default();
+        }
+        true
     }
 }
 
+/// The file (or AZURE_*) config plus the models defined in the environment,
+/// which replace file models of the same name. With env models the config
+/// file is optional, which suits containers and CI. Sets up the
+/// `[[middlewares]]` its clients send through.
 pub fn load_config() -> Result<AppConfig> {
+    let config = load_with_env_models()?;
+    crate::middleware::configure(&config);
+    Ok(config)
+}
+
+fn load_with_env_models() -> Result<AppConfig> {
+    let models = env_models()?;
+    if models.is_empty() {
+        return load_base_config();
+    }
+    let mut config = match load_base_config() {
+        Ok(config) => config,
+        Err(_) if !get_config_path().exists() => AppConfig {
+            github_username: "leonardo-matheus".to_string(),
+            language: Language::detect(),
+            ..Default::default()
+        },
+        Err(e) => return Err(e),
+    };
+
+    let first = models[0].name.clone();
+    for model in models {
+        config.models.insert(model.name.clone(), model);
+    }
+    if let Some(active) = std::env::var("AICLI_ACTIVE_MODEL").ok().filter(|a| !a.is_empty()) {
+        if !config.models.contains_key(&active) {
+            anyhow::bail!("AICLI_ACTIVE_MODEL is '{}', which is not a configured model", active);
+        }
+        config.active_model = active;
+    } else if config.get_active_model().is_none() {
+        config.active_model = first;
+    }
+    Ok(config)
+}
+
+/// Fields of an env model, as AICLI_MODEL_<NAME>__<FIELD> suffixes or
+/// AICLI_MODELS keys (lowercase)
+const ENV_MODEL_FIELDS: &[&str] = &[
+    "endpoint", "api_key", "deployment", "model_type", "max_tokens", "temperature", "prompt_caching",
+    "input_price", "output_price", "requests_per_minute", "tokens_per_minute", "context_strategy",
+    "compact_threshold", "keep_messages", "thinking_budget", "api_key_header", "headers", "api_version",
+    "url_template",
+];
+
+/// Models from `AICLI_MODELS` (JSON: an array of models with a "name", or an
+/// object keyed by name) and `AICLI_MODEL_<NAME>__<FIELD>` groups, sorted by
+/// name. A group's name is lowercased with `_` as `-` (GPT4_MINI: gpt4-mini);
+/// the groups override AICLI_MODELS fields of the same model.
+pub fn env_models() -> Result<Vec<ModelConfig>> {
+    let mut fields: std::collections::BTreeMap<String, Vec<(String, String)>> = Default::default();
+
+    if let Some(json) = std::env::var("AICLI_MODELS").ok().filter(|j| !j.trim().is_empty()) {
+        let value: serde_json::Value = serde_json::from_str(&json).context("AICLI_MODELS is not valid JSON")?;
+        let entries: Vec<(String, serde_json::Value)> = match value {
+            serde_json::Value::Array(items) => items
+                .into_iter()
+                .map(|item| {
+                    let name = item["name"].as_str().map(String::from);
+                    name.map(|n| (n, item)).ok_or_else(|| anyhow::anyhow!("Every model in AICLI_MODELS needs a \"name\""))
+                })
+                .collect::<Result<_>>()?,
+            serde_json::Value::Object(map) => map.into_iter().collect(),
+            _ => anyhow::bail!("AICLI_MODELS must be a JSON array or object of models"),
+        };
+        for (name, model) in entries {
+            let Some(object) = model.as_object() else {
+                anyhow::bail!("AICLI_MODELS: model '{}' is not an object", name);
+            };
+            let entry = fields.entry(name).or_default();
+            for (field, value) in object.iter().filter(|(field, _)| *field != "name") {
+                let value = value.as_str().map(String::from).unwrap_or_else(|| value.to_string());
+                entry.push((field.to_lowercase(), value));
+            }
+        }
+    }
+
+    let mut groups: Vec<(String, String)> = std::env::vars()
+        .filter_map(|(key, value)| {
+            let (name, field) = key.strip_prefix("AICLI_MODEL_")?.split_once("__")?;
+            Some((format!("{}__{}", name.to_lowercase().replace('_', "-"), field.to_lowercase()), value))
+        })
+        .collect();
+    groups.sort();
+    for (key, value) in groups {
+        let (name, field) = key.split_once("__").unwrap_or_default();
+        fields.entry(name.to_string()).or_default().push((field.to_string(), value));
+    }
+
+    fields.into_iter().map(|(name, fields)| env_model(&name, &fields)).collect()
+}
+
+fn env_model(name: &str, fields: &[(String, String)]) -> Result<ModelConfig> {
+    let mut model = ModelConfig { name: name.to_string(), from_env: true, ..Default::default() };
+    let mut model_type = None;
+    for (field, value) in fields {
+        let invalid = || anyhow::anyhow!("Model '{}' from the environment: invalid {} '{}'", name, field, value);
+        match field.as_str() {
+            "endpoint" => model.endpoint = value.clone(),
+            "api_key" | "key" => model.api_key = value.clone(),
+            "deployment" => model.deployment = value.clone(),
+            "model_type" | "type" => {
+                let parsed = toml::Value::String(value.to_lowercase()).try_into::<ModelType>().map_err(|_| invalid())?;
+                model_type = Some(parsed);
+            }
+            "max_tokens" => model.max_tokens = value.parse().map_err(|_| invalid())?,
+            "temperature" => model.temperature = value.parse().map_err(|_| invalid())?,
+            "prompt_caching" => model.prompt_caching = value.parse().map_err(|_| invalid())?,
+            "input_price" => model.input_price = Some(value.parse().map_err(|_| invalid())?),
+            "output_price" => model.output_price = Some(value.parse().map_err(|_| invalid())?),
+            "requests_per_minute" => model.requests_per_minute = Some(value.parse().map_err(|_| invalid())?),
+            "tokens_per_minute" => model.tokens_per_minute = Some(value.parse().map_err(|_| invalid())?),
+            "context_strategy" => {
+                model.context_strategy =
+                    toml::Value::String(value.to_lowercase()).try_into::<ContextStrategy>().map_err(|_| invalid())?
+            }
+            "compact_threshold" => model.compact_threshold = Some(value.parse().map_err(|_| invalid())?),
+            "keep_messages" => model.keep_messages = Some(value.parse().map_err(|_| invalid())?),
+            "thinking_budget" => model.thinking_budget = Some(value.parse().map_err(|_| invalid())?),
+            "api_key_header" => model.api_key_header = Some(value.clone()),
+            // A JSON object of header names and values
+            "headers" => model.headers = serde_json::from_str(value).map_err(|_| invalid())?,
+            "api_version" => model.api_version = Some(value.clone()),
+            "url_template" => model.url_template = Some(value.clone()),
+            other => anyhow::bail!(
+                "Model '{}' from the environment: unknown field '{}' (known: {})",
+                name,
+                other,
+                ENV_MODEL_FIELDS.join(", ")
+            ),
+        }
+    }
+    if model.endpoint.is_empty() || model.api_key.is_empty() {
+        anyhow::bail!("Model '{}' from the environment needs an endpoint and an api_key", name);
+    }
+    if model.deployment.is_empty() {
+        model.deployment = name.to_string();
+    }
+    model.model_type = model_type.unwrap_or_else(|| detect_model_type(&model.deployment));
+    Ok(model)
+}
+
+fn load_base_config() -> Result<AppConfig> {
     // Try environment variables first
     if let (Ok(api_key), Ok(endpoint), Ok(deployment)) = (
         std::env::var("AZURE_API_KEY"),
@@ -111,8 +1004,7 @@ pub fn load_config() -> Result<AppConfig> {
             endpoint,
             deployment: deployment.clone(),
             model_type,
-            max_tokens: default_max_tokens(),
-            temperature: default_temperature(),
+            ..Default::default()
         };
 
         let mut models = HashMap::new();
@@ -122,7 +1014,8 @@ pub fn load_config() -> Result<AppConfig> {
             active_model: deployment,
             models,
             github_username: "leonardo-matheus".to_string(),
-            language: Language::default(),
+            language: Language::detect(),
+            ..Default::default()
         });
     }
 
@@ -131,36 +1024,100 @@ pub fn load_config() -> Result<AppConfig> {
     let content = fs::read_to_string(&config_path)
         .with_context(|| format!("Failed to read config from {:?}", config_path))?;
 
-    // Try new format first
-    if let Ok(config) = toml::from_str::<AppConfig>(&content) {
-        return Ok(config);
+    let content = migrate(&content)?;
+    let mut config: AppConfig = toml::from_str(&content).with_context(|| "Failed to parse config file")?;
+    crate::vault::unlock(&mut config)?;
+    Ok(config)
+}
+
+/// Version written by this build. Bump it with a new MIGRATIONS step when
+/// the file's structure changes.
+pub const CONFIG_VERSION: u32 = 2;
+
+/// `MIGRATIONS[n]` upgrades a version-n file to n+1. They edit the TOML
+/// document, so comments and keys this build doesn't know survive.
+const MIGRATIONS: &[fn(&mut toml_edit::DocumentMut)] = &[migrate_single_model, migrate_add_version];
+
+/// Schema version of a config document. Files from before the `version`
+/// field are 1 with a [models] table and 0 in the single-model format.
+pub fn schema_version(doc: &toml_edit::DocumentMut) -> u32 {
+    match doc.get("version").and_then(|v| v.as_integer()) {
+        Some(version) => version.max(0) as u32,
+        None if doc.contains_key("models") => 1,
+        None => 0,
     }
+}
 
-    // Fall back to legacy format
-    let legacy: LegacyConfig = toml::from_str(&content)
-        .with_context(|| "Failed to parse config file")?;
+/// Whether config.toml was written by a newer aicli
+fn is_newer_file() -> bool {
+    fs::read_to_string(get_config_path())
+        .ok()
+        .and_then(|content| content.parse::<toml_edit::DocumentMut>().ok())
+        .is_some_and(|doc| schema_version(&doc) > CONFIG_VERSION)
+}
 
-    let model = ModelConfig {
-        name: legacy.deployment.clone(),
-        api_key: legacy.api_key,
-        endpoint: legacy.endpoint,
-        deployment: legacy.deployment.clone(),
-        model_type: legacy.model_type,
-        max_tokens: legacy.max_tokens,
-        temperature: legacy.temperature,
-    };
+/// Bring config.toml up to CONFIG_VERSION, keeping a copy of the original
+/// as config.toml.v<old>.bak, and return the current content
+fn migrate(content: &str) -> Result<String> {
+    let mut doc: toml_edit::DocumentMut = content.parse().with_context(|| "Failed to parse config file")?;
+    let from = schema_version(&doc);
+    if from > CONFIG_VERSION {
+        anyhow::bail!(
+            "config.toml is version {}, newer than this aicli understands ({}). Run 'aicli upgrade'",
+            from,
+            CONFIG_VERSION
+        );
+    }
+    if from == CONFIG_VERSION {
+        return Ok(content.to_string());
+    }
+
+    for step in &MIGRATIONS[from as usize..] {
+        step(&mut doc);
+    }
+    doc["version"] = toml_edit::value(CONFIG_VERSION as i64);
 
-    let mut models = HashMap::new();
-    models.insert(legacy.deployment.clone(), model);
+    let path = get_config_path();
+    let backup = path.with_extension(format!("toml.v{}.bak", from));
+    if !backup.exists() {
+        fs::write(&backup, content).with_context(|| format!("Failed to back up config to {}", backup.display()))?;
+    }
+    let migrated = doc.to_string();
+    fs::write(&path, &migrated)?;
+    eprintln!(
+        "\x1b[32m✓ Migrated {} from version {} to {} (backup: {})\x1b[0m",
+        path.display(),
+        from,
+        CONFIG_VERSION,
+        backup.display()
+    );
+    Ok(migrated)
+}
 
-    Ok(AppConfig {
-        active_model: legacy.deployment,
-        models,
-        github_username: "leonardo-matheus".to_string(),
-        language: Language::default(),
-    })
+/// 0 to 1: the top-level api_key/endpoint/deployment/... of the first
+/// releases become a [models.<deployment>] table
+fn migrate_single_model(doc: &mut toml_edit::DocumentMut) {
+    let deployment = doc.get("deployment").and_then(|d| d.as_str()).unwrap_or("default").to_string();
+    let mut model = toml_edit::Table::new();
+    model["name"] = toml_edit::value(deployment.clone());
+    for key in ["api_key", "endpoint", "deployment", "model_type", "max_tokens", "temperature"] {
+        if let Some(item) = doc.remove(key) {
+            model[key] = item;
+        }
+    }
+    let mut models = toml_edit::Table::new();
+    models.set_implicit(true);
+    models[deployment.as_str()] = toml_edit::Item::Table(model);
+    doc["models"] = toml_edit::Item::Table(models);
+    doc["active_model"] = toml_edit::value(deployment);
+    if !doc.contains_key("github_username") {
+        doc["github_username"] = toml_edit::value("leonardo-matheus");
+    }
 }
 
+/// 1 to 2: only adds the `version` field
+fn migrate_add_version(_doc: &mut toml_edit::DocumentMut) {}
+
 pub fn save_config(config: &AppConfig) -> Result<()> {
     let config_path = get_config_path();
 
@@ -168,7 +1125,19 @@ pub fn save_config(config: &AppConfig) -> Result<()> {
         fs::create_dir_all(parent)?;
     }
 
-    let content = toml::to_string_pretty(config)?;
+    // Models from the environment stay out of the file
+    let mut stored = config.clone();
+    stored.version = CONFIG_VERSION;
+    stored.models.retain(|_, model| !model.from_env);
+    if stored.get_active_model().is_none() {
+        let mut names: Vec<&String> = stored.models.keys().collect();
+        names.sort();
+        stored.active_model = names.first().map(|n| n.to_string()).unwrap_or_default();
+    }
+    let content = match stored.encryption {
+        Some(_) => toml::to_string_pretty(&crate::vault::lock(&stored)?)?,
+        None => toml::to_string_pretty(&stored)?,
+    };
     fs::write(&config_path, content)?;
 
     Ok(())
@@ -187,51 +1156,109 @@ pub fn detect_model_type(deployment: &str) -> ModelType {
     }
 }
 
+/// Read a line without echoing it, for API keys. Falls back to a plain read
+/// when stdin is not a terminal (piped setup).
+pub fn read_secret(prompt: &str) -> Result<String> {
+    print!("{}", prompt);
+    io::stdout().flush()?;
+
+    if !io::stdin().is_terminal() {
+        let mut line = String::new();
+        io::stdin().read_line(&mut line)?;
+        return Ok(line.trim().to_string());
+    }
+
+    terminal::enable_raw_mode()?;
+    let mut secret = String::new();
+    let result = loop {
+        let event = match event::read() {
+            Ok(event) => event,
+            Err(e) => break Err(e.into()),
+        };
+        let Event::Key(key) = event else { continue };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Enter => break Ok(()),
+            KeyCode::Char('c') if ctrl => break Err(anyhow::anyhow!("Cancelled")),
+            KeyCode::Char('u') if ctrl => secret.clear(),
+            KeyCode::Backspace => {
+                secret.pop();
+            }
+            KeyCode::Char(c) if !ctrl => secret.push(c),
+            _ => {}
+        }
+    };
+    terminal::disable_raw_mode()?;
+    println!();
+
+    result.map(|_| secret.trim().to_string())
+}
+
+/// Only the last 4 characters of a secret, for display
+pub fn mask_secret(secret: &str) -> String {
+    let chars: Vec<char> = secret.chars().collect();
+    if chars.len() <= 8 {
+        return "••••".to_string();
+    }
+    let tail: String = chars[chars.len() - 4..].iter().collect();
+    format!("••••••••{}", tail)
+}
+
 pub async fn setup_config_interactive() -> Result<AppConfig> {
+    let language = Language::detect();
+    let s = Strings::new(language);
+    let title = s.get("setup_title");
+    let padding = 63usize.saturating_sub(text::width(title));
     println!("\x1b[36m╔═══════════════════════════════════════════════════════════════╗\x1b[0m");
-    println!("\x1b[36m║              AICLI Configuration Setup                        ║\x1b[0m");
+    println!("\x1b[36m║{}{}{}║\x1b[0m", " ".repeat(padding / 2), title, " ".repeat(padding - padding / 2));
     println!("\x1b[36m╚═══════════════════════════════════════════════════════════════╝\x1b[0m\n");
 
-    let mut config = load_config().unwrap_or_else(|_| AppConfig {
-        active_model: String::new(),
-        models: HashMap::new(),
-        github_username: "leonardo-matheus".to_string(),
-        language: Language::default(),
-    });
+    let mut config = match load_config() {
+        Ok(config) => config,
+        // Starting over would replace the encrypted secrets, or a newer
+        // aicli's settings
+        Err(e) if crate::vault::is_encrypted_file() || is_newer_file() => return Err(e),
+        Err(_) => AppConfig {
+            active_model: String::new(),
+            models: HashMap::new(),
+            github_username: "leonardo-matheus".to_string(),
+            language,
+            ..Default::default()
+        },
+    };
 
     loop {
-        println!("\x1b[33mAdd a new model configuration:\x1b[0m\n");
+        println!("\x1b[33m{}\x1b[0m\n", s.get("setup_add_model"));
 
-        print!("\x1b[33mModel name (e.g., gpt-4, claude-opus):\x1b[0m ");
+        print!("\x1b[33m{}\x1b[0m ", s.get("setup_model_name"));
         io::stdout().flush()?;
         let mut name = String::new();
         io::stdin().read_line(&mut name)?;
         let name = name.trim().to_string();
 
-        print!("\x1b[33mAzure AI Endpoint URL:\x1b[0m ");
+        print!("\x1b[33m{}\x1b[0m ", s.get("setup_endpoint"));
         io::stdout().flush()?;
         let mut endpoint = String::new();
         io::stdin().read_line(&mut endpoint)?;
         let endpoint = endpoint.trim().to_string();
 
-        print!("\x1b[33mAPI Key:\x1b[0m ");
-        io::stdout().flush()?;
-        let mut api_key = String::new();
-        io::stdin().read_line(&mut api_key)?;
-        let api_key = api_key.trim().to_string();
+        let api_key = read_secret(&format!("\x1b[33m{}\x1b[0m ", s.get("setup_api_key")))?;
 
-        print!("\x1b[33mDeployment/Model ID:\x1b[0m ");
+        print!("\x1b[33m{}\x1b[0m ", s.get("setup_deployment"));
         io::stdout().flush()?;
         let mut deployment = String::new();
         io::stdin().read_line(&mut deployment)?;
         let deployment = deployment.trim().to_string();
 
-        println!("\n\x1b[33mSelect model type:\x1b[0m");
+        println!("\n\x1b[33m{}\x1b[0m", s.get("setup_model_type"));
         println!("  1. Claude (Anthropic)");
         println!("  2. GPT (OpenAI)");
         println!("  3. DeepSeek");
-        println!("  4. Other");
-        print!("\x1b[33mChoice [1-4]:\x1b[0m ");
+        println!("  4. {}", s.get("setup_other"));
+        print!("\x1b[33m{}\x1b[0m ", s.get("setup_choice"));
         io::stdout().flush()?;
         let mut choice = String::new();
         io::stdin().read_line(&mut choice)?;
@@ -243,13 +1270,13 @@ pub async fn setup_config_interactive() -> Result<AppConfig> {
             _ => detect_model_type(&deployment),
         };
 
-        print!("\x1b[33mMax tokens [4096]:\x1b[0m ");
+        print!("\x1b[33m{}\x1b[0m ", s.get("setup_max_tokens"));
         io::stdout().flush()?;
         let mut max_tokens_str = String::new();
         io::stdin().read_line(&mut max_tokens_str)?;
         let max_tokens: u32 = max_tokens_str.trim().parse().unwrap_or(4096);
 
-        print!("\x1b[33mTemperature [0.7]:\x1b[0m ");
+        print!("\x1b[33m{}\x1b[0m ", s.get("setup_temperature"));
         io::stdout().flush()?;
         let mut temp_str = String::new();
         io::stdin().read_line(&mut temp_str)?;
@@ -263,49 +1290,47 @@ pub async fn setup_config_interactive() -> Result<AppConfig> {
             model_type,
             max_tokens,
             temperature,
+            ..Default::default()
         };
 
         config.add_model(model);
-        println!("\n\x1b[32m✓ Model '{}' added!\x1b[0m", name);
+        println!("\n\x1b[32m✓ {}\x1b[0m", s.format("model_added", &[("name", &name)]));
 
-        print!("\n\x1b[33mAdd another model? [y/N]:\x1b[0m ");
+        print!("\n\x1b[33m{}\x1b[0m ", s.get("setup_add_another"));
         io::stdout().flush()?;
         let mut another = String::new();
         io::stdin().read_line(&mut another)?;
-        if !another.trim().to_lowercase().starts_with('y') {
+        if !s.is_yes(&another) {
             break;
         }
         println!();
     }
 
     save_config(&config)?;
-    println!("\n\x1b[32m✓ Configuration saved to {:?}\x1b[0m", get_config_path());
+    println!("\n\x1b[32m✓ {} {:?}\x1b[0m", s.get("setup_saved"), get_config_path());
 
     Ok(config)
 }
 
 pub fn add_model_interactive(config: &mut AppConfig) -> Result<()> {
-    println!("\n\x1b[36m━━━ Add New Model ━━━\x1b[0m\n");
+    let s = Strings::new(config.language);
+    println!("\n\x1b[36m━━━ {} ━━━\x1b[0m\n", s.get("add_model_title"));
 
-    print!("\x1b[33mModel name:\x1b[0m ");
+    print!("\x1b[33m{}\x1b[0m ", s.get("add_model_name"));
     io::stdout().flush()?;
     let mut name = String::new();
     io::stdin().read_line(&mut name)?;
     let name = name.trim().to_string();
 
-    print!("\x1b[33mEndpoint URL:\x1b[0m ");
+    print!("\x1b[33m{}\x1b[0m ", s.get("add_model_endpoint"));
     io::stdout().flush()?;
     let mut endpoint = String::new();
     io::stdin().read_line(&mut endpoint)?;
     let endpoint = endpoint.trim().to_string();
 
-    print!("\x1b[33mAPI Key:\x1b[0m ");
-    io::stdout().flush()?;
-    let mut api_key = String::new();
-    io::stdin().read_line(&mut api_key)?;
-    let api_key = api_key.trim().to_string();
+    let api_key = read_secret(&format!("\x1b[33m{}\x1b[0m ", s.get("setup_api_key")))?;
 
-    print!("\x1b[33mDeployment ID:\x1b[0m ");
+    print!("\x1b[33m{}\x1b[0m ", s.get("add_model_deployment"));
     io::stdout().flush()?;
     let mut deployment = String::new();
     io::stdin().read_line(&mut deployment)?;
@@ -319,13 +1344,12 @@ pub fn add_model_interactive(config: &mut AppConfig) -> Result<()> {
         endpoint,
         deployment,
         model_type,
-        max_tokens: default_max_tokens(),
-        temperature: default_temperature(),
+        ..Default::default()
     };
 
     config.add_model(model);
     save_config(config)?;
-    println!("\x1b[32m✓ Model '{}' added!\x1b[0m\n", name);
+    println!("\x1b[32m✓ {}\x1b[0m\n", s.format("model_added", &[("name", &name)]));
 
     Ok(())
 }
@@ -0,0 +1,169 @@
+//! What the terminal can show. Windows Terminal, VS Code and Unix terminals
+//! take all of aicli's escape sequences; the classic Windows console
+//! (conhost, as opened by PowerShell and cmd) needs virtual terminal
+//! processing switched on, and even then gets 16 colors and no hyperlinks.
+//! Where it can't be switched on, escape sequences are left out, and so
+//! they are when stdout isn't a terminal or `NO_COLOR` is set.
+
+use regex::Regex;
+use std::borrow::Cow;
+use std::io::IsTerminal;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::OnceLock;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Level {
+    /// 256 colors, OSC 8 hyperlinks, scroll regions
+    Full,
+    /// Colors limited to the 16 basic ones, no hyperlinks
+    Basic,
+    /// No escape sequences at all
+    None,
+}
+
+static LEVEL: AtomicU8 = AtomicU8::new(0);
+
+/// Call once at startup, before anything is printed
+pub fn init() {
+    let no_color = std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+    let level = if no_color || !std::io::stdout().is_terminal() { Level::None } else { detect() };
+    LEVEL.store(level as u8, Ordering::Relaxed);
+}
+
+/// `print!` and `println!` with the console's limits applied, for modules
+/// that write their own escape sequences (`use crate::console::println`)
+macro_rules! print {
+    ($($arg:tt)*) => { std::print!("{}", crate::ui::for_console(&format!($($arg)*))) };
+}
+
+macro_rules! println {
+    () => { std::println!() };
+    ($($arg:tt)*) => { std::println!("{}", crate::ui::for_console(&format!($($arg)*))) };
+}
+
+pub(crate) use {print, println};
+
+pub fn level() -> Level {
+    match LEVEL.load(Ordering::Relaxed) {
+        1 => Level::Basic,
+        2 => Level::None,
+        _ => Level::Full,
+    }
+}
+
+#[cfg(windows)]
+fn detect() -> Level {
+    let env = |name: &str| std::env::var(name).unwrap_or_default();
+    // Enables virtual terminal processing on the console as a side effect
+    let vt = crossterm::ansi_support::supports_ansi();
+    if !env("WT_SESSION").is_empty() || env("TERM_PROGRAM") == "vscode" || env("ConEmuANSI") == "ON" {
+        Level::Full
+    } else if vt {
+        Level::Basic
+    } else {
+        Level::None
+    }
+}
+
+#[cfg(not(windows))]
+fn detect() -> Level {
+    Level::Full
+}
+
+/// Terminals known to render OSC 8 hyperlinks
+pub fn supports_hyperlinks() -> bool {
+    let env = |name: &str| std::env::var(name).unwrap_or_default();
+    matches!(env("TERM_PROGRAM").as_str(), "iTerm.app" | "WezTerm" | "vscode" | "Hyper" | "ghostty")
+        || !env("WT_SESSION").is_empty()
+        || !env("KITTY_WINDOW_ID").is_empty()
+        || env("VTE_VERSION").parse::<u32>().is_ok_and(|v| v >= 5000)
+}
+
+/// `text` as the console can show it
+pub fn adapt(text: &str) -> Cow<'_, str> {
+    match level() {
+        Level::Full => Cow::Borrowed(text),
+        _ if !text.contains('\x1b') => Cow::Borrowed(text),
+        Level::None => Cow::Owned(crate::text::strip_ansi(text)),
+        Level::Basic => Cow::Owned(basic_colors(&without_hyperlinks(text))),
+    }
+}
+
+/// OSC 8 links become their text
+fn without_hyperlinks(text: &str) -> Cow<'_, str> {
+    static LINK: OnceLock<Regex> = OnceLock::new();
+    let link = LINK.get_or_init(|| Regex::new(r"\x1b\]8;[^\x07\x1b]*;[^\x07\x1b]*(\x07|\x1b\\)").unwrap());
+    link.replace_all(text, "")
+}
+
+/// 256-color and truecolor SGR codes mapped to the nearest of the 16 basic colors
+fn basic_colors(text: &str) -> String {
+    static SGR: OnceLock<Regex> = OnceLock::new();
+    let sgr = SGR.get_or_init(|| Regex::new(r"\x1b\[([0-9;]*)m").unwrap());
+    sgr.replace_all(text, |caps: &regex::Captures| {
+        let params: Vec<u16> = caps[1].split(';').map(|p| p.parse().unwrap_or(0)).collect();
+        let mut out = Vec::new();
+        let mut i = 0;
+        while i < params.len() {
+            match (params[i], params.get(i + 1)) {
+                (kind @ (38 | 48), Some(5)) => {
+                    let color = params.get(i + 2).copied().unwrap_or(7) as u8;
+                    out.push(basic(xterm_rgb(color), kind == 48).to_string());
+                    i += 3;
+                }
+                (kind @ (38 | 48), Some(2)) => {
+                    let channel = |n: usize| params.get(i + n).copied().unwrap_or(0).min(255) as u8;
+                    out.push(basic((channel(2), channel(3), channel(4)), kind == 48).to_string());
+                    i += 5;
+                }
+                (other, _) => {
+                    out.push(other.to_string());
+                    i += 1;
+                }
+            }
+        }
+        format!("\x1b[{}m", out.join(";"))
+    })
+    .into_owned()
+}
+
+/// The color an xterm 256-color index stands for
+fn xterm_rgb(index: u8) -> (u8, u8, u8) {
+    const BASIC: [(u8, u8, u8); 16] = [
+        (0, 0, 0), (128, 0, 0), (0, 128, 0), (128, 128, 0), (0, 0, 128), (128, 0, 128), (0, 128, 128), (192, 192, 192),
+        (128, 128, 128), (255, 0, 0), (0, 255, 0), (255, 255, 0), (0, 0, 255), (255, 0, 255), (0, 255, 255), (255, 255, 255),
+    ];
+    match index {
+        0..=15 => BASIC[index as usize],
+        16..=231 => {
+            let level = |n: u8| if n == 0 { 0 } else { 55 + n * 40 };
+            let n = index - 16;
+            (level(n / 36), level(n / 6 % 6), level(n % 6))
+        }
+        _ => {
+            let gray = 8 + (index - 232) * 10;
+            (gray, gray, gray)
+        }
+    }
+}
+
+/// The SGR code of the nearest basic color: 30-37 and 90-97, +10 for backgrounds
+fn basic((r, g, b): (u8, u8, u8), background: bool) -> u16 {
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let code = if max - min < 40 {
+        // Grays: black, bright black, white, bright white
+        match max {
+            0..=60 => 30,
+            61..=150 => 90,
+            151..=220 => 37,
+            _ => 97,
+        }
+    } else {
+        // The channels that are near the strongest one make up the hue
+        let on = |c: u8| c as u16 * 2 > max as u16 + min as u16;
+        let hue = on(r) as u16 | (on(g) as u16) << 1 | (on(b) as u16) << 2;
+        if max > 170 { 90 + hue } else { 30 + hue }
+    };
+    if background { code + 10 } else { code }
+}
@@ -0,0 +1,222 @@
+//! `/context`: every part of the next request with its estimated tokens,
+//! the same rough 4 characters a token as the status line. Pinned files,
+//! turns of the conversation, the followed log and what waits for the next
+//! message can be dropped from it.
+
+use std::ops::Range;
+
+use crate::client::Message;
+use crate::console::println;
+use crate::history;
+use crate::input::format_tokens;
+use crate::paths;
+use crate::text;
+use crate::ui::{MenuItem, UI};
+
+const SUMMARY_PREFIX: &str = "[Conversation Summary";
+
+/// What the next request is made of, as the chat holds it
+pub struct Request<'a> {
+    pub system: String,
+    pub tools: usize,
+    /// Bytes of the tool definitions sent
+    pub tools_chars: usize,
+    pub memory: String,
+    pub instructions: String,
+    pub pinned: &'a [String],
+    pub tail: Option<String>,
+    pub messages: &'a [Message],
+    pub quotes: &'a [String],
+    pub piped: Option<&'a str>,
+}
+
+/// A part that can be dropped
+#[derive(Clone)]
+pub enum Part {
+    Pinned(String),
+    Tail,
+    /// Messages of the conversation: a prompt, the tool results and answers after it
+    Turn(Range<usize>),
+    Quote(usize),
+    Piped,
+}
+
+struct Item {
+    group: &'static str,
+    label: String,
+    detail: String,
+    tokens: usize,
+    part: Option<Part>,
+}
+
+fn tokens(text: &str) -> usize {
+    text.len() / 4
+}
+
+/// The conversation split at each prompt the user wrote
+fn turns(messages: &[Message]) -> Vec<Range<usize>> {
+    let mut turns: Vec<Range<usize>> = Vec::new();
+    for (index, message) in messages.iter().enumerate() {
+        let prompt = message.role == "user" && !history::is_tool_results(message);
+        match turns.last_mut() {
+            Some(turn) if !prompt => turn.end = index + 1,
+            _ => turns.push(index..index + 1),
+        }
+    }
+    turns
+}
+
+/// The @files sent with a prompt, by the headers read_file_context writes
+fn attached_files(prompt: &str) -> Vec<&str> {
+    prompt
+        .lines()
+        .filter_map(|line| line.strip_prefix("--- File: ")?.strip_suffix(" ---"))
+        .collect()
+}
+
+fn items(request: &Request) -> Vec<Item> {
+    let fixed = |label: &str, detail: String, text_tokens: usize| Item {
+        group: "System prompt",
+        label: label.to_string(),
+        detail,
+        tokens: text_tokens,
+        part: None,
+    };
+    let mut items = vec![
+        fixed("Instructions", "aicli's own, with the OS, date and directory".to_string(), tokens(&request.system)),
+        fixed("Tool definitions", format!("{} tools", request.tools), request.tools_chars / 4),
+    ];
+    if !request.memory.is_empty() {
+        items.push(fixed("Memory", "/memory lists and deletes the facts".to_string(), tokens(&request.memory)));
+    }
+    if !request.instructions.is_empty() {
+        items.push(fixed("AICLI.md", "project instructions".to_string(), tokens(&request.instructions)));
+    }
+    for path in request.pinned {
+        let display = paths::display(std::path::Path::new(path));
+        let content = crate::input::read_file_context(std::slice::from_ref(&display));
+        items.push(Item {
+            group: "Pinned files",
+            label: display,
+            detail: String::new(),
+            tokens: tokens(&content),
+            part: Some(Part::Pinned(path.clone())),
+        });
+    }
+    if let Some(tail) = &request.tail {
+        items.push(Item {
+            group: "System prompt",
+            label: "Followed log".to_string(),
+            detail: "/tail".to_string(),
+            tokens: tokens(tail),
+            part: Some(Part::Tail),
+        });
+    }
+
+    for (number, turn) in turns(request.messages).into_iter().enumerate() {
+        let messages = &request.messages[turn.clone()];
+        let prompt = messages[0].content.as_text();
+        let size: usize = messages.iter().map(|m| tokens(&m.content.as_text())).sum();
+        let mut detail = vec![format!("{} message(s)", messages.len())];
+        let files = attached_files(&prompt);
+        if !files.is_empty() {
+            detail.push(format!("with {}", files.join(", ")));
+        }
+        let tools: usize = messages.iter().filter(|m| history::is_tool_results(m)).count();
+        if tools > 0 {
+            detail.push(format!("{} tool round(s)", tools));
+        }
+        let label = if prompt.starts_with(SUMMARY_PREFIX) {
+            "Summary of earlier messages".to_string()
+        } else {
+            let preview = history::prompt_text(&messages[0]).split_whitespace().collect::<Vec<_>>().join(" ");
+            format!("{:>3}. {}", number + 1, text::truncate(&preview, 60))
+        };
+        items.push(Item { group: "Conversation", label, detail: detail.join(" · "), tokens: size, part: Some(Part::Turn(turn)) });
+    }
+
+    for (index, quote) in request.quotes.iter().enumerate() {
+        let preview = quote.split_whitespace().collect::<Vec<_>>().join(" ");
+        items.push(Item {
+            group: "With the next message",
+            label: format!("Quote: {}", text::truncate(&preview, 60)),
+            detail: String::new(),
+            tokens: tokens(quote),
+            part: Some(Part::Quote(index)),
+        });
+    }
+    if let Some(piped) = request.piped {
+        items.push(Item {
+            group: "With the next message",
+            label: "Piped input".to_string(),
+            detail: String::new(),
+            tokens: tokens(piped),
+            part: Some(Part::Piped),
+        });
+    }
+    items
+}
+
+fn title(items: &[Item], context_max: usize) -> String {
+    let total: usize = items.iter().map(|item| item.tokens).sum();
+    let percent = (total * 100).checked_div(context_max).unwrap_or(0);
+    format!("Next request: ~{} tokens, {}% of {}", format_tokens(total), percent, format_tokens(context_max))
+}
+
+/// Show the parts of the next request. In a terminal the list is a menu,
+/// and the part picked is returned when it can be dropped and the user
+/// confirms; elsewhere it is printed.
+pub fn browse(request: &Request, context_max: usize, selected: usize, ui: &UI) -> Option<(Part, usize)> {
+    let items = items(request);
+    let title = title(&items, context_max);
+
+    if !ui.menus_interactive() {
+        println!();
+        println!("  \x1b[1;37m{}\x1b[0m", title);
+        let mut group = "";
+        for item in &items {
+            if item.group != group {
+                group = item.group;
+                println!("\n    \x1b[38;5;245m{}\x1b[0m", group);
+            }
+            println!(
+                "    \x1b[38;5;75m{:>7}\x1b[0m  {} \x1b[38;5;245m{}\x1b[0m",
+                format_tokens(item.tokens),
+                item.label,
+                item.detail
+            );
+        }
+        println!();
+        return None;
+    }
+
+    let menu: Vec<MenuItem> = items
+        .iter()
+        .map(|item| {
+            let detail = match item.detail.is_empty() {
+                true => format!("~{} tokens", format_tokens(item.tokens)),
+                false => format!("~{} tokens · {}", format_tokens(item.tokens), item.detail),
+            };
+            MenuItem::new(&item.label, detail, false).in_group(item.group)
+        })
+        .collect();
+    let mut selected = selected;
+    loop {
+        selected = ui.pick(&title, &menu, selected)?;
+        let item = &items[selected];
+        let Some(part) = &item.part else {
+            ui.print_info(&format!("{} is always sent", item.label));
+            continue;
+        };
+        let what = match part {
+            Part::Pinned(_) => format!("Unpin {}", item.label),
+            Part::Tail => "Stop following the log".to_string(),
+            Part::Turn(turn) => format!("Drop these {} message(s) from the conversation", turn.len()),
+            Part::Quote(_) => "Drop the quote".to_string(),
+            Part::Piped => "Drop the piped input".to_string(),
+        };
+        if ui.confirm(&format!("{} (~{} tokens)?", what, format_tokens(item.tokens))) {
+            return Some((part.clone(), selected));
+        }
+    }
+}
@@ -0,0 +1,315 @@
+use crate::config::{DatabaseConfig, DatabaseKind, ToolsConfig};
+use crate::text;
+use anyhow::Result;
+use reqwest::Url;
+use serde_json::Value;
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+use std::sync::OnceLock;
+
+const DEFAULT_MAX_ROWS: usize = 100;
+const MAX_CELL_WIDTH: usize = 60;
+
+/// Statements that can only read; anything else is rejected before it
+/// reaches the database, which is also opened read-only where the client allows.
+const READ_ONLY_PREFIXES: &[&str] = &["select", "with", "explain", "show", "describe", "desc", "pragma", "values"];
+
+/// Whether a backslash appears outside the string literals, quoted
+/// identifiers and dollar quotes of `query`. The mysql and psql clients read
+/// `\!`, `\o` and the like there as their own commands (a shell, a file
+/// write), so such a query can't be trusted to stay a query. Quotes and
+/// comments are read the way that client reads them: backslash escapes in
+/// every MySQL string but only in `E'...'` ones for PostgreSQL, `#` and
+/// `-- ` comments for one, nested `/* */` and `$tag$` quotes for the other.
+fn has_client_command(query: &str, kind: DatabaseKind) -> bool {
+    let mysql = kind == DatabaseKind::Mysql;
+    let chars: Vec<char> = query.chars().collect();
+    let at = |i: usize| chars.get(i).copied().unwrap_or('\0');
+    let is_ident = |c: char| c.is_alphanumeric() || c == '_' || c == '$';
+    let line_comment = |i: usize| match at(i) {
+        '-' => at(i + 1) == '-' && (!mysql || at(i + 2).is_whitespace() || at(i + 2) == '\0'),
+        '#' => mysql,
+        _ => false,
+    };
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            '\\' => return true,
+            '\'' | '"' | '`' => {
+                let escapes = if mysql {
+                    c != '`'
+                } else {
+                    c == '\'' && at(i.wrapping_sub(1)).eq_ignore_ascii_case(&'e') && !is_ident(at(i.wrapping_sub(2)))
+                };
+                i += 1;
+                while i < chars.len() && chars[i] != c {
+                    i += if escapes && chars[i] == '\\' { 2 } else { 1 };
+                }
+            }
+            '-' | '#' if line_comment(i) => {
+                while i < chars.len() && chars[i] != '\n' {
+                    if chars[i] == '\\' {
+                        return true;
+                    }
+                    i += 1;
+                }
+            }
+            // MySQL runs `/*! ... */` and reads `/*+ ... */` hints, so they're query text
+            '/' if at(i + 1) == '*' && !(mysql && matches!(at(i + 2), '!' | '+')) => {
+                let mut depth = 0;
+                while i < chars.len() {
+                    if chars[i] == '\\' {
+                        return true;
+                    } else if chars[i] == '/' && at(i + 1) == '*' && (depth == 0 || !mysql) {
+                        depth += 1;
+                        i += 1;
+                    } else if chars[i] == '*' && at(i + 1) == '/' {
+                        depth -= 1;
+                        i += 1;
+                        if depth == 0 {
+                            break;
+                        }
+                    }
+                    i += 1;
+                }
+            }
+            '$' if !mysql && !is_ident(at(i.wrapping_sub(1))) && !at(i + 1).is_ascii_digit() => {
+                let tag_end = (i + 1..chars.len()).find(|&j| !is_ident(chars[j]) || chars[j] == '$');
+                if let Some(end) = tag_end.filter(|&end| chars[end] == '$') {
+                    let tag: String = chars[i..=end].iter().collect();
+                    let rest: String = chars[end + 1..].iter().collect();
+                    match rest.find(&tag) {
+                        Some(close) => i = end + rest[..close].chars().count() + tag.chars().count(),
+                        None => return false,
+                    }
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    false
+}
+
+/// Functions and clauses that reach the file system of the client or the
+/// server from a plain SELECT: the sqlite3 shell's `writefile()`, `edit()`
+/// and the rest, server file reads and MySQL's `INTO OUTFILE`
+fn file_access() -> &'static regex::Regex {
+    static FILE_ACCESS: OnceLock<regex::Regex> = OnceLock::new();
+    FILE_ACCESS.get_or_init(|| {
+        regex::Regex::new(
+            r#"(?is)["`\[]?\b(writefile|readfile|edit|load_extension|load_file|pg_read_file|pg_read_binary_file|lo_import|lo_export)\b["`\]]?(\s|/\*.*?\*/|--[^\n]*\n)*\(|\b(outfile|dumpfile)\b"#,
+        )
+        .unwrap()
+    })
+}
+
+fn validate_query(query: &str, kind: DatabaseKind) -> Result<String> {
+    let query = query.trim().trim_end_matches(';').trim();
+    if query.contains(';') {
+        anyhow::bail!("Only a single statement is allowed");
+    }
+    if has_client_command(query, kind) {
+        anyhow::bail!("Backslashes are only allowed inside string literals");
+    }
+    if let Some(found) = file_access().captures(query) {
+        let name = found.get(1).or_else(|| found.get(3)).map_or("", |m| m.as_str());
+        anyhow::bail!("'{}' is not allowed: queries may not read or write files", name);
+    }
+
+    let first = query
+        .split_whitespace()
+        .next()
+        .unwrap_or_default()
+        .to_lowercase();
+    if !READ_ONLY_PREFIXES.contains(&first.as_str()) {
+        anyhow::bail!("Only read-only queries are allowed (SELECT, WITH, EXPLAIN, SHOW, DESCRIBE, PRAGMA)");
+    }
+
+    Ok(query.to_string())
+}
+
+/// Fetch one row more than requested so we can tell the model there are more
+fn limit_query(query: &str, max_rows: usize) -> String {
+    let lower = query.to_lowercase();
+    if lower.starts_with("select") || lower.starts_with("with") {
+        format!("SELECT * FROM ({}) AS aicli_q LIMIT {}", query, max_rows + 1)
+    } else {
+        query.to_string()
+    }
+}
+
+fn build_command(db: &DatabaseConfig, query: &str) -> Result<Command> {
+    let cmd = match db.kind {
+        DatabaseKind::Sqlite => {
+            let path = db.url.trim_start_matches("sqlite://");
+            let mut cmd = Command::new("sqlite3");
+            // -safe also turns off the shell's file functions and extensions
+            cmd.args(["-safe", "-readonly", "-bail", "-header", "-separator", "\t", path, query]);
+            cmd
+        }
+        DatabaseKind::Postgres => {
+            let mut cmd = Command::new("psql");
+            // Keep the password off the command line, as for MySQL
+            let mut url = db.url.clone();
+            if let Ok(mut parsed) = Url::parse(&db.url) {
+                if let Some(password) = parsed.password() {
+                    cmd.env("PGPASSWORD", percent_decode(password));
+                    let _ = parsed.set_password(None);
+                    url = parsed.to_string();
+                }
+            }
+            cmd.args([url.as_str(), "-X", "-q", "-A", "-F", "\t", "-P", "footer=off", "-v", "ON_ERROR_STOP=1", "-c", query]);
+            cmd.env("PGOPTIONS", "-c default_transaction_read_only=on");
+            cmd
+        }
+        DatabaseKind::Mysql => {
+            let url = Url::parse(&db.url)
+                .map_err(|e| anyhow::anyhow!("Invalid MySQL URL: {}", e))?;
+            let mut cmd = Command::new("mysql");
+            cmd.arg("--batch")
+                .arg("--init-command=SET SESSION TRANSACTION READ ONLY")
+                .arg(format!("--host={}", url.host_str().unwrap_or("localhost")))
+                .arg(format!("--port={}", url.port().unwrap_or(3306)));
+            if !url.username().is_empty() {
+                cmd.arg(format!("--user={}", url.username()));
+            }
+            // Keep the password off the command line
+            if let Some(password) = url.password() {
+                cmd.env("MYSQL_PWD", percent_decode(password));
+            }
+            let database = url.path().trim_start_matches('/');
+            if !database.is_empty() {
+                cmd.arg(database);
+            }
+            // The query goes in on stdin (see run_client)
+            cmd
+        }
+    };
+    Ok(cmd)
+}
+
+/// `%XX` escapes of a URL's user info, which the database clients want decoded
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes.get(i + 1..i + 3).and_then(|h| u8::from_str_radix(std::str::from_utf8(h).ok()?, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Run the client; mysql reads the query in batch mode from stdin rather
+/// than from `-e` on its command line
+fn run_client(db: &DatabaseConfig, query: &str) -> Result<Output> {
+    let mut cmd = build_command(db, query)?;
+    let output = if db.kind == DatabaseKind::Mysql {
+        let mut child = cmd.stdin(Stdio::piped()).stdout(Stdio::piped()).stderr(Stdio::piped()).spawn()?;
+        if let Some(mut stdin) = child.stdin.take() {
+            writeln!(stdin, "{};", query)?;
+        }
+        child.wait_with_output()?
+    } else {
+        cmd.output()?
+    };
+    Ok(output)
+}
+
+/// Render tab-separated client output as an aligned text table
+fn format_table(raw: &str, max_rows: usize) -> String {
+    let mut lines = raw.lines().filter(|l| !l.is_empty());
+    let Some(header) = lines.next() else {
+        return "Query returned no rows".to_string();
+    };
+
+    let header: Vec<String> = header.split('\t').map(|c| text::truncate(c, MAX_CELL_WIDTH)).collect();
+    let mut rows: Vec<Vec<String>> = lines
+        .map(|l| l.split('\t').map(|c| text::truncate(c, MAX_CELL_WIDTH)).collect())
+        .collect();
+
+    let more = rows.len() > max_rows;
+    rows.truncate(max_rows);
+
+    let mut widths: Vec<usize> = header.iter().map(|h| text::width(h)).collect();
+    for row in &rows {
+        for (i, cell) in row.iter().enumerate() {
+            if i < widths.len() {
+                widths[i] = widths[i].max(text::width(cell));
+            }
+        }
+    }
+
+    let format_row = |cells: &[String]| -> String {
+        cells
+            .iter()
+            .enumerate()
+            .map(|(i, c)| text::pad(c, widths.get(i).copied().unwrap_or(0)))
+            .collect::<Vec<_>>()
+            .join(" | ")
+            .trim_end()
+            .to_string()
+    };
+
+    let mut result = format_row(&header);
+    result.push('\n');
+    result.push_str(&widths.iter().map(|w| "-".repeat(*w)).collect::<Vec<_>>().join("-+-"));
+    for row in &rows {
+        result.push('\n');
+        result.push_str(&format_row(row));
+    }
+
+    result.push_str(&format!("\n\n({} row{})", rows.len(), if rows.len() == 1 { "" } else { "s" }));
+    if more {
+        result.push_str(&format!(" - more rows available, showing the first {}", max_rows));
+    }
+    result
+}
+
+/// Run a read-only query against a database defined in [tools.databases]
+pub fn database_query(input: &Value, config: &ToolsConfig) -> Result<String> {
+    if config.databases.is_empty() {
+        anyhow::bail!("No databases configured. Add [tools.databases.<name>] to ~/.aicli/config.toml");
+    }
+
+    let mut names: Vec<&str> = config.databases.keys().map(|k| k.as_str()).collect();
+    names.sort();
+
+    let name = input
+        .get("database")
+        .and_then(|d| d.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing 'database' parameter. Configured: {}", names.join(", ")))?;
+    let query = input
+        .get("query")
+        .and_then(|q| q.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing 'query' parameter"))?;
+    let max_rows = input
+        .get("max_rows")
+        .and_then(|m| m.as_u64())
+        .map(|m| m as usize)
+        .unwrap_or(DEFAULT_MAX_ROWS);
+
+    let db = config.databases.get(name).ok_or_else(|| {
+        anyhow::anyhow!("Unknown database '{}'. Configured: {}", name, names.join(", "))
+    })?;
+
+    let query = limit_query(&validate_query(query, db.kind)?, max_rows);
+    let output = run_client(db, &query).map_err(|e| anyhow::anyhow!("Could not run {} client: {}", db.kind, e))?;
+
+    if !output.status.success() {
+        anyhow::bail!("{}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+
+    Ok(format_table(&String::from_utf8_lossy(&output.stdout), max_rows))
+}
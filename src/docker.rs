@@ -0,0 +1,118 @@
+use crate::config::DockerConfig;
+use anyhow::Result;
+use serde_json::Value;
+use std::process::Command;
+
+const DEFAULT_LOG_LINES: u64 = 200;
+const MAX_LOG_LINES: u64 = 2000;
+
+fn ensure_enabled(config: &DockerConfig) -> Result<()> {
+    if !config.enabled {
+        anyhow::bail!("Docker tools are disabled. Set [tools.docker] enabled = true in ~/.aicli/config.toml");
+    }
+    Ok(())
+}
+
+/// Containers are matched by name against glob patterns; an empty list allows all
+fn ensure_container_allowed(config: &DockerConfig, container: &str) -> Result<()> {
+    if config.containers.is_empty() {
+        return Ok(());
+    }
+    let allowed = config.containers.iter().any(|p| {
+        glob::Pattern::new(p)
+            .map(|pattern| pattern.matches(container))
+            .unwrap_or(p == container)
+    });
+    if !allowed {
+        anyhow::bail!(
+            "Container '{}' is not in [tools.docker] containers ({})",
+            container,
+            config.containers.join(", ")
+        );
+    }
+    Ok(())
+}
+
+fn container_param(input: &Value) -> Result<&str> {
+    let container = input
+        .get("container")
+        .and_then(|c| c.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing 'container' parameter"))?;
+    // Stop flags sneaking in as the container name
+    if container.starts_with('-') {
+        anyhow::bail!("Invalid container name '{}'", container);
+    }
+    Ok(container)
+}
+
+fn run_docker(args: &[&str]) -> Result<String> {
+    let output = Command::new("docker")
+        .args(args)
+        .output()
+        .map_err(|e| anyhow::anyhow!("Could not run docker: {}", e))?;
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    if !output.status.success() && stdout.trim().is_empty() {
+        anyhow::bail!("{}", stderr.trim());
+    }
+
+    let mut result = stdout.to_string();
+    if !stderr.is_empty() {
+        if !result.is_empty() {
+            result.push('\n');
+        }
+        result.push_str(&stderr);
+    }
+    if result.trim().is_empty() {
+        result = "(no output)".to_string();
+    }
+    Ok(result)
+}
+
+pub fn docker_ps(input: &Value, config: &DockerConfig) -> Result<String> {
+    ensure_enabled(config)?;
+
+    let mut args = vec!["ps", "--format", "table {{.Names}}\t{{.Image}}\t{{.Status}}\t{{.Ports}}"];
+    if input.get("all").and_then(|a| a.as_bool()).unwrap_or(false) {
+        args.push("--all");
+    }
+    run_docker(&args)
+}
+
+pub fn docker_logs(input: &Value, config: &DockerConfig) -> Result<String> {
+    ensure_enabled(config)?;
+    let container = container_param(input)?;
+    ensure_container_allowed(config, container)?;
+
+    let lines = input
+        .get("lines")
+        .and_then(|l| l.as_u64())
+        .unwrap_or(DEFAULT_LOG_LINES)
+        .min(MAX_LOG_LINES)
+        .to_string();
+
+    let mut args = vec!["logs", "--tail", lines.as_str()];
+    if let Some(since) = input.get("since").and_then(|s| s.as_str()) {
+        args.extend(["--since", since]);
+    }
+    args.push(container);
+    run_docker(&args)
+}
+
+pub fn docker_exec(input: &Value, config: &DockerConfig) -> Result<String> {
+    ensure_enabled(config)?;
+    if !config.allow_exec {
+        anyhow::bail!("docker_exec is disabled. Set [tools.docker] allow_exec = true to allow it");
+    }
+    let container = container_param(input)?;
+    ensure_container_allowed(config, container)?;
+
+    let command = input
+        .get("command")
+        .and_then(|c| c.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing 'command' parameter"))?;
+
+    run_docker(&["exec", container, "sh", "-c", command])
+}
@@ -0,0 +1,345 @@
+use anyhow::Result;
+use std::io::IsTerminal;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::client::{ApiError, AzureClient};
+use crate::config::{self, AppConfig, ModelConfig};
+use crate::console::println;
+use crate::text;
+
+/// How long a model probe may take before the endpoint counts as unreachable
+const PROBE_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(PartialEq)]
+enum Status {
+    Ok,
+    Warn,
+    Fail,
+}
+
+struct Check {
+    name: String,
+    status: Status,
+    detail: String,
+    fix: Option<String>,
+}
+
+impl Check {
+    fn ok(name: &str, detail: impl Into<String>) -> Self {
+        Self { name: name.to_string(), status: Status::Ok, detail: detail.into(), fix: None }
+    }
+
+    fn warn(name: &str, detail: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self { name: name.to_string(), status: Status::Warn, detail: detail.into(), fix: Some(fix.into()) }
+    }
+
+    fn fail(name: &str, detail: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self { name: name.to_string(), status: Status::Fail, detail: detail.into(), fix: Some(fix.into()) }
+    }
+}
+
+/// `aicli doctor [--offline]`: check the config, every model's endpoint, the
+/// installation and the terminal, and print a fix for each problem. Exits
+/// with status 1 when any check fails so scripts can gate on it.
+pub async fn run(offline: bool) -> Result<()> {
+    println!("\n\x1b[1;37m  AICLI Doctor\x1b[0m\n");
+
+    let mut checks = Vec::new();
+    let config = check_config(&mut checks);
+    if let Some(config) = &config {
+        check_models(config, offline, &mut checks).await;
+        check_tools(config, &mut checks);
+    }
+    check_install(&mut checks);
+    check_terminal(&mut checks);
+
+    let width = checks.iter().map(|c| text::width(&c.name)).max().unwrap_or(0);
+    for check in &checks {
+        let icon = match check.status {
+            Status::Ok => "\x1b[38;5;84m✓\x1b[0m",
+            Status::Warn => "\x1b[38;5;228m!\x1b[0m",
+            Status::Fail => "\x1b[38;5;203m✗\x1b[0m",
+        };
+        println!("  {} {}  {}", icon, text::pad(&check.name, width), check.detail);
+        if let Some(fix) = &check.fix {
+            println!("    {}  \x1b[38;5;245m→ {}\x1b[0m", " ".repeat(width), fix);
+        }
+    }
+
+    let failed = checks.iter().filter(|c| c.status == Status::Fail).count();
+    let warned = checks.iter().filter(|c| c.status == Status::Warn).count();
+    println!();
+    if failed > 0 {
+        println!("  \x1b[38;5;203m{} check(s) failed\x1b[0m, {} warning(s)\n", failed, warned);
+        std::process::exit(1);
+    }
+    println!("  \x1b[38;5;84mAll checks passed\x1b[0m{}\n", if warned > 0 { format!(" ({} warning(s))", warned) } else { String::new() });
+    Ok(())
+}
+
+fn check_config(checks: &mut Vec<Check>) -> Option<AppConfig> {
+    let path = config::get_config_path();
+    let from_env = ["AZURE_API_KEY", "AZURE_ENDPOINT", "AZURE_DEPLOYMENT"]
+        .iter()
+        .all(|v| std::env::var(v).is_ok());
+
+    if let Some(policy) = crate::policy::get() {
+        let path = crate::policy::get_policy_path();
+        checks.push(Check::ok("Policy", format!("{} ({})", path.display(), crate::policy::summary(policy))));
+    }
+
+    if let Some(loaded) = crate::dotenv::loaded().filter(|l| !l.files.is_empty()) {
+        let files: Vec<String> = loaded.files.iter().map(|f| crate::paths::display(f)).collect();
+        let detail = format!("{} ({} variable(s) set)", files.join(", "), loaded.variables.len());
+        if loaded.skipped.is_empty() {
+            checks.push(Check::ok(".env", detail));
+        } else {
+            checks.push(Check::warn(".env", detail, format!("Remove {}; a .env may not set them", loaded.skipped.join(", "))));
+        }
+    }
+
+    match config::env_models() {
+        Ok(models) if !models.is_empty() => {
+            let names: Vec<&str> = models.iter().map(|m| m.name.as_str()).collect();
+            checks.push(Check::ok("Env models", names.join(", ")));
+        }
+        Ok(_) => {}
+        Err(e) => {
+            checks.push(Check::fail("Env models", e.to_string(), "Fix AICLI_MODELS or the AICLI_MODEL_<NAME>__<FIELD> variables"));
+            return None;
+        }
+    }
+
+    if !path.exists() {
+        if from_env {
+            checks.push(Check::ok("Config", "using AZURE_API_KEY/AZURE_ENDPOINT/AZURE_DEPLOYMENT"));
+            return config::load_config().ok();
+        }
+        if let Some(config) = config::load_config().ok().filter(|c| !c.models.is_empty()) {
+            checks.push(Check::ok("Config", "no config file; using the models from the environment"));
+            return Some(config);
+        }
+        checks.push(Check::fail("Config", format!("{} not found", path.display()), "Run `aicli --config` to create it"));
+        return None;
+    }
+
+    let content = match std::fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) => {
+            checks.push(Check::fail("Config", format!("cannot read {}: {}", path.display(), e), "Check the file's permissions"));
+            return None;
+        }
+    };
+    let version = match content.parse::<toml_edit::DocumentMut>() {
+        Ok(doc) => config::schema_version(&doc),
+        Err(e) => {
+            let message = e.to_string().lines().next().unwrap_or_default().to_string();
+            checks.push(Check::fail("Config", format!("invalid TOML: {}", message), format!("Fix {} (see config.example.toml)", path.display())));
+            return None;
+        }
+    };
+    if version > config::CONFIG_VERSION {
+        checks.push(Check::fail(
+            "Config",
+            format!("version {} is newer than this aicli ({})", version, config::CONFIG_VERSION),
+            "Run `aicli upgrade`",
+        ));
+        return None;
+    }
+
+    // Migrates older files, with a backup
+    let config = match config::load_config() {
+        Ok(config) => config,
+        Err(e) => {
+            let message = format!("{:#}", e).lines().next().unwrap_or_default().to_string();
+            checks.push(Check::fail("Config", message, format!("Fix {} (see config.example.toml)", path.display())));
+            return None;
+        }
+    };
+    if from_env {
+        checks.push(Check::warn("Config", format!("{} is ignored", path.display()), "Unset AZURE_API_KEY/AZURE_ENDPOINT/AZURE_DEPLOYMENT to use the file"));
+    } else {
+        checks.push(Check::ok("Config", path.display().to_string()));
+    }
+
+    if config.get_active_model().is_none() {
+        let mut names: Vec<&str> = config.models.keys().map(|k| k.as_str()).collect();
+        names.sort();
+        checks.push(Check::fail(
+            "Active model",
+            format!("'{}' is not defined", config.active_model),
+            format!("Set active_model to one of: {}", names.join(", ")),
+        ));
+    }
+    Some(config)
+}
+
+async fn check_models(config: &AppConfig, offline: bool, checks: &mut Vec<Check>) {
+    let mut names: Vec<&String> = config.models.keys().collect();
+    names.sort();
+    for name in names {
+        let model = &config.models[name];
+        let label = format!("Model {}", name);
+        if let Err(problem) = validate_model(model) {
+            checks.push(Check::fail(&label, problem.0, problem.1));
+            continue;
+        }
+        if offline {
+            checks.push(Check::ok(&label, format!("{} (not contacted)", model.endpoint)));
+            continue;
+        }
+        checks.push(probe_model(&label, model).await);
+    }
+}
+
+/// Problems visible without a request: (what is wrong, how to fix it)
+fn validate_model(model: &ModelConfig) -> std::result::Result<(), (String, String)> {
+    if model.api_key.trim().is_empty() {
+        return Err(("no api_key".to_string(), format!("Set api_key under [models.{}]", model.name)));
+    }
+    match reqwest::Url::parse(&model.endpoint) {
+        Ok(url) if url.scheme() == "https" || url.host_str().is_some_and(|h| h == "localhost" || h == "127.0.0.1") => {}
+        Ok(_) => return Err((format!("endpoint is not https: {}", model.endpoint), "Use the https:// endpoint from the Azure portal".to_string())),
+        Err(e) => return Err((format!("invalid endpoint '{}': {}", model.endpoint, e), "Copy the endpoint URL from the Azure portal (Keys and Endpoint)".to_string())),
+    }
+    if model.deployment.trim().is_empty() {
+        return Err(("no deployment".to_string(), format!("Set deployment under [models.{}]", model.name)));
+    }
+    if let Err(e) = crate::policy::check_endpoint(&model.endpoint) {
+        return Err((e.to_string(), "Use an endpoint the policy lists in allowed_endpoints, or ask your administrator".to_string()));
+    }
+    Ok(())
+}
+
+/// Send a one-word prompt and classify the outcome
+async fn probe_model(label: &str, model: &ModelConfig) -> Check {
+    let client = AzureClient::new(model.clone());
+    let result = tokio::time::timeout(PROBE_TIMEOUT, client.complete("Reply with OK.", "ping")).await;
+
+    let error = match result {
+        Ok(Ok(_)) => return Check::ok(label, format!("{} responds", model.deployment)),
+        Ok(Err(e)) => e,
+        Err(_) => {
+            return Check::fail(label, format!("no answer from {} in {}s", model.endpoint, PROBE_TIMEOUT.as_secs()), "Check the endpoint URL, VPN and proxy settings");
+        }
+    };
+
+    if let Some(api) = error.downcast_ref::<ApiError>() {
+        let body = text::truncate(api.body.trim(), 100);
+        return match api.status {
+            401 | 403 => Check::fail(label, format!("key rejected (HTTP {})", api.status), format!("Update api_key under [models.{}] from Keys and Endpoint in the portal", model.name)),
+            404 => Check::fail(label, format!("deployment '{}' not found", model.deployment), "Check the deployment name in Azure AI Foundry (Deployments) and the endpoint's resource"),
+            429 => Check::warn(label, "reachable but rate limited (HTTP 429)", "Wait, or raise the deployment's tokens-per-minute quota"),
+            status => Check::fail(label, format!("HTTP {}: {}", status, body), "Check the endpoint, deployment and model_type"),
+        };
+    }
+    if let Some(http) = error.downcast_ref::<reqwest::Error>() {
+        if http.is_connect() || http.is_timeout() {
+            return Check::fail(label, format!("cannot reach {}", model.endpoint), "Check the endpoint URL, DNS, VPN and proxy settings");
+        }
+    }
+    Check::fail(label, text::truncate(&error.to_string(), 100), "Check the endpoint, deployment and model_type")
+}
+
+/// External programs the configured features shell out to
+fn check_tools(config: &AppConfig, checks: &mut Vec<Check>) {
+    let tools = &config.tools;
+    let mut needed: Vec<(String, String)> = vec![("git".to_string(), "/pr".to_string())];
+    for (name, db) in &tools.databases {
+        let program = db.kind.to_string();
+        if !needed.iter().any(|(p, _)| *p == program) {
+            needed.push((program, format!("database '{}'", name)));
+        }
+    }
+    if tools.docker.enabled {
+        needed.push(("docker".to_string(), "[tools.docker]".to_string()));
+    }
+    if tools.kubernetes.enabled {
+        needed.push(("kubectl".to_string(), "[tools.kubernetes]".to_string()));
+    }
+
+    for (program, used_by) in needed {
+        if find_in_path(&program).is_some() {
+            checks.push(Check::ok(&program, format!("found (for {})", used_by)));
+        } else {
+            checks.push(Check::warn(&program, format!("not on PATH (needed for {})", used_by), format!("Install {} or remove {} from the config", program, used_by)));
+        }
+    }
+}
+
+fn check_install(checks: &mut Vec<Check>) {
+    let current = std::env::current_exe().ok().and_then(|p| p.canonicalize().ok());
+    match find_in_path("aicli") {
+        Some(found) => {
+            let found_real = found.canonicalize().unwrap_or(found.clone());
+            if current.as_ref().is_some_and(|c| *c != found_real) {
+                checks.push(Check::warn(
+                    "PATH",
+                    format!("`aicli` runs {}, not this binary", found.display()),
+                    "Run /install from this binary to update the installed copy",
+                ));
+            } else {
+                checks.push(Check::ok("PATH", found.display().to_string()));
+            }
+        }
+        None => checks.push(Check::warn("PATH", "aicli is not on PATH", "Run /install inside aicli, then open a new terminal")),
+    }
+}
+
+fn check_terminal(checks: &mut Vec<Check>) {
+    if !std::io::stdout().is_terminal() {
+        checks.push(Check::warn("Terminal", "stdout is not a terminal", "Colors and the spinner are meant for interactive use"));
+        return;
+    }
+
+    match crate::console::level() {
+        crate::console::Level::Full => {}
+        crate::console::Level::Basic => checks.push(Check::warn(
+            "Console",
+            "classic Windows console: 16 colors, no links",
+            "Windows Terminal shows the full colors and links",
+        )),
+        crate::console::Level::None => checks.push(Check::warn(
+            "Console",
+            "virtual terminal sequences unavailable: no colors",
+            "Use Windows 10 or later, or Windows Terminal",
+        )),
+    }
+
+    let colorterm = std::env::var("COLORTERM").unwrap_or_default();
+    if colorterm == "truecolor" || colorterm == "24bit" {
+        checks.push(Check::ok("Truecolor", "supported"));
+    } else {
+        checks.push(Check::warn("Truecolor", "not advertised (COLORTERM unset)", "AICLI uses 256-color codes, so this only matters for custom themes"));
+    }
+
+    if crate::console::supports_hyperlinks() {
+        checks.push(Check::ok("Hyperlinks", "OSC 8 supported"));
+    } else {
+        checks.push(Check::warn("Hyperlinks", "not detected", "Links print as plain text; iTerm2, WezTerm, kitty, Windows Terminal and VS Code support them"));
+    }
+
+    let locale = ["LC_ALL", "LC_CTYPE", "LANG"]
+        .iter()
+        .find_map(|v| std::env::var(v).ok().filter(|s| !s.is_empty()))
+        .unwrap_or_default();
+    if cfg!(windows) || locale.to_lowercase().contains("utf-8") || locale.to_lowercase().contains("utf8") {
+        checks.push(Check::ok("Unicode", "UTF-8"));
+    } else {
+        checks.push(Check::warn("Unicode", format!("locale '{}' is not UTF-8", locale), "Set LANG=en_US.UTF-8 (or your language) so borders and emoji render"));
+    }
+
+    let (width, _) = crossterm::terminal::size().unwrap_or((80, 24));
+    if width < 80 {
+        checks.push(Check::warn("Width", format!("{} columns", width), "Widen the window to at least 80 columns"));
+    }
+}
+
+pub fn find_in_path(program: &str) -> Option<PathBuf> {
+    let path = std::env::var_os("PATH")?;
+    let file = format!("{}{}", program, std::env::consts::EXE_SUFFIX);
+    std::env::split_paths(&path)
+        .map(|dir| dir.join(&file))
+        .find(|candidate| candidate.is_file())
+}
@@ -0,0 +1,169 @@
+//! `.env` loading at startup, before the config is resolved. The file in
+//! the working directory and the one at the workspace root are read, in that
+//! order; variables already set (in the shell or by the first file) win.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// Variables a project's .env may not change: they decide which programs the
+/// tools run (and what every shell runs first), where aicli keeps its own
+/// files and which endpoints get the user's keys: an env model could point
+/// at any host and `${VAR}` a key in, a proxy and CA of the repository's
+/// could read them in transit
+const PROTECTED: &[&str] = &[
+    "PATH", "HOME", "USERPROFILE", "SHELL", "COMSPEC", "PATHEXT", "LD_PRELOAD", "LD_LIBRARY_PATH",
+    "BASH_ENV", "ENV",
+    "AICLI_MODELS", "AICLI_ACTIVE_MODEL",
+    "HTTPS_PROXY", "HTTP_PROXY", "ALL_PROXY", "NO_PROXY", "SSL_CERT_FILE", "SSL_CERT_DIR",
+];
+const PROTECTED_PREFIXES: &[&str] = &["DYLD_", "AICLI_MODEL_"];
+
+fn is_protected(key: &str) -> bool {
+    // reqwest reads the proxy variables in lowercase too
+    PROTECTED.contains(&key.to_uppercase().as_str())
+        || PROTECTED_PREFIXES.iter().any(|p| key.starts_with(p))
+        || (key.starts_with("AZURE_") && key.ends_with("ENDPOINT"))
+}
+
+/// What was loaded, for the chat banner and `aicli doctor`
+#[derive(Debug, Default)]
+pub struct Loaded {
+    pub files: Vec<PathBuf>,
+    /// Names of the variables set from the files
+    pub variables: Vec<String>,
+    /// Names skipped because they are protected
+    pub skipped: Vec<String>,
+}
+
+static LOADED: OnceLock<Loaded> = OnceLock::new();
+
+pub fn loaded() -> Option<&'static Loaded> {
+    LOADED.get()
+}
+
+/// Load ./.env and <workspace root>/.env into the process environment
+pub fn load() {
+    let mut candidates = Vec::new();
+    if let Ok(cwd) = std::env::current_dir() {
+        candidates.push(cwd.join(".env"));
+    }
+    candidates.push(Path::new(&crate::memory::project_root()).join(".env"));
+    candidates.dedup();
+
+    let mut loaded = Loaded::default();
+    for path in candidates {
+        let Ok(content) = fs::read_to_string(&path) else {
+            continue;
+        };
+        for (key, value) in parse(&content) {
+            if is_protected(&key) {
+                loaded.skipped.push(key);
+                continue;
+            }
+            if std::env::var_os(&key).is_none() {
+                std::env::set_var(&key, value);
+                loaded.variables.push(key);
+            }
+        }
+        loaded.files.push(path);
+    }
+    let _ = LOADED.set(loaded);
+}
+
+/// `KEY=value` lines, with optional `export `, `#` comments, single quotes
+/// (literal), double quotes (`\n`, `\"`, `\\` escapes, may span lines) and
+/// `${VAR}` expansion in unquoted and double-quoted values
+pub fn parse(content: &str) -> Vec<(String, String)> {
+    let mut pairs: Vec<(String, String)> = Vec::new();
+    let mut lines = content.lines();
+    while let Some(line) = lines.next() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let Some((key, raw)) = line.split_once('=') else {
+            continue;
+        };
+        let key = key.trim();
+        if key.is_empty() || !key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            continue;
+        }
+        let raw = raw.trim_start();
+
+        let value = if let Some(rest) = raw.strip_prefix('\'') {
+            rest.split_once('\'').map(|(v, _)| v).unwrap_or(rest).to_string()
+        } else if let Some(rest) = raw.strip_prefix('"') {
+            let mut quoted = rest.to_string();
+            while !closes(&quoted) {
+                match lines.next() {
+                    Some(next) => {
+                        quoted.push('\n');
+                        quoted.push_str(next);
+                    }
+                    None => break,
+                }
+            }
+            expand(&unescape(&quoted), &pairs)
+        } else {
+            let unquoted = match raw.find(" #") {
+                Some(comment) => &raw[..comment],
+                None => raw,
+            };
+            expand(unquoted.trim_end(), &pairs)
+        };
+        pairs.push((key.to_string(), value));
+    }
+    pairs
+}
+
+/// Whether a double-quoted value (after its opening quote) has its closing
+/// quote
+fn closes(quoted: &str) -> bool {
+    let mut escaped = false;
+    for c in quoted.chars() {
+        match c {
+            '\\' if !escaped => escaped = true,
+            '"' if !escaped => return true,
+            _ => escaped = false,
+        }
+    }
+    false
+}
+
+/// The double-quoted value up to its closing quote, escapes resolved
+fn unescape(quoted: &str) -> String {
+    let mut value = String::new();
+    let mut chars = quoted.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => break,
+            '\\' => match chars.next() {
+                Some('n') => value.push('\n'),
+                Some('t') => value.push('\t'),
+                Some(other) => value.push(other),
+                None => {}
+            },
+            other => value.push(other),
+        }
+    }
+    value
+}
+
+/// `${VAR}` from earlier lines of the file, then the environment
+fn expand(value: &str, earlier: &[(String, String)]) -> String {
+    let pattern = regex::Regex::new(r"\$\{([A-Za-z_][A-Za-z0-9_]*)\}").expect("valid regex");
+    pattern
+        .replace_all(value, |caps: &regex::Captures| {
+            let name = &caps[1];
+            earlier
+                .iter()
+                .rev()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value.clone())
+                .or_else(|| std::env::var(name).ok())
+                .unwrap_or_default()
+        })
+        .to_string()
+}
@@ -0,0 +1,306 @@
+use anyhow::{Context, Result};
+use regex::Regex;
+use serde::Deserialize;
+use serde_json::Value;
+use std::fs;
+use std::path::Path;
+use std::time::Instant;
+
+use crate::client::AzureClient;
+use crate::codeblocks;
+use crate::config::AppConfig;
+use crate::console::println;
+use crate::text;
+
+const DEFAULT_SYSTEM_PROMPT: &str = "You are a helpful assistant.";
+
+const GRADER_PROMPT: &str = "You grade an AI assistant's answer against a criterion. Reply with PASS or FAIL \
+on the first line and a one-sentence reason on the second. Judge only the criterion, not style.";
+
+/// An eval file: shared settings and the cases
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Suite {
+    /// Model that answers; the active model when empty
+    #[serde(default)]
+    model: Option<String>,
+    /// Model that grades `llm` assertions; the answering model when empty
+    #[serde(default)]
+    grader: Option<String>,
+    #[serde(default)]
+    system: Option<String>,
+    #[serde(rename = "case", default)]
+    cases: Vec<Case>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Case {
+    name: String,
+    prompt: String,
+    /// Fixture files sent with the prompt, relative to the eval file
+    #[serde(default)]
+    files: Vec<String>,
+    #[serde(default)]
+    system: Option<String>,
+    #[serde(rename = "assert", default)]
+    assertions: Vec<Assertion>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum Assertion {
+    Contains { value: String, #[serde(default)] ignore_case: bool },
+    NotContains { value: String, #[serde(default)] ignore_case: bool },
+    Regex { pattern: String },
+    /// The answer (or its first ```json block) is JSON matching the schema
+    JsonSchema { schema: Value },
+    /// Another model call decides whether the answer meets `criteria`
+    Llm { criteria: String },
+}
+
+impl Assertion {
+    fn label(&self) -> String {
+        match self {
+            Assertion::Contains { value, .. } => format!("contains {:?}", value),
+            Assertion::NotContains { value, .. } => format!("does not contain {:?}", value),
+            Assertion::Regex { pattern } => format!("matches /{}/", pattern),
+            Assertion::JsonSchema { .. } => "matches the JSON schema".to_string(),
+            Assertion::Llm { criteria } => format!("llm: {}", criteria),
+        }
+    }
+}
+
+fn read_suite(path: &Path) -> Result<Suite> {
+    let content = fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let suite: Suite = match path.extension().and_then(|e| e.to_str()) {
+        Some("json") => serde_json::from_str(&content).with_context(|| format!("Invalid eval file {}", path.display()))?,
+        Some("yaml" | "yml") => anyhow::bail!(
+            "YAML eval files are not supported; write {} as TOML or JSON (same fields)",
+            path.display()
+        ),
+        _ => toml::from_str(&content).with_context(|| format!("Invalid eval file {}", path.display()))?,
+    };
+    if suite.cases.is_empty() {
+        anyhow::bail!("{} has no [[case]] entries", path.display());
+    }
+    if let Some(case) = suite.cases.iter().find(|c| c.assertions.is_empty()) {
+        anyhow::bail!("Case '{}' has no [[case.assert]] entries", case.name);
+    }
+    Ok(suite)
+}
+
+/// `aicli eval <FILE> [--model NAME]`: answer each case's prompt and check
+/// the assertions. Exits with status 1 when any case fails, for CI.
+pub async fn run(config: &AppConfig, file: &str, model: Option<&str>) -> Result<()> {
+    let path = crate::paths::resolve(file);
+    let suite = read_suite(&path)?;
+    let base = path.parent().unwrap_or(Path::new("."));
+
+    let pick = |name: Option<&str>| -> Result<AzureClient> {
+        let model = match name {
+            Some(name) => config.models.get(name).ok_or_else(|| anyhow::anyhow!("No model '{}' configured", name))?,
+            None => config.get_active_model().ok_or_else(|| anyhow::anyhow!("No active model configured"))?,
+        };
+        Ok(AzureClient::new(model.clone()))
+    };
+    let answering = model.or(suite.model.as_deref());
+    let client = pick(answering)?;
+    let grader = pick(suite.grader.as_deref().or(answering))?;
+
+    println!(
+        "\n\x1b[1m{}\x1b[0m  \x1b[90m{} case(s) · {}\x1b[0m\n",
+        crate::paths::display(&path),
+        suite.cases.len(),
+        client.get_model_name()
+    );
+    let mut failed = 0;
+    for case in &suite.cases {
+        let files: Vec<String> = case.files.iter().map(|f| base.join(f).display().to_string()).collect();
+        let prompt = format!("{}{}", case.prompt, crate::input::read_file_context(&files));
+        let system = case.system.as_deref().or(suite.system.as_deref()).unwrap_or(DEFAULT_SYSTEM_PROMPT);
+
+        let started = Instant::now();
+        let answer = match client.complete(system, &prompt).await {
+            Ok(answer) => answer,
+            Err(e) => {
+                failed += 1;
+                println!("  \x1b[31m✗\x1b[0m {}  \x1b[31m{}\x1b[0m", case.name, text::truncate(&e.to_string(), 100));
+                continue;
+            }
+        };
+        let elapsed = started.elapsed();
+
+        let mut problems = Vec::new();
+        for assertion in &case.assertions {
+            if let Err(problem) = check(assertion, &answer, &grader).await {
+                problems.push(format!("{}: {}", assertion.label(), problem));
+            }
+        }
+        if problems.is_empty() {
+            println!("  \x1b[32m✓\x1b[0m {}  \x1b[90m{:.1}s\x1b[0m", case.name, elapsed.as_secs_f64());
+            continue;
+        }
+        failed += 1;
+        println!("  \x1b[31m✗\x1b[0m {}  \x1b[90m{:.1}s\x1b[0m", case.name, elapsed.as_secs_f64());
+        for problem in problems {
+            println!("      \x1b[31m{}\x1b[0m", problem);
+        }
+        println!("      \x1b[90manswer: {}\x1b[0m", text::truncate(&answer.replace('\n', " "), 120));
+    }
+
+    let passed = suite.cases.len() - failed;
+    println!();
+    if failed > 0 {
+        println!("  \x1b[31m{} failed\x1b[0m, {} passed\n", failed, passed);
+        std::process::exit(1);
+    }
+    println!("  \x1b[32mAll {} case(s) passed\x1b[0m\n", passed);
+    Ok(())
+}
+
+/// Ok, or why the answer fails the assertion
+async fn check(assertion: &Assertion, answer: &str, grader: &AzureClient) -> std::result::Result<(), String> {
+    match assertion {
+        Assertion::Contains { value, ignore_case } | Assertion::NotContains { value, ignore_case } => {
+            let found = if *ignore_case {
+                answer.to_lowercase().contains(&value.to_lowercase())
+            } else {
+                answer.contains(value.as_str())
+            };
+            let wanted = matches!(assertion, Assertion::Contains { .. });
+            match (found, wanted) {
+                (true, false) => Err("found".to_string()),
+                (false, true) => Err("not found".to_string()),
+                _ => Ok(()),
+            }
+        }
+        Assertion::Regex { pattern } => {
+            let regex = Regex::new(pattern).map_err(|e| format!("invalid pattern: {}", e))?;
+            if regex.is_match(answer) {
+                Ok(())
+            } else {
+                Err("no match".to_string())
+            }
+        }
+        Assertion::JsonSchema { schema } => {
+            let json = parse_json(answer).ok_or("the answer is not JSON")?;
+            validate(schema, &json, "$")
+        }
+        Assertion::Llm { criteria } => {
+            let prompt = format!("Criterion: {}\n\nAnswer:\n{}", criteria, answer);
+            let verdict = grader.complete(GRADER_PROMPT, &prompt).await.map_err(|e| format!("grader failed: {}", e))?;
+            let mut lines = verdict.trim().lines();
+            let first = lines.next().unwrap_or_default().trim().to_uppercase();
+            let reason = lines.collect::<Vec<_>>().join(" ");
+            if first.starts_with("PASS") {
+                Ok(())
+            } else {
+                Err(if reason.trim().is_empty() { verdict.trim().to_string() } else { reason.trim().to_string() })
+            }
+        }
+    }
+}
+
+/// The whole answer as JSON, or its first json (or untagged) code block
+fn parse_json(answer: &str) -> Option<Value> {
+    if let Ok(json) = serde_json::from_str(answer.trim()) {
+        return Some(json);
+    }
+    codeblocks::extract(answer)
+        .into_iter()
+        .filter(|b| b.lang.is_empty() || b.lang == "json")
+        .find_map(|b| serde_json::from_str(&b.code).ok())
+}
+
+/// The JSON Schema keywords evals need: type, enum, const, required,
+/// properties, additionalProperties (false), items, minItems, maxItems,
+/// minimum, maximum, minLength, maxLength and pattern
+fn validate(schema: &Value, value: &Value, at: &str) -> std::result::Result<(), String> {
+    if let Some(expected) = schema.get("type") {
+        let types: Vec<&str> = match expected {
+            Value::String(t) => vec![t.as_str()],
+            Value::Array(ts) => ts.iter().filter_map(|t| t.as_str()).collect(),
+            _ => Vec::new(),
+        };
+        let matches = |t: &str| match t {
+            "object" => value.is_object(),
+            "array" => value.is_array(),
+            "string" => value.is_string(),
+            "number" => value.is_number(),
+            "integer" => value.is_i64() || value.is_u64(),
+            "boolean" => value.is_boolean(),
+            "null" => value.is_null(),
+            _ => false,
+        };
+        if !types.is_empty() && !types.iter().any(|t| matches(t)) {
+            return Err(format!("{} should be {}", at, types.join(" or ")));
+        }
+    }
+    if let Some(options) = schema.get("enum").and_then(|e| e.as_array()) {
+        if !options.contains(value) {
+            return Err(format!("{} is not one of {}", at, Value::Array(options.clone())));
+        }
+    }
+    if let Some(constant) = schema.get("const") {
+        if constant != value {
+            return Err(format!("{} should be {}", at, constant));
+        }
+    }
+
+    if let Some(object) = value.as_object() {
+        for key in schema.get("required").and_then(|r| r.as_array()).into_iter().flatten().filter_map(|k| k.as_str()) {
+            if !object.contains_key(key) {
+                return Err(format!("{} is missing '{}'", at, key));
+            }
+        }
+        let properties = schema.get("properties").and_then(|p| p.as_object());
+        for (key, item) in object {
+            match properties.and_then(|p| p.get(key)) {
+                Some(property) => validate(property, item, &format!("{}.{}", at, key))?,
+                None if schema.get("additionalProperties") == Some(&Value::Bool(false)) => {
+                    return Err(format!("{} has unexpected '{}'", at, key));
+                }
+                None => {}
+            }
+        }
+    }
+    if let Some(items) = value.as_array() {
+        let count = items.len() as u64;
+        if schema.get("minItems").and_then(|m| m.as_u64()).is_some_and(|min| count < min) {
+            return Err(format!("{} has too few items ({})", at, count));
+        }
+        if schema.get("maxItems").and_then(|m| m.as_u64()).is_some_and(|max| count > max) {
+            return Err(format!("{} has too many items ({})", at, count));
+        }
+        if let Some(item_schema) = schema.get("items") {
+            for (i, item) in items.iter().enumerate() {
+                validate(item_schema, item, &format!("{}[{}]", at, i))?;
+            }
+        }
+    }
+    if let Some(number) = value.as_f64() {
+        if schema.get("minimum").and_then(|m| m.as_f64()).is_some_and(|min| number < min) {
+            return Err(format!("{} is below the minimum", at));
+        }
+        if schema.get("maximum").and_then(|m| m.as_f64()).is_some_and(|max| number > max) {
+            return Err(format!("{} is above the maximum", at));
+        }
+    }
+    if let Some(string) = value.as_str() {
+        let length = string.chars().count() as u64;
+        if schema.get("minLength").and_then(|m| m.as_u64()).is_some_and(|min| length < min) {
+            return Err(format!("{} is too short", at));
+        }
+        if schema.get("maxLength").and_then(|m| m.as_u64()).is_some_and(|max| length > max) {
+            return Err(format!("{} is too long", at));
+        }
+        if let Some(pattern) = schema.get("pattern").and_then(|p| p.as_str()) {
+            let regex = Regex::new(pattern).map_err(|e| format!("invalid pattern in the schema: {}", e))?;
+            if !regex.is_match(string) {
+                return Err(format!("{} does not match /{}/", at, pattern));
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,247 @@
+use crate::config::GitHubConfig;
+use anyhow::{Context, Result};
+use serde_json::{json, Value};
+use std::process::Command;
+
+const DEFAULT_ISSUE_LIMIT: u64 = 20;
+const MAX_COMMENTS: usize = 20;
+
+/// Token from config, then GITHUB_TOKEN/GH_TOKEN, then the GitHub CLI
+/// (which keeps it in the OS keyring)
+fn token(config: &GitHubConfig) -> Result<String> {
+    if let Some(token) = config.token.as_ref().filter(|t| !t.is_empty()) {
+        return Ok(token.clone());
+    }
+    for var in ["GITHUB_TOKEN", "GH_TOKEN"] {
+        if let Ok(token) = std::env::var(var) {
+            if !token.is_empty() {
+                return Ok(token);
+            }
+        }
+    }
+    if let Ok(output) = Command::new("gh").args(["auth", "token"]).output() {
+        let token = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        if output.status.success() && !token.is_empty() {
+            return Ok(token);
+        }
+    }
+    anyhow::bail!("No GitHub token. Set [tools.github] token, GITHUB_TOKEN, or run `gh auth login`")
+}
+
+pub fn git(args: &[&str]) -> Result<String> {
+    let output = Command::new("git").args(args).output().context("Could not run git")?;
+    if !output.status.success() {
+        anyhow::bail!("git {}: {}", args.join(" "), String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// "owner/name" from the input, or parsed from the origin remote
+fn repo(input: &Value) -> Result<String> {
+    if let Some(repo) = input.get("repo").and_then(|r| r.as_str()) {
+        return Ok(repo.to_string());
+    }
+
+    let remote = git(&["remote", "get-url", "origin"])?;
+    // git@github.com:owner/name.git or https://github.com/owner/name(.git)
+    let path = remote
+        .split_once("github.com")
+        .map(|(_, rest)| rest.trim_start_matches([':', '/']))
+        .ok_or_else(|| anyhow::anyhow!("origin ({}) is not a GitHub remote; pass 'repo'", remote))?;
+    Ok(path.trim_end_matches('/').trim_end_matches(".git").to_string())
+}
+
+pub fn current_branch() -> Result<String> {
+    git(&["rev-parse", "--abbrev-ref", "HEAD"])
+}
+
+async fn api(config: &GitHubConfig, method: reqwest::Method, path: &str, body: Option<Value>) -> Result<Value> {
+    let url = format!("{}{}", config.api_url.trim_end_matches('/'), path);
+    let mut request = reqwest::Client::new()
+        .request(method, &url)
+        .bearer_auth(token(config)?)
+        .header("Accept", "application/vnd.github+json")
+        .header("User-Agent", "aicli");
+    if let Some(body) = body {
+        request = request.json(&body);
+    }
+
+    let response = request.send().await?;
+    let status = response.status();
+    let body: Value = response.json().await.unwrap_or(Value::Null);
+    if !status.is_success() {
+        let message = body.get("message").and_then(|m| m.as_str()).unwrap_or("request failed");
+        anyhow::bail!("GitHub API error ({}): {}", status, message);
+    }
+    Ok(body)
+}
+
+fn str_field<'a>(value: &'a Value, key: &str) -> &'a str {
+    value.get(key).and_then(|v| v.as_str()).unwrap_or("")
+}
+
+fn number_param(input: &Value) -> Result<u64> {
+    input
+        .get("number")
+        .and_then(|n| n.as_u64().or_else(|| n.as_str().and_then(|s| s.trim_start_matches('#').parse().ok())))
+        .ok_or_else(|| anyhow::anyhow!("Missing 'number' parameter"))
+}
+
+fn text_param<'a>(input: &'a Value, key: &str) -> Result<&'a str> {
+    input
+        .get(key)
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing '{}' parameter", key))
+}
+
+pub fn list_issues(input: &Value, config: &GitHubConfig) -> Result<String> {
+    let repo = repo(input)?;
+    let state = input.get("state").and_then(|s| s.as_str()).unwrap_or("open");
+    let limit = input.get("limit").and_then(|l| l.as_u64()).unwrap_or(DEFAULT_ISSUE_LIMIT).min(100);
+    let mut path = format!("/repos/{}/issues?state={}&per_page={}", repo, state, limit);
+    if let Some(labels) = input.get("labels").and_then(|l| l.as_str()) {
+        path.push_str(&format!("&labels={}", labels));
+    }
+
+    let issues = crate::tools::block_on(api(config, reqwest::Method::GET, &path, None))?;
+    let issues = issues.as_array().cloned().unwrap_or_default();
+    if issues.is_empty() {
+        return Ok(format!("No {} issues in {}", state, repo));
+    }
+
+    let lines: Vec<String> = issues
+        .iter()
+        .map(|issue| {
+            let kind = if issue.get("pull_request").is_some() { "PR" } else { "issue" };
+            let labels: Vec<&str> = issue["labels"]
+                .as_array()
+                .map(|l| l.iter().map(|l| str_field(l, "name")).collect())
+                .unwrap_or_default();
+            format!(
+                "#{} [{}] {}{} (by {})",
+                issue["number"],
+                kind,
+                str_field(issue, "title"),
+                if labels.is_empty() { String::new() } else { format!(" [{}]", labels.join(", ")) },
+                str_field(&issue["user"], "login"),
+            )
+        })
+        .collect();
+    Ok(lines.join("\n"))
+}
+
+pub fn read_issue(input: &Value, config: &GitHubConfig) -> Result<String> {
+    let repo = repo(input)?;
+    let number = number_param(input)?;
+
+    let (issue, comments) = crate::tools::block_on(async {
+        let issue = api(config, reqwest::Method::GET, &format!("/repos/{}/issues/{}", repo, number), None).await?;
+        let comments = api(
+            config,
+            reqwest::Method::GET,
+            &format!("/repos/{}/issues/{}/comments?per_page={}", repo, number, MAX_COMMENTS),
+            None,
+        )
+        .await?;
+        Ok::<_, anyhow::Error>((issue, comments))
+    })?;
+
+    let mut result = format!(
+        "#{} {}\nState: {} · Author: {} · {}\n\n{}",
+        number,
+        str_field(&issue, "title"),
+        str_field(&issue, "state"),
+        str_field(&issue["user"], "login"),
+        str_field(&issue, "html_url"),
+        str_field(&issue, "body"),
+    );
+    for comment in comments.as_array().cloned().unwrap_or_default() {
+        result.push_str(&format!(
+            "\n\n--- {} ({}) ---\n{}",
+            str_field(&comment["user"], "login"),
+            str_field(&comment, "created_at"),
+            str_field(&comment, "body"),
+        ));
+    }
+    Ok(result)
+}
+
+/// Open a pull request; head defaults to the current branch and base to the
+/// repository's default branch
+pub async fn create_pr(
+    config: &GitHubConfig,
+    repo: &str,
+    title: &str,
+    body: &str,
+    head: &str,
+    base: Option<&str>,
+    draft: bool,
+) -> Result<String> {
+    let base = match base {
+        Some(base) => base.to_string(),
+        None => default_branch(config, repo).await?,
+    };
+    let pr = api(
+        config,
+        reqwest::Method::POST,
+        &format!("/repos/{}/pulls", repo),
+        Some(json!({ "title": title, "body": body, "head": head, "base": base, "draft": draft })),
+    )
+    .await?;
+    Ok(str_field(&pr, "html_url").to_string())
+}
+
+pub async fn default_branch(config: &GitHubConfig, repo: &str) -> Result<String> {
+    let info = api(config, reqwest::Method::GET, &format!("/repos/{}", repo), None).await?;
+    Ok(info.get("default_branch").and_then(|b| b.as_str()).unwrap_or("main").to_string())
+}
+
+pub fn create_pr_tool(input: &Value, config: &GitHubConfig) -> Result<String> {
+    let repo = repo(input)?;
+    let title = text_param(input, "title")?;
+    let body = input.get("body").and_then(|b| b.as_str()).unwrap_or("");
+    let head = match input.get("head").and_then(|h| h.as_str()) {
+        Some(head) => head.to_string(),
+        None => current_branch()?,
+    };
+    let base = input.get("base").and_then(|b| b.as_str());
+    let draft = input.get("draft").and_then(|d| d.as_bool()).unwrap_or(false);
+
+    let url = crate::tools::block_on(create_pr(config, &repo, title, body, &head, base, draft))?;
+    Ok(format!("Created pull request: {}", url))
+}
+
+pub fn comment(input: &Value, config: &GitHubConfig) -> Result<String> {
+    let repo = repo(input)?;
+    let number = number_param(input)?;
+    let body = text_param(input, "body")?;
+
+    let comment = crate::tools::block_on(api(
+        config,
+        reqwest::Method::POST,
+        &format!("/repos/{}/issues/{}/comments", repo, number),
+        Some(json!({ "body": body })),
+    ))?;
+    Ok(format!("Commented on #{}: {}", number, str_field(&comment, "html_url")))
+}
+
+/// Repository of the working directory, for /pr
+pub fn current_repo() -> Result<String> {
+    repo(&Value::Null)
+}
+
+/// Push the current branch to origin, setting upstream
+pub fn push_current_branch() -> Result<()> {
+    git(&["push", "-u", "origin", "HEAD"]).map(|_| ())
+}
+
+/// Commits and diff of `head` against `base`, used to write the PR description
+pub fn branch_summary(base: &str, max_chars: usize) -> Result<String> {
+    let log = git(&["log", "--oneline", &format!("origin/{}..HEAD", base)])?;
+    let stat = git(&["diff", "--stat", &format!("origin/{}...HEAD", base)])?;
+    let diff = git(&["diff", &format!("origin/{}...HEAD", base)])?;
+    Ok(crate::tools::truncate_output(
+        &format!("Commits:\n{}\n\nFiles:\n{}\n\nDiff:\n{}", log, stat, diff),
+        max_chars,
+    ))
+}
@@ -0,0 +1,216 @@
+use base64::Engine;
+use std::io::{ErrorKind, Write};
+use std::path::Path;
+use std::process::Command;
+
+use crate::config::ImageProtocol;
+use crate::paths;
+use crate::text;
+
+/// Width of inline images, in terminal columns
+const PREVIEW_COLUMNS: usize = 40;
+/// The same width in pixels for sixel, assuming ~12px cells
+const PREVIEW_PIXELS: usize = 480;
+/// Kitty takes the payload in chunks of at most 4096 bytes
+const KITTY_CHUNK: usize = 4096;
+
+/// The protocol to use: the configured one, or one detected from the
+/// variables terminals set
+pub fn detect(configured: ImageProtocol) -> ImageProtocol {
+    if configured != ImageProtocol::Auto {
+        return configured;
+    }
+    let var = |name: &str| std::env::var(name).unwrap_or_default();
+    let term = var("TERM");
+    let program = var("TERM_PROGRAM");
+
+    if term == "xterm-kitty" || term == "xterm-ghostty" || !var("KITTY_WINDOW_ID").is_empty() || program == "ghostty" {
+        ImageProtocol::Kitty
+    } else if program == "iTerm.app" || program == "WezTerm" || var("LC_TERMINAL") == "iTerm2" {
+        ImageProtocol::Iterm
+    } else if term.contains("sixel") || term == "foot" || term.starts_with("mlterm") || program == "mintty" {
+        ImageProtocol::Sixel
+    } else {
+        ImageProtocol::Off
+    }
+}
+
+/// Extensions treated as images when referenced with @
+const IMAGE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "gif", "webp"];
+
+pub fn is_image(path: &Path) -> bool {
+    path.extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .is_some_and(|ext| IMAGE_EXTENSIONS.contains(&ext.as_str()))
+}
+
+/// Draw an image inline followed by a path and dimensions caption. Where
+/// the terminal can't draw it (or kitty gets a non-PNG) only the caption
+/// is printed.
+pub fn show(path: &Path, configured: ImageProtocol) {
+    let Ok(data) = std::fs::read(path) else {
+        println!("  \x1b[38;5;245m🖼 {} (unreadable)\x1b[0m", paths::display(path));
+        return;
+    };
+    let format = Format::of(&data);
+    let drawn = match detect(configured) {
+        ImageProtocol::Kitty if format == Some(Format::Png) => {
+            kitty(&data);
+            true
+        }
+        ImageProtocol::Iterm if format.is_some() => {
+            iterm(&data);
+            true
+        }
+        ImageProtocol::Sixel if format.is_some() => sixel(path),
+        _ => false,
+    };
+    if drawn {
+        println!();
+    }
+    println!("  \x1b[38;5;245m🖼 {}\x1b[0m", caption(path, &data));
+}
+
+/// "logo.png · 1024×1024 PNG · 1.2 MB"
+pub fn caption(path: &Path, data: &[u8]) -> String {
+    let mut parts = vec![paths::display(path)];
+    match (Format::of(data), dimensions(data)) {
+        (Some(format), Some((width, height))) => parts.push(format!("{}×{} {}", width, height, format.name())),
+        (Some(format), None) => parts.push(format.name().to_string()),
+        (None, _) => parts.push("unknown format".to_string()),
+    }
+    parts.push(text::format_size(data.len() as u64));
+    parts.join(" · ")
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Png,
+    Jpeg,
+    Gif,
+    Webp,
+}
+
+impl Format {
+    /// Sniffed from the file's magic bytes, not its extension
+    pub fn of(data: &[u8]) -> Option<Self> {
+        if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+            Some(Format::Png)
+        } else if data.starts_with(&[0xff, 0xd8, 0xff]) {
+            Some(Format::Jpeg)
+        } else if data.starts_with(b"GIF8") {
+            Some(Format::Gif)
+        } else if data.len() >= 12 && &data[..4] == b"RIFF" && &data[8..12] == b"WEBP" {
+            Some(Format::Webp)
+        } else {
+            None
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Format::Png => "PNG",
+            Format::Jpeg => "JPEG",
+            Format::Gif => "GIF",
+            Format::Webp => "WebP",
+        }
+    }
+}
+
+/// Width and height read from the image header
+pub fn dimensions(data: &[u8]) -> Option<(u32, u32)> {
+    let be16 = |at: usize| Some(u16::from_be_bytes(data.get(at..at + 2)?.try_into().ok()?) as u32);
+    let le16 = |at: usize| Some(u16::from_le_bytes(data.get(at..at + 2)?.try_into().ok()?) as u32);
+    let be32 = |at: usize| Some(u32::from_be_bytes(data.get(at..at + 4)?.try_into().ok()?));
+    let le24 = |at: usize| {
+        let b = data.get(at..at + 3)?;
+        Some(b[0] as u32 | (b[1] as u32) << 8 | (b[2] as u32) << 16)
+    };
+
+    match Format::of(data)? {
+        // IHDR is always the first chunk
+        Format::Png => Some((be32(16)?, be32(20)?)),
+        Format::Gif => Some((le16(6)?, le16(8)?)),
+        Format::Jpeg => {
+            // Walk the segments up to the start-of-frame marker
+            let mut at = 2;
+            while at + 9 < data.len() {
+                if data[at] != 0xff {
+                    return None;
+                }
+                let marker = data[at + 1];
+                let is_frame = (0xc0..=0xcf).contains(&marker) && !matches!(marker, 0xc4 | 0xc8 | 0xcc);
+                if is_frame {
+                    return Some((be16(at + 7)?, be16(at + 5)?));
+                }
+                at += 2 + be16(at + 2)? as usize;
+            }
+            None
+        }
+        Format::Webp => match data.get(12..16)? {
+            b"VP8X" => Some((le24(24)? + 1, le24(27)? + 1)),
+            b"VP8 " => Some((le16(26)? & 0x3fff, le16(28)? & 0x3fff)),
+            b"VP8L" => {
+                let bits = u32::from_le_bytes(data.get(21..25)?.try_into().ok()?);
+                Some(((bits & 0x3fff) + 1, ((bits >> 14) & 0x3fff) + 1))
+            }
+            _ => None,
+        },
+    }
+}
+
+/// Kitty graphics protocol; only PNG is accepted as-is
+fn kitty(png: &[u8]) {
+    let payload = base64::engine::general_purpose::STANDARD.encode(png);
+    let chunks: Vec<&[u8]> = payload.as_bytes().chunks(KITTY_CHUNK).collect();
+    let mut out = std::io::stdout().lock();
+    for (i, chunk) in chunks.iter().enumerate() {
+        let more = if i + 1 < chunks.len() { 1 } else { 0 };
+        // Only the first chunk carries the control keys
+        let keys = if i == 0 { format!("a=T,f=100,c={},", PREVIEW_COLUMNS) } else { String::new() };
+        let _ = write!(out, "\x1b_G{}m={};{}\x1b\\", keys, more, String::from_utf8_lossy(chunk));
+    }
+    let _ = out.flush();
+}
+
+/// iTerm2 inline images; any format macOS can decode
+fn iterm(image: &[u8]) {
+    let payload = base64::engine::general_purpose::STANDARD.encode(image);
+    print!(
+        "\x1b]1337;File=inline=1;size={};width={};preserveAspectRatio=1:{}\x07",
+        image.len(),
+        PREVIEW_COLUMNS,
+        payload
+    );
+    let _ = std::io::stdout().flush();
+}
+
+/// Terminals can't take PNG as sixel, so convert with whichever of
+/// img2sixel and ImageMagick is installed
+fn sixel(path: &Path) -> bool {
+    let file = path.display().to_string();
+    let width = PREVIEW_PIXELS.to_string();
+    let resize = format!("{}x", PREVIEW_PIXELS);
+    let converters: [(&str, Vec<&str>); 3] = [
+        ("img2sixel", vec!["-w", width.as_str(), file.as_str()]),
+        ("magick", vec![file.as_str(), "-resize", resize.as_str(), "sixel:-"]),
+        ("convert", vec![file.as_str(), "-resize", resize.as_str(), "sixel:-"]),
+    ];
+    for (program, args) in &converters {
+        // Windows' convert.exe converts file systems, not images
+        if cfg!(windows) && *program == "convert" {
+            continue;
+        }
+        match Command::new(program).args(args).output() {
+            Ok(output) if output.status.success() => {
+                let mut out = std::io::stdout().lock();
+                let _ = out.write_all(&output.stdout);
+                let _ = out.flush();
+                return true;
+            }
+            Err(e) if e.kind() == ErrorKind::NotFound => continue,
+            _ => return false,
+        }
+    }
+    false
+}
@@ -0,0 +1,169 @@
+//! `/history`: browse the conversation, read a message in full with the
+//! tool results that came after it, copy it, or fork the session from it.
+
+use crate::client::Message;
+use crate::codeblocks;
+use crate::console::println;
+use crate::text;
+use crate::ui::{MenuItem, UI};
+
+const TOOL_RESULTS_PREFIX: &str = "Tool execution results:";
+/// Lines of each tool's output shown by "Tool results"
+const TOOL_OUTPUT_LINES: usize = 40;
+
+enum Action {
+    Read,
+    Tools,
+    Copy,
+    Fork,
+}
+
+/// A prompt or answer, with the tool results that followed it
+struct Entry {
+    index: usize,
+    /// Indices of the tool results messages that belong to it
+    tools: Vec<usize>,
+}
+
+/// The messages aicli sends with the output of the tools the model called
+pub fn is_tool_results(message: &Message) -> bool {
+    message.content.as_text().starts_with(TOOL_RESULTS_PREFIX)
+}
+
+fn entries(messages: &[Message]) -> Vec<Entry> {
+    let mut entries: Vec<Entry> = Vec::new();
+    for (index, message) in messages.iter().enumerate() {
+        match entries.last_mut() {
+            Some(entry) if is_tool_results(message) => entry.tools.push(index),
+            _ => entries.push(Entry { index, tools: Vec::new() }),
+        }
+    }
+    entries
+}
+
+/// `[Tool: name | Success: true]` blocks of a tool results message
+fn tool_results(message: &Message) -> Vec<(String, bool, String)> {
+    let content = message.content.as_text();
+    let body = content.trim_start_matches(TOOL_RESULTS_PREFIX).trim_end_matches("Continue with the task.");
+    body.split("\n\n---\n\n")
+        .filter_map(|block| {
+            let block = block.trim_matches('\n');
+            let header = block.lines().next()?.strip_prefix("[Tool: ")?.strip_suffix(']')?;
+            let (name, success) = header.split_once(" | Success: ")?;
+            let output = block.split_once('\n').map(|(_, rest)| rest).unwrap_or_default();
+            Some((name.to_string(), success == "true", output.to_string()))
+        })
+        .collect()
+}
+
+/// The prompt without the @file context inlined into it
+pub fn prompt_text(message: &Message) -> String {
+    let content = message.content.as_text();
+    content.split("\n\nFile context:").next().unwrap_or_default().to_string()
+}
+
+fn menu_item(messages: &[Message], entry: &Entry) -> MenuItem {
+    let message = &messages[entry.index];
+    let (who, content) = if message.role == "assistant" {
+        let content = message.content.as_text();
+        let tools = message.content.tool_names();
+        match content.is_empty() && !tools.is_empty() {
+            true => ("aicli", format!("⚙ {}", tools.join(", "))),
+            false => ("aicli", content),
+        }
+    } else {
+        ("you", prompt_text(message))
+    };
+    let preview = content.split_whitespace().collect::<Vec<_>>().join(" ");
+    let label = format!("{:>3}. {}: {}", entry.index + 1, who, text::truncate(&preview, 70));
+    let tools: usize = entry.tools.iter().map(|&i| tool_results(&messages[i]).len()).sum();
+    let detail = if tools > 0 { format!("⚙ {} tool result(s)", tools) } else { String::new() };
+    MenuItem::new(label, detail, false)
+}
+
+/// Browse `messages` until the user leaves. Returns how many messages the
+/// conversation keeps when they choose to fork it.
+pub fn browse(messages: &[Message], ui: &UI) -> Option<usize> {
+    let entries = entries(messages);
+    if entries.is_empty() {
+        ui.print_info("No messages yet");
+        return None;
+    }
+    let items: Vec<MenuItem> = entries.iter().map(|entry| menu_item(messages, entry)).collect();
+    let title = format!("Conversation ({} messages)", messages.len());
+    let mut selected = entries.len() - 1;
+    loop {
+        selected = ui.pick(&title, &items, selected)?;
+        let entry = &entries[selected];
+        let message = &messages[entry.index];
+
+        let mut actions = vec![(Action::Read, MenuItem::new("Read in full", "", false))];
+        if !entry.tools.is_empty() {
+            actions.push((Action::Tools, MenuItem::new("Tool results", items[selected].detail.clone(), false)));
+        }
+        actions.push((Action::Copy, MenuItem::new("Copy to the clipboard", "", false)));
+        let fork = if message.role == "assistant" {
+            "keeps this answer and everything before it"
+        } else {
+            "keeps everything before this prompt, to ask it differently"
+        };
+        actions.push((Action::Fork, MenuItem::new("Fork the session here", fork, false)));
+        let menu: Vec<MenuItem> = actions.iter().map(|(_, item)| item.clone()).collect();
+        let Some(action) = ui.pick(&items[selected].label, &menu, 0) else {
+            continue;
+        };
+
+        match actions[action].0 {
+            Action::Read => show_message(message, ui),
+            Action::Tools => {
+                for &i in &entry.tools {
+                    show_tool_results(&messages[i]);
+                }
+            }
+            Action::Copy => {
+                let content = if message.role == "assistant" { message.content.as_text() } else { prompt_text(message) };
+                match codeblocks::copy_to_clipboard(&content) {
+                    Ok(program) => ui.print_success(&format!("Copied message {} ({})", entry.index + 1, program)),
+                    Err(e) => ui.print_error(&e.to_string()),
+                }
+            }
+            Action::Fork => {
+                let keep = if message.role == "assistant" {
+                    entry.tools.last().copied().unwrap_or(entry.index) + 1
+                } else {
+                    entry.index
+                };
+                return Some(keep);
+            }
+        }
+    }
+}
+
+fn show_message(message: &Message, ui: &UI) {
+    println!();
+    if message.role == "assistant" {
+        ui.reset_code_state();
+        ui.print_assistant_prefix();
+        ui.print_token(&message.content.as_text());
+        ui.print_newline();
+    } else {
+        println!("\x1b[32m❯\x1b[0m {}", message.content.as_text());
+    }
+    println!();
+}
+
+fn show_tool_results(message: &Message) {
+    println!();
+    for (name, success, output) in tool_results(message) {
+        let status = if success { "\x1b[38;5;82m✓\x1b[0m" } else { "\x1b[38;5;203m✗\x1b[0m" };
+        println!("  {} \x1b[38;5;75m{}\x1b[0m", status, name);
+        let lines: Vec<&str> = output.lines().collect();
+        for line in lines.iter().take(TOOL_OUTPUT_LINES) {
+            println!("     \x1b[38;5;245m{}\x1b[0m", line);
+        }
+        if lines.len() > TOOL_OUTPUT_LINES {
+            println!("     \x1b[38;5;240m... +{} more lines\x1b[0m", lines.len() - TOOL_OUTPUT_LINES);
+        }
+    }
+    println!();
+}
@@ -0,0 +1,119 @@
+use crate::config::ToolsConfig;
+use anyhow::Result;
+use reqwest::{redirect, Method, Url};
+use serde_json::Value;
+use std::time::Duration;
+
+const DEFAULT_TIMEOUT_SECS: u64 = 30;
+const MAX_REDIRECTS: usize = 10;
+
+pub fn host_allowed(host: &str, allowed: &[String]) -> bool {
+    let host = host.trim_start_matches('[').trim_end_matches(']').to_lowercase();
+    allowed.iter().any(|entry| {
+        let entry = entry.to_lowercase();
+        entry == "*" || host == entry || host.ends_with(&format!(".{}", entry))
+    })
+}
+
+/// Follow a redirect only to where a request could have gone directly, so an
+/// allowed host can't forward the agent to one that isn't
+fn redirect_policy(allowed: Vec<String>) -> redirect::Policy {
+    redirect::Policy::custom(move |attempt| {
+        let host = attempt.url().host_str().unwrap_or_default().to_string();
+        if attempt.previous().len() >= MAX_REDIRECTS {
+            attempt.error(format!("more than {} redirects", MAX_REDIRECTS))
+        } else if !matches!(attempt.url().scheme(), "http" | "https") {
+            attempt.error("redirected to a URL that is not http or https")
+        } else if !host_allowed(&host, &allowed) {
+            attempt.error(format!("redirected to host '{}', which is not allowed", host))
+        } else {
+            attempt.follow()
+        }
+    })
+}
+
+/// Pretty-print JSON bodies, leave everything else as is
+fn format_body(body: &str) -> String {
+    serde_json::from_str::<Value>(body)
+        .ok()
+        .and_then(|json| serde_json::to_string_pretty(&json).ok())
+        .unwrap_or_else(|| body.to_string())
+}
+
+/// Perform an HTTP request for the agent, restricted to the configured hosts
+pub fn http_request(input: &Value, config: &ToolsConfig) -> Result<String> {
+    let url = input
+        .get("url")
+        .and_then(|u| u.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing 'url' parameter"))?;
+    let url = Url::parse(url).map_err(|e| anyhow::anyhow!("Invalid URL '{}': {}", url, e))?;
+
+    if !matches!(url.scheme(), "http" | "https") {
+        anyhow::bail!("Only http and https URLs are supported");
+    }
+    let host = url.host_str().unwrap_or_default();
+    if !host_allowed(host, &config.http_allowed_hosts) {
+        anyhow::bail!(
+            "Host '{}' is not allowed. Add it to [tools] http_allowed_hosts in ~/.aicli/config.toml",
+            host
+        );
+    }
+
+    let method = input.get("method").and_then(|m| m.as_str()).unwrap_or("GET");
+    let method = Method::from_bytes(method.to_uppercase().as_bytes())
+        .map_err(|_| anyhow::anyhow!("Invalid HTTP method '{}'", method))?;
+    let timeout = input
+        .get("timeout_secs")
+        .and_then(|t| t.as_u64())
+        .unwrap_or(DEFAULT_TIMEOUT_SECS);
+
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(timeout))
+        .redirect(redirect_policy(config.http_allowed_hosts.clone()))
+        .build()?;
+    let mut request = client.request(method.clone(), url.clone());
+
+    if let Some(headers) = input.get("headers").and_then(|h| h.as_object()) {
+        for (name, value) in headers {
+            let value = value.as_str().map(|s| s.to_string()).unwrap_or_else(|| value.to_string());
+            request = request.header(name.as_str(), value);
+        }
+    }
+
+    // Objects and arrays are sent as JSON, strings as the raw body
+    match input.get("body") {
+        Some(Value::String(body)) => request = request.body(body.clone()),
+        Some(Value::Null) | None => {}
+        Some(body) => request = request.json(body),
+    }
+
+    let (status, headers, body) = crate::tools::block_on(async {
+        let response = request.send().await?;
+        let status = response.status();
+        let headers: Vec<String> = response
+            .headers()
+            .iter()
+            .map(|(name, value)| format!("{}: {}", name, value.to_str().unwrap_or("<binary>")))
+            .collect();
+        let body = response.text().await?;
+        Ok::<_, reqwest::Error>((status, headers, body))
+    })
+    .map_err(|e| {
+        if e.is_timeout() {
+            anyhow::anyhow!("Request timed out after {}s", timeout)
+        } else if let Some(reason) = e.is_redirect().then(|| std::error::Error::source(&e)).flatten() {
+            anyhow::anyhow!("Request failed: {}", reason)
+        } else {
+            anyhow::anyhow!("Request failed: {}", e)
+        }
+    })?;
+
+    Ok(format!(
+        "{} {}\nHTTP {}\n{}\n\n{}",
+        method,
+        url,
+        status,
+        headers.join("\n"),
+        format_body(&body)
+    ))
+}
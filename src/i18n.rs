@@ -1,16 +1,18 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::process::Command;
+use std::sync::OnceLock;
 
-#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum Language {
+    #[default]
     En,
     Pt,
-}
-
-impl Default for Language {
-    fn default() -> Self {
-        Language::En
-    }
+    Es,
+    Fr,
+    De,
+    Zh,
 }
 
 impl std::fmt::Display for Language {
@@ -18,244 +20,192 @@ impl std::fmt::Display for Language {
         match self {
             Language::En => write!(f, "English"),
             Language::Pt => write!(f, "Português"),
+            Language::Es => write!(f, "Español"),
+            Language::Fr => write!(f, "Français"),
+            Language::De => write!(f, "Deutsch"),
+            Language::Zh => write!(f, "中文"),
         }
     }
 }
 
-pub struct Strings {
-    pub lang: Language,
-}
-
-impl Strings {
-    pub fn new(lang: Language) -> Self {
-        Self { lang }
-    }
-
-    // Banner & Welcome
-    pub fn cli_subtitle(&self) -> &'static str {
-        match self.lang {
-            Language::En => "Your AI Assistant",
-            Language::Pt => "Seu Assistente IA",
+impl Language {
+    pub const ALL: [Language; 6] = [
+        Language::En,
+        Language::Pt,
+        Language::Es,
+        Language::Fr,
+        Language::De,
+        Language::Zh,
+    ];
+
+    /// Code used in config.toml and `/lang`
+    pub fn code(&self) -> &'static str {
+        match self {
+            Language::En => "en",
+            Language::Pt => "pt",
+            Language::Es => "es",
+            Language::Fr => "fr",
+            Language::De => "de",
+            Language::Zh => "zh",
         }
     }
 
-    pub fn tips_commands(&self) -> &'static str {
-        match self.lang {
-            Language::En => "commands",
-            Language::Pt => "comandos",
+    /// Name in English, for instructing the model
+    pub fn english_name(&self) -> &'static str {
+        match self {
+            Language::En => "English",
+            Language::Pt => "Portuguese",
+            Language::Es => "Spanish",
+            Language::Fr => "French",
+            Language::De => "German",
+            Language::Zh => "Simplified Chinese",
         }
     }
 
-    pub fn tips_files(&self) -> &'static str {
-        match self.lang {
-            Language::En => "files",
-            Language::Pt => "arquivos",
+    /// chrono format for dates shown to the user and the model
+    pub fn date_format(&self) -> &'static str {
+        match self {
+            Language::En => "%Y-%m-%d",
+            Language::Pt | Language::Es | Language::Fr => "%d/%m/%Y",
+            Language::De => "%d.%m.%Y",
+            Language::Zh => "%Y年%m月%d日",
+        }
+    }
+
+    /// Language of the OS locale, used on first run before a config exists.
+    /// Falls back to English for unsupported locales.
+    pub fn detect() -> Language {
+        system_locale()
+            .and_then(|locale| Language::from_locale(&locale))
+            .unwrap_or_default()
+    }
+
+    /// "pt_BR.UTF-8", "de-DE", "zh_CN:en" -> language by its code
+    fn from_locale(locale: &str) -> Option<Language> {
+        let code = locale
+            .split(['_', '-', '.', '@', ':'])
+            .next()?
+            .to_lowercase();
+        Language::ALL.into_iter().find(|lang| lang.code() == code)
+    }
+
+    /// Parse a code, English name or native name ("pt", "portuguese", "português")
+    pub fn parse(input: &str) -> Option<Language> {
+        let input = input.trim().to_lowercase();
+        let lang = match input.as_str() {
+            "en" | "english" | "ing" | "inglês" | "ingles" => Language::En,
+            "pt" | "portuguese" | "português" | "portugues" | "br" => Language::Pt,
+            "es" | "spanish" | "español" | "espanol" => Language::Es,
+            "fr" | "french" | "français" | "francais" => Language::Fr,
+            "de" | "german" | "deutsch" => Language::De,
+            "zh" | "chinese" | "中文" | "cn" => Language::Zh,
+            _ => return None,
+        };
+        Some(lang)
+    }
+
+    fn locale_source(&self) -> &'static str {
+        match self {
+            Language::En => include_str!("../locales/en.toml"),
+            Language::Pt => include_str!("../locales/pt.toml"),
+            Language::Es => include_str!("../locales/es.toml"),
+            Language::Fr => include_str!("../locales/fr.toml"),
+            Language::De => include_str!("../locales/de.toml"),
+            Language::Zh => include_str!("../locales/zh.toml"),
         }
     }
+}
 
-    pub fn tips_quit(&self) -> &'static str {
-        match self.lang {
-            Language::En => "quit",
-            Language::Pt => "sair",
+/// Locale name from the POSIX variables, or the OS setting on Windows and
+/// macOS where GUI-launched terminals often leave LANG unset
+fn system_locale() -> Option<String> {
+    for var in ["LC_ALL", "LC_MESSAGES", "LANG", "LANGUAGE"] {
+        if let Ok(value) = std::env::var(var) {
+            if !value.is_empty() && value != "C" && value != "POSIX" && !value.starts_with("C.") {
+                return Some(value);
+            }
         }
     }
 
-    // Commands help
-    pub fn cmd_help(&self) -> &'static str {
-        match self.lang {
-            Language::En => "Show this help",
-            Language::Pt => "Mostra esta ajuda",
-        }
+    let output = match std::env::consts::OS {
+        "windows" => Command::new("powershell")
+            .args(["-NoProfile", "-Command", "(Get-UICulture).Name"])
+            .output(),
+        "macos" => Command::new("defaults").args(["read", "-g", "AppleLocale"]).output(),
+        _ => return None,
     }
+    .ok()
+    .filter(|o| o.status.success())?;
+    let locale = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (!locale.is_empty()).then_some(locale)
+}
 
-    pub fn cmd_exit(&self) -> &'static str {
-        match self.lang {
-            Language::En => "Exit the CLI",
-            Language::Pt => "Sair do CLI",
-        }
-    }
+type Locale = HashMap<String, String>;
+
+/// Locale tables parsed from the embedded `locales/*.toml` on first use
+fn locales() -> &'static HashMap<Language, Locale> {
+    static LOCALES: OnceLock<HashMap<Language, Locale>> = OnceLock::new();
+    LOCALES.get_or_init(|| {
+        Language::ALL
+            .iter()
+            .map(|lang| {
+                let table = toml::from_str::<Locale>(lang.locale_source()).unwrap_or_else(|e| {
+                    panic!("locales/{}.toml is invalid: {}", lang.code(), e)
+                });
+                (*lang, table)
+            })
+            .collect()
+    })
+}
 
-    pub fn cmd_clear(&self) -> &'static str {
-        match self.lang {
-            Language::En => "Clear history",
-            Language::Pt => "Limpar histórico",
-        }
-    }
+pub struct Strings {
+    pub lang: Language,
+}
 
-    pub fn cmd_model(&self) -> &'static str {
-        match self.lang {
-            Language::En => "List models",
-            Language::Pt => "Listar modelos",
-        }
+impl Strings {
+    pub fn new(lang: Language) -> Self {
+        Self { lang }
     }
 
-    pub fn cmd_model_switch(&self) -> &'static str {
-        match self.lang {
-            Language::En => "Switch model",
-            Language::Pt => "Trocar modelo",
-        }
+    /// String for `key` in this language, falling back to English and then
+    /// to the key itself
+    pub fn get(&self, key: &'static str) -> &'static str {
+        let locales = locales();
+        [self.lang, Language::En]
+            .iter()
+            .find_map(|lang| locales.get(lang)?.get(key))
+            .map(|s| s.as_str())
+            .unwrap_or(key)
     }
 
-    pub fn cmd_add_model(&self) -> &'static str {
-        match self.lang {
-            Language::En => "Add new model",
-            Language::Pt => "Adicionar modelo",
-        }
+    /// `get` with `{name}` placeholders replaced
+    pub fn format(&self, key: &'static str, args: &[(&str, &str)]) -> String {
+        args.iter().fold(self.get(key).to_string(), |text, (name, value)| {
+            text.replace(&format!("{{{}}}", name), value)
+        })
     }
 
-    pub fn cmd_config(&self) -> &'static str {
-        match self.lang {
-            Language::En => "Show config",
-            Language::Pt => "Mostrar config",
-        }
+    /// Whether `answer` means yes in this language (English always works)
+    pub fn is_yes(&self, answer: &str) -> bool {
+        let answer = answer.trim().to_lowercase();
+        !answer.is_empty() && self.get("yes_answers").split_whitespace().any(|yes| yes == answer)
     }
+}
 
-    pub fn cmd_lang(&self) -> &'static str {
-        match self.lang {
-            Language::En => "Change language",
-            Language::Pt => "Mudar idioma",
+/// Shorthand accessors for the most used keys
+macro_rules! strings {
+    ($($key:ident),* $(,)?) => {
+        impl Strings {
+            $(pub fn $key(&self) -> &'static str { self.get(stringify!($key)) })*
         }
-    }
+    };
+}
 
+strings!(
+    // Commands help
+    cmd_help, cmd_exit, cmd_clear, cmd_model, cmd_config, cmd_lang,
     // Section titles
-    pub fn title_commands(&self) -> &'static str {
-        match self.lang {
-            Language::En => "Commands",
-            Language::Pt => "Comandos",
-        }
-    }
-
-    pub fn title_models(&self) -> &'static str {
-        match self.lang {
-            Language::En => "Available Models",
-            Language::Pt => "Modelos Disponíveis",
-        }
-    }
-
-    pub fn title_config(&self) -> &'static str {
-        match self.lang {
-            Language::En => "Configuration",
-            Language::Pt => "Configuração",
-        }
-    }
-
-    pub fn title_context(&self) -> &'static str {
-        match self.lang {
-            Language::En => "Context",
-            Language::Pt => "Contexto",
-        }
-    }
-
-    pub fn title_language(&self) -> &'static str {
-        match self.lang {
-            Language::En => "Language",
-            Language::Pt => "Idioma",
-        }
-    }
-
+    title_commands, title_models, title_language,
     // Messages
-    pub fn thinking(&self) -> &'static str {
-        match self.lang {
-            Language::En => "Thinking...",
-            Language::Pt => "Pensando...",
-        }
-    }
-
-    pub fn executing(&self) -> &'static str {
-        match self.lang {
-            Language::En => "Executing",
-            Language::Pt => "Executando",
-        }
-    }
-
-    pub fn switched_to(&self) -> &'static str {
-        match self.lang {
-            Language::En => "Switched to",
-            Language::Pt => "Trocado para",
-        }
-    }
-
-    pub fn cleared(&self) -> &'static str {
-        match self.lang {
-            Language::En => "Conversation cleared",
-            Language::Pt => "Conversa limpa",
-        }
-    }
-
-    pub fn goodbye(&self) -> &'static str {
-        match self.lang {
-            Language::En => "Goodbye!",
-            Language::Pt => "Até logo!",
-        }
-    }
-
-    pub fn not_found(&self) -> &'static str {
-        match self.lang {
-            Language::En => "not found",
-            Language::Pt => "não encontrado",
-        }
-    }
-
-    pub fn unknown_cmd(&self) -> &'static str {
-        match self.lang {
-            Language::En => "Unknown command (try /help)",
-            Language::Pt => "Comando desconhecido (tente /help)",
-        }
-    }
-
-    pub fn file_context_hint(&self) -> &'static str {
-        match self.lang {
-            Language::En => "Use @path/file to include files",
-            Language::Pt => "Use @caminho/arquivo para incluir arquivos",
-        }
-    }
-
-    pub fn example(&self) -> &'static str {
-        match self.lang {
-            Language::En => "Example",
-            Language::Pt => "Exemplo",
-        }
-    }
-
-    pub fn select_language(&self) -> &'static str {
-        match self.lang {
-            Language::En => "Select language",
-            Language::Pt => "Selecione o idioma",
-        }
-    }
-
-    pub fn language_changed(&self) -> &'static str {
-        match self.lang {
-            Language::En => "Language changed to",
-            Language::Pt => "Idioma alterado para",
-        }
-    }
-
-    pub fn current(&self) -> &'static str {
-        match self.lang {
-            Language::En => "current",
-            Language::Pt => "atual",
-        }
-    }
-
-    pub fn model_switch_hint(&self) -> &'static str {
-        match self.lang {
-            Language::En => "/model <name> to switch",
-            Language::Pt => "/model <nome> para trocar",
-        }
-    }
-
-    pub fn add_model_hint(&self) -> &'static str {
-        match self.lang {
-            Language::En => "/add-model to add new",
-            Language::Pt => "/add-model para adicionar",
-        }
-    }
-
-    pub fn ctrl_c_hint(&self) -> &'static str {
-        match self.lang {
-            Language::En => "Ctrl+C - type /exit to quit",
-            Language::Pt => "Ctrl+C - digite /exit para sair",
-        }
-    }
-}
+    thinking, cleared, goodbye, not_found, unknown_cmd, language_changed, ctrl_c_hint,
+);
@@ -0,0 +1,90 @@
+use anyhow::{Context, Result};
+use base64::Engine;
+use serde_json::{json, Value};
+use std::path::PathBuf;
+
+use crate::config::{ImagesConfig, ModelConfig};
+use crate::paths;
+
+const API_VERSION: &str = "2024-02-01";
+/// Words of the prompt used in the file name
+const NAME_WORDS: usize = 5;
+
+/// An image /imagine saved to disk
+pub struct GeneratedImage {
+    pub path: PathBuf,
+    /// DALL-E 3 rewrites prompts before drawing; this is what it drew
+    pub revised_prompt: Option<String>,
+}
+
+/// Generate one image from `prompt` with the [images] deployment and save
+/// it as PNG. The endpoint and key default to the active model's.
+pub async fn generate(config: &ImagesConfig, model: &ModelConfig, prompt: &str) -> Result<GeneratedImage> {
+    if config.deployment.is_empty() {
+        anyhow::bail!("Set [images] deployment to your DALL-E deployment name in config.toml");
+    }
+    let endpoint = if config.endpoint.is_empty() { &model.endpoint } else { &config.endpoint };
+    let api_key = if config.api_key.is_empty() { &model.api_key } else { &config.api_key };
+    let url = format!(
+        "{}/openai/deployments/{}/images/generations?api-version={}",
+        endpoint.trim_end_matches('/'),
+        config.deployment,
+        API_VERSION
+    );
+
+    crate::policy::check_endpoint(&url)?;
+    let client = reqwest::Client::new();
+    let response = client
+        .post(&url)
+        .header("api-key", api_key)
+        .json(&json!({ "prompt": prompt, "n": 1, "size": config.size }))
+        .send()
+        .await
+        .context("Could not reach the image deployment")?;
+    let status = response.status();
+    let body: Value = response.json().await.unwrap_or_default();
+    if !status.is_success() {
+        let message = body["error"]["message"].as_str().unwrap_or("no details");
+        anyhow::bail!("Image generation failed ({}): {}", status, message);
+    }
+
+    let image = &body["data"][0];
+    // Newer image models only return base64; DALL-E returns a URL by default
+    let png = if let Some(data) = image["b64_json"].as_str() {
+        base64::engine::general_purpose::STANDARD.decode(data).context("Invalid image data")?
+    } else if let Some(link) = image["url"].as_str() {
+        client.get(link).send().await?.error_for_status()?.bytes().await?.to_vec()
+    } else {
+        anyhow::bail!("The response contained no image");
+    };
+
+    let dir = if config.output_dir.is_empty() {
+        std::env::current_dir()?
+    } else {
+        paths::resolve(&config.output_dir)
+    };
+    std::fs::create_dir_all(&dir)?;
+    let path = dir.join(file_name(prompt));
+    std::fs::write(&path, png).with_context(|| format!("Failed to write {}", path.display()))?;
+
+    Ok(GeneratedImage {
+        path,
+        revised_prompt: image["revised_prompt"].as_str().map(|p| p.to_string()),
+    })
+}
+
+/// "a-logo-for-my-cat-20261014-153000.png"
+fn file_name(prompt: &str) -> String {
+    let slug: Vec<String> = prompt
+        .split_whitespace()
+        .map(|word| word.chars().filter(|c| c.is_alphanumeric()).collect::<String>().to_lowercase())
+        .filter(|word| !word.is_empty())
+        .take(NAME_WORDS)
+        .collect();
+    let stamp = chrono::Local::now().format("%Y%m%d-%H%M%S");
+    if slug.is_empty() {
+        format!("image-{}.png", stamp)
+    } else {
+        format!("{}-{}.png", slug.join("-"), stamp)
+    }
+}
@@ -0,0 +1,252 @@
+use anyhow::{Context, Result};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs;
+
+use crate::client::{Message, MessageContent};
+use crate::console::println;
+use crate::session::{self, Session, SessionInfo};
+use crate::text;
+
+/// Tool output kept per imported call; the rest is cut
+const MAX_TOOL_OUTPUT_CHARS: usize = 4000;
+const MAX_TOOL_INPUT_CHARS: usize = 300;
+
+/// Where an exported conversation came from
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    /// conversations.json from ChatGPT's "Export data"
+    ChatGpt,
+    /// A session transcript from ~/.claude/projects/<project>/<id>.jsonl
+    ClaudeCode,
+}
+
+impl Format {
+    pub const NAMES: [&'static str; 2] = ["chatgpt", "claude-code"];
+
+    pub fn parse(name: &str) -> Option<Format> {
+        match name.to_lowercase().as_str() {
+            "chatgpt" | "openai" => Some(Format::ChatGpt),
+            "claude-code" | "claude" => Some(Format::ClaudeCode),
+            _ => None,
+        }
+    }
+}
+
+/// `aicli import --format <format> <file>`: convert the export and save every
+/// conversation in it as an aicli session
+pub fn run(format: Format, file: &str) -> Result<()> {
+    let path = crate::paths::resolve(file);
+    let content = fs::read_to_string(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let sessions = match format {
+        Format::ChatGpt => parse_chatgpt(&content)?,
+        Format::ClaudeCode => vec![parse_claude_code(&content)?],
+    };
+    if sessions.is_empty() {
+        anyhow::bail!("{} has no conversations with messages", path.display());
+    }
+
+    for mut session in sessions {
+        session.info.id = unused_id(&session.info.id);
+        session::write(&session)?;
+        println!(
+            "\x1b[32m✓\x1b[0m \x1b[38;5;141m{}\x1b[0m  {}  \x1b[90m{} msgs\x1b[0m",
+            session.info.id,
+            text::truncate(&session.display_title(), 60),
+            session.messages.len()
+        );
+    }
+    println!("\n\x1b[90mContinue one with: aicli sessions resume <id>\x1b[0m");
+    Ok(())
+}
+
+/// `id`, or `id-2`, `id-3`... when a saved session already has it
+fn unused_id(id: &str) -> String {
+    if !session::exists(id) {
+        return id.to_string();
+    }
+    (2..).map(|n| format!("{}-{}", id, n)).find(|c| !session::exists(c)).unwrap_or_default()
+}
+
+/// A session started at `timestamp` (Unix seconds, 0 for unknown)
+fn new_session(timestamp: i64, model: Option<String>, cwd: Option<String>, title: Option<String>) -> SessionInfo {
+    let mut info = SessionInfo::new(&model.unwrap_or_else(|| "imported".to_string()));
+    if let Some(started) = chrono::DateTime::from_timestamp(timestamp, 0).filter(|_| timestamp > 0) {
+        info.id = started.with_timezone(&chrono::Local).format("%Y%m%d-%H%M%S").to_string();
+        info.created_at = timestamp;
+    }
+    if let Some(cwd) = cwd {
+        info.cwd = cwd;
+    }
+    info.title = title.map(|t| text::truncate(t.trim(), 60)).filter(|t| !t.is_empty());
+    info
+}
+
+/// Append to the history, merging into the previous message when the role
+/// repeats so user and assistant turns alternate
+fn push(messages: &mut Vec<Message>, role: &str, text: String) {
+    let text = text.trim().to_string();
+    if text.is_empty() {
+        return;
+    }
+    if let Some(last) = messages.last_mut().filter(|m| m.role == role) {
+        last.content = MessageContent::Text(format!("{}\n\n{}", last.content.as_text(), text));
+        return;
+    }
+    messages.push(Message { role: role.to_string(), content: MessageContent::Text(text) });
+}
+
+/// ChatGPT's export: one conversation object or an array of them. Each is a
+/// tree of message nodes; the branch ending at `current_node` is the one the
+/// user last saw.
+fn parse_chatgpt(content: &str) -> Result<Vec<Session>> {
+    let value: Value = serde_json::from_str(content).context("Not a ChatGPT export (invalid JSON)")?;
+    let conversations = match &value {
+        Value::Array(items) => items.iter().collect(),
+        Value::Object(_) => vec![&value],
+        _ => anyhow::bail!("Not a ChatGPT export"),
+    };
+    if conversations.iter().any(|c| c.get("mapping").is_none()) {
+        anyhow::bail!("Not a ChatGPT export: conversations have no 'mapping'");
+    }
+    Ok(conversations.into_iter().filter_map(chatgpt_conversation).collect())
+}
+
+fn chatgpt_conversation(conversation: &Value) -> Option<Session> {
+    let mapping = conversation["mapping"].as_object()?;
+    let leaf = conversation["current_node"].as_str().map(String::from).or_else(|| {
+        // Older exports have no current_node: take the newest leaf
+        mapping
+            .iter()
+            .filter(|(_, node)| node["children"].as_array().is_none_or(|c| c.is_empty()))
+            .max_by(|a, b| {
+                let time = |node: &Value| node["message"]["create_time"].as_f64().unwrap_or(0.0);
+                time(a.1).total_cmp(&time(b.1))
+            })
+            .map(|(id, _)| id.clone())
+    })?;
+
+    let mut branch = Vec::new();
+    let mut next = Some(leaf);
+    while let Some(node) = next.and_then(|id| mapping.get(&id)) {
+        branch.push(node);
+        next = node["parent"].as_str().map(String::from);
+    }
+    branch.reverse();
+
+    let mut messages = Vec::new();
+    let mut model = None;
+    for node in branch {
+        let message = &node["message"];
+        let role = message["author"]["role"].as_str().unwrap_or_default();
+        if !matches!(role, "user" | "assistant") || message["metadata"]["is_visually_hidden_from_conversation"] == true {
+            continue;
+        }
+        let content = &message["content"];
+        let text = match content["content_type"].as_str().unwrap_or_default() {
+            "text" | "multimodal_text" => content["parts"]
+                .as_array()
+                .map(|parts| parts.iter().filter_map(|p| p.as_str()).collect::<Vec<_>>().join("\n"))
+                .unwrap_or_default(),
+            "code" => format!("```\n{}\n```", content["text"].as_str().unwrap_or_default()),
+            _ => continue,
+        };
+        if let Some(slug) = message["metadata"]["model_slug"].as_str() {
+            model = Some(slug.to_string());
+        }
+        push(&mut messages, role, text);
+    }
+    if messages.is_empty() {
+        return None;
+    }
+
+    let time = |key: &str| conversation[key].as_f64().unwrap_or(0.0) as i64;
+    let title = conversation["title"].as_str().map(String::from);
+    let mut info = new_session(time("create_time"), model, None, title);
+    info.updated_at = time("update_time").max(info.created_at);
+    Some(Session { info, messages })
+}
+
+/// Claude Code's JSONL transcript: one event per line. Text and tool calls
+/// are kept (tool results in aicli's own "Tool execution results" form);
+/// meta events and subagent sidechains are dropped.
+fn parse_claude_code(content: &str) -> Result<Session> {
+    let mut messages = Vec::new();
+    let mut title = None;
+    let mut model = None;
+    let mut cwd = None;
+    let mut times = Vec::new();
+    // tool_use id -> tool name, to label the results
+    let mut tools: HashMap<String, String> = HashMap::new();
+
+    for line in content.lines().filter(|l| !l.trim().is_empty()) {
+        let event: Value = serde_json::from_str(line).context("Not a Claude Code transcript (invalid JSON line)")?;
+        let kind = event["type"].as_str().unwrap_or_default();
+        if kind == "summary" {
+            title = event["summary"].as_str().map(String::from);
+            continue;
+        }
+        if !matches!(kind, "user" | "assistant") || event["isMeta"] == true || event["isSidechain"] == true {
+            continue;
+        }
+        if let Some(at) = event["timestamp"].as_str().and_then(|t| chrono::DateTime::parse_from_rfc3339(t).ok()) {
+            times.push(at.timestamp());
+        }
+        if cwd.is_none() {
+            cwd = event["cwd"].as_str().map(String::from);
+        }
+        let message = &event["message"];
+        if let Some(name) = message["model"].as_str().filter(|m| !m.starts_with('<')) {
+            model = Some(name.to_string());
+        }
+
+        let blocks = match &message["content"] {
+            Value::String(text) => vec![serde_json::json!({ "type": "text", "text": text })],
+            Value::Array(blocks) => blocks.clone(),
+            _ => continue,
+        };
+        let mut said = Vec::new();
+        let mut results = Vec::new();
+        for block in &blocks {
+            match block["type"].as_str().unwrap_or_default() {
+                "text" => {
+                    let text = block["text"].as_str().unwrap_or_default();
+                    // Slash-command bookkeeping the CLI writes as user turns
+                    if !text.trim_start().starts_with("<command-") && !text.trim_start().starts_with("<local-command-") {
+                        said.push(text.to_string());
+                    }
+                }
+                "tool_use" => {
+                    let name = block["name"].as_str().unwrap_or("tool").to_string();
+                    if let Some(id) = block["id"].as_str() {
+                        tools.insert(id.to_string(), name.clone());
+                    }
+                    said.push(format!("[Tool call: {} {}]", name, text::truncate(&block["input"].to_string(), MAX_TOOL_INPUT_CHARS)));
+                }
+                "tool_result" => {
+                    let name = block["tool_use_id"].as_str().and_then(|id| tools.get(id)).map(|n| n.as_str()).unwrap_or("tool");
+                    let output = match &block["content"] {
+                        Value::String(text) => text.clone(),
+                        Value::Array(parts) => parts.iter().filter_map(|p| p["text"].as_str()).collect::<Vec<_>>().join("\n"),
+                        _ => String::new(),
+                    };
+                    let success = block["is_error"] != true;
+                    results.push(format!("[Tool: {} | Success: {}]\n{}", name, success, text::truncate(&output, MAX_TOOL_OUTPUT_CHARS)));
+                }
+                _ => {}
+            }
+        }
+        if !results.is_empty() {
+            push(&mut messages, "user", format!("Tool execution results:\n\n{}", results.join("\n\n---\n\n")));
+        }
+        push(&mut messages, kind, said.join("\n\n"));
+    }
+    if messages.is_empty() {
+        anyhow::bail!("Not a Claude Code transcript: no user or assistant messages");
+    }
+
+    let started = times.iter().min().copied().unwrap_or(0);
+    let mut info = new_session(started, model, cwd, title);
+    info.updated_at = times.iter().max().copied().unwrap_or(info.created_at);
+    Ok(Session { info, messages })
+}
@@ -1,35 +1,108 @@
+use crate::graphics;
+use crate::i18n::Language;
+use crate::paths;
+use crate::redact;
+use crate::text;
 use rustyline::completion::{Completer, Pair};
 use rustyline::error::ReadlineError;
 use rustyline::highlight::Highlighter;
-use rustyline::hint::Hinter;
+use rustyline::hint::{Hint, Hinter};
 use rustyline::validate::Validator;
-use rustyline::{Context, Editor};
+use rustyline::{
+    Cmd, ConditionalEventHandler, Context, Editor, Event, EventContext, EventHandler, ExternalPrinter, KeyCode, KeyEvent,
+    Modifiers, RepeatCount,
+};
 use rustyline_derive::Helper;
 use std::borrow::Cow;
+use std::collections::HashMap;
 use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
 
-const COMMANDS: &[(&str, &str)] = &[
+pub const COMMANDS: &[(&str, &str)] = &[
     ("/help", "Show available commands"),
     ("/exit", "Exit the CLI"),
     ("/quit", "Exit the CLI"),
     ("/clear", "Clear conversation history"),
     ("/model", "List and switch models"),
     ("/config", "Show current configuration"),
-    ("/history", "Show conversation history"),
+    ("/history", "Browse the conversation, copy or fork from a message"),
+    ("/cost", "Show session token usage and cost"),
     ("/add-model", "Add a new model"),
     ("/lang", "Change language (en/pt)"),
+    ("/router", "Toggle fast/smart model routing"),
+    ("/pr", "Push the branch and open a GitHub pull request"),
+    ("/redact", "Toggle secret redaction (on/off)"),
+    ("/copy-code", "Copy a code block from the last answer"),
+    ("/save-code", "Save a code block to a file"),
+    ("/apply-code", "Write code blocks to the files they name"),
+    ("/apply", "Apply the last diff from the answer"),
+    ("/title", "Show or set the session title"),
+    ("/search", "Search saved conversations"),
+    ("/remember", "Remember a fact across sessions"),
+    ("/memory", "List or delete remembered facts"),
+    ("/resume", "Pick a saved session to resume"),
+    ("/pin", "Pin files sent with every request"),
+    ("/unpin", "Unpin files"),
+    ("/init", "Generate AICLI.md for this project"),
+    ("/speak", "Read answers aloud (Azure Speech)"),
+    ("/mic", "Speak a prompt (Azure Speech)"),
+    ("/imagine", "Generate an image (Azure DALL-E)"),
+    ("/continue", "Continue the last answer"),
+    ("/output", "Also write answers to a file"),
+    ("/perf", "Local latency and error metrics"),
+    ("/dryrun", "Describe tool changes without making them"),
+    ("/revert", "Undo the agent's file changes of a turn"),
+    ("/export", "Save the conversation as Markdown"),
+    ("/fix", "Send build errors to the agent to fix"),
+    ("/tail", "Follow a log file and attach its new lines"),
+    ("/quote", "Quote a message or code block in the next prompt"),
+    ("/retry", "Send the last prompt again, optionally with another model or temperature"),
+    ("/compare", "Send a prompt to two models at once and compare the answers"),
+    ("/best", "Ask for several answers and keep the best one"),
+    ("/fork", "Continue in a copy of this session, keeping the original"),
+    ("/pipe", "Pipe answers through a command before showing them"),
+    ("/context", "Show and trim what the next request sends"),
+    ("/thinking", "Show Claude's thinking for the last answer"),
     ("/install", "Install AICLI globally"),
     ("/uninstall", "Uninstall AICLI"),
 ];
 
+/// `[snippets]`: what each `;name` typed at the prompt expands to
+type Snippets = Arc<Mutex<HashMap<String, String>>>;
+
 #[derive(Helper)]
 pub struct InputHelper {
     pub model_names: Vec<String>,
+    snippets: Snippets,
+    /// Set by Space after a snippet: the completion that follows replaces
+    /// that snippet alone
+    expanding: Arc<AtomicBool>,
+    /// Tokens the conversation uses and the model's context window, for the
+    /// estimate shown while a prompt is typed
+    context: (usize, usize),
+}
+
+/// Shown dimmed after the cursor. Only the rest of a command name can be
+/// taken with the right arrow; the token estimate is just for reading.
+pub struct InputHint {
+    display: String,
+    completion: Option<String>,
+}
+
+impl Hint for InputHint {
+    fn display(&self) -> &str {
+        &self.display
+    }
+
+    fn completion(&self) -> Option<&str> {
+        self.completion.as_deref()
+    }
 }
 
 impl InputHelper {
-    pub fn new(model_names: Vec<String>) -> Self {
-        Self { model_names }
+    pub fn new(model_names: Vec<String>, snippets: Snippets, expanding: Arc<AtomicBool>) -> Self {
+        Self { model_names, snippets, expanding, context: (0, 0) }
     }
 
     pub fn update_models(&mut self, model_names: Vec<String>) {
@@ -59,80 +132,24 @@ impl InputHelper {
 
         if let Some(at_pos) = before_cursor.rfind('@') {
             let partial_path = &before_cursor[at_pos + 1..];
-
-            // Determine directory and prefix
-            let (dir, prefix) = if partial_path.contains('/') || partial_path.contains('\\') {
-                let path = Path::new(partial_path);
-                if let Some(parent) = path.parent() {
-                    let file_prefix = path.file_name()
-                        .and_then(|f| f.to_str())
-                        .unwrap_or("");
-                    (parent.to_string_lossy().to_string(), file_prefix.to_string())
-                } else {
-                    (".".to_string(), partial_path.to_string())
-                }
-            } else {
-                (".".to_string(), partial_path.to_string())
-            };
+            let quoted = partial_path.starts_with('"');
+            let partial_path = partial_path.trim_start_matches('"');
 
             let mut matches = Vec::new();
-            let search_dir = if dir.is_empty() { "." } else { &dir };
-
-            // Collect files with metadata for sorting
-            let mut files_with_time: Vec<(String, String, bool, std::time::SystemTime)> = Vec::new();
-
-            if let Ok(entries) = std::fs::read_dir(search_dir) {
-                for entry in entries.flatten() {
-                    if let Ok(metadata) = entry.metadata() {
-                        let name = entry.file_name().to_string_lossy().to_string();
-
-                        // Skip hidden files unless searching for them
-                        if name.starts_with('.') && !prefix.starts_with('.') {
-                            continue;
-                        }
-
-                        // Skip common ignored directories
-                        if name == "node_modules" || name == "target" || name == ".git" {
-                            continue;
-                        }
-
-                        // Filter by prefix (case insensitive)
-                        if prefix.is_empty() || name.to_lowercase().starts_with(&prefix.to_lowercase()) {
-                            let is_dir = metadata.is_dir();
-                            let full_path = if dir == "." {
-                                name.clone()
-                            } else {
-                                format!("{}/{}", dir, name)
-                            };
-
-                            let modified = metadata.modified().unwrap_or(std::time::SystemTime::UNIX_EPOCH);
-                            files_with_time.push((name, full_path, is_dir, modified));
-                        }
-                    }
-                }
-            }
-
-            // Sort: directories first, then by modification time (most recent first)
-            files_with_time.sort_by(|a, b| {
-                match (a.2, b.2) {
-                    (true, false) => std::cmp::Ordering::Less,
-                    (false, true) => std::cmp::Ordering::Greater,
-                    _ => b.3.cmp(&a.3),
-                }
-            });
-
-            // Take top 6 results
-            for (name, full_path, is_dir, _) in files_with_time.into_iter().take(6) {
+            for (name, full_path, is_dir, separator) in list_paths(partial_path) {
                 let display = if is_dir {
                     format!("+ {}/", name)
                 } else {
                     format!("+ {}", name)
                 };
 
-                let replacement = if is_dir {
-                    format!("@{}/", full_path)
-                } else {
-                    format!("@{}", full_path)
+                // Quote paths with spaces; directories stay open so typing can continue
+                let needs_quotes = quoted || full_path.contains(' ');
+                let replacement = match (is_dir, needs_quotes) {
+                    (true, true) => format!("@\"{}{}", full_path, separator),
+                    (true, false) => format!("@{}{}", full_path, separator),
+                    (false, true) => format!("@\"{}\"", full_path),
+                    (false, false) => format!("@{}", full_path),
                 };
 
                 matches.push(Pair {
@@ -147,6 +164,70 @@ impl InputHelper {
         }
     }
 
+    /// The argument after `/resume`, `/lang`, `/export`, `/output` or
+    /// `/tail`: session ids, language codes or paths. Returns where the
+    /// argument starts.
+    fn complete_argument(&self, line: &str, pos: usize) -> (usize, Vec<Pair>) {
+        let before_cursor = &line[..pos];
+        let Some((command, partial)) = before_cursor.split_once(' ') else {
+            return (0, Vec::new());
+        };
+        if partial.contains(' ') {
+            return (0, Vec::new());
+        }
+        let start = command.len() + 1;
+
+        let matches = match command.to_lowercase().as_str() {
+            "/resume" => crate::session::list()
+                .into_iter()
+                .filter(|s| s.info.id.starts_with(partial))
+                .take(10)
+                .map(|s| {
+                    let title = s.display_title().split_whitespace().collect::<Vec<_>>().join(" ");
+                    Pair { display: format!("{}  {}", s.info.id, text::truncate(&title, 50)), replacement: s.info.id }
+                })
+                .collect(),
+            "/lang" => Language::ALL
+                .iter()
+                .filter(|lang| lang.code().starts_with(&partial.to_lowercase()))
+                .map(|lang| Pair { display: format!("{}  {}", lang.code(), lang), replacement: lang.code().to_string() })
+                .collect(),
+            "/export" | "/output" | "/tail" => list_paths(partial)
+                .into_iter()
+                .map(|(name, full_path, is_dir, separator)| match is_dir {
+                    true => Pair { display: format!("+ {}/", name), replacement: format!("{}{}", full_path, separator) },
+                    false => Pair { display: format!("+ {}", name), replacement: full_path },
+                })
+                .collect(),
+            _ => Vec::new(),
+        };
+        (start, matches)
+    }
+
+    /// The `;name` snippets starting with the word before the cursor
+    fn complete_snippet(&self, line: &str, pos: usize) -> (usize, Vec<Pair>) {
+        let Some((start, partial)) = snippet_before(line, pos) else {
+            return (0, Vec::new());
+        };
+        let Ok(snippets) = self.snippets.lock() else {
+            return (0, Vec::new());
+        };
+        if self.expanding.swap(false, Ordering::Relaxed) {
+            let matches = snippets.get(partial).map(|text| Pair { display: String::new(), replacement: format!("{} ", text) });
+            return (start, matches.into_iter().collect());
+        }
+        let mut names: Vec<&String> = snippets.keys().filter(|name| name.starts_with(partial)).collect();
+        names.sort();
+        let matches = names
+            .into_iter()
+            .map(|name| {
+                let preview = snippets[name].split_whitespace().collect::<Vec<_>>().join(" ");
+                Pair { display: format!(";{} - {}", name, text::truncate(&preview, 50)), replacement: snippets[name].clone() }
+            })
+            .collect();
+        (start, matches)
+    }
+
     fn complete_model(&self, line: &str) -> Vec<Pair> {
         let mut matches = Vec::new();
         let lower_line = line.to_lowercase();
@@ -172,6 +253,64 @@ impl InputHelper {
     }
 }
 
+/// The entries of the directory `partial_path` names whose names start with
+/// what follows its last separator: name, path as typed, whether it is a
+/// directory, and the separator to add after one
+fn list_paths(partial_path: &str) -> Vec<(String, String, bool, char)> {
+    // Split into directory (with its trailing separator) and prefix.
+    // Backslash is only a separator on Windows.
+    let is_separator = |c: char| c == '/' || (cfg!(windows) && c == '\\');
+    let (dir, prefix) = match partial_path.rfind(is_separator) {
+        Some(i) => (&partial_path[..=i], &partial_path[i + 1..]),
+        None => ("", partial_path),
+    };
+    let separator = if dir.ends_with('\\') { '\\' } else { '/' };
+
+    let search_dir = if dir.is_empty() { Path::new(".").to_path_buf() } else { paths::resolve(dir) };
+
+    // Collect files with metadata for sorting
+    let mut files_with_time: Vec<(String, String, bool, std::time::SystemTime)> = Vec::new();
+
+    if let Ok(entries) = std::fs::read_dir(search_dir) {
+        for entry in entries.flatten() {
+            if let Ok(metadata) = entry.metadata() {
+                let name = entry.file_name().to_string_lossy().to_string();
+
+                // Skip hidden files unless searching for them
+                if name.starts_with('.') && !prefix.starts_with('.') {
+                    continue;
+                }
+
+                // Skip common ignored directories
+                if name == "node_modules" || name == "target" || name == ".git" {
+                    continue;
+                }
+
+                // Filter by prefix (case insensitive)
+                if prefix.is_empty() || name.to_lowercase().starts_with(&prefix.to_lowercase()) {
+                    let is_dir = metadata.is_dir();
+                    let full_path = format!("{}{}", dir, name);
+
+                    let modified = metadata.modified().unwrap_or(std::time::SystemTime::UNIX_EPOCH);
+                    files_with_time.push((name, full_path, is_dir, modified));
+                }
+            }
+        }
+    }
+
+    // Sort: directories first, then by modification time (most recent first)
+    files_with_time.sort_by(|a, b| {
+        match (a.2, b.2) {
+            (true, false) => std::cmp::Ordering::Less,
+            (false, true) => std::cmp::Ordering::Greater,
+            _ => b.3.cmp(&a.3),
+        }
+    });
+
+    // Take top 6 results
+    files_with_time.into_iter().take(6).map(|(name, full_path, is_dir, _)| (name, full_path, is_dir, separator)).collect()
+}
+
 impl Completer for InputHelper {
     type Candidate = Pair;
 
@@ -189,6 +328,14 @@ impl Completer for InputHelper {
             }
         }
 
+        // Check for ;snippet completion
+        if line.contains(';') {
+            let (start, matches) = self.complete_snippet(line, pos);
+            if !matches.is_empty() {
+                return Ok((start, matches));
+            }
+        }
+
         // Check for /model completion
         if line.to_lowercase().starts_with("/model") {
             let matches = self.complete_model(line);
@@ -197,6 +344,14 @@ impl Completer for InputHelper {
             }
         }
 
+        // Check for the arguments of other commands
+        if line.starts_with('/') && line.contains(' ') {
+            let (start, matches) = self.complete_argument(line, pos);
+            if !matches.is_empty() {
+                return Ok((start, matches));
+            }
+        }
+
         // Check for / command completion
         if line.starts_with('/') {
             let matches = self.complete_command(line);
@@ -208,9 +363,9 @@ impl Completer for InputHelper {
 }
 
 impl Hinter for InputHelper {
-    type Hint = String;
+    type Hint = InputHint;
 
-    fn hint(&self, line: &str, pos: usize, _ctx: &Context<'_>) -> Option<String> {
+    fn hint(&self, line: &str, pos: usize, _ctx: &Context<'_>) -> Option<InputHint> {
         if pos < line.len() {
             return None;
         }
@@ -221,15 +376,58 @@ impl Hinter for InputHelper {
             for (cmd, desc) in COMMANDS {
                 if cmd.starts_with(&input) && *cmd != input {
                     let hint = &cmd[line.len()..];
-                    return Some(format!("{} \x1b[38;5;245m({})\x1b[0m", hint, desc));
+                    return Some(InputHint {
+                        display: format!("{} \x1b[38;5;245m({})\x1b[0m", hint, desc),
+                        completion: Some(hint.to_string()),
+                    });
                 }
             }
         }
 
+        // What the prompt and its @files will add to the context
+        if !line.trim().is_empty() && !line.starts_with('/') {
+            let tokens = estimate_prompt_tokens(line);
+            let (used, max) = self.context;
+            let percent = ((used + tokens) * 100).checked_div(max).unwrap_or(0);
+            let color = match percent {
+                0..=79 => "\x1b[38;5;240m",
+                80..=99 => "\x1b[38;5;214m",
+                _ => "\x1b[38;5;203m",
+            };
+            return Some(InputHint {
+                display: format!("  {}~{} tokens · {}% of context\x1b[0m", color, format_tokens(tokens), percent),
+                completion: None,
+            });
+        }
+
         None
     }
 }
 
+/// Rough size of a prompt as it will be sent (1 token ≈ 4 chars), counting
+/// the files it references; an image only adds a line naming it
+fn estimate_prompt_tokens(line: &str) -> usize {
+    let text = strip_file_references(line).len();
+    let files: u64 = parse_file_references(line)
+        .iter()
+        .map(|file| paths::resolve(file))
+        .filter_map(|path| match graphics::is_image(&path) {
+            true => path.is_file().then_some(80),
+            false => std::fs::metadata(&path).ok().filter(|m| m.is_file()).map(|m| m.len()),
+        })
+        .sum();
+    (text + files as usize).div_ceil(4)
+}
+
+/// 1234 as "1.2k"
+pub fn format_tokens(tokens: usize) -> String {
+    if tokens < 1000 {
+        tokens.to_string()
+    } else {
+        format!("{:.1}k", tokens as f64 / 1000.0)
+    }
+}
+
 impl Highlighter for InputHelper {
     fn highlight_prompt<'b, 's: 'b, 'p: 'b>(&'s self, prompt: &'p str, _default: bool) -> Cow<'b, str> {
         // Add cyan color to the prompt
@@ -262,6 +460,19 @@ impl Highlighter for InputHelper {
                     in_at_path = true;
                     result.push_str("\x1b[38;5;39m@");
                 }
+                ';' if result.is_empty() || result.ends_with(char::is_whitespace) => {
+                    // Snippet highlighting, for the names that are defined
+                    let word: String = std::iter::once(c).chain(chars.clone().take_while(|n| !n.is_whitespace())).collect();
+                    let known = self.snippets.lock().is_ok_and(|snippets| snippets.contains_key(&word[1..]));
+                    if known {
+                        result.push_str(&format!("\x1b[38;5;171m{}\x1b[0m", word));
+                        for _ in 1..word.chars().count() {
+                            chars.next();
+                        }
+                    } else {
+                        result.push(c);
+                    }
+                }
                 ' ' | '\t' if in_at_path => {
                     result.push_str("\x1b[0m");
                     result.push(c);
@@ -293,11 +504,75 @@ impl Validator for InputHelper {}
 
 pub struct InputReader {
     editor: Editor<InputHelper, rustyline::history::DefaultHistory>,
+    /// Follow-up suggestions offered after the last answer
+    quick_replies: Arc<Mutex<Vec<String>>>,
+    /// The suggestion whose number was pressed
+    chosen: Arc<Mutex<Option<String>>>,
+    /// The line being typed when Ctrl+P asked for the command palette
+    palette: Arc<Mutex<Option<String>>>,
+    snippets: Snippets,
+}
+
+/// Pressing a suggestion's number on an empty line sends it
+struct QuickReply {
+    number: usize,
+    replies: Arc<Mutex<Vec<String>>>,
+    chosen: Arc<Mutex<Option<String>>>,
+}
+
+impl ConditionalEventHandler for QuickReply {
+    fn handle(&self, _: &Event, _: RepeatCount, _: bool, ctx: &EventContext) -> Option<Cmd> {
+        if !ctx.line().is_empty() {
+            return None;
+        }
+        let reply = self.replies.lock().ok()?.get(self.number - 1).cloned()?;
+        *self.chosen.lock().ok()? = Some(reply);
+        Some(Cmd::AcceptLine)
+    }
+}
+
+/// Space after a `;name` snippet expands it in place, by way of the
+/// completer, which also leaves the cursor after the text
+struct SnippetKey {
+    snippets: Snippets,
+    expanding: Arc<AtomicBool>,
+}
+
+impl ConditionalEventHandler for SnippetKey {
+    fn handle(&self, _: &Event, _: RepeatCount, _: bool, ctx: &EventContext) -> Option<Cmd> {
+        let (_, name) = snippet_before(ctx.line(), ctx.pos())?;
+        if !self.snippets.lock().ok()?.contains_key(name) {
+            return None;
+        }
+        self.expanding.store(true, Ordering::Relaxed);
+        Some(Cmd::Complete)
+    }
+}
+
+/// The `;name` word that ends at `pos`: where it starts, and the name
+fn snippet_before(line: &str, pos: usize) -> Option<(usize, &str)> {
+    let before = &line[..pos];
+    let start = before.trim_end_matches(|c: char| !c.is_whitespace()).len();
+    Some((start, before[start..].strip_prefix(';')?))
+}
+
+/// Ctrl+P leaves the prompt, keeping the line, for the command palette
+struct PaletteKey {
+    typed: Arc<Mutex<Option<String>>>,
+}
+
+impl ConditionalEventHandler for PaletteKey {
+    fn handle(&self, _: &Event, _: RepeatCount, _: bool, ctx: &EventContext) -> Option<Cmd> {
+        *self.typed.lock().ok()? = Some(ctx.line().to_string());
+        Some(Cmd::Interrupt)
+    }
 }
 
 impl InputReader {
-    pub fn new(model_names: Vec<String>) -> Self {
-        let helper = InputHelper::new(model_names);
+    pub fn new(model_names: Vec<String>, snippets: HashMap<String, String>) -> Self {
+        let snippets = Arc::new(Mutex::new(snippets));
+        let expanding = Arc::new(AtomicBool::new(false));
+        let helper = InputHelper::new(model_names, snippets.clone(), expanding.clone());
         let config = rustyline::Config::builder()
             .completion_type(rustyline::CompletionType::List)
             .completion_prompt_limit(10)
@@ -309,7 +584,74 @@ impl InputReader {
         let mut editor = Editor::with_config(config).expect("Failed to create editor");
         editor.set_helper(Some(helper));
 
-        Self { editor }
+        let quick_replies = Arc::new(Mutex::new(Vec::new()));
+        let chosen = Arc::new(Mutex::new(None));
+        for number in 1..=9 {
+            let digit = char::from_digit(number as u32, 10).unwrap_or('1');
+            let handler = QuickReply { number, replies: quick_replies.clone(), chosen: chosen.clone() };
+            editor.bind_sequence(KeyEvent(KeyCode::Char(digit), Modifiers::NONE), EventHandler::Conditional(Box::new(handler)));
+        }
+
+        let palette = Arc::new(Mutex::new(None));
+        let handler = PaletteKey { typed: palette.clone() };
+        editor.bind_sequence(KeyEvent(KeyCode::Char('P'), Modifiers::CTRL), EventHandler::Conditional(Box::new(handler)));
+
+        let handler = SnippetKey { snippets: snippets.clone(), expanding };
+        editor.bind_sequence(KeyEvent(KeyCode::Char(' '), Modifiers::NONE), EventHandler::Conditional(Box::new(handler)));
+
+        Self { editor, quick_replies, chosen, palette, snippets }
+    }
+
+    pub fn set_snippets(&mut self, snippets: HashMap<String, String>) {
+        if let Ok(mut current) = self.snippets.lock() {
+            *current = snippets;
+        }
+    }
+
+    /// `line` with each `;name` word replaced by its snippet, for one sent
+    /// before Space could expand it
+    fn expand_snippets(&self, line: String) -> String {
+        let Ok(snippets) = self.snippets.lock() else {
+            return line;
+        };
+        if snippets.is_empty() || !line.contains(';') {
+            return line;
+        }
+        let mut expanded = String::with_capacity(line.len());
+        let mut rest = line.as_str();
+        while !rest.is_empty() {
+            let (word, tail) = rest.split_at(rest.find(char::is_whitespace).unwrap_or(rest.len()));
+            match word.strip_prefix(';').and_then(|name| snippets.get(name)) {
+                Some(text) => expanded.push_str(text),
+                None => expanded.push_str(word),
+            }
+            let (space, tail) = tail.split_at(tail.find(|c: char| !c.is_whitespace()).unwrap_or(tail.len()));
+            expanded.push_str(space);
+            rest = tail;
+        }
+        expanded
+    }
+
+    /// The line that was being typed, when the last prompt was left with
+    /// Ctrl+P rather than Ctrl+C
+    pub fn take_palette(&mut self) -> Option<String> {
+        self.palette.lock().ok()?.take()
+    }
+
+    /// Offer `replies` at the next prompt, numbered from 1
+    pub fn set_quick_replies(&mut self, replies: Vec<String>) {
+        if let Ok(mut quick_replies) = self.quick_replies.lock() {
+            *quick_replies = replies;
+        }
+    }
+
+    /// The suggestion picked at the last prompt, if one was. The offer ends
+    /// with that prompt either way.
+    pub fn take_quick_reply(&mut self) -> Option<String> {
+        if let Ok(mut quick_replies) = self.quick_replies.lock() {
+            quick_replies.clear();
+        }
+        self.chosen.lock().ok()?.take()
     }
 
     pub fn update_models(&mut self, model_names: Vec<String>) {
@@ -318,56 +660,139 @@ impl InputReader {
         }
     }
 
+    /// `used` tokens of the model's `max`, for the estimate shown while typing
+    pub fn set_context(&mut self, used: usize, max: usize) {
+        if let Some(helper) = self.editor.helper_mut() {
+            helper.context = (used, max);
+        }
+    }
+
     pub fn readline(&mut self, prompt: &str) -> Result<String, ReadlineError> {
-        self.editor.readline(prompt)
+        let line = self.editor.readline(prompt)?;
+        Ok(self.expand_snippets(line))
+    }
+
+    /// Read a line that starts out filled with `initial`, cursor at the end
+    pub fn readline_with_initial(&mut self, prompt: &str, initial: &str) -> Result<String, ReadlineError> {
+        let line = self.editor.readline_with_initial(prompt, (initial, ""))?;
+        Ok(self.expand_snippets(line))
     }
 
     pub fn add_history_entry(&mut self, line: &str) {
         let _ = self.editor.add_history_entry(line);
     }
+
+    /// Prints above the prompt from another thread without breaking the line
+    /// being typed (/tail)
+    pub fn printer(&mut self) -> Option<crate::logtail::Printer> {
+        let mut printer = self.editor.create_external_printer().ok()?;
+        Some(Box::new(move |message| {
+            let _ = printer.print(message);
+        }))
+    }
 }
 
-/// Parse file references from input (e.g., @path/to/file.txt)
-pub fn parse_file_references(input: &str) -> Vec<String> {
-    let mut files = Vec::new();
-    let mut chars = input.chars().peekable();
-
-    while let Some(c) = chars.next() {
-        if c == '@' {
-            let mut path = String::new();
-            while let Some(&next) = chars.peek() {
-                if next.is_whitespace() {
+/// `cat error.log | aicli`: the piped text, with the terminal put back as
+/// stdin so the chat can be typed into. None when stdin is a terminal, when
+/// stdout isn't (`aicli < questions.txt > answers.md` is a batch run, not a
+/// chat), or when there's no terminal to type in; piped lines are then read
+/// as prompts, as before.
+#[cfg(unix)]
+pub fn take_piped_stdin() -> Option<String> {
+    use std::io::{IsTerminal, Read};
+    use std::os::fd::AsRawFd;
+
+    if std::io::stdin().is_terminal() || !std::io::stdout().is_terminal() {
+        return None;
+    }
+    let tty = std::fs::OpenOptions::new().read(true).write(true).open("/dev/tty").ok()?;
+    let mut bytes = Vec::new();
+    std::io::stdin().read_to_end(&mut bytes).ok()?;
+    // SAFETY: both descriptors are open; dup2 only replaces descriptor 0
+    if unsafe { libc::dup2(tty.as_raw_fd(), libc::STDIN_FILENO) } < 0 {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+#[cfg(not(unix))]
+pub fn take_piped_stdin() -> Option<String> {
+    None
+}
+
+/// Find `@path` references: the byte range of each (including the `@`) and
+/// the path. `@` only counts at the start of a word so e-mail addresses are
+/// left alone. Paths with spaces can be quoted (`@"My Docs/a.txt"`) or, outside
+/// Windows, escaped (`@My\ Docs/a.txt`).
+fn scan_file_references(input: &str) -> Vec<(std::ops::Range<usize>, String)> {
+    let mut refs = Vec::new();
+    let mut chars = input.char_indices().peekable();
+    let mut prev: Option<char> = None;
+
+    while let Some((start, c)) = chars.next() {
+        let at_word_start = prev.is_none_or(|p| p.is_whitespace());
+        prev = Some(c);
+        if c != '@' || !at_word_start {
+            continue;
+        }
+
+        let mut path = String::new();
+        let mut end = start + 1;
+        let quote = chars.peek().map(|&(_, q)| q).filter(|q| *q == '"' || *q == '\'');
+
+        if let Some(quote) = quote {
+            chars.next();
+            end += 1;
+            for (i, ch) in chars.by_ref() {
+                end = i + ch.len_utf8();
+                if ch == quote {
                     break;
                 }
-                path.push(chars.next().unwrap());
+                path.push(ch);
             }
-            if !path.is_empty() {
-                files.push(path);
+        } else {
+            while let Some(&(i, ch)) = chars.peek() {
+                if ch.is_whitespace() {
+                    break;
+                }
+                chars.next();
+                end = i + ch.len_utf8();
+                if ch == '\\' && !cfg!(windows) {
+                    if let Some(&(j, ' ')) = chars.peek() {
+                        chars.next();
+                        end = j + 1;
+                        path.push(' ');
+                        continue;
+                    }
+                }
+                path.push(ch);
             }
         }
+        prev = input[..end].chars().last();
+
+        if !path.is_empty() {
+            refs.push((start..end, path));
+        }
     }
 
-    files
+    refs
+}
+
+/// Parse file references from input (e.g., @path/to/file.txt)
+pub fn parse_file_references(input: &str) -> Vec<String> {
+    scan_file_references(input).into_iter().map(|(_, path)| path).collect()
 }
 
 /// Remove file references from input and return clean text
 pub fn strip_file_references(input: &str) -> String {
     let mut result = String::new();
-    let mut chars = input.chars().peekable();
+    let mut last = 0;
 
-    while let Some(c) = chars.next() {
-        if c == '@' {
-            // Skip until whitespace
-            while let Some(&next) = chars.peek() {
-                if next.is_whitespace() {
-                    break;
-                }
-                chars.next();
-            }
-        } else {
-            result.push(c);
-        }
+    for (range, _) in scan_file_references(input) {
+        result.push_str(&input[last..range.start]);
+        last = range.end;
     }
+    result.push_str(&input[last..]);
 
     result.trim().to_string()
 }
@@ -377,10 +802,22 @@ pub fn read_file_context(files: &[String]) -> String {
     let mut context = String::new();
 
     for file_path in files {
-        match std::fs::read_to_string(file_path) {
+        let path = paths::resolve(file_path);
+        // Only text is sent; tell the model an image was attached rather
+        // than failing to read it as UTF-8
+        if graphics::is_image(&path) {
+            if let Ok(data) = std::fs::read(&path) {
+                context.push_str(&format!(
+                    "\n[Image attached: {}. Image content is not sent to the model]\n",
+                    graphics::caption(Path::new(file_path), &data)
+                ));
+                continue;
+            }
+        }
+        match std::fs::read_to_string(&path) {
             Ok(content) => {
                 context.push_str(&format!("\n--- File: {} ---\n", file_path));
-                context.push_str(&content);
+                context.push_str(&redact::redact_file(file_path, &content));
                 context.push_str("\n--- End of file ---\n");
             }
             Err(e) => {
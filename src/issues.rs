@@ -0,0 +1,173 @@
+use crate::config::{IssueProvider, IssueTrackerConfig};
+use crate::tools::block_on;
+use anyhow::Result;
+use serde_json::{json, Value};
+
+const MAX_COMMENTS: usize = 10;
+
+const LINEAR_API: &str = "https://api.linear.app/graphql";
+
+const LINEAR_QUERY: &str = "query($id: String!) { issue(id: $id) { identifier title description url \
+state { name } assignee { name } labels { nodes { name } } \
+comments(first: 10) { nodes { body createdAt user { name } } } } }";
+
+fn str_at<'a>(value: &'a Value, pointer: &str) -> &'a str {
+    value.pointer(pointer).and_then(|v| v.as_str()).unwrap_or("")
+}
+
+/// Jira fields can be plain strings (API v2) or rich-text documents; pull
+/// the text out of either so custom fields work too
+fn field_text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Object(map) => {
+            if let Some(text) = map.get("text").and_then(|t| t.as_str()) {
+                return text.to_string();
+            }
+            map.get("content")
+                .map(field_text)
+                .or_else(|| map.get("value").map(field_text))
+                .unwrap_or_default()
+        }
+        Value::Array(items) => items
+            .iter()
+            .map(field_text)
+            .filter(|t| !t.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n"),
+        Value::Null => String::new(),
+        other => other.to_string(),
+    }
+}
+
+async fn fetch_jira(config: &IssueTrackerConfig, key: &str) -> Result<String> {
+    let base = config.url.trim_end_matches('/');
+    if base.is_empty() {
+        anyhow::bail!("Set [tools.issues] url to your Jira site, e.g. https://yourcompany.atlassian.net");
+    }
+
+    let mut request = reqwest::Client::new()
+        .get(format!("{}/rest/api/2/issue/{}", base, key))
+        .header("Accept", "application/json");
+    // Cloud uses email + API token, Server/Data Center a personal access token
+    request = match &config.email {
+        Some(email) => request.basic_auth(email, Some(&config.token)),
+        None => request.bearer_auth(&config.token),
+    };
+
+    let response = request.send().await?;
+    let status = response.status();
+    let issue: Value = response.json().await.unwrap_or(Value::Null);
+    if !status.is_success() {
+        let message = issue
+            .pointer("/errorMessages/0")
+            .and_then(|m| m.as_str())
+            .unwrap_or("request failed");
+        anyhow::bail!("Jira API error ({}): {}", status, message);
+    }
+
+    let fields = &issue["fields"];
+    let mut result = format!(
+        "{} {}\nType: {} · Status: {} · Assignee: {}\n{}/browse/{}\n\n{}",
+        key,
+        str_at(fields, "/summary"),
+        str_at(fields, "/issuetype/name"),
+        str_at(fields, "/status/name"),
+        fields.pointer("/assignee/displayName").and_then(|a| a.as_str()).unwrap_or("unassigned"),
+        base,
+        key,
+        field_text(&fields["description"]),
+    );
+
+    if let Some(field) = &config.acceptance_criteria_field {
+        let criteria = field_text(&fields[field.as_str()]);
+        if !criteria.is_empty() {
+            result.push_str(&format!("\n\n## Acceptance criteria\n{}", criteria));
+        }
+    }
+
+    let comments = fields.pointer("/comment/comments").and_then(|c| c.as_array()).cloned().unwrap_or_default();
+    for comment in comments.iter().rev().take(MAX_COMMENTS).rev() {
+        result.push_str(&format!(
+            "\n\n--- {} ({}) ---\n{}",
+            str_at(comment, "/author/displayName"),
+            str_at(comment, "/created"),
+            field_text(&comment["body"]),
+        ));
+    }
+    Ok(result)
+}
+
+async fn fetch_linear(config: &IssueTrackerConfig, key: &str) -> Result<String> {
+    let response = reqwest::Client::new()
+        .post(LINEAR_API)
+        .header("Authorization", &config.token)
+        .json(&json!({ "query": LINEAR_QUERY, "variables": { "id": key } }))
+        .send()
+        .await?;
+    let status = response.status();
+    let body: Value = response.json().await.unwrap_or(Value::Null);
+
+    if let Some(message) = body.pointer("/errors/0/message").and_then(|m| m.as_str()) {
+        anyhow::bail!("Linear API error: {}", message);
+    }
+    if !status.is_success() {
+        anyhow::bail!("Linear API error ({})", status);
+    }
+
+    let issue = &body["data"]["issue"];
+    if issue.is_null() {
+        anyhow::bail!("Issue {} not found", key);
+    }
+
+    let labels: Vec<&str> = issue
+        .pointer("/labels/nodes")
+        .and_then(|l| l.as_array())
+        .map(|l| l.iter().filter_map(|l| l["name"].as_str()).collect())
+        .unwrap_or_default();
+
+    let mut result = format!(
+        "{} {}\nStatus: {} · Assignee: {}{}\n{}\n\n{}",
+        str_at(issue, "/identifier"),
+        str_at(issue, "/title"),
+        str_at(issue, "/state/name"),
+        issue.pointer("/assignee/name").and_then(|a| a.as_str()).unwrap_or("unassigned"),
+        if labels.is_empty() { String::new() } else { format!(" · Labels: {}", labels.join(", ")) },
+        str_at(issue, "/url"),
+        str_at(issue, "/description"),
+    );
+
+    let comments = issue.pointer("/comments/nodes").and_then(|c| c.as_array()).cloned().unwrap_or_default();
+    for comment in comments {
+        result.push_str(&format!(
+            "\n\n--- {} ({}) ---\n{}",
+            str_at(&comment, "/user/name"),
+            str_at(&comment, "/createdAt"),
+            str_at(&comment, "/body"),
+        ));
+    }
+    Ok(result)
+}
+
+/// Fetch a ticket's title, description, acceptance criteria and recent comments
+pub fn read_issue(input: &Value, config: &IssueTrackerConfig) -> Result<String> {
+    let key = input
+        .get("key")
+        .and_then(|k| k.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing 'key' parameter"))?
+        .trim()
+        .to_uppercase();
+
+    if config.token.is_empty() {
+        anyhow::bail!("No issue tracker configured. Add [tools.issues] provider and token to ~/.aicli/config.toml");
+    }
+    // The key ends up in a URL path
+    if !key.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        anyhow::bail!("Invalid issue key '{}'", key);
+    }
+
+    match config.provider {
+        IssueProvider::Jira => block_on(fetch_jira(config, &key)),
+        IssueProvider::Linear => block_on(fetch_linear(config, &key)),
+    }
+}
@@ -0,0 +1,158 @@
+use crate::config::KubernetesConfig;
+use anyhow::Result;
+use serde_json::Value;
+use std::process::Command;
+
+const DEFAULT_LOG_LINES: u64 = 200;
+const MAX_LOG_LINES: u64 = 2000;
+
+/// Resolve and check the context/namespace pair for a call. Without an
+/// explicit context kubectl's current one is used, which must itself be allowed.
+fn scope_args(input: &Value, config: &KubernetesConfig) -> Result<Vec<String>> {
+    if !config.enabled {
+        anyhow::bail!("Kubernetes tools are disabled. Set [tools.kubernetes] enabled = true in ~/.aicli/config.toml");
+    }
+
+    let context = match input.get("context").and_then(|c| c.as_str()) {
+        Some(context) => context.to_string(),
+        None => current_context()?,
+    };
+    if !config.contexts.is_empty() && !config.contexts.contains(&context) {
+        anyhow::bail!(
+            "Context '{}' is not allowed. Allowed contexts: {}",
+            context,
+            config.contexts.join(", ")
+        );
+    }
+
+    let namespace = input
+        .get("namespace")
+        .and_then(|n| n.as_str())
+        .map(|n| n.to_string())
+        .or_else(|| config.namespaces.first().cloned())
+        .unwrap_or_else(|| "default".to_string());
+    if !config.namespaces.is_empty() && !config.namespaces.contains(&namespace) {
+        anyhow::bail!(
+            "Namespace '{}' is not allowed. Allowed namespaces: {}",
+            namespace,
+            config.namespaces.join(", ")
+        );
+    }
+
+    Ok(vec![format!("--context={}", context), format!("--namespace={}", namespace)])
+}
+
+fn current_context() -> Result<String> {
+    let output = Command::new("kubectl")
+        .args(["config", "current-context"])
+        .output()
+        .map_err(|e| anyhow::anyhow!("Could not run kubectl: {}", e))?;
+    if !output.status.success() {
+        anyhow::bail!("{}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Names and resource types go straight to kubectl, so refuse anything flag-like
+fn plain_arg<'a>(input: &'a Value, key: &str) -> Result<Option<&'a str>> {
+    match input.get(key).and_then(|v| v.as_str()) {
+        Some(value) if value.starts_with('-') || value.contains(char::is_whitespace) => {
+            anyhow::bail!("Invalid {} '{}'", key, value)
+        }
+        value => Ok(value),
+    }
+}
+
+fn required_arg<'a>(input: &'a Value, key: &str) -> Result<&'a str> {
+    plain_arg(input, key)?.ok_or_else(|| anyhow::anyhow!("Missing '{}' parameter", key))
+}
+
+fn run_kubectl(args: &[String]) -> Result<String> {
+    let output = Command::new("kubectl")
+        .args(args)
+        .output()
+        .map_err(|e| anyhow::anyhow!("Could not run kubectl: {}", e))?;
+
+    if !output.status.success() {
+        anyhow::bail!("{}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+    if stdout.trim().is_empty() {
+        Ok(String::from_utf8_lossy(&output.stderr).trim().to_string())
+    } else {
+        Ok(stdout)
+    }
+}
+
+/// Secrets are readable with get -o yaml and describe; keep them out of the
+/// conversation in every spelling kubectl takes (`Secrets`, `secret/name`,
+/// `secrets.v1.`), and refuse `all`, a category whose members the cluster decides
+fn check_not_secret(resource: &str) -> Result<()> {
+    let reads_secrets = resource.split(',').any(|r| {
+        let r = r.trim().to_lowercase();
+        let kind = r.split('/').next().unwrap_or_default();
+        let kind = kind.split('.').next().unwrap_or_default();
+        matches!(kind, "secret" | "secrets" | "all")
+    });
+    if reads_secrets {
+        anyhow::bail!("Reading secrets is not allowed (name the resource types instead of 'all')");
+    }
+    Ok(())
+}
+
+pub fn kubectl_get(input: &Value, config: &KubernetesConfig) -> Result<String> {
+    let scope = scope_args(input, config)?;
+    let resource = required_arg(input, "resource")?;
+    check_not_secret(resource)?;
+
+    let mut args = vec!["get".to_string(), resource.to_string()];
+    if let Some(name) = plain_arg(input, "name")? {
+        args.push(name.to_string());
+    }
+    if let Some(selector) = plain_arg(input, "selector")? {
+        args.push(format!("--selector={}", selector));
+    }
+    let output = input
+        .get("output")
+        .and_then(|o| o.as_str())
+        .filter(|o| matches!(*o, "wide" | "yaml" | "json"))
+        .unwrap_or("wide");
+    args.push(format!("--output={}", output));
+    args.extend(scope);
+    run_kubectl(&args)
+}
+
+pub fn kubectl_describe(input: &Value, config: &KubernetesConfig) -> Result<String> {
+    let scope = scope_args(input, config)?;
+    let resource = required_arg(input, "resource")?;
+    check_not_secret(resource)?;
+
+    let mut args = vec!["describe".to_string(), resource.to_string()];
+    if let Some(name) = plain_arg(input, "name")? {
+        args.push(name.to_string());
+    }
+    args.extend(scope);
+    run_kubectl(&args)
+}
+
+pub fn kubectl_logs(input: &Value, config: &KubernetesConfig) -> Result<String> {
+    let scope = scope_args(input, config)?;
+    let pod = required_arg(input, "pod")?;
+    let lines = input
+        .get("lines")
+        .and_then(|l| l.as_u64())
+        .unwrap_or(DEFAULT_LOG_LINES)
+        .min(MAX_LOG_LINES);
+
+    let mut args = vec!["logs".to_string(), pod.to_string(), format!("--tail={}", lines)];
+    if let Some(container) = plain_arg(input, "container")? {
+        args.push(format!("--container={}", container));
+    }
+    // The crashed instance's logs are usually the interesting ones
+    if input.get("previous").and_then(|p| p.as_bool()).unwrap_or(false) {
+        args.push("--previous".to_string());
+    }
+    args.extend(scope);
+    run_kubectl(&args)
+}
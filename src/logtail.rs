@@ -0,0 +1,237 @@
+//! `/tail` and `aicli tail`: follow a log file in the background and attach
+//! the lines written since the previous prompt to the next one, so questions
+//! like "what just happened?" are answered from the log itself.
+
+use anyhow::Result;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::paths;
+
+pub const DEFAULT_LINES: usize = 50;
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+/// Lines kept between prompts; older ones are dropped and counted
+const MAX_PENDING_LINES: usize = 5_000;
+/// What one prompt gets, from the end of the batch
+const MAX_BATCH_LINES: usize = 300;
+const MAX_BATCH_CHARS: usize = 24_000;
+
+/// Prints a line above the prompt while the user is typing
+pub type Printer = Box<dyn FnMut(String) + Send>;
+
+#[derive(Default)]
+struct Pending {
+    lines: Vec<String>,
+    dropped: usize,
+    /// Lines since the last notice above the prompt
+    unannounced: usize,
+    error: Option<String>,
+}
+
+impl Pending {
+    fn push(&mut self, line: String) {
+        if self.lines.len() >= MAX_PENDING_LINES {
+            self.lines.remove(0);
+            self.dropped += 1;
+        }
+        self.lines.push(line);
+    }
+}
+
+pub struct LogTail {
+    path: PathBuf,
+    pending: Arc<Mutex<Pending>>,
+    stop: Arc<AtomicBool>,
+    /// Notices are only printed while the prompt waits for input
+    idle: Arc<AtomicBool>,
+    /// Lines attached to prompts so far
+    attached: usize,
+}
+
+impl LogTail {
+    /// Follow `path`, starting with its last `initial` lines
+    pub fn start(path: &Path, initial: usize, printer: Option<Printer>) -> Result<Self> {
+        let path = std::path::absolute(path)?;
+        if !path.is_file() {
+            anyhow::bail!("{} is not a file", paths::display(&path));
+        }
+        let content = std::fs::read(&path)?;
+        let mut pending = Pending::default();
+        let text = String::from_utf8_lossy(&content);
+        let lines: Vec<&str> = text.lines().collect();
+        for line in &lines[lines.len().saturating_sub(initial)..] {
+            pending.push(line.to_string());
+        }
+
+        let tail = LogTail {
+            path,
+            pending: Arc::new(Mutex::new(pending)),
+            stop: Arc::new(AtomicBool::new(false)),
+            idle: Arc::new(AtomicBool::new(false)),
+            attached: 0,
+        };
+        let follower = Follower {
+            path: tail.path.clone(),
+            offset: content.len() as u64,
+            partial: String::new(),
+            pending: tail.pending.clone(),
+            stop: tail.stop.clone(),
+            idle: tail.idle.clone(),
+            printer,
+        };
+        std::thread::spawn(move || follower.run());
+        Ok(tail)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn set_idle(&self, idle: bool) {
+        self.idle.store(idle, Ordering::Relaxed);
+    }
+
+    /// The lines written since the last call, as a block for the prompt
+    pub fn take(&mut self) -> Option<String> {
+        let (lines, dropped, error) = {
+            let mut pending = self.pending.lock().unwrap();
+            pending.unannounced = 0;
+            (std::mem::take(&mut pending.lines), std::mem::take(&mut pending.dropped), pending.error.take())
+        };
+        let name = paths::display(&self.path);
+        if lines.is_empty() {
+            return error.map(|e| format!("(Reading {} failed: {})", name, e));
+        }
+        self.attached += lines.len();
+
+        let mut shown = Vec::new();
+        let mut size = 0;
+        for line in lines.iter().rev().take(MAX_BATCH_LINES) {
+            size += line.len() + 1;
+            if size > MAX_BATCH_CHARS {
+                break;
+            }
+            shown.push(crate::text::truncate(line, 2_000));
+        }
+        shown.reverse();
+        let skipped = dropped + lines.len() - shown.len();
+        let count = if skipped > 0 {
+            format!("{} lines, the last {} shown", skipped + shown.len(), shown.len())
+        } else {
+            format!("{} line(s)", shown.len())
+        };
+        Some(format!("New lines in {} since the last message ({}):\n```log\n{}\n```", name, count, shown.join("\n")))
+    }
+
+    /// For `/tail` without arguments
+    pub fn status(&self) -> String {
+        let waiting = self.pending.lock().map(|p| p.lines.len() + p.dropped).unwrap_or(0);
+        format!(
+            "Following {} · {} line(s) waiting for the next prompt, {} attached so far",
+            paths::display(&self.path),
+            waiting,
+            self.attached
+        )
+    }
+
+    /// For the system prompt while a log is followed
+    pub fn prompt_section(&self) -> String {
+        format!(
+            "\n\n# Followed log\nThe user is following the log file {}. Lines written to it since their previous message are attached to their messages. When those lines contain errors or stack traces, find the code they point to with search_content, find_definition and read_file before explaining or fixing them.",
+            paths::display(&self.path)
+        )
+    }
+}
+
+impl Drop for LogTail {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+struct Follower {
+    path: PathBuf,
+    offset: u64,
+    /// The start of a line whose newline hasn't been written yet
+    partial: String,
+    pending: Arc<Mutex<Pending>>,
+    stop: Arc<AtomicBool>,
+    idle: Arc<AtomicBool>,
+    printer: Option<Printer>,
+}
+
+impl Follower {
+    fn run(mut self) {
+        while !self.stop.load(Ordering::Relaxed) {
+            if let Err(e) = self.poll() {
+                self.pending.lock().unwrap().error = Some(e.to_string());
+            }
+            self.announce();
+            std::thread::sleep(POLL_INTERVAL);
+        }
+    }
+
+    /// Read what was appended since the last poll. The file is reopened each
+    /// time, so a rotated log is followed from its start.
+    fn poll(&mut self) -> Result<()> {
+        let Ok(mut file) = std::fs::File::open(&self.path) else {
+            // Between a rotation's rename and the new file
+            return Ok(());
+        };
+        let len = file.metadata()?.len();
+        if len < self.offset {
+            self.offset = 0;
+            self.partial.clear();
+        }
+        if len == self.offset {
+            return Ok(());
+        }
+        file.seek(SeekFrom::Start(self.offset))?;
+        let mut bytes = Vec::new();
+        file.take(len - self.offset).read_to_end(&mut bytes)?;
+        self.offset += bytes.len() as u64;
+
+        self.partial.push_str(&String::from_utf8_lossy(&bytes));
+        let Some(end) = self.partial.rfind('\n') else {
+            return Ok(());
+        };
+        let complete: String = self.partial.drain(..=end).collect();
+        let mut pending = self.pending.lock().unwrap();
+        for line in complete.lines() {
+            pending.push(line.trim_end_matches('\r').to_string());
+            pending.unannounced += 1;
+        }
+        Ok(())
+    }
+
+    /// One line above the prompt per batch, with the number of errors in it
+    fn announce(&mut self) {
+        if !self.idle.load(Ordering::Relaxed) {
+            return;
+        }
+        let Some(printer) = self.printer.as_mut() else {
+            return;
+        };
+        let (count, errors) = {
+            let mut pending = self.pending.lock().unwrap();
+            let count = std::mem::take(&mut pending.unannounced);
+            let start = pending.lines.len().saturating_sub(count);
+            let errors = pending.lines[start..].iter().filter(|l| is_error(l)).count();
+            (count, errors)
+        };
+        if count == 0 {
+            return;
+        }
+        let name = self.path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+        let errors = if errors > 0 { format!(", \x1b[38;5;203m{} error(s)\x1b[38;5;103m", errors) } else { String::new() };
+        printer(format!("\x1b[38;5;103m  ▸ {}: +{} line(s){} · ask about them or keep typing\x1b[0m", name, count, errors));
+    }
+}
+
+fn is_error(line: &str) -> bool {
+    let lower = line.to_lowercase();
+    ["error", "exception", "panic", "fatal", "traceback", "critical"].iter().any(|w| lower.contains(w))
+}
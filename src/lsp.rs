@@ -0,0 +1,580 @@
+//! Code navigation through language servers: find_definition,
+//! find_references, document_symbols, diagnostics and rename_symbol. A server from
+//! `[tools.lsp.servers]` is started in the workspace root the first time a
+//! file of its language is asked about, and runs until the session ends.
+
+use anyhow::{Context, Result};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use url::Url;
+
+use crate::config::{LspConfig, LspServerConfig};
+
+const MAX_LOCATIONS: usize = 100;
+/// After the first diagnostics for a file, how long to wait for updated ones
+const DIAGNOSTICS_SETTLE: Duration = Duration::from_millis(1500);
+
+const SYMBOL_KINDS: [&str; 26] = [
+    "file", "module", "namespace", "package", "class", "method", "property", "field", "constructor", "enum",
+    "interface", "function", "variable", "constant", "string", "number", "boolean", "array", "object", "key",
+    "null", "enum member", "struct", "event", "operator", "type parameter",
+];
+
+/// Running servers by language name
+fn servers() -> &'static Mutex<HashMap<String, Server>> {
+    static SERVERS: OnceLock<Mutex<HashMap<String, Server>>> = OnceLock::new();
+    SERVERS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+struct Server {
+    child: Child,
+    stdin: ChildStdin,
+    messages: Receiver<Value>,
+    next_id: u64,
+    timeout: Duration,
+    /// Open documents: version and the text the server has
+    open: HashMap<Url, (i64, String)>,
+    /// Latest publishDiagnostics per document
+    diagnostics: HashMap<Url, Vec<Value>>,
+}
+
+impl Server {
+    fn start(language: &str, config: &LspServerConfig, root: &Path, timeout: Duration) -> Result<Self> {
+        let mut child = Command::new(&config.command)
+            .args(&config.args)
+            .current_dir(root)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .with_context(|| format!("Could not start the {} language server '{}'", language, config.command))?;
+        let stdin = child.stdin.take().context("No stdin for the language server")?;
+        let stdout = child.stdout.take().context("No stdout for the language server")?;
+        let (sender, messages) = mpsc::channel();
+        std::thread::spawn(move || read_messages(stdout, sender));
+
+        let mut server = Self {
+            child,
+            stdin,
+            messages,
+            next_id: 1,
+            timeout,
+            open: HashMap::new(),
+            diagnostics: HashMap::new(),
+        };
+        let root_uri = Url::from_directory_path(root).map_err(|_| anyhow::anyhow!("Invalid workspace root"))?;
+        let name = root.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+        server.request(
+            "initialize",
+            json!({
+                "processId": std::process::id(),
+                "rootUri": root_uri.as_str(),
+                "workspaceFolders": [{ "uri": root_uri.as_str(), "name": name }],
+                "capabilities": {
+                    "textDocument": {
+                        "definition": { "linkSupport": true },
+                        "documentSymbol": { "hierarchicalDocumentSymbolSupport": true },
+                        "publishDiagnostics": {}
+                    },
+                    "workspace": { "workspaceFolders": true, "configuration": true }
+                }
+            }),
+        )?;
+        server.notify("initialized", json!({}))?;
+        Ok(server)
+    }
+
+    fn send(&mut self, message: Value) -> Result<()> {
+        let body = message.to_string();
+        write!(self.stdin, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+        self.stdin.flush()?;
+        Ok(())
+    }
+
+    fn notify(&mut self, method: &str, params: Value) -> Result<()> {
+        self.send(json!({ "jsonrpc": "2.0", "method": method, "params": params }))
+    }
+
+    fn request(&mut self, method: &str, params: Value) -> Result<Value> {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.send(json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params }))?;
+
+        let deadline = Instant::now() + self.timeout;
+        loop {
+            let message = match self.messages.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+                Ok(message) => message,
+                Err(RecvTimeoutError::Timeout) => {
+                    anyhow::bail!("The language server didn't answer {} within {}s", method, self.timeout.as_secs())
+                }
+                Err(RecvTimeoutError::Disconnected) => anyhow::bail!("The language server exited"),
+            };
+            if message.get("method").is_none() && message.get("id").and_then(|i| i.as_u64()) == Some(id) {
+                if let Some(error) = message.get("error") {
+                    let text = error.get("message").and_then(|m| m.as_str()).unwrap_or("unknown error");
+                    anyhow::bail!("{} failed: {}", method, text);
+                }
+                return Ok(message.get("result").cloned().unwrap_or(Value::Null));
+            }
+            self.handle(message)?;
+        }
+    }
+
+    /// Answer the server's own requests and keep its diagnostics
+    fn handle(&mut self, message: Value) -> Result<()> {
+        let Some(method) = message.get("method").and_then(|m| m.as_str()) else {
+            return Ok(());
+        };
+        if method == "textDocument/publishDiagnostics" {
+            let params = &message["params"];
+            if let Some(uri) = params.get("uri").and_then(|u| u.as_str()).and_then(|u| Url::parse(u).ok()) {
+                let list = params.get("diagnostics").and_then(|d| d.as_array()).cloned().unwrap_or_default();
+                self.diagnostics.insert(uri, list);
+            }
+            return Ok(());
+        }
+        if let Some(id) = message.get("id").cloned() {
+            // Default settings for every configuration item; null for the rest
+            let result = match method {
+                "workspace/configuration" => {
+                    let items = message["params"]["items"].as_array().map(|i| i.len()).unwrap_or(0);
+                    Value::Array(vec![Value::Null; items])
+                }
+                _ => Value::Null,
+            };
+            self.send(json!({ "jsonrpc": "2.0", "id": id, "result": result }))?;
+        }
+        Ok(())
+    }
+
+    /// Give the server the file's current text; returns its URI
+    fn sync(&mut self, path: &Path, language: &str) -> Result<Url> {
+        let uri = Url::from_file_path(path).map_err(|_| anyhow::anyhow!("Invalid path {}", path.display()))?;
+        let text = fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        match self.open.get(&uri) {
+            Some((_, known)) if *known == text => {}
+            Some((version, _)) => {
+                let version = version + 1;
+                self.notify(
+                    "textDocument/didChange",
+                    json!({
+                        "textDocument": { "uri": uri.as_str(), "version": version },
+                        "contentChanges": [{ "text": text }]
+                    }),
+                )?;
+                self.diagnostics.remove(&uri);
+                self.open.insert(uri.clone(), (version, text));
+            }
+            None => {
+                self.notify(
+                    "textDocument/didOpen",
+                    json!({ "textDocument": { "uri": uri.as_str(), "languageId": language, "version": 1, "text": text } }),
+                )?;
+                self.diagnostics.remove(&uri);
+                self.open.insert(uri.clone(), (1, text));
+            }
+        }
+        Ok(uri)
+    }
+
+    /// Diagnostics for `uri`, waiting for the server to publish them
+    fn wait_for_diagnostics(&mut self, uri: &Url) -> Result<Vec<Value>> {
+        let deadline = Instant::now() + self.timeout;
+        let mut settle: Option<Instant> = None;
+        loop {
+            if settle.is_none() && self.diagnostics.contains_key(uri) {
+                settle = Some(Instant::now() + DIAGNOSTICS_SETTLE);
+            }
+            let until = settle.unwrap_or(deadline);
+            match self.messages.recv_timeout(until.saturating_duration_since(Instant::now())) {
+                Ok(message) => self.handle(message)?,
+                Err(RecvTimeoutError::Timeout) if settle.is_some() => break,
+                Err(RecvTimeoutError::Timeout) => {
+                    anyhow::bail!("The language server published no diagnostics within {}s", self.timeout.as_secs())
+                }
+                Err(RecvTimeoutError::Disconnected) => anyhow::bail!("The language server exited"),
+            }
+        }
+        Ok(self.diagnostics.get(uri).cloned().unwrap_or_default())
+    }
+
+    fn stop(mut self) {
+        if self.request("shutdown", Value::Null).is_ok() {
+            let _ = self.notify("exit", Value::Null);
+        }
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// Content-Length framed JSON-RPC messages from the server's stdout
+fn read_messages(stdout: ChildStdout, sender: Sender<Value>) {
+    let mut reader = BufReader::new(stdout);
+    loop {
+        let mut length = None;
+        loop {
+            let mut line = String::new();
+            if reader.read_line(&mut line).unwrap_or(0) == 0 {
+                return;
+            }
+            let line = line.trim_end();
+            if line.is_empty() {
+                break;
+            }
+            if let Some((name, value)) = line.split_once(':') {
+                if name.eq_ignore_ascii_case("content-length") {
+                    length = value.trim().parse::<usize>().ok();
+                }
+            }
+        }
+        let Some(length) = length else {
+            continue;
+        };
+        let mut body = vec![0; length];
+        if reader.read_exact(&mut body).is_err() {
+            return;
+        }
+        if let Ok(message) = serde_json::from_slice(&body) {
+            if sender.send(message).is_err() {
+                return;
+            }
+        }
+    }
+}
+
+/// Stop every language server; called when the session ends
+pub fn shutdown() {
+    let Some(servers) = servers().lock().ok().map(|mut s| std::mem::take(&mut *s)) else {
+        return;
+    };
+    for (_, server) in servers {
+        server.stop();
+    }
+}
+
+/// Run `f` with the server for `path`'s language, starting it if needed
+fn with_server<T>(config: &LspConfig, path: &Path, f: impl FnOnce(&mut Server, Url) -> Result<T>) -> Result<T> {
+    if config.servers.is_empty() {
+        anyhow::bail!(
+            "No language servers configured. Add one under [tools.lsp.servers.<language>] in ~/.aicli/config.toml"
+        );
+    }
+    let extension = path.extension().and_then(|e| e.to_str()).unwrap_or_default();
+    let (language, server_config) = config
+        .servers
+        .iter()
+        .find(|(_, s)| s.extensions.iter().any(|e| e.trim_start_matches('.').eq_ignore_ascii_case(extension)))
+        .ok_or_else(|| anyhow::anyhow!("No language server configured for .{} files", extension))?;
+
+    let mut servers = servers().lock().unwrap();
+    // A server that crashed is started again
+    if servers.get_mut(language).is_some_and(|s| !matches!(s.child.try_wait(), Ok(None))) {
+        servers.remove(language);
+    }
+    if !servers.contains_key(language) {
+        let root = PathBuf::from(crate::memory::project_root());
+        let server = Server::start(language, server_config, &root, Duration::from_secs(config.timeout_secs.max(1)))?;
+        servers.insert(language.clone(), server);
+    }
+    let server = servers.get_mut(language).unwrap();
+    let uri = server.sync(path, language)?;
+    f(server, uri)
+}
+
+fn path_param(input: &Value) -> Result<PathBuf> {
+    let path = input
+        .get("path")
+        .and_then(|p| p.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing 'path' parameter"))?;
+    let path = std::path::absolute(crate::paths::resolve(path))?;
+    if !path.is_file() {
+        anyhow::bail!("{} is not a file", path.display());
+    }
+    Ok(path)
+}
+
+/// The LSP position of `symbol` (or 1-based `column`) on 1-based `line`
+fn position_param(input: &Value, path: &Path) -> Result<Value> {
+    let line = input
+        .get("line")
+        .and_then(|l| l.as_u64())
+        .filter(|l| *l > 0)
+        .ok_or_else(|| anyhow::anyhow!("Missing 'line' parameter (1-based)"))? as usize;
+    let text = fs::read_to_string(path)?;
+    let line_text = text
+        .lines()
+        .nth(line - 1)
+        .ok_or_else(|| anyhow::anyhow!("Line {} is past the end of the file ({} lines)", line, text.lines().count()))?;
+
+    let byte = if let Some(symbol) = input.get("symbol").and_then(|s| s.as_str()).filter(|s| !s.is_empty()) {
+        find_word(line_text, symbol)
+            .ok_or_else(|| anyhow::anyhow!("'{}' is not on line {}: {}", symbol, line, line_text.trim()))?
+    } else if let Some(column) = input.get("column").and_then(|c| c.as_u64()).filter(|c| *c > 0) {
+        line_text.char_indices().nth(column as usize - 1).map(|(i, _)| i).unwrap_or(line_text.len())
+    } else {
+        anyhow::bail!("Give 'symbol' (a name on that line) or 'column'");
+    };
+    // Positions count UTF-16 code units
+    let character = line_text[..byte].encode_utf16().count();
+    Ok(json!({ "line": line - 1, "character": character }))
+}
+
+/// Byte offset of `word` in `line`, preferring a whole-word match
+fn find_word(line: &str, word: &str) -> Option<usize> {
+    let is_ident = |c: char| c.is_alphanumeric() || c == '_';
+    let whole = line.match_indices(word).map(|(i, _)| i).find(|&i| {
+        let before = line[..i].chars().next_back().is_none_or(|c| !is_ident(c));
+        let after = line[i + word.len()..].chars().next().is_none_or(|c| !is_ident(c));
+        before && after
+    });
+    whole.or_else(|| line.find(word))
+}
+
+/// `path:line:column: source line` for Location, Location[] and LocationLink[] results
+fn format_locations(result: &Value) -> Vec<String> {
+    let items = match result {
+        Value::Array(items) => items.clone(),
+        Value::Null => Vec::new(),
+        single => vec![single.clone()],
+    };
+    let mut files: HashMap<PathBuf, Vec<String>> = HashMap::new();
+    items
+        .iter()
+        .filter_map(|item| {
+            let uri = item.get("uri").or_else(|| item.get("targetUri"))?.as_str()?;
+            let range = item.get("range").or_else(|| item.get("targetSelectionRange"))?;
+            let path = Url::parse(uri).ok()?.to_file_path().ok()?;
+            let line = range["start"]["line"].as_u64()? as usize;
+            let character = range["start"]["character"].as_u64()? as usize;
+            let lines = files
+                .entry(path.clone())
+                .or_insert_with(|| fs::read_to_string(&path).unwrap_or_default().lines().map(String::from).collect());
+            let source = lines.get(line).map(|l| l.trim().to_string()).unwrap_or_default();
+            Some(format!("{}:{}:{}: {}", display(&path), line + 1, character + 1, crate::text::truncate(&source, 120)))
+        })
+        .collect()
+}
+
+/// Relative to the working directory when inside it
+fn display(path: &Path) -> String {
+    let cwd = std::env::current_dir().unwrap_or_default();
+    crate::paths::display(path.strip_prefix(&cwd).unwrap_or(path))
+}
+
+fn list_or(lines: Vec<String>, empty: &str) -> String {
+    if lines.is_empty() {
+        return empty.to_string();
+    }
+    let total = lines.len();
+    let mut output = lines.into_iter().take(MAX_LOCATIONS).collect::<Vec<_>>().join("\n");
+    if total > MAX_LOCATIONS {
+        output.push_str(&format!("\n... and {} more", total - MAX_LOCATIONS));
+    }
+    output
+}
+
+pub fn find_definition(input: &Value, config: &LspConfig) -> Result<String> {
+    let path = path_param(input)?;
+    let position = position_param(input, &path)?;
+    let result = with_server(config, &path, |server, uri| {
+        server.request("textDocument/definition", json!({ "textDocument": { "uri": uri.as_str() }, "position": position }))
+    })?;
+    Ok(list_or(format_locations(&result), "No definition found"))
+}
+
+pub fn find_references(input: &Value, config: &LspConfig) -> Result<String> {
+    let path = path_param(input)?;
+    let position = position_param(input, &path)?;
+    let include_declaration = input.get("include_declaration").and_then(|i| i.as_bool()).unwrap_or(false);
+    let result = with_server(config, &path, |server, uri| {
+        server.request(
+            "textDocument/references",
+            json!({
+                "textDocument": { "uri": uri.as_str() },
+                "position": position,
+                "context": { "includeDeclaration": include_declaration }
+            }),
+        )
+    })?;
+    Ok(list_or(format_locations(&result), "No references found"))
+}
+
+pub fn document_symbols(input: &Value, config: &LspConfig) -> Result<String> {
+    let path = path_param(input)?;
+    let result = with_server(config, &path, |server, uri| {
+        server.request("textDocument/documentSymbol", json!({ "textDocument": { "uri": uri.as_str() } }))
+    })?;
+    let mut lines = Vec::new();
+    for symbol in result.as_array().into_iter().flatten() {
+        push_symbol(symbol, 0, &mut lines);
+    }
+    Ok(list_or(lines, "No symbols found"))
+}
+
+/// DocumentSymbol trees are indented; flat SymbolInformation lists show their container
+fn push_symbol(symbol: &Value, depth: usize, lines: &mut Vec<String>) {
+    let name = symbol["name"].as_str().unwrap_or_default();
+    let kind = symbol["kind"]
+        .as_u64()
+        .and_then(|k| SYMBOL_KINDS.get((k as usize).wrapping_sub(1)))
+        .unwrap_or(&"symbol");
+    let range = symbol.get("selectionRange").or_else(|| symbol.get("range")).unwrap_or(&symbol["location"]["range"]);
+    let line = range["start"]["line"].as_u64().unwrap_or(0) + 1;
+    let container = symbol
+        .get("containerName")
+        .and_then(|c| c.as_str())
+        .filter(|c| !c.is_empty())
+        .map(|c| format!(" (in {})", c))
+        .unwrap_or_default();
+    lines.push(format!("{}{} {}{}  line {}", "  ".repeat(depth), kind, name, container, line));
+    for child in symbol["children"].as_array().into_iter().flatten() {
+        push_symbol(child, depth + 1, lines);
+    }
+}
+
+pub fn diagnostics(input: &Value, config: &LspConfig) -> Result<String> {
+    let path = path_param(input)?;
+    let found = with_server(config, &path, |server, uri| server.wait_for_diagnostics(&uri))?;
+    let shown = display(&path);
+    let lines: Vec<String> = found
+        .iter()
+        .map(|d| {
+            let severity = match d["severity"].as_u64() {
+                Some(1) => "error",
+                Some(2) => "warning",
+                Some(3) => "info",
+                Some(4) => "hint",
+                _ => "problem",
+            };
+            let line = d["range"]["start"]["line"].as_u64().unwrap_or(0) + 1;
+            let character = d["range"]["start"]["character"].as_u64().unwrap_or(0) + 1;
+            let message = d["message"].as_str().unwrap_or_default().replace('\n', " ");
+            let source = match (d["source"].as_str(), d["code"].as_str().map(String::from).or(d["code"].as_u64().map(|c| c.to_string()))) {
+                (Some(source), Some(code)) => format!(" ({} {})", source, code),
+                (Some(source), None) => format!(" ({})", source),
+                (None, Some(code)) => format!(" ({})", code),
+                (None, None) => String::new(),
+            };
+            format!("{}:{}:{}: {}: {}{}", shown, line, character, severity, message, source)
+        })
+        .collect();
+    Ok(list_or(lines, &format!("No problems reported in {}", shown)))
+}
+
+/// `rename_symbol`: the server's project-wide rename, applied to the files
+/// (backed up first) and shown as a diff. With `apply` false, only the diff.
+pub fn rename_symbol(input: &Value, config: &LspConfig, apply: bool) -> Result<String> {
+    let path = path_param(input)?;
+    let position = position_param(input, &path)?;
+    let new_name = input
+        .get("new_name")
+        .and_then(|n| n.as_str())
+        .map(str::trim)
+        .filter(|n| !n.is_empty())
+        .ok_or_else(|| anyhow::anyhow!("Missing 'new_name' parameter"))?;
+    let edit = with_server(config, &path, |server, uri| {
+        server.request(
+            "textDocument/rename",
+            json!({ "textDocument": { "uri": uri.as_str() }, "position": position, "newName": new_name }),
+        )
+    })?;
+
+    let mut files: Vec<(PathBuf, Vec<Value>)> = Vec::new();
+    let mut add = |uri: &str, edits: &[Value]| {
+        if let Some(path) = Url::parse(uri).ok().and_then(|u| u.to_file_path().ok()) {
+            match files.iter_mut().find(|(p, _)| *p == path) {
+                Some((_, existing)) => existing.extend(edits.iter().cloned()),
+                None => files.push((path, edits.to_vec())),
+            }
+        }
+    };
+    for (uri, edits) in edit["changes"].as_object().into_iter().flatten() {
+        add(uri, edits.as_array().map(|e| e.as_slice()).unwrap_or_default());
+    }
+    let mut skipped = 0;
+    for change in edit["documentChanges"].as_array().into_iter().flatten() {
+        match (change["textDocument"]["uri"].as_str(), change["edits"].as_array()) {
+            (Some(uri), Some(edits)) => add(uri, edits),
+            // Creating, renaming or deleting files isn't applied
+            _ => skipped += 1,
+        }
+    }
+    if files.is_empty() {
+        anyhow::bail!("The language server found nothing to rename there");
+    }
+
+    let mut diffs = Vec::new();
+    let mut changed = Vec::new();
+    let mut count = 0;
+    for (file, edits) in &files {
+        let old = fs::read_to_string(file).with_context(|| format!("Failed to read {}", file.display()))?;
+        let new = apply_edits(&old, edits).with_context(|| format!("Invalid edit for {}", file.display()))?;
+        count += edits.len();
+        if new != old {
+            diffs.push(crate::patch::unified(&display(file), &old, &new));
+            changed.push((file, new));
+        }
+    }
+
+    let summary = format!("{} edit(s) in {} file(s) to rename to '{}'", count, changed.len(), new_name);
+    let note = if skipped > 0 { format!("\n{} file create/rename/delete operation(s) were not applied", skipped) } else { String::new() };
+    if !apply {
+        return Ok(format!("{}\nWould make {}:{}\n{}", crate::tools::DRY_RUN_NOTE, summary, note, diffs.concat()));
+    }
+    for (file, new) in &changed {
+        crate::snapshot::before_write(file)?;
+        fs::write(file, new).with_context(|| format!("Failed to write {}", file.display()))?;
+    }
+    Ok(format!("Made {}:{}\n{}", summary, note, diffs.concat()))
+}
+
+/// `text` with LSP TextEdits applied; positions are in UTF-16 code units
+fn apply_edits(text: &str, edits: &[Value]) -> Result<String> {
+    let offset = |position: &Value| -> Option<usize> {
+        let line = position["line"].as_u64()? as usize;
+        let character = position["character"].as_u64()? as usize;
+        let mut start = 0;
+        for (index, content) in text.split_inclusive('\n').enumerate() {
+            if index == line {
+                let content = content.trim_end_matches(['\n', '\r']);
+                let mut units = 0;
+                for (byte, c) in content.char_indices() {
+                    if units >= character {
+                        return Some(start + byte);
+                    }
+                    units += c.len_utf16();
+                }
+                return Some(start + content.len());
+            }
+            start += content.len();
+        }
+        Some(text.len())
+    };
+
+    let mut ranges = Vec::new();
+    for edit in edits {
+        let from = offset(&edit["range"]["start"]).context("missing range")?;
+        let to = offset(&edit["range"]["end"]).context("missing range")?;
+        let new_text = edit["newText"].as_str().unwrap_or_default();
+        ranges.push((from, to.max(from), new_text));
+    }
+    // Back to front, so earlier offsets stay valid
+    ranges.sort_by_key(|r| std::cmp::Reverse(r.0));
+    let mut result = text.to_string();
+    let mut limit = text.len();
+    for (from, to, new_text) in ranges {
+        if to > limit {
+            anyhow::bail!("overlapping edits");
+        }
+        result.replace_range(from..to, new_text);
+        limit = from;
+    }
+    Ok(result)
+}
@@ -1,60 +1,258 @@
 mod config;
+mod console;
 mod client;
 mod tools;
 mod ui;
 mod chat;
 mod input;
 mod i18n;
+mod router;
+mod cache;
+mod ratelimit;
+mod process;
+mod http;
+mod database;
+mod docker;
+mod kubernetes;
+mod github;
+mod issues;
+mod azure_devops;
+mod redact;
+mod guard;
+mod paths;
+mod text;
+mod codeblocks;
+mod patch;
+mod doctor;
+mod cli;
+mod upgrade;
+mod session;
+mod history;
+mod memory;
+mod workspace;
+mod project;
+mod speech;
+mod graphics;
+mod images;
+mod safety;
+mod output;
+mod templates;
+mod import;
+mod vault;
+mod dotenv;
+mod reload;
+mod policy;
+mod metrics;
+mod bench;
+mod eval;
+mod snapshot;
+mod checkpoint;
+mod lsp;
+mod outline;
+mod buildtools;
+mod logtail;
+mod recipe;
+mod pipe;
+mod shellhook;
+mod palette;
+mod suggest;
+mod context;
+mod catalog;
+mod middleware;
 
 use anyhow::Result;
+use cli::Command;
+use console::println;
+use i18n::Language;
 use std::env;
 
-#[tokio::main]
-async fn main() -> Result<()> {
-    let args: Vec<String> = env::args().collect();
+fn main() -> Result<()> {
+    console::init();
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    // --no-dotenv is accepted anywhere on the command line
+    let use_dotenv = !args.iter().any(|a| a == "--no-dotenv") && env::var_os("AICLI_NO_DOTENV").is_none();
+    args.retain(|a| a != "--no-dotenv");
+    // So is --dry-run: tools then only describe their changes
+    if args.iter().any(|a| a == "--dry-run") {
+        tools::set_dry_run(true);
+        args.retain(|a| a != "--dry-run");
+    }
+    // And --ascii, for terminals that garble box drawing and emoji
+    if args.iter().any(|a| a == "--ascii") {
+        ui::set_ascii_only(true);
+        args.retain(|a| a != "--ascii");
+    }
+    // Before the runtime's threads exist, as setting variables isn't safe
+    // while other threads may read them
+    if use_dotenv {
+        dotenv::load();
+    }
+    tokio::runtime::Runtime::new()?.block_on(run(args))
+}
 
-    if args.len() > 1 {
-        match args[1].as_str() {
-            "--help" | "-h" => {
-                print_help();
-                return Ok(());
+async fn run(args: Vec<String>) -> Result<()> {
+    if let Err(e) = policy::load() {
+        eprintln!("\x1b[31m✗ {:#}\x1b[0m", e);
+        std::process::exit(1);
+    }
+    let command = match cli::parse(&args) {
+        Ok(command) => command,
+        Err(e) => {
+            eprintln!("\x1b[31m✗ {}\x1b[0m\n\n{}\nRun 'aicli --help' for more.", e, cli::usage());
+            std::process::exit(2);
+        }
+    };
+
+    match command {
+        Command::Help => print_help(),
+        Command::Version => println!("aicli v1.0.0"),
+        Command::Config => {
+            config::setup_config_interactive().await?;
+        }
+        Command::ConfigEncrypt { key_file } => vault::encrypt(key_file.as_deref())?,
+        Command::ConfigDecrypt => vault::decrypt()?,
+        Command::Doctor { offline } => return doctor::run(offline).await,
+        Command::Run { prompt, no_cache, output, pipe } => {
+            let mut config = config::load_config()?;
+            if pipe.is_some() {
+                config.ui.pipe = pipe;
             }
-            "--version" | "-v" => {
-                println!("aicli v1.0.0");
-                return Ok(());
+            return chat::run_prompt(config, &prompt, !no_cache, output.as_deref(), None).await;
+        }
+        Command::RunRecipe { file, vars, output, pipe, yes } => {
+            let mut config = config::load_config()?;
+            let recipe = recipe::load(&file, &vars)?;
+            if let Some(pipe) = pipe.or(recipe.pipe.clone()) {
+                config.ui.pipe = Some(pipe);
+            }
+            if let Some(model) = &recipe.model {
+                if !config.set_active_model(model) {
+                    anyhow::bail!("No model '{}' (from {})", model, file);
+                }
             }
-            "--config" | "-c" => {
-                config::setup_config_interactive().await?;
-                return Ok(());
+            let output = output.or(recipe.output.clone());
+            match &recipe.prompt {
+                Some(prompt) => chat::run_prompt(config, prompt, false, output.as_deref(), recipe.tools.clone()).await?,
+                None => recipe::run_workflow(config, &recipe, output.as_deref(), yes).await?,
+            }
+            if let (Some(message), Some(output)) = (&recipe.commit, &output) {
+                match recipe::commit(output, message)? {
+                    Some(hash) => eprintln!("[commit] {} {}", hash, message),
+                    None => eprintln!("[commit] {} is unchanged; nothing committed", output),
+                }
             }
-            _ => {}
         }
-    }
-
-    let config = match config::load_config() {
-        Ok(c) => c,
-        Err(_) => {
-            println!("\x1b[33m⚠ No configuration found. Running setup...\x1b[0m\n");
-            config::setup_config_interactive().await?
+        Command::ModelsList => list_models(&config::load_config()?),
+        Command::ModelsAdd => {
+            let mut config = config::load_config()?;
+            config::add_model_interactive(&mut config)?;
         }
-    };
+        Command::ModelsCatalog { model } => {
+            return catalog::run(config::load_config()?, model.as_deref()).await;
+        }
+        Command::ModelsRemove { name } => {
+            let mut config = config::load_config()?;
+            if !config.remove_model(&name) {
+                anyhow::bail!("Model '{}' not found", name);
+            }
+            config::save_config(&config)?;
+            println!("\x1b[32m✓ Removed model '{}'\x1b[0m", name);
+        }
+        Command::SessionsList => session::print_list(),
+        Command::SessionsShow { id } => {
+            let session = session::load(&id)?;
+            let config = config::load_config().unwrap_or_default();
+            let mut ui = ui::UI::new(config.language);
+            ui.set_code_style(&config.ui);
+            session::print_transcript(&session, &ui);
+        }
+        Command::SessionsRemove { ids } => {
+            for id in ids {
+                let id = session::delete(&id)?;
+                println!("\x1b[32m✓ Deleted session {}\x1b[0m", id);
+            }
+        }
+        Command::SessionsResume { id } => {
+            let session = session::load(&id)?;
+            return chat::run(config::load_config()?, chat::Start { resume: Some(session), ..Default::default() }).await;
+        }
+        Command::Search { query } => session::print_search(&query)?,
+        Command::Import { format, file } => import::run(format, &file)?,
+        Command::Bench { models, prompt_file } => {
+            return bench::run(&config::load_config()?, &models, &prompt_file).await;
+        }
+        Command::Eval { file, model } => {
+            return eval::run(&config::load_config()?, &file, model.as_deref()).await;
+        }
+        Command::TemplatesList => templates::print_list(),
+        Command::New { template, name } => {
+            return templates::scaffold(&config::load_config()?, &template, &name).await;
+        }
+        Command::Install => {
+            let language = config::load_config().map(|c| c.language).unwrap_or_else(|_| Language::detect());
+            chat::install_aicli(&ui::UI::new(language))?;
+        }
+        Command::Upgrade { check_only } => return upgrade::run(check_only).await,
+        // Scripts for the shell to run, written as they are
+        Command::Completions { shell } => std::print!("{}", cli::completion_script(shell)),
+        Command::ShellHook { shell } => std::print!("{}", shellhook::script(shell)),
+        Command::Fix { command, status, shell } => {
+            shellhook::fix(&config::load_config()?, &command, status, shell.as_deref()).await?;
+        }
+        Command::Chat => {
+            // Before setup, which reads the terminal
+            let piped = input::take_piped_stdin();
+            let config = match config::load_config() {
+                Ok(c) => c,
+                Err(_) => {
+                    println!("\x1b[33m⚠ No configuration found. Running setup...\x1b[0m\n");
+                    config::setup_config_interactive().await?
+                }
+            };
+            return chat::run(config, chat::Start { piped, ..Default::default() }).await;
+        }
+        Command::Tail { file, lines } => {
+            return chat::run(config::load_config()?, chat::Start { tail: Some((file, lines)), ..Default::default() }).await;
+        }
+    }
+    Ok(())
+}
 
-    chat::run(config).await
+fn list_models(config: &config::AppConfig) {
+    if config.models.is_empty() {
+        println!("No models configured. Add one with 'aicli models add'.");
+        return;
+    }
+    let mut models: Vec<_> = config.models.values().collect();
+    models.sort_by(|a, b| a.name.cmp(&b.name));
+    for model in models {
+        let marker = if model.name == config.active_model { "\x1b[32m●\x1b[0m" } else { " " };
+        println!("{} {:<20} {:<9} {}", marker, model.name, model.model_type.to_string(), model.deployment);
+    }
 }
 
 fn print_help() {
-    println!(r#"
+    let help = format!(r#"
 ╔═══════════════════════════════════════════════════════════════╗
 ║                    AICLI - Azure AI CLI                       ║
 ║                  By Leonardo M. Silva                         ║
 ╚═══════════════════════════════════════════════════════════════╝
 
-Usage: aicli [OPTIONS]
-
+{}
 Options:
-  -h, --help      Show this help message
-  -v, --version   Show version
-  -c, --config    Configure API settings
+  -h, --help          Show this help message
+  -v, --version       Show version
+  -c, --config        Configure API settings (same as 'config')
+  -p, --prompt <TEXT> Run a single prompt (same as 'run -p')
+      --no-cache      With -p: skip the response cache
+  -o, --output <FILE> With -p: also write the answer's Markdown to FILE
+      --recipe <FILE> With run: prompt, model, tools and output from a TOML file
+      --var <K=V>     With --recipe: set a {{K}} placeholder; repeatable
+      --yes           With --recipe: approve the workflow's approval steps
+      --pipe <CMD>    With run: print and save the answer as CMD turns it
+      --no-dotenv     Don't load .env from the directory or workspace root
+      --dry-run       Tools describe edits and commands instead of running them
+      --ascii         Plain ASCII output: no box drawing, spinners or emoji
 
 Commands (inside chat):
   /help           Show available commands
@@ -64,7 +262,42 @@ Commands (inside chat):
   /model <name>   Switch to specific model
   /add-model      Add a new model
   /config         Show current configuration
-  /history        Show conversation history
+  /history        Browse the conversation, copy or fork from a message
+  /cost           Show session token usage and cost
+  /router         Toggle fast/smart model routing
+  /pr             Push the branch and open a GitHub pull request
+  /redact         Toggle secret redaction (on/off)
+  /copy-code      Copy a code block from the last answer
+  /save-code      Save a code block to a file
+  /apply-code     Write code blocks to the files they name
+  /apply          Apply the last diff from the answer
+  /title          Show or set the session title
+  /search         Search saved conversations
+  /remember       Remember a fact across sessions
+  /memory         List or delete remembered facts
+  /resume         Pick a saved session to resume
+  /pin            Pin files sent with every request
+  /unpin          Unpin files
+  /init           Generate AICLI.md for this project
+  /speak          Read answers aloud (Azure Speech)
+  /mic            Speak a prompt (Azure Speech)
+  /imagine        Generate an image (Azure DALL-E)
+  /continue       Continue the last answer
+  /output         Also write answers to a file
+  /perf           Local latency and error metrics
+  /dryrun         Describe tool changes without making them
+  /revert         Undo the agent's file changes of a turn
+  /export         Save the conversation as Markdown
+  /fix            Send build errors to the agent to fix
+  /tail           Follow a log file and attach its new lines
+  /quote          Quote a message or code block in the next prompt
+  /retry          Send the last prompt again, optionally with another model or temperature
+  /compare        Send a prompt to two models at once and compare the answers
+  /best           Ask for several answers and keep the best one
+  /fork           Continue in a copy of this session, keeping the original
+  /pipe           Pipe answers through a command before showing them
+  /context        Show and trim what the next request sends
+  /thinking       Show Claude's thinking for the last answer
 
 Features:
   • TAB completion for commands (/)
@@ -76,7 +309,13 @@ Environment Variables:
   AZURE_API_KEY       API key for Azure AI Foundry
   AZURE_ENDPOINT      Azure AI endpoint URL
   AZURE_DEPLOYMENT    Model deployment name
+  AICLI_MODELS        More models as JSON, added to the config file's
+  AICLI_MODEL_<NAME>__ENDPOINT, __API_KEY, __DEPLOYMENT, __MODEL_TYPE
+                      The same, one variable per field
+  AICLI_ACTIVE_MODEL  Model to start with
+  AICLI_NO_DOTENV     Set to skip .env files (same as --no-dotenv)
 
 Config file location: ~/.aicli/config.toml
-"#);
+"#, cli::usage());
+    println!("{}", help);
 }
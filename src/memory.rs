@@ -0,0 +1,147 @@
+use anyhow::Result;
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+
+/// Whose fact it is: the user's everywhere, or one project's
+#[derive(Debug, Clone, PartialEq)]
+pub enum Scope {
+    User,
+    /// Workspace root the fact belongs to
+    Project(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct Entry {
+    pub scope: Scope,
+    pub text: String,
+}
+
+pub fn get_memory_path() -> PathBuf {
+    let home = dirs::home_dir().unwrap_or_else(|| PathBuf::from("."));
+    home.join(".aicli").join("memory.md")
+}
+
+/// Git root of the working directory, or the directory itself outside a repo
+pub fn project_root() -> String {
+    let git_root = Command::new("git")
+        .args(["rev-parse", "--show-toplevel"])
+        .output()
+        .ok()
+        .filter(|o| o.status.success())
+        .map(|o| String::from_utf8_lossy(&o.stdout).trim().to_string())
+        .filter(|root| !root.is_empty());
+    git_root.unwrap_or_else(|| {
+        std::env::current_dir()
+            .map(|p| p.display().to_string())
+            .unwrap_or_else(|_| ".".to_string())
+    })
+}
+
+/// Entries in memory.md. The file is plain Markdown: a `## User` section and
+/// `## Project: <root>` sections of `- fact` bullets, so it can be edited by
+/// hand. Lines outside that shape are ignored.
+fn load() -> Vec<Entry> {
+    let content = fs::read_to_string(get_memory_path()).unwrap_or_default();
+    let mut entries = Vec::new();
+    let mut scope = None;
+    for line in content.lines() {
+        let line = line.trim();
+        if let Some(heading) = line.strip_prefix("## ") {
+            scope = match heading.trim() {
+                "User" => Some(Scope::User),
+                other => other.strip_prefix("Project:").map(|root| Scope::Project(root.trim().to_string())),
+            };
+        } else if let (Some(text), Some(scope)) = (line.strip_prefix("- "), &scope) {
+            if !text.trim().is_empty() {
+                entries.push(Entry { scope: scope.clone(), text: text.trim().to_string() });
+            }
+        }
+    }
+    entries
+}
+
+fn save(entries: &[Entry]) -> Result<()> {
+    let mut content = String::from("# AICLI memory\n");
+    let mut scopes: Vec<&Scope> = Vec::new();
+    for entry in entries {
+        if !scopes.contains(&&entry.scope) {
+            scopes.push(&entry.scope);
+        }
+    }
+    for scope in scopes {
+        match scope {
+            Scope::User => content.push_str("\n## User\n\n"),
+            Scope::Project(root) => content.push_str(&format!("\n## Project: {}\n\n", root)),
+        }
+        for entry in entries.iter().filter(|e| &e.scope == scope) {
+            content.push_str(&format!("- {}\n", entry.text));
+        }
+    }
+
+    let path = get_memory_path();
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, content)?;
+    Ok(())
+}
+
+/// User facts plus the current project's, in the order /memory numbers them
+pub fn visible() -> Vec<Entry> {
+    let project = Scope::Project(project_root());
+    let entries = load();
+    let user = entries.iter().filter(|e| e.scope == Scope::User);
+    let local = entries.iter().filter(|e| e.scope == project);
+    user.chain(local).cloned().collect()
+}
+
+pub fn remember(text: &str, project: bool) -> Result<Entry> {
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if text.is_empty() {
+        anyhow::bail!("Nothing to remember");
+    }
+    let scope = if project { Scope::Project(project_root()) } else { Scope::User };
+    let entry = Entry { scope, text };
+
+    let mut entries = load();
+    if !entries.iter().any(|e| e.scope == entry.scope && e.text == entry.text) {
+        entries.push(entry.clone());
+        save(&entries)?;
+    }
+    Ok(entry)
+}
+
+/// Delete the `number`th (1-based) entry of `visible()`
+pub fn forget(number: usize) -> Result<Entry> {
+    let target = number
+        .checked_sub(1)
+        .and_then(|i| visible().into_iter().nth(i))
+        .ok_or_else(|| anyhow::anyhow!("No memory #{}. See /memory", number))?;
+
+    let mut entries = load();
+    if let Some(i) = entries.iter().position(|e| e.scope == target.scope && e.text == target.text) {
+        entries.remove(i);
+    }
+    save(&entries)?;
+    Ok(target)
+}
+
+/// System prompt section with the remembered facts, empty when there are none
+pub fn prompt_section() -> String {
+    let entries = visible();
+    if entries.is_empty() {
+        return String::new();
+    }
+    let mut section = String::from(
+        "\n\n## Memory\n\nFacts the user asked you to remember across sessions. Treat them as true unless the user says otherwise:\n",
+    );
+    for entry in entries {
+        let label = match entry.scope {
+            Scope::User => "",
+            Scope::Project(_) => "(this project) ",
+        };
+        section.push_str(&format!("- {}{}\n", label, entry.text));
+    }
+    section
+}
@@ -0,0 +1,240 @@
+//! Local performance metrics, off unless `[metrics] enabled = true`. Each
+//! model request and tool call appends a line to ~/.aicli/metrics.jsonl;
+//! nothing is sent anywhere. `/perf` summarizes the file.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use crate::config::MetricsConfig;
+use crate::console::println;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Record {
+    /// One streamed chat request
+    Request {
+        time: i64,
+        model: String,
+        /// Until the first streamed token, None when nothing streamed
+        first_token_ms: Option<u64>,
+        total_ms: u64,
+        output_tokens: usize,
+        #[serde(default)]
+        error: bool,
+    },
+    Tool {
+        time: i64,
+        tool: String,
+        ms: u64,
+        success: bool,
+    },
+}
+
+impl Record {
+    fn time(&self) -> i64 {
+        match self {
+            Record::Request { time, .. } | Record::Tool { time, .. } => *time,
+        }
+    }
+}
+
+pub fn get_metrics_path() -> PathBuf {
+    crate::config::get_config_path().with_file_name("metrics.jsonl")
+}
+
+/// Load the on/off switch from config; call at startup and on reload
+pub fn configure(config: &MetricsConfig) {
+    ENABLED.store(config.enabled, Ordering::Relaxed);
+}
+
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+fn append(record: &Record) {
+    if !is_enabled() {
+        return;
+    }
+    let path = get_metrics_path();
+    let Ok(line) = serde_json::to_string(record) else {
+        return;
+    };
+    // Metrics are best effort: a full disk shouldn't interrupt a chat
+    let _ = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .and_then(|mut file| writeln!(file, "{}", line));
+}
+
+pub fn record_request(model: &str, first_token: Option<Duration>, total: Duration, output_tokens: usize, error: bool) {
+    append(&Record::Request {
+        time: chrono::Utc::now().timestamp(),
+        model: model.to_string(),
+        first_token_ms: first_token.map(|d| d.as_millis() as u64),
+        total_ms: total.as_millis() as u64,
+        output_tokens,
+        error,
+    });
+}
+
+pub fn record_tool(tool: &str, elapsed: Duration, success: bool) {
+    append(&Record::Tool {
+        time: chrono::Utc::now().timestamp(),
+        tool: tool.to_string(),
+        ms: elapsed.as_millis() as u64,
+        success,
+    });
+}
+
+/// Records from the last `days` days, oldest first; unreadable lines are skipped
+pub fn read(days: u64) -> Vec<Record> {
+    let since = chrono::Utc::now().timestamp() - (days * 86_400) as i64;
+    fs::read_to_string(get_metrics_path())
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|line| serde_json::from_str::<Record>(line).ok())
+        .filter(|record| record.time() >= since)
+        .collect()
+}
+
+pub fn clear() -> Result<()> {
+    let path = get_metrics_path();
+    if path.exists() {
+        fs::remove_file(path)?;
+    }
+    Ok(())
+}
+
+/// The value below which `p` (0-100) percent of `values` fall
+fn percentile(values: &mut [u64], p: usize) -> Option<u64> {
+    if values.is_empty() {
+        return None;
+    }
+    values.sort_unstable();
+    Some(values[(values.len() - 1) * p / 100])
+}
+
+fn format_ms(ms: Option<u64>) -> String {
+    match ms {
+        Some(ms) if ms >= 10_000 => format!("{:.0}s", ms as f64 / 1000.0),
+        Some(ms) if ms >= 1000 => format!("{:.1}s", ms as f64 / 1000.0),
+        Some(ms) => format!("{}ms", ms),
+        None => "-".to_string(),
+    }
+}
+
+fn error_rate(errors: usize, total: usize) -> String {
+    if total == 0 {
+        return "-".to_string();
+    }
+    format!("{:.1}%", errors as f64 * 100.0 / total as f64)
+}
+
+/// `/perf [days]`: per model and per tool, over the last `days` days
+pub fn print_summary(days: u64) {
+    let records = read(days);
+    if records.is_empty() {
+        println!("  \x1b[90mNo metrics in the last {} day(s).\x1b[0m", days);
+        if !is_enabled() {
+            println!("  \x1b[90mSet [metrics] enabled = true in config.toml to record them (stored locally only).\x1b[0m");
+        }
+        return;
+    }
+
+    #[derive(Default)]
+    struct ModelStats {
+        first_token: Vec<u64>,
+        total: Vec<u64>,
+        rates: Vec<f64>,
+        requests: usize,
+        errors: usize,
+    }
+    #[derive(Default)]
+    struct ToolStats {
+        durations: Vec<u64>,
+        failures: usize,
+    }
+
+    let mut models: BTreeMap<String, ModelStats> = BTreeMap::new();
+    let mut tools: BTreeMap<String, ToolStats> = BTreeMap::new();
+    for record in records {
+        match record {
+            Record::Request { model, first_token_ms, total_ms, output_tokens, error, .. } => {
+                let stats = models.entry(model).or_default();
+                stats.requests += 1;
+                if error {
+                    stats.errors += 1;
+                    continue;
+                }
+                stats.total.push(total_ms);
+                if let Some(first) = first_token_ms {
+                    stats.first_token.push(first);
+                    // Generation speed, after the first token arrived
+                    let generating = total_ms.saturating_sub(first);
+                    if output_tokens > 0 && generating > 0 {
+                        stats.rates.push(output_tokens as f64 * 1000.0 / generating as f64);
+                    }
+                }
+            }
+            Record::Tool { tool, ms, success, .. } => {
+                let stats = tools.entry(tool).or_default();
+                stats.durations.push(ms);
+                if !success {
+                    stats.failures += 1;
+                }
+            }
+        }
+    }
+
+    println!("\n\x1b[1mLast {} day(s)\x1b[0m", days);
+    if !models.is_empty() {
+        println!(
+            "\n  \x1b[90m{:<24} {:>8} {:>8} {:>8} {:>8} {:>9} {:>7}\x1b[0m",
+            "model", "requests", "ttft p50", "ttft p95", "tok/s", "total p50", "errors"
+        );
+        for (name, mut stats) in models {
+            let rate = if stats.rates.is_empty() {
+                "-".to_string()
+            } else {
+                format!("{:.1}", stats.rates.iter().sum::<f64>() / stats.rates.len() as f64)
+            };
+            println!(
+                "  {:<24} {:>8} {:>8} {:>8} {:>8} {:>9} {:>7}",
+                crate::text::truncate(&name, 24),
+                stats.requests,
+                format_ms(percentile(&mut stats.first_token, 50)),
+                format_ms(percentile(&mut stats.first_token, 95)),
+                rate,
+                format_ms(percentile(&mut stats.total, 50)),
+                error_rate(stats.errors, stats.requests)
+            );
+        }
+    }
+    if !tools.is_empty() {
+        println!(
+            "\n  \x1b[90m{:<24} {:>8} {:>8} {:>8} {:>8}\x1b[0m",
+            "tool", "calls", "p50", "p95", "failed"
+        );
+        for (name, mut stats) in tools {
+            let calls = stats.durations.len();
+            println!(
+                "  {:<24} {:>8} {:>8} {:>8} {:>8}",
+                crate::text::truncate(&name, 24),
+                calls,
+                format_ms(percentile(&mut stats.durations, 50)),
+                format_ms(percentile(&mut stats.durations, 95)),
+                error_rate(stats.failures, calls)
+            );
+        }
+    }
+    println!("\n  \x1b[90m{} · /perf <days> · /perf clear\x1b[0m\n", crate::paths::display(&get_metrics_path()));
+}
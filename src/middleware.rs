@@ -0,0 +1,223 @@
+//! The built-in request middlewares and the `[[middlewares]]` list that
+//! turns them on. Each model request passes through them in order before
+//! it is sent, and each response before its body streams: gateways that
+//! want headers of their own, payload logs, a last redaction pass. A
+//! `command` middleware runs a program of the user's, so anything else can
+//! be done without changing aicli.
+
+use anyhow::{Context, Result};
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::sync::{Arc, RwLock};
+
+use crate::client::{ApiRequest, ApiResponse, Middleware};
+use crate::config::{AppConfig, MiddlewareConfig, MiddlewareKind, ToolsConfig};
+
+/// Header values a payload log never shows
+const SECRET_HEADERS: &[&str] = &["api-key", "x-api-key", "authorization", "ocp-apim-subscription-key"];
+
+/// A configured middleware with the models it applies to (all when empty)
+type Configured = (Vec<String>, Arc<dyn Middleware>);
+
+static MIDDLEWARES: RwLock<Vec<Configured>> = RwLock::new(Vec::new());
+
+/// Build the middlewares of `[[middlewares]]`; called whenever the config
+/// is loaded, so the chat and the one-off commands go through the same ones
+pub fn configure(config: &AppConfig) {
+    let middlewares = config
+        .middlewares
+        .iter()
+        .map(|m| {
+            let middleware: Arc<dyn Middleware> = match m.kind {
+                MiddlewareKind::Headers => Arc::new(Headers(m.headers.clone())),
+                MiddlewareKind::Log => Arc::new(Log(log_path(m))),
+                MiddlewareKind::Redact => Arc::new(Redact),
+                MiddlewareKind::Command => Arc::new(UserCommand { command: m.command.clone(), tools: config.tools.clone() }),
+            };
+            (m.models.clone(), middleware)
+        })
+        .collect();
+    *MIDDLEWARES.write().unwrap() = middlewares;
+}
+
+/// The middlewares a client of `model` sends through
+pub fn for_model(model: &str) -> Vec<Arc<dyn Middleware>> {
+    MIDDLEWARES
+        .read()
+        .unwrap()
+        .iter()
+        .filter(|(models, _)| models.is_empty() || models.iter().any(|m| m == model))
+        .map(|(_, middleware)| middleware.clone())
+        .collect()
+}
+
+fn log_path(config: &MiddlewareConfig) -> PathBuf {
+    match &config.path {
+        Some(path) => crate::paths::resolve(path),
+        None => crate::config::get_config_path().with_file_name("requests.jsonl"),
+    }
+}
+
+fn headers_json(headers: impl Iterator<Item = (String, String)>) -> Value {
+    let headers: Map<String, Value> = headers
+        .map(|(name, value)| {
+            let shown = if SECRET_HEADERS.contains(&name.to_lowercase().as_str()) { "[REDACTED]".to_string() } else { value };
+            (name, Value::String(shown))
+        })
+        .collect();
+    Value::Object(headers)
+}
+
+fn response_json(response: &ApiResponse) -> Value {
+    json!({
+        "event": "response",
+        "model": response.model,
+        "url": response.url,
+        "status": response.status,
+        "elapsed_ms": response.elapsed.as_millis() as u64,
+        "headers": headers_json(response.headers.iter().map(|(n, v)| (n.to_string(), v.to_str().unwrap_or_default().to_string()))),
+    })
+}
+
+/// `kind = "headers"`: added to every request, replacing aicli's own of the same name
+struct Headers(HashMap<String, String>);
+
+impl Middleware for Headers {
+    fn on_request(&self, request: &mut ApiRequest) -> Result<()> {
+        for (name, value) in &self.0 {
+            request.set_header(name, value);
+        }
+        Ok(())
+    }
+}
+
+/// `kind = "log"`: one JSON line per request and per response, keys masked
+struct Log(PathBuf);
+
+impl Log {
+    fn append(&self, entry: Value) {
+        // Logging is best effort: a full disk shouldn't interrupt a chat
+        let _ = self
+            .0
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| OpenOptions::new().create(true).append(true).open(&self.0))
+            .and_then(|mut file| writeln!(file, "{}", entry));
+    }
+}
+
+impl Middleware for Log {
+    fn on_request(&self, request: &mut ApiRequest) -> Result<()> {
+        self.append(json!({
+            "time": chrono::Local::now().to_rfc3339(),
+            "event": "request",
+            "model": request.model,
+            "url": request.url,
+            "headers": headers_json(request.headers.iter().cloned()),
+            "body": request.body,
+        }));
+        Ok(())
+    }
+
+    fn on_response(&self, response: &ApiResponse) {
+        let mut entry = response_json(response);
+        entry["time"] = json!(chrono::Local::now().to_rfc3339());
+        self.append(entry);
+    }
+}
+
+/// `kind = "redact"`: the `[redaction]` patterns over every string of the
+/// body, tool definitions and requests of one-off commands included
+struct Redact;
+
+fn redact_strings(value: &mut Value) {
+    match value {
+        Value::String(text) => *text = crate::redact::redact(text),
+        Value::Array(items) => items.iter_mut().for_each(redact_strings),
+        Value::Object(fields) => fields.values_mut().for_each(redact_strings),
+        _ => {}
+    }
+}
+
+impl Middleware for Redact {
+    fn on_request(&self, request: &mut ApiRequest) -> Result<()> {
+        redact_strings(&mut request.body);
+        Ok(())
+    }
+}
+
+/// `kind = "command"`: a program run through the shell with the request
+/// as JSON on stdin. What it prints (`url`, `headers`, `body`, any of them)
+/// replaces the request's; nothing printed leaves it as it was, and a
+/// failure stops it. Responses are passed the same way, output ignored.
+struct UserCommand {
+    command: String,
+    tools: ToolsConfig,
+}
+
+impl UserCommand {
+    fn run(&self, input: &Value) -> Result<String> {
+        let mut child = crate::tools::shell_command(&self.command, &self.tools)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .with_context(|| format!("Middleware '{}' could not be started", self.command))?;
+        if let Some(mut stdin) = child.stdin.take() {
+            // From a thread, so a program that doesn't read it all can't block on a full pipe
+            let input = input.to_string();
+            std::thread::spawn(move || writeln!(stdin, "{}", input));
+        }
+        let output = child.wait_with_output()?;
+        if !output.status.success() {
+            anyhow::bail!(
+                "Middleware '{}' failed ({}): {}",
+                self.command,
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+}
+
+impl Middleware for UserCommand {
+    fn on_request(&self, request: &mut ApiRequest) -> Result<()> {
+        let input = json!({
+            "event": "request",
+            "model": request.model,
+            "url": request.url,
+            "headers": request.headers.iter().map(|(n, v)| (n.clone(), json!(v))).collect::<Map<String, Value>>(),
+            "body": request.body,
+        });
+        let output = self.run(&input)?;
+        if output.is_empty() {
+            return Ok(());
+        }
+        let changed: Value = serde_json::from_str(&output)
+            .with_context(|| format!("Middleware '{}' printed something other than a JSON object", self.command))?;
+        if let Some(url) = changed.get("url").and_then(|u| u.as_str()) {
+            request.url = url.to_string();
+        }
+        if let Some(headers) = changed.get("headers").and_then(|h| h.as_object()) {
+            request.headers = headers
+                .iter()
+                .map(|(name, value)| (name.clone(), value.as_str().map(String::from).unwrap_or_else(|| value.to_string())))
+                .collect();
+        }
+        if let Some(body) = changed.get("body") {
+            request.body = body.clone();
+        }
+        Ok(())
+    }
+
+    fn on_response(&self, response: &ApiResponse) {
+        if let Err(e) = self.run(&response_json(response)) {
+            eprintln!("\x1b[33m⚠ {}\x1b[0m", e);
+        }
+    }
+}
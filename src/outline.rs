@@ -0,0 +1,150 @@
+//! `get_outline`: the declarations of a source file with their line numbers,
+//! so the model can find its way around a big file and read only the part
+//! it needs. Declarations are recognized line by line with per-language
+//! patterns, without a full parser; nesting follows the indentation.
+
+use anyhow::Result;
+use regex::Regex;
+use serde_json::Value;
+use std::sync::OnceLock;
+
+use crate::paths;
+
+const MAX_SIGNATURE_CHARS: usize = 120;
+const MAX_ENTRIES: usize = 400;
+
+/// Declaration patterns, matched against the line without its indentation
+fn patterns(language: &str) -> Option<&'static [Regex]> {
+    static PATTERNS: OnceLock<Vec<(&'static str, Vec<Regex>)>> = OnceLock::new();
+    let all = PATTERNS.get_or_init(|| {
+        let compile = |sources: &[&str]| sources.iter().map(|s| Regex::new(s).unwrap()).collect::<Vec<_>>();
+        let javascript = compile(&[
+            r"^(export\s+)?(default\s+)?(declare\s+)?(abstract\s+)?(async\s+)?(function\*?|class|interface|type|enum|namespace)\s+[\w$]",
+            r"^(export\s+)?(const|let|var)\s+[\w$]+\s*(:[^=]+)?=\s*(async\s*)?(function\b|\([^)]*\)\s*(:[^=]+)?=>|[\w$]+\s*=>)",
+            r"^((public|private|protected|static|readonly|async|get|set|override)\s+)*[\w$]+\s*(<[^>]*>)?\([^)]*\)\s*(:\s*[^{;]+)?\{\s*$",
+        ]);
+        let c_like = compile(&[
+            r"^((public|private|protected|internal|static|final|abstract|sealed|partial|export|inline|virtual)\s+)*(class|interface|enum|record|struct|union|namespace|object|trait)\s+\w",
+            r"^(typedef\s+)?(struct|enum|union)\s+\w+\s*\{",
+            r"^((public|private|protected|internal|static|final|abstract|override|virtual|async|synchronized|extern|inline|const|unsafe|suspend|open)\s+)*[\w:<>\[\],\*&~]+[\s\*&]+[\w:~]+\s*\([^;]*$",
+        ]);
+        vec![
+            ("rust", compile(&[
+                r#"^(pub(\([^)]*\))?\s+)?((async|const|unsafe|extern\s+"[^"]*"|default)\s+)*(fn|struct|enum|trait|impl|mod|type|union|static|const)\b"#,
+                r"^macro_rules!",
+            ])),
+            ("python", compile(&[r"^(async\s+)?(def|class)\s+\w"])),
+            ("go", compile(&[r"^func\s", r"^type\s+\w+\s+(struct|interface)\b", r"^type\s+\w+\s"])),
+            ("ruby", compile(&[r"^(def|class|module)\s"])),
+            ("php", compile(&[
+                r"^((abstract|final|public|private|protected|static)\s+)*(function|class|interface|trait|enum)\s+\w",
+            ])),
+            ("swift", compile(&[
+                r"^((public|private|internal|fileprivate|open|static|final|override|mutating)\s+)*(func|class|struct|enum|protocol|extension|init)\b",
+            ])),
+            ("bash", compile(&[r"^(function\s+)?[\w-]+\s*\(\)\s*\{?", r"^function\s+[\w-]+"])),
+            ("powershell", compile(&[r"(?i)^(function|filter|class)\s+[\w-]+"])),
+            ("sql", compile(&[r"(?i)^create\s+(or\s+replace\s+)?(table|view|function|procedure|index|trigger|type)\b"])),
+            ("markdown", compile(&[r"^#{1,6}\s"])),
+            ("javascript", javascript.clone()),
+            ("typescript", javascript.clone()),
+            ("jsx", javascript.clone()),
+            ("tsx", javascript.clone()),
+            ("vue", javascript),
+            ("java", c_like.clone()),
+            ("kotlin", c_like.clone()),
+            ("csharp", c_like.clone()),
+            ("c", c_like.clone()),
+            ("cpp", c_like),
+        ]
+    });
+    all.iter().find(|(name, _)| *name == language).map(|(_, patterns)| patterns.as_slice())
+}
+
+/// Words that start control flow, not declarations, in C-like languages
+const STATEMENTS: &[&str] = &[
+    "if", "else", "for", "foreach", "while", "switch", "catch", "return", "do", "try", "using", "lock", "throw",
+    "new", "await", "yield", "sizeof", "case", "when", "defer", "go", "delete", "elif",
+];
+
+fn is_comment(line: &str, language: &str) -> bool {
+    if language == "markdown" {
+        return false;
+    }
+    ["//", "/*", "*", "#", "--", "<!--"].iter().any(|p| line.starts_with(p))
+}
+
+/// The declaration part of a line: up to the body or the end of the signature
+fn signature(line: &str, language: &str) -> String {
+    let mut end = line.len();
+    if language != "python" && language != "markdown" {
+        if let Some(brace) = line.find('{') {
+            end = brace;
+        }
+    }
+    let signature = line[..end].trim().trim_end_matches([':', '=']).trim_end();
+    crate::text::truncate(signature, MAX_SIGNATURE_CHARS)
+}
+
+pub fn get_outline(input: &Value) -> Result<String> {
+    let path = input
+        .get("path")
+        .and_then(|p| p.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing 'path' parameter"))?;
+    let target = paths::resolve(path);
+    let language = crate::tools::detect_language(&target).unwrap_or_default();
+    let patterns = patterns(language).ok_or_else(|| {
+        let kind = if language.is_empty() { "these" } else { language };
+        anyhow::anyhow!("No outline for {} files; use read_file or search_content instead", kind)
+    })?;
+    let content = std::fs::read_to_string(&target)?;
+
+    // Indentation levels seen so far, to nest entries under their parents
+    let mut levels: Vec<usize> = Vec::new();
+    let mut entries = Vec::new();
+    let mut in_block_comment = false;
+    for (index, raw) in content.lines().enumerate() {
+        let trimmed = raw.trim_start();
+        if in_block_comment {
+            in_block_comment = !trimmed.contains("*/");
+            continue;
+        }
+        if trimmed.starts_with("/*") && !trimmed.contains("*/") && language != "python" {
+            in_block_comment = true;
+            continue;
+        }
+        if trimmed.is_empty() || trimmed.starts_with('}') || is_comment(trimmed, language) {
+            continue;
+        }
+        if !patterns.iter().any(|p| p.is_match(trimmed)) {
+            continue;
+        }
+        let first_word = trimmed.split(|c: char| !c.is_alphanumeric() && c != '_').next().unwrap_or_default();
+        if STATEMENTS.contains(&first_word) || trimmed.ends_with(';') && language != "rust" {
+            continue;
+        }
+
+        let indent = if language == "markdown" {
+            trimmed.chars().take_while(|c| *c == '#').count() - 1
+        } else {
+            raw.len() - trimmed.len()
+        };
+        while levels.last().is_some_and(|&level| level >= indent) {
+            levels.pop();
+        }
+        let depth = levels.len();
+        levels.push(indent);
+        entries.push(format!("{:>5}  {}{}", index + 1, "  ".repeat(depth), signature(trimmed, language)));
+    }
+
+    if entries.is_empty() {
+        return Ok(format!("No declarations found in {} ({} lines)", path, content.lines().count()));
+    }
+    let total = entries.len();
+    let mut outline = format!("{} ({} lines, {})\n", path, content.lines().count(), language);
+    outline.push_str(&entries.into_iter().take(MAX_ENTRIES).collect::<Vec<_>>().join("\n"));
+    if total > MAX_ENTRIES {
+        outline.push_str(&format!("\n... and {} more", total - MAX_ENTRIES));
+    }
+    Ok(outline)
+}
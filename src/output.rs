@@ -0,0 +1,84 @@
+use anyhow::{Context, Result};
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::paths;
+
+/// A copy of the assistant's raw Markdown, written to a file as it streams
+/// (`aicli run --output` and `/output`). Answers are separated by a blank line.
+pub struct OutputFile {
+    path: PathBuf,
+    file: File,
+    /// The current answer has written something
+    started: bool,
+    /// The file ends in a newline
+    at_line_start: bool,
+    /// An answer was written, so the next one starts after a blank line
+    separate: bool,
+    /// First write failure, reported after the answer
+    error: Option<String>,
+}
+
+impl OutputFile {
+    /// Create or truncate the file, with its parent directories
+    pub fn create(raw: &str) -> Result<Self> {
+        let path = paths::resolve(raw);
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+        let file = File::create(&path).with_context(|| format!("Failed to create {}", paths::display(&path)))?;
+        Ok(Self { path, file, started: false, at_line_start: true, separate: false, error: None })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Append a streamed token
+    pub fn write(&mut self, token: &str) {
+        if token.is_empty() {
+            return;
+        }
+        if std::mem::take(&mut self.separate) {
+            self.write_raw(if self.at_line_start { "\n" } else { "\n\n" });
+        }
+        self.write_raw(token);
+        self.started = true;
+        self.at_line_start = token.ends_with('\n');
+    }
+
+    /// End of an answer; the next one starts after a blank line
+    pub fn finish(&mut self) {
+        if std::mem::take(&mut self.started) {
+            self.separate = true;
+        }
+    }
+
+    /// The next tokens continue the last answer (/continue) instead of
+    /// starting a new one
+    pub fn resume(&mut self) {
+        self.separate = false;
+    }
+
+    pub fn take_error(&mut self) -> Option<String> {
+        self.error.take()
+    }
+
+    fn write_raw(&mut self, text: &str) {
+        if self.error.is_some() {
+            return;
+        }
+        if let Err(e) = self.file.write_all(text.as_bytes()).and_then(|_| self.file.flush()) {
+            self.error = Some(format!("Failed to write {}: {}", paths::display(&self.path), e));
+        }
+    }
+}
+
+impl Drop for OutputFile {
+    fn drop(&mut self) {
+        if !self.at_line_start {
+            self.write_raw("\n");
+        }
+    }
+}
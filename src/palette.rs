@@ -0,0 +1,78 @@
+//! Ctrl+P at the prompt: one menu of the slash commands, the prompts sent in
+//! this session, the models and the files of the current directory. Typing
+//! filters it; what is picked goes onto the input line, to edit or send.
+
+use crate::client::Message;
+use crate::history;
+use crate::input::COMMANDS;
+use crate::text;
+use crate::ui::{MenuItem, UI};
+
+/// Prompts of this session offered, most recent first
+const RECENT_PROMPTS: usize = 10;
+/// Entries of the current directory offered, most recently changed first
+const RECENT_FILES: usize = 15;
+
+enum Entry {
+    /// Replaces the input line
+    Line(String),
+    /// Added to the input line as an @file reference
+    File(String),
+}
+
+/// The input line after picking from the palette; `typed` again when the
+/// user leaves it
+pub fn open(typed: &str, messages: &[Message], models: &[String], active_model: &str, ui: &UI) -> String {
+    let mut entries = Vec::new();
+    let mut items = Vec::new();
+
+    for (command, description) in COMMANDS {
+        entries.push(Entry::Line(format!("{} ", command)));
+        items.push(MenuItem::new(*command, *description, false).in_group("Commands"));
+    }
+
+    let mut prompts: Vec<String> = Vec::new();
+    for message in messages.iter().rev().filter(|m| m.role == "user" && !history::is_tool_results(m)) {
+        let prompt = history::prompt_text(message).trim().to_string();
+        if !prompt.is_empty() && !prompt.starts_with('/') && !prompts.contains(&prompt) {
+            prompts.push(prompt);
+        }
+        if prompts.len() == RECENT_PROMPTS {
+            break;
+        }
+    }
+    for prompt in prompts {
+        let preview = prompt.split_whitespace().collect::<Vec<_>>().join(" ");
+        items.push(MenuItem::new(text::truncate(&preview, 70), "", false).in_group("Recent prompts"));
+        entries.push(Entry::Line(prompt));
+    }
+
+    let mut models = models.to_vec();
+    models.sort();
+    for model in models {
+        let detail = if model == active_model { "in use" } else { "" };
+        items.push(MenuItem::new(&model, detail, false).in_group("Models"));
+        entries.push(Entry::Line(format!("/model {}", model)));
+    }
+
+    for file in UI::get_recent_files(".", RECENT_FILES) {
+        items.push(MenuItem::new(format!("@{}", file), "", false).in_group("Files"));
+        entries.push(Entry::File(file));
+    }
+
+    let Some(choice) = ui.pick("Command palette", &items, 0) else {
+        return typed.to_string();
+    };
+    match &entries[choice] {
+        Entry::Line(line) => line.clone(),
+        Entry::File(file) => {
+            let reference = if file.contains(' ') { format!("@\"{}\"", file) } else { format!("@{}", file) };
+            // A directory stays open so Tab can go on into it
+            let space = if file.ends_with('/') { "" } else { " " };
+            match typed.trim_end() {
+                "" => format!("{}{}", reference, space),
+                typed => format!("{} {}{}", typed, reference, space),
+            }
+        }
+    }
+}
@@ -0,0 +1,335 @@
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::paths;
+
+/// One file's changes from a unified diff
+#[derive(Debug, Clone)]
+pub struct FilePatch {
+    /// None for a new file (--- /dev/null)
+    pub old_path: Option<String>,
+    /// None for a deleted file (+++ /dev/null)
+    pub new_path: Option<String>,
+    pub hunks: Vec<Hunk>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Hunk {
+    /// 1-based start line in the old file, when the header has one
+    pub old_start: Option<usize>,
+    pub lines: Vec<HunkLine>,
+}
+
+#[derive(Debug, Clone)]
+pub enum HunkLine {
+    Context(String),
+    Remove(String),
+    Add(String),
+}
+
+impl FilePatch {
+    /// Path the patch is applied to
+    pub fn target(&self) -> &str {
+        self.new_path.as_deref().or(self.old_path.as_deref()).unwrap_or_default()
+    }
+
+    /// Lines added and removed
+    pub fn stats(&self) -> (usize, usize) {
+        let mut added = 0;
+        let mut removed = 0;
+        for line in self.hunks.iter().flat_map(|h| &h.lines) {
+            match line {
+                HunkLine::Add(_) => added += 1,
+                HunkLine::Remove(_) => removed += 1,
+                HunkLine::Context(_) => {}
+            }
+        }
+        (added, removed)
+    }
+}
+
+/// Whether `text` looks like a unified diff
+pub fn is_diff(text: &str) -> bool {
+    text.lines().any(|l| l.starts_with("@@")) && text.lines().any(|l| l.starts_with("--- ") || l.starts_with("+++ "))
+}
+
+/// Parse a unified diff (git or plain `diff -u` output). Hunk headers without
+/// line numbers (`@@ ... @@`) are accepted; those hunks are located by their
+/// context alone.
+pub fn parse(diff: &str) -> Result<Vec<FilePatch>> {
+    let mut patches: Vec<FilePatch> = Vec::new();
+    let mut old_path: Option<Option<String>> = None;
+    let mut in_hunk = false;
+
+    let lines: Vec<&str> = diff.lines().collect();
+    for (i, line) in lines.iter().enumerate() {
+        // A removed "-- comment" line also starts with "--- ", so only a
+        // "---" followed by "+++" starts a file
+        let next_is_new_path = lines.get(i + 1).is_some_and(|l| l.starts_with("+++ "));
+        if let Some(path) = line.strip_prefix("--- ").filter(|_| next_is_new_path) {
+            old_path = Some(parse_path(path, "a/"));
+            in_hunk = false;
+        } else if let (Some(path), Some(old)) = (line.strip_prefix("+++ "), old_path.take()) {
+            patches.push(FilePatch {
+                old_path: old,
+                new_path: parse_path(path, "b/"),
+                hunks: Vec::new(),
+            });
+            in_hunk = false;
+        } else if let Some(header) = line.strip_prefix("@@") {
+            let patch = patches.last_mut()
+                .ok_or_else(|| anyhow::anyhow!("Hunk before any ---/+++ file header"))?;
+            patch.hunks.push(Hunk { old_start: parse_old_start(header), lines: Vec::new() });
+            in_hunk = true;
+        } else if in_hunk {
+            let Some(hunk) = patches.last_mut().and_then(|p| p.hunks.last_mut()) else {
+                continue;
+            };
+            if let Some(text) = line.strip_prefix('+') {
+                hunk.lines.push(HunkLine::Add(text.to_string()));
+            } else if let Some(text) = line.strip_prefix('-') {
+                hunk.lines.push(HunkLine::Remove(text.to_string()));
+            } else if let Some(text) = line.strip_prefix(' ') {
+                hunk.lines.push(HunkLine::Context(text.to_string()));
+            } else if line.is_empty() {
+                // Editors and models often strip the space from blank context lines
+                hunk.lines.push(HunkLine::Context(String::new()));
+            } else if line.starts_with('\\') {
+                // "\ No newline at end of file"
+            } else {
+                in_hunk = false;
+            }
+        }
+    }
+
+    for hunk in patches.iter_mut().flat_map(|p| p.hunks.iter_mut()) {
+        // Blank lines after the last hunk are usually just the end of the message
+        while matches!(hunk.lines.last(), Some(HunkLine::Context(t)) if t.is_empty()) {
+            hunk.lines.pop();
+        }
+    }
+    patches.retain(|p| !p.hunks.is_empty());
+    if patches.is_empty() {
+        anyhow::bail!("No hunks found in the diff");
+    }
+    Ok(patches)
+}
+
+fn parse_path(raw: &str, prefix: &str) -> Option<String> {
+    // Drop the timestamp `diff -u` appends after a tab
+    let path = raw.split('\t').next().unwrap_or(raw).trim();
+    if path == "/dev/null" {
+        return None;
+    }
+    Some(path.strip_prefix(prefix).unwrap_or(path).to_string())
+}
+
+/// "-12,7 +12,9 @@ fn main()" -> 12
+fn parse_old_start(header: &str) -> Option<usize> {
+    let range = header.trim().strip_prefix('-')?;
+    let start = range.split([',', ' ']).next()?;
+    start.parse().ok()
+}
+
+/// Apply every file's hunks. All files are patched in memory first, so a
+/// hunk that does not match leaves every file untouched. Returns the paths
+/// written or deleted.
+pub fn apply_all(patches: &[FilePatch]) -> Result<Vec<PathBuf>> {
+    let mut planned = Vec::new();
+    for patch in patches {
+        let path = paths::resolve(patch.target());
+        let content = patched_content(patch, &path)?;
+        planned.push((path, content));
+    }
+
+    for (path, content) in &planned {
+        match content {
+            Some(content) => {
+                if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+                    fs::create_dir_all(parent)?;
+                }
+                fs::write(path, content)
+                    .with_context(|| format!("Failed to write {}", paths::display(path)))?;
+            }
+            None => fs::remove_file(path)
+                .with_context(|| format!("Failed to delete {}", paths::display(path)))?,
+        }
+    }
+    Ok(planned.into_iter().map(|(path, _)| path).collect())
+}
+
+/// New content of `path` after the patch, or None when the patch deletes it.
+/// Each hunk is matched at its stated line first, then at the nearest place
+/// its context and removed lines occur, ignoring trailing whitespace.
+fn patched_content(patch: &FilePatch, path: &Path) -> Result<Option<String>> {
+    if patch.new_path.is_none() {
+        return Ok(None);
+    }
+
+    let original = match &patch.old_path {
+        Some(_) => fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", paths::display(path)))?,
+        None => String::new(),
+    };
+    let crlf = original.contains("\r\n");
+    let trailing_newline = original.is_empty() || original.ends_with('\n');
+    let mut lines: Vec<String> = original
+        .lines()
+        .map(|l| l.strip_suffix('\r').unwrap_or(l).to_string())
+        .collect();
+
+    // Lines added/removed by earlier hunks shift later hunks' stated positions
+    let mut offset: isize = 0;
+    for (number, hunk) in patch.hunks.iter().enumerate() {
+        let old: Vec<&str> = hunk.lines.iter().filter_map(|l| match l {
+            HunkLine::Context(t) | HunkLine::Remove(t) => Some(t.as_str()),
+            HunkLine::Add(_) => None,
+        }).collect();
+        let new: Vec<String> = hunk.lines.iter().filter_map(|l| match l {
+            HunkLine::Context(t) | HunkLine::Add(t) => Some(t.clone()),
+            HunkLine::Remove(_) => None,
+        }).collect();
+
+        let hint = hunk.old_start
+            .map(|s| (s.saturating_sub(1) as isize + offset).max(0) as usize)
+            .unwrap_or(0)
+            .min(lines.len());
+        let start = find_hunk(&lines, &old, hint, hunk.old_start.is_some()).ok_or_else(|| {
+            anyhow::anyhow!(
+                "Hunk {} does not match {} (the file may have changed since the diff was written)",
+                number + 1,
+                paths::display(path)
+            )
+        })?;
+
+        offset += new.len() as isize - old.len() as isize;
+        lines.splice(start..start + old.len(), new);
+    }
+
+    let mut content = lines.join(if crlf { "\r\n" } else { "\n" });
+    if trailing_newline && !content.is_empty() {
+        content.push_str(if crlf { "\r\n" } else { "\n" });
+    }
+    Ok(Some(content))
+}
+
+/// Index where `old` occurs in `lines`, searching outward from `hint`
+fn find_hunk(lines: &[String], old: &[&str], hint: usize, positioned: bool) -> Option<usize> {
+    if old.is_empty() {
+        // Pure addition: only its stated position means anything
+        return Some(if positioned { hint } else { lines.len() });
+    }
+    if old.len() > lines.len() {
+        return None;
+    }
+
+    let matches_at = |start: usize| {
+        lines[start..start + old.len()]
+            .iter()
+            .zip(old)
+            .all(|(have, want)| have.trim_end() == want.trim_end())
+    };
+
+    let last = lines.len() - old.len();
+    let hint = hint.min(last);
+    (0..=last.max(hint))
+        .flat_map(|distance| {
+            let before = hint.checked_sub(distance);
+            let after = Some(hint + distance).filter(|&i| i <= last && distance > 0);
+            before.into_iter().chain(after)
+        })
+        .find(|&start| matches_at(start))
+}
+
+/// Lines of context around each change in `unified`
+const DIFF_CONTEXT: usize = 3;
+/// Above this many (old x new) differing lines, the changed region is shown
+/// as removed and re-added instead of line by line
+const MAX_DIFF_CELLS: usize = 4_000_000;
+
+/// A unified diff from `old` to `new`, for showing a change before (or
+/// instead of) making it. Empty when nothing changes.
+pub fn unified(path: &str, old: &str, new: &str) -> String {
+    let old_lines: Vec<&str> = old.lines().collect();
+    let new_lines: Vec<&str> = new.lines().collect();
+    if old_lines == new_lines {
+        return String::new();
+    }
+
+    // Common head and tail, then a line-level LCS of what is left
+    let prefix = old_lines.iter().zip(&new_lines).take_while(|(a, b)| a == b).count();
+    let suffix = old_lines[prefix..]
+        .iter()
+        .rev()
+        .zip(new_lines[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let a = &old_lines[prefix..old_lines.len() - suffix];
+    let b = &new_lines[prefix..new_lines.len() - suffix];
+
+    let mut lines: Vec<HunkLine> = old_lines[..prefix].iter().map(|l| HunkLine::Context(l.to_string())).collect();
+    if a.len() * b.len() > MAX_DIFF_CELLS {
+        lines.extend(a.iter().map(|l| HunkLine::Remove(l.to_string())));
+        lines.extend(b.iter().map(|l| HunkLine::Add(l.to_string())));
+    } else {
+        let width = b.len() + 1;
+        let mut lcs = vec![0u32; (a.len() + 1) * width];
+        for i in (0..a.len()).rev() {
+            for j in (0..b.len()).rev() {
+                lcs[i * width + j] = if a[i] == b[j] {
+                    lcs[(i + 1) * width + j + 1] + 1
+                } else {
+                    lcs[(i + 1) * width + j].max(lcs[i * width + j + 1])
+                };
+            }
+        }
+        let (mut i, mut j) = (0, 0);
+        while i < a.len() || j < b.len() {
+            if i < a.len() && j < b.len() && a[i] == b[j] {
+                lines.push(HunkLine::Context(a[i].to_string()));
+                i += 1;
+                j += 1;
+            } else if i < a.len() && (j == b.len() || lcs[(i + 1) * width + j] >= lcs[i * width + j + 1]) {
+                lines.push(HunkLine::Remove(a[i].to_string()));
+                i += 1;
+            } else {
+                lines.push(HunkLine::Add(b[j].to_string()));
+                j += 1;
+            }
+        }
+    }
+    lines.extend(old_lines[old_lines.len() - suffix..].iter().map(|l| HunkLine::Context(l.to_string())));
+
+    let mut diff = format!("--- a/{}\n+++ b/{}\n", path, path);
+    let changed: Vec<usize> = (0..lines.len()).filter(|&i| !matches!(lines[i], HunkLine::Context(_))).collect();
+    let mut index = 0;
+    while index < changed.len() {
+        // Changes closer than twice the context share a hunk
+        let start = changed[index].saturating_sub(DIFF_CONTEXT);
+        let mut end = changed[index];
+        while index < changed.len() && changed[index] <= end + 2 * DIFF_CONTEXT {
+            end = changed[index];
+            index += 1;
+        }
+        let end = (end + DIFF_CONTEXT + 1).min(lines.len());
+
+        // (start, length) on one side; an empty range starts at the line before
+        let range = |skip: fn(&HunkLine) -> bool| {
+            let before = lines[..start].iter().filter(|l| !skip(l)).count();
+            let length = lines[start..end].iter().filter(|l| !skip(l)).count();
+            (if length == 0 { before } else { before + 1 }, length)
+        };
+        let (old_start, old_length) = range(|l| matches!(l, HunkLine::Add(_)));
+        let (new_start, new_length) = range(|l| matches!(l, HunkLine::Remove(_)));
+        diff.push_str(&format!("@@ -{},{} +{},{} @@\n", old_start, old_length, new_start, new_length));
+        for line in &lines[start..end] {
+            match line {
+                HunkLine::Context(text) => diff.push_str(&format!(" {}\n", text)),
+                HunkLine::Remove(text) => diff.push_str(&format!("-{}\n", text)),
+                HunkLine::Add(text) => diff.push_str(&format!("+{}\n", text)),
+            }
+        }
+    }
+    diff
+}
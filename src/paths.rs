@@ -0,0 +1,57 @@
+use std::path::{Path, PathBuf};
+
+/// Windows refuses paths longer than this unless they use the `\\?\` prefix
+const WINDOWS_MAX_PATH: usize = 259;
+
+/// Turn a path as typed by the user or the model into one the OS accepts:
+/// surrounding quotes removed, `~` expanded, and on Windows forward slashes
+/// converted and long paths given the `\\?\` (or `\\?\UNC\`) prefix.
+pub fn resolve(raw: &str) -> PathBuf {
+    let trimmed = raw.trim();
+    let unquoted = ['"', '\'']
+        .iter()
+        .find_map(|q| trimmed.strip_prefix(*q).and_then(|s| s.strip_suffix(*q)))
+        .unwrap_or(trimmed);
+
+    let expanded = match unquoted.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with('/') || rest.starts_with('\\') => {
+            match dirs::home_dir() {
+                Some(home) => format!("{}{}", home.display(), rest),
+                None => unquoted.to_string(),
+            }
+        }
+        _ => unquoted.to_string(),
+    };
+
+    if !cfg!(windows) {
+        return PathBuf::from(expanded);
+    }
+
+    let native = expanded.replace('/', "\\");
+    if native.len() <= WINDOWS_MAX_PATH || native.starts_with(r"\\?\") {
+        return PathBuf::from(native);
+    }
+
+    // The prefix disables normalization, so it needs an absolute path
+    let absolute = if Path::new(&native).is_absolute() || native.starts_with(r"\\") {
+        native
+    } else {
+        std::env::current_dir()
+            .map(|cwd| format!("{}\\{}", cwd.display(), native.trim_start_matches(".\\")))
+            .unwrap_or(native)
+    };
+    match absolute.strip_prefix(r"\\") {
+        Some(unc) => PathBuf::from(format!(r"\\?\UNC\{}", unc)),
+        None => PathBuf::from(format!(r"\\?\{}", absolute)),
+    }
+}
+
+/// A path for display, without the long-path prefix `resolve` may add
+pub fn display(path: &Path) -> String {
+    let text = path.display().to_string();
+    if let Some(unc) = text.strip_prefix(r"\\?\UNC\") {
+        format!(r"\\{}", unc)
+    } else {
+        text.strip_prefix(r"\\?\").unwrap_or(&text).to_string()
+    }
+}
@@ -0,0 +1,55 @@
+//! Answers piped through a command before they are shown or saved (`[ui]
+//! pipe`, `/pipe`, `aicli run --pipe`): a Markdown renderer like `glow -`,
+//! `jq .` for JSON, or a project's own linter.
+
+use anyhow::{Context, Result};
+use std::io::Write;
+use std::process::Stdio;
+
+use crate::config::ToolsConfig;
+use crate::tools::shell_command;
+
+pub struct Piped {
+    pub stdout: String,
+    pub stderr: String,
+    /// None when the command was killed by a signal
+    pub status: Option<i32>,
+}
+
+impl Piped {
+    pub fn success(&self) -> bool {
+        self.status == Some(0)
+    }
+
+    /// "`jq .` exited with status 5: parse error ..."
+    pub fn failure(&self, command: &str) -> String {
+        let status = self.status.map(|s| format!("exited with status {}", s)).unwrap_or_else(|| "was killed".to_string());
+        let stderr = self.stderr.trim();
+        if stderr.is_empty() {
+            format!("`{}` {}", command, status)
+        } else {
+            format!("`{}` {}: {}", command, status, crate::text::truncate(stderr, 500))
+        }
+    }
+}
+
+/// Run `command` in the tools' shell with `input` on its stdin
+pub fn run(command: &str, input: &str, config: &ToolsConfig) -> Result<Piped> {
+    let mut cmd = shell_command(command, config);
+    cmd.stdin(Stdio::piped()).stdout(Stdio::piped()).stderr(Stdio::piped());
+    let mut child = cmd.spawn().with_context(|| format!("Could not run `{}`", command))?;
+    // From a thread, so a command that writes before reading all of its
+    // input can't fill the pipe and block both sides
+    let mut stdin = child.stdin.take().context("No stdin")?;
+    let input = input.to_string();
+    let writer = std::thread::spawn(move || {
+        let _ = stdin.write_all(input.as_bytes());
+    });
+    let output = child.wait_with_output()?;
+    let _ = writer.join();
+    Ok(Piped {
+        stdout: String::from_utf8_lossy(&output.stdout).to_string(),
+        stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+        status: output.status.code(),
+    })
+}
@@ -0,0 +1,171 @@
+//! Organization policy: a file an administrator puts at
+//! /etc/aicli/policy.toml (%ProgramData%\aicli\policy.toml on Windows).
+//! aicli only reads it, and its rules win over config.toml: endpoints
+//! outside `allowed_endpoints` are refused, `disabled_tools` are hidden from
+//! the model, redaction can be enforced with extra patterns, and tool calls
+//! and model requests can be written to an audit log.
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+
+/// Tool arguments kept per audit entry
+const MAX_AUDIT_INPUT_CHARS: usize = 2000;
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Policy {
+    /// Hosts model, image, speech and content safety requests may go to; an
+    /// entry also matches its subdomains. Empty allows every host.
+    pub allowed_endpoints: Vec<String>,
+    /// Tools the model is never offered and that never run
+    pub disabled_tools: Vec<String>,
+    pub redaction: RedactionPolicy,
+    pub audit: AuditPolicy,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct RedactionPolicy {
+    /// Keep redaction on, whatever config.toml or /redact say
+    pub enforce: bool,
+    /// Regexes masked in addition to the user's
+    pub patterns: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct AuditPolicy {
+    pub enabled: bool,
+    /// JSON Lines file appended to; defaults to ~/.aicli/audit.jsonl
+    pub path: Option<String>,
+}
+
+static POLICY: OnceLock<Option<Policy>> = OnceLock::new();
+static AUDIT_WARNED: AtomicBool = AtomicBool::new(false);
+
+pub fn get_policy_path() -> PathBuf {
+    if cfg!(windows) {
+        let data = std::env::var("ProgramData").unwrap_or_else(|_| r"C:\ProgramData".to_string());
+        PathBuf::from(data).join("aicli").join("policy.toml")
+    } else {
+        PathBuf::from("/etc/aicli/policy.toml")
+    }
+}
+
+/// Read the policy file, once per process. A file that exists but can't be
+/// read or parsed is an error rather than no policy.
+pub fn load() -> Result<()> {
+    let path = get_policy_path();
+    let policy = match fs::read_to_string(&path) {
+        Ok(content) => Some(toml::from_str::<Policy>(&content).with_context(|| format!("Invalid organization policy {}", path.display()))?),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+        Err(e) => return Err(e).with_context(|| format!("Failed to read organization policy {}", path.display())),
+    };
+    let _ = POLICY.set(policy);
+    Ok(())
+}
+
+pub fn get() -> Option<&'static Policy> {
+    POLICY.get().and_then(|p| p.as_ref())
+}
+
+/// Fail unless the policy allows requests to `url`'s host
+pub fn check_endpoint(url: &str) -> Result<()> {
+    let Some(policy) = get().filter(|p| !p.allowed_endpoints.is_empty()) else {
+        return Ok(());
+    };
+    let host = reqwest::Url::parse(url).ok().and_then(|u| u.host_str().map(String::from)).unwrap_or_default();
+    if !crate::http::host_allowed(&host, &policy.allowed_endpoints) {
+        anyhow::bail!("Endpoint '{}' is not allowed by the organization policy ({})", host, get_policy_path().display());
+    }
+    Ok(())
+}
+
+pub fn tool_disabled(name: &str) -> bool {
+    get().is_some_and(|p| p.disabled_tools.iter().any(|t| t == name))
+}
+
+pub fn redaction_enforced() -> bool {
+    get().is_some_and(|p| p.redaction.enforce)
+}
+
+/// The policy's extra redaction patterns
+pub fn redaction_patterns() -> &'static [String] {
+    get().map(|p| p.redaction.patterns.as_slice()).unwrap_or_default()
+}
+
+fn audit_path(policy: &AuditPolicy) -> PathBuf {
+    match &policy.path {
+        Some(path) => crate::paths::resolve(path),
+        None => crate::config::get_config_path().with_file_name("audit.jsonl"),
+    }
+}
+
+/// Append an `event` entry to the audit log when the policy asks for one.
+/// A log that can't be written is reported once on stderr.
+pub fn audit(event: &str, details: Value) {
+    let Some(policy) = get().filter(|p| p.audit.enabled) else {
+        return;
+    };
+    let user = std::env::var("USER").or_else(|_| std::env::var("USERNAME")).unwrap_or_default();
+    let cwd = std::env::current_dir().map(|d| d.display().to_string()).unwrap_or_default();
+    let mut entry = json!({
+        "time": chrono::Local::now().to_rfc3339(),
+        "user": user,
+        "cwd": cwd,
+        "event": event,
+    });
+    if let (Some(entry), Value::Object(details)) = (entry.as_object_mut(), details) {
+        entry.extend(details);
+    }
+
+    let path = audit_path(&policy.audit);
+    let written = path
+        .parent()
+        .map_or(Ok(()), fs::create_dir_all)
+        .and_then(|_| OpenOptions::new().create(true).append(true).open(&path))
+        .and_then(|mut file| writeln!(file, "{}", entry));
+    if let Err(e) = written {
+        if !AUDIT_WARNED.swap(true, Ordering::Relaxed) {
+            eprintln!("\x1b[33m⚠ Could not write the audit log {}: {}\x1b[0m", path.display(), e);
+        }
+    }
+}
+
+/// A tool call's audit entry; arguments are redacted and cut
+pub fn audit_tool_call(name: &str, input: &Value, success: bool) {
+    if get().is_none_or(|p| !p.audit.enabled) {
+        return;
+    }
+    let input = crate::text::truncate(&crate::redact::redact(&input.to_string()), MAX_AUDIT_INPUT_CHARS);
+    audit("tool_call", json!({ "tool": name, "input": input, "success": success }));
+}
+
+/// One line per rule, for `aicli doctor`
+pub fn summary(policy: &Policy) -> String {
+    let mut parts = Vec::new();
+    if !policy.allowed_endpoints.is_empty() {
+        parts.push(format!("endpoints: {}", policy.allowed_endpoints.join(", ")));
+    }
+    if !policy.disabled_tools.is_empty() {
+        parts.push(format!("{} tool(s) disabled", policy.disabled_tools.len()));
+    }
+    if policy.redaction.enforce || !policy.redaction.patterns.is_empty() {
+        let state = if policy.redaction.enforce { "enforced" } else { "extra patterns" };
+        parts.push(format!("redaction {}", state));
+    }
+    if policy.audit.enabled {
+        parts.push(format!("audit log {}", crate::paths::display(&audit_path(&policy.audit))));
+    }
+    if parts.is_empty() {
+        "no rules".to_string()
+    } else {
+        parts.join(" · ")
+    }
+}
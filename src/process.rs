@@ -0,0 +1,202 @@
+use crate::config::ToolsConfig;
+use crate::paths;
+use crate::tools::{apply_call_env, shell_command};
+use anyhow::Result;
+use serde_json::Value;
+use std::collections::{BTreeMap, VecDeque};
+use std::io::{BufRead, BufReader, Read};
+use std::process::{Child, Stdio};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Instant;
+
+/// Lines of recent output kept per process
+const RING_CAPACITY: usize = 500;
+const DEFAULT_TAIL_LINES: usize = 50;
+
+type OutputBuffer = Arc<Mutex<VecDeque<String>>>;
+
+struct BackgroundProcess {
+    command: String,
+    child: Child,
+    output: OutputBuffer,
+    started: Instant,
+}
+
+struct Registry {
+    next_id: u32,
+    processes: BTreeMap<u32, BackgroundProcess>,
+}
+
+fn registry() -> &'static Mutex<Registry> {
+    static REGISTRY: OnceLock<Mutex<Registry>> = OnceLock::new();
+    REGISTRY.get_or_init(|| {
+        Mutex::new(Registry {
+            next_id: 1,
+            processes: BTreeMap::new(),
+        })
+    })
+}
+
+/// Copy lines from a child pipe into the ring buffer until it closes
+fn spawn_reader(pipe: impl Read + Send + 'static, output: OutputBuffer, prefix: &'static str) {
+    std::thread::spawn(move || {
+        for line in BufReader::new(pipe).lines().map_while(|l| l.ok()) {
+            let mut buffer = output.lock().unwrap();
+            if buffer.len() >= RING_CAPACITY {
+                buffer.pop_front();
+            }
+            buffer.push_back(format!("{}{}", prefix, line));
+        }
+    });
+}
+
+fn parse_id(input: &Value) -> Result<u32> {
+    input
+        .get("id")
+        .and_then(|i| i.as_u64().or_else(|| i.as_str().and_then(|s| s.parse().ok())))
+        .map(|i| i as u32)
+        .ok_or_else(|| anyhow::anyhow!("Missing 'id' parameter"))
+}
+
+fn status_of(process: &mut BackgroundProcess) -> String {
+    match process.child.try_wait() {
+        Ok(Some(status)) => format!("exited ({})", status.code().map(|c| c.to_string()).unwrap_or_else(|| "signal".to_string())),
+        Ok(None) => "running".to_string(),
+        Err(e) => format!("unknown ({})", e),
+    }
+}
+
+/// Start a command without waiting for it (dev servers, watchers)
+pub fn run_background(input: &Value, config: &ToolsConfig) -> Result<String> {
+    let command = input
+        .get("command")
+        .and_then(|c| c.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing 'command' parameter"))?;
+
+    let working_dir = input.get("working_dir").and_then(|w| w.as_str()).map(paths::resolve);
+
+    let mut cmd = shell_command(command, config);
+    apply_call_env(&mut cmd, input);
+    if let Some(dir) = working_dir {
+        cmd.current_dir(dir);
+    }
+    cmd.stdin(Stdio::null()).stdout(Stdio::piped()).stderr(Stdio::piped());
+
+    // Own process group so kill_process also takes down children (npm -> node)
+    isolate_process_group(&mut cmd);
+
+    let mut child = cmd.spawn()?;
+    let output: OutputBuffer = Arc::new(Mutex::new(VecDeque::new()));
+    if let Some(stdout) = child.stdout.take() {
+        spawn_reader(stdout, output.clone(), "");
+    }
+    if let Some(stderr) = child.stderr.take() {
+        spawn_reader(stderr, output.clone(), "[stderr] ");
+    }
+
+    let pid = child.id();
+    let mut registry = registry().lock().unwrap();
+    let id = registry.next_id;
+    registry.next_id += 1;
+    registry.processes.insert(id, BackgroundProcess {
+        command: command.to_string(),
+        child,
+        output,
+        started: Instant::now(),
+    });
+
+    Ok(format!(
+        "Started background process {} (pid {}): {}\nUse check_process with id {} to read its output.",
+        id, pid, command, id
+    ))
+}
+
+/// Report status and recent output, or list all processes when no id is given
+pub fn check_process(input: &Value) -> Result<String> {
+    let mut registry = registry().lock().unwrap();
+
+    if input.get("id").is_none() {
+        if registry.processes.is_empty() {
+            return Ok("No background processes".to_string());
+        }
+        let lines: Vec<String> = registry.processes
+            .iter_mut()
+            .map(|(id, p)| format!("{}: [{}] {} ({}s)", id, status_of(p), p.command, p.started.elapsed().as_secs()))
+            .collect();
+        return Ok(lines.join("\n"));
+    }
+
+    let id = parse_id(input)?;
+    let tail = input
+        .get("lines")
+        .and_then(|l| l.as_u64())
+        .map(|l| l as usize)
+        .unwrap_or(DEFAULT_TAIL_LINES);
+
+    let process = registry.processes
+        .get_mut(&id)
+        .ok_or_else(|| anyhow::anyhow!("No background process with id {}", id))?;
+
+    let status = status_of(process);
+    let output = process.output.lock().unwrap();
+    let recent: Vec<&str> = output.iter().skip(output.len().saturating_sub(tail)).map(|s| s.as_str()).collect();
+
+    Ok(format!(
+        "Process {}: {}\nStatus: {} · running for {}s\n--- last {} lines ---\n{}",
+        id,
+        process.command,
+        status,
+        process.started.elapsed().as_secs(),
+        recent.len(),
+        recent.join("\n")
+    ))
+}
+
+/// Kill a child and everything it spawned. On Unix this assumes the child
+/// was started in its own process group.
+pub fn kill_tree(child: &mut Child) {
+    if let Ok(Some(_)) = child.try_wait() {
+        return;
+    }
+
+    let pid = child.id().to_string();
+    if cfg!(windows) {
+        let _ = std::process::Command::new("taskkill").args(["/PID", &pid, "/T", "/F"]).output();
+    } else {
+        // Negative pid signals the whole process group
+        let _ = std::process::Command::new("kill").args(["-TERM", "--", &format!("-{}", pid)]).output();
+    }
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+/// Put the command in its own process group so kill_tree reaches its children
+pub fn isolate_process_group(cmd: &mut std::process::Command) {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        cmd.process_group(0);
+    }
+    #[cfg(not(unix))]
+    let _ = cmd;
+}
+
+pub fn kill_process(input: &Value) -> Result<String> {
+    let id = parse_id(input)?;
+    let mut registry = registry().lock().unwrap();
+    let mut process = registry.processes
+        .remove(&id)
+        .ok_or_else(|| anyhow::anyhow!("No background process with id {}", id))?;
+
+    kill_tree(&mut process.child);
+
+    Ok(format!("Killed background process {}: {}", id, process.command))
+}
+
+/// Stop everything the agent started; called when the session ends
+pub fn kill_all() {
+    let mut registry = registry().lock().unwrap();
+    for (_, mut process) in std::mem::take(&mut registry.processes) {
+        kill_tree(&mut process.child);
+    }
+}
@@ -0,0 +1,121 @@
+use std::fs;
+use std::path::Path;
+
+use crate::text;
+
+/// Project guide loaded into the system prompt and written by /init
+pub const INSTRUCTIONS_FILE: &str = "AICLI.md";
+
+const MAX_INSTRUCTIONS_CHARS: usize = 20000;
+const MAX_FILE_CHARS: usize = 3000;
+const MAX_README_CHARS: usize = 4000;
+const MAX_LAYOUT_ENTRIES: usize = 200;
+
+pub const INIT_PROMPT: &str = "Write AICLI.md for the repository described below: a guide for an AI coding \
+assistant that will work in it. Use these sections: `# <project name>`, `## Overview` (what it is, in 2-4 sentences), \
+`## Build and Test` (the exact commands, in code blocks), `## Project Layout` (the important directories and files), \
+and `## Conventions` (code style, error handling, test layout and other patterns the files show). \
+Only state what the files support, and keep it under 80 lines. Reply with the Markdown only.";
+
+/// Files that say how a project is built, run and tested
+const BUILD_FILES: &[&str] = &[
+    "Cargo.toml", "package.json", "pyproject.toml", "setup.py", "requirements.txt", "go.mod",
+    "pom.xml", "build.gradle", "build.gradle.kts", "composer.json", "Gemfile", "Makefile",
+    "justfile", "CMakeLists.txt", "Dockerfile", "docker-compose.yml", "tsconfig.json",
+];
+
+/// Directories left out of the layout
+const SKIP_DIRS: &[&str] = &["node_modules", "target", "dist", "build", "vendor", "__pycache__", "venv", "bin", "obj"];
+
+/// System prompt section with the root's AICLI.md, empty when there is none
+pub fn instructions_section(root: &str) -> String {
+    let Ok(content) = fs::read_to_string(Path::new(root).join(INSTRUCTIONS_FILE)) else {
+        return String::new();
+    };
+    if content.trim().is_empty() {
+        return String::new();
+    }
+    format!(
+        "\n\n## Project Instructions ({})\n\n{}",
+        INSTRUCTIONS_FILE,
+        text::truncate(content.trim(), MAX_INSTRUCTIONS_CHARS)
+    )
+}
+
+/// What /init sends the model: build files, CI workflows, README and layout
+pub fn scan(root: &Path) -> String {
+    let mut summary = String::new();
+
+    for name in BUILD_FILES {
+        if let Ok(content) = fs::read_to_string(root.join(name)) {
+            summary.push_str(&format!("--- {} ---\n{}\n\n", name, text::truncate(&content, MAX_FILE_CHARS)));
+        }
+    }
+
+    let workflows = root.join(".github").join("workflows");
+    if let Ok(entries) = fs::read_dir(&workflows) {
+        let mut files: Vec<_> = entries.flatten().map(|e| e.path()).collect();
+        files.sort();
+        for path in files.iter().take(3) {
+            if let Ok(content) = fs::read_to_string(path) {
+                let name = path.strip_prefix(root).unwrap_or(path).display();
+                summary.push_str(&format!("--- {} ---\n{}\n\n", name, text::truncate(&content, MAX_FILE_CHARS)));
+            }
+        }
+    }
+
+    let readme = ["README.md", "README", "README.rst", "readme.md"]
+        .iter()
+        .find_map(|name| Some((name, fs::read_to_string(root.join(name)).ok()?)));
+    if let Some((name, content)) = readme {
+        summary.push_str(&format!("--- {} ---\n{}\n\n", name, text::truncate(&content, MAX_README_CHARS)));
+    }
+
+    let mut layout = Vec::new();
+    list_layout(root, root, 0, &mut layout);
+    summary.push_str(&format!("--- Directory layout ---\n{}\n", layout.join("\n")));
+    summary
+}
+
+/// Two levels of the tree, directories first, hidden and build output skipped
+fn list_layout(root: &Path, dir: &Path, depth: usize, out: &mut Vec<String>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    let mut entries: Vec<_> = entries
+        .flatten()
+        .filter(|e| {
+            let name = e.file_name().to_string_lossy().to_string();
+            !name.starts_with('.') && !SKIP_DIRS.contains(&name.as_str())
+        })
+        .collect();
+    entries.sort_by_key(|e| (!e.path().is_dir(), e.file_name()));
+
+    for entry in entries {
+        if out.len() >= MAX_LAYOUT_ENTRIES {
+            out.push("...".to_string());
+            return;
+        }
+        let path = entry.path();
+        let relative = path.strip_prefix(root).unwrap_or(&path).display().to_string();
+        if path.is_dir() {
+            out.push(format!("{}/", relative));
+            if depth < 1 {
+                list_layout(root, &path, depth + 1, out);
+            }
+        } else {
+            out.push(relative);
+        }
+    }
+}
+
+/// The generated guide without the code fence models like to wrap it in
+pub fn clean_generated(markdown: &str) -> String {
+    let trimmed = markdown.trim();
+    let unfenced = trimmed
+        .strip_prefix("```markdown")
+        .or_else(|| trimmed.strip_prefix("```md"))
+        .and_then(|rest| rest.trim_end().strip_suffix("```"))
+        .unwrap_or(trimmed);
+    format!("{}\n", unfenced.trim())
+}
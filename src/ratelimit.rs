@@ -0,0 +1,106 @@
+use crate::config::ModelConfig;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+/// Classic token bucket: `capacity` units, refilled continuously over a minute
+#[derive(Debug)]
+struct Bucket {
+    capacity: f64,
+    available: f64,
+    last_refill: Instant,
+}
+
+impl Bucket {
+    fn per_minute(limit: u32) -> Self {
+        Self {
+            capacity: limit as f64,
+            available: limit as f64,
+            last_refill: Instant::now(),
+        }
+    }
+
+    fn refill(&mut self) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+        self.available = (self.available + elapsed * self.capacity / 60.0).min(self.capacity);
+        self.last_refill = now;
+    }
+
+    /// How long until `amount` units are available (zero if they are now)
+    fn wait_time(&mut self, amount: f64) -> Duration {
+        self.refill();
+        // A single request bigger than the whole budget would never fit
+        let amount = amount.min(self.capacity);
+        if self.available >= amount {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64((amount - self.available) * 60.0 / self.capacity)
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Limiter {
+    requests: Option<Bucket>,
+    tokens: Option<Bucket>,
+}
+
+/// Limiters are shared per model name so every client (router, sub-calls,
+/// parallel requests) draws from the same budget.
+fn registry() -> &'static Mutex<HashMap<String, Arc<Mutex<Limiter>>>> {
+    static REGISTRY: OnceLock<Mutex<HashMap<String, Arc<Mutex<Limiter>>>>> = OnceLock::new();
+    REGISTRY.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+fn limiter_for(model: &ModelConfig) -> Option<Arc<Mutex<Limiter>>> {
+    if model.requests_per_minute.is_none() && model.tokens_per_minute.is_none() {
+        return None;
+    }
+
+    let mut registry = registry().lock().unwrap();
+    let limiter = registry.entry(model.name.clone()).or_insert_with(|| {
+        Arc::new(Mutex::new(Limiter {
+            requests: model.requests_per_minute.map(Bucket::per_minute),
+            tokens: model.tokens_per_minute.map(Bucket::per_minute),
+        }))
+    });
+    Some(limiter.clone())
+}
+
+/// Wait until the model's budget allows a request of `tokens` tokens, then
+/// debit it. Azure counts prompt tokens plus `max_tokens` against TPM quota
+/// when the request arrives, so callers should pass that sum.
+pub async fn acquire(model: &ModelConfig, tokens: usize) {
+    let Some(limiter) = limiter_for(model) else {
+        return;
+    };
+
+    loop {
+        let wait = {
+            let mut limiter = limiter.lock().unwrap();
+            let request_wait = limiter.requests.as_mut()
+                .map(|b| b.wait_time(1.0))
+                .unwrap_or(Duration::ZERO);
+            let token_wait = limiter.tokens.as_mut()
+                .map(|b| b.wait_time(tokens as f64))
+                .unwrap_or(Duration::ZERO);
+            let wait = request_wait.max(token_wait);
+
+            if wait.is_zero() {
+                if let Some(b) = limiter.requests.as_mut() {
+                    b.available -= 1.0;
+                }
+                if let Some(b) = limiter.tokens.as_mut() {
+                    b.available -= (tokens as f64).min(b.capacity);
+                }
+            }
+            wait
+        };
+
+        if wait.is_zero() {
+            return;
+        }
+        tokio::time::sleep(wait).await;
+    }
+}
@@ -0,0 +1,247 @@
+//! `aicli run --recipe FILE`: a prompt template saved with its model, the
+//! tools it may use and where the answer goes, so cron or Task Scheduler can
+//! run it unattended (a nightly dependency audit written to a report, say).
+//! A recipe with `[[step]]`s instead of a prompt is a workflow: prompts, tool
+//! calls and approval gates run in order, each step optionally depending on
+//! what an earlier one produced.
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs;
+use std::io::IsTerminal;
+use std::path::Path;
+
+use crate::chat::Headless;
+use crate::client::{AzureClient, StopReason};
+use crate::config::AppConfig;
+use crate::github::git;
+use crate::paths;
+use crate::tools::{ToolCall, ToolExecutor};
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Recipe {
+    /// `{date}`, `{time}`, `{project}` and the `[vars]` are filled in
+    #[serde(default)]
+    pub prompt: Option<String>,
+    /// The active model when left out
+    #[serde(default)]
+    pub model: Option<String>,
+    /// Tools the model may call: all of them when left out, none when empty
+    #[serde(default)]
+    pub tools: Option<Vec<String>>,
+    /// File the answer is written to, with the same placeholders
+    #[serde(default)]
+    pub output: Option<String>,
+    /// Command the answers are piped through before they are printed and saved
+    #[serde(default)]
+    pub pipe: Option<String>,
+    /// Commit `output` with this message after the run
+    #[serde(default)]
+    pub commit: Option<String>,
+    /// Directory to run in, relative to the recipe; the current one otherwise
+    #[serde(default)]
+    pub dir: Option<String>,
+    #[serde(default)]
+    pub vars: HashMap<String, String>,
+    #[serde(rename = "step", default)]
+    pub steps: Vec<Step>,
+}
+
+/// One step of a workflow: exactly one of `prompt`, `tool` and `approve`
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Step {
+    /// For `{steps.NAME}` and `when.step` in later steps
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Ask the model; prompt steps share one conversation
+    #[serde(default)]
+    pub prompt: Option<String>,
+    /// Run a tool directly with `input`, whatever `tools` allows the model
+    #[serde(default)]
+    pub tool: Option<String>,
+    #[serde(default)]
+    pub input: Option<toml::Value>,
+    /// Ask this question and stop the workflow unless the answer is yes
+    #[serde(default)]
+    pub approve: Option<String>,
+    /// Only run when an earlier step's output matches
+    #[serde(default)]
+    pub when: Option<Condition>,
+    /// Go on when the tool fails instead of stopping the workflow
+    #[serde(default)]
+    pub continue_on_error: bool,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Condition {
+    /// The `name` of an earlier step
+    pub step: String,
+    #[serde(default)]
+    pub contains: Option<String>,
+    #[serde(default)]
+    pub not_contains: Option<String>,
+    /// Whether the step's tool ran without error, or its answer completed. A
+    /// command that exits non-zero still ran: test its output instead
+    #[serde(default)]
+    pub success: Option<bool>,
+}
+
+/// What a step produced, for the conditions and placeholders after it
+struct StepOutput {
+    text: String,
+    success: bool,
+}
+
+impl Condition {
+    fn holds(&self, outputs: &HashMap<String, StepOutput>) -> Result<bool> {
+        let output = outputs
+            .get(&self.step)
+            .ok_or_else(|| anyhow::anyhow!("`when` refers to step '{}', which hasn't run", self.step))?;
+        Ok(self.contains.as_ref().is_none_or(|c| output.text.contains(c.as_str()))
+            && self.not_contains.as_ref().is_none_or(|c| !output.text.contains(c.as_str()))
+            && self.success.is_none_or(|s| s == output.success))
+    }
+}
+
+/// Read the recipe at `path`, move to its directory and fill in its
+/// placeholders; `overrides` (`--var KEY=VALUE`) win over its `[vars]`
+pub fn load(path: &str, overrides: &[(String, String)]) -> Result<Recipe> {
+    let path = paths::resolve(path);
+    let content = fs::read_to_string(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let mut recipe: Recipe = toml::from_str(&content).with_context(|| format!("Invalid recipe {}", path.display()))?;
+
+    let known: Vec<String> = AzureClient::get_tools_schema()
+        .iter()
+        .filter_map(|tool| tool["function"]["name"].as_str().map(String::from))
+        .collect();
+    let named = recipe.tools.iter().flatten().chain(recipe.steps.iter().filter_map(|s| s.tool.as_ref()));
+    if let Some(unknown) = named.into_iter().find(|t| !known.contains(t)) {
+        anyhow::bail!("Unknown tool '{}' in {}. Tools: {}", unknown, path.display(), known.join(", "));
+    }
+    match (&recipe.prompt, recipe.steps.is_empty()) {
+        (Some(_), false) => anyhow::bail!("{}: a recipe has either a `prompt` or [[step]]s, not both", path.display()),
+        (None, true) => anyhow::bail!("{} has no `prompt` and no [[step]]s", path.display()),
+        _ => {}
+    }
+    for (index, step) in recipe.steps.iter().enumerate() {
+        let kinds = [step.prompt.is_some(), step.tool.is_some(), step.approve.is_some()];
+        if kinds.iter().filter(|k| **k).count() != 1 {
+            anyhow::bail!("{}: step {} needs exactly one of `prompt`, `tool` and `approve`", path.display(), index + 1);
+        }
+    }
+    if recipe.commit.is_some() && recipe.output.is_none() {
+        anyhow::bail!("{}: `commit` needs an `output` file to commit", path.display());
+    }
+    if let Some(dir) = &recipe.dir {
+        let base = path.parent().unwrap_or(Path::new("."));
+        let dir = base.join(paths::resolve(dir));
+        std::env::set_current_dir(&dir).with_context(|| format!("Cannot run in {}", dir.display()))?;
+    }
+
+    let vars = &mut recipe.vars;
+    let now = chrono::Local::now();
+    vars.entry("date".to_string()).or_insert_with(|| now.format("%Y-%m-%d").to_string());
+    vars.entry("time".to_string()).or_insert_with(|| now.format("%H:%M").to_string());
+    let project = crate::memory::project_root();
+    let project = Path::new(&project).file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    vars.entry("project".to_string()).or_insert(project);
+    vars.extend(overrides.iter().cloned());
+
+    // Steps are filled in as they run, when the earlier steps' output is known
+    recipe.prompt = recipe.prompt.map(|p| render(&p, &recipe.vars));
+    recipe.output = recipe.output.map(|o| render(&o, &recipe.vars));
+    recipe.commit = recipe.commit.map(|c| render(&c, &recipe.vars));
+    Ok(recipe)
+}
+
+/// Run the steps of a workflow in order. `yes` answers the approval gates,
+/// which otherwise need a terminal.
+pub async fn run_workflow(config: AppConfig, recipe: &Recipe, output: Option<&str>, yes: bool) -> Result<()> {
+    let tool_executor = ToolExecutor::new(config.tools.clone());
+    let mut run = Headless::new(config, output, recipe.tools.clone())?;
+    let mut vars = recipe.vars.clone();
+    let mut outputs: HashMap<String, StepOutput> = HashMap::new();
+    let total = recipe.steps.len();
+
+    for (index, step) in recipe.steps.iter().enumerate() {
+        let label = step.name.clone().unwrap_or_else(|| format!("step {}", index + 1));
+        if let Some(when) = &step.when {
+            if !when.holds(&outputs)? {
+                eprintln!("[skip {}/{}] {}: the condition on '{}' doesn't hold", index + 1, total, label, when.step);
+                continue;
+            }
+        }
+        eprintln!("[step {}/{}] {}", index + 1, total, label);
+
+        let result = if let Some(question) = &step.approve {
+            let question = render(question, &vars);
+            if !yes && !approve(&question)? {
+                eprintln!("[stopped] not approved: {}", question);
+                break;
+            }
+            StepOutput { text: "approved".to_string(), success: true }
+        } else if let Some(tool) = &step.tool {
+            let input = step.input.as_ref().map(serde_json::to_value).transpose()?.unwrap_or(Value::Object(Default::default()));
+            let call = ToolCall { id: format!("step{}", index + 1), name: tool.clone(), input: render_value(input, &vars) };
+            let result = tool_executor.execute(&call);
+            eprintln!("[tool] {}{}", tool, if result.success { "" } else { " failed" });
+            if !result.success && !step.continue_on_error {
+                anyhow::bail!("Step '{}' failed: {}", label, crate::text::truncate(&result.output, 2_000));
+            }
+            StepOutput { text: result.output, success: result.success }
+        } else {
+            let prompt = render(step.prompt.as_deref().unwrap_or_default(), &vars);
+            let answer = run.ask(&prompt).await?;
+            StepOutput { text: answer.text, success: answer.stop == StopReason::Complete }
+        };
+
+        if let Some(name) = &step.name {
+            vars.insert(format!("steps.{}", name), result.text.clone());
+            outputs.insert(name.clone(), result);
+        }
+    }
+    run.finish()
+}
+
+/// Ask on the terminal; without one there is nobody to approve
+fn approve(question: &str) -> Result<bool> {
+    if !std::io::stdin().is_terminal() {
+        anyhow::bail!("Approval needed ({}), but there is no terminal to ask on; pass --yes to approve", question);
+    }
+    eprint!("? {} [y/N] ", question);
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+/// A tool input with the placeholders in its strings filled in
+fn render_value(value: Value, vars: &HashMap<String, String>) -> Value {
+    match value {
+        Value::String(text) => Value::String(render(&text, vars)),
+        Value::Array(items) => Value::Array(items.into_iter().map(|v| render_value(v, vars)).collect()),
+        Value::Object(map) => Value::Object(map.into_iter().map(|(k, v)| (k, render_value(v, vars))).collect()),
+        other => other,
+    }
+}
+
+/// `{name}` replaced for every variable; other braces are left alone, so
+/// code in the prompt survives
+fn render(template: &str, vars: &HashMap<String, String>) -> String {
+    vars.iter().fold(template.to_string(), |text, (name, value)| text.replace(&format!("{{{}}}", name), value))
+}
+
+/// Commit `output` alone with `message`. Returns the short hash, or None
+/// when the file didn't change.
+pub fn commit(output: &str, message: &str) -> Result<Option<String>> {
+    git(&["add", "--", output])?;
+    if git(&["diff", "--cached", "--quiet", "--", output]).is_ok() {
+        return Ok(None);
+    }
+    git(&["commit", "-q", "-m", message, "--", output])?;
+    Ok(Some(git(&["rev-parse", "--short", "HEAD"])?))
+}
@@ -0,0 +1,141 @@
+use crate::config::RedactionConfig;
+use regex::{Captures, Regex};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{OnceLock, RwLock};
+
+/// A secret pattern. When the regex has a `value` group only that part is
+/// masked, so `API_KEY=...` keeps its name and stays readable.
+struct Rule {
+    name: String,
+    regex: Regex,
+}
+
+const BUILTIN_RULES: &[(&str, &str)] = &[
+    ("private-key", r"-----BEGIN [A-Z ]*PRIVATE KEY-----[\s\S]*?-----END [A-Z ]*PRIVATE KEY-----"),
+    ("aws-access-key", r"\b(?:AKIA|ASIA|A3T[A-Z0-9])[A-Z0-9]{16}\b"),
+    ("github-token", r"\b(?:gh[pousr]_[A-Za-z0-9]{36,}|github_pat_[A-Za-z0-9_]{22,})\b"),
+    ("api-key", r"\bsk-(?:ant-)?[A-Za-z0-9_\-]{20,}"),
+    ("slack-token", r"\bxox[abprs]-[A-Za-z0-9\-]{10,}"),
+    ("google-api-key", r"\bAIza[0-9A-Za-z_\-]{35}\b"),
+    ("jwt", r"\beyJ[A-Za-z0-9_\-]{10,}\.[A-Za-z0-9_\-]{10,}\.[A-Za-z0-9_\-]{10,}"),
+    ("azure-key", r"(?i)(?:AccountKey|SharedAccessKey|SharedAccessSignature)=(?P<value>[^;\s]+)"),
+    ("url-password", r"[a-zA-Z][a-zA-Z0-9+.\-]*://[^\s:/@]+:(?P<value>[^\s@/]+)@"),
+    (
+        "secret",
+        // Only literal-looking values, so code like `password = hash(input)` survives
+        r#"(?im)^\s*(?:export\s+)?[A-Za-z0-9_.\-]*(?:key|secret|token|password|passwd|pwd|credential)[A-Za-z0-9_.\-]*\s*[=:]\s*["']?(?P<value>[A-Za-z0-9_\-+/=.~!@$%^&*]{6,})["']?\s*[,;]?\s*$"#,
+    ),
+    (
+        "secret",
+        r#"(?i)"[A-Za-z0-9_\-]*(?:api_?key|secret|token|password)[A-Za-z0-9_\-]*"\s*:\s*"(?P<value>[^"]{4,})""#,
+    ),
+];
+
+/// Every value in a .env file is treated as a secret
+const ENV_LINE: &str = r#"(?m)^\s*(?:export\s+)?[A-Za-z_][A-Za-z0-9_]*\s*=\s*["']?(?P<value>[^\s"'#][^"'\n]*?)["']?\s*$"#;
+
+struct Redactor {
+    rules: Vec<Rule>,
+    env_line: Regex,
+}
+
+static ENABLED: AtomicBool = AtomicBool::new(true);
+/// Set once `/redact` has decided for the session, which config reloads keep
+static OVERRIDDEN: AtomicBool = AtomicBool::new(false);
+
+fn redactor() -> &'static RwLock<Redactor> {
+    static REDACTOR: OnceLock<RwLock<Redactor>> = OnceLock::new();
+    REDACTOR.get_or_init(|| RwLock::new(Redactor::new(&[])))
+}
+
+impl Redactor {
+    fn new(extra: &[String]) -> Self {
+        let mut rules: Vec<Rule> = BUILTIN_RULES
+            .iter()
+            .map(|(name, pattern)| Rule {
+                name: name.to_string(),
+                regex: Regex::new(pattern).expect("built-in redaction pattern"),
+            })
+            .collect();
+
+        for pattern in extra {
+            match Regex::new(pattern) {
+                Ok(regex) => rules.push(Rule { name: "custom".to_string(), regex }),
+                Err(e) => eprintln!("Ignoring invalid redaction pattern '{}': {}", pattern, e),
+            }
+        }
+
+        Self {
+            rules,
+            env_line: Regex::new(ENV_LINE).unwrap(),
+        }
+    }
+}
+
+/// Load patterns and the on/off switch from config, at startup and on every
+/// reload; a `/redact` override stays. The organization policy can force
+/// redaction on and add patterns.
+pub fn configure(config: &RedactionConfig) {
+    let enabled = if OVERRIDDEN.load(Ordering::Relaxed) { is_enabled() } else { config.enabled };
+    ENABLED.store(enabled || crate::policy::redaction_enforced(), Ordering::Relaxed);
+    let patterns = [config.patterns.as_slice(), crate::policy::redaction_patterns()].concat();
+    *redactor().write().unwrap() = Redactor::new(&patterns);
+}
+
+/// Session override (`/redact on|off`)
+pub fn set_enabled(enabled: bool) {
+    OVERRIDDEN.store(true, Ordering::Relaxed);
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+fn mask(regex: &Regex, text: &str, name: &str) -> String {
+    regex
+        .replace_all(text, |caps: &Captures| {
+            let whole = caps.get(0).unwrap();
+            match caps.name("value") {
+                Some(value) => {
+                    let start = value.start() - whole.start();
+                    let end = value.end() - whole.start();
+                    let matched = whole.as_str();
+                    format!("{}[REDACTED:{}]{}", &matched[..start], name, &matched[end..])
+                }
+                None => format!("[REDACTED:{}]", name),
+            }
+        })
+        .into_owned()
+}
+
+/// Mask secrets in text that is about to be shown or sent to the model
+pub fn redact(text: &str) -> String {
+    if !is_enabled() {
+        return text.to_string();
+    }
+    let redactor = redactor().read().unwrap();
+    redactor
+        .rules
+        .iter()
+        .fold(text.to_string(), |text, rule| mask(&rule.regex, &text, &rule.name))
+}
+
+/// Like `redact`, plus every value when the file is a .env file
+pub fn redact_file(path: &str, content: &str) -> String {
+    if !is_enabled() {
+        return content.to_string();
+    }
+    let is_env_file = Path::new(path)
+        .file_name()
+        .map(|n| n.to_string_lossy())
+        .is_some_and(|n| n == ".env" || n.starts_with(".env.") || n.ends_with(".env"));
+    let content = if is_env_file {
+        let redactor = redactor().read().unwrap();
+        mask(&redactor.env_line, content, "env")
+    } else {
+        content.to_string()
+    };
+    redact(&content)
+}
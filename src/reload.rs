@@ -0,0 +1,133 @@
+//! Live reload of config.toml during a chat. The file's modification time is
+//! checked each time a prompt is entered; changes that are safe mid-session
+//! are applied, the rest are reported as needing a restart.
+
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use serde::Serialize;
+
+use crate::config::{self, AppConfig};
+
+pub struct ConfigWatcher {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+}
+
+/// What a reload changed
+#[derive(Debug, Default)]
+pub struct Changes {
+    /// Applied, one short description each ("models +gpt-5 ~gpt-4o", "ui")
+    pub applied: Vec<String>,
+    /// Sections that only take effect after a restart
+    pub restart: Vec<String>,
+    /// The active model's settings changed; the client needs the new ones
+    pub active_model: bool,
+    pub ui: bool,
+    pub language: bool,
+}
+
+impl Changes {
+    pub fn is_empty(&self) -> bool {
+        self.applied.is_empty() && self.restart.is_empty()
+    }
+}
+
+impl ConfigWatcher {
+    pub fn new() -> Self {
+        let path = config::get_config_path();
+        let modified = modified(&path);
+        Self { path, modified }
+    }
+
+    /// The reloaded config when the file changed since the last call. A file
+    /// that no longer parses yields the error once and is retried after its
+    /// next change.
+    pub fn poll(&mut self) -> Option<anyhow::Result<AppConfig>> {
+        let now = modified(&self.path);
+        if now.is_none() || now == self.modified {
+            return None;
+        }
+        self.modified = now;
+        Some(config::load_config())
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// Same serialized form; the config types don't implement PartialEq
+fn same<T: Serialize>(a: &T, b: &T) -> bool {
+    toml::Value::try_from(a).ok() == toml::Value::try_from(b).ok()
+}
+
+/// Move the live-applicable parts of `new` into `current`. The active model
+/// stays the one in use: a different `active_model` in the file is left for
+/// /model or the next start, and a model removed from the file is kept while
+/// it is active.
+pub fn apply(current: &mut AppConfig, new: AppConfig) -> Changes {
+    let mut changes = Changes::default();
+
+    let mut models = Vec::new();
+    for (name, model) in &new.models {
+        match current.models.get(name) {
+            None => models.push(format!("+{}", name)),
+            Some(old) if !same(old, model) => {
+                models.push(format!("~{}", name));
+                changes.active_model |= *name == current.active_model;
+            }
+            Some(_) => {}
+        }
+    }
+    for name in current.models.keys().filter(|n| !new.models.contains_key(*n) && **n != current.active_model) {
+        models.push(format!("-{}", name));
+    }
+    if !models.is_empty() {
+        models.sort();
+        changes.applied.push(format!("models {}", models.join(" ")));
+        let active = current.models.remove(&current.active_model);
+        current.models = new.models.clone();
+        if let Some(active) = active.filter(|a| !current.models.contains_key(&a.name)) {
+            current.models.insert(active.name.clone(), active);
+        }
+    }
+
+    if current.language != new.language {
+        current.language = new.language;
+        changes.language = true;
+        changes.applied.push(format!("language {}", new.language.code()));
+    }
+    if !same(&current.ui, &new.ui) {
+        current.ui = new.ui.clone();
+        changes.ui = true;
+        changes.applied.push("ui".to_string());
+    }
+    if !same(&current.redaction, &new.redaction) {
+        current.redaction = new.redaction.clone();
+        crate::redact::configure(&current.redaction);
+        changes.applied.push("redaction".to_string());
+    }
+
+    if !same(&current.metrics, &new.metrics) {
+        current.metrics = new.metrics.clone();
+        crate::metrics::configure(&current.metrics);
+        changes.applied.push("metrics".to_string());
+    }
+
+    macro_rules! live {
+        ($($field:ident),*) => {$(
+            if !same(&current.$field, &new.$field) {
+                current.$field = new.$field.clone();
+                changes.applied.push(stringify!($field).to_string());
+            }
+        )*};
+    }
+    live!(router, cache, agent, sessions, speech, images, content_safety, snippets, github_username);
+
+    // The tool executor and its guard state are built once per session
+    if !same(&current.tools, &new.tools) {
+        changes.restart.push("tools".to_string());
+    }
+    changes
+}
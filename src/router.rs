@@ -0,0 +1,88 @@
+use crate::client::AzureClient;
+use crate::config::{AppConfig, ModelConfig, RouterStrategy};
+
+/// Which tier of model a turn should go to
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Route {
+    Fast,
+    Smart,
+}
+
+impl std::fmt::Display for Route {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Route::Fast => write!(f, "fast"),
+            Route::Smart => write!(f, "smart"),
+        }
+    }
+}
+
+// Prompts longer than this almost always carry code or a detailed task
+const LONG_PROMPT_CHARS: usize = 400;
+
+const SMART_KEYWORDS: &[&str] = &[
+    "implement", "refactor", "debug", "fix", "bug", "error", "architecture", "design",
+    "optimiz", "optimis", "migrate", "review", "test", "analy", "explain why", "step by step",
+    "create", "build", "write", "generate", "project", "function", "class", "deploy",
+    // Portuguese
+    "implemente", "refatore", "corrija", "erro", "crie", "escreva", "gere", "projeto",
+    "analise", "otimiz", "função", "teste",
+];
+
+const CLASSIFIER_PROMPT: &str = "You route requests for a coding assistant. \
+Reply with exactly one word: FAST if the request is a short question, chit-chat, translation \
+or simple lookup; SMART if it needs coding, multi-step work, tool use or careful reasoning.";
+
+/// Classify a prompt using cheap local heuristics
+pub fn classify_heuristic(prompt: &str, has_files: bool) -> Route {
+    if has_files || prompt.contains("```") || prompt.len() > LONG_PROMPT_CHARS {
+        return Route::Smart;
+    }
+
+    let lower = prompt.to_lowercase();
+    if SMART_KEYWORDS.iter().any(|kw| lower.contains(kw)) {
+        Route::Smart
+    } else {
+        Route::Fast
+    }
+}
+
+/// Ask the fast model to classify the prompt, falling back to the heuristic on failure
+async fn classify_with_model(fast: &ModelConfig, prompt: &str, has_files: bool) -> Route {
+    if has_files {
+        return Route::Smart;
+    }
+
+    let client = AzureClient::new(fast.clone());
+    match client.complete(CLASSIFIER_PROMPT, prompt).await {
+        Ok(answer) if answer.to_uppercase().contains("SMART") => Route::Smart,
+        Ok(answer) if answer.to_uppercase().contains("FAST") => Route::Fast,
+        _ => classify_heuristic(prompt, has_files),
+    }
+}
+
+/// Pick the model for this turn. Returns None when routing is disabled or
+/// the configured fast/smart models don't exist.
+pub async fn pick_model<'a>(
+    config: &'a AppConfig,
+    prompt: &str,
+    has_files: bool,
+) -> Option<(Route, &'a ModelConfig)> {
+    let router = &config.router;
+    if !router.enabled {
+        return None;
+    }
+
+    let fast = config.models.get(&router.fast_model)?;
+    let smart = config.models.get(&router.smart_model)?;
+
+    let route = match router.strategy {
+        RouterStrategy::Heuristic => classify_heuristic(prompt, has_files),
+        RouterStrategy::Model => classify_with_model(fast, prompt, has_files).await,
+    };
+
+    match route {
+        Route::Fast => Some((route, fast)),
+        Route::Smart => Some((route, smart)),
+    }
+}
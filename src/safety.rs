@@ -0,0 +1,104 @@
+use anyhow::{Context, Result};
+use serde_json::{json, Value};
+
+use crate::client::ApiError;
+use crate::config::ContentSafetyConfig;
+use crate::text;
+
+const API_VERSION: &str = "2023-10-01";
+/// text:analyze takes at most 10k characters per call
+const MAX_ANALYZE_CHARS: usize = 10000;
+
+/// A harm category that was flagged, with how severe it was
+#[derive(Debug, Clone)]
+pub struct Flag {
+    pub category: String,
+    /// "high", "medium", "low" from the filter annotations; 0-6 from Content Safety
+    pub severity: String,
+}
+
+/// "hate (high), violence (medium)"
+pub fn describe(flags: &[Flag]) -> String {
+    flags
+        .iter()
+        .map(|f| format!("{} ({})", f.category, f.severity))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// The categories Azure's built-in content filter blocked, when this error
+/// is a content-filter rejection (HTTP 400 with code "content_filter")
+pub fn filtered_categories(error: &anyhow::Error) -> Option<Vec<Flag>> {
+    let api = error.downcast_ref::<ApiError>()?;
+    let body: Value = serde_json::from_str(&api.body).ok()?;
+    let error = &body["error"];
+    let code = error["code"].as_str().unwrap_or_default();
+    let inner = error["innererror"]["code"].as_str().unwrap_or_default();
+    if code != "content_filter" && inner != "ResponsibleAIPolicyViolation" {
+        return None;
+    }
+
+    let results = error["innererror"]["content_filter_result"].as_object();
+    let flags = results
+        .into_iter()
+        .flatten()
+        .filter(|(_, result)| result["filtered"].as_bool() == Some(true))
+        .map(|(category, result)| Flag {
+            category: category.replace('_', " "),
+            // Jailbreak and protected material are detected, not graded
+            severity: result["severity"].as_str().unwrap_or("detected").to_string(),
+        })
+        .collect();
+    Some(flags)
+}
+
+/// Content-filter rejections turned into an error naming the blocked
+/// categories; other errors are returned unchanged
+pub fn explain(error: anyhow::Error) -> anyhow::Error {
+    match filtered_categories(&error) {
+        Some(flags) if flags.is_empty() => anyhow::anyhow!("Blocked by Azure's content filter"),
+        Some(flags) => anyhow::anyhow!("Blocked by Azure's content filter: {}", describe(&flags)),
+        None => error,
+    }
+}
+
+/// Categories of `text` at or above the configured severity, via the
+/// Azure AI Content Safety text:analyze API
+pub async fn analyze(config: &ContentSafetyConfig, text: &str) -> Result<Vec<Flag>> {
+    let endpoint = config
+        .endpoint()
+        .ok_or_else(|| anyhow::anyhow!("Set [content_safety] endpoint, or CONTENT_SAFETY_ENDPOINT"))?;
+    let key = config
+        .key()
+        .ok_or_else(|| anyhow::anyhow!("Set [content_safety] key, or CONTENT_SAFETY_KEY"))?;
+    let url = format!("{}/contentsafety/text:analyze?api-version={}", endpoint.trim_end_matches('/'), API_VERSION);
+    crate::policy::check_endpoint(&url)?;
+
+    let response = reqwest::Client::new()
+        .post(&url)
+        .header("Ocp-Apim-Subscription-Key", key)
+        .json(&json!({ "text": text::truncate(text, MAX_ANALYZE_CHARS), "outputType": "FourSeverityLevels" }))
+        .send()
+        .await
+        .context("Could not reach Content Safety")?;
+    let status = response.status();
+    let body: Value = response.json().await.unwrap_or_default();
+    if !status.is_success() {
+        let message = body["error"]["message"].as_str().unwrap_or("no details");
+        anyhow::bail!("Content Safety returned {}: {}", status, message);
+    }
+
+    let flags = body["categoriesAnalysis"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|analysis| {
+            let severity = analysis["severity"].as_u64()?;
+            (severity >= config.threshold as u64).then(|| Flag {
+                category: analysis["category"].as_str().unwrap_or_default().to_lowercase().replace("selfharm", "self harm"),
+                severity: severity.to_string(),
+            })
+        })
+        .collect();
+    Ok(flags)
+}
@@ -0,0 +1,383 @@
+use crate::client::{AzureClient, Message};
+use crate::config::ModelConfig;
+use crate::console::println;
+use crate::snapshot::FileChange;
+use crate::text;
+use crate::ui::UI;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+const TITLE_PROMPT: &str = "Write a title of at most six words for the conversation below. \
+Use the conversation's language. Reply with the title only, without quotes or punctuation at the end.";
+const TITLE_MAX_CHARS: usize = 60;
+/// Characters of context shown on each side of a search match
+const EXCERPT_CONTEXT: usize = 60;
+const EXCERPTS_PER_SESSION: usize = 3;
+
+/// Who, where and when of a saved conversation
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SessionInfo {
+    pub id: String,
+    pub created_at: i64,
+    pub updated_at: i64,
+    pub cwd: String,
+    pub model: String,
+    /// Set with /title or generated after the first exchange
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// Files each agent turn changed, for transcripts and /export
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub changes: Vec<TurnChanges>,
+}
+
+/// The files changed by the turn that ended at message `after`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TurnChanges {
+    pub after: usize,
+    pub files: Vec<FileChange>,
+}
+
+/// A conversation as stored in ~/.aicli/sessions/<id>.json
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Session {
+    #[serde(flatten)]
+    pub info: SessionInfo,
+    pub messages: Vec<Message>,
+}
+
+impl SessionInfo {
+    /// A new session; the id is its start time, so ids sort chronologically
+    pub fn new(model: &str) -> Self {
+        let now = chrono::Local::now();
+        let cwd = std::env::current_dir()
+            .map(|p| p.display().to_string())
+            .unwrap_or_default();
+        Self {
+            id: now.format("%Y%m%d-%H%M%S").to_string(),
+            created_at: now.timestamp(),
+            updated_at: now.timestamp(),
+            cwd,
+            model: model.to_string(),
+            title: None,
+            changes: Vec::new(),
+        }
+    }
+}
+
+impl Session {
+    /// Changes that go after message `index`. /compact shortens the
+    /// conversation, so turns past its end are shown after the last message.
+    fn changes_after(&self, index: usize) -> impl Iterator<Item = &FileChange> {
+        let last = self.messages.len();
+        self.info
+            .changes
+            .iter()
+            .filter(move |c| c.after.clamp(1, last) == index + 1)
+            .flat_map(|c| &c.files)
+    }
+
+    /// First user prompt, on one line
+    pub fn preview(&self) -> String {
+        self.messages
+            .iter()
+            .find(|m| m.role == "user")
+            .map(|m| {
+                let text = m.content.as_text();
+                // Drop the inlined @file context
+                let prompt = text.split("\n\nFile context:").next().unwrap_or_default().to_string();
+                prompt.split_whitespace().collect::<Vec<_>>().join(" ")
+            })
+            .unwrap_or_default()
+    }
+
+    /// The title, or the first prompt for untitled sessions
+    pub fn display_title(&self) -> String {
+        self.info.title.clone().unwrap_or_else(|| self.preview())
+    }
+}
+
+/// A short title for the conversation from its first prompt and answer.
+/// Falls back to the start of the prompt when the model call fails.
+pub async fn generate_title(model: &ModelConfig, messages: &[Message]) -> String {
+    let session = Session { info: SessionInfo::default(), messages: messages.to_vec() };
+    let prompt = session.preview();
+    let answer = messages
+        .iter()
+        .filter(|m| m.role == "assistant")
+        .map(|m| m.content.as_text())
+        .find(|answer| !answer.is_empty())
+        .unwrap_or_default();
+    let exchange = format!("User: {}\n\nAssistant: {}", text::truncate(&prompt, 1000), text::truncate(&answer, 1000));
+
+    let generated = AzureClient::new(model.clone()).complete(TITLE_PROMPT, &exchange).await.ok();
+    let title = generated
+        .map(|t| t.lines().next().unwrap_or_default().trim().trim_matches(['"', '\'', '*', '#']).trim().to_string())
+        .filter(|t| !t.is_empty())
+        .unwrap_or(prompt);
+    text::truncate(&title, TITLE_MAX_CHARS)
+}
+
+pub fn get_sessions_dir() -> PathBuf {
+    let home = dirs::home_dir().unwrap_or_else(|| PathBuf::from("."));
+    home.join(".aicli").join("sessions")
+}
+
+fn session_path(id: &str) -> PathBuf {
+    get_sessions_dir().join(format!("{}.json", id))
+}
+
+/// Write the conversation under `info.id`, bumping its update time.
+/// Empty conversations are not saved.
+pub fn save(info: &mut SessionInfo, messages: &[Message]) -> Result<()> {
+    if messages.is_empty() {
+        return Ok(());
+    }
+    info.updated_at = chrono::Utc::now().timestamp();
+    write(&Session { info: info.clone(), messages: messages.to_vec() })
+}
+
+/// Write a session as it is, timestamps included (`aicli import`)
+pub fn write(session: &Session) -> Result<()> {
+    fs::create_dir_all(get_sessions_dir())?;
+    fs::write(session_path(&session.info.id), serde_json::to_string(session)?)?;
+    Ok(())
+}
+
+pub fn exists(id: &str) -> bool {
+    session_path(id).exists()
+}
+
+/// Every saved session, most recently updated first. Unreadable files are skipped.
+pub fn list() -> Vec<Session> {
+    let Ok(entries) = fs::read_dir(get_sessions_dir()) else {
+        return Vec::new();
+    };
+    let mut sessions: Vec<Session> = entries
+        .flatten()
+        .filter(|e| e.path().extension().is_some_and(|ext| ext == "json"))
+        .filter_map(|e| serde_json::from_str(&fs::read_to_string(e.path()).ok()?).ok())
+        .collect();
+    sessions.sort_by_key(|s| std::cmp::Reverse(s.info.updated_at));
+    sessions
+}
+
+/// Full id for `id` or a unique prefix of one; "last" is the most recent session
+pub fn resolve(id: &str) -> Result<String> {
+    let ids: Vec<String> = list().into_iter().map(|s| s.info.id).collect();
+    if id == "last" {
+        return ids.into_iter().next().ok_or_else(|| anyhow::anyhow!("No saved sessions"));
+    }
+    if ids.iter().any(|i| i == id) {
+        return Ok(id.to_string());
+    }
+    let matches: Vec<&String> = ids.iter().filter(|i| i.starts_with(id)).collect();
+    match matches.as_slice() {
+        [one] => Ok(one.to_string()),
+        [] => anyhow::bail!("No session matches '{}'. See 'aicli sessions list'", id),
+        many => anyhow::bail!(
+            "'{}' matches {} sessions: {}",
+            id,
+            many.len(),
+            many.iter().map(|s| s.as_str()).collect::<Vec<_>>().join(", ")
+        ),
+    }
+}
+
+pub fn load(id: &str) -> Result<Session> {
+    let id = resolve(id)?;
+    let path = session_path(&id);
+    let content = fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    serde_json::from_str(&content).with_context(|| format!("{} is not a valid session file", path.display()))
+}
+
+/// Delete a session, returning its full id
+pub fn delete(id: &str) -> Result<String> {
+    let id = resolve(id)?;
+    fs::remove_file(session_path(&id))?;
+    Ok(id)
+}
+
+/// A saved session containing the search query
+pub struct SearchHit {
+    pub session: Session,
+    /// One-line snippets around the first matches, match highlighted
+    pub excerpts: Vec<String>,
+}
+
+/// Case-insensitive search of saved prompts, answers and titles, most
+/// recent session first. Words of the query may be split by any whitespace
+/// (line breaks included). Tool output and inlined @file context are not
+/// searched.
+pub fn search(query: &str) -> Result<Vec<SearchHit>> {
+    let words: Vec<String> = query.split_whitespace().map(regex::escape).collect();
+    if words.is_empty() {
+        anyhow::bail!("Nothing to search for");
+    }
+    let pattern = regex::Regex::new(&format!("(?i){}", words.join(r"\s+")))?;
+
+    let mut hits = Vec::new();
+    for session in list() {
+        let mut excerpts = Vec::new();
+        if let Some(title) = session.info.title.as_deref().filter(|t| pattern.is_match(t)) {
+            excerpts.push(highlight(title, &pattern));
+        }
+        for message in &session.messages {
+            let text = message.content.as_text();
+            if text.starts_with("Tool execution results:") {
+                continue;
+            }
+            let text = text.split("\n\nFile context:").next().unwrap_or_default();
+            for found in pattern.find_iter(text) {
+                if excerpts.len() >= EXCERPTS_PER_SESSION {
+                    break;
+                }
+                excerpts.push(excerpt(text, found.start(), found.end(), &pattern));
+            }
+        }
+        if !excerpts.is_empty() {
+            hits.push(SearchHit { session, excerpts });
+        }
+    }
+    Ok(hits)
+}
+
+/// The match with up to EXCERPT_CONTEXT characters around it, on one line
+fn excerpt(text: &str, start: usize, end: usize, pattern: &regex::Regex) -> String {
+    let from = text[..start]
+        .char_indices()
+        .rev()
+        .nth(EXCERPT_CONTEXT - 1)
+        .map(|(i, _)| i)
+        .unwrap_or(0);
+    let to = text[end..]
+        .char_indices()
+        .nth(EXCERPT_CONTEXT)
+        .map(|(i, _)| end + i)
+        .unwrap_or(text.len());
+
+    let snippet = text[from..to].split_whitespace().collect::<Vec<_>>().join(" ");
+    format!(
+        "{}{}{}",
+        if from > 0 { "…" } else { "" },
+        highlight(&snippet, pattern),
+        if to < text.len() { "…" } else { "" }
+    )
+}
+
+fn highlight(text: &str, pattern: &regex::Regex) -> String {
+    pattern.replace_all(text, "\x1b[1;38;5;228m$0\x1b[0m").to_string()
+}
+
+/// `aicli search` and `/search`
+pub fn print_search(query: &str) -> Result<()> {
+    let hits = search(query)?;
+    if hits.is_empty() {
+        println!("No saved session mentions \"{}\"", query);
+        return Ok(());
+    }
+    for hit in &hits {
+        println!(
+            "\x1b[38;5;141m{}\x1b[0m  {}  \x1b[1m{}\x1b[0m",
+            hit.session.info.id,
+            format_time(hit.session.info.updated_at),
+            text::truncate(&hit.session.display_title(), 60)
+        );
+        for excerpt in &hit.excerpts {
+            println!("    {}", excerpt);
+        }
+    }
+    println!(
+        "\n\x1b[90m{} session(s) · resume with: aicli sessions resume <id>\x1b[0m",
+        hits.len()
+    );
+    Ok(())
+}
+
+pub fn format_time(timestamp: i64) -> String {
+    chrono::DateTime::from_timestamp(timestamp, 0)
+        .map(|t| t.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M").to_string())
+        .unwrap_or_default()
+}
+
+/// `aicli sessions list`
+pub fn print_list() {
+    let sessions = list();
+    if sessions.is_empty() {
+        println!("No saved sessions yet. Conversations are saved to {}", get_sessions_dir().display());
+        return;
+    }
+    for session in &sessions {
+        println!(
+            "\x1b[38;5;141m{}\x1b[0m  {}  \x1b[90m{:>3} msgs · {}\x1b[0m",
+            session.info.id,
+            format_time(session.info.updated_at),
+            session.messages.len(),
+            session.info.model
+        );
+        println!("    {}", text::truncate(&session.display_title(), 90));
+        println!("    \x1b[90m{}\x1b[0m", session.info.cwd);
+    }
+}
+
+/// `aicli sessions show <id>`: the transcript, with answers rendered like in chat
+pub fn print_transcript(session: &Session, ui: &UI) {
+    println!("\n\x1b[1m{}\x1b[0m", session.display_title());
+    println!(
+        "\x1b[38;5;141m{}\x1b[0m · {} · {} · {}\n",
+        session.info.id,
+        format_time(session.info.created_at),
+        session.info.model,
+        session.info.cwd
+    );
+    for (index, message) in session.messages.iter().enumerate() {
+        let content = message.content.as_text();
+        if message.role == "assistant" && content.is_empty() {
+            println!("\x1b[90m  ⚙ {}\x1b[0m", message.content.tool_names().join(", "));
+        } else if message.role == "assistant" {
+            ui.reset_code_state();
+            ui.print_assistant_prefix();
+            ui.print_token(&content);
+            ui.print_newline();
+        } else if content.starts_with("Tool execution results:") {
+            println!("\x1b[90m  ⚙ {}\x1b[0m", text::truncate(&content.replace('\n', " "), 100));
+        } else {
+            println!("\x1b[32m❯\x1b[0m {}", content);
+        }
+        for change in session.changes_after(index) {
+            println!("\x1b[90m  ✎ {}\x1b[0m", change.describe());
+        }
+        println!();
+    }
+}
+
+/// The conversation as Markdown, for /export
+pub fn to_markdown(session: &Session) -> String {
+    let mut markdown = format!(
+        "# {}\n\n{} · {} · {} · `{}`\n",
+        session.display_title(),
+        session.info.id,
+        format_time(session.info.created_at),
+        session.info.model,
+        session.info.cwd
+    );
+    for (index, message) in session.messages.iter().enumerate() {
+        let content = message.content.as_text();
+        if message.role == "assistant" && content.is_empty() {
+            markdown.push_str(&format!("\n> ⚙ {}\n", message.content.tool_names().join(", ")));
+        } else if message.role == "assistant" {
+            markdown.push_str(&format!("\n## Assistant\n\n{}\n", content.trim()));
+        } else if content.starts_with("Tool execution results:") {
+            markdown.push_str(&format!("\n> ⚙ {}\n", text::truncate(&content.replace('\n', " "), 200)));
+        } else {
+            markdown.push_str(&format!("\n## User\n\n{}\n", content.trim()));
+        }
+        let changes: Vec<String> = session.changes_after(index).map(|c| format!("- `{}`", c.describe())).collect();
+        if !changes.is_empty() {
+            markdown.push_str(&format!("\n**Changed files**\n\n{}\n", changes.join("\n")));
+        }
+    }
+    markdown
+}
@@ -0,0 +1,84 @@
+//! Shell integration: `aicli shell-hook SHELL` prints a script that records
+//! each command and its exit status and binds Ctrl+G to `aicli fix`, which
+//! explains the last command on stderr and prints a corrected one on stdout
+//! for the script to put on the command line.
+
+use anyhow::Result;
+use std::io::{IsTerminal, Write};
+
+use crate::cli::Shell;
+use crate::client::AzureClient;
+use crate::codeblocks;
+use crate::config::AppConfig;
+use crate::safety;
+
+const FIX_PROMPT: &str = "You help with a shell command the user just ran or is about to run. In one or two short \
+sentences, explain what it does or, when it failed, the most likely reason. When it can be fixed or is clearly \
+meant differently, end with the corrected command alone in one ```sh code block; otherwise add no code block. \
+The command is for the shell and OS given, run in the directory given.";
+/// Directory entries listed for the model, so it can spot a mistyped path
+const LISTED_ENTRIES: usize = 40;
+
+pub fn script(shell: Shell) -> &'static str {
+    match shell {
+        Shell::Bash => include_str!("../shell/aicli.bash"),
+        Shell::Zsh => include_str!("../shell/aicli.zsh"),
+        Shell::Fish => include_str!("../shell/aicli.fish"),
+        Shell::Powershell => include_str!("../shell/aicli.ps1"),
+    }
+}
+
+/// `aicli fix [--status N] [--shell S] -- COMMAND`
+pub async fn fix(config: &AppConfig, command: &str, status: Option<i32>, shell: Option<&str>) -> Result<()> {
+    let model = config
+        .models
+        .get(&config.router.fast_model)
+        .or(config.get_active_model())
+        .ok_or_else(|| anyhow::anyhow!("No active model configured"))?;
+    let cwd = std::env::current_dir().unwrap_or_default();
+    let mut entries: Vec<String> = std::fs::read_dir(&cwd)
+        .map(|dir| dir.flatten().map(|e| e.file_name().to_string_lossy().to_string()).collect())
+        .unwrap_or_default();
+    entries.sort();
+    let more = entries.len().saturating_sub(LISTED_ENTRIES);
+    entries.truncate(LISTED_ENTRIES);
+
+    let shell = shell.map(String::from).unwrap_or_else(|| {
+        std::env::var("SHELL").ok().and_then(|s| s.rsplit('/').next().map(String::from)).unwrap_or_default()
+    });
+    let mut prompt = format!("Shell: {}\nOS: {}\nDirectory: {}\n", shell, std::env::consts::OS, cwd.display());
+    prompt.push_str(&format!("Files there: {}{}\n", entries.join(", "), if more > 0 { format!(" (+{} more)", more) } else { String::new() }));
+    match status {
+        Some(0) => prompt.push_str("It exited with status 0\n"),
+        Some(status) => prompt.push_str(&format!("It failed with exit status {}\n", status)),
+        None => prompt.push_str("It hasn't been run yet\n"),
+    }
+    prompt.push_str(&format!("Command:\n```sh\n{}\n```", command));
+
+    let interactive = std::io::stderr().is_terminal();
+    if interactive {
+        eprint!("\x1b[38;5;103m  aicli · asking {}...\x1b[0m", model.name);
+    }
+    let answer = AzureClient::new(model.clone()).complete(FIX_PROMPT, &prompt).await.map_err(safety::explain);
+    if interactive {
+        eprint!("\r\x1b[2K");
+    }
+    let answer = answer?;
+
+    let blocks = codeblocks::extract(&answer);
+    let fixed = blocks.last().map(|b| b.code.trim().to_string()).filter(|code| !code.is_empty() && code != command.trim());
+    let explanation = answer.split("```").next().unwrap_or_default().trim();
+    if interactive {
+        eprintln!("\x1b[38;5;117m  ✦\x1b[0m {}", explanation);
+        if fixed.is_some() {
+            eprintln!("\x1b[38;5;245m    the fix is on the command line · Enter runs it\x1b[0m");
+        }
+    } else {
+        eprintln!("{}", explanation);
+    }
+    if let Some(fixed) = fixed {
+        println!("{}", fixed);
+        std::io::stdout().flush()?;
+    }
+    Ok(())
+}
@@ -0,0 +1,236 @@
+//! Per-turn backups of the files the agent writes, so a turn can be undone
+//! where there is no git. The first write_file/edit_file of a file in a turn
+//! copies it to <workspace>/.aicli/backups/<turn>/ (or records that it did
+//! not exist); `/revert` puts the files back.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Oldest turns are deleted beyond this many
+const MAX_TURNS: usize = 50;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Turn {
+    pub id: String,
+    pub created_at: i64,
+    /// The prompt that started the turn, shortened
+    #[serde(default)]
+    pub prompt: String,
+    pub files: Vec<BackedUpFile>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackedUpFile {
+    pub path: PathBuf,
+    /// False when the agent created the file; reverting deletes it
+    pub existed: bool,
+}
+
+/// The turn in progress and its prompt; the turn is written on its first backup
+static CURRENT: Mutex<Option<(String, Option<Turn>)>> = Mutex::new(None);
+
+pub fn get_backups_dir() -> PathBuf {
+    Path::new(&crate::memory::project_root()).join(".aicli").join("backups")
+}
+
+/// Start a new turn; files written from now on are backed up under it
+pub fn begin_turn(prompt: &str) {
+    *CURRENT.lock().unwrap() = Some((crate::text::truncate(prompt.trim(), 80), None));
+}
+
+/// Back up `path` before the agent writes it, once per turn. A failed
+/// backup is an error so the write doesn't go ahead unprotected.
+pub fn before_write(path: &Path) -> Result<()> {
+    let path = std::path::absolute(path)?;
+    let mut current = CURRENT.lock().unwrap();
+    let (prompt, turn) = current.get_or_insert_with(|| (String::new(), None));
+    if turn.as_ref().is_some_and(|t| t.files.iter().any(|f| f.path == path)) {
+        return Ok(());
+    }
+    let turn = match turn {
+        Some(turn) => turn,
+        None => turn.insert(new_turn(prompt)?),
+    };
+
+    let dir = get_backups_dir().join(&turn.id);
+    let existed = path.is_file();
+    if existed {
+        let copy = dir.join("files").join(turn.files.len().to_string());
+        fs::create_dir_all(dir.join("files"))?;
+        fs::copy(&path, &copy).with_context(|| format!("Failed to back up {}", path.display()))?;
+    }
+    turn.files.push(BackedUpFile { path, existed });
+    save(turn)
+}
+
+fn new_turn(prompt: &str) -> Result<Turn> {
+    let root = get_backups_dir();
+    fs::create_dir_all(&root)?;
+    // Keeps the backups out of `git status` when there is a repository after all
+    let ignore = root.join(".gitignore");
+    if !ignore.exists() {
+        fs::write(&ignore, "*\n")?;
+    }
+
+    let base = chrono::Local::now().format("%Y%m%d-%H%M%S").to_string();
+    let id = (1..)
+        .map(|n| if n == 1 { base.clone() } else { format!("{}-{}", base, n) })
+        .find(|id| !root.join(id).exists())
+        .unwrap_or(base);
+    prune(&root);
+    Ok(Turn { id, created_at: chrono::Utc::now().timestamp(), prompt: prompt.to_string(), files: Vec::new() })
+}
+
+fn save(turn: &Turn) -> Result<()> {
+    let dir = get_backups_dir().join(&turn.id);
+    fs::create_dir_all(&dir)?;
+    fs::write(dir.join("manifest.json"), serde_json::to_string_pretty(turn)?)?;
+    Ok(())
+}
+
+/// Delete the oldest turns so MAX_TURNS - 1 remain before a new one
+fn prune(root: &Path) {
+    let turns = list();
+    if turns.len() >= MAX_TURNS {
+        for turn in &turns[..turns.len() + 1 - MAX_TURNS] {
+            let _ = fs::remove_dir_all(root.join(&turn.id));
+        }
+    }
+}
+
+/// The turn in progress, when it has written files
+pub fn current_turn() -> Option<Turn> {
+    CURRENT.lock().unwrap().as_ref().and_then(|(_, turn)| turn.clone())
+}
+
+/// What a turn did to one file, for the summary after the turn
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileChange {
+    pub path: String,
+    pub status: ChangeStatus,
+    pub added: usize,
+    pub removed: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeStatus {
+    Created,
+    Modified,
+    Deleted,
+}
+
+impl FileChange {
+    /// "src/main.rs +3 -1", "notes.md (created) +12"
+    pub fn describe(&self) -> String {
+        let mut line = self.path.clone();
+        match self.status {
+            ChangeStatus::Created => line.push_str(" (created)"),
+            ChangeStatus::Deleted => line.push_str(" (deleted)"),
+            ChangeStatus::Modified => {}
+        }
+        if self.added > 0 {
+            line.push_str(&format!(" +{}", self.added));
+        }
+        if self.removed > 0 {
+            line.push_str(&format!(" -{}", self.removed));
+        }
+        line
+    }
+}
+
+/// The files `turn` changed, compared with their backups. Files written
+/// back to what they were are left out.
+pub fn changes(turn: &Turn) -> Vec<FileChange> {
+    let dir = get_backups_dir().join(&turn.id);
+    let read = |path: &Path| fs::read(path).map(|bytes| String::from_utf8_lossy(&bytes).into_owned());
+    let cwd = std::env::current_dir().unwrap_or_default();
+    let mut changes = Vec::new();
+    for (index, file) in turn.files.iter().enumerate() {
+        let before = if file.existed { read(&dir.join("files").join(index.to_string())).ok() } else { None };
+        let after = read(&file.path).ok();
+        let status = match (&before, &after) {
+            (None, None) => continue,
+            (None, Some(_)) => ChangeStatus::Created,
+            (Some(_), None) => ChangeStatus::Deleted,
+            (Some(_), Some(_)) => ChangeStatus::Modified,
+        };
+        if status == ChangeStatus::Modified && before == after {
+            continue;
+        }
+        let display = crate::paths::display(file.path.strip_prefix(&cwd).unwrap_or(&file.path));
+        let diff = crate::patch::unified(&display, before.as_deref().unwrap_or_default(), after.as_deref().unwrap_or_default());
+        // Past the ---/+++ header
+        let (added, removed) = diff.lines().skip(2).fold((0, 0), |(added, removed), line| match line.as_bytes().first() {
+            Some(b'+') => (added + 1, removed),
+            Some(b'-') => (added, removed + 1),
+            _ => (added, removed),
+        });
+        changes.push(FileChange { path: display, status, added, removed });
+    }
+    changes
+}
+
+/// Saved turns of this workspace, oldest first
+pub fn list() -> Vec<Turn> {
+    let Ok(entries) = fs::read_dir(get_backups_dir()) else {
+        return Vec::new();
+    };
+    let mut turns: Vec<Turn> = entries
+        .flatten()
+        .filter_map(|e| fs::read_to_string(e.path().join("manifest.json")).ok())
+        .filter_map(|content| serde_json::from_str(&content).ok())
+        .collect();
+    turns.sort_by(|a, b| (a.created_at, &a.id).cmp(&(b.created_at, &b.id)));
+    turns
+}
+
+/// The turns `/revert [turn]` undoes, newest first: the last turn, or the
+/// one whose id starts with `id` and every turn after it
+pub fn to_revert(id: Option<&str>) -> Result<Vec<Turn>> {
+    let mut turns = list();
+    let start = match id {
+        None => turns.len().checked_sub(1).ok_or_else(|| anyhow::anyhow!("No agent changes to revert in this workspace"))?,
+        Some(id) => {
+            let matches: Vec<usize> = (0..turns.len()).filter(|&i| turns[i].id.starts_with(id)).collect();
+            match matches.as_slice() {
+                [index] => *index,
+                [] => anyhow::bail!("No turn '{}'. /revert list shows them", id),
+                _ => anyhow::bail!("'{}' matches several turns; use more of the id", id),
+            }
+        }
+    };
+    let mut undone = turns.split_off(start);
+    undone.reverse();
+    Ok(undone)
+}
+
+/// Restore the files of `turns` (newest first, as from `to_revert`) and
+/// drop their backups. Returns the paths restored or deleted.
+pub fn revert(turns: &[Turn]) -> Result<Vec<PathBuf>> {
+    // A turn started now must not back up the restored files again
+    *CURRENT.lock().unwrap() = None;
+    let mut touched = Vec::new();
+    for turn in turns {
+        let dir = get_backups_dir().join(&turn.id);
+        for (index, file) in turn.files.iter().enumerate() {
+            if file.existed {
+                if let Some(parent) = file.path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                fs::copy(dir.join("files").join(index.to_string()), &file.path)
+                    .with_context(|| format!("Failed to restore {}", file.path.display()))?;
+            } else if file.path.exists() {
+                fs::remove_file(&file.path).with_context(|| format!("Failed to delete {}", file.path.display()))?;
+            }
+            if !touched.contains(&file.path) {
+                touched.push(file.path.clone());
+            }
+        }
+        fs::remove_dir_all(&dir)?;
+    }
+    Ok(touched)
+}
@@ -0,0 +1,357 @@
+use anyhow::{Context, Result};
+use std::io::{ErrorKind, Write};
+use std::process::{Child, Command, Stdio};
+use std::time::Duration;
+use std::sync::{Arc, Mutex, OnceLock};
+use tokio::sync::mpsc::{self, UnboundedSender};
+
+use crate::config::SpeechConfig;
+use crate::i18n::Language;
+
+/// Azure Speech output format; WAV plays everywhere without a decoder
+const OUTPUT_FORMAT: &str = "riff-24khz-16bit-mono-pcm";
+/// Recording format the speech-to-text REST endpoint expects
+const RECORDING_CONTENT_TYPE: &str = "audio/wav; codecs=audio/pcm; samplerate=16000";
+
+/// Windows has no command-line recorder, so record through winmm's MCI.
+/// The script stops recording when it gets a line on stdin.
+const WINDOWS_RECORD_SCRIPT: &str = r#"$mci = Add-Type -Name Mci -Namespace Aicli -PassThru -MemberDefinition '[DllImport("winmm.dll")] public static extern int mciSendString(string command, System.Text.StringBuilder buffer, int size, IntPtr callback);'
+$null = $mci::mciSendString('open new type waveaudio alias aicli', $null, 0, [IntPtr]::Zero)
+$null = $mci::mciSendString('set aicli bitspersample 16 samplespersec 16000 channels 1', $null, 0, [IntPtr]::Zero)
+$null = $mci::mciSendString('record aicli', $null, 0, [IntPtr]::Zero)
+$null = [Console]::In.ReadLine()
+$null = $mci::mciSendString('save aicli "' + $env:AICLI_RECORDING + '"', $null, 0, [IntPtr]::Zero)
+$null = $mci::mciSendString('close aicli', $null, 0, [IntPtr]::Zero)"#;
+
+/// Reads assistant answers aloud with Azure Speech. Tokens are split into
+/// sentences as they stream in, and each sentence is synthesized and played
+/// in order on a background task, so speech starts before the answer ends.
+/// Code blocks are skipped.
+pub struct Speaker {
+    sentences: UnboundedSender<String>,
+    /// Current unfinished line of the answer
+    pending: String,
+    in_code_block: bool,
+    /// First synthesis or playback failure, shown after the answer
+    error: Arc<Mutex<Option<String>>>,
+}
+
+impl Speaker {
+    pub fn start(config: &SpeechConfig, language: Language) -> Result<Self> {
+        let key = config.key().ok_or_else(|| {
+            anyhow::anyhow!("Set [speech] key and region in config.toml, or AZURE_SPEECH_KEY and AZURE_SPEECH_REGION")
+        })?;
+        let region = config.region().ok_or_else(|| anyhow::anyhow!("Set [speech] region (e.g. \"eastus\")"))?;
+        let voice = config.voice.clone().unwrap_or_else(|| default_voice(language).to_string());
+
+        let (sentences, mut queue) = mpsc::unbounded_channel::<String>();
+        let error = Arc::new(Mutex::new(None));
+        let worker_error = error.clone();
+        tokio::spawn(async move {
+            let client = reqwest::Client::new();
+            while let Some(sentence) = queue.recv().await {
+                let result = match synthesize(&client, &key, &region, &voice, &sentence).await {
+                    Ok(audio) => tokio::task::spawn_blocking(move || play(&audio))
+                        .await
+                        .unwrap_or_else(|e| Err(anyhow::anyhow!(e))),
+                    Err(e) => Err(e),
+                };
+                if let Err(e) = result {
+                    worker_error.lock().unwrap().get_or_insert(e.to_string());
+                    // Don't keep failing sentence after sentence
+                    while queue.try_recv().is_ok() {}
+                }
+            }
+        });
+
+        Ok(Self { sentences, pending: String::new(), in_code_block: false, error })
+    }
+
+    /// Feed a streamed token; complete sentences are queued for speaking
+    pub fn push(&mut self, token: &str) {
+        self.pending.push_str(token);
+
+        while let Some(newline) = self.pending.find('\n') {
+            let line: String = self.pending.drain(..=newline).collect();
+            if line.trim_start().starts_with("```") {
+                self.in_code_block = !self.in_code_block;
+            } else if !self.in_code_block {
+                self.say(&line);
+            }
+        }
+
+        // Speak finished sentences of a long line without waiting for its end,
+        // unless the line may still turn out to be a code fence
+        if self.in_code_block || self.pending.trim_start().starts_with('`') {
+            return;
+        }
+        if let Some(end) = last_sentence_end(&self.pending) {
+            let done: String = self.pending.drain(..end).collect();
+            self.say(&done);
+        }
+    }
+
+    /// End of the answer: speak whatever is left
+    pub fn finish(&mut self) {
+        let rest = std::mem::take(&mut self.pending);
+        if !self.in_code_block {
+            self.say(&rest);
+        }
+        self.in_code_block = false;
+    }
+
+    pub fn take_error(&self) -> Option<String> {
+        self.error.lock().unwrap().take()
+    }
+
+    fn say(&self, text: &str) {
+        let spoken = plain_text(text);
+        if spoken.chars().any(|c| c.is_alphanumeric()) {
+            let _ = self.sentences.send(spoken);
+        }
+    }
+}
+
+/// Byte index just past the last sentence end in `text` that is followed by
+/// whitespace (or a CJK full stop, which needs none)
+fn last_sentence_end(text: &str) -> Option<usize> {
+    let chars: Vec<(usize, char)> = text.char_indices().collect();
+    chars.iter().enumerate().rev().find_map(|(i, (at, c))| {
+        let end = at + c.len_utf8();
+        match c {
+            '。' | '！' | '？' => Some(end),
+            '.' | '!' | '?' | ':' | ';' => chars.get(i + 1).filter(|(_, next)| next.is_whitespace()).map(|_| end),
+            _ => None,
+        }
+    })
+}
+
+/// Markdown reduced to what should be read aloud
+fn plain_text(markdown: &str) -> String {
+    static LINK: OnceLock<regex::Regex> = OnceLock::new();
+    let link = LINK.get_or_init(|| regex::Regex::new(r"\[([^\]]*)\]\([^)]*\)").unwrap());
+    let text = link.replace_all(markdown, "$1");
+    // Single underscores are kept: they are usually part of an identifier
+    let text = text.replace("__", "");
+    let text: String = text.chars().filter(|c| !matches!(c, '*' | '#' | '`' | '>' | '|')).collect();
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Speech-to-text locale: the configured voice's, else the UI language's
+fn recognition_locale(config: &SpeechConfig, language: Language) -> String {
+    voice_locale(config.voice.as_deref().unwrap_or(default_voice(language)))
+}
+
+/// The locale a voice name starts with ("pt-BR-FranciscaNeural" is pt-BR)
+fn voice_locale(voice: &str) -> String {
+    voice.split('-').take(2).collect::<Vec<_>>().join("-")
+}
+
+/// A neural voice for each UI language
+fn default_voice(language: Language) -> &'static str {
+    match language {
+        Language::En => "en-US-JennyNeural",
+        Language::Pt => "pt-BR-FranciscaNeural",
+        Language::Es => "es-ES-ElviraNeural",
+        Language::Fr => "fr-FR-DeniseNeural",
+        Language::De => "de-DE-KatjaNeural",
+        Language::Zh => "zh-CN-XiaoxiaoNeural",
+    }
+}
+
+async fn synthesize(client: &reqwest::Client, key: &str, region: &str, voice: &str, text: &str) -> Result<Vec<u8>> {
+    let locale = voice_locale(voice);
+    let ssml = format!(
+        "<speak version='1.0' xml:lang='{}'><voice name='{}'>{}</voice></speak>",
+        locale,
+        voice,
+        xml_escape(text)
+    );
+
+    let url = format!("https://{}.tts.speech.microsoft.com/cognitiveservices/v1", region);
+    crate::policy::check_endpoint(&url)?;
+    let response = client
+        .post(&url)
+        .header("Ocp-Apim-Subscription-Key", key)
+        .header("Content-Type", "application/ssml+xml")
+        .header("X-Microsoft-OutputFormat", OUTPUT_FORMAT)
+        .header("User-Agent", "aicli")
+        .body(ssml)
+        .send()
+        .await
+        .context("Could not reach Azure Speech")?;
+    let status = response.status();
+    if !status.is_success() {
+        anyhow::bail!("Azure Speech returned {} (check [speech] key and region)", status);
+    }
+    Ok(response.bytes().await?.to_vec())
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('\'', "&apos;")
+        .replace('"', "&quot;")
+}
+
+/// Play WAV audio with the platform's command-line player, blocking until done
+fn play(audio: &[u8]) -> Result<()> {
+    let path = std::env::temp_dir().join(format!("aicli-speech-{}.wav", std::process::id()));
+    std::fs::write(&path, audio)?;
+    let file = path.display().to_string();
+
+    let windows_script = format!("(New-Object Media.SoundPlayer '{}').PlaySync()", file.replace('\'', "''"));
+    let players: Vec<(&str, Vec<&str>)> = match std::env::consts::OS {
+        "macos" => vec![("afplay", vec![file.as_str()])],
+        "windows" => vec![("powershell", vec!["-NoProfile", "-Command", windows_script.as_str()])],
+        _ => vec![
+            ("paplay", vec![file.as_str()]),
+            ("pw-play", vec![file.as_str()]),
+            ("aplay", vec!["-q", file.as_str()]),
+            ("ffplay", vec!["-nodisp", "-autoexit", "-loglevel", "quiet", file.as_str()]),
+        ],
+    };
+
+    let mut result = Err(anyhow::anyhow!(
+        "No audio player found (tried {})",
+        players.iter().map(|(p, _)| *p).collect::<Vec<_>>().join(", ")
+    ));
+    for (program, args) in &players {
+        match Command::new(program).args(args).stdout(Stdio::null()).stderr(Stdio::null()).status() {
+            Ok(status) if status.success() => {
+                result = Ok(());
+                break;
+            }
+            Ok(status) => {
+                result = Err(anyhow::anyhow!("{} exited with {}", program, status));
+                break;
+            }
+            Err(e) if e.kind() == ErrorKind::NotFound => continue,
+            Err(e) => {
+                result = Err(anyhow::anyhow!("Failed to run {}: {}", program, e));
+                break;
+            }
+        }
+    }
+    let _ = std::fs::remove_file(&path);
+    result
+}
+
+/// Record from the default microphone until Enter is pressed, then
+/// transcribe with Azure Speech (/mic). The short-audio endpoint takes up to
+/// 60 seconds of speech.
+pub async fn listen(config: &SpeechConfig, language: Language) -> Result<String> {
+    let key = config.key().ok_or_else(|| {
+        anyhow::anyhow!("Set [speech] key and region in config.toml, or AZURE_SPEECH_KEY and AZURE_SPEECH_REGION")
+    })?;
+    let region = config.region().ok_or_else(|| anyhow::anyhow!("Set [speech] region (e.g. \"eastus\")"))?;
+
+    let audio = tokio::task::spawn_blocking(record).await??;
+    transcribe(&key, &region, &recognition_locale(config, language), audio).await
+}
+
+async fn transcribe(key: &str, region: &str, locale: &str, audio: Vec<u8>) -> Result<String> {
+    let url = format!(
+        "https://{}.stt.speech.microsoft.com/speech/recognition/conversation/cognitiveservices/v1?language={}&format=simple",
+        region, locale
+    );
+    crate::policy::check_endpoint(&url)?;
+    let response = reqwest::Client::new()
+        .post(&url)
+        .header("Ocp-Apim-Subscription-Key", key)
+        .header("Content-Type", RECORDING_CONTENT_TYPE)
+        .header("Accept", "application/json")
+        .body(audio)
+        .send()
+        .await
+        .context("Could not reach Azure Speech")?;
+    let status = response.status();
+    if !status.is_success() {
+        anyhow::bail!("Azure Speech returned {} (check [speech] key and region)", status);
+    }
+
+    let result: serde_json::Value = response.json().await?;
+    match result["RecognitionStatus"].as_str().unwrap_or_default() {
+        "Success" => Ok(result["DisplayText"].as_str().unwrap_or_default().trim().to_string()),
+        "NoMatch" | "InitialSilenceTimeout" | "BabbleTimeout" => anyhow::bail!("No speech recognized"),
+        other => anyhow::bail!("Recognition failed: {}", other),
+    }
+}
+
+/// Record 16 kHz mono WAV with the platform's recorder, blocking until
+/// Enter is pressed
+fn record() -> Result<Vec<u8>> {
+    let path = std::env::temp_dir().join(format!("aicli-mic-{}.wav", std::process::id()));
+    let file = path.display().to_string();
+
+    let recorders: Vec<(&str, Vec<&str>)> = match std::env::consts::OS {
+        "macos" => vec![
+            ("rec", vec!["-q", "-r", "16000", "-c", "1", "-b", "16", file.as_str()]),
+            ("ffmpeg", vec!["-loglevel", "quiet", "-y", "-f", "avfoundation", "-i", ":0", "-ac", "1", "-ar", "16000", file.as_str()]),
+        ],
+        "windows" => vec![("powershell", vec!["-NoProfile", "-Command", WINDOWS_RECORD_SCRIPT])],
+        _ => vec![
+            ("arecord", vec!["-q", "-f", "S16_LE", "-r", "16000", "-c", "1", "-t", "wav", file.as_str()]),
+            ("rec", vec!["-q", "-r", "16000", "-c", "1", "-b", "16", file.as_str()]),
+            ("ffmpeg", vec!["-loglevel", "quiet", "-y", "-f", "pulse", "-i", "default", "-ac", "1", "-ar", "16000", file.as_str()]),
+        ],
+    };
+
+    let mut started = None;
+    for (program, args) in &recorders {
+        let spawned = Command::new(program)
+            .args(args)
+            .env("AICLI_RECORDING", &file)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn();
+        match spawned {
+            Ok(mut child) => {
+                // A recorder that can't open the microphone exits right away
+                std::thread::sleep(Duration::from_millis(300));
+                if let Ok(Some(status)) = child.try_wait() {
+                    anyhow::bail!("{} could not record ({})", program, status);
+                }
+                started = Some(child);
+                break;
+            }
+            Err(e) if e.kind() == ErrorKind::NotFound => continue,
+            Err(e) => anyhow::bail!("Failed to run {}: {}", program, e),
+        }
+    }
+    let Some(mut recorder) = started else {
+        anyhow::bail!(
+            "No audio recorder found (tried {})",
+            recorders.iter().map(|(p, _)| *p).collect::<Vec<_>>().join(", ")
+        );
+    };
+
+    let mut line = String::new();
+    let _ = std::io::stdin().read_line(&mut line);
+    stop_recorder(&mut recorder);
+
+    let audio = std::fs::read(&path).context("The recorder wrote no audio");
+    let _ = std::fs::remove_file(&path);
+    audio
+}
+
+/// Stop gracefully so the recorder finishes the WAV header
+fn stop_recorder(recorder: &mut Child) {
+    if cfg!(windows) {
+        if let Some(stdin) = recorder.stdin.as_mut() {
+            let _ = stdin.write_all(b"\n");
+        }
+    } else {
+        let _ = Command::new("kill").args(["-INT", &recorder.id().to_string()]).output();
+    }
+    for _ in 0..50 {
+        if let Ok(Some(_)) = recorder.try_wait() {
+            return;
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    }
+    let _ = recorder.kill();
+    let _ = recorder.wait();
+}
@@ -0,0 +1,162 @@
+//! Files a prompt mentions without an `@`: a file name (`client.rs`) or an
+//! identifier declared in the workspace (`AzureClient`, `read_file_context`).
+//! The chat offers to attach them; `[ui] suggest_files = false` turns it off.
+
+use regex::Regex;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// Files offered per prompt
+const MAX_SUGGESTIONS: usize = 3;
+/// The walk stops after this many files, so huge trees stay fast
+const MAX_FILES: usize = 5_000;
+/// Larger files aren't searched for declarations
+const MAX_FILE_BYTES: u64 = 512 * 1024;
+/// Shorter identifiers are too common to point at one file
+const MIN_IDENTIFIER_CHARS: usize = 4;
+
+pub struct Suggestion {
+    /// As written in the prompt
+    pub mention: String,
+    /// Relative to the current directory
+    pub path: String,
+}
+
+/// File names and identifiers in `prompt`, in order and without repeats
+fn mentions(prompt: &str) -> (Vec<String>, Vec<String>) {
+    static PATTERNS: OnceLock<[Regex; 4]> = OnceLock::new();
+    let [quoted, file, camel, snake] = PATTERNS.get_or_init(|| {
+        [
+            Regex::new(r"`([^`\s]+)`").unwrap(),
+            Regex::new(r"(?:^|[\s(])((?:[\w-]+/)*[\w-]+\.[A-Za-z][A-Za-z0-9]{0,4})\b").unwrap(),
+            Regex::new(r"\b[A-Z][a-z0-9]+(?:[A-Z][a-z0-9]*)+\b").unwrap(),
+            Regex::new(r"\b[a-z][a-z0-9]*(?:_[a-z0-9]+)+\b").unwrap(),
+        ]
+    });
+
+    let mut files: Vec<String> = Vec::new();
+    let mut identifiers: Vec<String> = Vec::new();
+    let add = |list: &mut Vec<String>, word: &str| {
+        if !list.iter().any(|w| w == word) {
+            list.push(word.to_string());
+        }
+    };
+    for caps in quoted.captures_iter(prompt) {
+        let span = &caps[1];
+        if span.contains('/') || file.is_match(&format!(" {}", span)) {
+            add(&mut files, span.trim_start_matches("./"));
+        } else {
+            // `client::AzureClient` or `ui.pick`: the last segment is the one declared
+            let name = span.rsplit([':', '.']).next().unwrap_or(span).trim_end_matches("()");
+            if name.len() >= MIN_IDENTIFIER_CHARS && name.chars().all(|c| c.is_alphanumeric() || c == '_') {
+                add(&mut identifiers, name);
+            }
+        }
+    }
+    for caps in file.captures_iter(prompt) {
+        add(&mut files, &caps[1]);
+    }
+    for word in camel.find_iter(prompt).chain(snake.find_iter(prompt)) {
+        if word.as_str().len() >= MIN_IDENTIFIER_CHARS {
+            add(&mut identifiers, word.as_str());
+        }
+    }
+    (files, identifiers)
+}
+
+/// The files under the current directory, skipping hidden and build directories
+fn workspace_files() -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    let mut dirs = vec![PathBuf::from(".")];
+    while let Some(dir) = dirs.pop() {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        let mut entries: Vec<_> = entries.flatten().collect();
+        entries.sort_by_key(|e| e.file_name());
+        for entry in entries {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if name.starts_with('.') || name == "node_modules" || name == "target" {
+                continue;
+            }
+            let path = entry.path();
+            if path.is_dir() {
+                dirs.push(path);
+            } else if files.len() < MAX_FILES {
+                files.push(path);
+            }
+        }
+    }
+    files
+}
+
+fn relative(path: &Path) -> String {
+    crate::paths::display(path.strip_prefix(".").unwrap_or(path))
+}
+
+/// Files worth attaching for what `prompt` mentions, leaving out those in
+/// `attached` (paths as typed or absolute)
+pub fn files(prompt: &str, attached: &[String]) -> Vec<Suggestion> {
+    let (file_mentions, identifiers) = mentions(prompt);
+    if file_mentions.is_empty() && identifiers.is_empty() {
+        return Vec::new();
+    }
+    let attached: Vec<PathBuf> = attached.iter().filter_map(|a| crate::paths::resolve(a).canonicalize().ok()).collect();
+    let is_new = |path: &Path| path.canonicalize().is_ok_and(|p| !attached.contains(&p));
+    let tree = workspace_files();
+    let mut suggestions: Vec<Suggestion> = Vec::new();
+    let offer = |mention: &str, path: &Path, suggestions: &mut Vec<Suggestion>| {
+        let path = relative(path);
+        if !suggestions.iter().any(|s| s.path == path) {
+            suggestions.push(Suggestion { mention: mention.to_string(), path });
+        }
+    };
+
+    for mention in &file_mentions {
+        let suffix = format!("/{}", mention);
+        let matching: Vec<&PathBuf> =
+            tree.iter().filter(|f| relative(f) == *mention || relative(f).ends_with(&suffix)).collect();
+        // A name found all over the tree (mod.rs, index.ts) points at none of them
+        if let [one] = matching.as_slice() {
+            if is_new(one) {
+                offer(mention, one, &mut suggestions);
+            }
+        }
+    }
+
+    if !identifiers.is_empty() {
+        let declarations: Vec<(String, Regex)> = identifiers
+            .iter()
+            .filter_map(|id| {
+                let pattern = format!(
+                    r"\b(struct|enum|trait|class|interface|record|object|module|fn|def|func|function|type|const|static)\s+{}\b",
+                    regex::escape(id)
+                );
+                Some((id.clone(), Regex::new(&pattern).ok()?))
+            })
+            .collect();
+        let sources = tree.iter().filter(|f| {
+            crate::tools::detect_language(f).is_some() && std::fs::metadata(f).is_ok_and(|m| m.len() <= MAX_FILE_BYTES)
+        });
+        let mut found: Vec<&str> = Vec::new();
+        for file in sources {
+            let Ok(content) = std::fs::read_to_string(file) else {
+                continue;
+            };
+            for (id, declaration) in &declarations {
+                if !found.contains(&id.as_str()) && declaration.is_match(&content) {
+                    found.push(id);
+                    if is_new(file) {
+                        offer(id, file, &mut suggestions);
+                    }
+                }
+            }
+            if found.len() == declarations.len() {
+                break;
+            }
+        }
+    }
+
+    suggestions.truncate(MAX_SUGGESTIONS);
+    suggestions
+}
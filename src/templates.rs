@@ -0,0 +1,205 @@
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Component, PathBuf};
+
+use crate::client::AzureClient;
+use crate::codeblocks;
+use crate::config::AppConfig;
+use crate::console::println;
+use crate::text;
+use crate::ui::UI;
+
+const SCAFFOLD_PROMPT: &str = "You scaffold new software projects. Write every file of the project as a fenced \
+code block whose header is the language and the file's path relative to the project root, like ```toml Cargo.toml. \
+Write complete, working files: no placeholders, ellipses or \"rest of the code\" comments. Never put a code fence \
+inside a file; in Markdown files, indent code by four spaces instead. Outside the code blocks, write at most one \
+short line per file.";
+
+/// Templates that ship with aicli; a file of the same name in
+/// ~/.aicli/templates replaces one
+const BUILT_IN: &[(&str, &str)] = &[
+    ("rust-cli", include_str!("../templates/rust-cli.toml")),
+    ("fastapi-service", include_str!("../templates/fastapi-service.toml")),
+    ("react-app", include_str!("../templates/react-app.toml")),
+];
+
+/// A project recipe: what to ask the model for and what to run afterwards
+#[derive(Debug, Clone, Deserialize)]
+pub struct Template {
+    #[serde(skip)]
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    /// What to build; `{name}` is replaced with the project name
+    pub prompt: String,
+    /// Commands run in the new project once its files are written
+    #[serde(default)]
+    pub setup: Vec<String>,
+    /// Where the template was loaded from, None for built-ins
+    #[serde(skip)]
+    pub source: Option<PathBuf>,
+}
+
+pub fn get_templates_dir() -> PathBuf {
+    let home = dirs::home_dir().unwrap_or_else(|| PathBuf::from("."));
+    home.join(".aicli").join("templates")
+}
+
+/// Built-in and user templates by name. Unreadable user files are skipped
+/// with a warning.
+pub fn list() -> BTreeMap<String, Template> {
+    let mut templates = BTreeMap::new();
+    for (name, content) in BUILT_IN {
+        if let Ok(mut template) = toml::from_str::<Template>(content) {
+            template.name = name.to_string();
+            templates.insert(name.to_string(), template);
+        }
+    }
+
+    let Ok(entries) = fs::read_dir(get_templates_dir()) else {
+        return templates;
+    };
+    for path in entries.flatten().map(|e| e.path()) {
+        if path.extension().is_none_or(|ext| ext != "toml") {
+            continue;
+        }
+        let Some(name) = path.file_stem().map(|s| s.to_string_lossy().to_string()) else {
+            continue;
+        };
+        let parsed = fs::read_to_string(&path)
+            .map_err(anyhow::Error::from)
+            .and_then(|content| Ok(toml::from_str::<Template>(&content)?));
+        match parsed {
+            Ok(mut template) => {
+                template.name = name.clone();
+                template.source = Some(path);
+                templates.insert(name, template);
+            }
+            Err(e) => eprintln!("\x1b[33m⚠ Skipping template {}: {}\x1b[0m", path.display(), e),
+        }
+    }
+    templates
+}
+
+/// `aicli new` without arguments
+pub fn print_list() {
+    for template in list().values() {
+        let origin = match &template.source {
+            Some(path) => path.display().to_string(),
+            None => "built-in".to_string(),
+        };
+        println!(
+            "\x1b[38;5;141m{:<18}\x1b[0m {}  \x1b[90m({})\x1b[0m",
+            template.name, template.description, origin
+        );
+    }
+    println!(
+        "\n\x1b[90mCreate a project with: aicli new <template> <name> · add templates to {}\x1b[0m",
+        get_templates_dir().display()
+    );
+}
+
+/// `aicli new <template> <name>`: ask the active model for the project's
+/// files, show the tree, and write it to ./<name> once confirmed. The
+/// template's setup commands run afterwards, also after a confirmation.
+pub async fn scaffold(config: &AppConfig, template_name: &str, name: &str) -> Result<()> {
+    if name.is_empty() || name.contains(['/', '\\']) || name.starts_with('.') {
+        anyhow::bail!("'{}' is not a valid project name", name);
+    }
+    let templates = list();
+    let template = templates.get(template_name).ok_or_else(|| {
+        let names: Vec<&str> = templates.keys().map(|k| k.as_str()).collect();
+        anyhow::anyhow!("No template '{}'. Available: {}", template_name, names.join(", "))
+    })?;
+
+    let root = std::env::current_dir()?.join(name);
+    if fs::read_dir(&root).is_ok_and(|mut entries| entries.next().is_some()) {
+        anyhow::bail!("{} already exists and is not empty", root.display());
+    }
+
+    let model = config
+        .get_active_model()
+        .ok_or_else(|| anyhow::anyhow!("No active model configured"))?
+        .clone();
+    let ui = UI::new(config.language);
+
+    println!("  Generating {} from '{}' with {}...", name, template.name, model.name);
+    let client = AzureClient::new(model);
+    let answer = client.complete(SCAFFOLD_PROMPT, &template.prompt.replace("{name}", name)).await?;
+    let files = parse_files(&answer)?;
+
+    println!("\n\x1b[1m{}/\x1b[0m", name);
+    for (path, content) in &files {
+        println!(
+            "  {}  \x1b[90m{}\x1b[0m",
+            path.display(),
+            text::format_size(content.len() as u64)
+        );
+    }
+    println!();
+    if !ui.confirm(&format!("Create these {} files in {}?", files.len(), root.display())) {
+        println!("Nothing written.");
+        return Ok(());
+    }
+
+    for (path, content) in &files {
+        let target = root.join(path);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&target, content).with_context(|| format!("Failed to write {}", target.display()))?;
+    }
+    ui.print_success(&format!("Created {} ({} files)", root.display(), files.len()));
+
+    if template.setup.is_empty() {
+        return Ok(());
+    }
+    println!();
+    for command in &template.setup {
+        println!("  \x1b[38;5;245m$\x1b[0m {}", command);
+    }
+    if !ui.confirm("Run these setup commands?") {
+        return Ok(());
+    }
+    for command in &template.setup {
+        println!("\x1b[38;5;245m$\x1b[0m {}", command);
+        let status = crate::tools::shell_command(command, &config.tools)
+            .current_dir(&root)
+            .status()
+            .with_context(|| format!("Failed to run '{}'", command))?;
+        if !status.success() {
+            anyhow::bail!("'{}' failed ({}); the project files are in {}", command, status, root.display());
+        }
+    }
+    ui.print_success("Setup finished");
+    Ok(())
+}
+
+/// The answer's files by path; blocks without a path are skipped. Fails when
+/// a path points outside the project or the answer was cut off mid-file.
+fn parse_files(answer: &str) -> Result<BTreeMap<PathBuf, String>> {
+    let fences = answer.lines().filter(|l| l.trim_start().starts_with("```")).count();
+    if fences % 2 == 1 {
+        anyhow::bail!("The model's answer was cut off mid-file; try again or raise max_tokens");
+    }
+
+    let mut files = BTreeMap::new();
+    for block in codeblocks::extract(answer) {
+        let Some(raw) = block.path else {
+            continue;
+        };
+        let path = PathBuf::from(raw.trim());
+        let inside = path.components().all(|c| matches!(c, Component::Normal(_) | Component::CurDir));
+        if !inside || path.as_os_str().is_empty() {
+            anyhow::bail!("The model named a file outside the project: {}", raw);
+        }
+        let path: PathBuf = path.components().filter(|c| *c != Component::CurDir).collect();
+        files.insert(path, block.code);
+    }
+    if files.is_empty() {
+        anyhow::bail!("The model's answer named no files");
+    }
+    Ok(files)
+}
@@ -0,0 +1,139 @@
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+/// Columns `s` takes in the terminal. ANSI escape sequences take none; wide
+/// characters (CJK, most emoji) take two.
+pub fn width(s: &str) -> usize {
+    if !s.contains('\x1b') {
+        return s.width();
+    }
+
+    let mut visible = String::with_capacity(s.len());
+    let mut in_escape = false;
+    for c in s.chars() {
+        if c == '\x1b' {
+            in_escape = true;
+        } else if in_escape {
+            if c == 'm' || c == '\\' {
+                in_escape = false;
+            }
+        } else {
+            visible.push(c);
+        }
+    }
+    visible.width()
+}
+
+/// `s` without ANSI escape sequences: colors and cursor movement (CSI),
+/// hyperlinks and titles (OSC) and cursor save/restore
+pub fn strip_ansi(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\x1b' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('[') => {
+                // Parameters, then one final byte in @..~
+                for c in chars.by_ref() {
+                    if ('@'..='~').contains(&c) {
+                        break;
+                    }
+                }
+            }
+            Some(']') => {
+                // Ends with BEL or ESC \
+                while let Some(c) = chars.next() {
+                    if c == '\x07' || c == '\x1b' && chars.next_if_eq(&'\\').is_some() {
+                        break;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    out
+}
+
+/// Cut `s` to at most `max_width` columns, ending in "..." when shortened.
+/// Never splits a grapheme cluster, so emoji sequences and combining marks
+/// stay intact.
+pub fn truncate(s: &str, max_width: usize) -> String {
+    if s.width() <= max_width {
+        return s.to_string();
+    }
+
+    let budget = max_width.saturating_sub(3);
+    let mut result = String::new();
+    let mut used = 0;
+    for grapheme in s.graphemes(true) {
+        let w = grapheme.width();
+        if used + w > budget {
+            break;
+        }
+        result.push_str(grapheme);
+        used += w;
+    }
+    result.push_str("...");
+    result
+}
+
+/// Like `truncate` but keeps the end of `s` ("...src/main.rs"), for paths
+pub fn truncate_start(s: &str, max_width: usize) -> String {
+    if s.width() <= max_width {
+        return s.to_string();
+    }
+
+    let budget = max_width.saturating_sub(3);
+    let mut tail: Vec<&str> = Vec::new();
+    let mut used = 0;
+    for grapheme in s.graphemes(true).rev() {
+        let w = grapheme.width();
+        if used + w > budget {
+            break;
+        }
+        tail.push(grapheme);
+        used += w;
+    }
+    tail.reverse();
+    format!("...{}", tail.concat())
+}
+
+/// Pad `s` with spaces to `target` columns (`format!` pads by chars, which
+/// misaligns wide characters)
+pub fn pad(s: &str, target: usize) -> String {
+    format!("{}{}", s, " ".repeat(target.saturating_sub(width(s))))
+}
+
+/// Hard-wrap `s` into lines of at most `max_width` columns, splitting between
+/// grapheme clusters. An empty string gives one empty line.
+pub fn wrap(s: &str, max_width: usize) -> Vec<String> {
+    let max_width = max_width.max(1);
+    let mut lines = Vec::new();
+    let mut current = String::new();
+    let mut used = 0;
+    for grapheme in s.graphemes(true) {
+        let w = grapheme.width();
+        if used + w > max_width && !current.is_empty() {
+            lines.push(std::mem::take(&mut current));
+            used = 0;
+        }
+        current.push_str(grapheme);
+        used += w;
+    }
+    lines.push(current);
+    lines
+}
+
+/// Byte count for people: "512 B", "3.4 KB", "1.2 MB"
+pub fn format_size(size: u64) -> String {
+    if size < 1024 {
+        format!("{} B", size)
+    } else if size < 1024 * 1024 {
+        format!("{:.1} KB", size as f64 / 1024.0)
+    } else {
+        format!("{:.1} MB", size as f64 / (1024.0 * 1024.0))
+    }
+}
@@ -1,7 +1,27 @@
+use crate::azure_devops;
+use crate::buildtools::{self, Task};
+use crate::config::ToolsConfig;
+use crate::database;
+use crate::docker;
+use crate::github;
+use crate::guard::Guard;
+use crate::kubernetes;
+use crate::http;
+use crate::issues;
+use crate::lsp;
+use crate::outline;
+use crate::paths;
+use crate::process;
+use crate::redact;
+use crate::text;
 use anyhow::Result;
 use serde_json::Value;
-use std::path::{Path, PathBuf};
-use std::process::Command;
+use std::io::Read;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
 
 #[derive(Debug, Clone)]
 pub struct ToolCall {
@@ -18,43 +38,303 @@ pub struct ToolResult {
     pub success: bool,
 }
 
-pub trait Tool {
-    fn name(&self) -> &str;
-    fn execute(&self, input: &Value) -> Result<String>;
+/// Tools that change files, processes or remote state; in a dry run they
+/// only report what they would do (http_request too, unless it only reads)
+const SIDE_EFFECT_TOOLS: &[&str] = &[
+    "write_file", "edit_file", "create_directory", "execute_command", "run_background", "kill_process",
+    "docker_exec", "github_create_pr", "github_comment", "azdo_create_pr", "rename_symbol",
+    "run_build", "run_tests", "run_lint",
+];
+
+/// First line of every dry-run result
+pub const DRY_RUN_NOTE: &str = "[dry run] Nothing was changed. Continue as if it succeeded.";
+
+static DRY_RUN: AtomicBool = AtomicBool::new(false);
+
+/// `--dry-run` and `/dryrun on|off`
+pub fn set_dry_run(enabled: bool) {
+    DRY_RUN.store(enabled, Ordering::Relaxed);
+}
+
+pub fn is_dry_run() -> bool {
+    DRY_RUN.load(Ordering::Relaxed)
+}
+
+fn has_side_effects(tool_call: &ToolCall) -> bool {
+    match tool_call.name.as_str() {
+        "http_request" => {
+            let method = tool_call.input.get("method").and_then(|m| m.as_str()).unwrap_or("GET");
+            !matches!(method.to_uppercase().as_str(), "GET" | "HEAD" | "OPTIONS")
+        }
+        name => SIDE_EFFECT_TOOLS.contains(&name),
+    }
 }
 
-pub struct ToolExecutor;
+/// Build a command that runs `command` through the platform shell
+pub fn shell_command(command: &str, config: &ToolsConfig) -> Command {
+    let default_shell = if cfg!(windows) { "cmd" } else { "sh" };
+    let shell = config.shell_for_os().unwrap_or(default_shell);
+
+    let mut cmd = match shell.to_lowercase().as_str() {
+        "cmd" | "cmd.exe" => {
+            let mut cmd = Command::new("cmd");
+            cmd.args(["/C", command]);
+            cmd
+        }
+        name @ ("pwsh" | "pwsh.exe" | "powershell" | "powershell.exe") => {
+            let mut cmd = Command::new(name);
+            cmd.args(["-NoProfile", "-NonInteractive", "-Command", command]);
+            cmd
+        }
+        "nushell" | "nu" => {
+            let mut cmd = Command::new("nu");
+            cmd.args(["-c", command]);
+            cmd
+        }
+        // bash, zsh, fish, sh or a full path to any of them
+        _ => {
+            let mut cmd = Command::new(shell);
+            cmd.args(["-c", command]);
+            cmd
+        }
+    };
+
+    cmd.envs(&config.env);
+    cmd
+}
+
+/// Apply the optional `env` object of a tool call to the command
+pub fn apply_call_env(cmd: &mut Command, input: &Value) {
+    if let Some(env) = input.get("env").and_then(|e| e.as_object()) {
+        for (key, value) in env {
+            match value.as_str() {
+                Some(s) => cmd.env(key, s),
+                None => cmd.env(key, value.to_string()),
+            };
+        }
+    }
+}
+
+/// Run `cmd` in its own process group, killing it after `timeout_secs`
+/// (0 = no limit). Returns the exit status, None when it timed out, and
+/// the output so far.
+pub fn run_with_timeout(mut cmd: Command, timeout_secs: u64) -> Result<(Option<std::process::ExitStatus>, String, String)> {
+    cmd.stdin(Stdio::null()).stdout(Stdio::piped()).stderr(Stdio::piped());
+    process::isolate_process_group(&mut cmd);
+
+    let mut child = cmd.spawn()?;
+    let stdout_buf = collect_pipe(child.stdout.take());
+    let stderr_buf = collect_pipe(child.stderr.take());
+
+    let deadline = (timeout_secs > 0).then(|| Instant::now() + Duration::from_secs(timeout_secs));
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break Some(status);
+        }
+        if deadline.is_some_and(|d| Instant::now() >= d) {
+            process::kill_tree(&mut child);
+            break None;
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    };
+    Ok((status, drain_pipe(stdout_buf), drain_pipe(stderr_buf)))
+}
+
+/// Read a child pipe on a thread so a chatty process can't fill the pipe and block
+fn collect_pipe(pipe: Option<impl Read + Send + 'static>) -> Option<std::thread::JoinHandle<Vec<u8>>> {
+    pipe.map(|mut pipe| {
+        std::thread::spawn(move || {
+            let mut buf = Vec::new();
+            let _ = pipe.read_to_end(&mut buf);
+            buf
+        })
+    })
+}
+
+fn drain_pipe(handle: Option<std::thread::JoinHandle<Vec<u8>>>) -> String {
+    handle
+        .and_then(|h| h.join().ok())
+        .map(|buf| String::from_utf8_lossy(&buf).to_string())
+        .unwrap_or_default()
+}
+
+/// Run async work (HTTP APIs) from a synchronous tool. Tools run on the
+/// multi-threaded runtime, so the current worker can block.
+pub fn block_on<F: std::future::Future>(future: F) -> F::Output {
+    tokio::task::block_in_place(|| tokio::runtime::Handle::current().block_on(future))
+}
+
+/// Cut `output` to at most `max_chars` characters, keeping the head and the
+/// tail (where errors and summaries usually are) around a marker.
+pub fn truncate_output(output: &str, max_chars: usize) -> String {
+    let total = output.chars().count();
+    if max_chars == 0 || total <= max_chars {
+        return output.to_string();
+    }
+
+    let head_chars = max_chars * 6 / 10;
+    let tail_chars = max_chars - head_chars;
+    let head: String = output.chars().take(head_chars).collect();
+    let tail: String = output.chars().skip(total - tail_chars).collect();
+
+    format!(
+        "{}\n\n[... {} characters truncated ...]\n\n{}",
+        head,
+        total - head_chars - tail_chars,
+        tail
+    )
+}
+
+pub struct ToolExecutor {
+    config: ToolsConfig,
+    guard: Mutex<Guard>,
+    /// Tools that may run; all of them when None (recipes narrow it)
+    allowed: Option<Vec<String>>,
+}
 
 impl ToolExecutor {
-    pub fn execute(tool_call: &ToolCall) -> ToolResult {
+    pub fn new(config: ToolsConfig) -> Self {
+        let guard = Mutex::new(Guard::new(config.guard.clone()));
+        Self { config, guard, allowed: None }
+    }
+
+    /// Only run the tools in `tools`
+    pub fn restrict(&mut self, tools: Vec<String>) {
+        self.allowed = Some(tools);
+    }
+
+    /// The untrusted tool whose output this call's arguments were copied
+    /// from, if any; such calls should be confirmed by the user first
+    pub fn taint_source(&self, tool_call: &ToolCall) -> Option<String> {
+        self.guard.lock().unwrap().taint_source(tool_call)
+    }
+
+    pub fn execute(&self, tool_call: &ToolCall) -> ToolResult {
+        let started = Instant::now();
         let result = match tool_call.name.as_str() {
-            "execute_command" => Self::execute_command(&tool_call.input),
+            name if crate::policy::tool_disabled(name) => {
+                Err(anyhow::anyhow!("{} is disabled by the organization policy", name))
+            }
+            name if self.allowed.as_ref().is_some_and(|allowed| !allowed.iter().any(|t| t == name)) => {
+                Err(anyhow::anyhow!("{} is not one of the tools allowed for this run", name))
+            }
+            _ if is_dry_run() && has_side_effects(tool_call) => self.dry_run(tool_call),
+            "execute_command" => self.execute_command(&tool_call.input),
             "read_file" => Self::read_file(&tool_call.input),
             "write_file" => Self::write_file(&tool_call.input),
             "edit_file" => Self::edit_file(&tool_call.input),
             "list_directory" => Self::list_directory(&tool_call.input),
             "search_files" => Self::search_files(&tool_call.input),
             "search_content" => Self::search_content(&tool_call.input),
+            "create_directory" => Self::create_directory(&tool_call.input),
+            "stat_file" => Self::stat_file(&tool_call.input),
+            "get_outline" => outline::get_outline(&tool_call.input),
+            "run_background" => process::run_background(&tool_call.input, &self.config),
+            "run_build" => buildtools::run(Task::Build, &tool_call.input, &self.config),
+            "run_tests" => buildtools::run(Task::Test, &tool_call.input, &self.config),
+            "run_lint" => buildtools::run(Task::Lint, &tool_call.input, &self.config),
+            "check_process" => process::check_process(&tool_call.input),
+            "kill_process" => process::kill_process(&tool_call.input),
+            "http_request" => http::http_request(&tool_call.input, &self.config),
+            "database_query" => database::database_query(&tool_call.input, &self.config),
+            "docker_ps" => docker::docker_ps(&tool_call.input, &self.config.docker),
+            "docker_logs" => docker::docker_logs(&tool_call.input, &self.config.docker),
+            "docker_exec" => docker::docker_exec(&tool_call.input, &self.config.docker),
+            "kubectl_get" => kubernetes::kubectl_get(&tool_call.input, &self.config.kubernetes),
+            "kubectl_describe" => kubernetes::kubectl_describe(&tool_call.input, &self.config.kubernetes),
+            "kubectl_logs" => kubernetes::kubectl_logs(&tool_call.input, &self.config.kubernetes),
+            "find_definition" => lsp::find_definition(&tool_call.input, &self.config.lsp),
+            "find_references" => lsp::find_references(&tool_call.input, &self.config.lsp),
+            "document_symbols" => lsp::document_symbols(&tool_call.input, &self.config.lsp),
+            "diagnostics" => lsp::diagnostics(&tool_call.input, &self.config.lsp),
+            "rename_symbol" => lsp::rename_symbol(&tool_call.input, &self.config.lsp, true),
+            "github_list_issues" => github::list_issues(&tool_call.input, &self.config.github),
+            "github_read_issue" => github::read_issue(&tool_call.input, &self.config.github),
+            "github_create_pr" => github::create_pr_tool(&tool_call.input, &self.config.github),
+            "github_comment" => github::comment(&tool_call.input, &self.config.github),
+            "read_issue" => issues::read_issue(&tool_call.input, &self.config.issues),
+            "azdo_read_work_item" => azure_devops::read_work_item(&tool_call.input, &self.config.azure_devops),
+            "azdo_pipeline_runs" => azure_devops::pipeline_runs(&tool_call.input, &self.config.azure_devops),
+            "azdo_pipeline_logs" => azure_devops::pipeline_logs(&tool_call.input, &self.config.azure_devops),
+            "azdo_create_pr" => azure_devops::create_pr(&tool_call.input, &self.config.azure_devops),
             _ => Err(anyhow::anyhow!("Unknown tool: {}", tool_call.name)),
         };
 
+        crate::metrics::record_tool(&tool_call.name, started.elapsed(), result.is_ok());
+        crate::policy::audit_tool_call(&tool_call.name, &tool_call.input, result.is_ok());
         match result {
             Ok(output) => ToolResult {
                 tool_call_id: tool_call.id.clone(),
                 tool_name: tool_call.name.clone(),
-                output,
+                output: self.guard.lock().unwrap().wrap_output(
+                    tool_call,
+                    truncate_output(&redact::redact(&output), self.config.max_output_chars),
+                ),
                 success: true,
             },
             Err(e) => ToolResult {
                 tool_call_id: tool_call.id.clone(),
                 tool_name: tool_call.name.clone(),
-                output: format!("Error: {}", e),
+                output: redact::redact(&format!("Error: {}", e)),
                 success: false,
             },
         }
     }
 
-    fn execute_command(input: &Value) -> Result<String> {
+    /// What a side-effecting tool would do, without doing it. File changes
+    /// are shown as a diff; the checks the real tool makes still apply.
+    fn dry_run(&self, tool_call: &ToolCall) -> Result<String> {
+        let input = &tool_call.input;
+        let text = |key: &str| {
+            input
+                .get(key)
+                .and_then(|v| v.as_str())
+                .ok_or_else(|| anyhow::anyhow!("Missing '{}' parameter", key))
+        };
+        let note = DRY_RUN_NOTE;
+        match tool_call.name.as_str() {
+            "write_file" => {
+                let path = text("path")?;
+                let content = text("content")?;
+                let old = std::fs::read_to_string(paths::resolve(path)).unwrap_or_default();
+                let action = if paths::resolve(path).exists() { "overwrite" } else { "create" };
+                Ok(format!(
+                    "{}\nWould {} {} ({} bytes):\n{}",
+                    note,
+                    action,
+                    path,
+                    content.len(),
+                    crate::patch::unified(path, &old, content)
+                ))
+            }
+            "edit_file" => {
+                let path = text("path")?;
+                let old_text = text("old_text")?;
+                let new_text = text("new_text")?;
+                let content = std::fs::read_to_string(paths::resolve(path))?;
+                if !content.contains(old_text) {
+                    return Err(anyhow::anyhow!("Could not find the specified text to replace in {}", path));
+                }
+                let edited = content.replace(old_text, new_text);
+                Ok(format!(
+                    "{}\nWould edit {} ({} occurrences):\n{}",
+                    note,
+                    path,
+                    content.matches(old_text).count(),
+                    crate::patch::unified(path, &content, &edited)
+                ))
+            }
+            "rename_symbol" => lsp::rename_symbol(input, &self.config.lsp, false),
+            "execute_command" | "run_background" => {
+                let command = text("command")?;
+                let dir = input.get("working_dir").and_then(|w| w.as_str()).unwrap_or(".");
+                Ok(format!("{}\nWould run in {}:\n$ {}\nIts output is unknown.", note, dir, command))
+            }
+            name => Ok(format!("{}\nWould call {} with {}", note, name, input)),
+        }
+    }
+
+    fn execute_command(&self, input: &Value) -> Result<String> {
         let command = input
             .get("command")
             .and_then(|c| c.as_str())
@@ -63,26 +343,24 @@ impl ToolExecutor {
         let working_dir = input
             .get("working_dir")
             .and_then(|w| w.as_str())
-            .map(PathBuf::from);
-
-        let output = if cfg!(windows) {
-            let mut cmd = Command::new("cmd");
-            cmd.args(["/C", command]);
-            if let Some(dir) = working_dir {
-                cmd.current_dir(dir);
-            }
-            cmd.output()?
-        } else {
-            let mut cmd = Command::new("sh");
-            cmd.args(["-c", command]);
-            if let Some(dir) = working_dir {
-                cmd.current_dir(dir);
-            }
-            cmd.output()?
-        };
-
-        let stdout = String::from_utf8_lossy(&output.stdout);
-        let stderr = String::from_utf8_lossy(&output.stderr);
+            .map(paths::resolve);
+
+        // The model may ask for less time than the configured limit, never more
+        let limit = self.config.command_timeout_secs;
+        let timeout_secs = input
+            .get("timeout_secs")
+            .and_then(|t| t.as_u64())
+            .filter(|t| *t > 0)
+            .map(|t| if limit > 0 { t.min(limit) } else { t })
+            .unwrap_or(limit);
+
+        let mut cmd = shell_command(command, &self.config);
+        apply_call_env(&mut cmd, input);
+        if let Some(dir) = working_dir {
+            cmd.current_dir(dir);
+        }
+        let (status, stdout, stderr) = run_with_timeout(cmd, timeout_secs)?;
+        let timed_out = status.is_none();
 
         let mut result = String::new();
         if !stdout.is_empty() {
@@ -90,14 +368,23 @@ impl ToolExecutor {
         }
         if !stderr.is_empty() {
             if !result.is_empty() {
-                result.push_str("\n");
+                result.push('\n');
             }
             result.push_str("[stderr]\n");
             result.push_str(&stderr);
         }
 
-        if result.is_empty() {
-            result = format!("Command completed with exit code: {}", output.status.code().unwrap_or(-1));
+        if timed_out {
+            if !result.is_empty() {
+                result.push('\n');
+            }
+            result.push_str(&format!(
+                "[timed out after {}s - process killed, output above is partial. Use run_background for long-running commands]",
+                timeout_secs
+            ));
+        } else if result.is_empty() {
+            let code = status.and_then(|s| s.code()).unwrap_or(-1);
+            result = format!("Command completed with exit code: {}", code);
         }
 
         Ok(result)
@@ -109,13 +396,16 @@ impl ToolExecutor {
             .and_then(|p| p.as_str())
             .ok_or_else(|| anyhow::anyhow!("Missing 'path' parameter"))?;
 
-        let content = std::fs::read_to_string(path)?;
+        // Redacted before the line numbers go in, which the .env rules would not expect
+        let content = redact::redact_file(path, &std::fs::read_to_string(paths::resolve(path))?);
 
-        // Add line numbers
+        // Add line numbers; only the separator is ours to make ASCII, the
+        // text has to stay as it is for edit_file to match it
+        let separator = crate::ui::ascii("│");
         let numbered: String = content
             .lines()
             .enumerate()
-            .map(|(i, line)| format!("{:4} │ {}", i + 1, line))
+            .map(|(i, line)| format!("{:4} {} {}", i + 1, separator, line))
             .collect::<Vec<_>>()
             .join("\n");
 
@@ -133,12 +423,15 @@ impl ToolExecutor {
             .and_then(|c| c.as_str())
             .ok_or_else(|| anyhow::anyhow!("Missing 'content' parameter"))?;
 
+        let target = paths::resolve(path);
+
+        crate::snapshot::before_write(&target)?;
         // Create parent directories if needed
-        if let Some(parent) = Path::new(path).parent() {
+        if let Some(parent) = target.parent() {
             std::fs::create_dir_all(parent)?;
         }
 
-        std::fs::write(path, content)?;
+        std::fs::write(&target, content)?;
 
         Ok(format!("Successfully wrote {} bytes to {}", content.len(), path))
     }
@@ -159,7 +452,8 @@ impl ToolExecutor {
             .and_then(|n| n.as_str())
             .ok_or_else(|| anyhow::anyhow!("Missing 'new_text' parameter"))?;
 
-        let content = std::fs::read_to_string(path)?;
+        let target = paths::resolve(path);
+        let content = std::fs::read_to_string(&target)?;
 
         if !content.contains(old_text) {
             return Err(anyhow::anyhow!(
@@ -169,7 +463,8 @@ impl ToolExecutor {
         }
 
         let new_content = content.replace(old_text, new_text);
-        std::fs::write(path, &new_content)?;
+        crate::snapshot::before_write(&target)?;
+        std::fs::write(&target, &new_content)?;
 
         Ok(format!(
             "Successfully edited {}. Replaced {} occurrences.",
@@ -184,7 +479,7 @@ impl ToolExecutor {
             .and_then(|p| p.as_str())
             .unwrap_or(".");
 
-        let entries = std::fs::read_dir(path)?;
+        let entries = std::fs::read_dir(paths::resolve(path))?;
 
         let mut dirs = Vec::new();
         let mut files = Vec::new();
@@ -195,17 +490,9 @@ impl ToolExecutor {
             let name = entry.file_name().to_string_lossy().to_string();
 
             if metadata.is_dir() {
-                dirs.push(format!("📁 {}/", name));
+                dirs.push(format!("{} {}/", crate::ui::ascii("📁"), name));
             } else {
-                let size = metadata.len();
-                let size_str = if size < 1024 {
-                    format!("{} B", size)
-                } else if size < 1024 * 1024 {
-                    format!("{:.1} KB", size as f64 / 1024.0)
-                } else {
-                    format!("{:.1} MB", size as f64 / (1024.0 * 1024.0))
-                };
-                files.push(format!("📄 {} ({})", name, size_str));
+                files.push(format!("{} {} ({})", crate::ui::ascii("📄"), name, text::format_size(metadata.len())));
             }
         }
 
@@ -225,6 +512,92 @@ impl ToolExecutor {
         Ok(result)
     }
 
+    fn create_directory(input: &Value) -> Result<String> {
+        let path = input
+            .get("path")
+            .and_then(|p| p.as_str())
+            .ok_or_else(|| anyhow::anyhow!("Missing 'path' parameter"))?;
+
+        let target = paths::resolve(path);
+        if target.is_dir() {
+            return Ok(format!("Directory already exists: {}", path));
+        }
+
+        std::fs::create_dir_all(&target)?;
+
+        Ok(format!("Created directory {}", path))
+    }
+
+    fn stat_file(input: &Value) -> Result<String> {
+        let path = input
+            .get("path")
+            .and_then(|p| p.as_str())
+            .ok_or_else(|| anyhow::anyhow!("Missing 'path' parameter"))?;
+
+        let target = paths::resolve(path);
+        let metadata = std::fs::metadata(&target)?;
+        let is_symlink = std::fs::symlink_metadata(&target)
+            .map(|m| m.file_type().is_symlink())
+            .unwrap_or(false);
+
+        let kind = if is_symlink {
+            "symlink"
+        } else if metadata.is_dir() {
+            "directory"
+        } else {
+            "file"
+        };
+
+        let modified = metadata
+            .modified()
+            .map(|m| chrono::DateTime::<chrono::Local>::from(m).format("%Y-%m-%d %H:%M:%S").to_string())
+            .unwrap_or_else(|_| "unknown".to_string());
+
+        let mut result = format!("Path: {}\nType: {}\n", path, kind);
+        result.push_str(&format!("Size: {} bytes\n", metadata.len()));
+        result.push_str(&format!("Modified: {}\n", modified));
+        result.push_str(&format!("Permissions: {}\n", Self::format_permissions(&metadata)));
+
+        if metadata.is_file() {
+            // Binary files fail UTF-8 decoding, which is exactly what we want to report
+            match std::fs::read_to_string(&target) {
+                Ok(content) => result.push_str(&format!("Lines: {}\n", content.lines().count())),
+                Err(_) => result.push_str("Lines: n/a (binary)\n"),
+            }
+            if let Some(lang) = detect_language(&target) {
+                result.push_str(&format!("Language: {}\n", lang));
+            }
+        }
+
+        Ok(result)
+    }
+
+    #[cfg(unix)]
+    fn format_permissions(metadata: &std::fs::Metadata) -> String {
+        use std::os::unix::fs::PermissionsExt;
+
+        let mode = metadata.permissions().mode();
+        let flags: String = [
+            (0o400, 'r'), (0o200, 'w'), (0o100, 'x'),
+            (0o040, 'r'), (0o020, 'w'), (0o010, 'x'),
+            (0o004, 'r'), (0o002, 'w'), (0o001, 'x'),
+        ]
+        .iter()
+        .map(|(bit, c)| if mode & bit != 0 { *c } else { '-' })
+        .collect();
+
+        format!("{} ({:o})", flags, mode & 0o777)
+    }
+
+    #[cfg(not(unix))]
+    fn format_permissions(metadata: &std::fs::Metadata) -> String {
+        if metadata.permissions().readonly() {
+            "read-only".to_string()
+        } else {
+            "read-write".to_string()
+        }
+    }
+
     fn search_files(input: &Value) -> Result<String> {
         let pattern = input
             .get("pattern")
@@ -237,7 +610,7 @@ impl ToolExecutor {
             .unwrap_or(".");
 
         let mut matches = Vec::new();
-        Self::search_files_recursive(Path::new(base_path), pattern, &mut matches)?;
+        Self::search_files_recursive(&paths::resolve(base_path), pattern, &mut matches)?;
 
         if matches.is_empty() {
             Ok(format!("No files matching '{}' found in {}", pattern, base_path))
@@ -271,7 +644,7 @@ impl ToolExecutor {
             } else {
                 let file_name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
                 if glob_pattern.matches(file_name) {
-                    matches.push(path.display().to_string());
+                    matches.push(paths::display(&path));
                 }
             }
         }
@@ -298,7 +671,7 @@ impl ToolExecutor {
         let mut results = Vec::new();
 
         Self::search_content_recursive(
-            Path::new(base_path),
+            &paths::resolve(base_path),
             &regex,
             file_pattern,
             &mut results,
@@ -321,7 +694,7 @@ impl ToolExecutor {
             return Ok(());
         }
 
-        let glob_pattern = file_pattern.map(|p| glob::Pattern::new(p).ok()).flatten();
+        let glob_pattern = file_pattern.and_then(|p| glob::Pattern::new(p).ok());
 
         for entry in std::fs::read_dir(dir)? {
             let entry = entry?;
@@ -348,7 +721,7 @@ impl ToolExecutor {
                         if regex.is_match(line) {
                             results.push(format!(
                                 "{}:{}: {}",
-                                path.display(),
+                                paths::display(&path),
                                 line_num + 1,
                                 line.trim()
                             ));
@@ -361,3 +734,49 @@ impl ToolExecutor {
         Ok(())
     }
 }
+
+/// Guess a file's language from its extension (or well-known file name)
+pub fn detect_language(path: &Path) -> Option<&'static str> {
+    let file_name = path.file_name()?.to_str()?.to_lowercase();
+    match file_name.as_str() {
+        "dockerfile" => return Some("dockerfile"),
+        "makefile" => return Some("makefile"),
+        "cargo.toml" | "pyproject.toml" => return Some("toml"),
+        _ => {}
+    }
+
+    let ext = path.extension()?.to_str()?.to_lowercase();
+    let lang = match ext.as_str() {
+        "rs" => "rust",
+        "ts" | "mts" | "cts" => "typescript",
+        "tsx" => "tsx",
+        "js" | "mjs" | "cjs" => "javascript",
+        "jsx" => "jsx",
+        "py" | "pyi" => "python",
+        "java" => "java",
+        "kt" | "kts" => "kotlin",
+        "go" => "go",
+        "php" => "php",
+        "rb" => "ruby",
+        "c" | "h" => "c",
+        "cpp" | "cc" | "cxx" | "hpp" | "hh" => "cpp",
+        "cs" => "csharp",
+        "swift" => "swift",
+        "sql" => "sql",
+        "sh" | "bash" | "zsh" => "bash",
+        "ps1" | "psm1" => "powershell",
+        "html" | "htm" => "html",
+        "css" => "css",
+        "scss" | "sass" => "scss",
+        "vue" => "vue",
+        "json" => "json",
+        "yaml" | "yml" => "yaml",
+        "toml" => "toml",
+        "xml" => "xml",
+        "md" | "markdown" => "markdown",
+        "properties" => "properties",
+        "gradle" => "gradle",
+        _ => return None,
+    };
+    Some(lang)
+}
@@ -1,18 +1,134 @@
 use crossterm::{
     cursor,
+    event::{self, Event, KeyCode, KeyEventKind, KeyModifiers, MouseButton, MouseEventKind},
     execute,
     terminal::{self, ClearType},
 };
-use std::io::{self, Write};
+use std::borrow::Cow;
+use std::io::{self, IsTerminal, Write};
 use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use crate::bench::Comparison;
+use crate::client::{RequestTiming, TokenUsage};
+use crate::codeblocks;
+use crate::console;
+use crate::config::{CodeTheme, UiConfig};
 use crate::i18n::{Language, Strings};
+use crate::text;
+use crate::snapshot::{ChangeStatus, FileChange};
+use unicode_width::UnicodeWidthStr;
+
+static ASCII_ONLY: AtomicBool = AtomicBool::new(false);
+
+/// `ascii_only` / `--ascii`: box drawing, spinners and emoji become plain
+/// ASCII, for terminals and locales that show them as mojibake
+pub fn set_ascii_only(enabled: bool) {
+    ASCII_ONLY.store(enabled, Ordering::Relaxed);
+}
+
+static PLAIN: AtomicBool = AtomicBool::new(false);
+
+/// Plain mode, for when stdin or stdout isn't a terminal
+/// (`aicli < questions.txt > answers.md`): no colors, animations or raw mode,
+/// and only the answers on stdout; everything else goes to stderr
+pub fn set_plain(enabled: bool) {
+    PLAIN.store(enabled, Ordering::Relaxed);
+}
+
+pub fn is_plain() -> bool {
+    PLAIN.load(Ordering::Relaxed)
+}
+
+/// Everything this file prints goes through `emit`
+macro_rules! print {
+    ($($arg:tt)*) => { emit(&format!($($arg)*), false) };
+}
+
+macro_rules! println {
+    () => { emit("", true) };
+    ($($arg:tt)*) => { emit(&format!($($arg)*), true) };
+}
+
+/// `text` with `ascii_only` and the console's limits applied
+pub fn for_console(text: &str) -> Cow<'_, str> {
+    match ascii(text) {
+        Cow::Borrowed(text) => console::adapt(text),
+        Cow::Owned(text) => Cow::Owned(console::adapt(&text).into_owned()),
+    }
+}
+
+/// Print `text` to stdout, or in plain mode without colors to stderr
+fn emit(text: &str, newline: bool) {
+    let text = for_console(text);
+    if !is_plain() {
+        if newline {
+            std::println!("{}", text);
+        } else {
+            std::print!("{}", text);
+        }
+        return;
+    }
+    let text = text::strip_ansi(&text);
+    if newline {
+        eprintln!("{}", text);
+    } else {
+        eprint!("{}", text);
+    }
+}
+
+/// `text` with the glyphs aicli draws replaced by ASCII when `ascii_only`
+/// is on. Letters, including accented and CJK ones, are kept.
+pub fn ascii(text: &str) -> Cow<'_, str> {
+    if !ASCII_ONLY.load(Ordering::Relaxed) || text.is_ascii() {
+        return Cow::Borrowed(text);
+    }
+    const DOTS: [char; 10] = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+    const BLOCKS: [char; 8] = ['⣾', '⣽', '⣻', '⢿', '⡿', '⣟', '⣯', '⣷'];
+    const TURNS: [&str; 4] = ["|", "/", "-", "\\"];
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        let replacement = match c {
+            '─' | '━' | '═' | '┄' => "-",
+            '│' | '┃' | '║' | '┆' => "|",
+            '┌' | '┐' | '└' | '┘' | '├' | '┤' | '┬' | '┴' | '┼' | '╭' | '╮' | '╰' | '╯' => "+",
+            '●' | '•' | '✎' => "*",
+            '○' => "o",
+            '·' => "-",
+            '✓' | '✔' => "ok",
+            '✗' | '✘' => "x",
+            'ℹ' => "i",
+            '⚡' | '❯' | '›' | '▸' | '→' => ">",
+            '⇄' => "<>",
+            '↓' => "v",
+            '⏱' => "t",
+            '⧗' => "ctx",
+            '⟳' => "~",
+            '≈' => "~",
+            '…' => "...",
+            '📁' => "[dir]",
+            '📄' => "[file]",
+            '\u{2580}'..='\u{259F}' => "#",
+            '\u{2500}'..='\u{257F}' => "+",
+            '\u{2800}'..='\u{28FF}' => {
+                let frame = DOTS.iter().position(|d| *d == c).or_else(|| BLOCKS.iter().position(|b| *b == c));
+                TURNS[frame.unwrap_or(0) % TURNS.len()]
+            }
+            // Emoji, and the variation selector that follows some of them
+            '\u{1F000}'..='\u{1FAFF}' | '\u{2600}'..='\u{27BF}' | '\u{FE0F}' => "",
+            _ => {
+                out.push(c);
+                continue;
+            }
+        };
+        out.push_str(replacement);
+    }
+    Cow::Owned(out)
+}
 
 const GITHUB_URL: &str = "https://github.com/leonardo-matheus";
 const VERSION: &str = "1.0.0";
 
 // Dracula theme colors
-const DRACULA_BG: &str = "236";      // #282a36
-const DRACULA_FG: &str = "255";      // #f8f8f2
 const DRACULA_CYAN: &str = "117";    // #8be9fd
 const DRACULA_GREEN: &str = "84";    // #50fa7b
 const DRACULA_ORANGE: &str = "215";  // #ffb86c
@@ -22,42 +138,226 @@ const DRACULA_RED: &str = "203";     // #ff5555
 const DRACULA_YELLOW: &str = "228";  // #f1fa8c
 const DRACULA_COMMENT: &str = "103"; // #6272a4
 
+/// 256-color codes used by the code block highlighter
+struct CodePalette {
+    keyword: &'static str,
+    string: &'static str,
+    comment: &'static str,
+}
+
+/// Escape sequence for one line of a unified diff
+/// "first token 0.8s · total 5.2s · 412 tokens · 78 tok/s" for a turn's
+/// requests; tokens/sec counts from each request's first token
+pub fn turn_stats(timings: &[RequestTiming]) -> Option<String> {
+    if timings.is_empty() {
+        return None;
+    }
+    let total: f64 = timings.iter().map(|t| t.total.as_secs_f64()).sum();
+    let tokens: usize = timings.iter().map(|t| t.output_tokens).sum();
+    let generating: f64 = timings.iter().map(|t| (t.total - t.first_token.unwrap_or_default()).as_secs_f64()).sum();
+    // Requests that only call tools stream no text
+    let mut stats = match timings.iter().find_map(|t| t.first_token) {
+        Some(ttft) => format!("first token {:.1}s · ", ttft.as_secs_f64()),
+        None => String::new(),
+    };
+    stats.push_str(&format!("total {:.1}s · {} tokens", total, tokens));
+    if generating > 0.0 && tokens > 0 {
+        stats.push_str(&format!(" · {:.0} tok/s", tokens as f64 / generating));
+    }
+    if timings.len() > 1 {
+        stats.push_str(&format!(" · {} requests", timings.len()));
+    }
+    Some(stats)
+}
+
+fn diff_line_color(line: &str) -> String {
+    if line.starts_with("+++") || line.starts_with("---") || line.starts_with("diff ") || line.starts_with("index ") {
+        "\x1b[1m".to_string()
+    } else if line.starts_with("@@") {
+        format!("\x1b[38;5;{}m", DRACULA_CYAN)
+    } else if line.starts_with('+') {
+        format!("\x1b[38;5;{}m", DRACULA_GREEN)
+    } else if line.starts_with('-') {
+        format!("\x1b[38;5;{}m", DRACULA_RED)
+    } else {
+        String::new()
+    }
+}
+
+fn code_palette(theme: CodeTheme) -> Option<CodePalette> {
+    let palette = match theme {
+        CodeTheme::Dracula => CodePalette { keyword: DRACULA_PINK, string: DRACULA_GREEN, comment: DRACULA_COMMENT },
+        CodeTheme::Monokai => CodePalette { keyword: "197", string: "186", comment: "242" },
+        CodeTheme::SolarizedDark => CodePalette { keyword: "64", string: "37", comment: "241" },
+        CodeTheme::GithubLight => CodePalette { keyword: "161", string: "24", comment: "245" },
+        CodeTheme::Plain => return None,
+    };
+    Some(palette)
+}
+
+/// A line of a menu: a group's name or one of its entries
+enum MenuRow {
+    Header(String),
+    Item(usize),
+}
+
+/// The lines showing `shown`, with a header where a group starts. An entry
+/// without a group after a grouped one gets an empty header to set it apart.
+fn menu_rows(items: &[MenuItem], shown: &[usize]) -> Vec<MenuRow> {
+    let mut rows = Vec::new();
+    let mut group: Option<&str> = None;
+    for (n, &i) in shown.iter().enumerate() {
+        let this = items[i].group.as_deref();
+        if n == 0 && this.is_some() || n > 0 && this != group {
+            rows.push(MenuRow::Header(this.unwrap_or_default().to_string()));
+        }
+        group = this;
+        rows.push(MenuRow::Item(i));
+    }
+    rows
+}
+
+fn item_at(rows: &[MenuRow], row: usize) -> Option<usize> {
+    match rows.get(row)? {
+        MenuRow::Item(i) => Some(*i),
+        MenuRow::Header(_) => None,
+    }
+}
+
+/// The entries matching `query`, in their order
+fn filter_items(items: &[MenuItem], query: &str) -> Vec<usize> {
+    let words: Vec<String> = query.to_lowercase().split_whitespace().map(str::to_string).collect();
+    (0..items.len()).filter(|&i| items[i].matches(&words)).collect()
+}
+
+/// How often the sticky status is redrawn while an answer streams
+const STATUS_REFRESH: std::time::Duration = std::time::Duration::from_millis(250);
+
+/// Rows a menu takes besides its entries: title, "more above" and the hint
+const MENU_CHROME: usize = 3;
+/// Terminal columns from which /compare shows its answers side by side
+const COMPARE_COLUMNS_WIDTH: usize = 120;
+/// Rows of a /retry or /compare comparison before it is cut off
+const SIDE_BY_SIDE_ROWS: usize = 60;
+/// Entries one wheel notch scrolls a menu by
+const MENU_WHEEL_STEP: usize = 3;
+
+/// One entry of a menu picked with `UI::pick`
+#[derive(Clone)]
+pub struct MenuItem {
+    pub label: String,
+    /// Shown dimmed after the label, and left out when the line is too narrow
+    pub detail: String,
+    /// The model, language or session in use, marked with a dot
+    pub current: bool,
+    /// Entries of a group follow each other under its name
+    pub group: Option<String>,
+}
+
+impl MenuItem {
+    pub fn new(label: impl Into<String>, detail: impl Into<String>, current: bool) -> Self {
+        MenuItem { label: label.into(), detail: detail.into(), current, group: None }
+    }
+
+    pub fn in_group(mut self, group: impl Into<String>) -> Self {
+        self.group = Some(group.into());
+        self
+    }
+
+    /// Every word of the filter appears in the label, detail or group, or
+    /// its letters appear in that order in the label (`cpc` finds /copy-code)
+    fn matches(&self, words: &[String]) -> bool {
+        let haystack = format!("{} {} {}", self.label, self.detail, self.group.as_deref().unwrap_or("")).to_lowercase();
+        let label = self.label.to_lowercase();
+        words.iter().all(|word| {
+            let mut letters = label.chars();
+            haystack.contains(word.as_str()) || word.chars().all(|c| letters.any(|l| l == c))
+        })
+    }
+
+    /// `marked`: the menu has a current entry, and every entry gets a dot
+    fn render(&self, selected: bool, width: usize, marked: bool) -> String {
+        let marker = match (marked, self.current) {
+            (false, _) => "",
+            (true, true) => "\x1b[38;5;82m●\x1b[0m ",
+            (true, false) => "\x1b[38;5;240m○\x1b[0m ",
+        };
+        let style = if selected { "\x1b[1;38;5;117m" } else if self.current { "\x1b[1;38;5;220m" } else { "" };
+        let label = text::truncate(&self.label, width.saturating_sub(2));
+        let room = width.saturating_sub(label.width() + 3);
+        let detail = if self.detail.is_empty() || room < 8 {
+            String::new()
+        } else {
+            format!(" \x1b[38;5;245m{}\x1b[0m", text::truncate(&self.detail, room))
+        };
+        format!("{}{}{}\x1b[0m{}", marker, style, label, detail)
+    }
+}
+
 pub struct UI {
     pub strings: Strings,
-    pub term_width: usize,
     pub context_used: usize,
     pub context_max: usize,
     pub current_model: String,
     pub current_model_type: String,
     pub current_path: String,
+    /// Router decision for the last turn, shown in the status bar
+    pub route: Option<String>,
     in_code_block: std::cell::Cell<bool>,
+    /// Width the open code block was drawn with, so its bottom border matches
+    code_width: std::cell::Cell<usize>,
     code_buffer: std::cell::RefCell<String>,
     code_lang: std::cell::RefCell<String>,
+    code_theme: CodeTheme,
+    line_numbers: bool,
+    /// Terminal height the sticky status line's scroll region was set for,
+    /// 0 while the status is printed after each turn instead
+    sticky_rows: std::cell::Cell<u16>,
+    /// Answer text streamed this turn, shown while it streams
+    streamed_chars: std::cell::Cell<usize>,
+    status_drawn: std::cell::Cell<Option<std::time::Instant>>,
 }
 
 impl UI {
     pub fn new(lang: Language) -> Self {
-        let term_width = terminal::size().map(|(w, _)| w as usize).unwrap_or(120);
         Self {
             strings: Strings::new(lang),
-            term_width: term_width.max(70),
             context_used: 0,
             context_max: 128000,
             current_model: String::new(),
             current_model_type: String::new(),
             current_path: String::new(),
+            route: None,
             in_code_block: std::cell::Cell::new(false),
+            code_width: std::cell::Cell::new(80),
             code_buffer: std::cell::RefCell::new(String::new()),
             code_lang: std::cell::RefCell::new(String::new()),
+            code_theme: CodeTheme::default(),
+            line_numbers: false,
+            sticky_rows: std::cell::Cell::new(0),
+            streamed_chars: std::cell::Cell::new(0),
+            status_drawn: std::cell::Cell::new(None),
         }
     }
 
+    /// Current terminal width. Queried on every render (a cheap ioctl) so
+    /// borders and separators follow window resizes.
+    pub fn term_width(&self) -> usize {
+        terminal::size().map(|(w, _)| w as usize).unwrap_or(120).max(40)
+    }
+
+    pub fn set_code_style(&mut self, config: &UiConfig) {
+        self.code_theme = config.code_theme;
+        self.line_numbers = config.line_numbers;
+    }
+
     pub fn set_context_max(&mut self, max: usize) {
         self.context_max = max;
     }
 
     pub fn update_context(&mut self, used: usize) {
         self.context_used = used;
+        self.refresh_status();
     }
 
     pub fn get_context_percent(&self) -> usize {
@@ -73,6 +373,7 @@ impl UI {
         self.current_model = model.to_string();
         self.current_model_type = model_type.to_string();
         self.current_path = path.to_string();
+        self.refresh_status();
     }
 
     fn hyperlink(text: &str, url: &str) -> String {
@@ -81,6 +382,9 @@ impl UI {
 
     /// Startup animation - simple fade in effect
     pub fn play_startup_animation(&self) {
+        if is_plain() {
+            return;
+        }
         let cat_frames = [
             vec![""],
             vec!["  /\\_/\\  "],
@@ -136,68 +440,132 @@ impl UI {
         println!();
     }
 
-    fn visible_len(s: &str) -> usize {
-        // Remove ANSI escape codes to get visible length
-        let mut len = 0;
-        let mut in_escape = false;
-        for c in s.chars() {
-            if c == '\x1b' {
-                in_escape = true;
-            } else if in_escape {
-                if c == 'm' || c == '\\' {
-                    in_escape = false;
-                }
-            } else {
-                len += 1;
-            }
-        }
-        len
-    }
-
     fn truncate_path(path: &str, max_len: usize) -> String {
-        if path.len() <= max_len {
-            path.to_string()
-        } else {
-            format!("...{}", &path[path.len() - max_len + 3..])
-        }
-    }
-
-    pub fn print_welcome_message(&self) {
-        println!("  \x1b[1;37mWelcome to AICLI\x1b[0m");
-        println!();
+        text::truncate_start(path, max_len)
     }
 
     pub fn print_separator(&self) {
-        println!("\x1b[38;5;240m{}\x1b[0m", "─".repeat(self.term_width));
+        println!("\x1b[38;5;240m{}\x1b[0m", "─".repeat(self.term_width()));
     }
 
-    pub fn print_input_hint(&self) {
-        // No need for extra hints - the status bar at bottom will show info
+    pub fn print_status_bar(&self) {
+        println!();
+        println!("\x1b[38;5;{}m{}\x1b[0m", DRACULA_COMMENT, "─".repeat(self.term_width().min(57)));
+        println!("{}", self.status_line(self.term_width(), false));
     }
 
-    pub fn print_status_bar(&self) {
-        let ctx_k = self.context_used / 1000;
+    /// Model, route, context use and, on the sticky line, the path and the
+    /// answer streaming in. Parts are left out until it fits in `width`, as a
+    /// last row that wraps would scroll the screen.
+    fn status_line(&self, width: usize, sticky: bool) -> String {
         let ctx_percent = self.get_context_percent();
         let ctx_color = if ctx_percent > 80 { DRACULA_RED } else if ctx_percent > 50 { DRACULA_ORANGE } else { DRACULA_GREEN };
+        let model = text::truncate(&self.current_model, 20);
+        let context = format!("{}k ({}%)", self.context_used / 1000, ctx_percent);
 
-        let model_display = if self.current_model.len() > 20 {
-            format!("{}...", &self.current_model[..17])
+        // (plain text, colored) for each part after the model
+        let mut parts: Vec<(String, String)> = Vec::new();
+        if let Some(route) = &self.route {
+            parts.push((format!(" ⇄ {}", route), format!(" \x1b[38;5;{}m⇄ {}\x1b[0m", DRACULA_PURPLE, route)));
+        }
+        parts.push((
+            format!("  ⧗ {}", context),
+            format!("  \x1b[38;5;{}m⧗\x1b[0m \x1b[38;5;{}m{}\x1b[0m", DRACULA_COMMENT, ctx_color, context),
+        ));
+        let streamed = self.streamed_chars.get();
+        if sticky && streamed > 0 {
+            let tokens = format!("  ↓ ~{} tokens", streamed / 4);
+            parts.push((tokens.clone(), format!("\x1b[38;5;{}m{}\x1b[0m", DRACULA_CYAN, tokens)));
+        }
+        if sticky && !self.current_path.is_empty() {
+            let path = text::truncate_start(&self.current_path, 40);
+            parts.push((format!("  {}", path), format!("  \x1b[38;5;{}m{}\x1b[0m", DRACULA_COMMENT, path)));
+        }
+        parts.push(("  /help".to_string(), format!("  \x1b[38;5;{}m/help\x1b[0m", DRACULA_COMMENT)));
+
+        let mut used = text::width(&model) + 2;
+        let mut line = format!("\x1b[38;5;{}m●\x1b[0m \x1b[38;5;{}m{}\x1b[0m", DRACULA_GREEN, DRACULA_YELLOW, model);
+        for (plain, colored) in parts {
+            used += text::width(&ascii(&plain));
+            if used >= width {
+                break;
+            }
+            line.push_str(&colored);
+        }
+        line
+    }
+
+    /// Keep the status on the last row: the rows above become a scroll region
+    /// (DECSTBM) and the status is redrawn in place. Needs a terminal on both
+    /// ends; otherwise the status keeps being printed after each turn.
+    pub fn set_sticky_status(&self, enabled: bool) {
+        let terminal = io::stdout().is_terminal() && io::stdin().is_terminal()
+            && std::env::var("TERM").map_or(true, |t| t != "dumb")
+            && console::level() != console::Level::None;
+        if enabled && terminal {
+            if self.sticky_rows.get() == 0 {
+                self.reserve_last_row();
+            }
         } else {
-            self.current_model.clone()
+            self.release_status();
+        }
+    }
+
+    fn reserve_last_row(&self) {
+        let Ok((_, rows)) = terminal::size() else {
+            return;
         };
+        if rows < 5 {
+            return;
+        }
+        // Scroll by a line first so the cursor isn't left on the reserved row;
+        // setting the region moves the cursor, hence the save and restore
+        let mut out = io::stdout().lock();
+        let _ = write!(out, "\n\x1b[1A\x1b7\x1b[1;{}r\x1b8", rows - 1);
+        let _ = out.flush();
+        self.sticky_rows.set(rows);
+        drop(out);
+        self.refresh_status();
+    }
 
-        // Compact status line
-        println!();
-        println!("\x1b[38;5;{}m─────────────────────────────────────────────────────────\x1b[0m", DRACULA_COMMENT);
-        print!("\x1b[38;5;{}m●\x1b[0m \x1b[38;5;{}m{}\x1b[0m",
-            DRACULA_GREEN, DRACULA_YELLOW, model_display);
-        print!("  \x1b[38;5;{}m⧗\x1b[0m \x1b[38;5;{}m{}k ({}%)\x1b[0m", DRACULA_COMMENT, ctx_color, ctx_k, ctx_percent);
-        println!("  \x1b[38;5;{}m/help\x1b[0m", DRACULA_COMMENT);
+    /// Redraw the sticky status line, following terminal resizes
+    pub fn refresh_status(&self) {
+        let rows = self.sticky_rows.get();
+        if rows == 0 {
+            return;
+        }
+        let Ok((columns, now)) = terminal::size() else {
+            return;
+        };
+        if now != rows {
+            self.sticky_rows.set(0);
+            self.reserve_last_row();
+            return;
+        }
+        let line = self.status_line(columns as usize, true);
+        let mut out = io::stdout().lock();
+        let _ = write!(out, "\x1b7\x1b[{};1H\x1b[2K{}\x1b8", rows, for_console(&line));
+        let _ = out.flush();
+        self.status_drawn.set(Some(std::time::Instant::now()));
+    }
+
+    /// Give the whole screen back, on exit or when turned off
+    pub fn release_status(&self) {
+        let rows = self.sticky_rows.replace(0);
+        if rows == 0 {
+            return;
+        }
+        let mut out = io::stdout().lock();
+        let _ = write!(out, "\x1b7\x1b[r\x1b[{};1H\x1b[2K\x1b8", rows);
+        let _ = out.flush();
     }
 
     /// Get the input prompt string
     pub fn get_prompt(&self) -> String {
-        "❯ ".to_string()
+        if is_plain() {
+            return String::new();
+        }
+        ascii("❯ ").into_owned()
     }
 
     /// Print a newline before input prompt
@@ -206,16 +574,6 @@ impl UI {
         io::stdout().flush().unwrap();
     }
 
-    /// Close the input box after reading input (no-op with simple prompt)
-    pub fn close_input_box(&self, _input: &str) {
-        // Simple prompt doesn't need closing
-    }
-
-    /// Draw bottom status bar with shortcuts (only shown once after response)
-    pub fn draw_shortcuts_bar(&self) {
-        // Now handled in print_context_status, this is a no-op
-    }
-
     pub fn print_model_switch(&self, model: &str, model_type: &str) {
         println!();
         println!("\x1b[38;5;82m●\x1b[0m Switched to \x1b[1;38;5;220m{}\x1b[0m \x1b[38;5;245m({})\x1b[0m", model, model_type);
@@ -224,11 +582,14 @@ impl UI {
 
     pub fn print_lang_switch(&self, lang: &str) {
         println!();
-        println!("  \x1b[38;5;82m✓\x1b[0m Language changed to \x1b[38;5;220m{}\x1b[0m", lang);
+        println!("  \x1b[38;5;82m✓\x1b[0m {} \x1b[38;5;220m{}\x1b[0m", self.strings.language_changed(), lang);
         println!();
     }
 
     pub fn print_thinking(&self, frame: usize) {
+        if is_plain() {
+            return;
+        }
         let spinners = ["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
         let dots = ["", ".", "..", "..."];
         let s = spinners[frame % spinners.len()];
@@ -238,23 +599,6 @@ impl UI {
         io::stdout().flush().unwrap();
     }
 
-    pub fn print_working(&self, frame: usize, task: &str) {
-        let spinners = ["⣾", "⣽", "⣻", "⢿", "⡿", "⣟", "⣯", "⣷"];
-        let s = spinners[frame % spinners.len()];
-        print!("\r\x1b[K\x1b[38;5;{}m{}\x1b[0m \x1b[38;5;{}m{}\x1b[0m",
-            DRACULA_ORANGE, s, DRACULA_COMMENT, task);
-        io::stdout().flush().unwrap();
-    }
-
-    /// Animated typing effect for text
-    pub fn print_typing(&self, text: &str, delay_ms: u64) {
-        for c in text.chars() {
-            print!("{}", c);
-            io::stdout().flush().unwrap();
-            std::thread::sleep(std::time::Duration::from_millis(delay_ms));
-        }
-    }
-
     pub fn clear_line(&self) {
         print!("\r\x1b[K");
         io::stdout().flush().unwrap();
@@ -268,72 +612,80 @@ impl UI {
     }
 
     pub fn print_token(&self, token: &str) {
+        // The answer's Markdown as it is, on stdout
+        if is_plain() {
+            std::print!("{}", ascii(token));
+            let _ = io::stdout().flush();
+            return;
+        }
+        if self.sticky_rows.get() > 0 {
+            self.streamed_chars.set(self.streamed_chars.get() + token.len());
+            if self.status_drawn.get().is_none_or(|at| at.elapsed() > STATUS_REFRESH) {
+                self.refresh_status();
+            }
+        }
         let mut buffer = self.code_buffer.borrow_mut();
         buffer.push_str(token);
 
         // Process the buffer looking for code block markers
-        loop {
-            if let Some(pos) = buffer.find("```") {
-                // Print everything before the marker
-                let before = &buffer[..pos];
-                if !before.is_empty() {
-                    if self.in_code_block.get() {
-                        // Inside code block - we'll highlight when closing
-                    } else {
-                        // Regular text
-                        print!("{}", before.replace("\n", "\n  "));
-                    }
+        while let Some(pos) = buffer.find("```") {
+            // Print everything before the marker
+            let before = &buffer[..pos];
+            if !before.is_empty() {
+                if self.in_code_block.get() {
+                    // Inside code block - we'll highlight when closing
+                } else {
+                    // Regular text
+                    print!("{}", before.replace("\n", "\n  "));
                 }
+            }
 
-                // Toggle code block state
-                if self.in_code_block.get() {
-                    // End of code block - highlight accumulated code
-                    let code_content = before.to_string();
-                    let lang = self.code_lang.borrow().clone();
-
-                    // Print highlighted code
-                    let highlighted = self.highlight_code(&code_content, &lang);
-                    for (i, line) in highlighted.lines().enumerate() {
-                        if i > 0 {
-                            print!("\n");
-                        }
-                        print!("  \x1b[38;5;240m│\x1b[0m {}", line);
-                    }
+            // Toggle code block state
+            if self.in_code_block.get() {
+                // End of code block - highlight accumulated code
+                let code_content = before.to_string();
+                let lang = self.code_lang.borrow().clone();
+
+                // Print highlighted code
+                print!("{}", self.code_lines(&code_content, &lang).join("\n"));
 
-                    // Close the code block
-                    let w = self.term_width.min(80);
-                    print!("\n  \x1b[38;5;240m└{}\x1b[0m", "─".repeat(w - 4));
-                    self.in_code_block.set(false);
-                    self.code_lang.borrow_mut().clear();
+                // Close the code block
+                let w = self.code_width.get();
+                print!("\n  \x1b[38;5;240m└{}\x1b[0m", "─".repeat(w - 4));
+                self.in_code_block.set(false);
+                self.code_lang.borrow_mut().clear();
+            } else {
+                // Start of code block - find the language tag
+                let after_marker = &buffer[pos + 3..];
+                if let Some(newline_pos) = after_marker.find('\n') {
+                    let (lang, path) = codeblocks::parse_header(&after_marker[..newline_pos]);
+                    *self.code_lang.borrow_mut() = lang.clone();
+
+                    let lang_display = match path {
+                        Some(path) if lang.is_empty() => path,
+                        Some(path) => format!("{} · {}", lang, path),
+                        None if lang.is_empty() => "code".to_string(),
+                        None => lang,
+                    };
+                    let w = self.term_width().min(80);
+                    self.code_width.set(w);
+                    print!("\n  \x1b[38;5;240m┌─ {} {}\x1b[0m\n",
+                        lang_display,
+                        "─".repeat(w.saturating_sub(8 + text::width(&lang_display))));
+
+                    self.in_code_block.set(true);
+                    *buffer = after_marker[newline_pos + 1..].to_string();
+                    continue;
                 } else {
-                    // Start of code block - find the language tag
-                    let after_marker = &buffer[pos + 3..];
-                    if let Some(newline_pos) = after_marker.find('\n') {
-                        let lang = after_marker[..newline_pos].trim().to_string();
-                        *self.code_lang.borrow_mut() = lang.clone();
-
-                        let lang_display = if lang.is_empty() { "code".to_string() } else { lang };
-                        let w = self.term_width.min(80);
-                        print!("\n  \x1b[38;5;240m┌─ {} {}\x1b[0m\n",
-                            lang_display,
-                            "─".repeat(w.saturating_sub(8 + lang_display.len())));
-
-                        self.in_code_block.set(true);
-                        *buffer = after_marker[newline_pos + 1..].to_string();
-                        continue;
-                    } else {
-                        // No newline yet, wait for more tokens
-                        break;
-                    }
+                    // No newline yet, wait for more tokens
+                    break;
                 }
+            }
 
-                *buffer = buffer[pos + 3..].to_string();
-                // Remove any trailing newline after closing ```
-                if buffer.starts_with('\n') {
-                    *buffer = buffer[1..].to_string();
-                }
-            } else {
-                break;
+            *buffer = buffer[pos + 3..].to_string();
+            // Remove any trailing newline after closing ```
+            if buffer.starts_with('\n') {
+                *buffer = buffer[1..].to_string();
             }
         }
 
@@ -353,13 +705,70 @@ impl UI {
         self.code_lang.borrow_mut().clear();
     }
 
-    /// Highlight code with simple Dracula-like colors (no external themes)
+    /// Set up rendering to carry on after `answer`, which was cut off. When
+    /// it stopped inside a code block, the block's code so far is buffered
+    /// again, so the whole block is drawn once the continuation closes it.
+    pub fn resume_code_state(&self, answer: &str) {
+        self.reset_code_state();
+        let mut open: Option<(String, Vec<&str>)> = None;
+        for line in answer.lines() {
+            match (&mut open, line.trim_start().strip_prefix("```")) {
+                (Some(_), Some(_)) => open = None,
+                (None, Some(header)) => open = Some((header.to_string(), Vec::new())),
+                (Some((_, body)), None) => body.push(line),
+                (None, None) => {}
+            }
+        }
+        if let Some((header, body)) = open {
+            *self.code_lang.borrow_mut() = codeblocks::parse_header(&header).0;
+            self.code_width.set(self.term_width().min(80));
+            *self.code_buffer.borrow_mut() = body.join("\n");
+            self.in_code_block.set(true);
+        }
+    }
+
+    /// Code block body lines with the left border, optional line numbers and
+    /// highlighting. Long lines wrap to the terminal width; continuation rows
+    /// get a blank gutter.
+    fn code_lines(&self, code: &str, lang: &str) -> Vec<String> {
+        let code = code.strip_suffix('\n').unwrap_or(code);
+        let count = code.lines().count().max(1);
+        let number_width = if self.line_numbers { count.to_string().len() } else { 0 };
+        let gutter = if self.line_numbers { number_width + 3 } else { 2 };
+        let available = self.term_width().saturating_sub(2 + gutter).max(20);
+
+        let is_diff = matches!(lang, "diff" | "patch");
+        let mut lines = Vec::new();
+        for (i, line) in code.lines().enumerate() {
+            let expanded = line.replace('\t', "    ");
+            // Diff lines are colored as a whole so wrapped rows keep the color
+            let diff_color = is_diff.then(|| diff_line_color(line));
+            for (row, part) in text::wrap(&expanded, available).iter().enumerate() {
+                let border = match (self.line_numbers, row) {
+                    (false, _) => "│".to_string(),
+                    (true, 0) => format!("{:>w$} │", i + 1, w = number_width),
+                    (true, _) => format!("{:>w$} ┆", "", w = number_width),
+                };
+                let body = match &diff_color {
+                    Some(color) => format!("{}{}\x1b[0m", color, part),
+                    None => self.highlight_code(part, lang),
+                };
+                lines.push(format!("  \x1b[38;5;240m{}\x1b[0m {}", border, body));
+            }
+        }
+        lines
+    }
+
+    /// Highlight code with the configured palette (no external themes, so
+    /// there is nothing to load at startup)
     fn highlight_code(&self, code: &str, lang: &str) -> String {
-        // Simple syntax highlighting without syntect themes
+        let Some(palette) = code_palette(self.code_theme) else {
+            return code.trim_end_matches('\n').to_string();
+        };
         let mut result = String::new();
 
         for line in code.lines() {
-            let highlighted = self.highlight_line_simple(line, lang);
+            let highlighted = self.highlight_line_simple(line, lang, &palette);
             result.push_str(&highlighted);
             result.push('\n');
         }
@@ -373,12 +782,12 @@ impl UI {
     }
 
     /// Simple line highlighting based on patterns
-    fn highlight_line_simple(&self, line: &str, _lang: &str) -> String {
+    fn highlight_line_simple(&self, line: &str, _lang: &str, palette: &CodePalette) -> String {
         let trimmed = line.trim();
 
         // Comments (gray)
         if trimmed.starts_with("//") || trimmed.starts_with("#") || trimmed.starts_with("--") {
-            return format!("\x1b[38;5;103m{}\x1b[0m", line);
+            return format!("\x1b[38;5;{}m{}\x1b[0m", palette.comment, line);
         }
 
         // Empty line
@@ -396,7 +805,7 @@ impl UI {
                        "pub ", "private ", "public ", "async ", "await ", "match ", "case "];
         for kw in keywords {
             if result.contains(kw) {
-                result = result.replace(kw, &format!("\x1b[38;5;205m{}\x1b[0m", kw));
+                result = result.replace(kw, &format!("\x1b[38;5;{}m{}\x1b[0m", palette.keyword, kw));
             }
         }
 
@@ -404,13 +813,11 @@ impl UI {
         let mut in_string = false;
         let mut string_char = '"';
         let mut new_result = String::new();
-        let mut chars = result.chars().peekable();
-
-        while let Some(c) = chars.next() {
+        for c in result.chars() {
             if !in_string && (c == '"' || c == '\'') {
                 in_string = true;
                 string_char = c;
-                new_result.push_str("\x1b[38;5;84m");
+                new_result.push_str(&format!("\x1b[38;5;{}m", palette.string));
                 new_result.push(c);
             } else if in_string && c == string_char {
                 new_result.push(c);
@@ -428,109 +835,33 @@ impl UI {
         new_result
     }
 
-    /// Format complete response with syntax highlighting for code blocks
-    pub fn format_response(&self, content: &str) -> String {
-        let mut result = String::new();
-        let mut in_code_block = false;
-        let mut code_lang = String::new();
-        let mut code_buffer = String::new();
-
-        for line in content.lines() {
-            if line.starts_with("```") {
-                if in_code_block {
-                    // End of code block - render it
-                    result.push_str(&self.render_code_block(&code_lang, &code_buffer));
-                    code_buffer.clear();
-                    code_lang.clear();
-                    in_code_block = false;
-                } else {
-                    // Start of code block
-                    in_code_block = true;
-                    code_lang = line.trim_start_matches('`').to_string();
-                }
-            } else if in_code_block {
-                code_buffer.push_str(line);
-                code_buffer.push('\n');
-            } else {
-                // Regular text - apply inline formatting
-                result.push_str(&self.format_inline(line));
-                result.push('\n');
-            }
+    /// Plain mode: the question as a heading above its answer on stdout,
+    /// since piped input isn't echoed
+    pub fn print_question(&self, question: &str) {
+        if is_plain() {
+            std::println!("\n## {}\n", question);
         }
-
-        // Handle unclosed code block
-        if in_code_block && !code_buffer.is_empty() {
-            result.push_str(&self.render_code_block(&code_lang, &code_buffer));
-        }
-
-        result
     }
 
-    fn render_code_block(&self, lang: &str, code: &str) -> String {
-        let w = self.term_width.min(100);
-        let border = "─".repeat(w - 6);
-
-        let lang_display = if lang.is_empty() { "code" } else { lang };
-
-        let mut result = String::new();
-        result.push_str(&format!("\n  \x1b[38;5;240m┌─ {} {}\x1b[0m\n", lang_display, border.chars().take(w - 10 - lang_display.len()).collect::<String>()));
-
-        for line in code.lines() {
-            let truncated = if line.len() > w - 8 {
-                format!("{}...", &line[..w - 11])
-            } else {
-                line.to_string()
-            };
-            result.push_str(&format!("  \x1b[38;5;240m│\x1b[0m \x1b[38;5;222m{}\x1b[0m\n", truncated));
+    /// Ends an answer, so on stdout in plain mode too
+    pub fn print_newline(&self) {
+        if is_plain() {
+            std::println!();
+        } else {
+            println!();
         }
-
-        result.push_str(&format!("  \x1b[38;5;240m└{}\x1b[0m\n", border));
-        result
     }
 
-    fn format_inline(&self, line: &str) -> String {
-        let mut result = line.to_string();
-
-        // Bold: **text** or __text__
-        let bold_re = regex::Regex::new(r"\*\*(.+?)\*\*|__(.+?)__").unwrap();
-        result = bold_re.replace_all(&result, "\x1b[1m$1$2\x1b[0m").to_string();
-
-        // Inline code: `code`
-        let code_re = regex::Regex::new(r"`([^`]+)`").unwrap();
-        result = code_re.replace_all(&result, "\x1b[38;5;222m$1\x1b[0m").to_string();
-
-        // Headers: ## Header
-        if result.starts_with("# ") {
-            result = format!("\x1b[1;38;5;75m{}\x1b[0m", &result[2..]);
-        } else if result.starts_with("## ") {
-            result = format!("\x1b[1;38;5;75m{}\x1b[0m", &result[3..]);
-        } else if result.starts_with("### ") {
-            result = format!("\x1b[1;38;5;245m{}\x1b[0m", &result[4..]);
-        }
-
-        // Bullet points
-        if result.starts_with("- ") || result.starts_with("* ") {
-            result = format!("\x1b[38;5;75m•\x1b[0m {}", &result[2..]);
+    pub fn print_context_status(&self) {
+        self.streamed_chars.set(0);
+        if is_plain() {
+            return;
         }
-
-        // Numbered lists (keep as-is but add color)
-        if result.chars().next().map(|c| c.is_ascii_digit()).unwrap_or(false) && result.contains(". ") {
-            if let Some(pos) = result.find(". ") {
-                let num = &result[..pos + 1];
-                let text = &result[pos + 2..];
-                result = format!("\x1b[38;5;75m{}\x1b[0m {}", num, text);
-            }
+        if self.sticky_rows.get() > 0 {
+            self.refresh_status();
+        } else {
+            self.print_status_bar();
         }
-
-        result
-    }
-
-    pub fn print_newline(&self) {
-        println!();
-    }
-
-    pub fn print_context_status(&self) {
-        self.print_status_bar();
     }
 
     pub fn print_tool_call(&self, tool_name: &str, input: &str) {
@@ -542,13 +873,8 @@ impl UI {
             if let Some(obj) = json.as_object() {
                 for (key, value) in obj.iter().take(3) {
                     let val_str = match value {
-                        serde_json::Value::String(s) => {
-                            if s.len() > 60 { format!("{}...", &s[..57]) } else { s.clone() }
-                        },
-                        _ => {
-                            let s = value.to_string();
-                            if s.len() > 60 { format!("{}...", &s[..57]) } else { s }
-                        }
+                        serde_json::Value::String(s) => text::truncate(s, 60),
+                        _ => text::truncate(&value.to_string(), 60),
                     };
                     println!("     \x1b[38;5;245m{}:\x1b[0m {}", key, val_str);
                 }
@@ -560,17 +886,18 @@ impl UI {
         let status = if success { "\x1b[38;5;82m✓\x1b[0m" } else { "\x1b[38;5;203m✗\x1b[0m" };
         println!("  {} \x1b[38;5;245m{}\x1b[0m", status, tool_name);
 
-        // Show condensed output
+        // Show condensed output; dry runs show their diff, which is the point
         let lines: Vec<&str> = output.lines().collect();
-        let max_lines = 5;
+        let dry_run = output.starts_with(crate::tools::DRY_RUN_NOTE);
+        let max_lines = if dry_run { 60 } else { 5 };
 
         for line in lines.iter().take(max_lines) {
-            let truncated = if line.len() > 80 {
-                format!("{}...", &line[..77])
-            } else {
-                line.to_string()
+            let color = match line.chars().next() {
+                Some('+') if dry_run && !line.starts_with("+++") => "38;5;84",
+                Some('-') if dry_run && !line.starts_with("---") => "38;5;203",
+                _ => "38;5;240",
             };
-            println!("     \x1b[38;5;240m{}\x1b[0m", truncated);
+            println!("     \x1b[{}m{}\x1b[0m", color, text::truncate(line, 80));
         }
 
         if lines.len() > max_lines {
@@ -586,93 +913,428 @@ impl UI {
         println!("\x1b[38;5;75mℹ\x1b[0m {}", message);
     }
 
+    /// True if p, space or Esc was pressed since the last check. Keys typed
+    /// while a tool runs sit in the terminal buffer until we look here.
+    pub fn pause_requested(&self) -> bool {
+        if !io::stdin().is_terminal() || terminal::enable_raw_mode().is_err() {
+            return false;
+        }
+        let mut requested = false;
+        while let Ok(true) = event::poll(std::time::Duration::ZERO) {
+            if let Ok(Event::Key(key)) = event::read() {
+                if matches!(key.code, KeyCode::Esc | KeyCode::Char('p') | KeyCode::Char(' ')) {
+                    requested = true;
+                }
+            }
+        }
+        let _ = terminal::disable_raw_mode();
+        requested
+    }
+
+    /// List the tool calls still waiting in a paused batch
+    pub fn print_tool_queue(&self, queue: &[(usize, &str, String, Option<&str>)]) {
+        println!();
+        println!("  \x1b[1;37mAgent paused\x1b[0m \x1b[38;5;245m- pending tool calls\x1b[0m");
+        for (number, name, preview, decision) in queue {
+            let status = match decision {
+                Some(decision) => format!(" \x1b[38;5;203m[{}]\x1b[0m", decision),
+                None => String::new(),
+            };
+            println!("    \x1b[38;5;220m{:>2}.\x1b[0m \x1b[38;5;75m{}\x1b[0m{} \x1b[38;5;245m{}\x1b[0m", number, name, status, preview);
+        }
+        println!();
+        println!("  \x1b[38;5;245mEnter resume · s <n> skip · d <n> [reason] deny · i <n> inspect · x deny all\x1b[0m");
+    }
+
+    /// Read one line of input after a prompt
+    pub fn read_line(&self, prompt: &str) -> Option<String> {
+        print!("{}", prompt);
+        let _ = io::stdout().flush();
+        let mut line = String::new();
+        io::stdin().read_line(&mut line).ok()?;
+        Some(line.trim().to_string())
+    }
+
+    /// Ask a yes/no question on the terminal; anything but y/yes is a no
+    pub fn confirm(&self, question: &str) -> bool {
+        // The next line of piped input is a question, not an answer
+        if is_plain() && !io::stdin().is_terminal() {
+            eprintln!("? {} no (stdin is not a terminal)", question);
+            return false;
+        }
+        let prompt = format!("\x1b[38;5;220m?\x1b[0m {} \x1b[38;5;245m{}\x1b[0m ", question, self.strings.get("yes_no"));
+        self.read_line(&prompt)
+            .is_some_and(|answer| self.strings.is_yes(&answer))
+    }
+
     pub fn print_success(&self, message: &str) {
         println!("\x1b[38;5;82m✓\x1b[0m {}", message);
     }
 
-    pub fn print_file_context(&self, files: &[String]) {
-        if files.is_empty() {
+    /// `[ui] verbose_stats`: where the turn's time went. A slow first token
+    /// points at the network or a queue, low tokens/sec at the model.
+    pub fn print_turn_stats(&self, timings: &[RequestTiming]) {
+        if let Some(stats) = turn_stats(timings) {
+            println!("\x1b[90m  ⏱ {}\x1b[0m", stats);
+        }
+    }
+
+    /// `[ui] follow_ups`: the suggestions after an answer, sent by pressing
+    /// their number on an empty prompt
+    pub fn print_follow_ups(&self, suggestions: &[String]) {
+        if suggestions.is_empty() {
             return;
         }
         println!();
-        for file in files {
-            println!("  \x1b[38;5;39m+\x1b[0m \x1b[38;5;75m{}\x1b[0m", file);
+        for (i, suggestion) in suggestions.iter().enumerate() {
+            println!("  \x1b[38;5;75m{}\x1b[0m \x1b[38;5;245m›\x1b[0m {}", i + 1, suggestion);
         }
-        println!();
+        println!("  \x1b[38;5;240mpress a number to send it\x1b[0m");
     }
 
-    pub fn print_models_list(&self, models: &[(String, String, bool)]) {
-        let s = &self.strings;
+    /// `/retry` and `/compare`: two answers in columns, word-wrapped, up to
+    /// SIDE_BY_SIDE_ROWS rows
+    pub fn print_side_by_side(&self, left_title: &str, left: &str, right_title: &str, right: &str) {
+        let column = (self.term_width() - 7) / 2;
+        let wrap = |text: &str| -> Vec<String> {
+            text.lines().flat_map(|line| textwrap::wrap(line, column).into_iter().map(|l| l.into_owned()).collect::<Vec<_>>()).collect()
+        };
+        let (left, right) = (wrap(left), wrap(right));
+        let rows = left.len().max(right.len());
         println!();
-        println!("  \x1b[1;37m{}\x1b[0m", s.title_models());
+        println!(
+            "  \x1b[1;38;5;245m{}\x1b[0m \x1b[38;5;240m│\x1b[0m \x1b[1;38;5;117m{}\x1b[0m",
+            text::pad(&text::truncate(left_title, column), column),
+            text::truncate(right_title, column)
+        );
+        println!("  \x1b[38;5;240m{}┼{}\x1b[0m", "─".repeat(column + 1), "─".repeat(column + 1));
+        for row in 0..rows.min(SIDE_BY_SIDE_ROWS) {
+            let cell = |lines: &[String]| lines.get(row).cloned().unwrap_or_default();
+            println!("  {} \x1b[38;5;240m│\x1b[0m {}", text::pad(&cell(&left), column), cell(&right));
+        }
+        if rows > SIDE_BY_SIDE_ROWS {
+            println!("  \x1b[38;5;240m... {} more lines\x1b[0m", rows - SIDE_BY_SIDE_ROWS);
+        }
         println!();
+    }
 
-        for (i, (name, model_type, is_active)) in models.iter().enumerate() {
-            let marker = if *is_active { "\x1b[38;5;82m●\x1b[0m" } else { "\x1b[38;5;240m○\x1b[0m" };
-            let name_style = if *is_active { "\x1b[1;38;5;220m" } else { "" };
-            println!("    {} \x1b[38;5;245m{}.\x1b[0m {}{}\x1b[0m \x1b[38;5;245m({})\x1b[0m",
-                marker, i + 1, name_style, name, model_type);
+    /// `/compare` and `/best`: the answers side by side when the terminal is
+    /// wide enough, one after the other otherwise, each with its time, tokens
+    /// and cost
+    pub fn print_comparison(&mut self, results: &[Comparison]) {
+        let answer = |result: &Comparison| match &result.answer {
+            Ok(answer) => answer.clone(),
+            Err(e) => format!("✗ {}", e),
+        };
+        if let [left, right] = results {
+            if !is_plain() && self.term_width() >= COMPARE_COLUMNS_WIDTH {
+                self.print_side_by_side(
+                    &format!("{} · {}", left.model, left.summary()),
+                    &answer(left),
+                    &format!("{} · {}", right.model, right.summary()),
+                    &answer(right),
+                );
+                return;
+            }
+        }
+        for result in results {
+            match &result.answer {
+                Ok(answer) => self.print_answer_section(&result.model, &result.summary(), answer),
+                Err(e) => {
+                    println!("\n\x1b[1;38;5;117m── {} \x1b[0m\x1b[38;5;245m{}\x1b[0m", result.model, result.summary());
+                    self.print_error(&e.to_string());
+                }
+            }
         }
-
-        println!();
-        println!("  \x1b[38;5;245mUse /model <name> to switch\x1b[0m");
         println!();
     }
 
-    /// Print model list and return selected index based on user input
-    /// Returns: Some(index) for model selection, Some(models.len()) for "Add model", None for cancel
-    pub fn select_model_interactive(&self, models: &[(String, String, bool)]) -> Option<usize> {
-        println!();
-        println!("  \x1b[1;37m{}\x1b[0m", self.strings.title_models());
-        println!();
+    /// A titled answer, rendered as markdown
+    pub fn print_answer_section(&mut self, title: &str, detail: &str, answer: &str) {
+        println!("\n\x1b[1;38;5;117m── {} \x1b[0m\x1b[38;5;245m{}\x1b[0m", title, detail);
+        self.reset_code_state();
+        self.print_assistant_prefix();
+        self.print_token(answer);
+        self.print_newline();
+    }
 
-        for (i, (name, model_type, is_active)) in models.iter().enumerate() {
-            let marker = if *is_active { "\x1b[38;5;82m●\x1b[0m" } else { "\x1b[38;5;240m○\x1b[0m" };
-            let name_style = if *is_active { "\x1b[1;38;5;220m" } else { "" };
-            println!("    \x1b[38;5;75m{}.\x1b[0m {} {}{}\x1b[0m \x1b[38;5;245m({})\x1b[0m",
-                i + 1, marker, name_style, name, model_type);
+    /// An answer as the /pipe command printed it, escape sequences included
+    pub fn print_piped(&self, output: &str) {
+        println!();
+        print!("{}", output);
+        if !output.ends_with('\n') {
+            println!();
         }
+    }
 
-        println!("    \x1b[38;5;75m{}.\x1b[0m \x1b[38;5;82m+ Add model\x1b[0m", models.len() + 1);
-        println!();
-        println!("  \x1b[38;5;245mDigite o número (1-{}) ou 'q' para cancelar:\x1b[0m", models.len() + 1);
+    /// The suggestion sent as the prompt
+    pub fn print_quick_reply(&self, reply: &str) {
+        println!("  \x1b[38;5;245m↳\x1b[0m {}", reply);
+    }
 
-        // Return None - the actual selection will be handled by the chat loop
-        // by reading the next input
-        None
+    /// The files a turn changed, after its answer
+    pub fn print_file_changes(&self, changes: &[FileChange]) {
+        println!("\n\x1b[90m  ✎ {} file(s) changed\x1b[0m", changes.len());
+        for change in changes {
+            let status = match change.status {
+                ChangeStatus::Created => " \x1b[32mcreated\x1b[0m",
+                ChangeStatus::Deleted => " \x1b[31mdeleted\x1b[0m",
+                ChangeStatus::Modified => "",
+            };
+            let mut counts = String::new();
+            if change.added > 0 {
+                counts.push_str(&format!(" \x1b[32m+{}\x1b[0m", change.added));
+            }
+            if change.removed > 0 {
+                counts.push_str(&format!(" \x1b[31m-{}\x1b[0m", change.removed));
+            }
+            println!("    {}{}{}", change.path, status, counts);
+        }
     }
 
-    /// Parse model selection from user input
-    /// Returns: Some(index) for valid selection, None for cancel or invalid
-    pub fn parse_model_selection(&self, input: &str, total_models: usize) -> Option<usize> {
-        let input = input.trim().to_lowercase();
+    pub fn print_file_context(&self, files: &[String]) {
+        if files.is_empty() {
+            return;
+        }
+        println!();
+        for file in files {
+            println!("  \x1b[38;5;39m+\x1b[0m \x1b[38;5;75m{}\x1b[0m", file);
+        }
+        println!();
+    }
 
-        if input == "q" || input == "cancel" || input == "cancelar" {
+    /// Let the user pick one of `items`, starting on `selected`. In a terminal
+    /// the menu takes arrow keys, clicks and the mouse wheel, typing filters
+    /// it, and lists longer than the screen scroll; elsewhere it is a numbered
+    /// list read from stdin.
+    pub fn pick(&self, title: &str, items: &[MenuItem], selected: usize) -> Option<usize> {
+        if items.is_empty() {
             return None;
         }
-
-        if let Ok(num) = input.parse::<usize>() {
-            if num >= 1 && num <= total_models + 1 {
-                return Some(num - 1);
+        if self.menus_interactive() {
+            if let Ok(choice) = self.pick_in_terminal(title, items, selected.min(items.len() - 1)) {
+                return choice;
             }
         }
+        self.pick_numbered(title, items)
+    }
 
-        None
+    /// Whether `pick` shows a menu driven by keys and the mouse
+    pub fn menus_interactive(&self) -> bool {
+        !is_plain() && console::level() != console::Level::None && io::stdin().is_terminal() && io::stdout().is_terminal()
     }
 
-    pub fn print_language_menu(&self, current_lang: Language) {
-        println!();
-        println!("  \x1b[1;37mLanguage\x1b[0m");
-        println!();
+    /// A number picks that entry; anything else narrows the list down to the
+    /// entries it matches, and the last one left is picked
+    fn pick_numbered(&self, title: &str, items: &[MenuItem]) -> Option<usize> {
+        let mut shown: Vec<usize> = (0..items.len()).collect();
+        let marked = items.iter().any(|item| item.current);
+        loop {
+            println!();
+            println!("  \x1b[1;37m{}\x1b[0m", title);
+            for row in menu_rows(items, &shown) {
+                match row {
+                    MenuRow::Header(group) => println!("\n    \x1b[38;5;245m{}\x1b[0m", group),
+                    MenuRow::Item(i) => {
+                        let number = shown.iter().position(|&s| s == i).unwrap_or(0) + 1;
+                        println!("    \x1b[38;5;75m{:>2}.\x1b[0m {}", number, items[i].render(false, 76, marked));
+                    }
+                }
+            }
+            println!();
+            let prompt = format!("  \x1b[38;5;245m1-{} · text to filter · q to cancel\x1b[0m \x1b[38;5;117m❯\x1b[0m ", shown.len());
+            let answer = self.read_line(&prompt)?;
+            if answer.is_empty() || answer == "q" {
+                return None;
+            }
+            if let Ok(n) = answer.parse::<usize>() {
+                return (1..=shown.len()).contains(&n).then(|| shown[n - 1]);
+            }
+            let matching: Vec<usize> = filter_items(items, &answer).into_iter().filter(|i| shown.contains(i)).collect();
+            match matching.as_slice() {
+                [] => self.print_info(&format!("Nothing matches '{}'", answer)),
+                [one] => return Some(*one),
+                _ => shown = matching,
+            }
+        }
+    }
 
-        let en_marker = if current_lang == Language::En { "\x1b[38;5;82m●\x1b[0m" } else { "\x1b[38;5;240m○\x1b[0m" };
-        let pt_marker = if current_lang == Language::Pt { "\x1b[38;5;82m●\x1b[0m" } else { "\x1b[38;5;240m○\x1b[0m" };
+    fn pick_in_terminal(&self, title: &str, items: &[MenuItem], mut selected: usize) -> io::Result<Option<usize>> {
+        // The sticky status line's row is not ours to draw on
+        let (width, rows) = terminal::size()?;
+        let rows = rows.saturating_sub(u16::from(self.sticky_rows.get() > 0)) as usize;
+        let all: Vec<usize> = (0..items.len()).collect();
+        let height = menu_rows(items, &all).len().min(rows.saturating_sub(MENU_CHROME + 2).max(3));
+        // Make room below the cursor, then draw from where that room starts
+        print!("{}", "\n".repeat(height + MENU_CHROME));
+        io::stdout().flush()?;
+        terminal::enable_raw_mode()?;
+        let top = cursor::position().map(|(_, row)| row.saturating_sub((height + MENU_CHROME) as u16));
+        let Ok(top) = top else {
+            let _ = terminal::disable_raw_mode();
+            return Err(io::Error::other("cursor position unknown"));
+        };
+        let _ = execute!(io::stdout(), event::EnableMouseCapture, cursor::Hide);
+
+        let first_row = top + 2;
+        let mut query = String::new();
+        let mut rows = menu_rows(items, &all);
+        let mut offset = 0;
+        let mut follow = true;
+        let choice = loop {
+            // Keys move the selection and the list follows it; the wheel moves
+            // the list and the selection stays on screen
+            let item_rows: Vec<usize> = (0..rows.len()).filter(|&r| matches!(rows[r], MenuRow::Item(_))).collect();
+            let position = |i: usize| rows.iter().position(|row| matches!(row, MenuRow::Item(s) if *s == i));
+            let at = position(selected).or(item_rows.first().copied());
+            if let Some(at) = at {
+                if follow {
+                    // Keep a group's header in view with its first entry
+                    let header = usize::from(at > 0 && matches!(rows[at - 1], MenuRow::Header(_)));
+                    offset = offset.min(at - header).max((at + 1).saturating_sub(height));
+                } else if at < offset || at >= offset + height {
+                    if let Some(i) = item_rows.iter().find(|&&r| r >= offset && r < offset + height).and_then(|&r| item_at(&rows, r)) {
+                        selected = i;
+                    }
+                }
+            }
+            offset = offset.min(rows.len().saturating_sub(height));
+            let current = at.and_then(|r| item_at(&rows, r));
+            if let Some(i) = current {
+                selected = i;
+            }
+            self.draw_menu(top, width as usize, title, items, &rows, current, offset, height, &query);
+            follow = true;
 
-        println!("    {} English", en_marker);
-        println!("    {} Português", pt_marker);
-        println!();
-        println!("  \x1b[38;5;245m/lang en · /lang pt\x1b[0m");
-        println!();
+            let Ok(event) = event::read() else {
+                break None;
+            };
+            let step = |by: isize| {
+                let last = rows.len().checked_sub(1)?;
+                let target = (at.unwrap_or(0) as isize + by).clamp(0, last as isize) as usize;
+                let row = if by < 0 {
+                    item_rows.iter().rev().find(|&&r| r <= target).or(item_rows.first())
+                } else {
+                    item_rows.iter().find(|&&r| r >= target).or(item_rows.last())
+                };
+                row.and_then(|&r| item_at(&rows, r))
+            };
+            let mut filtered = false;
+            match event {
+                Event::Key(key) if key.kind == KeyEventKind::Press => {
+                    let page = height.saturating_sub(1).max(1) as isize;
+                    let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+                    let moved = match key.code {
+                        KeyCode::Enter => match current {
+                            Some(i) => break Some(i),
+                            None => None,
+                        },
+                        KeyCode::Esc if query.is_empty() => break None,
+                        KeyCode::Esc => {
+                            query.clear();
+                            filtered = true;
+                            None
+                        }
+                        KeyCode::Char('c') if ctrl => break None,
+                        KeyCode::Char('u') if ctrl => {
+                            query.clear();
+                            filtered = true;
+                            None
+                        }
+                        KeyCode::Char(c) if !ctrl => {
+                            query.push(c);
+                            filtered = true;
+                            None
+                        }
+                        KeyCode::Backspace => {
+                            filtered = query.pop().is_some();
+                            None
+                        }
+                        KeyCode::Up => step(-1),
+                        KeyCode::Down => step(1),
+                        KeyCode::PageUp => step(-page),
+                        KeyCode::PageDown => step(page),
+                        KeyCode::Home => step(-(rows.len() as isize)),
+                        KeyCode::End => step(rows.len() as isize),
+                        _ => None,
+                    };
+                    if let Some(i) = moved {
+                        selected = i;
+                    }
+                }
+                Event::Mouse(mouse) => {
+                    let under = (mouse.row >= first_row)
+                        .then(|| offset + (mouse.row - first_row) as usize)
+                        .filter(|&r| r < offset + height)
+                        .and_then(|r| item_at(&rows, r));
+                    match mouse.kind {
+                        MouseEventKind::ScrollUp => {
+                            offset = offset.saturating_sub(MENU_WHEEL_STEP);
+                            follow = false;
+                        }
+                        MouseEventKind::ScrollDown => {
+                            offset += MENU_WHEEL_STEP;
+                            follow = false;
+                        }
+                        MouseEventKind::Down(MouseButton::Left) => {
+                            if let Some(i) = under {
+                                break Some(i);
+                            }
+                        }
+                        MouseEventKind::Moved => {
+                            if let Some(i) = under {
+                                selected = i;
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+                _ => {}
+            }
+            if filtered {
+                rows = menu_rows(items, &filter_items(items, &query));
+                offset = 0;
+            }
+        };
+
+        let mut out = io::stdout();
+        let _ = execute!(out, event::DisableMouseCapture, cursor::MoveTo(0, top), terminal::Clear(ClearType::FromCursorDown), cursor::Show);
+        let _ = terminal::disable_raw_mode();
+        self.refresh_status();
+        Ok(choice)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn draw_menu(&self, top: u16, width: usize, title: &str, items: &[MenuItem], rows: &[MenuRow], selected: Option<usize>, offset: usize, height: usize, query: &str) {
+        let marked = items.iter().any(|item| item.current);
+        let filter = if query.is_empty() {
+            "\x1b[38;5;240mtype to filter\x1b[0m".to_string()
+        } else {
+            format!("\x1b[38;5;245mfilter:\x1b[0m {}\x1b[38;5;117m▏\x1b[0m", text::truncate(query, width.saturating_sub(title.width() + 14)))
+        };
+        let mut frame = format!("\x1b[{};1H\x1b[K  \x1b[1;37m{}\x1b[0m  {}\r\n\x1b[K", top + 1, title, filter);
+        let above = if offset > 0 { format!("↑ {} more", offset) } else { String::new() };
+        frame.push_str(&format!("    \x1b[38;5;240m{}\x1b[0m", above));
+        for r in offset..offset + height {
+            frame.push_str("\r\n\x1b[K");
+            match rows.get(r) {
+                Some(MenuRow::Header(group)) => frame.push_str(&format!("    \x1b[38;5;245m{}\x1b[0m", text::truncate(group, width.saturating_sub(4)))),
+                Some(MenuRow::Item(i)) if Some(*i) == selected => {
+                    frame.push_str(&format!("  \x1b[38;5;117m❯\x1b[0m {}", items[*i].render(true, width.saturating_sub(5), marked)));
+                }
+                Some(MenuRow::Item(i)) => frame.push_str(&format!("    {}", items[*i].render(false, width.saturating_sub(5), marked))),
+                None if r == 0 => frame.push_str("    \x1b[38;5;245mNothing matches\x1b[0m"),
+                None => {}
+            }
+        }
+        let below = rows.len().saturating_sub(offset + height);
+        let more = if below > 0 { format!("↓ {} more · ", below) } else { String::new() };
+        frame.push_str(&format!(
+            "\r\n\x1b[K    \x1b[38;5;240m{}↑↓ or wheel to move · Enter or click to choose · Esc to cancel\x1b[0m",
+            more
+        ));
+        print!("{}", frame);
+        let _ = io::stdout().flush();
     }
 
     pub fn print_help(&self) {
@@ -685,20 +1347,60 @@ impl UI {
         println!("    \x1b[38;5;220m/clear\x1b[0m         {}", s.cmd_clear());
         println!("    \x1b[38;5;220m/model\x1b[0m         {}", s.cmd_model());
         println!("    \x1b[38;5;220m/config\x1b[0m        {}", s.cmd_config());
+        println!("    \x1b[38;5;220m/cost\x1b[0m          {}", s.get("cmd_cost"));
         println!("    \x1b[38;5;220m/lang\x1b[0m          {}", s.cmd_lang());
-        println!("    \x1b[38;5;220m/install\x1b[0m       Install AICLI globally");
-        println!("    \x1b[38;5;220m/uninstall\x1b[0m     Uninstall AICLI");
+        println!("    \x1b[38;5;220m/router\x1b[0m        {}", s.get("cmd_router"));
+        println!("    \x1b[38;5;220m/pr\x1b[0m            {}", s.get("cmd_pr"));
+        println!("    \x1b[38;5;220m/redact\x1b[0m        {}", s.get("cmd_redact"));
+        println!("    \x1b[38;5;220m/copy-code\x1b[0m     {}", s.get("cmd_copy_code"));
+        println!("    \x1b[38;5;220m/save-code\x1b[0m     {}", s.get("cmd_save_code"));
+        println!("    \x1b[38;5;220m/apply-code\x1b[0m    {}", s.get("cmd_apply_code"));
+        println!("    \x1b[38;5;220m/apply\x1b[0m         {}", s.get("cmd_apply"));
+        println!("    \x1b[38;5;220m/title\x1b[0m         {}", s.get("cmd_title"));
+        println!("    \x1b[38;5;220m/search\x1b[0m        {}", s.get("cmd_search"));
+        println!("    \x1b[38;5;220m/remember\x1b[0m      {}", s.get("cmd_remember"));
+        println!("    \x1b[38;5;220m/memory\x1b[0m        {}", s.get("cmd_memory"));
+        println!("    \x1b[38;5;220m/resume\x1b[0m        {}", s.get("cmd_resume"));
+        println!("    \x1b[38;5;220m/pin\x1b[0m           {}", s.get("cmd_pin"));
+        println!("    \x1b[38;5;220m/unpin\x1b[0m         {}", s.get("cmd_unpin"));
+        println!("    \x1b[38;5;220m/init\x1b[0m          {}", s.get("cmd_init"));
+        println!("    \x1b[38;5;220m/speak\x1b[0m         {}", s.get("cmd_speak"));
+        println!("    \x1b[38;5;220m/mic\x1b[0m           {}", s.get("cmd_mic"));
+        println!("    \x1b[38;5;220m/imagine\x1b[0m       {}", s.get("cmd_imagine"));
+        println!("    \x1b[38;5;220m/continue\x1b[0m      {}", s.get("cmd_continue"));
+        println!("    \x1b[38;5;220m/output\x1b[0m        {}", s.get("cmd_output"));
+        println!("    \x1b[38;5;220m/perf\x1b[0m          {}", s.get("cmd_perf"));
+        println!("    \x1b[38;5;220m/dryrun\x1b[0m        {}", s.get("cmd_dryrun"));
+        println!("    \x1b[38;5;220m/revert\x1b[0m        {}", s.get("cmd_revert"));
+        println!("    \x1b[38;5;220m/export\x1b[0m        {}", s.get("cmd_export"));
+        println!("    \x1b[38;5;220m/fix\x1b[0m           {}", s.get("cmd_fix"));
+        println!("    \x1b[38;5;220m/tail\x1b[0m          {}", s.get("cmd_tail"));
+        println!("    \x1b[38;5;220m/quote\x1b[0m         {}", s.get("cmd_quote"));
+        println!("    \x1b[38;5;220m/retry\x1b[0m         {}", s.get("cmd_retry"));
+        println!("    \x1b[38;5;220m/compare\x1b[0m       {}", s.get("cmd_compare"));
+        println!("    \x1b[38;5;220m/best\x1b[0m          {}", s.get("cmd_best"));
+        println!("    \x1b[38;5;220m/fork\x1b[0m          {}", s.get("cmd_fork"));
+        println!("    \x1b[38;5;220m/pipe\x1b[0m          {}", s.get("cmd_pipe"));
+        println!("    \x1b[38;5;220m/context\x1b[0m       {}", s.get("cmd_context"));
+        println!("    \x1b[38;5;220m/thinking\x1b[0m      {}", s.get("cmd_thinking"));
+        println!("    \x1b[38;5;220m/install\x1b[0m       {}", s.get("cmd_install"));
+        println!("    \x1b[38;5;220m/uninstall\x1b[0m     {}", s.get("cmd_uninstall"));
         println!();
         self.print_separator();
         println!();
-        println!("    \x1b[1mFile Context\x1b[0m");
-        println!("    \x1b[38;5;245mUse @filename to include files as context\x1b[0m");
-        println!("    \x1b[38;5;245mExample: explain @src/main.rs\x1b[0m");
+        println!("    \x1b[1m{}\x1b[0m", s.get("help_tools"));
+        println!("    \x1b[38;5;245m{}\x1b[0m", s.get("help_tools_pause"));
+        println!();
+        println!("    \x1b[1m{}\x1b[0m", s.get("help_file_context"));
+        println!("    \x1b[38;5;245m{}\x1b[0m", s.get("help_file_usage"));
+        println!("    \x1b[38;5;245m{}\x1b[0m", s.get("help_file_example"));
+        println!();
+        println!("    \x1b[38;5;245m{}\x1b[0m", s.get("help_palette"));
         println!();
     }
 
     pub fn print_config(&self, endpoint: &str, deployment: &str, model_type: &str,
-                        max_tokens: u32, temperature: f32, api_key_preview: &str) {
+                        max_tokens: u32, temperature: f32, api_key_masked: &str) {
         println!();
         println!("  \x1b[1;37mConfiguration\x1b[0m");
         println!();
@@ -707,7 +1409,31 @@ impl UI {
         println!("    Type:        {}", model_type);
         println!("    Max Tokens:  {}", max_tokens);
         println!("    Temperature: {}", temperature);
-        println!("    API Key:     {}***", api_key_preview);
+        println!("    API Key:     {}", api_key_masked);
+        println!();
+    }
+
+    pub fn print_cost(&self, usage: &TokenUsage, cost: Option<f64>, unpriced_calls: usize) {
+        println!();
+        println!("  \x1b[1;37mSession Usage\x1b[0m");
+        println!();
+        println!("    Prompt tokens:     {}", usage.prompt_tokens);
+        println!("    Completion tokens: {}", usage.completion_tokens);
+        if usage.thinking_tokens > 0 {
+            println!("    Thinking:          ~{} (in completion)", usage.thinking_tokens);
+        }
+        println!("    Cache read:        \x1b[38;5;{}m{}\x1b[0m", DRACULA_GREEN, usage.cache_read_tokens);
+        println!("    Cache write:       {}", usage.cache_write_tokens);
+        if usage.prompt_tokens > 0 && usage.cache_read_tokens > 0 {
+            println!("    Cache hit rate:    {}%", usage.cache_read_tokens * 100 / usage.prompt_tokens);
+        }
+        match cost {
+            Some(cost) => println!("    Estimated cost:    ${:.4}", cost),
+            None => println!("    Estimated cost:    \x1b[38;5;245mset input_price/output_price in config.toml\x1b[0m"),
+        }
+        if unpriced_calls > 0 && cost.is_some() {
+            println!("    \x1b[38;5;245m({} calls to models without prices not included)\x1b[0m", unpriced_calls);
+        }
         println!();
     }
 
@@ -717,6 +1443,7 @@ impl UI {
             terminal::Clear(ClearType::All),
             cursor::MoveTo(0, 0)
         ).unwrap();
+        self.refresh_status();
     }
 
     /// Get recent files in current directory for suggestions
@@ -744,7 +1471,7 @@ impl UI {
         }
 
         // Sort by modification time (most recent first)
-        files.sort_by(|a, b| b.1.cmp(&a.1));
+        files.sort_by_key(|f| std::cmp::Reverse(f.1));
 
         files.into_iter().take(limit).map(|(name, _)| name).collect()
     }
@@ -755,3 +1482,102 @@ impl Default for UI {
         Self::new(Language::default())
     }
 }
+
+impl Drop for UI {
+    fn drop(&mut self) {
+        self.release_status();
+    }
+}
+
+/// Lines of Claude's thinking shown while it streams
+const THINKING_ROWS: usize = 6;
+
+/// Claude's extended thinking as it streams: a dim block with its latest
+/// lines, collapsed to one line once the answer starts. /thinking shows
+/// all of it.
+#[derive(Default)]
+pub struct ThinkingBlock {
+    text: String,
+    started: Option<std::time::Instant>,
+    /// Rows the block takes on screen
+    rows: usize,
+    collapsed: bool,
+}
+
+impl ThinkingBlock {
+    pub fn push(&mut self, delta: &str) {
+        self.started.get_or_insert_with(std::time::Instant::now);
+        self.text.push_str(delta);
+        if !is_plain() {
+            self.redraw();
+        }
+    }
+
+    /// The last lines of the thinking, wrapped to `width`
+    fn tail(&self, width: usize) -> Vec<String> {
+        let mut lines: Vec<String> = Vec::new();
+        for paragraph in self.text.trim_start().lines().rev() {
+            let mut wrapped = vec![String::new()];
+            for word in paragraph.split_whitespace() {
+                let line = wrapped.last_mut().unwrap();
+                if !line.is_empty() && text::width(line) + 1 + text::width(word) > width {
+                    wrapped.push(text::truncate(word, width));
+                } else {
+                    if !line.is_empty() {
+                        line.push(' ');
+                    }
+                    line.push_str(&text::truncate(word, width));
+                }
+            }
+            lines.extend(wrapped.into_iter().rev());
+            if lines.len() >= THINKING_ROWS {
+                break;
+            }
+        }
+        lines.truncate(THINKING_ROWS);
+        lines.reverse();
+        lines
+    }
+
+    fn erase(&mut self) {
+        if self.rows > 0 {
+            print!("\x1b[{}A", self.rows);
+        }
+        print!("\r\x1b[J");
+        self.rows = 0;
+    }
+
+    fn redraw(&mut self) {
+        let width = terminal::size().map(|(w, _)| w as usize).unwrap_or(80).max(40) - 6;
+        let lines = self.tail(width);
+        self.erase();
+        println!("  \x1b[38;5;{}m✻ Thinking…\x1b[0m", DRACULA_COMMENT);
+        for line in &lines {
+            println!("    \x1b[2;3m{}\x1b[0m", line);
+        }
+        self.rows = 1 + lines.len();
+        let _ = io::stdout().flush();
+    }
+
+    /// Collapse the block to one line, once; nothing when the model didn't think
+    pub fn collapse(&mut self) {
+        let Some(started) = self.started.filter(|_| !self.collapsed && !is_plain()) else {
+            return;
+        };
+        self.collapsed = true;
+        self.erase();
+        println!(
+            "  \x1b[38;5;{}m✻ Thought for {:.1}s · ~{} tokens · /thinking shows it\x1b[0m",
+            DRACULA_COMMENT,
+            started.elapsed().as_secs_f32(),
+            self.text.len() / 4
+        );
+        let _ = io::stdout().flush();
+    }
+
+    /// The whole thinking, for /thinking
+    pub fn into_text(mut self) -> Option<String> {
+        self.collapse();
+        self.started.map(|_| self.text)
+    }
+}
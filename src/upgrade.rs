@@ -0,0 +1,248 @@
+use anyhow::{Context, Result};
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::chat;
+use crate::console::println;
+
+const RELEASES_URL: &str = "https://api.github.com/repos/leonardo-matheus/azure-ai-cli/releases/latest";
+
+/// `aicli upgrade [--check]`: replace this executable with the latest GitHub
+/// release. The archive is checked against the `.sha256` file published next
+/// to it before anything is replaced.
+pub async fn run(check_only: bool) -> Result<()> {
+    let current = env!("CARGO_PKG_VERSION");
+    let release = fetch_json(RELEASES_URL).await?;
+    let tag = release.get("tag_name").and_then(|t| t.as_str()).unwrap_or_default();
+    let latest = tag.trim_start_matches('v');
+
+    if !is_newer(latest, current) {
+        println!("\x1b[32m✓ aicli v{} is up to date\x1b[0m", current);
+        return Ok(());
+    }
+    println!("\x1b[36m→ aicli v{} is available (installed: v{})\x1b[0m", latest, current);
+    if check_only {
+        println!("  Run 'aicli upgrade' to install it.");
+        return Ok(());
+    }
+
+    let asset_name = asset_name()?;
+    let assets = release.get("assets").and_then(|a| a.as_array()).cloned().unwrap_or_default();
+    let asset_url = |name: &str| {
+        assets
+            .iter()
+            .find(|a| a.get("name").and_then(|n| n.as_str()) == Some(name))
+            .and_then(|a| a.get("browser_download_url")?.as_str().map(str::to_string))
+    };
+    let archive_url = asset_url(asset_name)
+        .ok_or_else(|| anyhow::anyhow!("Release {} has no {} asset", tag, asset_name))?;
+    let checksum_url = asset_url(&format!("{}.sha256", asset_name)).ok_or_else(|| {
+        anyhow::anyhow!("Release {} publishes no checksum for {}; not upgrading an unverified binary", tag, asset_name)
+    })?;
+
+    println!("  Downloading {}...", asset_name);
+    let archive = fetch_bytes(&archive_url).await?;
+    let checksum = String::from_utf8_lossy(&fetch_bytes(&checksum_url).await?).to_string();
+    let expected = checksum
+        .split_whitespace()
+        .next()
+        .map(str::to_lowercase)
+        .ok_or_else(|| anyhow::anyhow!("{}.sha256 is empty", asset_name))?;
+    let actual = to_hex(&sha256(&archive));
+    if actual != expected {
+        anyhow::bail!("Checksum mismatch for {}: expected {}, got {}", asset_name, expected, actual);
+    }
+    println!("  \x1b[32m✓\x1b[0m SHA-256 verified");
+
+    let work_dir = std::env::temp_dir().join(format!("aicli-upgrade-{}", std::process::id()));
+    fs::create_dir_all(&work_dir)?;
+    let result = install_from_archive(&archive, asset_name, &work_dir);
+    let _ = fs::remove_dir_all(&work_dir);
+    let target = result?;
+
+    println!("\x1b[32m✓ Upgraded {} to v{}\x1b[0m", target.display(), latest);
+    Ok(())
+}
+
+/// Release asset for this platform, as named by the release workflow
+fn asset_name() -> Result<&'static str> {
+    match (std::env::consts::OS, std::env::consts::ARCH) {
+        ("linux", "x86_64") => Ok("aicli-linux-x64.tar.gz"),
+        ("windows", "x86_64") => Ok("aicli-windows-x64.zip"),
+        (os, arch) => anyhow::bail!(
+            "No prebuilt release for {}-{}; build from source with `cargo install --git https://github.com/leonardo-matheus/azure-ai-cli`",
+            os,
+            arch
+        ),
+    }
+}
+
+/// The installed binary when there is one, otherwise the running executable
+fn target_path() -> Result<PathBuf> {
+    let installed = chat::install_path()?;
+    if installed.exists() {
+        return Ok(installed);
+    }
+    std::env::current_exe().context("Failed to get current executable")
+}
+
+fn install_from_archive(archive: &[u8], asset_name: &str, work_dir: &Path) -> Result<PathBuf> {
+    let archive_path = work_dir.join(asset_name);
+    fs::write(&archive_path, archive)?;
+
+    // tar reads both .tar.gz and, on Windows 10+, .zip
+    let status = Command::new("tar")
+        .arg("-xf")
+        .arg(&archive_path)
+        .arg("-C")
+        .arg(work_dir)
+        .status()
+        .context("Could not run tar to extract the release")?;
+    if !status.success() {
+        anyhow::bail!("Failed to extract {}", asset_name);
+    }
+
+    let exe_name = if cfg!(windows) { "aicli.exe" } else { "aicli" };
+    let new_exe = work_dir.join(exe_name);
+    if !new_exe.is_file() {
+        anyhow::bail!("{} does not contain {}", asset_name, exe_name);
+    }
+
+    let target = target_path()?;
+    replace_executable(&new_exe, &target)?;
+    Ok(target)
+}
+
+/// Swap `target` for `new_exe`. The new file is copied next to the target
+/// and renamed over it, so an interrupted upgrade never leaves a partial
+/// binary. Windows cannot overwrite a running executable but can rename it,
+/// so the old one is moved aside first.
+fn replace_executable(new_exe: &Path, target: &Path) -> Result<()> {
+    let staged = target.with_extension("new");
+    fs::copy(new_exe, &staged)
+        .with_context(|| format!("Failed to write {} (try running with more permissions)", staged.display()))?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&staged, fs::Permissions::from_mode(0o755))?;
+    }
+
+    if cfg!(windows) {
+        let old = target.with_extension("old.exe");
+        let _ = fs::remove_file(&old);
+        fs::rename(target, &old).with_context(|| format!("Failed to move {} aside", target.display()))?;
+    }
+    fs::rename(&staged, target).with_context(|| format!("Failed to replace {}", target.display()))?;
+    Ok(())
+}
+
+/// Whether dotted version `latest` is greater than `current`
+fn is_newer(latest: &str, current: &str) -> bool {
+    let parse = |v: &str| -> Vec<u64> {
+        v.split(['.', '-']).map(|part| part.parse().unwrap_or(0)).collect()
+    };
+    parse(latest) > parse(current)
+}
+
+async fn fetch_json(url: &str) -> Result<Value> {
+    let response = http_get(url)
+        .header("Accept", "application/vnd.github+json")
+        .send()
+        .await
+        .context("Could not reach GitHub to check for releases")?;
+    let status = response.status();
+    if !status.is_success() {
+        anyhow::bail!("GitHub releases request failed ({})", status);
+    }
+    Ok(response.json().await?)
+}
+
+async fn fetch_bytes(url: &str) -> Result<Vec<u8>> {
+    let response = http_get(url).send().await.with_context(|| format!("Failed to download {}", url))?;
+    let status = response.status();
+    if !status.is_success() {
+        anyhow::bail!("Download of {} failed ({})", url, status);
+    }
+    Ok(response.bytes().await?.to_vec())
+}
+
+fn http_get(url: &str) -> reqwest::RequestBuilder {
+    let mut request = reqwest::Client::new().get(url).header("User-Agent", "aicli");
+    // Avoids the unauthenticated rate limit on shared networks
+    if let Ok(token) = std::env::var("GITHUB_TOKEN").or_else(|_| std::env::var("GH_TOKEN")) {
+        if !token.is_empty() && url.starts_with("https://api.github.com") {
+            request = request.bearer_auth(token);
+        }
+    }
+    request
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// SHA-256 (FIPS 180-4)
+fn sha256(data: &[u8]) -> [u8; 32] {
+    const K: [u32; 64] = [
+        0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+        0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+        0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+        0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+        0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+        0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+        0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+        0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+    ];
+    let mut h: [u32; 8] = [
+        0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+    ];
+
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&((data.len() as u64) * 8).to_be_bytes());
+
+    for chunk in message.chunks_exact(64) {
+        let mut w = [0u32; 64];
+        for (i, word) in chunk.chunks_exact(4).enumerate() {
+            w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for i in 16..64 {
+            let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+            let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+            w[i] = w[i - 16].wrapping_add(s0).wrapping_add(w[i - 7]).wrapping_add(s1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut hh] = h;
+        for i in 0..64 {
+            let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+            let ch = (e & f) ^ (!e & g);
+            let t1 = hh.wrapping_add(s1).wrapping_add(ch).wrapping_add(K[i]).wrapping_add(w[i]);
+            let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+            let maj = (a & b) ^ (a & c) ^ (b & c);
+            let t2 = s0.wrapping_add(maj);
+            hh = g;
+            g = f;
+            f = e;
+            e = d.wrapping_add(t1);
+            d = c;
+            c = b;
+            b = a;
+            a = t1.wrapping_add(t2);
+        }
+        for (state, value) in h.iter_mut().zip([a, b, c, d, e, f, g, hh]) {
+            *state = state.wrapping_add(value);
+        }
+    }
+
+    let mut digest = [0u8; 32];
+    for (out, word) in digest.chunks_exact_mut(4).zip(h) {
+        out.copy_from_slice(&word.to_be_bytes());
+    }
+    digest
+}
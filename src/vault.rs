@@ -0,0 +1,285 @@
+//! Encryption of the secrets in config.toml. Each secret is stored as
+//! `enc:v1:<base64 of nonce, AES-256-GCM ciphertext and tag>`; everything
+//! else stays plain TOML, so the file can still be read and edited. The key
+//! comes from a passphrase (PBKDF2-HMAC-SHA256) or from a key file.
+
+use anyhow::{Context, Result};
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use std::sync::OnceLock;
+
+use crate::config::AppConfig;
+use crate::console::println;
+
+const PREFIX: &str = "enc:v1:";
+/// Sealed with the key to tell a wrong passphrase from a corrupt secret
+const CHECK_TEXT: &str = "aicli";
+#[cfg_attr(windows, allow(dead_code))]
+const KDF_ITERATIONS: u32 = 600_000;
+const PASSPHRASE_ENV: &str = "AICLI_PASSPHRASE";
+const UNLOCK_ATTEMPTS: usize = 3;
+
+/// The `[encryption]` table; present only when secrets are encrypted
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EncryptionConfig {
+    /// Base64 PBKDF2 salt (passphrase mode)
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub salt: String,
+    /// File holding a base64 256-bit key, used instead of a passphrase
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key_file: Option<String>,
+    /// CHECK_TEXT sealed with the key
+    pub check: String,
+}
+
+/// Key of the unlocked config, kept so saving re-encrypts without asking again
+static KEY: OnceLock<[u8; 32]> = OnceLock::new();
+
+/// Whether the config file on disk has encrypted secrets
+pub fn is_encrypted_file() -> bool {
+    fs::read_to_string(crate::config::get_config_path()).is_ok_and(|content| content.contains(PREFIX))
+}
+
+/// Every secret in the config: model and service keys, tool tokens and
+/// database URLs, and the headers of models and middlewares
+fn secrets_mut(config: &mut AppConfig) -> Vec<&mut String> {
+    let mut secrets: Vec<&mut String> = Vec::new();
+    for model in config.models.values_mut() {
+        secrets.push(&mut model.api_key);
+        secrets.extend(model.headers.values_mut());
+    }
+    secrets.push(&mut config.images.api_key);
+    secrets.push(&mut config.speech.key);
+    secrets.push(&mut config.content_safety.key);
+    secrets.push(&mut config.tools.issues.token);
+    secrets.extend(config.tools.github.token.as_mut());
+    secrets.extend(config.tools.azure_devops.pat.as_mut());
+    secrets.extend(config.tools.databases.values_mut().map(|d| &mut d.url));
+    // Gateways take their tokens in headers
+    secrets.extend(config.middlewares.iter_mut().flat_map(|m| m.headers.values_mut()));
+    secrets
+}
+
+/// Decrypt the secrets of a freshly loaded config in place. Asks for the
+/// passphrase (or reads AICLI_PASSPHRASE) the first time in a process.
+pub fn unlock(config: &mut AppConfig) -> Result<()> {
+    let Some(encryption) = config.encryption.clone() else {
+        return Ok(());
+    };
+    let key = match KEY.get() {
+        Some(key) => *key,
+        None => {
+            let key = unlock_key(&encryption)?;
+            *KEY.get_or_init(|| key)
+        }
+    };
+    for secret in secrets_mut(config) {
+        if let Some(sealed) = secret.strip_prefix(PREFIX) {
+            *secret = open(&key, sealed).context("A secret in config.toml could not be decrypted")?;
+        }
+    }
+    Ok(())
+}
+
+fn unlock_key(encryption: &EncryptionConfig) -> Result<[u8; 32]> {
+    let check = encryption.check.strip_prefix(PREFIX).unwrap_or(&encryption.check);
+    let is_valid = |key: &[u8; 32]| open(key, check).is_ok_and(|text| text == CHECK_TEXT);
+
+    if let Some(path) = &encryption.key_file {
+        let key = read_key_file(&crate::paths::resolve(path))?;
+        if !is_valid(&key) {
+            anyhow::bail!("{} does not hold the key config.toml was encrypted with", path);
+        }
+        return Ok(key);
+    }
+
+    let salt = base64::engine::general_purpose::STANDARD.decode(&encryption.salt).context("Invalid [encryption] salt")?;
+    if let Some(passphrase) = std::env::var(PASSPHRASE_ENV).ok().filter(|p| !p.is_empty()) {
+        let key = derive(&passphrase, &salt)?;
+        if !is_valid(&key) {
+            anyhow::bail!("{} is not the config passphrase", PASSPHRASE_ENV);
+        }
+        return Ok(key);
+    }
+    for _ in 0..UNLOCK_ATTEMPTS {
+        let passphrase = crate::config::read_secret("\x1b[33m🔒 Config passphrase:\x1b[0m ")?;
+        let key = derive(&passphrase, &salt)?;
+        if is_valid(&key) {
+            return Ok(key);
+        }
+        eprintln!("\x1b[31m✗ Wrong passphrase\x1b[0m");
+    }
+    anyhow::bail!("Could not unlock config.toml")
+}
+
+/// A copy of the config with every plain secret encrypted, for saving
+pub fn lock(config: &AppConfig) -> Result<AppConfig> {
+    let key = KEY.get().ok_or_else(|| anyhow::anyhow!("The config is encrypted but was not unlocked"))?;
+    let mut locked = config.clone();
+    for secret in secrets_mut(&mut locked) {
+        if !secret.is_empty() && !secret.starts_with(PREFIX) {
+            *secret = format!("{}{}", PREFIX, seal(key, secret)?);
+        }
+    }
+    Ok(locked)
+}
+
+/// `aicli config encrypt`: protect the secrets with a new passphrase, or
+/// with a key file (created when missing)
+pub fn encrypt(key_file: Option<&str>) -> Result<()> {
+    let mut config = crate::config::load_config()?;
+    if config.encryption.is_some() {
+        anyhow::bail!("config.toml is already encrypted. Run 'aicli config decrypt' first to change the key");
+    }
+
+    let (key, encryption) = match key_file {
+        Some(raw) => {
+            let path = crate::paths::resolve(raw);
+            let key = if path.exists() { read_key_file(&path)? } else { create_key_file(&path)? };
+            let encryption = EncryptionConfig { key_file: Some(raw.to_string()), ..Default::default() };
+            (key, encryption)
+        }
+        None => {
+            let passphrase = crate::config::read_secret("\x1b[33mNew config passphrase:\x1b[0m ")?;
+            if passphrase.chars().count() < 8 {
+                anyhow::bail!("Use a passphrase of at least 8 characters");
+            }
+            if crate::config::read_secret("\x1b[33mRepeat it:\x1b[0m ")? != passphrase {
+                anyhow::bail!("The passphrases don't match");
+            }
+            let mut salt = [0u8; 16];
+            random(&mut salt)?;
+            let key = derive(&passphrase, &salt)?;
+            let salt = base64::engine::general_purpose::STANDARD.encode(salt);
+            (key, EncryptionConfig { salt, ..Default::default() })
+        }
+    };
+
+    let _ = KEY.set(key);
+    config.encryption = Some(EncryptionConfig { check: format!("{}{}", PREFIX, seal(&key, CHECK_TEXT)?), ..encryption });
+    crate::config::save_config(&config)?;
+    println!("\x1b[32m✓ Encrypted the secrets in {}\x1b[0m", crate::config::get_config_path().display());
+    match key_file {
+        Some(path) => println!("  Keep {} private, and back it up: the secrets can't be read without it.", path),
+        None => println!("  aicli asks for the passphrase at startup; scripts can set {}.", PASSPHRASE_ENV),
+    }
+    Ok(())
+}
+
+/// `aicli config decrypt`: store the secrets in plain text again
+pub fn decrypt() -> Result<()> {
+    let mut config = crate::config::load_config()?;
+    if config.encryption.take().is_none() {
+        anyhow::bail!("config.toml is not encrypted");
+    }
+    crate::config::save_config(&config)?;
+    println!("\x1b[32m✓ Decrypted the secrets in {}\x1b[0m", crate::config::get_config_path().display());
+    Ok(())
+}
+
+fn read_key_file(path: &Path) -> Result<[u8; 32]> {
+    let content = fs::read_to_string(path).with_context(|| format!("Failed to read key file {}", path.display()))?;
+    let bytes = base64::engine::general_purpose::STANDARD
+        .decode(content.trim())
+        .ok()
+        .filter(|b| b.len() == 32)
+        .ok_or_else(|| anyhow::anyhow!("{} is not an aicli key file (base64 of 32 bytes)", path.display()))?;
+    let mut key = [0u8; 32];
+    key.copy_from_slice(&bytes);
+    Ok(key)
+}
+
+fn create_key_file(path: &Path) -> Result<[u8; 32]> {
+    let mut key = [0u8; 32];
+    random(&mut key)?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, format!("{}\n", base64::engine::general_purpose::STANDARD.encode(key)))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o600))?;
+    }
+    println!("  Created key file {}", path.display());
+    Ok(key)
+}
+
+fn seal(key: &[u8; 32], plaintext: &str) -> Result<String> {
+    let mut nonce = [0u8; 12];
+    random(&mut nonce)?;
+    let (ciphertext, tag) = aes_gcm_encrypt(key, &nonce, plaintext.as_bytes())?;
+    let payload = [nonce.as_slice(), &ciphertext, &tag].concat();
+    Ok(base64::engine::general_purpose::STANDARD.encode(payload))
+}
+
+fn open(key: &[u8; 32], sealed: &str) -> Result<String> {
+    let payload = base64::engine::general_purpose::STANDARD.decode(sealed.trim())?;
+    if payload.len() < 12 + 16 {
+        anyhow::bail!("Encrypted value is too short");
+    }
+    let (nonce, rest) = payload.split_at(12);
+    let (ciphertext, tag) = rest.split_at(rest.len() - 16);
+    let plaintext = aes_gcm_decrypt(key, nonce, ciphertext, tag)?;
+    Ok(String::from_utf8(plaintext)?)
+}
+
+#[cfg(not(windows))]
+fn derive(passphrase: &str, salt: &[u8]) -> Result<[u8; 32]> {
+    let mut key = [0u8; 32];
+    openssl::pkcs5::pbkdf2_hmac(
+        passphrase.as_bytes(),
+        salt,
+        KDF_ITERATIONS as usize,
+        openssl::hash::MessageDigest::sha256(),
+        &mut key,
+    )?;
+    Ok(key)
+}
+
+#[cfg(not(windows))]
+fn random(buf: &mut [u8]) -> Result<()> {
+    Ok(openssl::rand::rand_bytes(buf)?)
+}
+
+#[cfg(not(windows))]
+fn aes_gcm_encrypt(key: &[u8; 32], nonce: &[u8], plaintext: &[u8]) -> Result<(Vec<u8>, [u8; 16])> {
+    let mut tag = [0u8; 16];
+    let ciphertext =
+        openssl::symm::encrypt_aead(openssl::symm::Cipher::aes_256_gcm(), key, Some(nonce), &[], plaintext, &mut tag)?;
+    Ok((ciphertext, tag))
+}
+
+#[cfg(not(windows))]
+fn aes_gcm_decrypt(key: &[u8; 32], nonce: &[u8], ciphertext: &[u8], tag: &[u8]) -> Result<Vec<u8>> {
+    openssl::symm::decrypt_aead(openssl::symm::Cipher::aes_256_gcm(), key, Some(nonce), &[], ciphertext, tag)
+        .map_err(|_| anyhow::anyhow!("Wrong key or tampered value"))
+}
+
+// Windows builds link SChannel rather than OpenSSL
+#[cfg(windows)]
+fn unsupported<T>() -> Result<T> {
+    anyhow::bail!("Config encryption is not available in Windows builds yet")
+}
+
+#[cfg(windows)]
+fn derive(_passphrase: &str, _salt: &[u8]) -> Result<[u8; 32]> {
+    unsupported()
+}
+
+#[cfg(windows)]
+fn random(_buf: &mut [u8]) -> Result<()> {
+    unsupported()
+}
+
+#[cfg(windows)]
+fn aes_gcm_encrypt(_key: &[u8; 32], _nonce: &[u8], _plaintext: &[u8]) -> Result<(Vec<u8>, [u8; 16])> {
+    unsupported()
+}
+
+#[cfg(windows)]
+fn aes_gcm_decrypt(_key: &[u8; 32], _nonce: &[u8], _ciphertext: &[u8], _tag: &[u8]) -> Result<Vec<u8>> {
+    unsupported()
+}
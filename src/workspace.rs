@@ -0,0 +1,91 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::paths;
+
+/// What aicli remembers about one project between runs, keyed by its
+/// workspace root (see `memory::project_root`)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WorkspaceState {
+    /// Session last used here, offered by /resume
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_session: Option<String>,
+    /// Absolute paths of files sent with every request
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pinned: Vec<String>,
+    #[serde(default)]
+    pub updated_at: i64,
+}
+
+pub fn get_workspaces_path() -> PathBuf {
+    let home = dirs::home_dir().unwrap_or_else(|| PathBuf::from("."));
+    home.join(".aicli").join("workspaces.json")
+}
+
+fn load_all() -> HashMap<String, WorkspaceState> {
+    fs::read_to_string(get_workspaces_path())
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+pub fn load(root: &str) -> WorkspaceState {
+    load_all().remove(root).unwrap_or_default()
+}
+
+pub fn save(root: &str, state: &WorkspaceState) -> Result<()> {
+    let mut all = load_all();
+    let mut state = state.clone();
+    state.updated_at = chrono::Utc::now().timestamp();
+    all.insert(root.to_string(), state);
+
+    let path = get_workspaces_path();
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, serde_json::to_string_pretty(&all)?)?;
+    Ok(())
+}
+
+impl WorkspaceState {
+    /// Pin a file, returning its absolute path
+    pub fn pin(&mut self, raw: &str) -> Result<String> {
+        let path = paths::resolve(raw);
+        if !path.is_file() {
+            anyhow::bail!("{} is not a file", paths::display(&path));
+        }
+        let path = path.canonicalize().unwrap_or(path).display().to_string();
+        if !self.pinned.contains(&path) {
+            self.pinned.push(path.clone());
+        }
+        Ok(path)
+    }
+
+    /// Unpin by path or by 1-based position in the pin list
+    pub fn unpin(&mut self, raw: &str) -> Option<String> {
+        let index = match raw.parse::<usize>() {
+            Ok(number) => number.checked_sub(1).filter(|i| *i < self.pinned.len()),
+            Err(_) => {
+                let path = paths::resolve(raw);
+                let path = path.canonicalize().unwrap_or(path).display().to_string();
+                self.pinned.iter().position(|p| *p == path)
+            }
+        }?;
+        Some(self.pinned.remove(index))
+    }
+
+    /// System prompt section with the pinned files' current content
+    pub fn pinned_section(&self) -> String {
+        if self.pinned.is_empty() {
+            return String::new();
+        }
+        let files: Vec<String> = self.pinned.iter().map(|p| paths::display(Path::new(p))).collect();
+        format!(
+            "\n\n## Pinned Files\n\nThe user pinned these files for this project; this is their current content:\n{}",
+            crate::input::read_file_context(&files)
+        )
+    }
+}